@@ -0,0 +1,296 @@
+use anyhow::{bail, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+
+/// What of the command line clap defines: the commands (names and built-in
+/// aliases) and the global options that take a value.
+#[derive(Debug, Clone, Default)]
+pub struct Grammar {
+    pub commands: Vec<String>,
+    pub value_options: Vec<String>,
+}
+
+impl Grammar {
+    pub fn of(cmd: &clap::Command) -> Self {
+        let mut commands = vec!["help".to_string()];
+        for sub in cmd.get_subcommands() {
+            commands.push(sub.get_name().to_string());
+            commands.extend(sub.get_all_aliases().map(str::to_string));
+        }
+        let mut value_options = Vec::new();
+        for arg in cmd.get_arguments() {
+            if arg.is_positional() || !arg.get_action().takes_values() {
+                continue;
+            }
+            value_options.extend(arg.get_long().map(|l| format!("--{}", l)));
+            value_options.extend(arg.get_short().map(|s| format!("-{}", s)));
+        }
+        Grammar {
+            commands,
+            value_options,
+        }
+    }
+
+    pub fn is_command(&self, word: &str) -> bool {
+        self.commands.iter().any(|c| c == word)
+    }
+}
+
+/// A built-in alias and the command it stands for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Builtin {
+    pub alias: String,
+    pub command: String,
+}
+
+/// The aliases shown in `--help` (`up`, `ls`, ...), by alias.
+pub fn builtins(cmd: &clap::Command) -> Vec<Builtin> {
+    let mut out: Vec<Builtin> = cmd
+        .get_subcommands()
+        .flat_map(|sub| {
+            sub.get_visible_aliases().map(|a| Builtin {
+                alias: a.to_string(),
+                command: sub.get_name().to_string(),
+            })
+        })
+        .collect();
+    out.sort_by(|a, b| a.alias.cmp(&b.alias));
+    out
+}
+
+/// Why `name = "expansion"` cannot be a user alias (None: it can).
+pub fn invalid(name: &str, expansion: &str, grammar: &Grammar) -> Option<String> {
+    if name.is_empty() || name.starts_with('-') || name.contains(char::is_whitespace) {
+        return Some(format!(
+            "'{}' is not a command name (one word, not starting with '-')",
+            name
+        ));
+    }
+    if grammar.is_command(name) {
+        return Some(format!(
+            "'{}' is a built-in command or alias and cannot be redefined",
+            name
+        ));
+    }
+    if expansion.split_whitespace().next().is_none() {
+        return Some("expands to nothing".to_string());
+    }
+    None
+}
+
+/// Where the subcommand is in `args` (args[0] being the program): the first
+/// word that is neither an option nor the value of one. None when there is
+/// none before `--` or the end.
+fn command_at(args: &[OsString], grammar: &Grammar) -> Option<usize> {
+    let mut i = 1;
+    while i < args.len() {
+        let word = args[i].to_string_lossy();
+        if word == "--" {
+            return None;
+        }
+        if !word.starts_with('-') || word == "-" {
+            return Some(i);
+        }
+        // `--prefix DIR` and `-x V`; `--prefix=DIR` is one word
+        if grammar.value_options.iter().any(|o| *o == word) {
+            i += 1;
+        }
+        i += 1;
+    }
+    None
+}
+
+/// `args` with the user alias in command position replaced by its words,
+/// again and again while the first of them is another alias; everything
+/// after the alias follows its words. An alias that comes back is an error.
+pub fn expand(
+    args: Vec<OsString>,
+    aliases: &BTreeMap<String, String>,
+    grammar: &Grammar,
+) -> Result<Vec<OsString>> {
+    let mut args = args;
+    let mut seen: Vec<String> = Vec::new();
+    while let Some(at) = command_at(&args, grammar) {
+        let word = args[at].to_string_lossy().into_owned();
+        if grammar.is_command(&word) {
+            break;
+        }
+        let Some(expansion) = aliases.get(&word) else {
+            break;
+        };
+        if seen.contains(&word) {
+            seen.push(word);
+            bail!(
+                "alias loop in cli.aliases: {}\nEach alias must end in a windman command.",
+                seen.join(" -> ")
+            );
+        }
+        seen.push(word);
+        args.splice(at..=at, expansion.split_whitespace().map(OsString::from));
+    }
+    Ok(args)
+}
+
+/// The config file named on the command line (`--config FILE`,
+/// `--config=FILE`) or by WINDMAN_CONFIG_PATH.
+fn config_arg(args: &[OsString]) -> Option<String> {
+    let mut words = args.iter().skip(1).map(|a| a.to_string_lossy());
+    while let Some(word) = words.next() {
+        if word == "--" {
+            break;
+        }
+        if word == "--config" {
+            return words.next().map(|w| w.into_owned());
+        }
+        if let Some(file) = word.strip_prefix("--config=") {
+            return Some(file.to_string());
+        }
+    }
+    std::env::var("WINDMAN_CONFIG_PATH").ok()
+}
+
+/// `[cli.aliases]` of the config file `args` use. A config that cannot be
+/// read has none: the command itself reports what is wrong with it.
+fn user_aliases(args: &[OsString]) -> BTreeMap<String, String> {
+    crate::config::ConfigPaths::from_override(config_arg(args).as_deref())
+        .and_then(|paths| crate::config::Config::load(&paths))
+        .map(|(cfg, _)| cfg.cli.aliases)
+        .unwrap_or_default()
+}
+
+/// The command line of this run with the user aliases expanded, for clap.
+pub fn expand_args(args: Vec<OsString>, cmd: &clap::Command) -> Result<Vec<OsString>> {
+    let grammar = Grammar::of(cmd);
+    // the common case reads no config at all
+    match command_at(&args, &grammar) {
+        Some(at) if !grammar.is_command(&args[at].to_string_lossy()) => {}
+        _ => return Ok(args),
+    }
+    let aliases = user_aliases(&args);
+    expand(args, &aliases, &grammar)
+}
+
+/// `alias list`: one line per alias (name, what it runs, where it comes
+/// from), the built-in ones first.
+pub fn render(builtins: &[Builtin], user: &BTreeMap<String, String>) -> Vec<String> {
+    let rows: Vec<(&str, &str, &str)> = builtins
+        .iter()
+        .map(|b| (b.alias.as_str(), b.command.as_str(), "built-in"))
+        .chain(
+            user.iter()
+                .map(|(name, expansion)| (name.as_str(), expansion.as_str(), "cli.aliases")),
+        )
+        .collect();
+    let name_width = rows.iter().map(|r| r.0.len()).max().unwrap_or(0);
+    let runs_width = rows.iter().map(|r| r.1.len()).max().unwrap_or(0);
+    rows.iter()
+        .map(|(name, runs, from)| format!("{:<name_width$}  {:<runs_width$}  {}", name, runs, from))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    fn words(line: &str) -> Vec<OsString> {
+        line.split_whitespace().map(OsString::from).collect()
+    }
+
+    fn aliases(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn expanded(line: &str, pairs: &[(&str, &str)]) -> Result<String> {
+        let grammar = Grammar::of(&crate::cli::Cli::command());
+        let out = expand(words(line), &aliases(pairs), &grammar)?;
+        Ok(out
+            .iter()
+            .map(|w| w.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" "))
+    }
+
+    #[test]
+    fn aliases_expand_in_command_position_with_the_rest_appended() {
+        let user = [("latest", "update --dry-run"), ("l", "latest --json")];
+        assert_eq!(
+            expanded("windman latest --no-prune", &user).unwrap(),
+            "windman update --dry-run --no-prune"
+        );
+        // through another alias, after global options (and their values)
+        assert_eq!(
+            expanded("windman -v --prefix latest l -q", &user).unwrap(),
+            "windman -v --prefix latest update --dry-run --json -q"
+        );
+        // built-in commands win; arguments are never expanded
+        assert_eq!(
+            expanded("windman list latest", &user).unwrap(),
+            "windman list latest"
+        );
+        assert_eq!(
+            expanded("windman -- latest", &user).unwrap(),
+            "windman -- latest"
+        );
+        assert_eq!(expanded("windman nope", &user).unwrap(), "windman nope");
+    }
+
+    #[test]
+    fn an_alias_loop_is_an_error_naming_it() {
+        let user = [("a", "b --x"), ("b", "-v a"), ("c", "a")];
+        let e = expanded("windman c", &user).unwrap_err().to_string();
+        assert!(
+            e.starts_with("alias loop in cli.aliases: c -> a -> b -> a"),
+            "{}",
+            e
+        );
+        let e = expanded("windman self", &[("self", "self")]).unwrap_err();
+        assert!(e.to_string().contains("self -> self"), "{}", e);
+    }
+
+    #[test]
+    fn list_aligns_builtin_and_user_aliases() {
+        let builtins = [Builtin {
+            alias: "up".into(),
+            command: "update".into(),
+        }];
+        assert_eq!(
+            render(&builtins, &aliases(&[("latest", "update --dry-run")])),
+            [
+                "up      update            built-in",
+                "latest  update --dry-run  cli.aliases"
+            ]
+        );
+    }
+
+    #[test]
+    fn builtins_come_from_clap_and_cannot_be_redefined() {
+        let cmd = crate::cli::Cli::command();
+        let names: Vec<(String, String)> = builtins(&cmd)
+            .into_iter()
+            .map(|b| (b.alias, b.command))
+            .collect();
+        for pair in [
+            ("i", "install"),
+            ("ls", "list"),
+            ("rm", "remove"),
+            ("up", "update"),
+        ] {
+            assert!(
+                names.contains(&(pair.0.to_string(), pair.1.to_string())),
+                "{:?}",
+                names
+            );
+        }
+        let grammar = Grammar::of(&cmd);
+        assert!(invalid("latest", "update --dry-run", &grammar).is_none());
+        assert!(invalid("up", "list", &grammar).is_some());
+        assert!(invalid("status", "list", &grammar).is_some());
+        assert!(invalid("--x", "list", &grammar).is_some());
+        assert!(invalid("x", " ", &grammar).is_some());
+    }
+}
@@ -0,0 +1,215 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// JSON-lines log of mutating commands, in the state dir.
+pub const OPERATIONS_LOG: &str = "operations.log";
+
+/// `gc` trims the log to its newest entries past this size.
+pub const MAX_LOG_BYTES: u64 = 1024 * 1024;
+
+/// One mutating command, as appended to the operations log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Operation {
+    /// When the command started
+    pub at: DateTime<Utc>,
+    /// install, update, use, rollback, gc, ...
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Current version before and after the command
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub duration_ms: u64,
+    pub bytes_downloaded: u64,
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub fn log_path(state_dir: &Path) -> PathBuf {
+    state_dir.join(OPERATIONS_LOG)
+}
+
+pub fn append(state_dir: &Path, op: &Operation) -> Result<()> {
+    fs::create_dir_all(state_dir).with_context(|| format!("creating {}", state_dir.display()))?;
+    let p = log_path(state_dir);
+    let mut f = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&p)
+        .with_context(|| format!("opening {}", p.display()))?;
+    writeln!(f, "{}", serde_json::to_string(op)?)
+        .with_context(|| format!("writing {}", p.display()))
+}
+
+/// Run `f` and log it as `command`, success or failure. A log write error only
+/// warns: it must never change the outcome of the command.
+pub fn logged<T>(
+    state_dir: &Path,
+    command: &str,
+    profile: Option<&str>,
+    current_symlink: &Path,
+    f: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    let at = Utc::now();
+    let started = Instant::now();
+    let bytes_before = crate::download::bytes_downloaded();
+    let from = crate::history::current_version_name(current_symlink);
+
+    let result = f();
+
+    let op = Operation {
+        at,
+        command: command.to_string(),
+        profile: profile.map(str::to_string),
+        from,
+        to: crate::history::current_version_name(current_symlink),
+        duration_ms: started.elapsed().as_millis() as u64,
+        bytes_downloaded: crate::download::bytes_downloaded() - bytes_before,
+        ok: result.is_ok(),
+        error: result.as_ref().err().map(|e| format!("{:#}", e)),
+    };
+    if let Err(e) = append(state_dir, &op) {
+        crate::ui::warn(format_args!("operation not logged: {:#}", e));
+    }
+    result
+}
+
+/// Logged operations, oldest first; the last `limit` only when given.
+/// Lines that do not parse (e.g. cut by a crash) are skipped.
+pub fn read(state_dir: &Path, limit: Option<usize>) -> Vec<Operation> {
+    let Ok(s) = fs::read_to_string(log_path(state_dir)) else {
+        return Vec::new();
+    };
+    let all: Vec<Operation> = s
+        .lines()
+        .filter_map(|l| serde_json::from_str(l).ok())
+        .collect();
+    let skip = limit.map_or(0, |n| all.len().saturating_sub(n));
+    all.into_iter().skip(skip).collect()
+}
+
+/// Byte offset where the newest whole lines of `content` that fit in `max` start.
+fn keep_from(content: &str, max: u64) -> usize {
+    if content.len() as u64 <= max {
+        return 0;
+    }
+    let mut start = content.len();
+    for (i, _) in content
+        .match_indices('\n')
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+    {
+        let from = i + 1;
+        if (content.len() - from) as u64 > max {
+            break;
+        }
+        start = from;
+    }
+    start
+}
+
+/// Bytes trimming the log to `max` would drop (0 when it already fits).
+pub fn excess(state_dir: &Path, max: u64) -> u64 {
+    fs::read_to_string(log_path(state_dir)).map_or(0, |s| keep_from(&s, max) as u64)
+}
+
+/// Drop the oldest entries until the log fits in `max` bytes.
+pub fn trim(state_dir: &Path, max: u64) -> Result<()> {
+    let p = log_path(state_dir);
+    let Ok(s) = fs::read_to_string(&p) else {
+        return Ok(());
+    };
+    let start = keep_from(&s, max);
+    if start > 0 {
+        fs::write(&p, &s[start..]).with_context(|| format!("writing {}", p.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use tempfile::tempdir;
+
+    #[test]
+    fn success_and_failure_are_both_appended() {
+        let td = tempdir().unwrap();
+        let state = td.path().join("state");
+        let versions = td.path().join("versions");
+        let current = versions.join("current");
+        for v in ["1.0.0", "1.0.1"] {
+            fs::create_dir_all(versions.join(v)).unwrap();
+        }
+        std::os::unix::fs::symlink(versions.join("1.0.0"), &current).unwrap();
+
+        let v = logged(&state, "use", Some("next"), &current, || {
+            crate::util::atomic_symlink_switch(&versions.join("1.0.1"), &current)?;
+            Ok(42)
+        })
+        .unwrap();
+        assert_eq!(v, 42);
+        let err = logged(&state, "update", None, &current, || -> Result<()> {
+            Err(anyhow!("network down").context("fetching latest"))
+        })
+        .unwrap_err();
+        assert_eq!(err.to_string(), "fetching latest");
+
+        let ops = read(&state, None);
+        assert_eq!(ops.len(), 2);
+        assert_eq!(ops[0].command, "use");
+        assert_eq!(ops[0].profile.as_deref(), Some("next"));
+        assert_eq!(
+            (ops[0].from.as_deref(), ops[0].to.as_deref()),
+            (Some("1.0.0"), Some("1.0.1"))
+        );
+        assert!(ops[0].ok && ops[0].error.is_none());
+        assert_eq!(ops[1].command, "update");
+        assert!(!ops[1].ok);
+        assert_eq!(
+            ops[1].error.as_deref(),
+            Some("fetching latest: network down")
+        );
+        assert_eq!(ops[1].from, ops[1].to);
+
+        assert_eq!(read(&state, Some(1)), ops[1..]);
+    }
+
+    #[test]
+    fn trim_keeps_the_newest_whole_entries() {
+        let td = tempdir().unwrap();
+        let op = |n: u64| Operation {
+            at: Utc::now(),
+            command: "gc".into(),
+            profile: None,
+            from: None,
+            to: None,
+            duration_ms: n,
+            bytes_downloaded: 0,
+            ok: true,
+            error: None,
+        };
+        for n in 0..10 {
+            append(td.path(), &op(n)).unwrap();
+        }
+        let size = fs::metadata(log_path(td.path())).unwrap().len();
+        let line = size / 10;
+        assert_eq!(excess(td.path(), size), 0);
+
+        let max = line * 3 + line / 2;
+        assert_eq!(excess(td.path(), max), line * 7);
+        trim(td.path(), max).unwrap();
+        let kept: Vec<u64> = read(td.path(), None)
+            .iter()
+            .map(|o| o.duration_ms)
+            .collect();
+        assert_eq!(kept, [7, 8, 9]);
+    }
+}
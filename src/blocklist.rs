@@ -0,0 +1,88 @@
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Versions `update` skips and `install` refuses without `--force`, in the
+/// state dir (a JSON list).
+pub const BLOCKLIST_FILE: &str = "blocked.json";
+
+pub fn path(state_dir: &Path) -> PathBuf {
+    state_dir.join(BLOCKLIST_FILE)
+}
+
+/// The blocked versions; none when the file does not exist yet.
+pub fn load(state_dir: &Path) -> Result<BTreeSet<String>> {
+    let p = path(state_dir);
+    match fs::read_to_string(&p) {
+        Ok(s) => serde_json::from_str(&s).with_context(|| format!("reading {}", p.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeSet::new()),
+        Err(e) => Err(e).with_context(|| format!("reading {}", p.display())),
+    }
+}
+
+fn save(state_dir: &Path, blocked: &BTreeSet<String>) -> Result<()> {
+    fs::create_dir_all(state_dir).with_context(|| format!("creating {}", state_dir.display()))?;
+    let p = path(state_dir);
+    fs::write(&p, serde_json::to_string_pretty(blocked)?)
+        .with_context(|| format!("writing {}", p.display()))
+}
+
+/// The blocked versions, oldest first.
+pub fn sorted(blocked: &BTreeSet<String>) -> Vec<&str> {
+    let mut out: Vec<&str> = blocked.iter().map(String::as_str).collect();
+    out.sort_by(|a, b| crate::util::compare_version_names(a, b));
+    out
+}
+
+/// Add `version` (x.y.z); false when it was already blocked.
+pub fn block(state_dir: &Path, version: &str) -> Result<bool> {
+    if semver::Version::parse(version).is_err() {
+        bail!(
+            "{} is not a version (expected x.y.z, e.g. 1.12.10)",
+            version
+        );
+    }
+    let mut blocked = load(state_dir)?;
+    let added = blocked.insert(version.to_string());
+    if added {
+        save(state_dir, &blocked)?;
+    }
+    Ok(added)
+}
+
+/// Remove `version`; false when it was not blocked.
+pub fn unblock(state_dir: &Path, version: &str) -> Result<bool> {
+    let mut blocked = load(state_dir)?;
+    let removed = blocked.remove(version);
+    if removed {
+        save(state_dir, &blocked)?;
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn block_and_unblock_round_trip_through_the_state_dir() {
+        let td = tempdir().unwrap();
+        let state = td.path().join("state");
+        assert!(load(&state).unwrap().is_empty());
+
+        assert!(block(&state, "1.12.10").unwrap());
+        assert!(block(&state, "1.9.2").unwrap());
+        assert!(!block(&state, "1.12.10").unwrap());
+        assert!(block(&state, "latest").is_err());
+        assert_eq!(sorted(&load(&state).unwrap()), ["1.9.2", "1.12.10"]);
+
+        assert!(unblock(&state, "1.9.2").unwrap());
+        assert!(!unblock(&state, "1.9.2").unwrap());
+        assert_eq!(sorted(&load(&state).unwrap()), ["1.12.10"]);
+
+        fs::write(path(&state), "not json").unwrap();
+        assert!(load(&state).is_err());
+    }
+}
@@ -0,0 +1,420 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A downloaded archive kept in `<cache>/downloads/<channel or "url">/<version>/`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedArchive {
+    pub version: String,
+    /// Channel it was downloaded for, or "url" (`install --url`)
+    pub source: String,
+    pub path: PathBuf,
+    pub bytes: u64,
+    /// A `<archive>.sha256` was recorded next to it
+    pub checksum: bool,
+}
+
+/// `<cache>/downloads/<channel>/<version>` dirs, sorted.
+pub fn version_dirs(cache_dir: &Path) -> Vec<PathBuf> {
+    let mut out = Vec::new();
+    let Ok(channels) = fs::read_dir(cache_dir.join("downloads")) else {
+        return out;
+    };
+    for channel in channels.flatten().filter(|e| e.path().is_dir()) {
+        if let Ok(rd) = fs::read_dir(channel.path()) {
+            out.extend(rd.flatten().map(|e| e.path()).filter(|p| p.is_dir()));
+        }
+    }
+    out.sort();
+    out
+}
+
+pub(crate) fn is_archive(name: &str) -> bool {
+    name.ends_with(".tar.gz") || name.ends_with(".tar.zst")
+}
+
+/// Every cached archive, by version (newest first), then source.
+pub fn list(cache_dir: &Path) -> Vec<CachedArchive> {
+    let mut out = Vec::new();
+    for dir in version_dirs(cache_dir) {
+        let name = |p: &Path| {
+            p.file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default()
+        };
+        let (version, source) = (name(&dir), dir.parent().map(name).unwrap_or_default());
+        let Ok(rd) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in rd.flatten().map(|e| e.path()) {
+            if !path.is_file() || !is_archive(&name(&path)) {
+                continue;
+            }
+            out.push(CachedArchive {
+                version: version.clone(),
+                source: source.clone(),
+                bytes: fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+                checksum: crate::export::sidecar_path(&path).is_file(),
+                path,
+            });
+        }
+    }
+    out.sort_by(|a, b| {
+        crate::util::compare_version_names(&b.version, &a.version)
+            .then_with(|| a.source.cmp(&b.source))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    out
+}
+
+/// The cached archive of `version` (one with a recorded checksum first). The
+/// error lists the versions that are cached.
+pub fn find(cache_dir: &Path, version: &str) -> Result<CachedArchive> {
+    let all = list(cache_dir);
+    let mut matching: Vec<&CachedArchive> = all.iter().filter(|a| a.version == version).collect();
+    matching.sort_by_key(|a| !a.checksum);
+    if let Some(a) = matching.first() {
+        return Ok((*a).clone());
+    }
+    let mut versions: Vec<&str> = all.iter().map(|a| a.version.as_str()).collect();
+    versions.dedup();
+    if versions.is_empty() {
+        bail!(
+            "no archive of {} in the download cache ({} is empty)",
+            version,
+            cache_dir.join("downloads").display()
+        );
+    }
+    bail!(
+        "no archive of {} in the download cache; cached versions: {}",
+        version,
+        versions.join(", ")
+    )
+}
+
+/// Move the whole cache (archives, checksums, update checks) from `from` to
+/// `to`, across filesystems if need be. `to` must not exist or be empty; a
+/// missing `from` just creates `to`.
+pub fn relocate(from: &Path, to: &Path) -> Result<()> {
+    relocate_with(from, to, |s, d| fs::rename(s, d))
+}
+
+fn relocate_with(
+    from: &Path,
+    to: &Path,
+    rename: impl Fn(&Path, &Path) -> std::io::Result<()>,
+) -> Result<()> {
+    if from == to {
+        bail!("{} is already the cache directory", to.display());
+    }
+    if to.starts_with(from) || from.starts_with(to) {
+        bail!(
+            "cannot move {} to {}: one is inside the other",
+            from.display(),
+            to.display()
+        );
+    }
+    if let Some((lock, pid)) = held_download_lock(from) {
+        return Err(crate::error::WindmanError::LockHeld { pid, lock }.into());
+    }
+    if to.exists() {
+        let empty = fs::read_dir(to)
+            .with_context(|| format!("reading {}", to.display()))?
+            .next()
+            .is_none();
+        if !empty {
+            bail!("{} already exists and is not empty", to.display());
+        }
+        fs::remove_dir(to).with_context(|| format!("removing {}", to.display()))?;
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("creating {}", parent.display()))?;
+    }
+    if from.exists() {
+        crate::util::move_dir_with(from, to, rename)
+    } else {
+        fs::create_dir_all(to).with_context(|| format!("creating {}", to.display()))
+    }
+}
+
+/// Lock file of a version dir of the cache, holding the PID of the process
+/// downloading into it.
+pub const DOWNLOAD_LOCK: &str = ".download.lock";
+
+/// Held while one process downloads into a version dir of the cache, so a
+/// second run on the same (shared) cache waits, then reuses the archive
+/// instead of downloading it again. Released (the file removed) on drop.
+#[derive(Debug)]
+pub struct DownloadLock {
+    path: PathBuf,
+}
+
+impl DownloadLock {
+    /// Take the lock of `dir`, waiting while a live process holds it (`on_wait`
+    /// is told its PID, once). A lock left by a process that is gone is broken.
+    /// Also says whether it had to wait.
+    pub fn acquire(dir: &Path, on_wait: impl FnOnce(u32)) -> Result<(Self, bool)> {
+        fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        let path = dir.join(DOWNLOAD_LOCK);
+        let mut on_wait = Some(on_wait);
+        let mut waited = false;
+        loop {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(mut f) => {
+                    use std::io::Write;
+                    writeln!(f, "{}", std::process::id())
+                        .with_context(|| format!("writing {}", path.display()))?;
+                    return Ok((DownloadLock { path }, waited));
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e).with_context(|| format!("creating {}", path.display())),
+            }
+            let holder = fs::read_to_string(&path).ok();
+            match holder.as_deref().map(|h| h.trim().parse::<u32>()) {
+                Some(Ok(pid)) if pid_alive(pid) => {
+                    if let Some(f) = on_wait.take() {
+                        f(pid);
+                    }
+                    waited = true;
+                    crate::cancel::check()?;
+                    std::thread::sleep(std::time::Duration::from_millis(100));
+                }
+                // gone (no file: released meanwhile)
+                None => {}
+                // stale: its holder died; break it unless it changed meanwhile.
+                // A PID not written yet reads as empty: give it a moment first
+                Some(_) => {
+                    std::thread::sleep(std::time::Duration::from_millis(50));
+                    if fs::read_to_string(&path).ok() == holder {
+                        let _ = fs::remove_file(&path);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Drop for DownloadLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// The download lock of `cache_dir` a live process holds, with its PID.
+fn held_download_lock(cache_dir: &Path) -> Option<(PathBuf, u32)> {
+    version_dirs(cache_dir).into_iter().find_map(|dir| {
+        let lock = dir.join(DOWNLOAD_LOCK);
+        let pid = fs::read_to_string(&lock).ok()?.trim().parse::<u32>().ok()?;
+        pid_alive(pid).then_some((lock, pid))
+    })
+}
+
+/// A process with this PID exists (one of another user counts).
+fn pid_alive(pid: u32) -> bool {
+    // signal 0: nothing is sent, only whether it could be
+    let r = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    r == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// The most recent complete archive in a version dir of the cache (downloads
+/// only take their name once complete).
+pub fn newest_archive(dir: &Path) -> Option<PathBuf> {
+    let rd = fs::read_dir(dir).ok()?;
+    rd.flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            p.is_file()
+                && p.file_name()
+                    .is_some_and(|n| is_archive(&n.to_string_lossy()))
+        })
+        .max_by_key(|p| fs::metadata(p).and_then(|m| m.modified()).ok())
+}
+
+/// Record `sha256` of a downloaded archive next to it (`sha256sum` format), so
+/// an install from the cache can verify it later.
+pub fn record_checksum(archive: &Path, sha256: &str) -> Result<()> {
+    let name = archive
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let sidecar = crate::export::sidecar_path(archive);
+    fs::write(&sidecar, format!("{}  {}\n", sha256, name))
+        .map_err(|e| anyhow::anyhow!("writing {}: {}", sidecar.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn seed(cache: &Path, source: &str, version: &str, file: &str) -> PathBuf {
+        let dir = cache.join("downloads").join(source).join(version);
+        fs::create_dir_all(&dir).unwrap();
+        let p = dir.join(file);
+        fs::write(&p, version).unwrap();
+        p
+    }
+
+    #[test]
+    fn lists_archives_newest_first_and_skips_partial_files() {
+        let td = tempdir().unwrap();
+        seed(
+            td.path(),
+            "stable",
+            "1.12.7",
+            "Windsurf-linux-x64-1.12.7.tar.gz",
+        );
+        let b = seed(
+            td.path(),
+            "stable",
+            "1.13.0",
+            "Windsurf-linux-x64-1.13.0.tar.gz",
+        );
+        record_checksum(&b, "ab").unwrap();
+        seed(td.path(), "url", "1.12.7", "custom.tar.zst");
+        seed(
+            td.path(),
+            "stable",
+            "1.13.1",
+            "Windsurf-linux-x64-1.13.1.part",
+        );
+
+        let got: Vec<(String, String, bool)> = list(td.path())
+            .into_iter()
+            .map(|a| (a.version, a.source, a.checksum))
+            .collect();
+        assert_eq!(
+            got,
+            [
+                ("1.13.0".into(), "stable".into(), true),
+                ("1.12.7".into(), "stable".into(), false),
+                ("1.12.7".into(), "url".into(), false),
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(crate::export::sidecar_path(&b)).unwrap(),
+            "ab  Windsurf-linux-x64-1.13.0.tar.gz\n"
+        );
+    }
+
+    #[test]
+    fn relocate_falls_back_to_copying_across_filesystems() {
+        let td = tempdir().unwrap();
+        let from = td.path().join("home/.cache/windman");
+        let archive = seed(&from, "stable", "1.13.0", "w.tar.gz");
+        record_checksum(&archive, "ab").unwrap();
+        fs::create_dir_all(from.join("stable")).unwrap();
+        fs::write(from.join("stable/check.json"), "{}").unwrap();
+
+        // rename(2) between two mounts fails with EXDEV
+        let to = td.path().join("scratch/windman");
+        relocate_with(&from, &to, |_, _| {
+            Err(std::io::Error::from_raw_os_error(libc::EXDEV))
+        })
+        .unwrap();
+        assert!(!from.exists());
+        let moved = find(&to, "1.13.0").unwrap();
+        assert!(moved.checksum);
+        assert_eq!(fs::read_to_string(moved.path).unwrap(), "1.13.0");
+        assert!(to.join("stable/check.json").is_file());
+
+        // back, with a plain rename
+        relocate(&to, &from).unwrap();
+        assert_eq!(list(&from).len(), 1);
+    }
+
+    #[test]
+    fn relocate_refuses_a_busy_or_nested_target() {
+        let td = tempdir().unwrap();
+        let from = td.path().join("cache");
+        seed(&from, "stable", "1.13.0", "w.tar.gz");
+        let busy = td.path().join("busy");
+        fs::create_dir_all(&busy).unwrap();
+        fs::write(busy.join("x"), "").unwrap();
+
+        let err = relocate(&from, &busy).unwrap_err().to_string();
+        assert!(err.contains("not empty"), "{}", err);
+        let err = relocate(&from, &from.join("sub")).unwrap_err().to_string();
+        assert!(err.contains("inside the other"), "{}", err);
+        assert_eq!(list(&from).len(), 1);
+
+        // nothing cached yet: the new dir is simply created
+        relocate(&td.path().join("none"), &td.path().join("new")).unwrap();
+        assert!(td.path().join("new").is_dir());
+    }
+
+    #[test]
+    fn relocate_refuses_while_a_download_holds_its_lock() {
+        let td = tempdir().unwrap();
+        let from = td.path().join("cache");
+        let archive = seed(&from, "stable", "1.13.0", "w.tar.gz");
+        let lock = archive.parent().unwrap().join(DOWNLOAD_LOCK);
+        fs::write(&lock, format!("{}\n", std::process::id())).unwrap();
+
+        let err = relocate(&from, &td.path().join("new")).unwrap_err();
+        match err.downcast_ref::<crate::error::WindmanError>() {
+            Some(crate::error::WindmanError::LockHeld { pid, lock: held }) => {
+                assert_eq!(*pid, std::process::id());
+                assert_eq!(*held, lock);
+            }
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(list(&from).len(), 1);
+    }
+
+    #[test]
+    fn download_lock_waits_for_a_live_holder_and_breaks_a_dead_ones() {
+        let td = tempdir().unwrap();
+        let dir = td.path().join("downloads/stable/1.13.0");
+
+        let (held, waited) = DownloadLock::acquire(&dir, |_| panic!("free")).unwrap();
+        assert!(!waited);
+        let other = {
+            let dir = dir.clone();
+            std::thread::spawn(move || {
+                let mut seen = None;
+                let (_lock, waited) = DownloadLock::acquire(&dir, |pid| seen = Some(pid)).unwrap();
+                (waited, seen)
+            })
+        };
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        assert!(!other.is_finished());
+        drop(held);
+        assert_eq!(other.join().unwrap(), (true, Some(std::process::id())));
+        assert!(!dir.join(DOWNLOAD_LOCK).exists());
+
+        // left behind by a process that no longer runs
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let dead = child.id();
+        child.wait().unwrap();
+        fs::write(dir.join(DOWNLOAD_LOCK), format!("{}\n", dead)).unwrap();
+        let (_lock, waited) = DownloadLock::acquire(&dir, |_| panic!("stale")).unwrap();
+        assert!(!waited);
+        assert_eq!(
+            fs::read_to_string(dir.join(DOWNLOAD_LOCK)).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+    }
+
+    #[test]
+    fn find_prefers_a_recorded_checksum_and_lists_what_is_cached() {
+        let td = tempdir().unwrap();
+        assert!(find(td.path(), "1.12.7")
+            .unwrap_err()
+            .to_string()
+            .contains("is empty"));
+
+        seed(td.path(), "stable", "1.12.7", "a.tar.gz");
+        let url = seed(td.path(), "url", "1.12.7", "b.tar.gz");
+        record_checksum(&url, "ab").unwrap();
+        seed(td.path(), "stable", "1.13.0", "c.tar.gz");
+
+        assert_eq!(find(td.path(), "1.12.7").unwrap().path, url);
+        let err = find(td.path(), "1.11.0").unwrap_err().to_string();
+        assert!(err.ends_with("cached versions: 1.13.0, 1.12.7"), "{}", err);
+    }
+}
@@ -0,0 +1,32 @@
+use anyhow::{Context, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Returned by long-running loops once Ctrl-C was pressed; cleanup already happened.
+#[derive(Debug, thiserror::Error)]
+#[error("interrupted")]
+pub struct Cancelled;
+
+/// First Ctrl-C asks the download/extract loops to stop and clean up;
+/// a second one exits immediately.
+pub fn install_handler() -> Result<()> {
+    ctrlc::set_handler(|| {
+        if CANCELLED.swap(true, Ordering::SeqCst) {
+            std::process::exit(crate::EXIT_INTERRUPTED.into());
+        }
+    })
+    .context("installing Ctrl-C handler")
+}
+
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
+
+/// `Err(Cancelled)` once Ctrl-C was pressed.
+pub fn check() -> Result<()> {
+    if is_cancelled() {
+        return Err(Cancelled.into());
+    }
+    Ok(())
+}
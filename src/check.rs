@@ -0,0 +1,299 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::remote::LatestInfo;
+
+/// Last remote answer, cached so status bars can poll without hitting the API.
+pub const CACHE_FILE: &str = "check.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedLatest {
+    pub version: String,
+    pub url: String,
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl CachedLatest {
+    pub fn is_fresh(&self, now: DateTime<Utc>, ttl_minutes: u64) -> bool {
+        let age = now.signed_duration_since(self.fetched_at);
+        // a timestamp from the future (clock change) counts as stale
+        age >= Duration::zero() && age < Duration::minutes(ttl_minutes as i64)
+    }
+}
+
+/// `windman check --json` output; the state is in the JSON, never in the exit code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CheckReport {
+    pub local: Option<String>,
+    pub latest: Option<String>,
+    pub update_available: bool,
+    pub url: Option<String>,
+    pub checked_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub fn cache_path(cache_dir: &Path, channel: &str) -> PathBuf {
+    cache_dir.join(channel).join(CACHE_FILE)
+}
+
+pub fn read_cache(path: &Path) -> Option<CachedLatest> {
+    let s = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&s).ok()
+}
+
+pub fn write_cache(path: &Path, entry: &CachedLatest) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("creating {}", parent.display()))?;
+    }
+    fs::write(path, serde_json::to_string_pretty(entry)?)
+        .with_context(|| format!("writing {}", path.display()))
+}
+
+/// Latest version from the cache if fresh, else from `fetch` (then cached).
+/// On fetch failure a stale cache entry is still used, with the error attached.
+pub fn latest_cached(
+    path: &Path,
+    now: DateTime<Utc>,
+    ttl_minutes: u64,
+    refresh: bool,
+    fetch: impl FnOnce() -> Result<LatestInfo>,
+) -> (Option<CachedLatest>, Option<String>) {
+    let cached = read_cache(path);
+    if !refresh {
+        if let Some(c) = cached.as_ref().filter(|c| c.is_fresh(now, ttl_minutes)) {
+            return (Some(c.clone()), None);
+        }
+    }
+    match fetch() {
+        Ok(info) => {
+            let entry = CachedLatest {
+                version: info.version,
+                url: info.url,
+                fetched_at: now,
+            };
+            // best-effort: a read-only cache dir must not break the check
+            let _ = write_cache(path, &entry);
+            (Some(entry), None)
+        }
+        Err(e) => (cached, Some(format!("{:#}", e))),
+    }
+}
+
+/// The shim's update notice refreshes the cached answer at most this often.
+pub const NOTICE_REFRESH_MINUTES: u64 = 24 * 60;
+
+/// What `notify-check` does, from the cache alone (it runs before every launch):
+/// the newer version to announce, if any, and whether a background refresh is due.
+pub fn notice(path: &Path, local: Option<&str>, now: DateTime<Utc>) -> (Option<String>, bool) {
+    let cached = read_cache(path);
+    let stale = cached
+        .as_ref()
+        .is_none_or(|c| !c.is_fresh(now, NOTICE_REFRESH_MINUTES));
+    let r = report(local.map(str::to_string), cached, None);
+    (r.latest.filter(|_| r.update_available), stale)
+}
+
+pub fn report(
+    local: Option<String>,
+    latest: Option<CachedLatest>,
+    error: Option<String>,
+) -> CheckReport {
+    let update_available = match (&local, &latest) {
+        (_, None) => false,
+        (None, Some(_)) => true,
+        (Some(l), Some(r)) => match (
+            semver::Version::parse(l),
+            semver::Version::parse(&r.version),
+        ) {
+            (Ok(lv), Ok(rv)) => lv < rv,
+            _ => l != &r.version,
+        },
+    };
+    CheckReport {
+        local,
+        update_available,
+        latest: latest.as_ref().map(|c| c.version.clone()),
+        url: latest.as_ref().map(|c| c.url.clone()),
+        checked_at: latest.map(|c| c.fetched_at),
+        error,
+    }
+}
+
+/// One `check --watch` line, e.g. `[2025-10-01 10:00:00] local 1.12.9, latest
+/// 1.13.0: update available`, with the fetch error when there was one.
+pub fn watch_line(r: &CheckReport, at: impl std::fmt::Display) -> String {
+    let state = match (&r.latest, r.update_available) {
+        (Some(_), true) => "update available",
+        (Some(_), false) => "up to date",
+        (None, _) => "latest unknown",
+    };
+    let mut line = format!(
+        "[{}] local {}, latest {}: {}",
+        at,
+        r.local.as_deref().unwrap_or("<none>"),
+        r.latest.as_deref().unwrap_or("?"),
+        state
+    );
+    if let Some(e) = &r.error {
+        line += &format!(" (warning: {})", e);
+    }
+    line
+}
+
+/// The version `check --watch --on-update` runs its hook for: an update that
+/// was not announced yet (`announced` remembers the last one).
+pub fn newly_detected(r: &CheckReport, announced: &mut Option<String>) -> Option<String> {
+    let latest = r.latest.as_ref().filter(|_| r.update_available)?;
+    if announced.as_ref() == Some(latest) {
+        return None;
+    }
+    *announced = Some(latest.clone());
+    Some(latest.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use chrono::TimeZone;
+    use tempfile::tempdir;
+
+    fn at(h: u32, m: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 10, 1, h, m, 0).unwrap()
+    }
+
+    fn info(v: &str) -> LatestInfo {
+        LatestInfo {
+            version: v.to_string(),
+            url: format!("https://example.invalid/{}.tar.gz", v),
+            sha256: None,
+        }
+    }
+
+    #[test]
+    fn freshness_respects_ttl_and_future_timestamps() {
+        let c = CachedLatest {
+            version: "1.13.0".into(),
+            url: String::new(),
+            fetched_at: at(10, 0),
+        };
+        assert!(c.is_fresh(at(10, 59), 60));
+        assert!(!c.is_fresh(at(11, 0), 60));
+        assert!(!c.is_fresh(at(9, 59), 60));
+        assert!(!c.is_fresh(at(10, 0), 0));
+    }
+
+    #[test]
+    fn cache_is_reused_until_expiry_then_refetched() {
+        let td = tempdir().unwrap();
+        let path = cache_path(td.path(), "stable");
+
+        let (got, err) = latest_cached(&path, at(10, 0), 60, false, || Ok(info("1.13.0")));
+        assert_eq!(got.unwrap().version, "1.13.0");
+        assert!(err.is_none());
+
+        // within TTL: the fetcher must not run
+        let (got, _) = latest_cached(&path, at(10, 30), 60, false, || panic!("fetched"));
+        assert_eq!(got.unwrap().fetched_at, at(10, 0));
+
+        // expired: refetched and re-stamped
+        let (got, _) = latest_cached(&path, at(11, 5), 60, false, || Ok(info("1.13.1")));
+        let got = got.unwrap();
+        assert_eq!(
+            (got.version.as_str(), got.fetched_at),
+            ("1.13.1", at(11, 5))
+        );
+
+        // --refresh bypasses a fresh cache
+        let (got, _) = latest_cached(&path, at(11, 6), 60, true, || Ok(info("1.13.2")));
+        assert_eq!(got.unwrap().version, "1.13.2");
+    }
+
+    #[test]
+    fn fetch_error_falls_back_to_stale_cache() {
+        let td = tempdir().unwrap();
+        let path = cache_path(td.path(), "stable");
+        latest_cached(&path, at(8, 0), 60, false, || Ok(info("1.13.0")));
+
+        let (got, err) = latest_cached(&path, at(12, 0), 60, false, || Err(anyhow!("503")));
+        assert_eq!(got.unwrap().version, "1.13.0");
+        assert_eq!(err.as_deref(), Some("503"));
+    }
+
+    #[test]
+    fn notice_reads_the_cache_only() {
+        let td = tempdir().unwrap();
+        let path = cache_path(td.path(), "stable");
+        assert_eq!(notice(&path, Some("1.12.9"), at(10, 0)), (None, true));
+
+        latest_cached(&path, at(10, 0), 60, false, || Ok(info("1.13.0")));
+        assert_eq!(
+            notice(&path, Some("1.12.9"), at(12, 0)),
+            (Some("1.13.0".into()), false)
+        );
+        assert_eq!(notice(&path, Some("1.13.0"), at(12, 0)), (None, false));
+        // a day later: still announced, refresh due
+        let next_day = at(10, 0) + Duration::minutes(NOTICE_REFRESH_MINUTES as i64);
+        assert_eq!(
+            notice(&path, Some("1.12.9"), next_day),
+            (Some("1.13.0".into()), true)
+        );
+    }
+
+    #[test]
+    fn report_compares_semver() {
+        let latest = |v: &str| {
+            Some(CachedLatest {
+                version: v.into(),
+                url: "u".into(),
+                fetched_at: at(10, 0),
+            })
+        };
+        assert!(report(Some("1.12.9".into()), latest("1.13.0"), None).update_available);
+        assert!(!report(Some("1.13.0".into()), latest("1.13.0"), None).update_available);
+        assert!(!report(Some("1.13.1".into()), latest("1.13.0"), None).update_available);
+        assert!(report(None, latest("1.13.0"), None).update_available);
+        let r = report(Some("1.12.9".into()), None, Some("offline".into()));
+        assert!(!r.update_available);
+        assert_eq!(r.latest, None);
+    }
+
+    #[test]
+    fn watch_announces_each_new_version_once() {
+        let latest = |v: &str| {
+            Some(CachedLatest {
+                version: v.into(),
+                url: "u".into(),
+                fetched_at: at(10, 0),
+            })
+        };
+        let mut announced = None;
+        let up_to_date = report(Some("1.13.0".into()), latest("1.13.0"), None);
+        assert_eq!(newly_detected(&up_to_date, &mut announced), None);
+        let newer = report(Some("1.13.0".into()), latest("1.13.1"), None);
+        assert_eq!(
+            newly_detected(&newer, &mut announced).as_deref(),
+            Some("1.13.1")
+        );
+        assert_eq!(newly_detected(&newer, &mut announced), None);
+        let newest = report(Some("1.13.0".into()), latest("1.14.0"), None);
+        assert_eq!(
+            newly_detected(&newest, &mut announced).as_deref(),
+            Some("1.14.0")
+        );
+
+        assert_eq!(
+            watch_line(&newer, "2025-10-01 10:00:00"),
+            "[2025-10-01 10:00:00] local 1.13.0, latest 1.13.1: update available"
+        );
+        let offline = report(None, None, Some("503".into()));
+        assert_eq!(
+            watch_line(&offline, "t"),
+            "[t] local <none>, latest ?: latest unknown (warning: 503)"
+        );
+    }
+}
@@ -1,9 +1,11 @@
-use crate::config::{Config, ConfigPaths};
-use crate::paths::resolve_paths;
+use crate::config::{Config, ConfigPaths, Scope};
+use crate::paths::{parse_dir_arg, resolve_paths, EffectivePaths};
 use crate::{desktop, install, prune, version};
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use clap::{Args, Parser, Subcommand};
 use std::fs;
+use std::process::ExitCode;
+
 #[derive(Parser, Debug)]
 #[command(
     name = "windman",
@@ -15,51 +17,329 @@ pub struct Cli {
     #[arg(long, global = true, env = "WINDMAN_CONFIG_PATH")]
     pub config: Option<String>,
 
-    /// Override install prefix directory for this run (e.g., ~/.local/opt/windsurf)
-    #[arg(long, global = true, value_name = "DIR")]
+    /// Override install prefix directory for this run (e.g., ~/.local/opt/windsurf,
+    /// $HOME/opt/ws or ./ws-test)
+    #[arg(long, global = true, value_name = "DIR", value_parser = parse_dir_arg)]
     pub prefix: Option<String>,
 
     /// Override bin dir for this run (where the shim 'windsurf' is written)
-    #[arg(long, global = true, value_name = "DIR")]
+    #[arg(long, global = true, value_name = "DIR", value_parser = parse_dir_arg)]
     pub bin_dir: Option<String>,
 
+    /// System-wide install (/opt, /usr/local/bin, /usr/share); same as install.scope = "system"
+    #[arg(long, global = true)]
+    pub system: bool,
+
+    /// Select a named profile from [profiles.NAME] in config (e.g., --profile next)
+    #[arg(long, global = true, env = "WINDMAN_PROFILE", value_name = "NAME")]
+    pub profile: Option<String>,
+
     /// Verbose output
     #[arg(short, long, global = true)]
     pub verbose: bool,
 
+    /// Suppress progress bars and summaries
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Plain status lines (every 5s or 10%) instead of progress bars, e.g. for CI logs.
+    /// Also implied by TERM=dumb, NO_COLOR or a stderr that is not a terminal
+    #[arg(
+        long,
+        global = true,
+        env = "WINDMAN_NO_PROGRESS",
+        value_parser = clap::builder::FalseyValueParser::new()
+    )]
+    pub no_progress: bool,
+
+    /// Answer yes to every confirmation. Without it, a question asked while stdin
+    /// is not a terminal fails (exit code 4)
+    #[arg(
+        short = 'y',
+        long,
+        global = true,
+        env = "WINDMAN_ASSUME_YES",
+        value_parser = clap::builder::FalseyValueParser::new()
+    )]
+    pub assume_yes: bool,
+
+    /// When to color the output; NO_COLOR turns `auto` off. `--porcelain` and
+    /// `--json` output is never colored
+    #[arg(long, global = true, value_name = "WHEN", default_value = "auto")]
+    pub color: crate::ui::ColorWhen,
+
     #[command(subcommand)]
     pub cmd: Commands,
 }
 
+// Shown by `<command> --help` and as EXAMPLES in the man pages (`dev-mangen`).
+const INSTALL_EXAMPLES: &str = "Examples:
+  windman install --tar ~/Downloads/Windsurf-linux-x64-1.12.11.tar.gz
+  windman install --url https://example.com/Windsurf-linux-x64-1.12.7.tar.gz
+  windman install --from-cache 1.12.10
+  windman install --tar build.tar.gz --no-activate
+  windman install --tar build.tar.gz --target-dir /tmp/ws-eval";
+
+const UPDATE_EXAMPLES: &str = "Examples:
+  windman update
+  windman update --dry-run
+  windman update --all-profiles --notify
+  windman update --stream --no-prune
+  windman update --next-best";
+
+const USE_EXAMPLES: &str = "Examples:
+  windman use 1.12.11
+  windman use -1
+  windman use +1 --dry-run";
+
+const ACTIVATE_EXAMPLES: &str = "Examples:
+  windman update --stage-only
+  windman activate
+  windman activate 1.13.0 --no-prune";
+
+const LIST_EXAMPLES: &str = "Examples:
+  windman list --size
+  windman list --check-remote
+  windman list --porcelain | cut -f1
+  windman list --blocked";
+
+const INSPECT_EXAMPLES: &str = "Examples:
+  windman inspect
+  windman inspect 1.12.10 --depth 5
+  windman inspect 1.12.11 --json | jq '.tree[].name'";
+
+const PROVENANCE_EXAMPLES: &str = "Examples:
+  windman provenance
+  windman provenance 1.12.10 --json
+  windman provenance 1.12.11 --export cyclonedx > windsurf-1.12.11.cdx.json";
+
+const EXEC_EXAMPLES: &str = "Examples:
+  windman exec -- windsurf --version
+  windman exec --version 1.12.10 -- windsurf .";
+
+const OPEN_EXAMPLES: &str = "Examples:
+  windman open ~/project
+  windman open --version 1.12.10 ~/project ~/notes.md
+  windman open --wait ~/project";
+
+const CHECK_EXAMPLES: &str = "Examples:
+  windman check
+  windman check --json
+  windman check --watch --interval 30m --on-update 'notify-send \"Windsurf $WINDMAN_LATEST\"'";
+
+const BLOCK_EXAMPLES: &str = "Examples:
+  windman block 1.12.10
+  windman update --next-best
+  windman unblock 1.12.10";
+
+const DOCTOR_EXAMPLES: &str = "Examples:
+  windman doctor
+  windman doctor --fix";
+
+const INIT_EXAMPLES: &str = "Examples:
+  windman init
+  windman init --defaults --skip-install
+  windman --prefix ~/opt/windsurf init --no-desktop";
+
+const ROLLBACK_EXAMPLES: &str = "Examples:
+  windman rollback --list
+  windman rollback
+  windman rollforward";
+
+const PRUNE_EXAMPLES: &str = "Examples:
+  windman prune --dry-run
+  windman prune --keep 1";
+
+const EXPORT_EXAMPLES: &str = "Examples:
+  windman export 1.12.11
+  windman export 1.12.11 --out /media/usb/windsurf-1.12.11.tar.zst
+  windman install --tar /media/usb/windsurf-1.12.11.tar.zst";
+
+const ALIAS_EXAMPLES: &str = "Examples:
+  windman alias list
+  # in windman.toml: [cli.aliases] latest = \"update --dry-run\"
+  windman latest --json";
+
+const CONFIG_EXAMPLES: &str = "Examples:
+  windman config init
+  windman config show
+  windman config diff --json
+  windman config path";
+
 #[derive(Subcommand, Debug)]
 pub enum Commands {
-    /// Download + install latest stable (next step). For now: install from a provided tar.gz.
+    /// Install a Windsurf archive: a local file, a URL or one from the download cache
+    ///
+    /// The archive is checked (a Windsurf build, for this CPU and glibc), extracted
+    /// into <prefix>/<version>, tried out with a smoke test, then current, the
+    /// `windsurf` shim and the desktop entry switch to it and old versions are
+    /// pruned by the keep policy. A `<FILE>.sha256` next to a local archive is
+    /// verified first. `windman update` does the same with the latest release.
+    #[command(visible_alias = "i", after_long_help = INSTALL_EXAMPLES)]
     Install(InstallArgs),
     /// Compare local vs remote and update if needed
+    ///
+    /// Asks the update API for the latest release of the configured channel and,
+    /// when it is newer than current, downloads it into the cache, verifies it and
+    /// installs it like `install`. Exits 10 when nothing changed, so scripts and
+    /// timers can tell an update from a no-op.
+    #[command(visible_alias = "up", after_long_help = UPDATE_EXAMPLES)]
     Update(UpdateArgs),
     /// Show local version and paths
-    Status,
+    Status(StatusArgs),
+    /// Tell whether an update is available (cached; for status bars)
+    #[command(after_long_help = CHECK_EXAMPLES)]
+    Check(CheckArgs),
+    /// Print a one-line notice when the cached check found an update (the shim
+    /// runs it with install.shim_update_notice; never touches the network itself)
+    NotifyCheck(NotifyCheckArgs),
     /// Print install and shim paths
-    Where,
+    Where(WhereArgs),
+    /// Print the resolved Windsurf executable that the shim would run
+    Which(WhichArgs),
+    /// Skip a bad release: `update` will not install it, `install` needs --force
+    #[command(after_long_help = BLOCK_EXAMPLES)]
+    Block {
+        /// Version to block (e.g. 1.12.10)
+        version: String,
+    },
+    /// Allow a blocked release again
+    Unblock {
+        /// Version to unblock
+        version: String,
+    },
+    /// Show where an installed version came from (defaults to current)
+    Info(InfoArgs),
+    /// Print the file tree of an installed version (defaults to current), with
+    /// the executable and product.json windman found in it
+    ///
+    /// Directories come first; files of 1 MiB or more show their size, and a
+    /// directory deeper than --depth, of more than 100 entries or named
+    /// node_modules is summarized as its file count and size instead of listed.
+    #[command(after_long_help = INSPECT_EXAMPLES)]
+    Inspect(InspectArgs),
+    /// Show where an installed version (defaults to current) came from and how
+    /// it was verified, for audits; `--export cyclonedx` prints it as an SBOM
+    #[command(after_long_help = PROVENANCE_EXAMPLES)]
+    Provenance(ProvenanceArgs),
     /// List installed versions and show current
-    List,
-    /// Show changelog delta (coming soon)
-    Changelog,
+    #[command(visible_alias = "ls", after_long_help = LIST_EXAMPLES)]
+    List(ListArgs),
+    /// Show release notes of the versions newer than the installed one
+    Changelog(ChangelogArgs),
+    /// Print the release notes of an installed version (defaults to current)
+    Notes(NotesArgs),
+    /// Pack an installed version into a .tar.zst for offline machines
+    #[command(after_long_help = EXPORT_EXAMPLES)]
+    Export(ExportArgs),
     /// Remove installs and shims (keeps user data)
     Uninstall {
+        /// Also remove the desktop entry, download cache and windman config
         #[arg(long)]
         purge: bool,
+        /// With --purge, keep the windman config file
+        #[arg(long, requires = "purge")]
+        keep_config: bool,
+        /// Only list what would be removed, with sizes
+        #[arg(long)]
+        dry_run: bool,
     },
     /// Switch back to previous kept version
-    Rollback,
+    #[command(after_long_help = ROLLBACK_EXAMPLES)]
+    Rollback(RollbackArgs),
+
+    /// Redo the switch undone by the last rollback
+    Rollforward,
+
+    /// Show the switches of current, or with --operations the log of changing commands
+    History(HistoryArgs),
+
+    /// Remove an installed version (moved to trash when install.prune_to_trash is set)
+    #[command(visible_alias = "rm")]
+    Remove(RemoveArgs),
+
+    /// Hardlink identical files between consecutive installed versions
+    Dedupe,
+
+    /// Reclaim space: prune old versions, staging leftovers, stale downloads and old trash
+    Gc(GcArgs),
+
+    /// Remove old versions by the keep policy (what install and update do afterwards)
+    #[command(after_long_help = PRUNE_EXAMPLES)]
+    Prune(PruneArgs),
+
+    /// Check current, the shim and the desktop entry (e.g. after a prefix_dir change)
+    #[command(after_long_help = DOCTOR_EXAMPLES)]
+    Doctor(DoctorArgs),
+
+    /// First run: choose where Windsurf goes, write the config, check PATH and
+    /// install the latest version. Run again, it checks the install instead
+    #[command(after_long_help = INIT_EXAMPLES)]
+    Init(InitArgs),
+
+    /// Move installed versions to a new prefix and update shim, desktop entry and config
+    MigratePrefix(MigratePrefixArgs),
+
+    /// Inspect and recover versions removed into the trash
+    #[command(subcommand)]
+    Trash(TrashCmd),
+
+    /// Inspect downloaded archives kept in the cache
+    #[command(subcommand)]
+    Cache(CacheCmd),
 
     /// Switch current to a specific installed version (e.g., windman use 1.12.11)
+    #[command(after_long_help = USE_EXAMPLES)]
     Use(UseArgs),
 
-    /// Manage configuration
+    /// Switch to a version staged by `update --stage-only`: current, shim,
+    /// desktop entry and prune, as `update` would have done
+    #[command(after_long_help = ACTIVATE_EXAMPLES)]
+    Activate(ActivateArgs),
+
+    /// Run a command with a given Windsurf version first on PATH
+    /// (--version, else the nearest .windsurf-version, else current)
+    #[command(after_long_help = EXEC_EXAMPLES)]
+    Exec(ExecArgs),
+
+    /// Pin the current directory to a version (writes .windsurf-version)
+    Local(LocalArgs),
+
+    /// Start Windsurf in the background on PATHs and print its PID (the version
+    /// `exec` would run: --version, the nearest .windsurf-version, else current)
+    #[command(after_long_help = OPEN_EXAMPLES)]
+    Open(OpenArgs),
+
+    /// Put other executables of the install on PATH (launchers that follow current)
     #[command(subcommand)]
+    Shim(ShimCmd),
+
+    /// Manage configuration
+    #[command(subcommand, after_long_help = CONFIG_EXAMPLES)]
     Config(ConfigCmd),
 
+    /// Query remote releases
+    #[command(subcommand)]
+    Releases(ReleasesCmd),
+
+    /// Diagnose the remote update service
+    #[command(subcommand)]
+    Remote(RemoteCmd),
+
+    /// Schedule unattended updates with a systemd user timer
+    #[command(subcommand)]
+    Timer(TimerCmd),
+
+    /// Verify a tarball against a minisign signature (same check as update)
+    VerifyArchive(VerifyArchiveArgs),
+
+    /// Print the shell completion script, or install it where the shell looks
+    Completions(CompletionsArgs),
+
+    /// Show the command aliases: the built-in ones and [cli.aliases] in the config
+    #[command(subcommand, after_long_help = ALIAS_EXAMPLES)]
+    Alias(AliasCmd),
+
     /// Internal helper to test latest endpoint (hidden in help)
     #[command(hide = true)]
     DevLatest(DevLatestArgs),
@@ -67,421 +347,4536 @@ pub enum Commands {
     /// Internal helper to test the downloader (hidden in help)
     #[command(hide = true)]
     DevDownload(DevDownloadArgs),
+
+    /// Render the man pages (windman.1, windman-<command>.1) for packaging (hidden in help)
+    #[command(hide = true)]
+    DevMangen(DevMangenArgs),
 }
 
 #[derive(Args, Debug)]
 pub struct UseArgs {
-    /// Version folder name to activate (e.g., 1.12.11)
-    #[arg(value_name = "VERSION")]
+    /// Version folder name to activate (e.g., 1.12.11), or +N / -N to move N
+    /// steps to a newer / older installed version
+    #[arg(value_name = "VERSION", allow_hyphen_values = true)]
     pub version: String,
 
     /// Dry-run: show what would change without touching the system
     #[arg(long)]
     pub dry_run: bool,
+
+    /// Switch to a lower version than current without asking (+N/-N never ask)
+    #[arg(long)]
+    pub allow_downgrade: bool,
+
+    /// Refuse to switch current while Windsurf runs from another version
+    /// (install.block_if_running); by default it only warns
+    #[arg(long)]
+    pub require_closed: bool,
 }
 
 #[derive(Args, Debug)]
-pub struct DevLatestArgs {
+pub struct ListArgs {
+    /// Stable, script-friendly output: one line per installed version, no header,
+    /// tab-separated fields `<version>\t<current|>\t<pinned|>\t<size-bytes>\t<installed-at>`.
+    /// Column 2 is "current" or empty; column 3 is "pinned" (by `.windsurf-version`)
+    /// or empty; column 5 is RFC 3339 UTC, or empty when unknown.
+    /// Fields are only ever appended; existing ones will not change across releases.
     #[arg(long)]
-    pub timeout: Option<u64>,
+    pub porcelain: bool,
+
+    /// Print as JSON, trashed versions and `--check-remote` included
+    #[arg(long, conflicts_with = "porcelain")]
+    pub json: bool,
+
+    /// Add the size of each version (walks every file, slow)
     #[arg(long)]
-    pub dump_html: Option<String>,
+    pub size: bool,
+
+    /// Mark the latest remote version (`← latest`) when it is newer than every
+    /// installed one (uses the `check` cache)
+    #[arg(long, conflicts_with = "porcelain")]
+    pub check_remote: bool,
+
+    /// With install.arch_suffix, the versions of every architecture, not
+    /// only this machine's
+    #[arg(long, conflicts_with_all = ["porcelain", "json"])]
+    pub all_arch: bool,
+
+    /// List the blocked versions (`windman block`) instead
+    #[arg(long, conflicts_with_all = ["porcelain", "size", "check_remote", "all_arch"])]
+    pub blocked: bool,
 }
 
 #[derive(Args, Debug)]
-pub struct InstallArgs {
-    /// Path to a local Windsurf tar.gz (temporary until network fetch is added)
-    #[arg(long, value_name = "FILE")]
-    pub tar: Option<String>,
+pub struct StatusArgs {
+    /// Stable, script-friendly output: `key=value` lines with keys
+    /// profile, prefix, current_link, shim, version (empty value when unknown).
+    /// This format will not change across releases.
+    #[arg(long)]
+    pub porcelain: bool,
 
-    /// Force desktop integration even if disabled in config
+    /// Print as JSON (`current.state`: not_installed, broken, unknown_version or healthy)
+    #[arg(long, conflicts_with = "porcelain")]
+    pub json: bool,
+
+    /// Read the version from product.json again instead of the cached one
     #[arg(long)]
-    pub desktop: bool,
+    pub no_cache: bool,
 
-    /// Do not create/update desktop integration for this run
+    /// With install.arch_suffix, also show the current version of the other
+    /// architectures
+    #[arg(long, conflicts_with = "porcelain")]
+    pub all_arch: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct InfoArgs {
+    /// Installed version (defaults to current)
+    #[arg(value_name = "VERSION")]
+    pub version: Option<String>,
+
+    /// Print as JSON
     #[arg(long)]
-    pub no_desktop: bool,
+    pub json: bool,
+}
 
-    /// Keep N previous versions (overrides config)
-    #[arg(long, value_name = "N")]
-    pub keep: Option<usize>,
+#[derive(Args, Debug)]
+pub struct InspectArgs {
+    /// Installed version (defaults to current)
+    #[arg(value_name = "VERSION")]
+    pub version: Option<String>,
 
-    /// Dry-run: print actions without changing the system
+    /// Levels of the tree to list
+    #[arg(long, value_name = "N", default_value_t = 3, value_parser = clap::value_parser!(u64).range(1..))]
+    pub depth: u64,
+
+    /// Print the listing as JSON
     #[arg(long)]
-    pub dry_run: bool,
+    pub json: bool,
 }
 
 #[derive(Args, Debug)]
-pub struct UpdateArgs {
-    /// Force desktop integration for this run
+pub struct ProvenanceArgs {
+    /// Installed version (defaults to current)
+    #[arg(value_name = "VERSION")]
+    pub version: Option<String>,
+
+    /// Print the record as JSON (unknown fields are null)
+    #[arg(long, conflicts_with = "export")]
+    pub json: bool,
+
+    /// Print it in this SBOM format instead
+    #[arg(long, value_name = "FORMAT")]
+    pub export: Option<SbomFormat>,
+}
+
+/// What `provenance --export` writes.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SbomFormat {
+    /// A CycloneDX 1.5 JSON BOM with the version as its one component
+    Cyclonedx,
+}
+
+#[derive(Args, Debug)]
+pub struct GcArgs {
+    /// Print what would be removed without removing anything
     #[arg(long)]
-    pub desktop: bool,
+    pub dry_run: bool,
 
-    /// Do not create/update desktop integration for this run
+    /// Also drop the cached tarball of the current version
     #[arg(long)]
-    pub no_desktop: bool,
+    pub aggressive: bool,
+}
 
-    /// Dry-run
+#[derive(Args, Debug)]
+pub struct PruneArgs {
+    /// Print what would be removed without removing anything
     #[arg(long)]
     pub dry_run: bool,
+
+    /// List every installed version with why it is kept or removed
+    #[arg(long)]
+    pub explain: bool,
+
+    /// Keep this many instead of `install.keep`
+    #[arg(long, value_name = "N")]
+    pub keep: Option<usize>,
 }
 
-#[derive(Subcommand, Debug)]
-pub enum ConfigCmd {
-    /// Create default config file if missing
-    Init,
-    /// Show effective config (after env overrides)
-    Show,
+#[derive(Args, Debug)]
+pub struct RollbackArgs {
+    /// Only list where successive rollbacks would go (recorded switches
+    /// first, then by mtime) and whether each version is still on disk
+    #[arg(long)]
+    pub list: bool,
+
+    /// Only print the version it would switch to and why
+    #[arg(long, conflicts_with = "list")]
+    pub dry_run: bool,
 }
 
 #[derive(Args, Debug)]
-pub struct DevDownloadArgs {
-    /// URL to download
+pub struct HistoryArgs {
+    /// Show installs, updates, switches and cleanups with duration,
+    /// bytes downloaded and outcome
     #[arg(long)]
-    pub url: String,
+    pub operations: bool,
 
-    /// Output file path
+    /// Only the N most recent entries
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+
+    /// Print JSON
     #[arg(long)]
-    pub out: String,
+    pub json: bool,
+}
 
-    /// Request timeout in seconds (overrides default 30s)
+#[derive(Args, Debug)]
+pub struct DoctorArgs {
+    /// Rewrite the shim and desktop entry when they are missing or stale
     #[arg(long)]
-    pub timeout: Option<u64>,
+    pub fix: bool,
 }
 
-pub(crate) fn collect_installed(eff: &crate::paths::EffectivePaths) -> Vec<(String, bool)> {
-    use std::fs;
-    use std::path::Path;
+#[derive(Args, Debug)]
+pub struct InitArgs {
+    /// Accept every default (or the --prefix/--bin-dir given) without asking
+    #[arg(long)]
+    pub defaults: bool,
 
-    let mut entries = Vec::new();
-    let current_target = fs::read_link(&eff.current_symlink).ok();
+    /// Stop once the config is written, without installing Windsurf
+    #[arg(long)]
+    pub skip_install: bool,
 
-    if eff.versions_dir.exists() {
-        if let Ok(rd) = fs::read_dir(&eff.versions_dir) {
-            for ent in rd.flatten() {
-                let path = ent.path();
-                let file_name = ent.file_name();
-                let name = file_name.to_string_lossy().to_string();
+    /// Create a desktop entry (install.desktop_integration = true)
+    #[arg(long)]
+    pub desktop: bool,
 
-                if name == "current" {
-                    continue;
-                }
-                if path.is_dir() {
-                    let is_current = match &current_target {
-                        Some(ct) => Path::new(ct).file_name() == Some(file_name.as_ref()),
-                        None => false,
-                    };
-                    entries.push((name, is_current));
-                }
-            }
-        }
-    }
+    /// No desktop entry (install.desktop_integration = false)
+    #[arg(long, conflicts_with = "desktop")]
+    pub no_desktop: bool,
+}
 
-    // tri (semver desc > lexico desc)
-    entries.sort_by(|a, b| {
-        let asv = semver::Version::parse(&a.0);
-        let bsv = semver::Version::parse(&b.0);
-        match (asv, bsv) {
-            (Ok(av), Ok(bv)) => bv.cmp(&av),
-            _ => b.0.cmp(&a.0),
-        }
-    });
-    entries
+#[derive(Args, Debug)]
+pub struct MigratePrefixArgs {
+    /// New prefix directory (e.g., ~/apps/windsurf); must not exist or be empty
+    #[arg(value_name = "NEW_DIR", value_parser = parse_dir_arg)]
+    pub dir: String,
 }
 
-pub(crate) fn switch_to_version(
-    eff: &crate::paths::EffectivePaths,
-    version: &str,
-) -> anyhow::Result<()> {
-    let target = eff.versions_dir.join(version);
-    if !target.is_dir() {
-        // Préparer un message d’erreur utile avec les versions dispo
-        let mut available = Vec::new();
-        if eff.versions_dir.exists() {
-            if let Ok(rd) = fs::read_dir(&eff.versions_dir) {
-                for ent in rd.flatten() {
-                    let p = ent.path();
-                    if p.is_dir() {
-                        if let Some(name) = p.file_name().and_then(|n| n.to_str()) {
-                            if name != "current" {
-                                available.push(name.to_string());
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        available.sort();
-        anyhow::bail!(
-            "version '{}' not found under {}.\nAvailable: {}",
-            version,
-            eff.versions_dir.display(),
-            if available.is_empty() {
-                "<none>".to_string()
-            } else {
-                available.join(", ")
-            }
-        );
-    }
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// Installed version to export
+    #[arg(value_name = "VERSION")]
+    pub version: String,
 
-    // Si current pointe déjà sur cette version, rien à faire
-    if let Ok(cur) = fs::read_link(&eff.current_symlink) {
-        if cur == target {
-            println!("Already using {}.", version);
-            return Ok(());
-        }
-    }
+    /// Archive to write (default: windsurf-<VERSION>.tar.zst, or the kept archive's
+    /// name); a .sha256 is written next to it
+    #[arg(long, value_name = "FILE")]
+    pub out: Option<String>,
 
-    crate::util::atomic_symlink_switch(&target, &eff.current_symlink)?;
-    println!("Now using {}.", version);
-    Ok(())
+    /// Pack the version dir even when the archive it was installed from was kept
+    #[arg(long)]
+    pub repack: bool,
 }
 
-impl Cli {
-    pub fn parse() -> Self {
-        <Cli as Parser>::parse()
-    }
+#[derive(Args, Debug)]
+pub struct ChangelogArgs {
+    /// Print the notes as Markdown, ignoring changelog.render
+    #[arg(long)]
+    pub raw: bool,
+}
 
-    pub fn run(&self) -> Result<()> {
-        let cfg_paths = ConfigPaths::from_override(self.config.as_deref());
-        let mut cfg = Config::load_or_default(&cfg_paths)?;
+#[derive(Args, Debug)]
+pub struct NotesArgs {
+    /// Installed version (defaults to current)
+    #[arg(value_name = "VERSION")]
+    pub version: Option<String>,
+}
 
-        // Appliquer les overrides globaux (courte vie, n’écrit pas la config)
-        if let Some(p) = &self.prefix {
-            cfg.install.prefix_dir = p.clone();
-        }
-        if let Some(b) = &self.bin_dir {
-            cfg.install.bin_dir = b.clone();
-        }
+#[derive(Args, Debug)]
+pub struct RemoveArgs {
+    /// Installed version to remove (cannot be current)
+    #[arg(value_name = "VERSION")]
+    pub version: String,
+}
 
-        let eff = resolve_paths(&cfg)?;
+#[derive(Subcommand, Debug)]
+pub enum TrashCmd {
+    /// List trashed versions
+    List,
+    /// Move a trashed version back into place
+    Restore {
+        #[arg(value_name = "VERSION")]
+        version: String,
+    },
+    /// Permanently delete trashed versions
+    Empty {
+        /// Only entries trashed longer ago than this (e.g. 7d, 12h)
+        #[arg(long, value_name = "AGE")]
+        older_than: Option<String>,
+    },
+}
 
-        if self.verbose {
-            eprintln!("[windman] Using config at {}", cfg_paths.config_display());
-            eprintln!("[windman] Effective prefix: {}", eff.prefix_dir.display());
-            eprintln!("[windman] Effective bin   : {}", eff.bin_dir.display());
-        }
+#[derive(Subcommand, Debug)]
+pub enum CacheCmd {
+    /// List cached archives and whether their version is installed
+    List,
+    /// Print the cache directory in effect (WINDMAN_CACHE_DIR, paths.cache_dir
+    /// or the default)
+    Path,
+    /// Move the cache to NEW_DIR (across filesystems too) and set
+    /// paths.cache_dir in the config
+    Move {
+        /// New cache directory (e.g. /scratch/$USER/windman); must not exist or be empty
+        #[arg(value_name = "NEW_DIR", value_parser = parse_dir_arg)]
+        dir: String,
+    },
+}
 
-        match &self.cmd {
-            Commands::Install(args) => {
-                use std::path::PathBuf;
+#[derive(Args, Debug)]
+pub struct WhichArgs {
+    /// Resolve inside this installed version instead of current
+    #[arg(long, value_name = "VERSION")]
+    pub version: Option<String>,
+}
 
-                let keep = args.keep.unwrap_or(cfg.install.keep);
-                if args.dry_run {
-                    println!("[dry-run] would install to {:?}", eff.prefix_dir);
-                    println!("[dry-run] would maintain shim at {:?}", eff.bin_shim);
-                    return Ok(());
-                }
+#[derive(Args, Debug)]
+pub struct ExecArgs {
+    /// Installed version to use (overrides .windsurf-version)
+    #[arg(long, value_name = "VERSION")]
+    pub version: Option<String>,
 
-                if let Some(tar) = &args.tar {
-                    // mémoriser la current avant bascule
-                    let previous_current: Option<PathBuf> =
-                        std::fs::read_link(&eff.current_symlink).ok();
+    /// Command and arguments (default: windsurf)
+    #[arg(
+        trailing_var_arg = true,
+        allow_hyphen_values = true,
+        value_name = "CMD"
+    )]
+    pub cmd: Vec<String>,
+}
 
-                    let ver = install::install_from_tar(tar, &eff)?;
-                    println!("Installed Windsurf {} to {:?}", ver, eff.prefix_dir);
+#[derive(Args, Debug)]
+pub struct OpenArgs {
+    /// Installed version to use (overrides .windsurf-version)
+    #[arg(long, value_name = "VERSION")]
+    pub version: Option<String>,
 
-                    let want_desktop = if args.no_desktop {
-                        false
-                    } else {
-                        args.desktop || cfg.install.desktop_integration
-                    };
-                    if want_desktop {
-                        desktop::ensure_desktop_files(&eff)?;
-                        println!("Desktop entry installed");
-                    }
+    /// Stay attached and wait for Windsurf to exit, with its exit code
+    #[arg(long)]
+    pub wait: bool,
 
-                    // Prune: préserver la nouvelle current + l'ancienne current
-                    let mut preserve: Vec<PathBuf> = Vec::new();
-                    if let Ok(cur) = std::fs::read_link(&eff.current_symlink) {
-                        preserve.push(cur);
-                    }
-                    if let Some(prev) = previous_current {
-                        preserve.push(prev);
-                    }
-                    prune::prune_old_versions_with_preserve(&eff.versions_dir, keep, &preserve)?;
-                    Ok(())
-                } else {
-                    bail!("--tar <FILE> is required for now. Network download will be added next.")
-                }
-            }
+    /// Folders and files to open (passed to Windsurf as they are)
+    #[arg(value_name = "PATH", allow_hyphen_values = true)]
+    pub paths: Vec<String>,
+}
 
-            Commands::Use(args) => {
-                if args.dry_run {
-                    let target = eff.versions_dir.join(&args.version);
+#[derive(Args, Debug)]
+pub struct LocalArgs {
+    /// Version to pin (e.g., 1.11.3)
+    #[arg(value_name = "VERSION")]
+    pub version: String,
+}
+
+#[derive(Args, Debug)]
+pub struct WhereArgs {
+    /// Print shell code exporting WINDSURF_HOME and WINDSURF_CURRENT and
+    /// putting the bin dir first in PATH, for `eval "$(windman where --shell bash)"`
+    #[arg(long, value_enum, value_name = "SHELL")]
+    pub shell: Option<crate::shell::Shell>,
+
+    /// Print only this path, as is (for scripts)
+    #[arg(long, value_enum, value_name = "NAME", conflicts_with = "shell")]
+    pub var: Option<WhereVar>,
+}
+
+/// The paths `where --var` prints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum WhereVar {
+    Prefix,
+    Current,
+    Shim,
+    BinDir,
+    Cache,
+}
+
+impl WhereVar {
+    fn path(self, eff: &EffectivePaths) -> &std::path::Path {
+        match self {
+            WhereVar::Prefix => &eff.prefix_dir,
+            WhereVar::Current => &eff.current_symlink,
+            WhereVar::Shim => &eff.bin_shim,
+            WhereVar::BinDir => &eff.bin_dir,
+            WhereVar::Cache => &eff.cache_dir,
+        }
+    }
+}
+
+/// `where --shell` output: the environment other tools find the install by.
+fn where_env(shell: crate::shell::Shell, eff: &EffectivePaths) -> String {
+    crate::shell::env_script(
+        shell,
+        &[
+            ("WINDSURF_HOME", &eff.prefix_dir),
+            ("WINDSURF_CURRENT", &eff.current_symlink),
+        ],
+        &eff.bin_dir,
+    )
+}
+
+#[derive(Args, Debug)]
+pub struct CompletionsArgs {
+    /// Shell to complete for (default: the one in $SHELL)
+    #[arg(value_enum)]
+    pub shell: Option<crate::shell::Shell>,
+
+    /// Write the script to the shell's user completion directory
+    #[arg(long, conflicts_with = "uninstall")]
+    pub install: bool,
+
+    /// Remove the script written by --install
+    #[arg(long)]
+    pub uninstall: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct VerifyArchiveArgs {
+    /// Archive to verify
+    #[arg(value_name = "FILE")]
+    pub file: String,
+
+    /// Minisign signature file (.minisig)
+    #[arg(long, value_name = "FILE")]
+    pub sig: String,
+
+    /// Minisign public key (overrides security.public_key)
+    #[arg(long, value_name = "KEY")]
+    pub public_key: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct DevLatestArgs {
+    #[arg(long)]
+    pub timeout: Option<u64>,
+    #[arg(long)]
+    pub dump_html: Option<String>,
+    /// Query every Linux architecture and flag disagreements
+    #[arg(long)]
+    pub all_arches: bool,
+    /// Query this URL instead of the update API (or WINDMAN_LATEST_ENDPOINT).
+    /// With --verbose, the status, caching/rate-limit headers and raw body are printed
+    #[arg(long, value_name = "URL", conflicts_with_all = ["all_arches", "dump_html"])]
+    pub endpoint: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ReleasesCmd {
+    /// Latest release for every Linux architecture (x64, arm64)
+    Latest(ReleasesLatestArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct CheckArgs {
+    /// Print {local, latest, update_available, url, checked_at} as JSON
+    #[arg(long)]
+    pub json: bool,
+
+    /// Ignore the cached answer (network.check_cache_ttl_minutes) and query the API
+    /// without conditional headers
+    #[arg(long)]
+    pub refresh: bool,
+
+    /// Keep checking every --interval, rewriting the status line on a terminal
+    /// (a line per check otherwise, a JSON object per line with --json) until Ctrl-C.
+    /// The cached answer is reused within its TTL, so a short interval never
+    /// queries the API more often than a plain `check` would
+    #[arg(long, conflicts_with = "refresh")]
+    pub watch: bool,
+
+    /// Time between checks with --watch: 30s, 15m, 2h...
+    #[arg(
+        long,
+        value_name = "DURATION",
+        default_value = "30m",
+        requires = "watch"
+    )]
+    pub interval: String,
+
+    /// With --watch, run CMD (through `sh -c`) the first time each newer version is
+    /// seen; WINDMAN_LOCAL and WINDMAN_LATEST hold the versions
+    #[arg(long, value_name = "CMD", requires = "watch")]
+    pub on_update: Option<String>,
+
+    /// Stop after N checks (for tests)
+    #[arg(long, value_name = "N", hide = true, requires = "watch")]
+    pub max_iterations: Option<u64>,
+}
+
+#[derive(Args, Debug)]
+pub struct NotifyCheckArgs {
+    /// Query the API and update the cached answer (what the background run does)
+    #[arg(long, hide = true)]
+    pub refresh: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ShimCmd {
+    /// Write a launcher NAME in bin_dir for PATH inside the version dir
+    /// (e.g. windman shim add ws-cli Windsurf/bin/windsurf-cli)
+    Add {
+        #[arg(value_name = "NAME")]
+        name: String,
+        #[arg(value_name = "PATH")]
+        target: String,
+    },
+    /// List managed launchers and whether the current version has their target
+    List,
+    /// Delete a managed launcher
+    Remove {
+        #[arg(value_name = "NAME")]
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AliasCmd {
+    /// List the built-in aliases and those of [cli.aliases]
+    List {
+        /// Print {"builtin": [...], "user": {...}} as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TimerCmd {
+    /// Write windman-update.{service,timer} into ~/.config/systemd/user
+    Install,
+    /// Disable and delete the timer and service units
+    Remove,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RemoteCmd {
+    /// Probe the update API, releases page and download host; show the TLS trust in effect
+    Check {
+        /// Request timeout in seconds
+        #[arg(long)]
+        timeout: Option<u64>,
+    },
+    /// Print the latest release of each channel/arch as JSON (for mirroring tools)
+    Manifest(ManifestArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct ManifestArgs {
+    /// linux-x64, linux-arm64, or all
+    #[arg(long, default_value = "all")]
+    pub arch: String,
+
+    /// stable, next, or all (default: the configured channel)
+    #[arg(long)]
+    pub channel: Option<String>,
+
+    /// Download every artifact to hash it and check its size
+    #[arg(long)]
+    pub verify: bool,
+
+    /// Request timeout in seconds
+    #[arg(long)]
+    pub timeout: Option<u64>,
+}
+
+#[derive(Args, Debug)]
+pub struct ReleasesLatestArgs {
+    /// Print as JSON
+    #[arg(long)]
+    pub json: bool,
+
+    /// Request timeout in seconds
+    #[arg(long)]
+    pub timeout: Option<u64>,
+}
+
+#[derive(Args, Debug)]
+pub struct InstallArgs {
+    /// Path to a local Windsurf .tar.gz or .tar.zst; a `<FILE>.sha256` next to it is verified.
+    /// `-` reads the archive from stdin (a pipe; the version comes from product.json)
+    #[arg(long, value_name = "FILE")]
+    pub tar: Option<String>,
+
+    /// SHA-256 the --tar archive must have (hex)
+    #[arg(long, value_name = "HEX", requires = "tar", value_parser = parse_sha256)]
+    pub sha256: Option<String>,
+
+    /// Download a Windsurf archive (https://, http:// or file://) and install it
+    #[arg(long, value_name = "URL", conflicts_with = "tar")]
+    pub url: Option<String>,
+
+    /// Reinstall VERSION from the download cache, without network (see `windman cache list`)
+    #[arg(long, value_name = "VERSION", conflicts_with_all = ["tar", "url"])]
+    pub from_cache: Option<String>,
+
+    /// Force desktop integration even if disabled in config
+    #[arg(long)]
+    pub desktop: bool,
+
+    /// Do not create/update desktop integration for this run
+    #[arg(long)]
+    pub no_desktop: bool,
+
+    /// Do not write the `windsurf` shim in bin_dir (install.manage_shim = false)
+    #[arg(long)]
+    pub no_shim: bool,
+
+    /// Keep N previous versions (overrides config)
+    #[arg(long, value_name = "N", conflicts_with = "no_prune")]
+    pub keep: Option<usize>,
+
+    /// Do not prune old versions after installing (install.auto_prune = false)
+    #[arg(long)]
+    pub no_prune: bool,
+
+    /// Install even if the archive has no Windsurf executable, targets another
+    /// CPU architecture or would replace an installed version dir with different content
+    #[arg(long)]
+    pub force: bool,
+
+    /// Skip running the new version before switching current to it
+    #[arg(long)]
+    pub no_smoke_test: bool,
+
+    /// Switch current to a lower version without asking (and let prune remove newer ones)
+    #[arg(long)]
+    pub allow_downgrade: bool,
+
+    /// Refuse to switch current while Windsurf runs from another version
+    /// (install.block_if_running); by default it only warns
+    #[arg(long)]
+    pub require_closed: bool,
+
+    /// Keep the verified archive in `<version dir>/.artifact/` (install.keep_archive)
+    #[arg(long)]
+    pub keep_archive: bool,
+
+    /// Install the version without switching current to it: no shim, desktop
+    /// entry or prune (`windman use <VERSION>` activates it later)
+    #[arg(long)]
+    pub no_activate: bool,
+
+    /// Extract into DIR/<version> instead of the prefix, to try a build out
+    /// without touching the install (implies --no-activate)
+    #[arg(long, value_name = "DIR", value_parser = parse_dir_arg)]
+    pub target_dir: Option<String>,
+
+    /// Dry-run: print actions without changing the system
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct UpdateArgs {
+    /// Force desktop integration for this run
+    #[arg(long)]
+    pub desktop: bool,
+
+    /// Do not create/update desktop integration for this run
+    #[arg(long)]
+    pub no_desktop: bool,
+
+    /// Do not write the `windsurf` shim in bin_dir (install.manage_shim = false)
+    #[arg(long)]
+    pub no_shim: bool,
+
+    /// Install even if the archive has no Windsurf executable, targets another
+    /// CPU architecture or would replace an installed version dir with different content
+    #[arg(long)]
+    pub force: bool,
+
+    /// Skip running the new version before switching current to it
+    #[arg(long)]
+    pub no_smoke_test: bool,
+
+    /// Switch current to a lower version without asking (and let prune remove newer ones)
+    #[arg(long)]
+    pub allow_downgrade: bool,
+
+    /// Refuse to switch current while Windsurf runs from another version
+    /// (install.block_if_running); by default it only warns
+    #[arg(long)]
+    pub require_closed: bool,
+
+    /// Do not prune old versions after updating (install.auto_prune = false)
+    #[arg(long)]
+    pub no_prune: bool,
+
+    /// Extract while downloading, without keeping the archive in the cache
+    /// (install.stream_extract); saves disk space, the archive cannot be reused
+    #[arg(long)]
+    pub stream: bool,
+
+    /// Keep the verified archive in `<version dir>/.artifact/` (install.keep_archive)
+    #[arg(long, conflicts_with = "stream")]
+    pub keep_archive: bool,
+
+    /// Dry-run
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Update the default install and every [profiles.NAME] in one run
+    #[arg(long)]
+    pub all_profiles: bool,
+
+    /// Send a desktop notification (notify-send) when a version was installed or
+    /// the update failed (install.notify)
+    #[arg(long)]
+    pub notify: bool,
+
+    /// Query the update API unconditionally (ignore the stored ETag/Last-Modified)
+    #[arg(long)]
+    pub refresh: bool,
+
+    /// If the update API is down, install from the releases page download link
+    /// (signature verification still applies when enabled)
+    #[arg(long)]
+    pub allow_html_fallback: bool,
+
+    /// When the latest release is blocked (`windman block`), install the newest
+    /// one that is not, from the releases page, instead of staying put
+    #[arg(long)]
+    pub next_best: bool,
+
+    /// Download, verify and extract the new version but leave current, the
+    /// shim, the desktop entry and old versions alone; `windman activate`
+    /// switches to it later
+    #[arg(long, conflicts_with_all = ["no_prune", "desktop"])]
+    pub stage_only: bool,
+
+    /// Print a JSON report of the run on stdout (versions, bytes downloaded,
+    /// verification, desktop entry, pruned versions, duration; on failure the
+    /// error and the step it happened in); progress goes to stderr
+    #[arg(long, conflicts_with = "all_profiles")]
+    pub json: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ActivateArgs {
+    /// Installed version to switch to, or "staged" for the one
+    /// `update --stage-only` left
+    #[arg(value_name = "VERSION", default_value = "staged")]
+    pub version: String,
+
+    /// Do not refresh the desktop entry
+    #[arg(long)]
+    pub no_desktop: bool,
+
+    /// Do not prune old versions afterwards (install.auto_prune = false)
+    #[arg(long)]
+    pub no_prune: bool,
+
+    /// Switch to a lower version than current without asking
+    #[arg(long)]
+    pub allow_downgrade: bool,
+
+    /// Refuse to switch current while Windsurf runs from another version
+    /// (install.block_if_running); by default it only warns
+    #[arg(long)]
+    pub require_closed: bool,
+
+    /// Show what would change without touching the system
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCmd {
+    /// Create default config file if missing
+    Init,
+    /// Show effective config (after env overrides)
+    Show {
+        /// Only validate the config file and report problems
+        #[arg(long)]
+        check: bool,
+        /// Print secret-looking network.extra_headers values instead of <redacted>
+        #[arg(long)]
+        show_secrets: bool,
+        /// Print the built-in defaults instead
+        #[arg(long, conflicts_with = "check")]
+        default: bool,
+    },
+    /// Print only the settings whose effective value differs from the built-in
+    /// default, with where each was set (file, profile, env or cli)
+    Diff {
+        /// Print as JSON
+        #[arg(long)]
+        json: bool,
+        /// Print secret-looking network.extra_headers values instead of <redacted>
+        #[arg(long)]
+        show_secrets: bool,
+    },
+    /// Rewrite the config file with the current settings (legacy keys moved or
+    /// dropped); the original is kept as <file>.bak
+    Migrate,
+    /// Print the config file in effect (--config, WINDMAN_CONFIG_PATH or the
+    /// default) and whether it exists
+    Path,
+}
+
+#[derive(Args, Debug)]
+pub struct DevDownloadArgs {
+    /// URL to download
+    #[arg(long)]
+    pub url: String,
+
+    /// Output file path
+    #[arg(long)]
+    pub out: String,
+
+    /// Request timeout in seconds (overrides default 30s)
+    #[arg(long)]
+    pub timeout: Option<u64>,
+}
+
+/// Output of the last `windman open`, in the state dir.
+const OPEN_LOG: &str = "windsurf.log";
+
+#[derive(Args, Debug)]
+pub struct DevMangenArgs {
+    /// Directory to write the pages into (created if missing)
+    #[arg(long, value_name = "DIR")]
+    pub out_dir: String,
+}
+
+pub(crate) fn collect_installed(eff: &crate::paths::EffectivePaths) -> Vec<(String, bool)> {
+    use std::fs;
+
+    let mut entries = Vec::new();
+    let current_target = crate::util::resolve_symlink(&eff.current_symlink);
+
+    if eff.versions_dir.exists() {
+        if let Ok(rd) = fs::read_dir(&eff.versions_dir) {
+            for ent in rd.flatten() {
+                let path = ent.path();
+                let file_name = ent.file_name();
+                let name = file_name.to_string_lossy().to_string();
+
+                if !crate::util::is_version_dir_name(&name) {
+                    continue;
+                }
+                if path.is_dir() {
+                    let is_current = match &current_target {
+                        Some(ct) => fs::canonicalize(&path).ok().as_ref() == Some(ct),
+                        None => false,
+                    };
+                    entries.push((name, is_current));
+                }
+            }
+        }
+    }
+
+    // newest first (semver with prereleases, then non-semver names)
+    entries.sort_by(|a, b| crate::util::compare_version_names(&b.0, &a.0));
+    entries
+}
+
+/// `list --porcelain` rendering (format is a stable contract, see ListArgs).
+pub(crate) fn list_porcelain(eff: &crate::paths::EffectivePaths, pinned: Option<&str>) -> String {
+    let mut out = String::new();
+    for e in list_entries(eff, pinned, true) {
+        if e.state != ListState::Installed {
+            continue;
+        }
+        out.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}\n",
+            e.version,
+            if e.current { "current" } else { "" },
+            if e.pinned { "pinned" } else { "" },
+            e.size_bytes.unwrap_or(0),
+            e.installed_at
+                .map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+                .unwrap_or_default()
+        ));
+    }
+    out
+}
+
+/// Where a `windman list` row lives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ListState {
+    Installed,
+    /// In `.trash` (install.prune_to_trash), restorable with `trash restore`
+    Trash,
+    /// The latest remote version, newer than every installed one (`--check-remote`)
+    Available,
+}
+
+/// One row of `windman list`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub(crate) struct ListEntry {
+    pub version: String,
+    pub state: ListState,
+    pub current: bool,
+    /// Named by the `.windsurf-version` found from the working directory
+    pub pinned: bool,
+    pub installed_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trashed_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Only with `--size`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size_bytes: Option<u64>,
+    /// Release channel recorded at install
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+}
+
+/// When a version dir was installed: its metadata, else the dir mtime
+/// (versions installed before metadata existed).
+fn installed_at(dir: &std::path::Path) -> Option<chrono::DateTime<chrono::Utc>> {
+    let from_meta = crate::meta::read(dir)
+        .and_then(|m| chrono::DateTime::parse_from_rfc3339(&m.installed_at).ok())
+        .map(|t| t.with_timezone(&chrono::Utc));
+    from_meta.or_else(|| {
+        let t = fs::metadata(dir).and_then(|m| m.modified()).ok()?;
+        Some(chrono::DateTime::<chrono::Utc>::from(t))
+    })
+}
+
+/// Installed versions (newest first), then the trashed ones (most recently
+/// trashed first). `pinned` is the version `.windsurf-version` names.
+pub(crate) fn list_entries(
+    eff: &crate::paths::EffectivePaths,
+    pinned: Option<&str>,
+    with_size: bool,
+) -> Vec<ListEntry> {
+    let entry = |version: String, dir: &std::path::Path, state, current| ListEntry {
+        pinned: pinned == Some(version.as_str()),
+        version,
+        state,
+        current,
+        installed_at: installed_at(dir),
+        trashed_at: None,
+        size_bytes: with_size.then(|| crate::util::dir_size(dir)),
+        channel: crate::meta::read(dir).and_then(|m| m.channel),
+    };
+    let mut out: Vec<ListEntry> = collect_installed(eff)
+        .into_iter()
+        .map(|(name, current)| {
+            let dir = eff.versions_dir.join(&name);
+            entry(name, &dir, ListState::Installed, current)
+        })
+        .collect();
+    for t in crate::trash::list(&eff.versions_dir) {
+        out.push(ListEntry {
+            trashed_at: Some(t.trashed_at),
+            ..entry(t.version, &t.path, ListState::Trash, false)
+        });
+    }
+    out
+}
+
+/// Put the remote `latest` first when it is newer than every installed version.
+fn mark_latest(entries: &mut Vec<ListEntry>, latest: &str) {
+    let newer = entries
+        .iter()
+        .filter(|e| e.state == ListState::Installed)
+        .all(|e| crate::util::compare_version_names(latest, &e.version).is_gt());
+    if newer {
+        entries.insert(
+            0,
+            ListEntry {
+                version: latest.to_string(),
+                state: ListState::Available,
+                current: false,
+                pinned: false,
+                installed_at: None,
+                trashed_at: None,
+                size_bytes: None,
+                channel: None,
+            },
+        );
+    }
+}
+
+/// "2025-09-27 (3 days ago)", in UTC.
+fn date_with_age(t: chrono::DateTime<chrono::Utc>, now: chrono::DateTime<chrono::Utc>) -> String {
+    let age = match now.signed_duration_since(t).num_days() {
+        ..=0 => "today".to_string(),
+        1 => "yesterday".to_string(),
+        n => format!("{} days ago", n),
+    };
+    format!("{} ({})", t.format("%Y-%m-%d"), age)
+}
+
+/// The `windman list` table: marker, version, install date, size (when
+/// measured) and notes, each column as wide as its widest cell. Versions from
+/// more than one channel get theirs next to the version (`1.13.0 [next]`).
+pub(crate) fn render_list(entries: &[ListEntry], now: chrono::DateTime<chrono::Utc>) -> String {
+    let with_size = entries.iter().any(|e| e.size_bytes.is_some());
+    let mut channels: Vec<Option<&str>> = entries
+        .iter()
+        .filter(|e| e.state != ListState::Available)
+        .map(|e| e.channel.as_deref())
+        .collect();
+    channels.sort();
+    channels.dedup();
+    let mixed = channels.len() > 1;
+    let rows: Vec<Vec<String>> = entries
+        .iter()
+        .map(|e| {
+            let mut notes = Vec::new();
+            if e.current {
+                notes.push("current".to_string());
+            }
+            if e.pinned {
+                notes.push("pinned".to_string());
+            }
+            match (e.state, e.trashed_at) {
+                (ListState::Trash, Some(t)) => {
+                    notes.push(format!("in trash since {}", t.format("%Y-%m-%d")))
+                }
+                (ListState::Trash, None) => notes.push("in trash".to_string()),
+                (ListState::Available, _) => notes.push("← latest (not installed)".to_string()),
+                (ListState::Installed, _) => {}
+            }
+            let mut row = vec![
+                match (e.current, e.state) {
+                    (true, _) => "*",
+                    (_, ListState::Trash) => "~",
+                    (_, ListState::Available) => "+",
+                    _ => " ",
+                }
+                .to_string(),
+                match &e.channel {
+                    Some(c) if mixed => format!("{} [{}]", e.version, c),
+                    _ => e.version.clone(),
+                },
+                match (e.state, e.installed_at) {
+                    (ListState::Available, _) => String::new(),
+                    (_, Some(t)) => date_with_age(t, now),
+                    (_, None) => "-".to_string(),
+                },
+            ];
+            if with_size {
+                row.push(
+                    e.size_bytes
+                        .map(|b| indicatif::HumanBytes(b).to_string())
+                        .unwrap_or_default(),
+                );
+            }
+            row.push(notes.join(", "));
+            row
+        })
+        .collect();
+
+    let columns = rows.first().map_or(0, Vec::len);
+    let widths: Vec<usize> = (0..columns)
+        .map(|c| rows.iter().map(|r| r[c].chars().count()).max().unwrap_or(0))
+        .collect();
+    let mut out = String::new();
+    for row in rows {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(c, (cell, w))| {
+                // sizes right-aligned, the rest left-aligned
+                if with_size && c == 3 {
+                    format!("{:>w$}", cell, w = w)
+                } else {
+                    format!("{:<w$}", cell, w = w)
+                }
+            })
+            .collect();
+        out.push_str(line.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// This machine's paths, then with `--all-arch` those of the other
+/// architectures installed under the prefix (`install.arch_suffix`).
+fn arch_views(
+    eff: &crate::paths::EffectivePaths,
+    all_arch: bool,
+) -> Result<Vec<crate::paths::EffectivePaths>> {
+    if !all_arch {
+        return Ok(vec![eff.clone()]);
+    }
+    let Some(arch) = &eff.arch else {
+        bail!("--all-arch needs install.arch_suffix = true");
+    };
+    let mut views = vec![eff.clone()];
+    views.extend(
+        eff.installed_arches()
+            .iter()
+            .filter(|a| *a != arch)
+            .map(|a| eff.for_arch(a)),
+    );
+    Ok(views)
+}
+
+/// `config diff` as TOML fragments: each setting under its table, with where it
+/// was set and its default as a comment.
+fn config_diff_toml(changed: &[crate::config::Changed]) -> String {
+    let mut out = String::new();
+    let mut section = None;
+    for c in changed {
+        let (table, key) = c.key.rsplit_once('.').unwrap_or(("", &c.key));
+        if section != Some(table) {
+            if section.is_some() {
+                out.push('\n');
+            }
+            out.push_str(&format!("[{}]\n", table));
+            section = Some(table);
+        }
+        let bare = key
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_');
+        let key = match bare {
+            true => key.to_string(),
+            false => toml::Value::String(key.to_string()).to_string(),
+        };
+        let default = c
+            .default
+            .as_ref()
+            .map_or("none".to_string(), |d| d.to_string());
+        out.push_str(&format!(
+            "{} = {}  # {} (default: {})\n",
+            key, c.value, c.source, default
+        ));
+    }
+    out
+}
+
+/// `status --porcelain` rendering (format is a stable contract, see StatusArgs).
+pub(crate) fn status_porcelain(eff: &crate::paths::EffectivePaths, local: Option<&str>) -> String {
+    format!(
+        "profile={}\nprefix={}\ncurrent_link={}\nshim={}\nversion={}\n",
+        eff.profile.as_deref().unwrap_or(""),
+        eff.prefix_dir.display(),
+        eff.current_symlink.display(),
+        eff.bin_shim.display(),
+        local.unwrap_or("")
+    )
+}
+
+/// "version not found" error listing the available versions (shared by use/info).
+pub(crate) fn version_not_found(
+    eff: &crate::paths::EffectivePaths,
+    version: &str,
+) -> anyhow::Error {
+    // Préparer un message d’erreur utile avec les versions dispo
+    let mut available = Vec::new();
+    if eff.versions_dir.exists() {
+        if let Ok(rd) = fs::read_dir(&eff.versions_dir) {
+            for ent in rd.flatten() {
+                let p = ent.path();
+                if p.is_dir() {
+                    if let Some(name) = p.file_name().and_then(|n| n.to_str()) {
+                        if crate::util::is_version_dir_name(name) {
+                            available.push(name.to_string());
+                        }
+                    }
+                }
+            }
+        }
+    }
+    available.sort();
+    crate::error::WindmanError::VersionNotFound {
+        requested: version.to_string(),
+        dir: eff.versions_dir.clone(),
+        available,
+    }
+    .into()
+}
+
+/// Everything `windman info` reports about one installed version.
+#[derive(Debug, serde::Serialize)]
+pub(crate) struct VersionInfo {
+    pub version: String,
+    pub installed_at: Option<String>,
+    pub source: Option<String>,
+    pub sha256: Option<String>,
+    /// Release channel it came from (`update`)
+    pub channel: Option<String>,
+    /// Archive kept at install (`--keep-archive`)
+    pub artifact: Option<std::path::PathBuf>,
+    pub size_bytes: u64,
+    pub current: bool,
+    pub pinned: bool,
+    pub notes: crate::notes::NotesStatus,
+}
+
+/// Installed version named `version`, or the current one when None; with its current flag.
+fn installed_version(
+    eff: &crate::paths::EffectivePaths,
+    version: Option<&str>,
+) -> anyhow::Result<(String, bool)> {
+    let installed = collect_installed(eff);
+    match version {
+        Some(v) => installed
+            .into_iter()
+            .find(|(n, _)| n == v)
+            .ok_or_else(|| version_not_found(eff, v)),
+        None => installed
+            .into_iter()
+            .find(|(_, cur)| *cur)
+            .ok_or_else(|| anyhow::anyhow!("no current version (is Windsurf installed?)")),
+    }
+}
+
+/// Gather `info` for `version`, or the current version when None.
+pub(crate) fn version_info(
+    eff: &crate::paths::EffectivePaths,
+    version: Option<&str>,
+) -> anyhow::Result<VersionInfo> {
+    let (name, current) = installed_version(eff, version)?;
+    let dir = eff.versions_dir.join(&name);
+    let meta = crate::meta::read(&dir);
+    // Versions installed before metadata existed: fall back to the dir mtime
+    let installed_at = meta.as_ref().map(|m| m.installed_at.clone()).or_else(|| {
+        fs::metadata(&dir)
+            .and_then(|m| m.modified())
+            .ok()
+            .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339())
+    });
+    Ok(VersionInfo {
+        size_bytes: crate::util::dir_size(&dir),
+        artifact: crate::meta::artifact(&dir),
+        installed_at,
+        source: meta.as_ref().map(|m| m.source.clone()),
+        channel: meta.as_ref().and_then(|m| m.channel.clone()),
+        sha256: meta.and_then(|m| m.sha256),
+        notes: crate::notes::status(&dir),
+        version: name,
+        current,
+        pinned: false, // no version pinning yet
+    })
+}
+
+/// Probe every arch and print version + URL per arch. Errors only if all endpoints failed.
+/// `value`, or every known value for "all".
+fn pick(what: &str, value: &str, known: &[&str]) -> Result<Vec<String>> {
+    if value == "all" {
+        return Ok(known.iter().map(|s| s.to_string()).collect());
+    }
+    if !known.contains(&value) {
+        bail!(
+            "unknown {} '{}' (expected {} or all)",
+            what,
+            value,
+            known.join(", ")
+        );
+    }
+    Ok(vec![value.to_string()])
+}
+
+fn print_latest_all(channel: &str, timeout: Option<u64>, json: bool) -> Result<()> {
+    let results = crate::remote::latest_all(channel, timeout);
+    let agree = crate::remote::versions_agree(&results);
+    let all_failed = results.values().all(|r| r.is_err());
+
+    if json {
+        let arches: serde_json::Map<String, serde_json::Value> = results
+            .iter()
+            .map(|(arch, r)| {
+                let v = match r {
+                    Ok(info) => serde_json::json!({ "version": info.version, "url": info.url }),
+                    Err(e) => serde_json::json!({ "error": format!("{:#}", e) }),
+                };
+                (arch.clone(), v)
+            })
+            .collect();
+        let out = serde_json::json!({ "channel": channel, "consistent": agree, "arches": arches });
+        println!("{}", serde_json::to_string_pretty(&out)?);
+    } else {
+        for (arch, r) in &results {
+            match r {
+                Ok(info) => println!("{:<12} {:<10} {}", arch, info.version, info.url),
+                Err(e) => println!("{:<12} ERROR      {:#}", arch, e),
+            }
+        }
+        if !agree {
+            println!("note: architectures disagree on the latest version (staggered rollout?)");
+        }
+    }
+    if all_failed {
+        bail!("all release endpoints failed");
+    }
+    Ok(())
+}
+
+/// Version dir `exec` should use: explicit version, else the nearest
+/// `.windsurf-version` above `cwd`, else current. Errors if not installed.
+pub(crate) fn exec_version_dir(
+    eff: &crate::paths::EffectivePaths,
+    explicit: Option<&str>,
+    cwd: &std::path::Path,
+) -> anyhow::Result<std::path::PathBuf> {
+    let (version, pinned_by) = match explicit {
+        Some(v) => (v.to_string(), None),
+        None => match crate::project::pinned_version(cwd)? {
+            Some((v, file)) => (v, Some(file)),
+            None => {
+                return fs::canonicalize(&eff.current_symlink).map_err(|_| {
+                    crate::error::WindmanError::NotInstalled {
+                        version: None,
+                        pinned_by: None,
+                    }
+                    .into()
+                })
+            }
+        },
+    };
+    let dir = eff.versions_dir.join(&version);
+    if !dir.is_dir() {
+        return Err(crate::error::WindmanError::NotInstalled {
+            version: Some(version),
+            pinned_by,
+        }
+        .into());
+    }
+    Ok(dir)
+}
+
+/// Hardlink files of the new current that are identical in `previous` (install.hardlink_dedup).
+fn dedup_with_previous(
+    eff: &crate::paths::EffectivePaths,
+    previous: Option<&std::path::Path>,
+    quiet: bool,
+) -> anyhow::Result<()> {
+    let (Some(prev), Some(cur)) = (previous, crate::util::resolve_symlink(&eff.current_symlink))
+    else {
+        return Ok(());
+    };
+    if fs::canonicalize(prev).ok().as_ref() == Some(&cur) {
+        return Ok(());
+    }
+    let mut cache = crate::dedup::HashCache::load(&eff.versions_dir);
+    let stats = crate::dedup::dedup_against(&cur, prev, &mut cache)?;
+    cache.retain_existing(&eff.versions_dir);
+    cache.save(&eff.versions_dir)?;
+    if !quiet {
+        println!(
+            "Deduplicated {} files against the previous version (saved {})",
+            stats.files_linked,
+            indicatif::HumanBytes(stats.bytes_saved)
+        );
+    }
+    Ok(())
+}
+
+pub(crate) fn switch_to_version(
+    eff: &crate::paths::EffectivePaths,
+    version: &str,
+) -> anyhow::Result<()> {
+    let target = eff.versions_dir.join(version);
+    if !target.is_dir() {
+        return Err(version_not_found(eff, version));
+    }
+
+    // Si current pointe déjà sur cette version, rien à faire
+    if let Some(cur) = crate::util::resolve_symlink(&eff.current_symlink) {
+        if fs::canonicalize(&target).ok() == Some(cur) {
+            println!("Already using {}.", version);
+            return Ok(());
+        }
+    }
+
+    let previous = crate::history::current_version_name(&eff.current_symlink);
+    crate::version::forget_cached_version(&eff.state_dir, &target);
+    crate::util::atomic_symlink_switch(&target, &eff.current_symlink)?;
+    crate::history::record(&eff.versions_dir, previous.as_deref(), version);
+    println!("Now using {}.", version);
+    Ok(())
+}
+
+/// `<cache>/downloads/<channel>/<version>`, created.
+fn download_dir(
+    cfg: &Config,
+    eff: &crate::paths::EffectivePaths,
+    latest: &crate::remote::LatestInfo,
+) -> anyhow::Result<std::path::PathBuf> {
+    let dir = eff
+        .cache_dir
+        .join("downloads")
+        .join(&cfg.install.channel)
+        .join(&latest.version);
+    fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+    Ok(dir)
+}
+
+/// The cached check: local version against the latest release, the answer of
+/// the API reused within `network.check_cache_ttl_minutes` unless `refresh`.
+fn check_report(
+    cfg: &Config,
+    eff: &crate::paths::EffectivePaths,
+    refresh: bool,
+) -> crate::check::CheckReport {
+    let local = version::detect_local_version(eff).unwrap_or(None);
+    let (latest, error) = crate::check::latest_cached(
+        &crate::check::cache_path(&eff.cache_dir, &cfg.install.channel),
+        chrono::Utc::now(),
+        cfg.network.check_cache_ttl_minutes,
+        refresh,
+        || {
+            crate::remote::latest_linux_x64(
+                &cfg.install.channel,
+                None,
+                cfg.network.allow_html_fallback,
+                (!refresh)
+                    .then(|| crate::remote::http_cache_path(&eff.cache_dir, &cfg.install.channel)),
+            )
+        },
+    );
+    crate::check::report(local, latest, error)
+}
+
+/// `check --watch`: the cached check every `--interval` until Ctrl-C (or
+/// `--max-iterations`), running `--on-update` once per newly seen version.
+fn watch_check(
+    cfg: &Config,
+    eff: &crate::paths::EffectivePaths,
+    args: &CheckArgs,
+) -> anyhow::Result<()> {
+    use std::io::{IsTerminal, Write};
+    let interval = crate::util::parse_duration(&args.interval)?;
+    if interval.is_zero() {
+        bail!("--interval must be longer than 0s");
+    }
+    let rewrite = !args.json && std::io::stdout().is_terminal();
+    let mut announced = None;
+    let mut checks = 0u64;
+    loop {
+        let report = check_report(cfg, eff, false);
+        if args.json {
+            println!("{}", serde_json::to_string(&report)?);
+        } else {
+            let line =
+                crate::check::watch_line(&report, chrono::Local::now().format("%Y-%m-%d %H:%M:%S"));
+            let style = if report.update_available {
+                crate::ui::NOTICE
+            } else {
+                anstyle::Style::new()
+            };
+            if rewrite {
+                // the line is cleared even without colors
+                print!("\r\x1b[2K");
+                anstream::print!("{}", crate::ui::paint(style, line));
+                let _ = std::io::stdout().flush();
+            } else {
+                anstream::println!("{}", crate::ui::paint(style, line));
+            }
+        }
+        if let Some(cmd) = &args.on_update {
+            if let Some(latest) = crate::check::newly_detected(&report, &mut announced) {
+                if rewrite {
+                    println!(); // the hook's output starts on a line of its own
+                }
+                let status = std::process::Command::new("sh")
+                    .arg("-c")
+                    .arg(cmd)
+                    .env("WINDMAN_LOCAL", report.local.as_deref().unwrap_or(""))
+                    .env("WINDMAN_LATEST", &latest)
+                    .status();
+                match status {
+                    Ok(s) if s.success() => {}
+                    Ok(s) => crate::ui::warn(format_args!("--on-update hook failed ({})", s)),
+                    Err(e) => crate::ui::warn(format_args!("running --on-update hook: {}", e)),
+                }
+            }
+        }
+        checks += 1;
+        if args.max_iterations.is_some_and(|max| checks >= max) {
+            break;
+        }
+        // in short steps, so Ctrl-C ends the watch at once
+        let until = std::time::Instant::now() + interval;
+        while !crate::cancel::is_cancelled() {
+            let left = until.saturating_duration_since(std::time::Instant::now());
+            if left.is_zero() {
+                break;
+            }
+            std::thread::sleep(left.min(std::time::Duration::from_millis(200)));
+        }
+        if crate::cancel::is_cancelled() {
+            break;
+        }
+    }
+    if rewrite {
+        println!();
+    }
+    Ok(())
+}
+
+/// Download `url` into the cache, check its signature (when enabled) and
+/// install it with `url` recorded as the source. `place` maps an archive file
+/// name to its cache path: the name in `url` at first, then the one the server
+/// gave (`Content-Disposition`, or the URL it redirected to) if that differs.
+/// The checksum is recorded next to the archive for `install --from-cache`.
+/// `step` follows along (download, verify, install).
+fn download_and_install_from(
+    cfg: &Config,
+    eff: &crate::paths::EffectivePaths,
+    url: &str,
+    place: &dyn Fn(&str) -> anyhow::Result<std::path::PathBuf>,
+    install_opts: &install::InstallOptions,
+    step: &std::cell::Cell<crate::report::Step>,
+) -> anyhow::Result<String> {
+    step.set(crate::report::Step::Download);
+    let check_room = |cache: &std::path::Path, len: u64| {
+        crate::space::check_download(
+            len,
+            Some(cache),
+            &eff.versions_dir,
+            cfg.install.extract_space_factor,
+            cfg.install.space_check,
+        )
+    };
+    let (tar_path, digest, final_url) = fetch_into_cache(url, place, &check_room, install_opts)?;
+    let tar_path = tar_path.as_path();
+
+    // Signature (optional): abort loudly and quarantine on any failure
+    if cfg.security.signature_verification {
+        step.set(crate::report::Step::Verify);
+        let checked = crate::remote::fetch_signature(url, tar_path, None).and_then(|sig| {
+            crate::verify::verify_archive(tar_path, &sig, &cfg.security.public_key)
+        });
+        if let Err(e) = checked {
+            let kept = crate::verify::quarantine(tar_path)?;
+            bail!(
+                "SIGNATURE VERIFICATION FAILED — nothing was installed.\n{}\nDownloaded archive kept for inspection at {}",
+                e,
+                kept.display()
+            );
+        }
+        if !install_opts.quiet {
+            println!("Signature verified.");
+        }
+    }
+
+    step.set(crate::report::Step::Install);
+    let opts = install::InstallOptions {
+        source: Some(url.to_string()),
+        sha256: Some(digest.clone()),
+        final_url,
+        verification: Some(if cfg.security.signature_verification {
+            crate::report::Verification::Signature
+        } else {
+            crate::report::Verification::Unsigned
+        }),
+        ..install_opts.clone()
+    };
+    let version = match install::install_from_tar(tar_path.to_string_lossy().as_ref(), eff, &opts) {
+        Ok(v) => v,
+        // an incomplete download is of no use in the cache: the next try starts afresh
+        Err(e) if install::is_truncation_error(&e) => {
+            let _ = fs::remove_file(tar_path);
+            return Err(e);
+        }
+        Err(e) => return Err(e),
+    };
+    if let Err(e) = crate::cache::record_checksum(tar_path, &digest) {
+        crate::ui::warn(format_args!("{:#}", e));
+    }
+    Ok(version)
+}
+
+/// Download `url` to where `place` says and return `(archive, sha256,
+/// final_url)`, `final_url` being where the download ended once redirects
+/// were followed. When another process (or thread) was downloading into the
+/// same cache dir, wait for it and reuse whichever archive is then newest in
+/// that dir (`cache::newest_archive`), not necessarily the file of this URL;
+/// `final_url` is None then. Downloads take their name only once complete, so
+/// what a finished one left is whole. `check_room` gets the cache dir and the
+/// announced size before anything is written.
+fn fetch_into_cache(
+    url: &str,
+    place: &dyn Fn(&str) -> anyhow::Result<std::path::PathBuf>,
+    check_room: &dyn Fn(&std::path::Path, u64) -> anyhow::Result<()>,
+    install_opts: &install::InstallOptions,
+) -> anyhow::Result<(std::path::PathBuf, String, Option<String>)> {
+    let mut tar_path = place(archive_name(url))?;
+    let dir = tar_path
+        .parent()
+        .map(std::path::Path::to_path_buf)
+        .unwrap_or_default();
+    let (lock, waited) = crate::cache::DownloadLock::acquire(&dir, |pid| {
+        if !install_opts.quiet {
+            eprintln!(
+                "Waiting for process {} to finish downloading into {}...",
+                pid,
+                dir.display()
+            );
+        }
+    })?;
+    if waited {
+        if let Some(archive) = crate::cache::newest_archive(&dir) {
+            drop(lock);
+            if !install_opts.quiet {
+                println!("Reusing {}", archive.display());
+            }
+            let digest = crate::meta::sha256_file(&archive)?;
+            return Ok((archive, digest, None));
+        }
+    }
+    // a checksum left by an earlier download of the same name is not this one's
+    let _ = fs::remove_file(crate::export::sidecar_path(&tar_path));
+    let dl_started = std::time::Instant::now();
+    let done = crate::download::download_archive(url, &tar_path, install_opts.progress, &|len| {
+        check_room(&dir, len)
+    })
+    .map_err(|e| anyhow::anyhow!("downloading {}: {}", url, e))?;
+    let digest = done.sha256;
+    if let Some(name) = done.origin.file_name() {
+        let named = place(&name)?;
+        if named != tar_path {
+            let _ = fs::remove_file(crate::export::sidecar_path(&named));
+            fs::rename(&tar_path, &named).with_context(|| {
+                format!("renaming {} -> {}", tar_path.display(), named.display())
+            })?;
+            tar_path = named;
+        }
+    }
+    if install_opts.verbose {
+        if done.origin.final_url != url {
+            eprintln!("[windman] redirected to {}", done.origin.final_url);
+        }
+        eprintln!(
+            "[windman] downloaded {} in {:.1}s, verified inline (sha256 {})",
+            tar_path.display(),
+            dl_started.elapsed().as_secs_f64(),
+            digest
+        );
+    }
+    drop(lock);
+    if tar_path.parent() != Some(dir.as_path()) {
+        let _ = fs::remove_dir(&dir); // only when nothing else is in there
+    }
+    Ok((tar_path, digest, Some(done.origin.final_url)))
+}
+
+/// `install --from-cache`: reinstall `version` from its archive in the download
+/// cache, without network. A checksum recorded at download time is verified.
+pub(crate) fn install_from_cache(
+    eff: &crate::paths::EffectivePaths,
+    version: &str,
+    install_opts: &install::InstallOptions,
+) -> anyhow::Result<String> {
+    let archive = crate::cache::find(&eff.cache_dir, version)?;
+    if !archive.checksum {
+        crate::ui::warn(format_args!(
+            "no checksum recorded for {}, installing it unverified",
+            archive.path.display()
+        ));
+    }
+    // without network: say how to get a good copy instead of fetching one
+    install::install_from_tar(archive.path.to_string_lossy().as_ref(), eff, install_opts).map_err(
+        |e| {
+            if !install::is_truncation_error(&e) {
+                return e;
+            }
+            let path = archive.path.display();
+            e.context(format!(
+                "the cached archive of {} is incomplete; delete it (`rm {}`) and download it again (`windman update`, or `windman install --url <URL>`)",
+                version, path
+            ))
+        },
+    )
+}
+
+/// `--sha256`: 64 hex digits, kept lowercase.
+fn parse_sha256(s: &str) -> anyhow::Result<String> {
+    if s.len() != 64 || !s.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("expected 64 hex digits");
+    }
+    Ok(s.to_ascii_lowercase())
+}
+
+/// The archive's checksum must be `expected` (`--sha256`), when given.
+fn check_sha256(what: &str, expected: Option<&str>, actual: &str) -> anyhow::Result<()> {
+    match expected {
+        Some(e) if !e.eq_ignore_ascii_case(actual) => {
+            Err(crate::error::WindmanError::VerificationFailed {
+                what: what.to_string(),
+                reason: format!("checksum mismatch: expected {}, archive is {}", e, actual),
+            }
+            .into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// `install --tar <FILE> [--sha256 <HEX>]`.
+fn install_from_file(
+    tar: &str,
+    eff: &crate::paths::EffectivePaths,
+    expected: Option<&str>,
+    install_opts: &install::InstallOptions,
+) -> anyhow::Result<String> {
+    let Some(expected) = expected else {
+        return install::install_from_tar(tar, eff, install_opts);
+    };
+    let actual = crate::meta::sha256_file(std::path::Path::new(tar))?;
+    check_sha256(tar, Some(expected), &actual)?;
+    let opts = install::InstallOptions {
+        sha256: Some(actual),
+        verification: Some(crate::report::Verification::Checksum),
+        ..install_opts.clone()
+    };
+    install::install_from_tar(tar, eff, &opts)
+}
+
+/// Stands in for the file name of an archive read from stdin.
+const STDIN_NAME: &str = "<stdin>";
+
+/// `install --tar -`: the archive piped in (never a terminal). It is buffered in
+/// `<cache>/stdin/` and installed from there, or, with `install.stream_extract`,
+/// extracted as it arrives. The version comes from product.json; `expected` is
+/// `--sha256`, checked before anything moves.
+pub(crate) fn install_from_stdin(
+    cfg: &Config,
+    eff: &crate::paths::EffectivePaths,
+    expected: Option<&str>,
+    install_opts: &install::InstallOptions,
+) -> anyhow::Result<String> {
+    use std::io::IsTerminal;
+    if std::io::stdin().is_terminal() {
+        bail!("--tar - reads the archive from stdin, but stdin is a terminal; pipe it in (e.g. `curl -L <URL> | windman install --tar -`)");
+    }
+    let opts = install::InstallOptions {
+        source: Some("stdin".to_string()),
+        verification: expected.map(|_| crate::report::Verification::Checksum),
+        ..install_opts.clone()
+    };
+    let mut input = crate::download::read_piped(std::io::stdin().lock(), install_opts.progress)?;
+    if cfg.install.stream_extract {
+        return install::install_from_stream(input, STDIN_NAME, eff, &opts, |input| {
+            let (digest, ()) = input.finish();
+            check_sha256(STDIN_NAME, expected, &digest)?;
+            Ok(digest)
+        });
+    }
+    let dir = eff.cache_dir.join("stdin");
+    fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+    // removed once installed (or not)
+    let mut buffered = tempfile::Builder::new()
+        .prefix("stdin-")
+        .tempfile_in(&dir)
+        .with_context(|| format!("creating a file in {}", dir.display()))?;
+    std::io::copy(&mut input, buffered.as_file_mut())
+        .with_context(|| format!("reading the archive from stdin into {}", dir.display()))?;
+    let (digest, ()) = input.finish();
+    check_sha256(STDIN_NAME, expected, &digest)?;
+    let opts = install::InstallOptions {
+        sha256: Some(digest),
+        ..opts
+    };
+    install::install_from_tar(buffered.path().to_string_lossy().as_ref(), eff, &opts)
+}
+
+/// `install --url`: a `file://` URL installs the local archive it names, an
+/// http(s) one is downloaded to `<cache>/downloads/url/<version>/` first (the
+/// version from the file name, so `gc` treats it like any other download).
+pub(crate) fn install_from_url(
+    cfg: &Config,
+    eff: &crate::paths::EffectivePaths,
+    url: &str,
+    install_opts: &install::InstallOptions,
+) -> anyhow::Result<String> {
+    if let Some(path) = url.strip_prefix("file://") {
+        let opts = install::InstallOptions {
+            source: Some(url.to_string()),
+            ..install_opts.clone()
+        };
+        return install::install_from_tar(path, eff, &opts);
+    }
+    if !(url.starts_with("https://") || url.starts_with("http://")) {
+        bail!(
+            "unsupported URL '{}' (expected https://, http:// or file://)",
+            url
+        );
+    }
+    let place = |name: &str| {
+        let dir = eff.cache_dir.join("downloads/url").join(
+            install::extract_version_from_filename(name).unwrap_or_else(|| "unversioned".into()),
+        );
+        fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+        Ok(dir.join(name))
+    };
+    download_and_install_from(cfg, eff, url, &place, install_opts, &Default::default())
+}
+
+/// File name of a release download URL (query string aside). Used until the
+/// server names the file otherwise (`download::Origin::file_name`).
+fn archive_name(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.rsplit('/')
+        .next()
+        .filter(|n| !n.is_empty())
+        .unwrap_or("windsurf-linux-x64.tar.gz")
+}
+
+/// After a switch: refresh the `shim add` launchers and warn about those the
+/// new current lacks (the switch stands either way).
+fn warn_about_shims(eff: &crate::paths::EffectivePaths) {
+    for w in crate::shims::refresh(eff) {
+        crate::ui::warn(w);
+    }
+}
+
+/// `use <version>`: like `switch_to_version`, behind the downgrade gate.
+pub(crate) fn use_version(
+    eff: &crate::paths::EffectivePaths,
+    version: &str,
+    allow_downgrade: bool,
+) -> anyhow::Result<()> {
+    if eff.versions_dir.join(version).is_dir() {
+        let current = crate::history::current_version_name(&eff.current_symlink);
+        install::guard_downgrade(current.as_deref(), version, allow_downgrade)?;
+        warn_channel_switch(eff, current.as_deref(), version);
+    }
+    switch_to_version(eff, version)
+}
+
+/// Warn when current and `to` come from different release channels (both on
+/// record): `update` only follows the configured one.
+fn warn_channel_switch(eff: &crate::paths::EffectivePaths, current: Option<&str>, to: &str) {
+    let channel = |v: &str| crate::meta::read(&eff.versions_dir.join(v)).and_then(|m| m.channel);
+    let Some(current) = current else {
+        return;
+    };
+    if let (Some(from), Some(to_channel)) = (channel(current), channel(to)) {
+        if from != to_channel {
+            crate::ui::warn(format_args!(
+                "switching channels: {} is a {} build, {} a {} one",
+                current, from, to, to_channel
+            ));
+        }
+    }
+}
+
+/// Prune after an install or update switched current: the new current, the one
+/// it replaced and (unless the downgrade was allowed) newer versions are kept.
+/// Skipped with `--no-prune` or `install.auto_prune = false`. The versions
+/// removed, with their sizes.
+fn prune_after_switch(
+    cfg: &Config,
+    eff: &EffectivePaths,
+    keep: usize,
+    previous: Option<&std::path::Path>,
+    allow_downgrade: bool,
+    no_prune: bool,
+    quiet: bool,
+) -> anyhow::Result<Vec<crate::report::Pruned>> {
+    if no_prune || !cfg.install.auto_prune {
+        // `install.auto_prune = false` is a standing choice: no need to repeat it
+        if no_prune && !quiet {
+            println!("skipping prune (--no-prune)");
+        }
+        return Ok(Vec::new());
+    }
+    let decisions = prune::PrunePlan::for_install(eff, &cfg.install, keep)?
+        .previous(previous)
+        .protect_newer(!allow_downgrade)
+        .decide();
+    let over = prune::over_budget(&decisions);
+    let pruned = prune::removals(&decisions)
+        .into_iter()
+        .map(|p| crate::report::Pruned {
+            version: p
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            bytes: crate::util::dir_size(&p),
+            to_trash: cfg.install.prune_to_trash && !over.contains(&p),
+        })
+        .collect();
+    prune::apply(&eff.versions_dir, &decisions, cfg.install.prune_to_trash)?;
+    if let Some((used, limit)) = budget_usage(cfg, eff) {
+        warn_over_budget(used, limit);
+    }
+    Ok(pruned)
+}
+
+/// Bytes the install uses and `install.max_total_size`; None without a limit.
+fn budget_usage(cfg: &Config, eff: &EffectivePaths) -> Option<(u64, u64)> {
+    let limit = cfg.install.max_total_bytes()?;
+    Some((prune::usage(&eff.versions_dir), limit))
+}
+
+/// Warn when pruning cannot bring the install under the limit: what is left
+/// is current, pinned or kept by keep_patterns / keep_per_minor.
+fn warn_over_budget(used: u64, limit: u64) {
+    if used > limit {
+        crate::ui::warn(format_args!(
+            "{} used, over install.max_total_size ({}) even after pruning",
+            indicatif::HumanBytes(used),
+            indicatif::HumanBytes(limit)
+        ));
+    }
+}
+
+/// "2.10 GiB of 3.00 GiB (max_total_size)", or just the size without a limit.
+fn usage_text(used: u64, limit: Option<u64>) -> String {
+    match limit {
+        Some(limit) => format!(
+            "{} of {} (max_total_size)",
+            indicatif::HumanBytes(used),
+            indicatif::HumanBytes(limit)
+        ),
+        None => indicatif::HumanBytes(used).to_string(),
+    }
+}
+
+/// Resolve `use +N` / `use -N`: N steps newer / older than current in the
+/// installed versions (semver order). None when `spec` is not relative.
+pub(crate) fn relative_version(
+    eff: &crate::paths::EffectivePaths,
+    spec: &str,
+) -> anyhow::Result<Option<String>> {
+    let (newer, steps) = match spec.split_at(spec.len().min(1)) {
+        ("+", n) => (true, n),
+        ("-", n) => (false, n),
+        _ => return Ok(None),
+    };
+    let Ok(steps) = steps.parse::<usize>() else {
+        return Ok(None);
+    };
+    // newest first
+    let installed = collect_installed(eff);
+    let Some(pos) = installed.iter().position(|(_, cur)| *cur) else {
+        bail!("no current version to move from");
+    };
+    let target = if newer {
+        pos.checked_sub(steps)
+    } else {
+        Some(pos + steps).filter(|&i| i < installed.len())
+    };
+    match target {
+        Some(i) => Ok(Some(installed[i].0.clone())),
+        None if newer && pos == 0 => {
+            bail!("already at newest installed version ({})", installed[pos].0)
+        }
+        None if !newer && pos + 1 == installed.len() => {
+            bail!("already at oldest installed version ({})", installed[pos].0)
+        }
+        None => bail!(
+            "only {} {} installed version(s) than {}",
+            if newer {
+                pos
+            } else {
+                installed.len() - pos - 1
+            },
+            if newer { "newer" } else { "older" },
+            installed[pos].0
+        ),
+    }
+}
+
+/// Rewrite the shim (unless `manage_shim` is false) and the desktop entry (when
+/// `desktop` is given) if missing, pointing at another `current` or not matching
+/// `notice`. Returns a description of each repair made.
+pub(crate) fn refresh_integration(
+    eff: &crate::paths::EffectivePaths,
+    desktop: Option<&crate::config::DesktopConfig>,
+    manage_shim: bool,
+    notice: Option<&crate::util::ShimNotice>,
+) -> anyhow::Result<Vec<String>> {
+    let mut repaired = Vec::new();
+    if manage_shim
+        && (!crate::util::shim_points_to(&eff.bin_shim, &eff.shim_link())
+            || crate::util::shim_has_notice(&eff.bin_shim) != notice.is_some())
+    {
+        crate::util::write_shim(&eff.bin_shim, &eff.shim_link(), notice)?;
+        repaired.push(format!("shim {}", eff.bin_shim.display()));
+    }
+    if let Some(d) = desktop.filter(|d| !desktop::desktop_entry_is_fresh(eff, d)) {
+        desktop::ensure_desktop_files(eff, d)?;
+        repaired.push(format!("desktop entry {}", eff.desktop_file.display()));
+    }
+    Ok(repaired)
+}
+
+/// True when `dir` is one of the entries of a `PATH`-style list.
+fn path_has_dir(path: &std::ffi::OsStr, dir: &std::path::Path) -> bool {
+    let canonical = fs::canonicalize(dir).ok();
+    std::env::split_paths(path)
+        .any(|p| p == dir || (canonical.is_some() && fs::canonicalize(&p).ok() == canonical))
+}
+
+/// What `doctor` finds wrong with `current`, the shim (unless `manage_shim` is
+/// false) and (when `desktop` is given) the desktop entry. Empty when all is well.
+pub(crate) fn doctor_problems(
+    eff: &crate::paths::EffectivePaths,
+    desktop: Option<&crate::config::DesktopConfig>,
+    manage_shim: bool,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+    if fs::symlink_metadata(&eff.current_symlink).is_err() {
+        problems.push(format!(
+            "no current version ({} is missing)",
+            eff.current_symlink.display()
+        ));
+    } else if crate::util::resolve_symlink(&eff.current_symlink).is_none() {
+        problems.push(format!(
+            "{} points to a version that no longer exists",
+            eff.current_symlink.display()
+        ));
+    }
+    match crate::util::shim_current_link(&eff.bin_shim) {
+        _ if !manage_shim => {}
+        _ if !eff.bin_shim.exists() => {
+            problems.push(format!("shim {} is missing", eff.bin_shim.display()))
+        }
+        None => problems.push(format!("{} is not a windman shim", eff.bin_shim.display())),
+        Some(link) if link != eff.shim_link() => problems.push(format!(
+            "shim {} still runs {} (left over from another prefix)",
+            eff.bin_shim.display(),
+            link.display()
+        )),
+        Some(_) => {}
+    }
+    if desktop.is_some() {
+        problems.extend(desktop::integration_status(eff).problems());
+    }
+    problems
+}
+
+/// One `history --operations` row: when, what, versions, duration, download, outcome.
+fn operation_line(op: &crate::audit::Operation) -> String {
+    let versions = match (&op.from, &op.to) {
+        (f, t) if f == t => t.clone().unwrap_or_default(),
+        (f, t) => format!(
+            "{} -> {}",
+            f.as_deref().unwrap_or("<none>"),
+            t.as_deref().unwrap_or("<none>")
+        ),
+    };
+    let mut line = format!(
+        "{}  {:<14} {:<20} {:>7.1}s {:>10}  ",
+        op.at
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S"),
+        match &op.profile {
+            Some(p) => format!("{} [{}]", op.command, p),
+            None => op.command.clone(),
+        },
+        versions,
+        op.duration_ms as f64 / 1000.0,
+        indicatif::HumanBytes(op.bytes_downloaded).to_string()
+    );
+    match &op.error {
+        None => line.push_str("ok"),
+        Some(e) => line.push_str(&format!("failed: {}", e.lines().next().unwrap_or(e))),
+    }
+    line
+}
+
+impl Commands {
+    /// Commands that write into the prefix, bin dir or cache.
+    fn mutates_install(&self) -> bool {
+        matches!(
+            self,
+            Commands::Install(_)
+                | Commands::Update(_)
+                | Commands::Use(_)
+                | Commands::Activate(_)
+                | Commands::Rollback(RollbackArgs {
+                    list: false,
+                    dry_run: false
+                })
+                | Commands::Rollforward
+                | Commands::Uninstall { dry_run: false, .. }
+                | Commands::Remove(_)
+                | Commands::Trash(_)
+                | Commands::Dedupe
+                | Commands::Gc(_)
+                | Commands::Prune(_)
+                | Commands::Doctor(DoctorArgs { fix: true })
+                | Commands::MigratePrefix(_)
+                | Commands::Init(_)
+                | Commands::Shim(ShimCmd::Add { .. } | ShimCmd::Remove { .. })
+        )
+    }
+
+    /// Name in the operations log, for the runs that change something.
+    fn audit_name(&self) -> Option<&'static str> {
+        let name = match self {
+            Commands::Install(a) if !a.dry_run => "install",
+            Commands::Update(a) if !a.dry_run => "update",
+            Commands::Use(a) if !a.dry_run => "use",
+            Commands::Activate(a) if !a.dry_run => "activate",
+            Commands::Gc(a) if !a.dry_run => "gc",
+            Commands::Prune(a) if !a.dry_run => "prune",
+            Commands::Install(_)
+            | Commands::Update(_)
+            | Commands::Use(_)
+            | Commands::Activate(_)
+            | Commands::Gc(_)
+            | Commands::Prune(_) => return None,
+            Commands::Rollback(RollbackArgs {
+                list: false,
+                dry_run: false,
+            }) => "rollback",
+            Commands::Rollforward => "rollforward",
+            Commands::Uninstall { dry_run: false, .. } => "uninstall",
+            Commands::Remove(_) => "remove",
+            Commands::Trash(TrashCmd::Restore { .. }) => "trash restore",
+            Commands::Trash(TrashCmd::Empty { .. }) => "trash empty",
+            Commands::Dedupe => "dedupe",
+            Commands::Doctor(DoctorArgs { fix: true }) => "doctor --fix",
+            Commands::MigratePrefix(_) => "migrate-prefix",
+            Commands::Init(_) => "init",
+            Commands::Cache(CacheCmd::Move { .. }) => "cache move",
+            Commands::Shim(ShimCmd::Add { .. }) => "shim add",
+            Commands::Shim(ShimCmd::Remove { .. }) => "shim remove",
+            _ => return None,
+        };
+        Some(name)
+    }
+}
+
+impl Cli {
+    /// The command line of this run, user aliases (`[cli.aliases]`) expanded.
+    pub fn parse() -> Self {
+        use clap::CommandFactory;
+        let args = std::env::args_os().collect();
+        match crate::alias::expand_args(args, &Cli::command()) {
+            Ok(args) => <Cli as Parser>::parse_from(args),
+            Err(e) => Cli::command()
+                .error(clap::error::ErrorKind::InvalidSubcommand, e)
+                .exit(),
+        }
+    }
+
+    /// One `update` pass for a resolved config. Returns the installed version,
+    /// or None when already up to date (or dry-run). `report` is filled in as
+    /// each step completes.
+    fn update_one(
+        &self,
+        args: &UpdateArgs,
+        cfg: &Config,
+        eff: &EffectivePaths,
+        install_opts: &install::InstallOptions,
+        report: &mut crate::report::UpdateReport,
+    ) -> Result<Option<String>> {
+        use crate::report::{DesktopAction, Outcome, Step, Verification};
+        use std::path::PathBuf;
+
+        // 1) Local version
+        let local = version::detect_local_version(eff)?;
+        report.previous_version = local.clone();
+
+        // 2) Remote via API (version + url)
+        let mut latest = crate::remote::latest_linux_x64(
+            &cfg.install.channel,
+            None,
+            args.allow_html_fallback || cfg.network.allow_html_fallback,
+            (!args.refresh)
+                .then(|| crate::remote::http_cache_path(&eff.cache_dir, &cfg.install.channel)),
+        )?;
+
+        // 2b) A blocked latest: stay put, or fall back to the newest release
+        //     that is not blocked
+        let blocked = crate::blocklist::load(&eff.state_dir)?;
+        if blocked.contains(&latest.version) {
+            if !args.next_best {
+                println!(
+                    "Windsurf {} is blocked; staying on {} (`windman unblock {}` allows it, --next-best installs the newest release that is not blocked).",
+                    latest.version,
+                    local.as_deref().unwrap_or("<none>"),
+                    latest.version
+                );
+                report.latest_version = Some(latest.version);
+                report.outcome = Outcome::Blocked;
+                return Ok(None);
+            }
+            let html = crate::remote::fetch_releases_html(&cfg.changelog.source_url, None)?;
+            match crate::remote::releases_from_html(&html)
+                .into_iter()
+                .find(|r| !blocked.contains(&r.version))
+            {
+                Some(next) => {
+                    println!(
+                        "Windsurf {} is blocked; using {} instead.",
+                        latest.version, next.version
+                    );
+                    latest = next;
+                }
+                None => {
+                    println!(
+                        "Windsurf {} is blocked and the releases page lists no other release; staying on {}.",
+                        latest.version,
+                        local.as_deref().unwrap_or("<none>")
+                    );
+                    report.latest_version = Some(latest.version);
+                    report.outcome = Outcome::Blocked;
+                    return Ok(None);
+                }
+            }
+        }
+        report.latest_version = Some(latest.version.clone());
+        report.url = Some(latest.url.clone());
+
+        // 3) Compare: by version, else by the download current came from. A
+        //    current from another channel is no baseline: the newest install
+        //    from this one is, and switching back to it is no downgrade
+        let channel = &cfg.install.channel;
+        let current = version::LocalRelease::of_current(eff, local.clone());
+        let cross_channel = current.channel().filter(|_| !current.is_from(channel));
+        let installed = match cross_channel {
+            None => current.clone(),
+            Some(other) => {
+                let base = version::LocalRelease::baseline(eff, local.clone(), channel);
+                println!(
+                    "Current ({}) is a {} build; comparing the latest {} release with {}.",
+                    local.as_deref().unwrap_or("<none>"),
+                    other,
+                    channel,
+                    base.version
+                        .as_deref()
+                        .unwrap_or("nothing installed from it")
+                );
+                base
+            }
+        };
+        let install_opts = &install::InstallOptions {
+            channel: Some(channel.clone()),
+            allow_downgrade: install_opts.allow_downgrade || cross_channel.is_some(),
+            ..install_opts.clone()
+        };
+        let local_s = installed.version.as_deref().unwrap_or("<none>");
+        match version::decide_update_action(&installed, &latest) {
+            version::UpdateDecision::Install => {}
+            decision @ (version::UpdateDecision::UpToDate
+            | version::UpdateDecision::SameDownload) => {
+                let same_download = decision == version::UpdateDecision::SameDownload;
+                if !self.quiet {
+                    println!(
+                        "{}",
+                        crate::error::WindmanError::AlreadyUpToDate {
+                            local: local_s.to_string(),
+                            latest: latest.version.clone(),
+                            from_download: same_download.then(|| latest.url.clone()),
+                        }
+                    );
+                }
+                report.outcome = Outcome::UpToDate;
+                return Ok(None);
+            }
+            version::UpdateDecision::InstallUnversioned => crate::ui::warn(format_args!(
+                "the latest release has no version number ({}); installing it since its download differs from the installed one",
+                latest.version
+            )),
+            version::UpdateDecision::LocalUnknown if !args.force => {
+                crate::ui::warn(format_args!(
+                    "the installed version {} is not a release number and cannot be compared with {}; `windman update --force` reinstalls the latest",
+                    local_s, latest.version
+                ));
+                report.outcome = Outcome::UpToDate;
+                return Ok(None);
+            }
+            version::UpdateDecision::LocalUnknown => {}
+        }
+
+        // 3b) Staged already: nothing to download until it is activated
+        if args.stage_only {
+            let staged = crate::staged::load(&eff.state_dir, &eff.versions_dir)?;
+            if staged.is_some_and(|s| s.version == latest.version) {
+                println!(
+                    "Windsurf {} is already staged; `windman activate` switches to it.",
+                    latest.version
+                );
+                report.outcome = Outcome::UpToDate;
+                return Ok(None);
+            }
+        }
+
+        // 4) Dry-run?
+        if args.dry_run {
+            println!("[dry-run] local : {}", local_s);
+            println!("[dry-run] latest: {}", latest.version);
+            println!("[dry-run] url   : {}", latest.url);
+            report.outcome = Outcome::DryRun;
+            return Ok(None);
+        }
+
+        // 5) Download and install (avec capture de l'ancienne current pour prune):
+        //    through the cache, or extracted while downloading
+        let previous_current: Option<PathBuf> = crate::util::resolve_symlink(&eff.current_symlink);
+        let step = std::cell::Cell::new(Step::Download);
+        let downloaded_before = crate::download::bytes_downloaded();
+        let installed = if args.stream || cfg.install.stream_extract {
+            match self.stream_install(cfg, eff, &latest, install_opts, &step) {
+                // nothing to resume from: start over the regular way
+                Err(e)
+                    if crate::download::is_interrupted(&e) || install::is_truncation_error(&e) =>
+                {
+                    crate::ui::warn(format_args!("{:#}\nRetrying with a regular download.", e));
+                    self.download_and_install(cfg, eff, &latest, install_opts, &step)
+                }
+                r => r,
+            }
+        } else {
+            self.download_and_install(cfg, eff, &latest, install_opts, &step)
+        };
+        report.step = step.get();
+        report.bytes_downloaded = crate::download::bytes_downloaded() - downloaded_before;
+        let ver = installed?;
+        println!("Installed Windsurf {} to {:?}", ver, eff.prefix_dir);
+        report.new_version = Some(ver.clone());
+        report.sha256 = crate::meta::read(&eff.versions_dir.join(&ver)).and_then(|m| m.sha256);
+        report.verification = Some(if cfg.security.signature_verification {
+            Verification::Signature
+        } else {
+            Verification::Unsigned
+        });
+
+        // 6b) Release notes, kept for later (`windman notes`); never fails the update
+        let page = crate::remote::fetch_releases_html(&cfg.changelog.source_url, None);
+        let notes = crate::notes::store(&eff.versions_dir.join(&ver), &ver, &page);
+        if self.verbose {
+            eprintln!("[windman] release notes: {:?}", notes);
+        }
+
+        // 6c) Two-phase update: `activate` does the rest
+        if args.stage_only {
+            crate::staged::save(&eff.state_dir, &ver)?;
+            println!(
+                "Staged Windsurf {} (not active); `windman activate` switches to it.",
+                ver
+            );
+            report.step = Step::Done;
+            report.outcome = Outcome::Staged;
+            return Ok(Some(ver));
+        }
+        warn_about_shims(eff);
+
+        // 7) Desktop
+        report.step = Step::Desktop;
+        let want_desktop = if args.no_desktop {
+            false
+        } else {
+            args.desktop || cfg.install.desktop_integration
+        };
+        if want_desktop {
+            desktop::ensure_desktop_files(eff, &cfg.desktop)?;
+            if !self.quiet {
+                println!("Desktop entry installed");
+            }
+            report.desktop = Some(DesktopAction::Updated);
+        } else {
+            report.desktop = Some(DesktopAction::Skipped);
+        }
+
+        if cfg.install.hardlink_dedup {
+            dedup_with_previous(eff, previous_current.as_deref(), self.quiet)?;
+        }
+
+        // 8) Prune: préserver la nouvelle current + l'ancienne current
+        report.step = Step::Prune;
+        report.pruned = prune_after_switch(
+            cfg,
+            eff,
+            cfg.install.keep,
+            previous_current.as_deref(),
+            args.allow_downgrade,
+            args.no_prune,
+            self.quiet,
+        )?;
+
+        report.step = Step::Done;
+        report.outcome = Outcome::Updated;
+        Ok(Some(ver))
+    }
+
+    /// `windman init`. A first run asks for the prefix, bin dir and desktop
+    /// entry (unless `--defaults`), writes the config, checks PATH and runs the
+    /// first update; a later one finds the config and the install and repairs
+    /// what `doctor` finds wrong instead of installing again.
+    fn init(
+        &self,
+        args: &InitArgs,
+        cfg_paths: &ConfigPaths,
+        cfg: &Config,
+        eff: &EffectivePaths,
+        cfg_found: bool,
+        install_opts: &install::InstallOptions,
+    ) -> Result<()> {
+        if self.profile.is_some() {
+            bail!("init sets up the default install; add [profiles.NAME] to the config afterwards");
+        }
+        let (cfg, eff) = if cfg_found {
+            println!("Using the existing config {}", cfg_paths.config_display());
+            (cfg.clone(), eff.clone())
+        } else {
+            let mut new_cfg = cfg.clone();
+            if !args.defaults {
+                new_cfg.install.prefix_dir = crate::util::ask(
+                    "Install Windsurf versions under",
+                    &new_cfg.install.prefix_dir,
+                )?;
+                new_cfg.install.bin_dir =
+                    crate::util::ask("Put the `windsurf` launcher in", &new_cfg.install.bin_dir)?;
+            }
+            new_cfg.install.desktop_integration = match (args.desktop, args.no_desktop) {
+                (true, _) => true,
+                (_, true) => false,
+                _ if args.defaults => new_cfg.install.desktop_integration,
+                _ => crate::util::confirm("Add Windsurf to the applications menu?")?,
+            };
+            let problems = new_cfg.validate();
+            if !problems.is_empty() {
+                bail!("invalid choices:\n  - {}", problems.join("\n  - "));
+            }
+            new_cfg.save_if_missing(cfg_paths)?;
+            println!("Config written to {}", cfg_paths.config_display());
+            let new_eff = resolve_paths(&new_cfg, None)?;
+            (new_cfg, new_eff)
+        };
+
+        if cfg.install.manage_shim
+            && !path_has_dir(&std::env::var_os("PATH").unwrap_or_default(), &eff.bin_dir)
+        {
+            println!(
+                "warning: {} is not in PATH; add `export PATH=\"{}:$PATH\"` to your shell init (or `eval \"$(windman where --shell bash)\"`)",
+                eff.bin_dir.display(),
+                eff.bin_dir.display()
+            );
+        }
+
+        let desktop = cfg.install.desktop_integration.then_some(&cfg.desktop);
+        if fs::symlink_metadata(&eff.current_symlink).is_ok() {
+            // already set up: check it rather than install again
+            let version = crate::history::current_version_name(&eff.current_symlink);
+            println!(
+                "Windsurf {} is installed in {}",
+                version.as_deref().unwrap_or("?"),
+                eff.prefix_dir.display()
+            );
+            let problems = doctor_problems(&eff, desktop, cfg.install.manage_shim);
+            for p in &problems {
+                println!("problem: {}", p);
+            }
+            if !problems.is_empty()
+                && (args.defaults || crate::util::confirm("Repair these problems?")?)
+            {
+                let notice = self.shim_notice(&cfg, &eff);
+                for what in
+                    refresh_integration(&eff, desktop, cfg.install.manage_shim, notice.as_ref())?
+                {
+                    println!("Repaired {}", what);
+                }
+            }
+        } else if !args.skip_install
+            && (args.defaults || crate::util::confirm("Install the latest Windsurf now?")?)
+        {
+            let update = UpdateArgs {
+                desktop: false,
+                no_desktop: false,
+                force: false,
+                no_smoke_test: false,
+                allow_downgrade: false,
+                require_closed: false,
+                no_prune: false,
+                stream: false,
+                keep_archive: false,
+                dry_run: false,
+                all_profiles: false,
+                notify: false,
+                refresh: false,
+                allow_html_fallback: false,
+                next_best: false,
+                stage_only: false,
+                json: false,
+                no_shim: false,
+            };
+            let opts = install::InstallOptions {
+                shim_notice: self.shim_notice(&cfg, &eff),
+                ..install_opts.clone()
+            };
+            let mut report = crate::report::UpdateReport::new(None);
+            self.update_one(&update, &cfg, &eff, &opts, &mut report)?;
+        }
+
+        println!("\nNext:");
+        if crate::util::resolve_symlink(&eff.current_symlink).is_some() {
+            println!("  {:<18} start Windsurf", cfg.install.bin_name);
+        } else {
+            println!("  {:<18} install the latest Windsurf", "windman update");
+        }
+        println!("  {:<18} check for a newer release", "windman check");
+        println!("  {:<18} show what is installed where", "windman status");
+        println!("  {:<18} look for problems later on", "windman doctor");
+        Ok(())
+    }
+
+    /// Download the release archive into the cache, check its signature and install it.
+    fn download_and_install(
+        &self,
+        cfg: &Config,
+        eff: &EffectivePaths,
+        latest: &crate::remote::LatestInfo,
+        install_opts: &install::InstallOptions,
+        step: &std::cell::Cell<crate::report::Step>,
+    ) -> Result<String> {
+        let place = |name: &str| Ok(download_dir(cfg, eff, latest)?.join(name));
+        match download_and_install_from(cfg, eff, &latest.url, &place, install_opts, step) {
+            // the incomplete archive was dropped from the cache: one more try
+            Err(e) if install::is_truncation_error(&e) => {
+                crate::ui::warn(format_args!("{:#}\nDownloading it again.", e));
+                download_and_install_from(cfg, eff, &latest.url, &place, install_opts, step)
+            }
+            r => r,
+        }
+    }
+
+    /// `update --stream`: extract the download body as it arrives, checksum and
+    /// signature computed on the way; the archive itself is never written.
+    /// `step` stays at download until it is all in.
+    fn stream_install(
+        &self,
+        cfg: &Config,
+        eff: &EffectivePaths,
+        latest: &crate::remote::LatestInfo,
+        install_opts: &install::InstallOptions,
+        step: &std::cell::Cell<crate::report::Step>,
+    ) -> Result<String> {
+        step.set(crate::report::Step::Download);
+        let filename = archive_name(&latest.url);
+
+        // The signature first (it is small): the body can only be read once
+        let check = if cfg.security.signature_verification {
+            let dl_dir = download_dir(cfg, eff, latest)?;
+            let loaded = crate::remote::fetch_signature(&latest.url, &dl_dir.join(filename), None)
+                .and_then(|sig| {
+                    crate::verify::SignatureCheck::load(&sig, &cfg.security.public_key)
+                });
+            match loaded {
+                Ok(check) => Some(check),
+                Err(e) => bail!(
+                    "SIGNATURE VERIFICATION FAILED — nothing was installed.\n{}",
+                    e
+                ),
+            }
+        } else {
+            None
+        };
+        let verifier = check.as_ref().map(|c| c.verifier()).transpose()?;
+
+        let body = crate::download::stream(&latest.url, None, self.progress(), verifier)?;
+        if let Some(len) = body.expected_len() {
+            crate::space::check_download(
+                len,
+                None,
+                &eff.versions_dir,
+                cfg.install.extract_space_factor,
+                cfg.install.space_check,
+            )?;
+        }
+        let filename = body.origin().file_name().unwrap_or(filename.to_string());
+        let opts = install::InstallOptions {
+            source: Some(latest.url.clone()),
+            final_url: Some(body.origin().final_url.clone()),
+            verification: Some(if check.is_some() {
+                crate::report::Verification::Signature
+            } else {
+                crate::report::Verification::Unsigned
+            }),
+            ..install_opts.clone()
+        };
+        install::install_from_stream(body, &filename, eff, &opts, |body| {
+            let (digest, verifier) = body.finish();
+            if let Some(v) = verifier {
+                step.set(crate::report::Step::Verify);
+                if let Err(e) = v.finalize(&latest.url) {
+                    bail!(
+                        "SIGNATURE VERIFICATION FAILED — nothing was installed.\n{}",
+                        e
+                    );
+                }
+                if !self.quiet {
+                    println!("Signature verified.");
+                }
+            }
+            step.set(crate::report::Step::Install);
+            Ok(digest)
+        })
+    }
+
+    /// The update notice shims get with `install.shim_update_notice`: this binary,
+    /// run with the same config and the profile `eff` belongs to.
+    fn shim_notice(&self, cfg: &Config, eff: &EffectivePaths) -> Option<crate::util::ShimNotice> {
+        if !cfg.install.shim_update_notice {
+            return None;
+        }
+        let mut args = Vec::new();
+        if let Some(c) = &self.config {
+            args.extend(["--config".to_string(), c.clone()]);
+        }
+        if let Some(p) = &eff.profile {
+            args.extend(["--profile".to_string(), p.clone()]);
+        }
+        Some(crate::util::ShimNotice {
+            exe: std::env::current_exe().ok()?,
+            args,
+        })
+    }
+
+    /// `notify-check` with a stale cache: refresh it in a detached process so the
+    /// launch is not held up by the network.
+    fn spawn_notice_refresh(&self, eff: &EffectivePaths) -> Result<()> {
+        use std::os::unix::process::CommandExt;
+        use std::process::{Command, Stdio};
+        let mut cmd = Command::new(std::env::current_exe()?);
+        if let Some(c) = &self.config {
+            cmd.arg("--config").arg(c);
+        }
+        if let Some(p) = &eff.profile {
+            cmd.arg("--profile").arg(p);
+        }
+        cmd.args(["notify-check", "--refresh"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .process_group(0)
+            .spawn()?;
+        Ok(())
+    }
+
+    /// How downloads and extraction show progress in this run.
+    fn progress(&self) -> crate::progress::ProgressMode {
+        crate::progress::ProgressMode::detect(self.quiet, self.no_progress)
+    }
+
+    /// Load the config, apply `profile` and the global overrides, resolve paths.
+    /// Also says whether the config file was found.
+    fn resolve(
+        &self,
+        cfg_paths: &ConfigPaths,
+        profile: Option<&str>,
+    ) -> Result<(Config, EffectivePaths, bool)> {
+        // `config migrate` reports the legacy keys itself
+        let (mut cfg, found) = match &self.cmd {
+            Commands::Config(ConfigCmd::Migrate) => {
+                (Config::load(cfg_paths)?.0, cfg_paths.file.exists())
+            }
+            _ => Config::load_or_default(cfg_paths)?,
+        };
+
+        if let Some(name) = profile {
+            cfg.apply_profile(name)?;
+        }
+
+        // Appliquer les overrides globaux (courte vie, n’écrit pas la config)
+        if let Some(p) = &self.prefix {
+            cfg.install.prefix_dir = p.clone();
+        }
+        if let Some(b) = &self.bin_dir {
+            cfg.install.bin_dir = b.clone();
+        }
+
+        if self.system {
+            cfg.install.scope = Scope::System;
+        }
+        if matches!(
+            &self.cmd,
+            Commands::Install(InstallArgs { no_shim: true, .. })
+                | Commands::Update(UpdateArgs { no_shim: true, .. })
+        ) {
+            cfg.install.manage_shim = false;
+        }
+        if matches!(
+            &self.cmd,
+            Commands::Install(InstallArgs {
+                no_desktop: true,
+                ..
+            }) | Commands::Update(UpdateArgs {
+                no_desktop: true,
+                ..
+            }) | Commands::Activate(ActivateArgs {
+                no_desktop: true,
+                ..
+            })
+        ) {
+            cfg.install.desktop_integration = false;
+        }
+
+        let eff = resolve_paths(&cfg, profile)?;
+        Ok((cfg, eff, found))
+    }
+
+    /// Run the command; the ones that change something are recorded in the
+    /// operations log, failures included.
+    pub fn run(&self) -> Result<ExitCode> {
+        let Some(command) = self.cmd.audit_name() else {
+            return self.execute();
+        };
+        let cfg_paths = ConfigPaths::from_override(self.config.as_deref())?;
+        // a broken config fails the same way in execute, with nothing to log
+        let Ok((_, eff, _)) = self.resolve(&cfg_paths, self.profile.as_deref()) else {
+            return self.execute();
+        };
+        crate::audit::logged(
+            &eff.state_dir,
+            command,
+            eff.profile.as_deref(),
+            &eff.current_symlink,
+            || self.execute(),
+        )
+    }
+
+    fn execute(&self) -> Result<ExitCode> {
+        // build-time helper: the same output whatever the config or machine
+        if let Commands::DevMangen(args) = &self.cmd {
+            use clap::CommandFactory;
+            for page in crate::manpage::write_all(&Cli::command(), args.out_dir.as_ref())? {
+                if !self.quiet {
+                    println!("{}", page.display());
+                }
+            }
+            return Ok(ExitCode::SUCCESS);
+        }
+        let cfg_paths = ConfigPaths::from_override(self.config.as_deref())?;
+        let (cfg, eff, cfg_found) = self.resolve(&cfg_paths, self.profile.as_deref())?;
+        crate::http::configure(&cfg.network)?;
+
+        let wants_desktop = cfg.install.desktop_integration
+            || matches!(
+                &self.cmd,
+                Commands::Install(InstallArgs { desktop: true, .. })
+                    | Commands::Update(UpdateArgs { desktop: true, .. })
+            );
+
+        // System scope: fail early with an actionable message rather than half-way through
+        if cfg.install.scope == Scope::System && self.cmd.mutates_install() {
+            let mut dirs = vec![
+                eff.prefix_dir.as_path(),
+                eff.bin_dir.as_path(),
+                eff.cache_dir.as_path(),
+            ];
+            if wants_desktop {
+                dirs.extend(eff.desktop_file.parent());
+                dirs.push(&eff.icons_dir);
+            }
+            for dir in dirs.into_iter().filter(|d| !d.as_os_str().is_empty()) {
+                if let Err(e) = crate::util::ensure_writable(dir) {
+                    bail!(
+                        "{:#}.\nSystem-scope installs need root: re-run with sudo, e.g. `sudo windman --system ...`",
+                        e
+                    );
+                }
+            }
+        }
+
+        // Without HOME the desktop entry has no place: say so before installing anything
+        if wants_desktop
+            && eff.desktop_file.as_os_str().is_empty()
+            && matches!(&self.cmd, Commands::Install(_) | Commands::Update(_))
+        {
+            return Err(crate::paths::no_desktop_dir());
+        }
+
+        if self.verbose {
+            if cfg_found {
+                eprintln!("[windman] Using config at {}", cfg_paths.config_display());
+            } else {
+                eprintln!(
+                    "[windman] Using config at {} (config file not found, using defaults)",
+                    cfg_paths.config_display()
+                );
+            }
+            if let Some(p) = &eff.profile {
+                eprintln!("[windman] Profile         : {}", p);
+            }
+            eprintln!("[windman] Effective prefix: {}", eff.prefix_dir.display());
+            eprintln!("[windman] Effective bin   : {}", eff.bin_dir.display());
+            if cfg.network.user_agent.is_some() || !cfg.network.extra_headers.is_empty() {
+                for h in crate::http::describe(&crate::http::sent_headers()) {
+                    eprintln!("[windman] HTTP header     : {}", h);
+                }
+            }
+            let trust = crate::http::trust();
+            if trust != crate::http::Trust::Builtin {
+                eprintln!("[windman] TLS trust       : {}", trust);
+            }
+        }
+
+        // a prefix_dir change leaves the shim running the old prefix (which prune may delete)
+        if let Some(old) = crate::util::stale_shim_target(&eff.bin_shim, &eff.shim_link())
+            .filter(|_| cfg.install.manage_shim)
+        {
+            match &self.cmd {
+                Commands::Install(InstallArgs { dry_run: false, .. })
+                | Commands::Update(UpdateArgs { dry_run: false, .. }) => {
+                    crate::util::write_shim(
+                        &eff.bin_shim,
+                        &eff.shim_link(),
+                        self.shim_notice(&cfg, &eff).as_ref(),
+                    )?;
+                    eprintln!(
+                        "Rewrote shim {} (it ran {}, from another prefix)",
+                        eff.bin_shim.display(),
+                        old.display()
+                    );
+                }
+                Commands::Doctor(_) | Commands::MigratePrefix(_) => {}
+                _ => {
+                    crate::ui::warn(format_args!(
+                        "the shim {} still runs {}, not {}.",
+                        eff.bin_shim.display(),
+                        old.display(),
+                        eff.shim_link().display()
+                    ));
+                    crate::ui::warn(
+                        "prefix_dir changed? Run `windman doctor --fix` to rewrite it, or",
+                    );
+                    crate::ui::warn(
+                        "`windman migrate-prefix <dir>` to move the installed versions.",
+                    );
+                }
+            }
+        }
+
+        let mut install_opts = install::InstallOptions {
+            quiet: self.quiet,
+            progress: self.progress(),
+            verbose: self.verbose,
+            shim_notice: self.shim_notice(&cfg, &eff),
+            strict_compat_check: cfg.install.strict_compat_check,
+            require_closed: cfg.install.block_if_running,
+            extract_space_factor: cfg.install.extract_space_factor,
+            extract_fsync: cfg.install.extract_fsync,
+            no_shim: !cfg.install.manage_shim,
+            ..Default::default()
+        };
+        let mut smoke_test = cfg.install.smoke_test;
+        match &self.cmd {
+            Commands::Install(args) => {
+                install_opts.force = args.force;
+                install_opts.allow_downgrade = args.allow_downgrade;
+                install_opts.keep_archive = args.keep_archive || cfg.install.keep_archive;
+                install_opts.no_activate = args.no_activate || args.target_dir.is_some();
+                install_opts.require_closed |= args.require_closed;
+                install_opts.blocked = crate::blocklist::load(&eff.state_dir)?;
+                smoke_test &= !args.no_smoke_test;
+            }
+            Commands::Update(args) => {
+                install_opts.force = args.force;
+                install_opts.allow_downgrade = args.allow_downgrade;
+                install_opts.keep_archive = args.keep_archive || cfg.install.keep_archive;
+                install_opts.no_activate = args.stage_only;
+                install_opts.require_closed |= args.require_closed;
+                smoke_test &= !args.no_smoke_test;
+            }
+            _ => {}
+        }
+        if smoke_test {
+            install_opts.smoke_test = Some(install::SmokeTest {
+                cmd: cfg.install.smoke_test_cmd.clone(),
+                timeout: std::time::Duration::from_secs(cfg.install.smoke_test_timeout_secs),
+            });
+        }
+
+        let mut exit = ExitCode::SUCCESS;
+        match &self.cmd {
+            Commands::Install(args) => {
+                use std::path::PathBuf;
+
+                let keep = args.keep.unwrap_or(cfg.install.keep);
+                let install_into = |eff: &EffectivePaths| {
+                    match (&args.tar, &args.url, &args.from_cache) {
+                    (Some(tar), _, _) if tar == "-" => {
+                        install_from_stdin(&cfg, eff, args.sha256.as_deref(), &install_opts)
+                    }
+                    (Some(tar), _, _) => install_from_file(tar, eff, args.sha256.as_deref(), &install_opts),
+                    (None, Some(url), _) => install_from_url(&cfg, eff, url, &install_opts),
+                    (None, None, Some(v)) => install_from_cache(eff, v, &install_opts),
+                    (None, None, None) => bail!(
+                        "--tar <FILE>, --url <URL> or --from-cache <VERSION> is required (use `windman update` for the latest release)"
+                    ),
+                }
+                };
+                if let Some(dir) = &args.target_dir {
+                    if args.dry_run {
+                        println!("[dry-run] would extract to {}/<version>", dir);
+                        return Ok(ExitCode::SUCCESS);
+                    }
+                    let target = crate::paths::EffectivePaths {
+                        versions_dir: PathBuf::from(dir),
+                        ..eff.clone()
+                    };
+                    let ver = install_into(&target)?;
+                    let version_dir = target.versions_dir.join(&ver);
+                    println!("Extracted Windsurf {} to {}", ver, version_dir.display());
+                    match crate::util::find_windsurf_executable(&version_dir) {
+                        Some(exe) => println!("Executable: {}", exe.display()),
+                        None => crate::ui::warn(format_args!(
+                            "no Windsurf executable found in {}",
+                            version_dir.display()
+                        )),
+                    }
+                    return Ok(ExitCode::SUCCESS);
+                }
+                if args.dry_run {
+                    println!("[dry-run] would install to {:?}", eff.prefix_dir);
+                    if !args.no_activate && cfg.install.manage_shim {
+                        println!("[dry-run] would maintain shim at {:?}", eff.bin_shim);
+                    }
+                    return Ok(ExitCode::SUCCESS);
+                }
+
+                // mémoriser la current avant bascule
+                let previous_current: Option<PathBuf> =
+                    crate::util::resolve_symlink(&eff.current_symlink);
+
+                let ver = install_into(&eff)?;
+                println!("Installed Windsurf {} to {:?}", ver, eff.prefix_dir);
+                if args.no_activate {
+                    println!(
+                        "Not activated; `windman use {}` switches current to it",
+                        ver
+                    );
+                    return Ok(ExitCode::SUCCESS);
+                }
+                warn_about_shims(&eff);
+
+                let want_desktop = if args.no_desktop {
+                    false
+                } else {
+                    args.desktop || cfg.install.desktop_integration
+                };
+                if want_desktop {
+                    desktop::ensure_desktop_files(&eff, &cfg.desktop)?;
+                    println!("Desktop entry installed");
+                }
+
+                if cfg.install.hardlink_dedup {
+                    dedup_with_previous(&eff, previous_current.as_deref(), self.quiet)?;
+                }
+
+                // Prune: préserver la nouvelle current + l'ancienne current
+                prune_after_switch(
+                    &cfg,
+                    &eff,
+                    keep,
+                    previous_current.as_deref(),
+                    args.allow_downgrade,
+                    args.no_prune,
+                    self.quiet,
+                )?;
+                Ok(())
+            }
+
+            Commands::Use(args) => {
+                let relative = relative_version(&eff, &args.version)?;
+                let version = relative.clone().unwrap_or_else(|| args.version.clone());
+                if args.dry_run {
+                    let target = eff.versions_dir.join(&version);
+                    println!("[dry-run] would switch current -> {}", target.display());
+                    return Ok(ExitCode::SUCCESS);
+                }
+                // `use -N` asks for an older version by definition
+                let allow_downgrade = args.allow_downgrade || relative.is_some();
+                install::guard_running(
+                    &eff.versions_dir,
+                    &version,
+                    args.require_closed || cfg.install.block_if_running,
+                )?;
+                use_version(&eff, &version, allow_downgrade)?;
+                warn_about_shims(&eff);
+                // le shim pointe vers 'current', mais il a pu être supprimé ou écrit pour un autre bin_dir
+                for what in refresh_integration(
+                    &eff,
+                    cfg.install.desktop_integration.then_some(&cfg.desktop),
+                    cfg.install.manage_shim,
+                    self.shim_notice(&cfg, &eff).as_ref(),
+                )? {
+                    println!("Repaired {}", what);
+                }
+                Ok(())
+            }
+
+            Commands::Activate(args) => {
+                let staged = crate::staged::load(&eff.state_dir, &eff.versions_dir)?;
+                let version = if args.version == "staged" {
+                    match &staged {
+                        Some(s) => s.version.clone(),
+                        None => bail!(
+                            "nothing is staged (`windman update --stage-only` stages the latest release)"
+                        ),
+                    }
+                } else {
+                    args.version.clone()
+                };
+                if !eff.versions_dir.join(&version).is_dir() {
+                    return Err(version_not_found(&eff, &version));
+                }
+                if args.dry_run {
+                    let target = eff.versions_dir.join(&version);
                     println!("[dry-run] would switch current -> {}", target.display());
-                    return Ok(());
+                    return Ok(ExitCode::SUCCESS);
+                }
+                install::guard_running(
+                    &eff.versions_dir,
+                    &version,
+                    args.require_closed || cfg.install.block_if_running,
+                )?;
+                let previous = crate::util::resolve_symlink(&eff.current_symlink);
+                use_version(&eff, &version, args.allow_downgrade)?;
+                if staged.is_some_and(|s| s.version == version) {
+                    crate::staged::clear(&eff.state_dir)?;
+                }
+                warn_about_shims(&eff);
+                let desktop =
+                    (cfg.install.desktop_integration && !args.no_desktop).then_some(&cfg.desktop);
+                for what in refresh_integration(
+                    &eff,
+                    desktop,
+                    cfg.install.manage_shim,
+                    self.shim_notice(&cfg, &eff).as_ref(),
+                )? {
+                    println!("Refreshed {}", what);
+                }
+                if cfg.install.hardlink_dedup {
+                    dedup_with_previous(&eff, previous.as_deref(), self.quiet)?;
+                }
+                prune_after_switch(
+                    &cfg,
+                    &eff,
+                    cfg.install.keep,
+                    previous.as_deref(),
+                    args.allow_downgrade,
+                    args.no_prune,
+                    self.quiet,
+                )?;
+                Ok(())
+            }
+
+            Commands::Update(args) => {
+                if args.all_profiles && self.profile.is_some() {
+                    bail!("--all-profiles cannot be combined with --profile");
+                }
+                // Default install first, then every [profiles.NAME]
+                let mut targets = vec![(None, cfg.clone(), eff.clone())];
+                if args.all_profiles {
+                    for name in cfg.profiles.keys() {
+                        let (pcfg, peff, _) = self.resolve(&cfg_paths, Some(name))?;
+                        targets.push((Some(name.clone()), pcfg, peff));
+                    }
+                }
+                let notify = args.notify || cfg.install.notify;
+                let (mut updated, mut failed) = (0, 0);
+                for (name, tcfg, teff) in &targets {
+                    if let Some(n) = name {
+                        if !self.quiet {
+                            println!("==> profile {}", n);
+                        }
+                    }
+                    let opts = install::InstallOptions {
+                        shim_notice: self.shim_notice(tcfg, teff),
+                        ..install_opts.clone()
+                    };
+                    let started = std::time::Instant::now();
+                    let mut report =
+                        crate::report::UpdateReport::new(name.clone().or(teff.profile.clone()));
+                    let outcome = {
+                        // with --json, stdout carries the report alone
+                        let _redirect = match args.json {
+                            true => Some(crate::util::StdoutToStderr::new()?),
+                            false => None,
+                        };
+                        self.update_one(args, tcfg, teff, &opts, &mut report)
+                    };
+                    report.finish(started, &outcome);
+                    if args.json {
+                        println!("{}", serde_json::to_string_pretty(&report)?);
+                    } else if let Some(summary) = report.summary().filter(|_| !self.quiet) {
+                        println!("{}", summary);
+                    }
+                    if notify {
+                        let what = match name {
+                            Some(n) => format!("Windsurf ({})", n),
+                            None => "Windsurf".to_string(),
+                        };
+                        crate::notify::update_outcome(&mut crate::notify::Desktop, &what, &outcome);
+                    }
+                    match outcome {
+                        Ok(Some(_)) => updated += 1,
+                        Ok(None) => {}
+                        // a single target: keep the typed error (exit codes, friendly messages)
+                        Err(e) if targets.len() == 1 => return Err(e),
+                        Err(e) => {
+                            failed += 1;
+                            eprintln!("Error ({}): {:#}", name.as_deref().unwrap_or("default"), e);
+                        }
+                    }
+                }
+                if failed > 0 {
+                    bail!("{} of {} installs failed to update", failed, targets.len());
+                }
+                if updated == 0 && !args.dry_run {
+                    exit = ExitCode::from(crate::EXIT_UP_TO_DATE);
+                }
+                Ok(())
+            }
+
+            Commands::Status(args) => {
+                use crate::version::StatusKind;
+                let kind = StatusKind::detect(&eff, !args.no_cache)?;
+                if args.porcelain {
+                    print!("{}", status_porcelain(&eff, kind.version()));
+                    return Ok(ExitCode::SUCCESS);
+                }
+                let suggestion = kind.suggestion(&eff);
+                let trash = crate::trash::size(&eff.versions_dir);
+                let budget = budget_usage(&cfg, &eff);
+                let staged = crate::staged::load(&eff.state_dir, &eff.versions_dir)?
+                    .filter(|s| kind.version() != Some(s.version.as_str()));
+                let desktop_status = (cfg.install.desktop_integration || eff.desktop_file.exists())
+                    .then(|| desktop::integration_status(&eff));
+                let others = arch_views(&eff, args.all_arch)?
+                    .into_iter()
+                    .skip(1)
+                    .map(|e| {
+                        Ok((
+                            e.arch.clone().unwrap_or_default(),
+                            StatusKind::detect(&e, !args.no_cache)?,
+                        ))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                if args.json {
+                    let mut out = serde_json::json!({
+                        "profile": eff.profile,
+                        "prefix": eff.prefix_dir,
+                        "current_link": eff.current_symlink,
+                        "shim": cfg.install.manage_shim.then_some(&eff.bin_shim),
+                        "current": kind,
+                        "suggestion": suggestion,
+                        "trash_bytes": trash,
+                        "desktop": desktop_status,
+                    });
+                    if let Some(s) = &staged {
+                        out["staged"] = serde_json::json!(s.version);
+                    }
+                    if let Some((used, limit)) = budget {
+                        out["disk_bytes"] = serde_json::json!(used);
+                        out["max_total_bytes"] = serde_json::json!(limit);
+                    }
+                    if let Some(arch) = &eff.arch {
+                        out["arch"] = serde_json::json!(arch);
+                    }
+                    if args.all_arch {
+                        out["other_arches"] = others
+                            .iter()
+                            .map(|(a, k)| (a.clone(), serde_json::json!(k)))
+                            .collect::<serde_json::Map<_, _>>()
+                            .into();
+                    }
+                    println!("{}", serde_json::to_string_pretty(&out)?);
+                    return Ok(ExitCode::SUCCESS);
+                }
+                if let Some(p) = &eff.profile {
+                    println!("Profile        : {} ({})", p, cfg.install.channel);
+                }
+                println!("Install prefix : {}", eff.prefix_dir.display());
+                println!("Current link   : {}", eff.current_symlink.display());
+                if cfg.install.manage_shim {
+                    println!("Shim           : {}", eff.bin_shim.display());
+                } else {
+                    println!("Shim           : disabled (managed externally)");
+                }
+                match &desktop_status {
+                    None => println!("Desktop entry  : disabled"),
+                    Some(d) => {
+                        let problems = d.problems();
+                        if problems.is_empty() {
+                            println!("Desktop entry  : ok, {}", d.file.display());
+                        } else {
+                            println!("Desktop entry  : {}", problems.join("; "));
+                        }
+                        for note in d.notes() {
+                            println!("Desktop note   : {}", note);
+                        }
+                    }
+                }
+                if let Some(arch) = &eff.arch {
+                    println!("Architecture   : {}", arch);
+                }
+                match &kind {
+                    StatusKind::NotInstalled => println!("Local version  : <not installed>"),
+                    StatusKind::Broken { target } => println!(
+                        "Local version  : <broken> current points to {}, which no longer exists",
+                        target.display()
+                    ),
+                    StatusKind::UnknownVersion { target } => println!(
+                        "Local version  : <unknown> no readable product.json under {}",
+                        target.display()
+                    ),
+                    StatusKind::Healthy { version, .. } => println!("Local version  : {}", version),
+                }
+                if let Some(s) = &staged {
+                    println!("Staged         : {} (not active)", s.version);
+                }
+                for (arch, k) in &others {
+                    println!(
+                        "{:<15}: {}",
+                        arch,
+                        k.version().unwrap_or("<no readable version>")
+                    );
+                }
+                if trash > 0 {
+                    println!("Trash          : {}", indicatif::HumanBytes(trash));
+                }
+                if let Some((used, limit)) = budget {
+                    println!("Disk usage     : {}", usage_text(used, Some(limit)));
+                    warn_over_budget(used, limit);
+                }
+                if let Some(cmd) = suggestion {
+                    println!("Suggested      : {}", cmd);
+                }
+                Ok(())
+            }
+
+            Commands::Where(WhereArgs {
+                shell: Some(shell), ..
+            }) => {
+                print!("{}", where_env(*shell, &eff));
+                Ok(())
+            }
+
+            Commands::Where(WhereArgs { var: Some(var), .. }) => {
+                println!("{}", var.path(&eff).display());
+                Ok(())
+            }
+
+            Commands::Where(_) => {
+                println!("prefix : {}", eff.prefix_dir.display());
+                println!("current: {}", eff.current_symlink.display());
+                println!("shim   : {}", eff.bin_shim.display());
+                println!("cache  : {}", eff.cache_dir.display());
+                Ok(())
+            }
+
+            Commands::Exec(args) => {
+                let cwd = std::env::current_dir()?;
+                let dir = exec_version_dir(&eff, args.version.as_deref(), &cwd)?;
+                let Some(exe) = crate::util::find_windsurf_executable(&dir) else {
+                    bail!("no Windsurf executable found under {}", dir.display());
+                };
+                // The version's own bin dir goes first so `windsurf` resolves to it, not the shim
+                let mut path = vec![exe.parent().unwrap().to_path_buf()];
+                path.extend(std::env::split_paths(
+                    &std::env::var_os("PATH").unwrap_or_default(),
+                ));
+                let (program, rest) = match args.cmd.split_first() {
+                    Some((p, rest)) => (std::ffi::OsString::from(p), rest),
+                    None => (exe.clone().into_os_string(), &[][..]),
+                };
+                let status = std::process::Command::new(&program)
+                    .args(rest)
+                    .env("PATH", std::env::join_paths(path)?)
+                    .env("WINDMAN_WINDSURF_DIR", &dir)
+                    .status()
+                    .map_err(|e| anyhow::anyhow!("running {:?}: {}", program, e))?;
+                use std::os::unix::process::ExitStatusExt;
+                let code = status
+                    .code()
+                    .or(status.signal().map(|s| 128 + s))
+                    .unwrap_or(1);
+                exit = ExitCode::from(code as u8);
+                Ok(())
+            }
+
+            Commands::Open(args) => {
+                let cwd = std::env::current_dir()?;
+                let dir = exec_version_dir(&eff, args.version.as_deref(), &cwd)?;
+                let Some(exe) = crate::util::find_windsurf_executable(&dir) else {
+                    bail!("no Windsurf executable found under {}", dir.display());
+                };
+                let mut cmd = std::process::Command::new(&exe);
+                cmd.args(&args.paths).env("WINDMAN_WINDSURF_DIR", &dir);
+                if args.wait {
+                    let status = cmd
+                        .status()
+                        .with_context(|| format!("running {}", exe.display()))?;
+                    use std::os::unix::process::ExitStatusExt;
+                    let code = status
+                        .code()
+                        .or(status.signal().map(|s| 128 + s))
+                        .unwrap_or(1);
+                    exit = ExitCode::from(code as u8);
+                } else {
+                    let log = eff.state_dir.join(OPEN_LOG);
+                    let pid = crate::util::spawn_detached(&mut cmd, &log)?;
+                    if !self.quiet {
+                        eprintln!(
+                            "Started {} in the background; its output goes to {}",
+                            exe.display(),
+                            log.display()
+                        );
+                    }
+                    println!("{}", pid);
+                }
+                Ok(())
+            }
+
+            Commands::Local(args) => {
+                let file =
+                    crate::project::write_version_file(&std::env::current_dir()?, &args.version)?;
+                println!("Pinned {} in {}", args.version, file.display());
+                if !eff.versions_dir.join(&args.version).is_dir() {
+                    crate::ui::warn(format_args!(
+                        "{} is not installed yet (see `windman list`)",
+                        args.version
+                    ));
+                }
+                Ok(())
+            }
+
+            Commands::Which(args) => {
+                let dir = match &args.version {
+                    Some(v) => {
+                        let d = eff.versions_dir.join(v);
+                        if !d.is_dir() {
+                            return Err(version_not_found(&eff, v));
+                        }
+                        d
+                    }
+                    None => fs::canonicalize(&eff.current_symlink).map_err(|e| {
+                        anyhow::anyhow!(
+                            "cannot resolve {}: {} (is Windsurf installed?)",
+                            eff.current_symlink.display(),
+                            e
+                        )
+                    })?,
+                };
+                match crate::util::find_windsurf_executable(&dir) {
+                    Some(exe) => {
+                        println!("{}", exe.display());
+                        Ok(())
+                    }
+                    None => bail!(
+                        "no Windsurf executable found under {}. Tried:\n  {}",
+                        dir.display(),
+                        crate::util::WINDSURF_EXE_CANDIDATES.join("\n  ")
+                    ),
+                }
+            }
+
+            Commands::Check(args) if args.watch => watch_check(&cfg, &eff, args),
+
+            Commands::Check(args) => {
+                // Always exit 0: errors are reported in the output
+                let report = check_report(&cfg, &eff, args.refresh);
+                if args.json {
+                    println!("{}", serde_json::to_string(&report)?);
+                    return Ok(ExitCode::SUCCESS);
+                }
+                match (&report.latest, report.update_available) {
+                    (Some(latest), true) => anstream::println!(
+                        "{}",
+                        crate::ui::paint(
+                            crate::ui::NOTICE,
+                            format_args!(
+                                "Update available: {} -> {}",
+                                report.local.as_deref().unwrap_or("<none>"),
+                                latest
+                            )
+                        )
+                    ),
+                    (Some(latest), false) => println!("Up to date ({}).", latest),
+                    (None, _) => println!("Latest version unknown."),
+                }
+                if let Some(e) = &report.error {
+                    crate::ui::warn(e);
+                }
+                Ok(())
+            }
+
+            Commands::NotifyCheck(args) => {
+                // never fails: a broken notice must not get in the way of the launch
+                let path = crate::check::cache_path(&eff.cache_dir, &cfg.install.channel);
+                if args.refresh {
+                    crate::check::latest_cached(&path, chrono::Utc::now(), 0, true, || {
+                        crate::remote::latest_linux_x64(
+                            &cfg.install.channel,
+                            None,
+                            cfg.network.allow_html_fallback,
+                            Some(crate::remote::http_cache_path(
+                                &eff.cache_dir,
+                                &cfg.install.channel,
+                            )),
+                        )
+                    });
+                    return Ok(ExitCode::SUCCESS);
+                }
+                let local = crate::history::current_version_name(&eff.current_symlink);
+                let (newer, stale) =
+                    crate::check::notice(&path, local.as_deref(), chrono::Utc::now());
+                if let Some(v) = newer {
+                    let profile = eff
+                        .profile
+                        .as_ref()
+                        .map(|p| format!("--profile {} ", p))
+                        .unwrap_or_default();
+                    anstream::eprintln!(
+                        "{}",
+                        crate::ui::paint(
+                            crate::ui::NOTICE,
+                            format_args!(
+                                "Windsurf {} is available, run `windman {}update`",
+                                v, profile
+                            )
+                        )
+                    );
+                }
+                if stale {
+                    let _ = self.spawn_notice_refresh(&eff);
+                }
+                Ok(())
+            }
+
+            Commands::Info(args) => {
+                let info = version_info(&eff, args.version.as_deref())?;
+                if args.json {
+                    println!("{}", serde_json::to_string_pretty(&info)?);
+                    return Ok(ExitCode::SUCCESS);
+                }
+                let or_unknown = |o: &Option<String>| o.clone().unwrap_or("<unknown>".to_string());
+                println!("Version     : {}", info.version);
+                println!("Installed   : {}", or_unknown(&info.installed_at));
+                println!("Source      : {}", or_unknown(&info.source));
+                println!("Channel     : {}", or_unknown(&info.channel));
+                println!("SHA-256     : {}", or_unknown(&info.sha256));
+                if let Some(a) = &info.artifact {
+                    let bytes = fs::metadata(a).map(|m| m.len()).unwrap_or(0);
+                    println!(
+                        "Artifact    : {} ({})",
+                        a.display(),
+                        indicatif::HumanBytes(bytes)
+                    );
+                }
+                println!("Size        : {}", indicatif::HumanBytes(info.size_bytes));
+                println!("Current     : {}", if info.current { "yes" } else { "no" });
+                println!("Pinned      : {}", if info.pinned { "yes" } else { "no" });
+                println!(
+                    "Notes       : {}",
+                    match info.notes {
+                        crate::notes::NotesStatus::Stored => "stored (windman notes)",
+                        crate::notes::NotesStatus::Unavailable => "unavailable at install time",
+                        crate::notes::NotesStatus::Missing => "not stored",
+                    }
+                );
+                Ok(())
+            }
+
+            Commands::Inspect(args) => {
+                let (name, _) = installed_version(&eff, args.version.as_deref())?;
+                let inspection = crate::inspect::inspect(
+                    &name,
+                    &eff.versions_dir.join(&name),
+                    args.depth as usize,
+                    crate::inspect::BIG_DIR_ENTRIES,
+                );
+                if args.json {
+                    println!("{}", serde_json::to_string_pretty(&inspection)?);
+                } else {
+                    for line in crate::inspect::render(&inspection) {
+                        anstream::println!("{}", line);
+                    }
+                }
+                Ok(())
+            }
+
+            Commands::Provenance(args) => {
+                let (name, _) = installed_version(&eff, args.version.as_deref())?;
+                let record = crate::provenance::of(&name, &eff.versions_dir.join(&name));
+                match args.export {
+                    Some(SbomFormat::Cyclonedx) => println!(
+                        "{}",
+                        serde_json::to_string_pretty(&crate::provenance::cyclonedx(&record))?
+                    ),
+                    None if args.json => {
+                        println!("{}", serde_json::to_string_pretty(&record)?)
+                    }
+                    None => {
+                        for line in crate::provenance::render(&record) {
+                            println!("{}", line);
+                        }
+                    }
+                }
+                Ok(())
+            }
+
+            Commands::List(args) if args.blocked => {
+                let blocked = crate::blocklist::load(&eff.state_dir)?;
+                let versions = crate::blocklist::sorted(&blocked);
+                if args.json {
+                    println!("{}", serde_json::to_string_pretty(&versions)?);
+                } else if versions.is_empty() {
+                    println!("No blocked versions.");
+                } else {
+                    for v in versions {
+                        println!("{}", v);
+                    }
+                }
+                Ok(())
+            }
+
+            Commands::Block { version } => {
+                if crate::blocklist::block(&eff.state_dir, version)? {
+                    println!(
+                        "Blocked {}: `update` skips it and `install` needs --force.",
+                        version
+                    );
+                } else {
+                    println!("{} is already blocked.", version);
+                }
+                if crate::history::current_version_name(&eff.current_symlink).as_deref()
+                    == Some(version.as_str())
+                {
+                    println!("It is the current version; `windman rollback` goes back to the previous one.");
                 }
-                switch_to_version(&eff, &args.version)?;
-                // le shim pointe déjà vers 'current', donc rien à régénérer
                 Ok(())
             }
 
-            Commands::Update(args) => {
-                use directories::ProjectDirs;
-                use semver::Version;
-                use std::path::PathBuf;
+            Commands::Unblock { version } => {
+                if crate::blocklist::unblock(&eff.state_dir, version)? {
+                    println!("Unblocked {}.", version);
+                } else {
+                    println!("{} was not blocked.", version);
+                }
+                Ok(())
+            }
+
+            Commands::List(args) => {
+                let cwd = std::env::current_dir()?;
+                let pinned = crate::project::pinned_version(&cwd)
+                    .ok()
+                    .flatten()
+                    .map(|(v, _)| v);
+                if args.porcelain {
+                    print!("{}", list_porcelain(&eff, pinned.as_deref()));
+                    return Ok(ExitCode::SUCCESS);
+                }
+                let latest = if args.check_remote {
+                    let (latest, error) = crate::check::latest_cached(
+                        &crate::check::cache_path(&eff.cache_dir, &cfg.install.channel),
+                        chrono::Utc::now(),
+                        cfg.network.check_cache_ttl_minutes,
+                        false,
+                        || {
+                            crate::remote::latest_linux_x64(
+                                &cfg.install.channel,
+                                None,
+                                cfg.network.allow_html_fallback,
+                                Some(crate::remote::http_cache_path(
+                                    &eff.cache_dir,
+                                    &cfg.install.channel,
+                                )),
+                            )
+                        },
+                    );
+                    if let Some(e) = error {
+                        crate::ui::warn(format_args!("checking the latest version: {}", e));
+                    }
+                    latest.map(|l| l.version)
+                } else {
+                    None
+                };
+                for eff in arch_views(&eff, args.all_arch)? {
+                    let mut entries = list_entries(&eff, pinned.as_deref(), args.size);
+                    if let Some(l) = &latest {
+                        mark_latest(&mut entries, l);
+                    }
+                    if args.json {
+                        println!("{}", serde_json::to_string_pretty(&entries)?);
+                        continue;
+                    }
+                    let arch = eff.arch.as_ref().map(|a| format!(" ({})", a));
+                    let arch = arch.unwrap_or_default();
+
+                    if entries.is_empty() {
+                        println!(
+                            "No installed versions found in {}{}.",
+                            eff.versions_dir.display(),
+                            arch
+                        );
+                    } else {
+                        println!(
+                            "Installed versions in {}{}:",
+                            eff.versions_dir.display(),
+                            arch
+                        );
+                        // one line per entry, the current one highlighted
+                        let table = render_list(&entries, chrono::Utc::now());
+                        for (line, e) in table.lines().zip(&entries) {
+                            if e.current {
+                                anstream::println!(
+                                    "{}",
+                                    crate::ui::paint(crate::ui::CURRENT, line)
+                                );
+                            } else {
+                                println!("{}", line);
+                            }
+                        }
+                        if args.size {
+                            let used = prune::usage(&eff.versions_dir);
+                            let limit = cfg.install.max_total_bytes();
+                            println!("Total: {}", usage_text(used, limit));
+                            if let Some(limit) = limit {
+                                warn_over_budget(used, limit);
+                            }
+                        }
+                    }
+                }
+                Ok(())
+            }
 
-                // 1) Local version
+            Commands::Changelog(args) => {
                 let local = version::detect_local_version(&eff)?;
+                let url = &cfg.changelog.source_url;
+                let html = crate::remote::fetch_releases_html(url, None)
+                    .context("fetching the release notes (changelog.source_url)")?;
+                let newer = crate::notes::changelog(
+                    &html,
+                    url,
+                    local.as_deref(),
+                    cfg.changelog.max_entries,
+                )?;
+                if newer.is_empty() {
+                    println!(
+                        "No release notes newer than {}.",
+                        local.as_deref().unwrap_or("<none>")
+                    );
+                }
+                for n in newer {
+                    if args.raw {
+                        println!("# Windsurf {}\n\n{}\n", n.version, n.body);
+                    } else {
+                        anstream::println!(
+                            "{}",
+                            crate::notes::render_section(&n, cfg.changelog.render)
+                        );
+                    }
+                }
+                Ok(())
+            }
 
-                // 2) Remote via API (version + url)
-                let latest = crate::remote::latest_stable_linux_x64(None)?;
-                let latest_ver = Version::parse(&latest.version).map_err(|e| {
-                    anyhow::anyhow!("cannot parse remote version {}: {}", latest.version, e)
+            Commands::Notes(args) => {
+                let (name, _) = installed_version(&eff, args.version.as_deref())?;
+                let dir = eff.versions_dir.join(&name);
+                let text = crate::notes::load_or_fetch(&dir, &name, || {
+                    crate::remote::fetch_releases_html(&cfg.changelog.source_url, None)
                 })?;
+                print!("{}", text);
+                Ok(())
+            }
 
-                // 3) Compare
-                if let Some(local_s) = &local {
-                    if let Ok(local_ver) = Version::parse(local_s) {
-                        if local_ver >= latest_ver {
-                            println!(
-                                "Already up to date (local: {}, latest: {}).",
-                                local_ver, latest_ver
-                            );
-                            return Ok(());
-                        }
+            Commands::Export(args) => {
+                let dir = eff.versions_dir.join(&args.version);
+                if !dir.is_dir() || !crate::util::is_version_dir_name(&args.version) {
+                    return Err(version_not_found(&eff, &args.version));
+                }
+                let artifact = crate::meta::artifact(&dir).filter(|_| !args.repack);
+                let out = match (&args.out, &artifact) {
+                    (Some(o), _) => std::path::PathBuf::from(crate::paths::expand_tilde(o)?),
+                    (None, Some(a)) => a.file_name().unwrap_or_default().into(),
+                    (None, None) => format!("windsurf-{}.tar.zst", args.version).into(),
+                };
+                let done = match &artifact {
+                    Some(a) => {
+                        let sha = crate::meta::read(&dir).and_then(|m| m.sha256);
+                        crate::export::export_artifact(a, sha.as_deref(), &out)?
+                    }
+                    None => crate::export::export_version(&dir, &out)?,
+                };
+                if !self.quiet {
+                    if artifact.is_some() {
+                        println!(
+                            "Exported {} (the archive kept at install) to {}",
+                            args.version,
+                            done.archive.display()
+                        );
+                    } else {
+                        println!(
+                            "Exported {} ({} entries) to {}",
+                            args.version,
+                            done.entries,
+                            done.archive.display()
+                        );
                     }
+                    println!("Checksum written to {}", done.sidecar.display());
                 }
+                Ok(())
+            }
 
-                // 4) Dry-run?
-                if args.dry_run {
-                    println!("[dry-run] local : {}", local.as_deref().unwrap_or("<none>"));
-                    println!("[dry-run] latest: {}", latest_ver);
-                    println!("[dry-run] url   : {}", latest.url);
-                    return Ok(());
-                }
-
-                // 5) Download to cache
-                let proj = ProjectDirs::from("dev", "Windman", "windman")
-                    .ok_or_else(|| anyhow::anyhow!("cannot determine project dirs"))?;
-                let dl_dir = proj.cache_dir().join("downloads").join(&latest.version);
-                std::fs::create_dir_all(&dl_dir)?;
-                let filename = latest
-                    .url
-                    .rsplit('/')
-                    .next()
-                    .unwrap_or("windsurf-linux-x64.tar.gz");
-                let tar_path: PathBuf = dl_dir.join(filename);
-
-                crate::download::download_to_file_with_timeout(&latest.url, &tar_path, None)
-                    .map_err(|e| anyhow::anyhow!("downloading {}: {}", latest.url, e))?;
-                println!("Downloaded {}", tar_path.display());
-
-                // 6) Install (avec capture de l'ancienne current pour prune)
-                let previous_current: Option<PathBuf> =
-                    std::fs::read_link(&eff.current_symlink).ok();
+            Commands::Uninstall {
+                purge,
+                keep_config,
+                dry_run: true,
+            } => {
+                let config = (!*keep_config).then_some(&cfg_paths);
+                let plan = install::uninstall_plan(&eff, *purge, config);
+                if plan.is_empty() {
+                    println!("Nothing to remove.");
+                    return Ok(ExitCode::SUCCESS);
+                }
+                let mut total = 0;
+                for r in &plan {
+                    let bytes = r.bytes();
+                    total += bytes;
+                    println!(
+                        "Would remove {} ({})",
+                        r.path().display(),
+                        indicatif::HumanBytes(bytes)
+                    );
+                }
+                println!("Would reclaim {}", indicatif::HumanBytes(total));
+                println!(
+                    "Windsurf user data (~/.config/Windsurf, ~/.windsurf) would not be touched."
+                );
+                Ok(())
+            }
 
-                let ver = install::install_from_tar(tar_path.to_string_lossy().as_ref(), &eff)?;
-                println!("Installed Windsurf {} to {:?}", ver, eff.prefix_dir);
+            Commands::Uninstall {
+                purge,
+                keep_config,
+                dry_run: false,
+            } => {
+                let config = (!*keep_config).then_some(&cfg_paths);
+                let removed = install::uninstall_all(&eff, *purge, config)?;
+                for p in &removed {
+                    println!("Removed {}", p.display());
+                }
+                println!("Windman userland install removed.");
+                println!("Windsurf user data (~/.config/Windsurf, ~/.windsurf) was not touched.");
+                Ok(())
+            }
 
-                // 7) Desktop
-                let want_desktop = if args.no_desktop {
-                    false
+            Commands::Remove(args) => {
+                let dir = eff.versions_dir.join(&args.version);
+                if !dir.is_dir() || !crate::util::is_version_dir_name(&args.version) {
+                    return Err(version_not_found(&eff, &args.version));
+                }
+                if fs::canonicalize(&eff.current_symlink).ok() == fs::canonicalize(&dir).ok() {
+                    bail!(
+                        "{} is the current version; switch with `windman use` first",
+                        args.version
+                    );
+                }
+                if cfg.install.prune_to_trash {
+                    let to = crate::trash::move_to_trash(&eff.versions_dir, &dir)?;
+                    println!("Moved {} to trash ({}).", args.version, to.display());
                 } else {
-                    args.desktop || cfg.install.desktop_integration
-                };
-                if want_desktop {
-                    desktop::ensure_desktop_files(&eff)?;
-                    println!("Desktop entry installed");
+                    crate::util::remove_dir_within(&eff.versions_dir, &dir)?;
+                    println!("Removed {}.", args.version);
+                }
+                Ok(())
+            }
+
+            Commands::Trash(sub) => match sub {
+                TrashCmd::List => {
+                    let entries = crate::trash::list(&eff.versions_dir);
+                    if entries.is_empty() {
+                        println!("Trash is empty.");
+                        return Ok(ExitCode::SUCCESS);
+                    }
+                    for e in &entries {
+                        println!(
+                            "{:<12} trashed {}  {}",
+                            e.version,
+                            e.trashed_at.format("%Y-%m-%d %H:%M"),
+                            indicatif::HumanBytes(crate::util::dir_size(&e.path))
+                        );
+                    }
+                    println!(
+                        "Total: {}",
+                        indicatif::HumanBytes(crate::trash::size(&eff.versions_dir))
+                    );
+                    Ok(())
+                }
+                TrashCmd::Restore { version } => {
+                    let dir = crate::trash::restore(&eff.versions_dir, version)?;
+                    println!("Restored {} to {}.", version, dir.display());
+                    Ok(())
+                }
+                TrashCmd::Empty { older_than } => {
+                    let age = older_than
+                        .as_deref()
+                        .map(crate::util::parse_duration)
+                        .transpose()?;
+                    let n = crate::trash::empty(&eff.versions_dir, age)?;
+                    println!("Deleted {} trashed version(s).", n);
+                    Ok(())
+                }
+            },
+
+            Commands::Cache(CacheCmd::Path) => {
+                println!("{}", eff.cache_dir.display());
+                Ok(())
+            }
+
+            Commands::Cache(CacheCmd::Move { dir }) => {
+                if let Some(env) = std::env::var_os("WINDMAN_CACHE_DIR").filter(|v| !v.is_empty()) {
+                    bail!(
+                        "WINDMAN_CACHE_DIR is set ({}) and would still win over paths.cache_dir; unset it first",
+                        env.to_string_lossy()
+                    );
+                }
+                let mut new_cfg = cfg.clone();
+                new_cfg.paths.cache_dir = Some(dir.clone());
+                let new_dir = crate::paths::cache_dir(&new_cfg)?;
+                crate::cache::relocate(&eff.cache_dir, &new_dir)?;
+                println!("Moved {} -> {}", eff.cache_dir.display(), new_dir.display());
+                Config::set_cache_dir(&cfg_paths, dir).with_context(|| {
+                    format!(
+                        "cache moved, but the config was not updated: set cache_dir = \"{}\" in [paths] by hand",
+                        dir
+                    )
+                })?;
+                println!("Updated paths.cache_dir in {}", cfg_paths.config_display());
+                Ok(())
+            }
+
+            Commands::Cache(CacheCmd::List) => {
+                let archives = crate::cache::list(&eff.cache_dir);
+                if archives.is_empty() {
+                    println!(
+                        "No archives in {}.",
+                        eff.cache_dir.join("downloads").display()
+                    );
+                    return Ok(ExitCode::SUCCESS);
+                }
+                let current = crate::history::current_version_name(&eff.current_symlink);
+                for a in &archives {
+                    let state = if current.as_deref() == Some(a.version.as_str()) {
+                        "installed (current)"
+                    } else if eff.versions_dir.join(&a.version).is_dir() {
+                        "installed"
+                    } else {
+                        "not installed"
+                    };
+                    let line = format!(
+                        "{:<12} {:<7} {:>10}  {:<19}  {}",
+                        a.version,
+                        a.source,
+                        indicatif::HumanBytes(a.bytes).to_string(),
+                        state,
+                        if a.checksum { "sha256 recorded" } else { "" }
+                    );
+                    println!("{}", line.trim_end());
                 }
+                println!("Reinstall one with `windman install --from-cache <VERSION>`.");
+                Ok(())
+            }
 
-                // 8) Prune: préserver la nouvelle current + l'ancienne current
-                let mut preserve: Vec<PathBuf> = Vec::new();
-                if let Ok(cur) = std::fs::read_link(&eff.current_symlink) {
-                    preserve.push(cur);
+            Commands::Rollback(RollbackArgs { list: true, .. }) => {
+                let candidates = install::rollback_candidates(&eff)?;
+                if candidates.is_empty() {
+                    println!("No version to roll back to.");
+                    return Ok(ExitCode::SUCCESS);
                 }
-                if let Some(prev) = previous_current {
-                    preserve.push(prev);
+                let next = install::rollback_plan(&eff)
+                    .ok()
+                    .map(|p| p.target().to_string());
+                for c in &candidates {
+                    let why = match c.left_at {
+                        Some(at) => format!(
+                            "history (left {})",
+                            at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+                        ),
+                        None => "newest by mtime".to_string(),
+                    };
+                    println!(
+                        "{} {:<16} {:<30} {}",
+                        if next.as_ref() == Some(&c.name) {
+                            "*"
+                        } else {
+                            " "
+                        },
+                        c.name,
+                        why,
+                        if c.on_disk { "on disk" } else { "removed" }
+                    );
                 }
-                prune::prune_old_versions_with_preserve(
+                println!("* = where `windman rollback` goes now");
+                Ok(())
+            }
+
+            Commands::Rollback(RollbackArgs { dry_run: true, .. }) => {
+                let plan = install::rollback_plan(&eff)?;
+                println!("Would roll back to {}: {}", plan.target(), plan.reason());
+                Ok(())
+            }
+
+            Commands::Rollback(RollbackArgs { .. }) => {
+                let plan = install::rollback_plan(&eff)?;
+                install::guard_running(
                     &eff.versions_dir,
-                    cfg.install.keep,
-                    &preserve,
+                    plan.target(),
+                    cfg.install.block_if_running,
                 )?;
+                install::rollback(&eff)?;
+                warn_about_shims(&eff);
                 Ok(())
             }
 
-            Commands::Status => {
-                let local = version::detect_local_version(&eff)?;
-                println!("Install prefix : {}", eff.prefix_dir.display());
-                println!("Current link   : {}", eff.current_symlink.display());
-                println!("Shim           : {}", eff.bin_shim.display());
-                match local {
-                    Some(v) => println!("Local version  : {}", v),
-                    None => println!("Local version  : <not installed>"),
+            Commands::Rollforward => {
+                let redo = crate::history::History::load(&eff.versions_dir)
+                    .undone
+                    .pop();
+                if let Some(redo) = redo {
+                    install::guard_running(
+                        &eff.versions_dir,
+                        &redo.to,
+                        cfg.install.block_if_running,
+                    )?;
                 }
+                install::rollforward(&eff)?;
+                warn_about_shims(&eff);
                 Ok(())
             }
 
-            Commands::Where => {
-                println!("prefix : {}", eff.prefix_dir.display());
-                println!("current: {}", eff.current_symlink.display());
-                println!("shim   : {}", eff.bin_shim.display());
+            Commands::Shim(ShimCmd::Add { name, target }) => {
+                let e = crate::shims::add(&eff, name, target)?;
+                println!("Wrote {} -> <current>/{}", e.path.display(), e.target);
+                if !e.available {
+                    crate::ui::warn(format_args!(
+                        "{} is not an executable in the current version (yet)",
+                        e.target
+                    ));
+                }
+                Ok(())
+            }
+
+            Commands::Alias(AliasCmd::List { json }) => {
+                use clap::CommandFactory;
+                let builtins = crate::alias::builtins(&Cli::command());
+                if *json {
+                    let out = serde_json::json!({ "builtin": builtins, "user": cfg.cli.aliases });
+                    println!("{}", serde_json::to_string_pretty(&out)?);
+                } else {
+                    for line in crate::alias::render(&builtins, &cfg.cli.aliases) {
+                        println!("{}", line);
+                    }
+                }
+                if !*json && cfg.cli.aliases.is_empty() {
+                    println!(
+                        "\nNo aliases of your own: add them under [cli.aliases] in {}.",
+                        cfg_paths.config_display()
+                    );
+                }
+                Ok(())
+            }
+
+            Commands::Shim(ShimCmd::List) => {
+                let shims = crate::shims::list(&eff);
+                if shims.is_empty() {
+                    println!("No extra shims (add one with `windman shim add <name> <path>`).");
+                }
+                for e in &shims {
+                    println!(
+                        "{:<16} {:<40} {}",
+                        e.name,
+                        e.target,
+                        if e.available {
+                            "ok"
+                        } else {
+                            "missing in current"
+                        }
+                    );
+                }
+                Ok(())
+            }
+
+            Commands::Shim(ShimCmd::Remove { name }) => {
+                let path = crate::shims::remove(&eff, name)?;
+                println!("Removed {}", path.display());
+                Ok(())
+            }
+
+            Commands::History(args) if args.operations => {
+                let ops = crate::audit::read(&eff.state_dir, args.limit);
+                if args.json {
+                    println!("{}", serde_json::to_string_pretty(&ops)?);
+                } else if ops.is_empty() {
+                    println!("No operations logged yet.");
+                } else {
+                    for op in &ops {
+                        println!("{}", operation_line(op));
+                    }
+                }
+                Ok(())
+            }
+
+            Commands::History(args) => {
+                let mut switches = crate::history::History::load(&eff.versions_dir).done;
+                if let Some(n) = args.limit {
+                    switches.drain(..switches.len().saturating_sub(n));
+                }
+                if args.json {
+                    println!("{}", serde_json::to_string_pretty(&switches)?);
+                } else if switches.is_empty() {
+                    println!("No switches recorded yet.");
+                } else {
+                    for s in &switches {
+                        println!(
+                            "{}  {} -> {}",
+                            s.at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+                            s.from.as_deref().unwrap_or("<none>"),
+                            s.to
+                        );
+                    }
+                }
+                Ok(())
+            }
+
+            Commands::Config(sub) => match sub {
+                ConfigCmd::Init => {
+                    cfg.save_if_missing(&cfg_paths)?;
+                    println!("Config written to {}", cfg_paths.config_display());
+                    Ok(())
+                }
+                ConfigCmd::Show { check: true, .. } => {
+                    // an invalid config already failed to load, listing every problem
+                    if cfg_found {
+                        println!("{}: OK", cfg_paths.config_display());
+                    } else {
+                        println!(
+                            "{}: not found (built-in defaults apply)",
+                            cfg_paths.config_display()
+                        );
+                    }
+                    Ok(())
+                }
+                ConfigCmd::Path => {
+                    if cfg_found {
+                        println!("{} (exists)", cfg_paths.config_display());
+                    } else {
+                        println!(
+                            "{} (not found; `windman config init` creates it)",
+                            cfg_paths.config_display()
+                        );
+                    }
+                    Ok(())
+                }
+                ConfigCmd::Migrate => {
+                    if !cfg_paths.file.exists() {
+                        println!(
+                            "{}: not found (built-in defaults apply)",
+                            cfg_paths.config_display()
+                        );
+                        return Ok(ExitCode::SUCCESS);
+                    }
+                    let notes = Config::migrate_file(&cfg_paths)?;
+                    if notes.is_empty() {
+                        println!("{}: already up to date", cfg_paths.config_display());
+                        return Ok(ExitCode::SUCCESS);
+                    }
+                    println!(
+                        "Rewrote {} (original kept as {}):",
+                        cfg_paths.config_display(),
+                        crate::config::backup_path(&cfg_paths.file).display()
+                    );
+                    for m in &notes.migrations {
+                        println!("  - {}", m);
+                    }
+                    for k in &notes.unknown {
+                        println!("  - unknown key `{}` dropped", k);
+                    }
+                    Ok(())
+                }
+                ConfigCmd::Show { default: true, .. } => {
+                    println!("{}", toml::to_string_pretty(&Config::default())?);
+                    Ok(())
+                }
+                ConfigCmd::Diff { json, show_secrets } => {
+                    use crate::config::Origin;
+                    // the file alone, then with the profile: what the rest changed is
+                    // down to the flags and the environment
+                    let from_file = Config::load(&cfg_paths)?.0;
+                    let mut profiled = from_file.clone();
+                    if let Some(name) = self.profile.as_deref() {
+                        profiled.apply_profile(name)?;
+                    }
+                    let file_keys = crate::config::flatten(&from_file);
+                    let profile_keys = crate::config::flatten(&profiled);
+                    let env_cache = std::env::var("WINDMAN_CACHE_DIR")
+                        .ok()
+                        .filter(|d| !d.is_empty());
+
+                    let mut effective = cfg.clone();
+                    if env_cache.is_some() {
+                        effective.paths.cache_dir = env_cache.clone();
+                    }
+                    if !show_secrets {
+                        for (name, value) in effective.network.extra_headers.iter_mut() {
+                            *value = crate::http::redact(name, value).to_string();
+                        }
+                    }
+                    let source = |key: &str| match key {
+                        "install.prefix_dir" if self.prefix.is_some() => Origin::Cli,
+                        "install.bin_dir" if self.bin_dir.is_some() => Origin::Cli,
+                        "install.scope" if self.system => Origin::Cli,
+                        "paths.cache_dir" if env_cache.is_some() => Origin::Env,
+                        _ if profile_keys.get(key) != file_keys.get(key) => Origin::Profile,
+                        _ => Origin::File,
+                    };
+                    let changed = crate::config::diff(&effective, source);
+                    if *json {
+                        println!("{}", serde_json::to_string_pretty(&changed)?);
+                    } else if changed.is_empty() {
+                        println!("# every setting has its built-in default");
+                    } else {
+                        print!("{}", config_diff_toml(&changed));
+                    }
+                    Ok(())
+                }
+                ConfigCmd::Show {
+                    check: false,
+                    show_secrets,
+                    default: false,
+                } => {
+                    if !cfg_found {
+                        println!(
+                            "# {} (config file not found, using defaults)",
+                            cfg_paths.config_display()
+                        );
+                    }
+                    let mut shown = cfg.clone();
+                    if !show_secrets {
+                        for (name, value) in shown.network.extra_headers.iter_mut() {
+                            *value = crate::http::redact(name, value).to_string();
+                        }
+                    }
+                    println!("{}", toml::to_string_pretty(&shown)?);
+                    println!(
+                        "# install.keep = N keeps the N newest versions plus current and previous-current;"
+                    );
+                    println!("# install.keep = 0 keeps only current and previous-current.");
+                    Ok(())
+                }
+            },
+
+            Commands::VerifyArchive(args) => {
+                use std::path::Path;
+                let key = args
+                    .public_key
+                    .as_deref()
+                    .unwrap_or(&cfg.security.public_key);
+                crate::verify::verify_archive(Path::new(&args.file), Path::new(&args.sig), key)?;
+                println!("Signature OK: {}", args.file);
+                Ok(())
+            }
+
+            Commands::Releases(ReleasesCmd::Latest(args)) => {
+                print_latest_all(&cfg.install.channel, args.timeout, args.json)
+            }
+
+            Commands::Doctor(args) => {
+                let desktop = cfg.install.desktop_integration.then_some(&cfg.desktop);
+                let problems = doctor_problems(&eff, desktop, cfg.install.manage_shim);
+                // not a problem, but a switch now would happen under their feet
+                for (pid, version) in install::running_instances(&eff.versions_dir) {
+                    println!("running: Windsurf (pid {}) from {}", pid, version);
+                }
+                if desktop.is_some() {
+                    for note in desktop::integration_status(&eff).notes() {
+                        println!("note: {}", note);
+                    }
+                }
+                if problems.is_empty() {
+                    println!("No problems found.");
+                    return Ok(ExitCode::SUCCESS);
+                }
+                for p in &problems {
+                    println!("problem: {}", p);
+                }
+                if !args.fix {
+                    bail!(
+                        "{} problem(s) found; run `windman doctor --fix` to repair what can be",
+                        problems.len()
+                    );
+                }
+                for what in refresh_integration(
+                    &eff,
+                    desktop,
+                    cfg.install.manage_shim,
+                    self.shim_notice(&cfg, &eff).as_ref(),
+                )? {
+                    println!("Repaired {}", what);
+                }
+                let left = doctor_problems(&eff, desktop, cfg.install.manage_shim);
+                if !left.is_empty() {
+                    bail!("{} problem(s) left: {}", left.len(), left.join("; "));
+                }
+                Ok(())
+            }
+
+            Commands::Init(args) => {
+                self.init(args, &cfg_paths, &cfg, &eff, cfg_found, &install_opts)
+            }
+
+            Commands::MigratePrefix(args) => {
+                if self.prefix.is_some() {
+                    bail!("--prefix cannot be combined with migrate-prefix (give the new dir as argument)");
+                }
+                let mut new_cfg = cfg.clone();
+                new_cfg.install.prefix_dir = args.dir.clone();
+                let problems = new_cfg.validate();
+                if !problems.is_empty() {
+                    bail!("invalid new prefix:\n  - {}", problems.join("\n  - "));
+                }
+                let new_eff = resolve_paths(&new_cfg, self.profile.as_deref())?;
+                // config already edited by hand: the stale shim still knows the old prefix
+                let mut old_eff = eff.clone();
+                if let Some(link) = crate::util::stale_shim_target(&eff.bin_shim, &eff.shim_link())
+                    .filter(|_| eff.versions_dir == new_eff.versions_dir)
+                {
+                    if let Some(dir) = link.parent().filter(|d| d.is_dir()) {
+                        old_eff.prefix_dir = dir.to_path_buf();
+                        old_eff.versions_dir = dir.to_path_buf();
+                        old_eff.current_symlink = link.clone();
+                    }
+                }
+                let desktop = cfg.install.desktop_integration.then_some(&cfg.desktop);
+                crate::migrate::migrate_prefix(
+                    &old_eff,
+                    &new_eff,
+                    desktop,
+                    cfg.install.manage_shim,
+                    self.shim_notice(&cfg, &new_eff).as_ref(),
+                )?;
+                println!(
+                    "Moved {} -> {}",
+                    old_eff.versions_dir.display(),
+                    new_eff.versions_dir.display()
+                );
+                Config::set_prefix_dir(&cfg_paths, self.profile.as_deref(), &args.dir).with_context(
+                    || {
+                        format!(
+                            "versions moved, but the config was not updated: set prefix_dir = \"{}\" by hand",
+                            args.dir
+                        )
+                    },
+                )?;
+                println!("Updated prefix_dir in {}", cfg_paths.config_display());
+                Ok(())
+            }
+
+            Commands::Gc(args) => {
+                let policy = crate::gc::Policy {
+                    install: cfg.install.clone(),
+                    aggressive: args.aggressive,
+                };
+                let items = crate::gc::plan(&eff, &policy, chrono::Utc::now())?;
+                if items.is_empty() {
+                    println!("Nothing to clean up.");
+                    return Ok(ExitCode::SUCCESS);
+                }
+                let verb = if args.dry_run {
+                    "Would remove"
+                } else {
+                    "Removing"
+                };
+                for i in &items {
+                    println!(
+                        "{} {} ({})",
+                        verb,
+                        i.path.display(),
+                        indicatif::HumanBytes(i.bytes)
+                    );
+                }
+                if !args.dry_run {
+                    crate::gc::apply(&eff, &items, policy.install.prune_to_trash)?;
+                }
+                let mut total = 0;
+                for (category, n, bytes) in crate::gc::summary(&items) {
+                    println!(
+                        "{:<18} {:>3} item(s)  {}",
+                        category.label(),
+                        n,
+                        indicatif::HumanBytes(bytes)
+                    );
+                    total += bytes;
+                }
+                if policy.install.prune_to_trash
+                    && items
+                        .iter()
+                        .any(|i| i.category == crate::gc::Category::Versions)
+                {
+                    println!("(old versions were moved to the trash: install.prune_to_trash)");
+                }
+                println!(
+                    "{} {}",
+                    if args.dry_run {
+                        "Would reclaim"
+                    } else {
+                        "Reclaimed"
+                    },
+                    indicatif::HumanBytes(total)
+                );
+                Ok(())
+            }
+
+            Commands::Prune(args) => {
+                let decisions = prune::PrunePlan::for_install(
+                    &eff,
+                    &cfg.install,
+                    args.keep.unwrap_or(cfg.install.keep),
+                )?
+                .decide();
+                if args.explain {
+                    for d in &decisions {
+                        println!(
+                            "{:<7}{:<24}{}",
+                            if d.keep { "keep" } else { "remove" },
+                            d.entry.name,
+                            d.reason
+                        );
+                    }
+                }
+                let doomed = prune::removals(&decisions);
+                if doomed.is_empty() {
+                    println!("Nothing to prune.");
+                    return Ok(ExitCode::SUCCESS);
+                }
+                if !args.explain || !args.dry_run {
+                    let verb = if args.dry_run {
+                        "Would remove"
+                    } else {
+                        "Removing"
+                    };
+                    for p in &doomed {
+                        let kept = crate::meta::artifact(p)
+                            .and_then(|a| fs::metadata(a).ok())
+                            .map(|m| format!(", archive {}", indicatif::HumanBytes(m.len())));
+                        println!(
+                            "{} {} ({}{})",
+                            verb,
+                            p.display(),
+                            indicatif::HumanBytes(crate::util::dir_size(p)),
+                            kept.unwrap_or_default()
+                        );
+                    }
+                }
+                if !args.dry_run {
+                    prune::apply(&eff.versions_dir, &decisions, cfg.install.prune_to_trash)?;
+                    if cfg.install.prune_to_trash {
+                        println!("(moved to the trash: install.prune_to_trash)");
+                    }
+                    if let Some((used, limit)) = budget_usage(&cfg, &eff) {
+                        warn_over_budget(used, limit);
+                    }
+                }
+                Ok(())
+            }
+
+            Commands::Dedupe => {
+                // newest first: each version is linked against the next older one
+                let versions = collect_installed(&eff);
+                let mut cache = crate::dedup::HashCache::load(&eff.versions_dir);
+                let mut total = crate::dedup::DedupStats::default();
+                for pair in versions.windows(2) {
+                    let (new, old) = (&pair[0].0, &pair[1].0);
+                    let stats = crate::dedup::dedup_against(
+                        &eff.versions_dir.join(new),
+                        &eff.versions_dir.join(old),
+                        &mut cache,
+                    )?;
+                    if !self.quiet {
+                        println!(
+                            "{} -> {}: {} files linked ({})",
+                            new,
+                            old,
+                            stats.files_linked,
+                            indicatif::HumanBytes(stats.bytes_saved)
+                        );
+                    }
+                    total.add(stats);
+                }
+                cache.retain_existing(&eff.versions_dir);
+                cache.save(&eff.versions_dir)?;
+                println!(
+                    "Saved {} ({} files)",
+                    indicatif::HumanBytes(total.bytes_saved),
+                    total.files_linked
+                );
                 Ok(())
             }
 
-            Commands::List => {
-                let entries = collect_installed(&eff);
-
-                if entries.is_empty() {
-                    println!(
-                        "No installed versions found in {}.",
-                        eff.versions_dir.display()
-                    );
-                } else {
-                    println!("Installed versions in {}:", eff.versions_dir.display());
-                    for (name, is_current) in entries {
-                        if is_current {
-                            println!("* {}   (current)", name);
-                        } else {
-                            println!("  {}", name);
+            Commands::Completions(args) => {
+                use clap::CommandFactory;
+                let env = |k: &str| std::env::var(k).ok();
+                let shell = match args.shell {
+                    Some(s) => s,
+                    None => crate::shell::Shell::detect(&env)?,
+                };
+                let script = crate::shell::completion_script(shell, &Cli::command());
+                if !args.install && !args.uninstall {
+                    print!("{}", script);
+                    return Ok(ExitCode::SUCCESS);
+                }
+                let target = crate::shell::completion_target(shell, &env)?;
+                if args.uninstall {
+                    match fs::remove_file(&target.path) {
+                        Ok(()) => println!("Removed {}", target.path.display()),
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                            println!("No completion script at {}", target.path.display())
+                        }
+                        Err(e) => {
+                            return Err(e)
+                                .with_context(|| format!("removing {}", target.path.display()))
                         }
                     }
+                    return Ok(ExitCode::SUCCESS);
+                }
+                if let Some(dir) = target.path.parent() {
+                    fs::create_dir_all(dir)
+                        .with_context(|| format!("creating {}", dir.display()))?;
                 }
+                fs::write(&target.path, script)
+                    .with_context(|| format!("writing {}", target.path.display()))?;
+                println!("Wrote {}", target.path.display());
+                if let Some(line) = &target.rc_line {
+                    println!("Add this to ~/.zshrc (once) for zsh to find it:");
+                    println!("  {}", line);
+                }
+                println!("Open a new shell to use it.");
                 Ok(())
             }
 
-            Commands::Changelog => {
-                println!("Changelog delta not implemented yet (coming next).");
+            Commands::Timer(TimerCmd::Install) => {
+                let dir = crate::timer::user_unit_dir()?;
+                let exe = std::env::current_exe()?;
+                let exec = crate::timer::exec_start(&exe, self.config.as_deref());
+                for p in crate::timer::install(&dir, &exec)? {
+                    println!("Wrote {}", p.display());
+                }
+                println!("Enable it with:");
+                println!(
+                    "  systemctl --user daemon-reload && systemctl --user enable --now {}",
+                    crate::timer::TIMER_NAME
+                );
                 Ok(())
             }
 
-            Commands::Uninstall { purge } => {
-                install::uninstall_all(&eff, *purge)?;
-                println!("Windman userland install removed.");
+            Commands::Timer(TimerCmd::Remove) => {
+                let dir = crate::timer::user_unit_dir()?;
+                crate::timer::disable();
+                let removed = crate::timer::remove(&dir)?;
+                if removed.is_empty() {
+                    println!("No windman timer installed in {}", dir.display());
+                }
+                for p in removed {
+                    println!("Removed {}", p.display());
+                }
                 Ok(())
             }
 
-            Commands::Rollback => {
-                install::rollback(&eff)?;
+            Commands::Remote(RemoteCmd::Check { timeout }) => {
+                println!("{:<14} {}", "TLS trust", crate::http::trust());
+                let results = crate::remote::check_endpoints(
+                    &crate::remote::default_api_endpoint(&cfg.install.channel),
+                    crate::remote::releases_page_url(),
+                    *timeout,
+                );
+                for r in &results {
+                    let via = match &r.proxy {
+                        Some(p) => format!("via {}", p),
+                        None => "direct".to_string(),
+                    };
+                    println!(
+                        "{:<14} {:<4} {:>6} ms  {} ({})",
+                        r.name,
+                        if r.ok { "OK" } else { "FAIL" },
+                        r.latency.as_millis(),
+                        r.detail,
+                        via
+                    );
+                }
+                if results.iter().any(|r| !r.ok) {
+                    bail!("remote service degraded");
+                }
                 Ok(())
             }
 
-            Commands::Config(sub) => match sub {
-                ConfigCmd::Init => {
-                    cfg.save_if_missing(&cfg_paths)?;
-                    println!("Config written to {}", cfg_paths.config_display());
-                    Ok(())
-                }
-                ConfigCmd::Show => {
-                    println!("{}", toml::to_string_pretty(&cfg)?);
-                    Ok(())
+            Commands::Remote(RemoteCmd::Manifest(args)) => {
+                let arches = pick("arch", &args.arch, crate::remote::LINUX_ARCHES)?;
+                let channel = args.channel.as_deref().unwrap_or(&cfg.install.channel);
+                let channels = pick("channel", channel, crate::config::KNOWN_CHANNELS)?;
+                let targets: Vec<(String, String)> = channels
+                    .iter()
+                    .flat_map(|c| arches.iter().map(move |a| (c.clone(), a.clone())))
+                    .collect();
+                let manifest = crate::manifest::build(
+                    &targets,
+                    crate::remote::api_endpoint,
+                    args.verify,
+                    self.progress(),
+                    args.timeout,
+                )?;
+                println!("{}", serde_json::to_string_pretty(&manifest)?);
+                if manifest.entries.iter().all(|e| e.error.is_some()) {
+                    bail!("all release endpoints failed");
                 }
-            },
+                Ok(())
+            }
 
             Commands::DevLatest(args) => {
                 use anyhow::Context;
                 let timeout = args.timeout;
 
+                if args.all_arches {
+                    print_latest_all(&cfg.install.channel, timeout, false)?;
+                    return Ok(ExitCode::SUCCESS);
+                }
+
                 if let Some(path) = &args.dump_html {
-                    let html = crate::remote::fetch_releases_html(timeout)?;
+                    let html =
+                        crate::remote::fetch_releases_html(&cfg.changelog.source_url, timeout)?;
                     std::fs::write(path, &html).with_context(|| format!("writing {}", path))?;
                     println!("Dumped releases HTML to {}", path);
-                    return Ok(());
+                    return Ok(ExitCode::SUCCESS);
                 }
 
-                let info = crate::remote::latest_stable_linux_x64(timeout)?;
+                let endpoint = args
+                    .endpoint
+                    .clone()
+                    .unwrap_or_else(|| crate::remote::default_api_endpoint(&cfg.install.channel));
+                let info = if self.verbose {
+                    // the raw exchange, before any parsing or fallback
+                    let client = crate::remote::CachedClient::new(timeout, None)?;
+                    let resp = client.fetch(&endpoint)?;
+                    println!("GET {}", endpoint);
+                    for h in crate::http::describe(&crate::http::sent_headers()) {
+                        println!("> {}", h);
+                    }
+                    println!("HTTP {}", resp.status);
+                    for (name, value) in &resp.headers_of_interest {
+                        println!("{}: {}", name, value);
+                    }
+                    println!();
+                    if resp.body.trim().is_empty() {
+                        println!("(empty body)");
+                    } else {
+                        println!("{}", resp.body.trim_end());
+                    }
+                    println!();
+                    resp.parsed?
+                } else {
+                    crate::remote::latest_at(
+                        &endpoint,
+                        timeout,
+                        cfg.network.allow_html_fallback,
+                        None,
+                    )?
+                };
                 println!("latest.version = {}", info.version);
                 println!("latest.url     = {}", info.url);
                 Ok(())
@@ -489,15 +4884,26 @@ impl Cli {
 
             Commands::DevDownload(args) => {
                 use std::path::Path;
-                crate::download::download_to_file_with_timeout(
+                let done = crate::download::download_to_file_with_timeout(
                     &args.url,
                     Path::new(&args.out),
                     args.timeout,
+                    self.progress(),
                 )?;
                 println!("Downloaded to {}", args.out);
+                if done.origin.final_url != args.url {
+                    println!("final url = {}", done.origin.final_url);
+                }
+                if let Some(name) = done.origin.file_name() {
+                    println!("filename = {}", name);
+                }
+                println!("sha256 = {}", done.sha256);
                 Ok(())
             }
-        }
+
+            Commands::DevMangen(_) => unreachable!("rendered before the config is read"),
+        }?;
+        Ok(exit)
     }
 }
 
@@ -518,10 +4924,14 @@ mod tests_list_collect {
             bin_shim: tmp.path().join("bin/windsurf"),
             desktop_file: tmp.path().join("share/applications/windsurf.desktop"),
             icons_dir: tmp.path().join("share/icons"),
+            cache_dir: tmp.path().join("cache"),
+            state_dir: tmp.path().join("state"),
+            profile: None,
+            arch: None,
         };
-        fs::create_dir_all(&eff.versions_dir.join("1.12.9")).unwrap();
-        fs::create_dir_all(&eff.versions_dir.join("1.12.11")).unwrap();
-        std::os::unix::fs::symlink(&eff.versions_dir.join("1.12.9"), &eff.current_symlink).unwrap();
+        fs::create_dir_all(eff.versions_dir.join("1.12.9")).unwrap();
+        fs::create_dir_all(eff.versions_dir.join("1.12.11")).unwrap();
+        std::os::unix::fs::symlink(eff.versions_dir.join("1.12.9"), &eff.current_symlink).unwrap();
 
         let got = collect_installed(&eff);
         // tri semver desc => 1.12.11, 1.12.9
@@ -529,6 +4939,192 @@ mod tests_list_collect {
         assert_eq!(got[1].0, "1.12.9");
         assert!(got.iter().find(|(n, _)| n == "1.12.9").unwrap().1);
     }
+
+    #[test]
+    fn marks_current_for_relative_symlink_targets() {
+        let tmp = tempdir().unwrap();
+        let root = tmp.path();
+        let eff = crate::paths::EffectivePaths::for_test(root);
+        fs::create_dir_all(eff.versions_dir.join("1.12.9")).unwrap();
+        fs::create_dir_all(eff.versions_dir.join("1.12.11")).unwrap();
+
+        let absolute = eff.versions_dir.join("1.12.9");
+        for target in [
+            std::path::Path::new("versions/1.12.9"),
+            std::path::Path::new("./versions/1.12.9"),
+            std::path::Path::new("versions/../versions/1.12.9"),
+            absolute.as_path(),
+        ] {
+            let _ = fs::remove_file(&eff.current_symlink);
+            std::os::unix::fs::symlink(target, &eff.current_symlink).unwrap();
+            let current: Vec<_> = collect_installed(&eff)
+                .into_iter()
+                .filter(|(_, c)| *c)
+                .map(|(n, _)| n)
+                .collect();
+            assert_eq!(current, vec!["1.12.9"], "target {}", target.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_porcelain_golden {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn where_shell_matches_golden() {
+        use crate::shell::Shell;
+        // a space and a quote, to show the quoting
+        let eff = crate::paths::EffectivePaths::for_test(std::path::Path::new(
+            "/home/o'neil/Windsurf apps",
+        ));
+        assert_eq!(
+            where_env(Shell::Bash, &eff),
+            include_str!("../tests/golden/where_bash.txt")
+        );
+        assert_eq!(
+            where_env(Shell::Zsh, &eff),
+            include_str!("../tests/golden/where_bash.txt")
+        );
+        assert_eq!(
+            where_env(Shell::Fish, &eff),
+            include_str!("../tests/golden/where_fish.txt")
+        );
+    }
+
+    #[test]
+    fn list_porcelain_matches_golden() {
+        let tmp = tempdir().unwrap();
+        let eff = crate::paths::EffectivePaths::for_test(tmp.path());
+        fs::create_dir_all(eff.versions_dir.join("1.12.9/Windsurf")).unwrap();
+        fs::write(eff.versions_dir.join("1.12.9/Windsurf/a"), vec![0u8; 100]).unwrap();
+        fs::write(eff.versions_dir.join("1.12.9/b"), vec![0u8; 23]).unwrap();
+        fs::create_dir_all(eff.versions_dir.join("1.12.11")).unwrap();
+        fs::write(eff.versions_dir.join("1.12.11/c"), vec![0u8; 4096]).unwrap();
+        std::os::unix::fs::symlink(eff.versions_dir.join("1.12.9"), &eff.current_symlink).unwrap();
+
+        for (dir, at) in [
+            ("1.12.9", "2025-09-27T15:33:22Z"),
+            ("1.12.11", "2025-10-02T08:00:00Z"),
+        ] {
+            let meta = crate::meta::VersionMeta {
+                version: dir.into(),
+                installed_at: at.into(),
+                ..Default::default()
+            };
+            crate::meta::write(&eff.versions_dir.join(dir), &meta).unwrap();
+        }
+
+        // sizes include the metadata file (83 and 84 bytes)
+        assert_eq!(
+            list_porcelain(&eff, Some("1.12.11")),
+            include_str!("../tests/golden/list_porcelain.txt")
+        );
+    }
+
+    #[test]
+    fn list_table_matches_golden() {
+        let tmp = tempdir().unwrap();
+        let eff = crate::paths::EffectivePaths::for_test(tmp.path());
+        let trashed = eff.versions_dir.join(".trash/20250920120000-1.11.2");
+        for (dir, at) in [
+            (eff.versions_dir.join("1.12.9"), "2025-09-27T15:33:22Z"),
+            (eff.versions_dir.join("1.12.11"), "2025-10-02T08:00:00Z"),
+            (trashed, "2025-08-14T10:00:00Z"),
+        ] {
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("a"), vec![0u8; 2000]).unwrap();
+            let meta = crate::meta::VersionMeta {
+                version: "x".into(),
+                installed_at: at.into(),
+                ..Default::default()
+            };
+            crate::meta::write(&dir, &meta).unwrap();
+        }
+        std::os::unix::fs::symlink(eff.versions_dir.join("1.12.9"), &eff.current_symlink).unwrap();
+        let now = chrono::DateTime::parse_from_rfc3339("2025-10-03T09:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+
+        let mut entries = list_entries(&eff, Some("1.12.9"), true);
+        mark_latest(&mut entries, "1.13.0");
+        assert_eq!(
+            render_list(&entries, now),
+            include_str!("../tests/golden/list.txt")
+        );
+
+        // not newer than what is installed: no row
+        let mut entries = list_entries(&eff, None, false);
+        mark_latest(&mut entries, "1.12.11");
+        assert!(entries.iter().all(|e| e.state != ListState::Available));
+
+        // a build from another channel among them is tagged with it
+        let dir = eff.versions_dir.join("1.12.11");
+        let mut meta = crate::meta::read(&dir).unwrap();
+        meta.channel = Some("next".into());
+        crate::meta::write(&dir, &meta).unwrap();
+        let table = render_list(&list_entries(&eff, None, false), now);
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(
+            lines[0].starts_with("   1.12.11 [next]  2025-10-02"),
+            "{}",
+            table
+        );
+        assert!(lines[1].starts_with("*  1.12.9 "), "{}", table);
+    }
+
+    #[test]
+    fn status_porcelain_matches_golden() {
+        let tmp = tempdir().unwrap();
+        let mut eff = crate::paths::EffectivePaths::for_test(tmp.path());
+        eff.profile = Some("next".to_string());
+
+        let got = status_porcelain(&eff, Some("1.12.11"));
+        let want = include_str!("../tests/golden/status_porcelain.txt")
+            .replace("{root}", &tmp.path().display().to_string());
+        assert_eq!(got, want);
+    }
+}
+
+#[cfg(test)]
+mod tests_info {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn info_reads_metadata_and_defaults_to_current() {
+        let tmp = tempdir().unwrap();
+        let eff = crate::paths::EffectivePaths::for_test(tmp.path());
+        let v = eff.versions_dir.join("1.12.11");
+        fs::create_dir_all(&v).unwrap();
+        crate::meta::write(
+            &v,
+            &crate::meta::VersionMeta {
+                version: "1.12.11".into(),
+                installed_at: "2025-09-27T15:33:22Z".into(),
+                source: "https://mirror.example/Windsurf-1.12.11.tar.gz".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        std::os::unix::fs::symlink(&v, &eff.current_symlink).unwrap();
+
+        let info = version_info(&eff, None).unwrap();
+        assert_eq!(info.version, "1.12.11");
+        assert_eq!(info.notes, crate::notes::NotesStatus::Missing);
+        assert!(info.current);
+        assert_eq!(
+            info.source.as_deref(),
+            Some("https://mirror.example/Windsurf-1.12.11.tar.gz")
+        );
+
+        let err = version_info(&eff, Some("1.0.0")).unwrap_err().to_string();
+        assert!(err.contains("not found"));
+        assert!(err.contains("1.12.11"));
+    }
 }
 
 #[cfg(test)]
@@ -537,6 +5133,116 @@ mod tests_use_switch {
     use std::fs;
     use tempfile::tempdir;
 
+    fn current_name(eff: &crate::paths::EffectivePaths) -> String {
+        crate::history::current_version_name(&eff.current_symlink).unwrap()
+    }
+
+    #[test]
+    fn walks_forward_and_backward_across_three_versions() {
+        let tmp = tempdir().unwrap();
+        let eff = crate::paths::EffectivePaths::for_test(tmp.path());
+        for v in ["1.9.0", "1.10.0", "1.10.1"] {
+            fs::create_dir_all(eff.versions_dir.join(v)).unwrap();
+        }
+        std::os::unix::fs::symlink(eff.versions_dir.join("1.9.0"), &eff.current_symlink).unwrap();
+        let step = |spec: &str| -> anyhow::Result<String> {
+            let v = relative_version(&eff, spec)?.expect("relative spec");
+            switch_to_version(&eff, &v)?;
+            Ok(current_name(&eff))
+        };
+
+        // semver order, not lexical: 1.9.0 < 1.10.0 < 1.10.1
+        let err = step("-1").unwrap_err().to_string();
+        assert!(
+            err.contains("already at oldest installed version"),
+            "{}",
+            err
+        );
+        assert_eq!(step("+1").unwrap(), "1.10.0");
+        assert_eq!(step("+1").unwrap(), "1.10.1");
+        let err = step("+1").unwrap_err().to_string();
+        assert!(
+            err.contains("already at newest installed version"),
+            "{}",
+            err
+        );
+        assert_eq!(step("-2").unwrap(), "1.9.0");
+        assert!(step("-1").is_err());
+        assert_eq!(step("+2").unwrap(), "1.10.1");
+        assert_eq!(relative_version(&eff, "1.9.0").unwrap(), None);
+
+        // history: 1.9.0 -> 1.10.0 -> 1.10.1 -> 1.9.0 -> 1.10.1
+        assert_eq!(install::rollback(&eff).unwrap(), "1.9.0");
+        assert_eq!(install::rollback(&eff).unwrap(), "1.10.1");
+        assert_eq!(install::rollback(&eff).unwrap(), "1.10.0");
+        assert_eq!(install::rollforward(&eff).unwrap(), "1.10.1");
+        assert_eq!(install::rollforward(&eff).unwrap(), "1.9.0");
+        assert_eq!(install::rollback(&eff).unwrap(), "1.10.1");
+
+        // a new switch drops what was left to redo
+        assert_eq!(step("-1").unwrap(), "1.10.0");
+        let err = install::rollforward(&eff).unwrap_err().to_string();
+        assert!(err.contains("nothing to roll forward"), "{}", err);
+
+        let h = crate::history::History::load(&eff.versions_dir);
+        let last = h.done.last().unwrap();
+        assert_eq!(
+            (last.from.as_deref(), last.to.as_str()),
+            (Some("1.10.1"), "1.10.0")
+        );
+    }
+
+    #[test]
+    fn use_asks_before_switching_to_an_older_version() {
+        let tmp = tempdir().unwrap();
+        let eff = crate::paths::EffectivePaths::for_test(tmp.path());
+        for v in ["1.12.9", "1.13.0"] {
+            fs::create_dir_all(eff.versions_dir.join(v)).unwrap();
+        }
+        std::os::unix::fs::symlink(eff.versions_dir.join("1.13.0"), &eff.current_symlink).unwrap();
+
+        let err = use_version(&eff, "1.12.9", false).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("refusing to downgrade 1.13.0 → 1.12.9"),
+            "{}",
+            err
+        );
+        assert_eq!(current_name(&eff), "1.13.0");
+
+        use_version(&eff, "1.12.9", true).unwrap();
+        assert_eq!(current_name(&eff), "1.12.9");
+        // upgrades never ask
+        use_version(&eff, "1.13.0", false).unwrap();
+        assert_eq!(current_name(&eff), "1.13.0");
+        // unknown versions still report what is installed
+        assert!(use_version(&eff, "0.1.0", false)
+            .unwrap_err()
+            .to_string()
+            .contains("not found"));
+    }
+
+    #[test]
+    fn rollforward_refuses_after_a_manual_switch() {
+        let tmp = tempdir().unwrap();
+        let versions = tmp.path().join("versions");
+        let eff = crate::paths::EffectivePaths {
+            versions_dir: versions.clone(),
+            ..crate::paths::EffectivePaths::for_test(tmp.path())
+        };
+        for v in ["1.0.0", "1.0.1", "1.0.2"] {
+            fs::create_dir_all(versions.join(v)).unwrap();
+        }
+        std::os::unix::fs::symlink(versions.join("1.0.1"), &eff.current_symlink).unwrap();
+        switch_to_version(&eff, "1.0.2").unwrap();
+        assert_eq!(install::rollback(&eff).unwrap(), "1.0.1");
+
+        // current moved behind windman's back
+        crate::util::atomic_symlink_switch(&versions.join("1.0.0"), &eff.current_symlink).unwrap();
+        let err = install::rollforward(&eff).unwrap_err().to_string();
+        assert!(err.contains("current is now 1.0.0"), "{}", err);
+    }
+
     #[test]
     fn switches_current_symlink_to_requested_version() {
         let tmp = tempdir().unwrap();
@@ -548,6 +5254,10 @@ mod tests_use_switch {
             bin_shim: tmp.path().join("bin/windsurf"),
             desktop_file: tmp.path().join("share/applications/windsurf.desktop"),
             icons_dir: tmp.path().join("share/icons"),
+            cache_dir: tmp.path().join("cache"),
+            state_dir: tmp.path().join("state"),
+            profile: None,
+            arch: None,
         };
         fs::create_dir_all(eff.versions_dir.join("1.12.10")).unwrap();
         fs::create_dir_all(eff.versions_dir.join("1.12.11")).unwrap();
@@ -561,6 +5271,103 @@ mod tests_use_switch {
         assert_eq!(cur.file_name().unwrap().to_string_lossy(), "1.12.11");
     }
 
+    #[test]
+    fn doctor_flags_a_shim_left_on_the_old_prefix_and_fix_repairs_it() {
+        let tmp = tempdir().unwrap();
+        let mut eff = crate::paths::EffectivePaths {
+            prefix_dir: tmp.path().join("old"),
+            versions_dir: tmp.path().join("old"),
+            current_symlink: tmp.path().join("old/current"),
+            bin_dir: tmp.path().join("bin"),
+            bin_shim: tmp.path().join("bin/windsurf"),
+            desktop_file: tmp.path().join("share/applications/windsurf.desktop"),
+            icons_dir: tmp.path().join("share/icons"),
+            cache_dir: tmp.path().join("cache"),
+            state_dir: tmp.path().join("state"),
+            profile: None,
+            arch: None,
+        };
+        fs::create_dir_all(eff.versions_dir.join("1.12.11")).unwrap();
+        std::os::unix::fs::symlink(eff.versions_dir.join("1.12.11"), &eff.current_symlink).unwrap();
+        crate::util::write_shim(&eff.bin_shim, &eff.current_symlink, None).unwrap();
+        assert!(doctor_problems(&eff, None, true).is_empty());
+
+        // prefix_dir edited in the config, versions copied by hand
+        let old_current = eff.current_symlink.clone();
+        eff.prefix_dir = tmp.path().join("new");
+        eff.versions_dir = eff.prefix_dir.clone();
+        eff.current_symlink = eff.prefix_dir.join("current");
+        fs::create_dir_all(eff.versions_dir.join("1.12.11")).unwrap();
+        std::os::unix::fs::symlink(eff.versions_dir.join("1.12.11"), &eff.current_symlink).unwrap();
+
+        assert_eq!(
+            crate::util::stale_shim_target(&eff.bin_shim, &eff.current_symlink),
+            Some(old_current)
+        );
+        let problems = doctor_problems(&eff, None, true);
+        assert_eq!(problems.len(), 1, "{:?}", problems);
+        assert!(problems[0].contains("another prefix"), "{}", problems[0]);
+
+        refresh_integration(&eff, None, true, None).unwrap();
+        assert!(doctor_problems(&eff, None, true).is_empty());
+
+        fs::remove_dir_all(eff.versions_dir.join("1.12.11")).unwrap();
+        assert!(doctor_problems(&eff, None, true)[0].contains("no longer exists"));
+    }
+
+    #[test]
+    fn use_rewrites_shim_after_bin_dir_change() {
+        let tmp = tempdir().unwrap();
+        let mut eff = crate::paths::EffectivePaths::for_test(tmp.path());
+        fs::create_dir_all(eff.versions_dir.join("1.12.10")).unwrap();
+        fs::create_dir_all(eff.versions_dir.join("1.12.11")).unwrap();
+        std::os::unix::fs::symlink(eff.versions_dir.join("1.12.10"), &eff.current_symlink).unwrap();
+        crate::util::write_shim(&eff.bin_shim, &eff.current_symlink, None).unwrap();
+        crate::desktop::ensure_desktop_files(&eff, &Default::default()).unwrap();
+        assert!(
+            refresh_integration(&eff, Some(&Default::default()), true, None)
+                .unwrap()
+                .is_empty()
+        );
+
+        // user moves bin_dir in config
+        eff.bin_dir = tmp.path().join("other-bin");
+        eff.bin_shim = eff.bin_dir.join("windsurf");
+
+        switch_to_version(&eff, "1.12.11").unwrap();
+        let repaired = refresh_integration(&eff, Some(&Default::default()), true, None).unwrap();
+        assert_eq!(repaired.len(), 1, "{:?}", repaired);
+        assert!(crate::util::shim_points_to(
+            &eff.bin_shim,
+            &eff.current_symlink
+        ));
+
+        // desktop entry deleted -> recreated
+        fs::remove_file(&eff.desktop_file).unwrap();
+        let repaired = refresh_integration(&eff, Some(&Default::default()), true, None).unwrap();
+        assert!(repaired[0].starts_with("desktop entry"));
+        assert!(eff.desktop_file.is_file());
+    }
+
+    #[test]
+    fn use_works_on_restored_version() {
+        let tmp = tempdir().unwrap();
+        let eff = crate::paths::EffectivePaths::for_test(tmp.path());
+        fs::create_dir_all(eff.versions_dir.join("1.12.10")).unwrap();
+        fs::create_dir_all(eff.versions_dir.join("1.12.11")).unwrap();
+        std::os::unix::fs::symlink(eff.versions_dir.join("1.12.11"), &eff.current_symlink).unwrap();
+
+        crate::trash::move_to_trash(&eff.versions_dir, &eff.versions_dir.join("1.12.10")).unwrap();
+        assert!(switch_to_version(&eff, "1.12.10").is_err());
+        // .trash is not listed as a version
+        assert_eq!(collect_installed(&eff).len(), 1);
+
+        crate::trash::restore(&eff.versions_dir, "1.12.10").unwrap();
+        switch_to_version(&eff, "1.12.10").unwrap();
+        let cur = fs::read_link(&eff.current_symlink).unwrap();
+        assert_eq!(cur.file_name().unwrap().to_string_lossy(), "1.12.10");
+    }
+
     #[test]
     fn errors_if_version_missing_with_available_list() {
         let tmp = tempdir().unwrap();
@@ -572,6 +5379,10 @@ mod tests_use_switch {
             bin_shim: tmp.path().join("bin/windsurf"),
             desktop_file: tmp.path().join("share/applications/windsurf.desktop"),
             icons_dir: tmp.path().join("share/icons"),
+            cache_dir: tmp.path().join("cache"),
+            state_dir: tmp.path().join("state"),
+            profile: None,
+            arch: None,
         };
         fs::create_dir_all(eff.versions_dir.join("1.12.11")).unwrap();
 
@@ -581,3 +5392,311 @@ mod tests_use_switch {
         assert!(msg.contains("1.12.11"));
     }
 }
+
+#[cfg(test)]
+mod tests_exec_version {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn explicit_then_pin_then_current() {
+        let tmp = tempdir().unwrap();
+        let eff = crate::paths::EffectivePaths::for_test(tmp.path());
+        let project = tmp.path().join("project/src");
+        fs::create_dir_all(&project).unwrap();
+        for v in ["1.11.3", "1.12.0"] {
+            fs::create_dir_all(eff.versions_dir.join(v)).unwrap();
+        }
+        std::os::unix::fs::symlink(eff.versions_dir.join("1.12.0"), &eff.current_symlink).unwrap();
+
+        let name = |p: std::path::PathBuf| p.file_name().unwrap().to_string_lossy().to_string();
+        assert_eq!(
+            name(exec_version_dir(&eff, None, &project).unwrap()),
+            "1.12.0"
+        );
+
+        crate::project::write_version_file(&tmp.path().join("project"), "1.11.3").unwrap();
+        assert_eq!(
+            name(exec_version_dir(&eff, None, &project).unwrap()),
+            "1.11.3"
+        );
+        assert_eq!(
+            name(exec_version_dir(&eff, Some("1.12.0"), &project).unwrap()),
+            "1.12.0"
+        );
+    }
+
+    #[test]
+    fn pinned_but_missing_version_suggests_install() {
+        let tmp = tempdir().unwrap();
+        let eff = crate::paths::EffectivePaths::for_test(tmp.path());
+        crate::project::write_version_file(tmp.path(), "1.9.9").unwrap();
+
+        let err = exec_version_dir(&eff, None, tmp.path()).unwrap_err();
+        match err.downcast_ref::<crate::error::WindmanError>() {
+            Some(crate::error::WindmanError::NotInstalled {
+                version: Some(v),
+                pinned_by: Some(_),
+            }) => assert_eq!(v, "1.9.9"),
+            other => panic!("expected NotInstalled, got {:?}", other),
+        }
+        let msg = err.to_string();
+        assert!(msg.contains("1.9.9 is not installed"), "{}", msg);
+        assert!(msg.contains(".windsurf-version"), "{}", msg);
+        assert!(msg.contains("windman install"), "{}", msg);
+    }
+}
+
+#[cfg(test)]
+mod tests_install_url {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    /// Windsurf/bin/windsurf + a product.json naming `version`, gzipped.
+    fn fake_archive(version: &str) -> Vec<u8> {
+        let enc = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        let mut builder = tar::Builder::new(enc);
+        let product = format!(r#"{{ "windsurfVersion":"{}" }}"#, version);
+        for (path, mode, data) in [
+            ("Windsurf/bin/windsurf", 0o755, &b"#!"[..]),
+            (
+                "Windsurf/resources/app/product.json",
+                0o644,
+                product.as_bytes(),
+            ),
+        ] {
+            let mut hdr = tar::Header::new_gnu();
+            hdr.set_path(path).unwrap();
+            hdr.set_mode(mode);
+            hdr.set_size(data.len() as u64);
+            hdr.set_cksum();
+            builder.append(&hdr, data).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    /// Serve `body` once, whatever the request.
+    fn serve_once(body: Vec<u8>) -> String {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = stream.read(&mut buf);
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            let _ = stream.write_all(head.as_bytes());
+            let _ = stream.write_all(&body);
+        });
+        format!("http://{}", addr)
+    }
+
+    fn opts() -> install::InstallOptions {
+        install::InstallOptions {
+            quiet: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn downloads_to_the_cache_and_records_the_url() {
+        let td = tempdir().unwrap();
+        let eff = crate::paths::EffectivePaths::for_test(td.path());
+        let url = format!(
+            "{}/builds/Windsurf-linux-x64-1.12.7.tar.gz?token=abc",
+            serve_once(fake_archive("1.12.7"))
+        );
+
+        let ver = install_from_url(&Config::default(), &eff, &url, &opts()).unwrap();
+
+        assert_eq!(ver, "1.12.7");
+        assert!(eff
+            .cache_dir
+            .join("downloads/url/1.12.7/Windsurf-linux-x64-1.12.7.tar.gz")
+            .is_file());
+        let meta = crate::meta::read(&eff.versions_dir.join("1.12.7")).unwrap();
+        assert_eq!(meta.source, url);
+        assert!(meta.sha256.is_some());
+        assert_eq!(
+            crate::history::current_version_name(&eff.current_symlink).as_deref(),
+            Some("1.12.7")
+        );
+    }
+
+    #[test]
+    fn a_redirect_to_an_unnamed_file_is_cached_under_the_served_name() {
+        use std::io::{Read, Write};
+        let body = fake_archive("1.12.9");
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for resp in [
+                b"HTTP/1.1 302 Found\r\nLocation: /signed/3f6c0a9e-5b7d\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_vec(),
+                [
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Disposition: attachment; filename=\"Windsurf-linux-x64-1.12.9.tar.gz\"\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        body.len()
+                    )
+                    .into_bytes(),
+                    body,
+                ]
+                .concat(),
+            ] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let _ = stream.read(&mut [0u8; 4096]);
+                let _ = stream.write_all(&resp);
+            }
+        });
+        let td = tempdir().unwrap();
+        let eff = crate::paths::EffectivePaths::for_test(td.path());
+        let url = format!("http://{}/download/latest", addr);
+
+        let ver = install_from_url(&Config::default(), &eff, &url, &opts()).unwrap();
+
+        assert_eq!(ver, "1.12.9");
+        let cached = eff
+            .cache_dir
+            .join("downloads/url/1.12.9/Windsurf-linux-x64-1.12.9.tar.gz");
+        assert!(cached.is_file());
+        assert!(crate::export::sidecar_path(&cached).is_file());
+        assert!(!eff.cache_dir.join("downloads/url/unversioned").exists());
+    }
+
+    #[test]
+    fn simultaneous_downloads_into_one_cache_dir_fetch_once() {
+        use std::io::{Read, Write};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let body = fake_archive("1.12.8");
+        let served = std::sync::Arc::new(AtomicUsize::new(0));
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        {
+            let served = served.clone();
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let mut stream = stream.unwrap();
+                    let _ = stream.read(&mut [0u8; 4096]);
+                    served.fetch_add(1, Ordering::SeqCst);
+                    // slow enough for the other run to find the lock taken
+                    std::thread::sleep(std::time::Duration::from_millis(300));
+                    let head = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        body.len()
+                    );
+                    let _ = stream.write_all(head.as_bytes());
+                    let _ = stream.write_all(&body);
+                }
+            });
+        }
+        let td = tempdir().unwrap();
+        let dir = td.path().join("downloads/stable/1.12.8");
+        let url = format!("http://{}/Windsurf-linux-x64-1.12.8.tar.gz", addr);
+
+        let runs: Vec<_> = (0..2)
+            .map(|_| {
+                let (dir, url) = (dir.clone(), url.clone());
+                std::thread::spawn(move || {
+                    let place = |name: &str| Ok(dir.join(name));
+                    fetch_into_cache(&url, &place, &|_, _| Ok(()), &opts()).unwrap()
+                })
+            })
+            .collect();
+        let fetched: Vec<_> = runs.into_iter().map(|t| t.join().unwrap()).collect();
+
+        assert_eq!(served.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            (&fetched[0].0, &fetched[0].1),
+            (&fetched[1].0, &fetched[1].1)
+        );
+        assert_eq!(fetched[0].0, dir.join("Windsurf-linux-x64-1.12.8.tar.gz"));
+        assert_eq!(fs::read(&fetched[0].0).unwrap(), fake_archive("1.12.8"));
+        let left: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(left.len(), 1, "{:?}", left);
+    }
+
+    #[test]
+    fn file_urls_install_the_local_archive_and_other_schemes_are_refused() {
+        let td = tempdir().unwrap();
+        let eff = crate::paths::EffectivePaths::for_test(td.path());
+        let tar = td.path().join("Windsurf-linux-x64-1.12.8.tar.gz");
+        fs::write(&tar, fake_archive("1.12.8")).unwrap();
+        let url = format!("file://{}", tar.display());
+
+        assert_eq!(
+            install_from_url(&Config::default(), &eff, &url, &opts()).unwrap(),
+            "1.12.8"
+        );
+        assert_eq!(
+            crate::meta::read(&eff.versions_dir.join("1.12.8"))
+                .unwrap()
+                .source,
+            url
+        );
+        assert!(!eff.cache_dir.exists());
+
+        let err = install_from_url(&Config::default(), &eff, "ftp://x/y.tar.gz", &opts())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("unsupported URL"), "{}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests_shim_notice {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn config_toggle_adds_and_removes_the_notice_in_the_shim() {
+        let tmp = tempdir().unwrap();
+        let eff = crate::paths::EffectivePaths {
+            profile: Some("work".into()),
+            ..crate::paths::EffectivePaths::for_test(tmp.path())
+        };
+        fs::create_dir_all(eff.versions_dir.join("1.12.11")).unwrap();
+        std::os::unix::fs::symlink(eff.versions_dir.join("1.12.11"), &eff.current_symlink).unwrap();
+        let cli =
+            Cli::try_parse_from(["windman", "--config", "/etc/w.toml", "use", "1.12.11"]).unwrap();
+        let mut cfg = Config::default();
+
+        assert_eq!(cli.shim_notice(&cfg, &eff), None);
+        refresh_integration(&eff, None, true, None).unwrap();
+        assert!(!crate::util::shim_has_notice(&eff.bin_shim));
+
+        cfg.install.shim_update_notice = true;
+        let notice = cli.shim_notice(&cfg, &eff).unwrap();
+        assert_eq!(
+            notice.args,
+            ["--config", "/etc/w.toml", "--profile", "work"]
+        );
+        let repaired = refresh_integration(&eff, None, true, Some(&notice)).unwrap();
+        assert_eq!(repaired.len(), 1, "{:?}", repaired);
+        let shim = fs::read_to_string(&eff.bin_shim).unwrap();
+        assert!(
+            shim.contains("'/etc/w.toml' '--profile' 'work' notify-check"),
+            "{}",
+            shim
+        );
+        assert!(shim.contains("WINDMAN_NO_NOTICE"));
+        // already in place: nothing to do
+        assert!(refresh_integration(&eff, None, true, Some(&notice))
+            .unwrap()
+            .is_empty());
+
+        // switched off again: the next refresh drops it
+        assert_eq!(
+            refresh_integration(&eff, None, true, None).unwrap().len(),
+            1
+        );
+        assert!(!crate::util::shim_has_notice(&eff.bin_shim));
+    }
+}
@@ -0,0 +1,323 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// `.gnu.version_d`: version nodes a shared object defines
+const SHT_GNU_VERDEF: u32 = 0x6fff_fffd;
+/// `.gnu.version_r`: version nodes a file needs from its libraries
+const SHT_GNU_VERNEED: u32 = 0x6fff_fffe;
+
+/// Larger version sections are not from a real ELF file.
+const MAX_SECTION: u64 = 16 << 20;
+
+/// Where distributions put the glibc shared object.
+const LIBC_PATHS: &[&str] = &[
+    "/lib/x86_64-linux-gnu/libc.so.6",
+    "/lib/aarch64-linux-gnu/libc.so.6",
+    "/lib64/libc.so.6",
+    "/usr/lib64/libc.so.6",
+    "/usr/lib/libc.so.6",
+    "/lib/libc.so.6",
+];
+
+/// A glibc symbol version, e.g. 2.34 for the `GLIBC_2.34` node.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GlibcVersion(Vec<u32>);
+
+impl GlibcVersion {
+    /// From a version node name; None for `GLIBC_PRIVATE` and other libraries' nodes.
+    pub fn from_node(name: &str) -> Option<Self> {
+        name.strip_prefix("GLIBC_")?
+            .split('.')
+            .map(|p| p.parse().ok())
+            .collect::<Option<Vec<u32>>>()
+            .map(Self)
+    }
+}
+
+impl std::fmt::Display for GlibcVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parts: Vec<String> = self.0.iter().map(u32::to_string).collect();
+        write!(f, "{}", parts.join("."))
+    }
+}
+
+/// Version nodes of an ELF file: those it needs from its libraries and those
+/// it defines (a shared library).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolVersions {
+    pub needed: Vec<String>,
+    pub defined: Vec<String>,
+}
+
+impl SymbolVersions {
+    /// Newest glibc version among the needed nodes.
+    pub fn needs_glibc(&self) -> Option<GlibcVersion> {
+        self.needed
+            .iter()
+            .filter_map(|n| GlibcVersion::from_node(n))
+            .max()
+    }
+
+    /// Newest glibc version among the defined nodes.
+    pub fn defines_glibc(&self) -> Option<GlibcVersion> {
+        self.defined
+            .iter()
+            .filter_map(|n| GlibcVersion::from_node(n))
+            .max()
+    }
+}
+
+/// Byte order and word size of the file being read.
+#[derive(Clone, Copy)]
+struct Layout {
+    is64: bool,
+    le: bool,
+}
+
+impl Layout {
+    fn u16(self, b: &[u8], at: usize) -> Option<u16> {
+        let b: [u8; 2] = b.get(at..at + 2)?.try_into().ok()?;
+        Some(if self.le {
+            u16::from_le_bytes(b)
+        } else {
+            u16::from_be_bytes(b)
+        })
+    }
+
+    fn u32(self, b: &[u8], at: usize) -> Option<u32> {
+        let b: [u8; 4] = b.get(at..at + 4)?.try_into().ok()?;
+        Some(if self.le {
+            u32::from_le_bytes(b)
+        } else {
+            u32::from_be_bytes(b)
+        })
+    }
+
+    fn u64(self, b: &[u8], at: usize) -> Option<u64> {
+        let b: [u8; 8] = b.get(at..at + 8)?.try_into().ok()?;
+        Some(if self.le {
+            u64::from_le_bytes(b)
+        } else {
+            u64::from_be_bytes(b)
+        })
+    }
+
+    /// An address-sized field: 8 bytes in ELF64, 4 in ELF32.
+    fn word(self, b: &[u8], at: usize) -> Option<u64> {
+        if self.is64 {
+            self.u64(b, at)
+        } else {
+            self.u32(b, at).map(u64::from)
+        }
+    }
+}
+
+/// One section header, the fields we use.
+struct Section {
+    kind: u32,
+    offset: u64,
+    size: u64,
+    link: u32,
+    info: u32,
+}
+
+fn read_at(r: &mut (impl Read + Seek), offset: u64, len: u64) -> Option<Vec<u8>> {
+    if len > MAX_SECTION {
+        return None;
+    }
+    r.seek(SeekFrom::Start(offset)).ok()?;
+    let mut buf = vec![0u8; len as usize];
+    r.read_exact(&mut buf).ok()?;
+    Some(buf)
+}
+
+/// NUL-terminated string at `at` of a string table.
+fn c_str(strtab: &[u8], at: u32) -> Option<String> {
+    let s = strtab.get(at as usize..)?;
+    let end = s.iter().position(|&b| b == 0)?;
+    Some(String::from_utf8_lossy(&s[..end]).to_string())
+}
+
+/// The version nodes of an ELF file, reading only its headers and version
+/// sections (not the whole, possibly large, binary). None when it is not ELF.
+pub fn symbol_versions_of(r: &mut (impl Read + Seek)) -> Option<SymbolVersions> {
+    let ehdr = read_at(r, 0, 64).or_else(|| read_at(r, 0, 52))?;
+    if &ehdr[..4] != b"\x7fELF" {
+        return None;
+    }
+    let l = Layout {
+        is64: ehdr[4] == 2,
+        le: ehdr[5] == 1,
+    };
+    let (shoff, shentsize, shnum) = if l.is64 {
+        (
+            l.u64(&ehdr, 0x28)?,
+            l.u16(&ehdr, 0x3A)?,
+            l.u16(&ehdr, 0x3C)?,
+        )
+    } else {
+        (
+            u64::from(l.u32(&ehdr, 0x20)?),
+            l.u16(&ehdr, 0x2E)?,
+            l.u16(&ehdr, 0x30)?,
+        )
+    };
+    let table = read_at(r, shoff, u64::from(shentsize) * u64::from(shnum))?;
+    let sections: Vec<Section> = table
+        .chunks_exact(usize::from(shentsize.max(1)))
+        .filter_map(|h| {
+            let w = if l.is64 { 8 } else { 4 };
+            Some(Section {
+                kind: l.u32(h, 4)?,
+                offset: l.word(h, 8 + 2 * w)?,
+                size: l.word(h, 8 + 3 * w)?,
+                link: l.u32(h, 8 + 4 * w)?,
+                info: l.u32(h, 12 + 4 * w)?,
+            })
+        })
+        .collect();
+
+    let mut out = SymbolVersions::default();
+    for s in &sections {
+        if s.kind != SHT_GNU_VERNEED && s.kind != SHT_GNU_VERDEF {
+            continue;
+        }
+        let strs = sections.get(s.link as usize)?;
+        let strtab = read_at(r, strs.offset, strs.size)?;
+        let data = read_at(r, s.offset, s.size)?;
+        // sh_info entries, chained by their `next` offsets
+        let mut at = 0usize;
+        for _ in 0..s.info {
+            if s.kind == SHT_GNU_VERNEED {
+                // Elf_Verneed { version, cnt, file, aux, next }, then Elf_Vernaux
+                // { hash, flags, other, name, next }
+                let mut aux = at + l.u32(&data, at + 8)? as usize;
+                for _ in 0..l.u16(&data, at + 2)? {
+                    out.needed.push(c_str(&strtab, l.u32(&data, aux + 8)?)?);
+                    aux += l.u32(&data, aux + 12)? as usize;
+                }
+                at += match l.u32(&data, at + 12)? {
+                    0 => break,
+                    next => next as usize,
+                };
+            } else {
+                // Elf_Verdef { version, flags, ndx, cnt, hash, aux, next }; the
+                // first Elf_Verdaux { name, next } names the node
+                let aux = at + l.u32(&data, at + 12)? as usize;
+                out.defined.push(c_str(&strtab, l.u32(&data, aux)?)?);
+                at += match l.u32(&data, at + 16)? {
+                    0 => break,
+                    next => next as usize,
+                };
+            }
+        }
+    }
+    Some(out)
+}
+
+/// `symbol_versions_of` a file; None when it is unreadable or not ELF.
+pub fn symbol_versions(path: &Path) -> Option<SymbolVersions> {
+    symbol_versions_of(&mut File::open(path).ok()?)
+}
+
+/// The system glibc and the newest version it provides. None without one
+/// (musl distributions): then there is nothing to compare against.
+pub fn system_glibc() -> Option<(PathBuf, GlibcVersion)> {
+    LIBC_PATHS.iter().map(Path::new).find_map(|p| {
+        let v = symbol_versions(p)?.defines_glibc()?;
+        Some((p.to_path_buf(), v))
+    })
+}
+
+/// A file of a build that needs a newer glibc than the system's.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Missing {
+    pub file: PathBuf,
+    pub needs: GlibcVersion,
+}
+
+/// ELF files in the top levels of `dir` (the executable and the libraries
+/// next to it) needing a glibc newer than `system`, the neediest first.
+pub fn missing_glibc(dir: &Path, system: &GlibcVersion) -> Vec<Missing> {
+    let mut out: Vec<Missing> = walkdir::WalkDir::new(dir)
+        .max_depth(3)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let needs = symbol_versions(e.path())?.needs_glibc()?;
+            (needs > *system).then(|| Missing {
+                file: e.path().to_path_buf(),
+                needs,
+            })
+        })
+        .collect();
+    out.sort_by(|a, b| b.needs.cmp(&a.needs).then_with(|| a.file.cmp(&b.file)));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// `int main() { puts(..); }` built against glibc 2.36: needs GLIBC_2.2.5
+    /// (puts) and GLIBC_2.34 (__libc_start_main)
+    const NEEDS_2_34: &[u8] = include_bytes!("../tests/fixtures/elf/needs_glibc_2.34");
+    /// A shared object named libc.so.6 defining GLIBC_2.2.5 and GLIBC_2.31
+    const LIBC_2_31: &[u8] = include_bytes!("../tests/fixtures/elf/libc_2.31.so.6");
+
+    fn v(s: &str) -> GlibcVersion {
+        GlibcVersion::from_node(&format!("GLIBC_{}", s)).unwrap()
+    }
+
+    #[test]
+    fn glibc_versions_compare_numerically() {
+        assert!(v("2.2.5") < v("2.17"));
+        assert!(v("2.17") < v("2.34"));
+        assert_eq!(v("2.2.5").to_string(), "2.2.5");
+        assert_eq!(GlibcVersion::from_node("GLIBC_PRIVATE"), None);
+        assert_eq!(GlibcVersion::from_node("GCC_3.0"), None);
+    }
+
+    #[test]
+    fn reads_needed_and_defined_version_nodes() {
+        let exe = symbol_versions_of(&mut Cursor::new(NEEDS_2_34)).unwrap();
+        assert!(exe.needed.contains(&"GLIBC_2.2.5".to_string()));
+        assert_eq!(exe.needs_glibc(), Some(v("2.34")));
+        assert!(exe.defined.is_empty());
+
+        let libc = symbol_versions_of(&mut Cursor::new(LIBC_2_31)).unwrap();
+        assert_eq!(libc.defined, ["libc.so.6", "GLIBC_2.2.5", "GLIBC_2.31"]);
+        assert_eq!(libc.defines_glibc(), Some(v("2.31")));
+
+        assert_eq!(
+            symbol_versions_of(&mut Cursor::new(&b"#!/bin/sh\necho hi\n"[..])),
+            None
+        );
+        // cut short: no panic, just nothing
+        assert_eq!(
+            symbol_versions_of(&mut Cursor::new(&NEEDS_2_34[..200])),
+            None
+        );
+    }
+
+    #[test]
+    fn missing_glibc_names_the_file_and_the_version() {
+        let td = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(td.path().join("Windsurf/bin")).unwrap();
+        std::fs::write(td.path().join("Windsurf/windsurf"), NEEDS_2_34).unwrap();
+        std::fs::write(td.path().join("Windsurf/bin/windsurf"), "#!/bin/sh\n").unwrap();
+
+        assert_eq!(
+            missing_glibc(td.path(), &v("2.31")),
+            [Missing {
+                file: td.path().join("Windsurf/windsurf"),
+                needs: v("2.34"),
+            }]
+        );
+        assert!(missing_glibc(td.path(), &v("2.34")).is_empty());
+        assert!(missing_glibc(td.path(), &v("2.39")).is_empty());
+    }
+}
@@ -1,6 +1,6 @@
-use anyhow::{Context, Result};
-use directories::ProjectDirs;
+use anyhow::{anyhow, bail, Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -11,18 +11,23 @@ pub struct ConfigPaths {
 }
 
 impl ConfigPaths {
-    pub fn from_override(override_path: Option<&str>) -> Self {
+    pub fn from_override(override_path: Option<&str>) -> Result<Self> {
         if let Some(p) = override_path {
-            let file = shellexpand::tilde(p).into_owned();
-            let file = PathBuf::from(file);
-            let dir = file.parent().unwrap_or_else(|| Path::new(".")).to_path_buf();
-            return Self { dir, file };
-        }
-        let proj = ProjectDirs::from("dev", "Windman", "windman")
-            .expect("cannot determine config dir");
-        let dir = proj.config_dir().to_path_buf();
+            let file = PathBuf::from(crate::paths::expand_tilde(p).context("--config")?);
+            let dir = file
+                .parent()
+                .unwrap_or_else(|| Path::new("."))
+                .to_path_buf();
+            return Ok(Self { dir, file });
+        }
+        let dir = crate::paths::project_dir(crate::paths::XdgDir::Config).ok_or_else(|| {
+            crate::paths::no_home(
+                "The config file",
+                "pass --config FILE (or WINDMAN_CONFIG_PATH)",
+            )
+        })?;
         let file = dir.join("windman.toml");
-        Self { dir, file }
+        Ok(Self { dir, file })
     }
 
     pub fn config_display(&self) -> String {
@@ -30,24 +35,257 @@ impl ConfigPaths {
     }
 }
 
+/// Where Windman installs: the invoking user's home, or system-wide (/opt, /usr/...).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    #[default]
+    User,
+    System,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InstallConfig {
     /// Default userland prefix for versioned installs
     pub prefix_dir: String, // e.g. "~/.local/opt/windsurf"
     /// Where the shim is written
-    pub bin_dir: String,    // e.g. "~/.local/bin"
-    /// Only "stable" for now
+    pub bin_dir: String, // e.g. "~/.local/bin"
+    /// File name of the shim inside bin_dir (also names the desktop entry)
+    #[serde(default = "default_bin_name")]
+    pub bin_name: String,
+    /// "user" (default) or "system" (/opt, /usr/local/bin, /usr/share; needs root)
+    #[serde(default)]
+    pub scope: Scope,
+    /// Release channel queried on update ("stable", "next", ...)
     pub channel: String,
-    /// Keep N newest versions (prune policy)
+    /// Keep N newest versions plus current (prune policy); 0 keeps only current
+    #[serde(deserialize_with = "deserialize_keep")]
     pub keep: usize,
+    /// Also keep the newest install of each major.minor series
+    #[serde(default)]
+    pub keep_per_minor: bool,
+    /// Versions matching any of these globs ("1.11.*") are never pruned
+    #[serde(default)]
+    pub keep_patterns: Vec<String>,
+    /// Versions current came from in the last N switches survive pruning, so
+    /// successive rollbacks have somewhere to go
+    #[serde(default = "default_protect_history")]
+    pub protect_history: usize,
+    /// Prune the oldest versions until the versions dir (trash included) fits
+    /// in this size ("3G", "500M"); unset = no limit
+    #[serde(default)]
+    pub max_total_size: Option<String>,
+    /// `install` and `update` prune after switching (`windman prune` works either way)
+    #[serde(default = "default_true")]
+    pub auto_prune: bool,
     /// Create/update desktop integration by default
     pub desktop_integration: bool,
+    /// Namespace versions and `current` per machine (`<prefix>/x86_64/...`,
+    /// `current-x86_64`) for a home shared between architectures
+    #[serde(default)]
+    pub arch_suffix: bool,
+    /// Move pruned/removed versions to <prefix>/.trash instead of deleting them
+    #[serde(default)]
+    pub prune_to_trash: bool,
+    /// `windman gc` deletes trash entries older than this many days
+    #[serde(default = "default_trash_grace_days")]
+    pub trash_grace_days: u64,
+    /// After install, hardlink files identical to the previous version's
+    #[serde(default)]
+    pub hardlink_dedup: bool,
+    /// `update` extracts while downloading instead of keeping the archive in the cache
+    #[serde(default)]
+    pub stream_extract: bool,
+    /// Run the new version once before switching current to it
+    #[serde(default = "default_true")]
+    pub smoke_test: bool,
+    /// Smoke test command run via `sh -c`; {exe} and {dir} are substituted.
+    /// Empty = `{exe} --version`
+    #[serde(default)]
+    pub smoke_test_cmd: String,
+    /// Kill the smoke test and fail after this many seconds
+    #[serde(default = "default_smoke_test_timeout")]
+    pub smoke_test_timeout_secs: u64,
+    /// Refuse a build needing a newer glibc than the system's (default: warn)
+    #[serde(default)]
+    pub strict_compat_check: bool,
+    /// Keep the verified archive in the version dir (`.artifact/`)
+    #[serde(default)]
+    pub keep_archive: bool,
+    /// Write the shim in bin_dir; false leaves bin_dir alone, e.g. when a
+    /// distro package provides `windsurf` (like --no-shim)
+    #[serde(default = "default_true")]
+    pub manage_shim: bool,
+    /// The shim prints a notice when the cached update check found a newer version
+    #[serde(default)]
+    pub shim_update_notice: bool,
+    /// `update` sends a desktop notification on success or failure (like --notify)
+    #[serde(default)]
+    pub notify: bool,
+    /// Refuse to switch current while Windsurf runs from another version
+    /// (like --require-closed; default: warn)
+    #[serde(default)]
+    pub block_if_running: bool,
+    /// What to do when the cache or prefix filesystem looks too small for a
+    /// download and its extraction
+    #[serde(default)]
+    pub space_check: SpaceCheck,
+    /// Expected extracted size as a multiple of the archive size (for the
+    /// free space check)
+    #[serde(default = "default_extract_space_factor")]
+    pub extract_space_factor: f64,
+    /// How an extracted version is made durable before it is moved into place
+    #[serde(default)]
+    pub extract_fsync: ExtractFsync,
+}
+
+impl InstallConfig {
+    /// `max_total_size` in bytes (None when unset; `validate` rejects bad values).
+    pub fn max_total_bytes(&self) -> Option<u64> {
+        self.max_total_size
+            .as_deref()
+            .and_then(|s| crate::util::parse_size(s).ok())
+    }
+
+    /// `trash_grace_days` as a duration (saturating; `validate` bounds it).
+    pub fn trash_grace(&self) -> std::time::Duration {
+        self.trash_grace_days
+            .checked_mul(86400)
+            .map_or(std::time::Duration::MAX, std::time::Duration::from_secs)
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_smoke_test_timeout() -> u64 {
+    30
+}
+
+fn default_trash_grace_days() -> u64 {
+    7
+}
+
+fn default_protect_history() -> usize {
+    2
+}
+
+fn default_extract_space_factor() -> f64 {
+    4.0
+}
+
+/// `install.space_check`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpaceCheck {
+    /// Refuse to download
+    #[default]
+    Abort,
+    /// Say so and download anyway
+    Warn,
+    /// Do not look
+    Off,
+}
+
+/// `install.extract_fsync`: what is flushed to disk before a new version is
+/// moved into place (and `current` can point at it).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtractFsync {
+    /// Nothing: fastest, but after a crash the version may hold empty files
+    None,
+    /// One `syncfs` of the prefix filesystem for all the files at once, then
+    /// the staging dir and, once renamed, the versions dir
+    #[default]
+    Dir,
+    /// Every file and directory of the version, one by one
+    All,
+}
+
+/// Channels the update API serves.
+pub const KNOWN_CHANNELS: &[&str] = &["stable", "next"];
+
+/// Upper bound for `install.keep`; anything above is almost certainly a typo.
+pub const MAX_KEEP: usize = 100;
+
+/// Upper bound for `install.trash_grace_days` (100 years).
+pub const MAX_TRASH_GRACE_DAYS: u64 = 36_500;
+
+fn deserialize_keep<'de, D>(d: D) -> std::result::Result<usize, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let v = i64::deserialize(d)?;
+    if v < 0 {
+        return Err(serde::de::Error::custom(format!(
+            "keep must be >= 0 (got {}); use 0 to keep only the current version",
+            v
+        )));
+    }
+    if v as u64 > MAX_KEEP as u64 {
+        return Err(serde::de::Error::custom(format!(
+            "keep = {} is unreasonably large (max {})",
+            v, MAX_KEEP
+        )));
+    }
+    Ok(v as usize)
+}
+
+fn default_bin_name() -> String {
+    "windsurf".to_string()
+}
+
+/// Named profile: any field set here overrides `[install]` when selected with --profile.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProfileConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix_dir: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bin_dir: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bin_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub desktop_integration: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkConfig {
-    /// Reserved for future proxy support
+    /// Send every request through proxy_url (else HTTP(S)_PROXY/ALL_PROXY apply)
     pub proxy_enabled: bool,
+    /// Proxy URL (http://, https://, socks5:// or socks5h://); must be set when proxy_enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_url: Option<String>,
+    /// How long `windman check` reuses the last remote answer
+    #[serde(default = "default_check_cache_ttl")]
+    pub check_cache_ttl_minutes: u64,
+    /// When the update API is down, install from the download link on the releases page
+    #[serde(default)]
+    pub allow_html_fallback: bool,
+    /// User-Agent of every request (default: windman/<version>)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// Headers added to every request (e.g. a token a corporate proxy requires)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra_headers: BTreeMap<String, String>,
+    /// PEM file of extra root certificates to trust, e.g. the CA of a
+    /// TLS-intercepting proxy (default: SSL_CERT_FILE, if set)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<String>,
+    /// Check no HTTPS certificate at all (warned about on every run)
+    #[serde(default)]
+    pub danger_accept_invalid_certs: bool,
+}
+
+/// What `network.proxy_url` may start with.
+const PROXY_SCHEMES: &[&str] = &["http", "https", "socks5", "socks5h"];
+
+fn default_check_cache_ttl() -> u64 {
+    60
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -56,12 +294,111 @@ pub struct Config {
     #[serde(default)]
     pub changelog: ChangelogConfig,
     pub network: NetworkConfig,
+    #[serde(default)]
+    pub security: SecurityConfig,
+    #[serde(default)]
+    pub desktop: DesktopConfig,
+    #[serde(default)]
+    pub paths: PathsConfig,
+    #[serde(default)]
+    pub cli: CliConfig,
     // NOTE: telemetry removed (standalone, no tracking).
+    /// Named install profiles (e.g. [profiles.next])
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, ProfileConfig>,
+}
+
+/// `[desktop]`: customizes the generated .desktop entry. Empty = historical entry.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct DesktopConfig {
+    /// Display name (profile name is still appended in parentheses)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Appended to the Exec line before %U (e.g. "--ozone-platform-hint=auto")
+    #[serde(default)]
+    pub exec_args: String,
+    /// Raw `Key=Value` lines appended to [Desktop Entry]
+    #[serde(default)]
+    pub extra_entries: Vec<String>,
+    /// Desktop actions (right-click menu entries)
+    #[serde(default)]
+    pub actions: Vec<DesktopAction>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DesktopAction {
+    pub name: String,
+    /// Arguments appended to the Exec line for this action
+    #[serde(default)]
+    pub exec: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SecurityConfig {
+    /// Require a valid minisign signature (<url>.minisig or <url>.sig) before installing
+    #[serde(default)]
+    pub signature_verification: bool,
+    /// Minisign public key (the base64 "RW..." line of the .pub file)
+    #[serde(default)]
+    pub public_key: String,
+}
+
+/// `[paths]`: where windman keeps its own files.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PathsConfig {
+    /// Download and check cache (default ~/.cache/windman, /var/cache/windman in
+    /// system scope); WINDMAN_CACHE_DIR overrides it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_dir: Option<String>,
+}
+
+/// `[cli]`: how the command line is read.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct CliConfig {
+    /// Commands of your own: `latest = "update --dry-run"` makes `windman latest
+    /// ARGS` run `windman update --dry-run ARGS` (words split on whitespace)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, String>,
+}
+
+/// `[changelog]`: where release notes come from and how `changelog` prints them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChangelogConfig {
-    // reserved for future (e.g., show delta)
+    /// Releases page: HTML with one `<h2>X.Y.Z</h2>` section per version
+    /// (also read by `notes` and when installing)
+    #[serde(default = "default_changelog_source")]
+    pub source_url: String,
+    /// How `changelog` prints the notes (`--raw` prints the Markdown as is)
+    #[serde(default)]
+    pub render: ChangelogRender,
+    /// `changelog` shows at most this many versions, newest first (unset = all)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_entries: Option<usize>,
+}
+
+impl Default for ChangelogConfig {
+    fn default() -> Self {
+        Self {
+            source_url: default_changelog_source(),
+            render: ChangelogRender::default(),
+            max_entries: None,
+        }
+    }
+}
+
+fn default_changelog_source() -> String {
+    crate::remote::releases_page_url().to_string()
+}
+
+/// `changelog.render`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangelogRender {
+    /// Headings and bold text styled for the terminal, `•` bullets
+    #[default]
+    Markdown,
+    /// Formatting stripped: no `#`, `**` or backticks
+    Plain,
 }
 
 impl Default for Config {
@@ -70,30 +407,388 @@ impl Default for Config {
             install: InstallConfig {
                 prefix_dir: "~/.local/opt/windsurf".to_string(),
                 bin_dir: "~/.local/bin".to_string(),
+                bin_name: default_bin_name(),
+                scope: Scope::User,
                 channel: "stable".to_string(),
                 keep: 2,
+                keep_per_minor: false,
+                keep_patterns: Vec::new(),
+                protect_history: default_protect_history(),
+                max_total_size: None,
+                auto_prune: true,
                 desktop_integration: true,
+                arch_suffix: false,
+                prune_to_trash: false,
+                trash_grace_days: default_trash_grace_days(),
+                hardlink_dedup: false,
+                stream_extract: false,
+                smoke_test: true,
+                smoke_test_cmd: String::new(),
+                smoke_test_timeout_secs: default_smoke_test_timeout(),
+                strict_compat_check: false,
+                keep_archive: false,
+                manage_shim: true,
+                shim_update_notice: false,
+                notify: false,
+                block_if_running: false,
+                space_check: SpaceCheck::Abort,
+                extract_space_factor: default_extract_space_factor(),
+                extract_fsync: ExtractFsync::Dir,
             },
             changelog: ChangelogConfig::default(),
             network: NetworkConfig {
                 proxy_enabled: false,
+                proxy_url: None,
+                check_cache_ttl_minutes: default_check_cache_ttl(),
+                allow_html_fallback: false,
+                user_agent: None,
+                extra_headers: BTreeMap::new(),
+                ca_bundle: None,
+                danger_accept_invalid_certs: false,
             },
+            security: SecurityConfig::default(),
+            desktop: DesktopConfig::default(),
+            paths: PathsConfig::default(),
+            cli: CliConfig::default(),
+            profiles: BTreeMap::new(),
         }
     }
 }
 
+/// Keys renamed since early builds: (old dotted key, current dotted key).
+const RENAMED_KEYS: &[(&str, &str)] = &[
+    ("keep_versions", "install.keep"),
+    ("install.keep_versions", "install.keep"),
+];
+
+/// Keys and sections that no longer do anything, and why.
+const DROPPED_KEYS: &[(&str, &str)] = &[("telemetry", "windman collects no telemetry")];
+
+/// A legacy key found in a config file and what loading did with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Migration {
+    /// The value now lives at `to`
+    Renamed { from: String, to: String },
+    /// Both spellings are set: `to` wins, `from` is ignored
+    Shadowed { from: String, to: String },
+    /// No longer has any effect
+    Dropped { key: String, why: &'static str },
+}
+
+impl std::fmt::Display for Migration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Migration::Renamed { from, to } => {
+                write!(f, "`{}` is now `{}` (its value is used)", from, to)
+            }
+            Migration::Shadowed { from, to } => {
+                write!(f, "`{}` is ignored: `{}` is also set", from, to)
+            }
+            Migration::Dropped { key, why } => write!(f, "`{}` is no longer used ({})", key, why),
+        }
+    }
+}
+
+/// What reading a config file turned up besides the config itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Notes {
+    pub migrations: Vec<Migration>,
+    /// Dotted keys no setting reads (typos, keys of other builds)
+    pub unknown: Vec<String>,
+}
+
+impl Notes {
+    pub fn is_empty(&self) -> bool {
+        self.migrations.is_empty() && self.unknown.is_empty()
+    }
+
+    /// One line per legacy or unknown key.
+    pub fn warnings(&self) -> Vec<String> {
+        let unknown = self
+            .unknown
+            .iter()
+            .map(|k| format!("unknown key `{}` is ignored", k));
+        self.migrations
+            .iter()
+            .map(|m| m.to_string())
+            .chain(unknown)
+            .collect()
+    }
+}
+
 impl Config {
-    pub fn load_or_default(paths: &ConfigPaths) -> Result<Self> {
-        if paths.file.exists() {
-            let s = fs::read_to_string(&paths.file)
-                .with_context(|| format!("reading {}", paths.config_display()))?;
-            // Unknown fields (e.g., legacy [telemetry]) are ignored by default.
-            let cfg: Config = toml::from_str(&s)
-                .with_context(|| format!("parsing {}", paths.config_display()))?;
-            Ok(cfg)
-        } else {
-            Ok(Self::default())
+    /// The config file, or the defaults when there is none, and whether the
+    /// file was found. Legacy keys are mapped to their current setting; they and
+    /// unknown keys are reported on stderr (once per run) rather than rejected.
+    pub fn load_or_default(paths: &ConfigPaths) -> Result<(Self, bool)> {
+        static WARNED: std::sync::Once = std::sync::Once::new();
+        let (cfg, notes) = Self::load(paths)?;
+        if !notes.is_empty() {
+            WARNED.call_once(|| {
+                for w in notes.warnings() {
+                    crate::ui::warn(format_args!("{}: {}", paths.config_display(), w));
+                }
+                crate::ui::warn(
+                    "run `windman config migrate` to rewrite it with the current settings",
+                );
+            });
+        }
+        Ok((cfg, paths.file.exists()))
+    }
+
+    /// `load_or_default` without the warnings: they are returned instead.
+    pub fn load(paths: &ConfigPaths) -> Result<(Self, Notes)> {
+        if paths.file.is_dir() {
+            bail!(
+                "config file {} is a directory; --config takes the path of a TOML file",
+                paths.config_display()
+            );
+        }
+        if !paths.file.exists() {
+            return Ok((Self::default(), Notes::default()));
         }
+        let s = fs::read_to_string(&paths.file)
+            .with_context(|| format!("reading {}", paths.config_display()))?;
+        Self::parse(&s).map_err(|problems| {
+            anyhow!(
+                "invalid config {}:\n  - {}",
+                paths.config_display(),
+                problems.join("\n  - ")
+            )
+        })
+    }
+
+    /// Rewrite the config file with the current settings, every one spelled
+    /// out: legacy keys moved or dropped, unknown keys dropped. The original is
+    /// kept as `<file>.bak`. A file with nothing to migrate is left alone; the
+    /// returned notes say what changed.
+    pub fn migrate_file(paths: &ConfigPaths) -> Result<Notes> {
+        let (cfg, notes) = Self::load(paths)?;
+        if notes.is_empty() {
+            return Ok(notes);
+        }
+        let backup = backup_path(&paths.file);
+        fs::copy(&paths.file, &backup)
+            .with_context(|| format!("backing up to {}", backup.display()))?;
+        fs::write(&paths.file, toml::to_string_pretty(&cfg)?)
+            .with_context(|| format!("writing {}", paths.config_display()))?;
+        Ok(notes)
+    }
+
+    /// Parse and validate a config file. On failure, returns every problem found,
+    /// each prefixed with the setting it is about (e.g. `install.keep: ...`).
+    pub fn from_toml(s: &str) -> std::result::Result<Self, Vec<String>> {
+        Self::parse(s).map(|(cfg, _)| cfg)
+    }
+
+    /// `from_toml`, with the legacy and unknown keys it found.
+    pub fn parse(s: &str) -> std::result::Result<(Self, Notes), Vec<String>> {
+        let mut table: toml::Table =
+            toml::from_str(s).map_err(|e| vec![e.to_string().trim_end().to_string()])?;
+        let notes = Notes {
+            migrations: migrate_table(&mut table),
+            unknown: unknown_keys(&table),
+        };
+        Self::from_table(table).map(|cfg| (cfg, notes))
+    }
+
+    fn from_table(mut table: toml::Table) -> std::result::Result<Self, Vec<String>> {
+        let defaults = toml::Table::try_from(Config::default()).expect("default config serializes");
+
+        // serde stops at the first bad value: report it, put the default in its
+        // place and try again, so one run lists every type error
+        let mut problems = Vec::new();
+        let mut seen = Vec::new();
+        let cfg = loop {
+            let err = match table.clone().try_into::<Config>() {
+                Ok(cfg) => break Some(cfg),
+                Err(e) => e.to_string(),
+            };
+            let (what, key) = match err.trim_end().split_once("\nin `") {
+                Some((what, key)) => (
+                    what.to_string(),
+                    Some(key.trim_end_matches('`').to_string()),
+                ),
+                None => (err.trim_end().to_string(), None),
+            };
+            let key = match (what.strip_prefix("missing field `"), key) {
+                (Some(field), parent) => {
+                    let field = field.trim_end_matches('`');
+                    Some(parent.map_or(field.to_string(), |p| format!("{}.{}", p, field)))
+                }
+                (None, key) => key,
+            };
+            let Some(key) = key.filter(|k| !seen.contains(k)) else {
+                problems.push(what);
+                break None;
+            };
+            problems.push(format!("{}: {}{}", key, what, example(&defaults, &key)));
+            replace_with_default(&mut table, &defaults, &key);
+            seen.push(key);
+        };
+
+        if let Some(cfg) = &cfg {
+            problems.extend(cfg.validate());
+        }
+        match cfg {
+            Some(cfg) if problems.is_empty() => Ok(cfg),
+            _ => Err(problems),
+        }
+    }
+
+    /// Checks serde cannot express. Returns one message per problem.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let i = &self.install;
+        check_keep("install.keep", i.keep, &mut problems);
+        for p in &i.keep_patterns {
+            if p.trim().is_empty() || p.trim_matches('*').is_empty() {
+                problems.push(format!(
+                    "install.keep_patterns: '{}' would keep every version (use keep instead)",
+                    p
+                ));
+            }
+        }
+        if let Some(raw) = &i.max_total_size {
+            match crate::util::parse_size(raw) {
+                Ok(0) => problems.push("install.max_total_size: must be > 0".to_string()),
+                Ok(_) => {}
+                Err(e) => problems.push(format!("install.max_total_size: {}", e)),
+            }
+        }
+        if !(i.extract_space_factor >= 1.0 && i.extract_space_factor.is_finite()) {
+            problems.push(format!(
+                "install.extract_space_factor: {} is not a factor >= 1",
+                i.extract_space_factor
+            ));
+        }
+        check_channel("install.channel", &i.channel, &mut problems);
+        check_dirs("install", &i.prefix_dir, &i.bin_dir, &mut problems);
+        if let Some(raw) = &self.paths.cache_dir {
+            match crate::paths::expand_vars(raw) {
+                Ok(p) if Path::new(&p).is_absolute() => {}
+                Ok(_) => problems.push(format!(
+                    "paths.cache_dir: '{}' is not an absolute path (use e.g. \"~/...\" or \"/scratch/...\")",
+                    raw
+                )),
+                Err(e) => problems.push(format!("paths.cache_dir: {}", e)),
+            }
+        }
+        if i.trash_grace_days > MAX_TRASH_GRACE_DAYS {
+            problems.push(format!(
+                "install.trash_grace_days: {} is unreasonably large (0 to {})",
+                i.trash_grace_days, MAX_TRASH_GRACE_DAYS
+            ));
+        }
+        if i.smoke_test_timeout_secs == 0 {
+            problems.push("install.smoke_test_timeout_secs: must be > 0 (seconds)".to_string());
+        }
+
+        let source = &self.changelog.source_url;
+        match reqwest::Url::parse(source) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+            Ok(url) => problems.push(format!(
+                "changelog.source_url: unsupported scheme '{}' (use http or https)",
+                url.scheme()
+            )),
+            Err(e) => problems.push(format!(
+                "changelog.source_url: '{}' is not a valid URL ({})",
+                source, e
+            )),
+        }
+        if self.changelog.max_entries == Some(0) {
+            problems
+                .push("changelog.max_entries: must be > 0 (leave it unset for all)".to_string());
+        }
+
+        if self.network.proxy_enabled {
+            match self.network.proxy_url.as_deref().map(str::trim) {
+                None | Some("") => problems.push(
+                    "network.proxy_url: required when network.proxy_enabled = true".to_string(),
+                ),
+                Some(u) => match reqwest::Url::parse(u) {
+                    Ok(url) if PROXY_SCHEMES.contains(&url.scheme()) => {}
+                    Ok(url) => problems.push(format!(
+                        "network.proxy_url: unsupported scheme '{}' (use http, https, socks5 or socks5h)",
+                        url.scheme()
+                    )),
+                    Err(e) => problems.push(format!(
+                        "network.proxy_url: '{}' is not a valid URL ({})",
+                        u, e
+                    )),
+                },
+            }
+        }
+
+        if let Err(e) = crate::http::default_headers(&self.network) {
+            problems.push(format!("network: {}", e));
+        }
+        if let Err(e) = crate::http::trust_from(&self.network, None) {
+            problems.push(format!("{:#}", e));
+        }
+
+        if !self.cli.aliases.is_empty() {
+            let grammar =
+                crate::alias::Grammar::of(&<crate::cli::Cli as clap::CommandFactory>::command());
+            for (name, expansion) in &self.cli.aliases {
+                if let Some(why) = crate::alias::invalid(name, expansion, &grammar) {
+                    problems.push(format!("cli.aliases.{}: {}", name, why));
+                }
+            }
+        }
+
+        for (name, p) in &self.profiles {
+            let at = format!("profiles.{}", name);
+            if let Some(keep) = p.keep {
+                check_keep(&format!("{}.keep", at), keep, &mut problems);
+            }
+            if let Some(channel) = &p.channel {
+                check_channel(&format!("{}.channel", at), channel, &mut problems);
+            }
+            // same derivation as apply_profile
+            let prefix = p
+                .prefix_dir
+                .clone()
+                .unwrap_or_else(|| format!("{}-{}", i.prefix_dir, name));
+            let bin = p.bin_dir.as_deref().unwrap_or(&i.bin_dir);
+            check_dirs(&at, &prefix, bin, &mut problems);
+        }
+        problems
+    }
+
+    /// Merge the named profile over `[install]`. Errors if the profile is not defined.
+    pub fn apply_profile(&mut self, name: &str) -> Result<()> {
+        let Some(p) = self.profiles.get(name).cloned() else {
+            let known: Vec<&str> = self.profiles.keys().map(|k| k.as_str()).collect();
+            bail!(
+                "unknown profile '{}'. Defined profiles: {}",
+                name,
+                if known.is_empty() {
+                    "<none>".to_string()
+                } else {
+                    known.join(", ")
+                }
+            );
+        };
+        // Unset prefix/bin_name derive from the profile name so a profile never
+        // clobbers the default install, shim or desktop entry.
+        self.install.prefix_dir = p
+            .prefix_dir
+            .unwrap_or_else(|| format!("{}-{}", self.install.prefix_dir, name));
+        if let Some(v) = p.bin_dir {
+            self.install.bin_dir = v;
+        }
+        self.install.bin_name = p.bin_name.unwrap_or_else(|| format!("windsurf-{}", name));
+        if let Some(v) = p.channel {
+            self.install.channel = v;
+        }
+        if let Some(v) = p.keep {
+            self.install.keep = v;
+        }
+        if let Some(v) = p.desktop_integration {
+            self.install.desktop_integration = v;
+        }
+        Ok(())
     }
 
     pub fn save_if_missing(&self, paths: &ConfigPaths) -> Result<()> {
@@ -106,24 +801,970 @@ impl Config {
             out.push_str("[install]\n");
             out.push_str(&format!("prefix_dir = \"{}\"\n", self.install.prefix_dir));
             out.push_str(&format!("bin_dir = \"{}\"\n", self.install.bin_dir));
+            out.push_str(&format!("bin_name = \"{}\"\n", self.install.bin_name));
+            out.push_str(match self.install.scope {
+                Scope::User => "scope = \"user\"\n",
+                Scope::System => "scope = \"system\"\n",
+            });
             out.push_str(&format!("channel = \"{}\"\n", self.install.channel));
             out.push_str(&format!("keep = {}\n", self.install.keep));
             out.push_str(&format!(
-                "desktop_integration = {}\n\n",
+                "keep_per_minor = {}\n",
+                self.install.keep_per_minor
+            ));
+            out.push_str(&format!(
+                "keep_patterns = [{}]\n",
+                self.install
+                    .keep_patterns
+                    .iter()
+                    .map(|p| format!("\"{}\"", p))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+            out.push_str(&format!(
+                "protect_history = {}\n",
+                self.install.protect_history
+            ));
+            if let Some(size) = &self.install.max_total_size {
+                out.push_str(&format!("max_total_size = \"{}\"\n", size));
+            }
+            out.push_str(&format!("auto_prune = {}\n", self.install.auto_prune));
+            out.push_str(&format!("arch_suffix = {}\n", self.install.arch_suffix));
+            out.push_str(&format!(
+                "desktop_integration = {}\n",
                 self.install.desktop_integration
             ));
+            out.push_str(&format!(
+                "prune_to_trash = {}\n",
+                self.install.prune_to_trash
+            ));
+            out.push_str(&format!(
+                "trash_grace_days = {}\n",
+                self.install.trash_grace_days
+            ));
+            out.push_str(&format!(
+                "hardlink_dedup = {}\n",
+                self.install.hardlink_dedup
+            ));
+            out.push_str(&format!(
+                "stream_extract = {}\n",
+                self.install.stream_extract
+            ));
+            out.push_str(&format!("smoke_test = {}\n", self.install.smoke_test));
+            out.push_str(&format!(
+                "smoke_test_cmd = \"{}\"\n",
+                self.install.smoke_test_cmd
+            ));
+            out.push_str(&format!(
+                "smoke_test_timeout_secs = {}\n",
+                self.install.smoke_test_timeout_secs
+            ));
+            out.push_str(&format!(
+                "strict_compat_check = {}\n",
+                self.install.strict_compat_check
+            ));
+            out.push_str(&format!("keep_archive = {}\n", self.install.keep_archive));
+            out.push_str(&format!("manage_shim = {}\n", self.install.manage_shim));
+            out.push_str(&format!(
+                "shim_update_notice = {}\n",
+                self.install.shim_update_notice
+            ));
+            out.push_str(&format!("notify = {}\n", self.install.notify));
+            out.push_str(&format!(
+                "block_if_running = {}\n",
+                self.install.block_if_running
+            ));
+            out.push_str(match self.install.space_check {
+                SpaceCheck::Abort => "space_check = \"abort\"\n",
+                SpaceCheck::Warn => "space_check = \"warn\"\n",
+                SpaceCheck::Off => "space_check = \"off\"\n",
+            });
+            out.push_str(&format!(
+                "extract_space_factor = {:?}\n",
+                self.install.extract_space_factor
+            ));
+            out.push_str(match self.install.extract_fsync {
+                ExtractFsync::None => "extract_fsync = \"none\"\n\n",
+                ExtractFsync::Dir => "extract_fsync = \"dir\"\n\n",
+                ExtractFsync::All => "extract_fsync = \"all\"\n\n",
+            });
 
-            out.push_str("[changelog]\n\n");
+            out.push_str("[changelog]\n");
+            out.push_str(&format!("source_url = \"{}\"\n", self.changelog.source_url));
+            out.push_str(match self.changelog.render {
+                ChangelogRender::Markdown => "render = \"markdown\"\n\n",
+                ChangelogRender::Plain => "render = \"plain\"\n\n",
+            });
 
             out.push_str("[network]\n");
+            out.push_str(&format!("proxy_enabled = {}\n", self.network.proxy_enabled));
+            out.push_str(&format!(
+                "check_cache_ttl_minutes = {}\n",
+                self.network.check_cache_ttl_minutes
+            ));
+            out.push_str(&format!(
+                "allow_html_fallback = {}\n",
+                self.network.allow_html_fallback
+            ));
             out.push_str(&format!(
-                "proxy_enabled = {}\n",
-                self.network.proxy_enabled
+                "danger_accept_invalid_certs = {}\n",
+                self.network.danger_accept_invalid_certs
             ));
 
+            out.push_str("\n[security]\n");
+            out.push_str(&format!(
+                "signature_verification = {}\n",
+                self.security.signature_verification
+            ));
+            out.push_str(&format!("public_key = \"{}\"\n", self.security.public_key));
+
             fs::write(&paths.file, out)
                 .with_context(|| format!("writing {}", paths.config_display()))?;
         }
         Ok(())
     }
+
+    /// Set `prefix_dir` in `[install]` (or `[profiles.NAME]`), editing the file
+    /// line-wise so comments and every other setting survive.
+    pub fn set_prefix_dir(paths: &ConfigPaths, profile: Option<&str>, prefix: &str) -> Result<()> {
+        let section = profile.map_or("install".to_string(), |p| format!("profiles.{}", p));
+        Self::set_string(paths, &section, "prefix_dir", prefix)
+    }
+
+    /// Set `cache_dir` in `[paths]`, like `set_prefix_dir`.
+    pub fn set_cache_dir(paths: &ConfigPaths, dir: &str) -> Result<()> {
+        Self::set_string(paths, "paths", "cache_dir", dir)
+    }
+
+    /// Set a string setting in the file (created from the defaults if missing),
+    /// refusing a result that would not load.
+    fn set_string(paths: &ConfigPaths, section: &str, key: &str, value: &str) -> Result<()> {
+        Config::default().save_if_missing(paths)?;
+        let s = fs::read_to_string(&paths.file)
+            .with_context(|| format!("reading {}", paths.config_display()))?;
+        let value = toml::Value::String(value.to_string()).to_string();
+        let out = set_key(&s, section, key, &value);
+        Self::from_toml(&out).map_err(|problems| {
+            anyhow!(
+                "not updating {}, the result would be invalid:\n  - {}",
+                paths.config_display(),
+                problems.join("\n  - ")
+            )
+        })?;
+        fs::write(&paths.file, out).with_context(|| format!("writing {}", paths.config_display()))
+    }
+}
+
+/// `text` with `key = value` set in `[section]`: replaced in place, else added
+/// right after the header, else in a new section at the end.
+fn set_key(text: &str, section: &str, key: &str, value: &str) -> String {
+    let line = format!("{} = {}", key, value);
+    let mut out: Vec<String> = Vec::new();
+    let (mut in_section, mut header_at, mut done) = (false, None, false);
+    for l in text.lines() {
+        let t = l.trim();
+        if t.starts_with('[') && !t.starts_with("[[") {
+            in_section = t.trim_start_matches('[').trim_end_matches(']').trim() == section;
+            if in_section && header_at.is_none() {
+                header_at = Some(out.len());
+            }
+        } else if in_section && !done {
+            if let Some(rest) = t.strip_prefix(key) {
+                if rest.trim_start().starts_with('=') {
+                    out.push(line.clone());
+                    done = true;
+                    continue;
+                }
+            }
+        }
+        out.push(l.to_string());
+    }
+    match (done, header_at) {
+        (true, _) => {}
+        (false, Some(i)) => out.insert(i + 1, line),
+        (false, None) => {
+            if out.last().is_some_and(|l| !l.trim().is_empty()) {
+                out.push(String::new());
+            }
+            out.push(format!("[{}]", section));
+            out.push(line);
+        }
+    }
+    let mut s = out.join("\n");
+    s.push('\n');
+    s
+}
+
+fn check_keep(key: &str, keep: usize, problems: &mut Vec<String>) {
+    if keep > MAX_KEEP {
+        problems.push(format!(
+            "{}: {} is unreasonably large (0 to {})",
+            key, keep, MAX_KEEP
+        ));
+    }
+}
+
+fn check_channel(key: &str, channel: &str, problems: &mut Vec<String>) {
+    if !KNOWN_CHANNELS.contains(&channel) {
+        problems.push(format!(
+            "{}: unknown channel '{}' (known: {})",
+            key,
+            channel,
+            KNOWN_CHANNELS.join(", ")
+        ));
+    }
+}
+
+/// prefix_dir and bin_dir must be absolute once ~ and variables are expanded, and distinct.
+fn check_dirs(section: &str, prefix_dir: &str, bin_dir: &str, problems: &mut Vec<String>) {
+    // without HOME, ~ is the environment's problem, not the config's: it only
+    // matters when no --prefix/--bin-dir replaces the path (resolve_paths says so)
+    if crate::paths::lacks_home(prefix_dir) || crate::paths::lacks_home(bin_dir) {
+        return;
+    }
+    let mut expand = |name: &str, raw: &str| match crate::paths::expand_vars(raw) {
+        Ok(p) => Some(PathBuf::from(p)),
+        Err(e) => {
+            problems.push(format!("{}.{}: {}", section, name, e));
+            None
+        }
+    };
+    let (Some(prefix), Some(bin)) = (expand("prefix_dir", prefix_dir), expand("bin_dir", bin_dir))
+    else {
+        return;
+    };
+    for (name, raw, path) in [
+        ("prefix_dir", prefix_dir, &prefix),
+        ("bin_dir", bin_dir, &bin),
+    ] {
+        if !path.is_absolute() {
+            problems.push(format!(
+                "{}.{}: '{}' is not an absolute path (use e.g. \"~/...\" or \"/opt/...\")",
+                section, name, raw
+            ));
+        }
+    }
+    if prefix == bin {
+        problems.push(format!(
+            "{}.bin_dir: must differ from prefix_dir ('{}')",
+            section, bin_dir
+        ));
+    }
+}
+
+fn lookup<'a>(table: &'a toml::Table, key: &str) -> Option<&'a toml::Value> {
+    let (parent, last) = match key.rsplit_once('.') {
+        Some((parent, last)) => (Some(parent), last),
+        None => (None, key),
+    };
+    let parent = match parent {
+        Some(p) => lookup(table, p)?.as_table()?,
+        None => table,
+    };
+    parent.get(last)
+}
+
+/// " (e.g. keep = 2)" from the default config, or "" when there is no default.
+fn example(defaults: &toml::Table, key: &str) -> String {
+    match lookup(defaults, key) {
+        Some(v) if !v.is_table() => {
+            format!(" (e.g. {} = {})", key.rsplit('.').next().unwrap_or(key), v)
+        }
+        _ => String::new(),
+    }
+}
+
+/// `<file>.bak`, next to the config file.
+pub fn backup_path(file: &Path) -> PathBuf {
+    let mut name = file.as_os_str().to_owned();
+    name.push(".bak");
+    PathBuf::from(name)
+}
+
+/// Move renamed keys of `table` to their current place and drop obsolete ones.
+pub fn migrate_table(table: &mut toml::Table) -> Vec<Migration> {
+    let mut done = Vec::new();
+    for (from, to) in RENAMED_KEYS {
+        let Some(value) = remove(table, from) else {
+            continue;
+        };
+        let (from, to) = (from.to_string(), to.to_string());
+        if lookup(table, &to).is_some() {
+            done.push(Migration::Shadowed { from, to });
+        } else {
+            let (parent, last) = to.rsplit_once('.').unwrap_or(("", &to));
+            table_mut(table, parent).insert(last.to_string(), value);
+            done.push(Migration::Renamed { from, to });
+        }
+    }
+    for (key, why) in DROPPED_KEYS {
+        if remove(table, key).is_some() {
+            done.push(Migration::Dropped {
+                key: key.to_string(),
+                why,
+            });
+        }
+    }
+    done
+}
+
+/// Dotted keys of `table` that no setting reads.
+pub fn unknown_keys(table: &toml::Table) -> Vec<String> {
+    let mut out = Vec::new();
+    collect_unknown(table, &known_keys(), "", &mut out);
+    out.sort();
+    out.dedup();
+    out
+}
+
+/// Every setting as a table: the defaults, with a value for each optional one
+/// and a sample profile named "*".
+fn known_keys() -> toml::Table {
+    let mut all = Config::default();
+    all.install.max_total_size = Some(String::new());
+    all.changelog.max_entries = Some(0);
+    all.network.proxy_url = Some(String::new());
+    all.network.user_agent = Some(String::new());
+    all.network.ca_bundle = Some(String::new());
+    all.network
+        .extra_headers
+        .insert("*".to_string(), String::new());
+    all.desktop.name = Some(String::new());
+    all.paths.cache_dir = Some(String::new());
+    all.cli.aliases.insert("*".to_string(), String::new());
+    all.desktop.actions.push(DesktopAction {
+        name: String::new(),
+        exec: String::new(),
+    });
+    let some = Some(String::new());
+    all.profiles.insert(
+        "*".to_string(),
+        ProfileConfig {
+            prefix_dir: some.clone(),
+            bin_dir: some.clone(),
+            bin_name: some.clone(),
+            channel: some,
+            keep: Some(0),
+            desktop_integration: Some(false),
+        },
+    );
+    toml::Table::try_from(all).expect("config serializes")
+}
+
+fn collect_unknown(table: &toml::Table, known: &toml::Table, prefix: &str, out: &mut Vec<String>) {
+    for (k, v) in table {
+        let key = format!("{}{}", prefix, k);
+        // any profile, header or alias name goes: checked against the sample one
+        let schema = match prefix {
+            "profiles." | "network.extra_headers." | "cli.aliases." => known.get("*"),
+            _ => known.get(k),
+        };
+        match (v, schema) {
+            (_, None) => out.push(key),
+            (toml::Value::Table(t), Some(toml::Value::Table(s))) => {
+                collect_unknown(t, s, &format!("{}.", key), out)
+            }
+            (toml::Value::Array(items), Some(toml::Value::Array(s))) => {
+                if let Some(toml::Value::Table(s)) = s.first() {
+                    for t in items.iter().filter_map(|i| i.as_table()) {
+                        collect_unknown(t, s, &format!("{}.", key), out);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Take the value at dotted `key` out of `table`.
+fn remove(table: &mut toml::Table, key: &str) -> Option<toml::Value> {
+    match key.split_once('.') {
+        Some((first, rest)) => remove(table.get_mut(first)?.as_table_mut()?, rest),
+        None => table.remove(key),
+    }
+}
+
+/// The table at dotted `key` ("" = `table`), created (over any other value) as needed.
+fn table_mut<'a>(table: &'a mut toml::Table, key: &str) -> &'a mut toml::Table {
+    let mut node = table;
+    for part in key.split('.').filter(|p| !p.is_empty()) {
+        let entry = node
+            .entry(part.to_string())
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        if !entry.is_table() {
+            *entry = toml::Value::Table(toml::Table::new());
+        }
+        node = entry.as_table_mut().unwrap();
+    }
+    node
+}
+
+/// Put the default value at `key` (or drop the key when there is none, e.g. in a profile).
+fn replace_with_default(table: &mut toml::Table, defaults: &toml::Table, key: &str) {
+    let (parent, last) = key.rsplit_once('.').unwrap_or(("", key));
+    let node = table_mut(table, parent);
+    match lookup(defaults, key) {
+        Some(v) => {
+            node.insert(last.to_string(), v.clone());
+        }
+        None => {
+            node.remove(last);
+        }
+    }
+}
+
+/// Where the effective value of a setting comes from (`config diff`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Origin {
+    /// The config file
+    File,
+    /// The `[profiles.NAME]` picked with `--profile`
+    Profile,
+    /// An environment variable (WINDMAN_CACHE_DIR)
+    Env,
+    /// A command-line flag (`--prefix`, `--bin-dir`, `--system`)
+    Cli,
+}
+
+impl std::fmt::Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Origin::File => "file",
+            Origin::Profile => "profile",
+            Origin::Env => "env",
+            Origin::Cli => "cli",
+        })
+    }
+}
+
+/// A setting whose effective value is not the built-in default.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Changed {
+    /// Dotted key, e.g. `install.keep`
+    pub key: String,
+    pub value: toml::Value,
+    /// None for settings without a default (`network.proxy_url`, a profile)
+    pub default: Option<toml::Value>,
+    pub source: Origin,
+}
+
+/// Every setting of `cfg` by dotted key. Tables are walked down to their
+/// values; arrays (`install.keep_patterns`, `desktop.actions`) count as one value.
+pub fn flatten(cfg: &Config) -> BTreeMap<String, toml::Value> {
+    fn walk(table: &toml::Table, prefix: &str, out: &mut BTreeMap<String, toml::Value>) {
+        for (k, v) in table {
+            let key = format!("{}{}", prefix, k);
+            match v {
+                toml::Value::Table(t) => walk(t, &format!("{}.", key), out),
+                _ => {
+                    out.insert(key, v.clone());
+                }
+            }
+        }
+    }
+    let mut out = BTreeMap::new();
+    walk(
+        &toml::Table::try_from(cfg).expect("config serializes"),
+        "",
+        &mut out,
+    );
+    out
+}
+
+/// The settings of `effective` that differ from `Config::default()`, compared
+/// value by value on the serialized trees, sorted by key. `source` says where
+/// each one was set.
+pub fn diff(effective: &Config, source: impl Fn(&str) -> Origin) -> Vec<Changed> {
+    let defaults = flatten(&Config::default());
+    flatten(effective)
+        .into_iter()
+        .filter(|(k, v)| defaults.get(k) != Some(v))
+        .map(|(key, value)| Changed {
+            default: defaults.get(&key).cloned(),
+            source: source(&key),
+            key,
+            value,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(keep: &str) -> Result<Config> {
+        let s = format!(
+            "[install]\nprefix_dir = \"~/p\"\nbin_dir = \"~/b\"\nchannel = \"stable\"\nkeep = {}\ndesktop_integration = false\n[network]\nproxy_enabled = false\n",
+            keep
+        );
+        Ok(toml::from_str(&s)?)
+    }
+
+    #[test]
+    fn keep_zero_is_accepted() {
+        assert_eq!(parse("0").unwrap().install.keep, 0);
+    }
+
+    fn problems(toml: &str) -> Vec<String> {
+        Config::from_toml(toml).unwrap_err()
+    }
+
+    const VALID: &str = "[install]\nprefix_dir = \"/opt/ws\"\nbin_dir = \"/opt/bin\"\nchannel = \"stable\"\nkeep = 2\ndesktop_integration = false\n[network]\nproxy_enabled = false\n";
+
+    #[test]
+    fn valid_config_loads() {
+        let cfg = Config::from_toml(VALID).unwrap();
+        assert_eq!(cfg.install.prefix_dir, "/opt/ws");
+        assert!(Config::default().validate().is_empty());
+    }
+
+    #[test]
+    fn type_errors_name_the_setting_and_are_all_reported() {
+        let p = problems(
+            &VALID
+                .replace("keep = 2", "keep = \"two\"")
+                .replace("\"/opt/ws\"", "2"),
+        );
+        assert_eq!(p.len(), 2, "{:?}", p);
+        assert!(
+            p.iter()
+                .any(|m| m.starts_with("install.keep: ") && m.ends_with("(e.g. keep = 2)")),
+            "{:?}",
+            p
+        );
+        assert!(
+            p.iter()
+                .any(|m| m.starts_with("install.prefix_dir: ") && m.contains("expected a string")),
+            "{:?}",
+            p
+        );
+    }
+
+    #[test]
+    fn missing_sections_and_syntax_errors_are_reported() {
+        let p = problems(&VALID.replace("[network]\nproxy_enabled = false\n", ""));
+        assert_eq!(p, ["network: missing field `network`"]);
+
+        let p = problems("[install\n");
+        assert_eq!(p.len(), 1);
+        assert!(p[0].contains("line 1"), "{:?}", p);
+    }
+
+    #[test]
+    fn unknown_channel_is_rejected() {
+        let p = problems(&VALID.replace("\"stable\"", "\"stabel\""));
+        assert_eq!(
+            p,
+            ["install.channel: unknown channel 'stabel' (known: stable, next)"]
+        );
+    }
+
+    #[test]
+    fn dirs_must_be_absolute_and_distinct() {
+        let p = problems(&VALID.replace("\"/opt/ws\"", "\"opt/ws\""));
+        assert!(
+            p[0].starts_with("install.prefix_dir: 'opt/ws' is not an absolute path"),
+            "{:?}",
+            p
+        );
+
+        let p = problems(&VALID.replace("\"/opt/bin\"", "\"/opt/ws/\""));
+        assert_eq!(
+            p,
+            ["install.bin_dir: must differ from prefix_dir ('/opt/ws/')"]
+        );
+
+        let mut cfg = Config::default();
+        cfg.install.prefix_dir = "~/.local/opt/windsurf".into();
+        assert!(cfg.validate().is_empty(), "~ expands to an absolute path");
+
+        cfg.install.bin_dir = "$WINDMAN_TEST_UNSET_1087/bin".into();
+        assert_eq!(
+            cfg.validate(),
+            ["install.bin_dir: '$WINDMAN_TEST_UNSET_1087/bin': environment variable $WINDMAN_TEST_UNSET_1087 is not set"]
+        );
+    }
+
+    #[test]
+    fn max_total_size_must_parse() {
+        let with = |v: &str| {
+            VALID.replace(
+                "[network]",
+                &format!("max_total_size = \"{}\"\n[network]", v),
+            )
+        };
+        let cfg = Config::from_toml(&with("3G")).unwrap();
+        assert_eq!(cfg.install.max_total_bytes(), Some(3 << 30));
+        assert_eq!(Config::default().install.max_total_bytes(), None);
+        assert_eq!(
+            problems(&with("0")),
+            ["install.max_total_size: must be > 0"]
+        );
+        assert_eq!(
+            problems(&with("3 gigs")),
+            ["install.max_total_size: invalid size unit in '3 gigs' (use K, M, G or T)"]
+        );
+    }
+
+    #[test]
+    fn changelog_section_has_defaults_and_checks_the_source() {
+        let cfg = Config::from_toml(VALID).unwrap();
+        assert_eq!(cfg.changelog.source_url, crate::remote::releases_page_url());
+        assert_eq!(cfg.changelog.render, ChangelogRender::Markdown);
+        assert_eq!(cfg.changelog.max_entries, None);
+
+        let cfg = Config::from_toml(&format!(
+            "{}[changelog]\nrender = \"plain\"\nmax_entries = 3\n",
+            VALID
+        ))
+        .unwrap();
+        assert_eq!(cfg.changelog.render, ChangelogRender::Plain);
+        assert_eq!(cfg.changelog.max_entries, Some(3));
+
+        let p = problems(&format!(
+            "{}[changelog]\nsource_url = \"releases\"\nmax_entries = 0\n",
+            VALID
+        ));
+        assert_eq!(p.len(), 2, "{:?}", p);
+        assert!(
+            p[0].starts_with("changelog.source_url: 'releases' is not a valid URL"),
+            "{:?}",
+            p
+        );
+        let p = problems(&format!(
+            "{}[changelog]\nsource_url = \"ftp://x.invalid/\"\n",
+            VALID
+        ));
+        assert_eq!(
+            p,
+            ["changelog.source_url: unsupported scheme 'ftp' (use http or https)"]
+        );
+    }
+
+    #[test]
+    fn extract_fsync_defaults_to_dir_and_round_trips() {
+        let cfg = Config::from_toml(VALID).unwrap();
+        assert_eq!(cfg.install.extract_fsync, ExtractFsync::Dir);
+        let with = VALID.replace("[network]", "extract_fsync = \"none\"\n[network]");
+        let cfg = Config::from_toml(&with).unwrap();
+        assert_eq!(cfg.install.extract_fsync, ExtractFsync::None);
+        let td = tempfile::tempdir().unwrap();
+        let paths =
+            ConfigPaths::from_override(Some(td.path().join("w.toml").to_str().unwrap())).unwrap();
+        cfg.save_if_missing(&paths).unwrap();
+        let written = fs::read_to_string(&paths.file).unwrap();
+        assert!(
+            written.contains("extract_fsync = \"none\"\n"),
+            "{}",
+            written
+        );
+        let bad = VALID.replace("[network]", "extract_fsync = \"some\"\n[network]");
+        assert!(!problems(&bad).is_empty());
+    }
+
+    #[test]
+    fn space_check_defaults_to_abort_with_a_sane_factor() {
+        let cfg = Config::from_toml(VALID).unwrap();
+        assert_eq!(cfg.install.space_check, SpaceCheck::Abort);
+        assert_eq!(cfg.install.extract_space_factor, 4.0);
+        let with = |extra: &str| VALID.replace("[network]", &format!("{}\n[network]", extra));
+        let cfg = Config::from_toml(&with("space_check = \"warn\"")).unwrap();
+        assert_eq!(cfg.install.space_check, SpaceCheck::Warn);
+        assert_eq!(
+            problems(&with("extract_space_factor = 0.5")),
+            ["install.extract_space_factor: 0.5 is not a factor >= 1"]
+        );
+    }
+
+    #[test]
+    fn timeouts_must_be_positive() {
+        let p = problems(&VALID.replace("[network]", "smoke_test_timeout_secs = 0\n[network]"));
+        assert_eq!(
+            p,
+            ["install.smoke_test_timeout_secs: must be > 0 (seconds)"]
+        );
+    }
+
+    #[test]
+    fn trash_grace_days_is_bounded() {
+        let with = |days: u64| {
+            VALID.replace(
+                "[network]",
+                &format!("trash_grace_days = {}\n[network]", days),
+            )
+        };
+        assert!(Config::from_toml(&with(MAX_TRASH_GRACE_DAYS)).is_ok());
+        assert_eq!(
+            problems(&with(u64::MAX >> 1)),
+            ["install.trash_grace_days: 9223372036854775807 is unreasonably large (0 to 36500)"]
+        );
+        let mut cfg = Config::from_toml(VALID).unwrap();
+        cfg.install.trash_grace_days = u64::MAX;
+        assert_eq!(cfg.install.trash_grace(), std::time::Duration::MAX);
+        cfg.install.trash_grace_days = 7;
+        assert_eq!(cfg.install.trash_grace().as_secs(), 7 * 86400);
+    }
+
+    #[test]
+    fn proxy_url_is_checked_only_when_enabled() {
+        let with = |url: &str| {
+            VALID.replace(
+                "proxy_enabled = false\n",
+                &format!("proxy_enabled = true\n{}", url),
+            )
+        };
+        assert!(problems(&with(""))[0].contains("required when network.proxy_enabled"));
+        assert!(problems(&with("proxy_url = \"not a url\"\n"))[0].contains("not a valid URL"));
+        assert!(
+            problems(&with("proxy_url = \"ftp://p:21\"\n"))[0].contains("unsupported scheme 'ftp'")
+        );
+        assert!(Config::from_toml(&with("proxy_url = \"http://proxy:3128\"\n")).is_ok());
+        assert!(Config::from_toml(&with("proxy_url = \"socks5h://127.0.0.1:1080\"\n")).is_ok());
+        assert!(Config::from_toml(&VALID.replace(
+            "proxy_enabled = false\n",
+            "proxy_enabled = false\nproxy_url = \"bogus\"\n"
+        ))
+        .is_ok());
+    }
+
+    #[test]
+    fn extra_headers_are_known_keys_and_must_be_valid_http() {
+        let with = |extra: &str| {
+            VALID.replace(
+                "proxy_enabled = false\n",
+                &format!("proxy_enabled = false\n{}", extra),
+            )
+        };
+        let ok = with("user_agent = \"acme/1\"\nextra_headers = { \"X-Corp-Token\" = \"t\" }\n");
+        let (cfg, notes) = Config::parse(&ok).unwrap();
+        assert!(notes.is_empty(), "{:?}", notes.unknown);
+        assert_eq!(cfg.network.extra_headers["X-Corp-Token"], "t");
+
+        let p = problems(&with("extra_headers = { \"X Corp\" = \"t\" }\n"));
+        assert!(
+            p[0].starts_with("network: extra_headers: 'X Corp'"),
+            "{:?}",
+            p
+        );
+    }
+
+    #[test]
+    fn cli_aliases_are_known_keys_and_cannot_shadow_commands() {
+        let ok = format!("{}[cli.aliases]\nlatest = \"update --dry-run\"\n", VALID);
+        let (cfg, notes) = Config::parse(&ok).unwrap();
+        assert!(notes.is_empty(), "{:?}", notes.unknown);
+        assert_eq!(cfg.cli.aliases["latest"], "update --dry-run");
+
+        let p = problems(&format!(
+            "{}[cli.aliases]\nls = \"list --size\"\nnothing = \"\"\n",
+            VALID
+        ));
+        assert_eq!(p.len(), 2, "{:?}", p);
+        assert!(
+            p[0].starts_with("cli.aliases.ls: 'ls' is a built-in"),
+            "{:?}",
+            p
+        );
+        assert_eq!(p[1], "cli.aliases.nothing: expands to nothing");
+    }
+
+    #[test]
+    fn profiles_are_validated_with_their_derived_paths() {
+        let p = problems(&format!(
+            "{}[profiles.next]\nchannel = \"nightly\"\nkeep = 1000\nbin_dir = \"/opt/ws-next\"\n",
+            VALID
+        ));
+        assert_eq!(
+            p,
+            [
+                "profiles.next.keep: 1000 is unreasonably large (0 to 100)",
+                "profiles.next.channel: unknown channel 'nightly' (known: stable, next)",
+                "profiles.next.bin_dir: must differ from prefix_dir ('/opt/ws-next')",
+            ]
+        );
+    }
+
+    #[test]
+    fn keep_negative_or_absurd_is_rejected() {
+        let err = parse("-1").unwrap_err().to_string();
+        assert!(err.contains("keep must be >= 0"), "{}", err);
+        let err = parse("100000").unwrap_err().to_string();
+        assert!(err.contains("unreasonably large"), "{}", err);
+    }
+
+    #[test]
+    fn set_prefix_dir_edits_in_place_and_keeps_comments() {
+        let td = tempfile::tempdir().unwrap();
+        let paths =
+            ConfigPaths::from_override(Some(td.path().join("w.toml").to_str().unwrap())).unwrap();
+        fs::write(
+            &paths.file,
+            format!("# my setup\n{}[profiles.next]\nchannel = \"next\"\n", VALID),
+        )
+        .unwrap();
+
+        Config::set_prefix_dir(&paths, None, "~/apps/windsurf").unwrap();
+        Config::set_prefix_dir(&paths, Some("next"), "~/apps/windsurf-next").unwrap();
+        let s = fs::read_to_string(&paths.file).unwrap();
+        assert!(s.starts_with("# my setup\n"));
+        assert_eq!(s.matches("prefix_dir").count(), 2, "{}", s);
+        let cfg = Config::from_toml(&s).unwrap();
+        assert_eq!(cfg.install.prefix_dir, "~/apps/windsurf");
+        assert_eq!(
+            cfg.profiles["next"].prefix_dir.as_deref(),
+            Some("~/apps/windsurf-next")
+        );
+
+        // a missing file is created from the defaults first
+        let fresh =
+            ConfigPaths::from_override(Some(td.path().join("new.toml").to_str().unwrap())).unwrap();
+        Config::set_prefix_dir(&fresh, None, "/srv/windsurf").unwrap();
+        let (cfg, found) = Config::load_or_default(&fresh).unwrap();
+        assert!(found);
+        assert_eq!(cfg.install.prefix_dir, "/srv/windsurf");
+
+        let err = Config::set_prefix_dir(&fresh, None, "relative/dir").unwrap_err();
+        assert!(format!("{:#}", err).contains("install.prefix_dir"));
+    }
+
+    #[test]
+    fn a_missing_file_is_reported_and_a_directory_refused() {
+        let td = tempfile::tempdir().unwrap();
+        let missing =
+            ConfigPaths::from_override(Some(td.path().join("x.toml").to_str().unwrap())).unwrap();
+        let (cfg, found) = Config::load_or_default(&missing).unwrap();
+        assert!(!found);
+        assert_eq!(cfg.install.keep, Config::default().install.keep);
+
+        let dir = ConfigPaths::from_override(Some(td.path().to_str().unwrap())).unwrap();
+        let err = Config::load_or_default(&dir).unwrap_err().to_string();
+        assert!(err.contains("is a directory"), "{}", err);
+    }
+
+    fn fixture(name: &str) -> &'static str {
+        match name {
+            "legacy_telemetry" => include_str!("../tests/fixtures/config/legacy_telemetry.toml"),
+            "legacy_keep_versions" => {
+                include_str!("../tests/fixtures/config/legacy_keep_versions.toml")
+            }
+            "legacy_top_level" => include_str!("../tests/fixtures/config/legacy_top_level.toml"),
+            "unknown_keys" => include_str!("../tests/fixtures/config/unknown_keys.toml"),
+            _ => unreachable!("{}", name),
+        }
+    }
+
+    #[test]
+    fn each_legacy_shape_maps_to_current_settings() {
+        let (cfg, notes) = Config::parse(fixture("legacy_telemetry")).unwrap();
+        assert_eq!(cfg.install.keep, 3);
+        assert_eq!(
+            notes.migrations,
+            [Migration::Dropped {
+                key: "telemetry".into(),
+                why: "windman collects no telemetry"
+            }]
+        );
+        assert!(notes.unknown.is_empty(), "{:?}", notes.unknown);
+
+        // the old key no longer falls back to the default keep
+        let (cfg, notes) = Config::parse(fixture("legacy_keep_versions")).unwrap();
+        assert_eq!(cfg.install.keep, 5);
+        assert_eq!(
+            notes.warnings(),
+            ["`install.keep_versions` is now `install.keep` (its value is used)"]
+        );
+
+        // both spellings: the current key wins
+        let (cfg, notes) = Config::parse(fixture("legacy_top_level")).unwrap();
+        assert_eq!(cfg.install.keep, 1);
+        assert_eq!(notes.migrations.len(), 2);
+        assert_eq!(
+            notes.migrations[0],
+            Migration::Shadowed {
+                from: "keep_versions".into(),
+                to: "install.keep".into()
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_warnings_not_errors() {
+        let (cfg, notes) = Config::parse(fixture("unknown_keys")).unwrap();
+        assert_eq!(cfg.profiles["next"].channel.as_deref(), Some("next"));
+        assert!(notes.migrations.is_empty());
+        assert_eq!(
+            notes.unknown,
+            [
+                "desktop.actions.icon",
+                "extras",
+                "install.kep",
+                "profiles.next.chanel"
+            ]
+        );
+        assert!(Config::parse(VALID).unwrap().1.is_empty());
+    }
+
+    #[test]
+    fn migrate_file_rewrites_the_current_settings_and_keeps_a_backup() {
+        let td = tempfile::tempdir().unwrap();
+        let paths =
+            ConfigPaths::from_override(Some(td.path().join("w.toml").to_str().unwrap())).unwrap();
+        let original = fixture("legacy_keep_versions")
+            .replace("[network]", "[telemetry]\nenabled = true\n\n[network]");
+        fs::write(&paths.file, &original).unwrap();
+
+        let notes = Config::migrate_file(&paths).unwrap();
+        assert_eq!(notes.migrations.len(), 2);
+        assert_eq!(
+            fs::read_to_string(backup_path(&paths.file)).unwrap(),
+            original
+        );
+        let (cfg, notes) = Config::load(&paths).unwrap();
+        assert!(notes.is_empty(), "{:?}", notes);
+        assert_eq!(cfg.install.keep, 5);
+        let rewritten = fs::read_to_string(&paths.file).unwrap();
+        assert!(
+            rewritten.contains("smoke_test_timeout_secs"),
+            "{}",
+            rewritten
+        );
+
+        // nothing left to do: the file is not touched again
+        fs::remove_file(backup_path(&paths.file)).unwrap();
+        assert!(Config::migrate_file(&paths).unwrap().is_empty());
+        assert!(!backup_path(&paths.file).exists());
+        assert_eq!(fs::read_to_string(&paths.file).unwrap(), rewritten);
+    }
+
+    #[test]
+    fn diff_lists_only_the_settings_that_differ_from_the_defaults() {
+        assert!(diff(&Config::default(), |_| Origin::File).is_empty());
+
+        let mut cfg = parse("2").unwrap();
+        cfg.install.prefix_dir = Config::default().install.prefix_dir;
+        cfg.install.bin_dir = Config::default().install.bin_dir;
+        cfg.install.desktop_integration = true;
+        cfg.install.keep = 5;
+        cfg.network.user_agent = Some("acme/1".into());
+        let source = |k: &str| match k {
+            "install.keep" => Origin::Cli,
+            _ => Origin::File,
+        };
+        let got: Vec<(String, Option<String>, Origin)> = diff(&cfg, source)
+            .into_iter()
+            .map(|c| (c.key, c.default.map(|d| d.to_string()), c.source))
+            .collect();
+        assert_eq!(
+            got,
+            [
+                (
+                    "install.keep".to_string(),
+                    Some("2".to_string()),
+                    Origin::Cli
+                ),
+                ("network.user_agent".to_string(), None, Origin::File),
+            ]
+        );
+    }
 }
@@ -0,0 +1,246 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// Content hashes of already-seen files, kept in the versions dir between runs.
+pub const HASH_CACHE_FILE: &str = ".windman-hashes.json";
+
+/// What a dedup pass did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DedupStats {
+    pub files_linked: u64,
+    pub bytes_saved: u64,
+    /// Files actually read to compute a hash (cache misses)
+    pub files_hashed: u64,
+}
+
+impl DedupStats {
+    pub fn add(&mut self, other: DedupStats) {
+        self.files_linked += other.files_linked;
+        self.bytes_saved += other.bytes_saved;
+        self.files_hashed += other.files_hashed;
+    }
+}
+
+/// SHA-256 per file identity (device, inode, size, mtime): a file rewritten in
+/// place gets a new mtime and is hashed again.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct HashCache {
+    entries: BTreeMap<String, String>,
+}
+
+impl HashCache {
+    pub fn load(versions_dir: &Path) -> Self {
+        fs::read_to_string(versions_dir.join(HASH_CACHE_FILE))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, versions_dir: &Path) -> Result<()> {
+        let p = versions_dir.join(HASH_CACHE_FILE);
+        fs::write(&p, serde_json::to_string(self)?)
+            .with_context(|| format!("writing {}", p.display()))
+    }
+
+    fn hash(&mut self, path: &Path, md: &fs::Metadata, stats: &mut DedupStats) -> Result<String> {
+        let key = format!(
+            "{}:{}:{}:{}.{}",
+            md.dev(),
+            md.ino(),
+            md.size(),
+            md.mtime(),
+            md.mtime_nsec()
+        );
+        if let Some(h) = self.entries.get(&key) {
+            return Ok(h.clone());
+        }
+        let h = crate::meta::sha256_file(path)?;
+        stats.files_hashed += 1;
+        self.entries.insert(key, h.clone());
+        Ok(h)
+    }
+
+    /// Drop entries for inodes that no longer exist anywhere under `versions_dir`.
+    pub fn retain_existing(&mut self, versions_dir: &Path) {
+        let mut live = std::collections::BTreeSet::new();
+        for e in WalkDir::new(versions_dir).into_iter().flatten() {
+            if let Ok(md) = e.metadata() {
+                if md.is_file() {
+                    live.insert((md.dev(), md.ino()));
+                }
+            }
+        }
+        self.entries.retain(|k, _| {
+            let mut it = k.split(':');
+            match (
+                it.next().and_then(|d| d.parse().ok()),
+                it.next().and_then(|i| i.parse().ok()),
+            ) {
+                (Some(dev), Some(ino)) => live.contains(&(dev, ino)),
+                _ => false,
+            }
+        });
+    }
+}
+
+/// Replace files of `new_dir` that are identical to the file at the same relative
+/// path in `old_dir` with a hardlink to it. Identical means same size, same
+/// permissions (a link shares them) and same SHA-256. Files on another
+/// filesystem, symlinks and anything not a regular file are left alone.
+pub fn dedup_against(new_dir: &Path, old_dir: &Path, cache: &mut HashCache) -> Result<DedupStats> {
+    let mut stats = DedupStats::default();
+    for entry in WalkDir::new(new_dir).follow_links(false) {
+        let entry = entry.with_context(|| format!("walking {}", new_dir.display()))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let new_path = entry.path();
+        let rel = new_path
+            .strip_prefix(new_dir)
+            .expect("walkdir stays under root");
+        let old_path = old_dir.join(rel);
+
+        let new_md = entry.metadata()?;
+        let Ok(old_md) = fs::symlink_metadata(&old_path) else {
+            continue;
+        };
+        if !old_md.is_file()
+            || old_md.size() != new_md.size()
+            || old_md.dev() != new_md.dev()
+            || old_md.ino() == new_md.ino()
+            || old_md.mode() != new_md.mode()
+        {
+            continue;
+        }
+        if cache.hash(&old_path, &old_md, &mut stats)?
+            != cache.hash(new_path, &new_md, &mut stats)?
+        {
+            continue;
+        }
+
+        // Link next to the target, then rename over it: the file is never missing
+        let tmp = new_path.with_file_name(format!(
+            ".{}.windman-link",
+            new_path.file_name().unwrap().to_string_lossy()
+        ));
+        let _ = fs::remove_file(&tmp);
+        fs::hard_link(&old_path, &tmp)
+            .with_context(|| format!("linking {} -> {}", tmp.display(), old_path.display()))?;
+        fs::rename(&tmp, new_path).with_context(|| format!("replacing {}", new_path.display()))?;
+        stats.files_linked += 1;
+        stats.bytes_saved += new_md.size();
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::tempdir;
+
+    fn write(p: &Path, content: &[u8], mode: u32) {
+        fs::create_dir_all(p.parent().unwrap()).unwrap();
+        fs::write(p, content).unwrap();
+        fs::set_permissions(p, fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    fn ino(p: &Path) -> u64 {
+        fs::metadata(p).unwrap().ino()
+    }
+
+    #[test]
+    fn links_identical_files_and_keeps_the_rest() {
+        let td = tempdir().unwrap();
+        let (old, new) = (td.path().join("1.0.0"), td.path().join("1.0.1"));
+        write(&old.join("Windsurf/bin/windsurf"), b"#!/bin/sh\n", 0o755);
+        write(&new.join("Windsurf/bin/windsurf"), b"#!/bin/sh\n", 0o755);
+        write(&old.join("resources/app.js"), b"v1", 0o644);
+        write(&new.join("resources/app.js"), b"v2", 0o644);
+        write(&new.join("resources/new.txt"), b"only new", 0o644);
+        std::os::unix::fs::symlink("bin/windsurf", old.join("Windsurf/link")).unwrap();
+        std::os::unix::fs::symlink("bin/windsurf", new.join("Windsurf/link")).unwrap();
+
+        let stats = dedup_against(&new, &old, &mut HashCache::default()).unwrap();
+
+        assert_eq!(stats.files_linked, 1);
+        assert_eq!(stats.bytes_saved, 10);
+        let exe = new.join("Windsurf/bin/windsurf");
+        assert_eq!(ino(&exe), ino(&old.join("Windsurf/bin/windsurf")));
+        assert_eq!(
+            fs::metadata(&exe).unwrap().permissions().mode() & 0o777,
+            0o755
+        );
+        assert_ne!(
+            ino(&new.join("resources/app.js")),
+            ino(&old.join("resources/app.js"))
+        );
+        assert_eq!(fs::read(new.join("resources/app.js")).unwrap(), b"v2");
+        assert!(fs::symlink_metadata(new.join("Windsurf/link"))
+            .unwrap()
+            .file_type()
+            .is_symlink());
+    }
+
+    #[test]
+    fn different_permissions_are_not_linked() {
+        let td = tempdir().unwrap();
+        let (old, new) = (td.path().join("a"), td.path().join("b"));
+        write(&old.join("tool"), b"same", 0o644);
+        write(&new.join("tool"), b"same", 0o755);
+
+        let stats = dedup_against(&new, &old, &mut HashCache::default()).unwrap();
+
+        assert_eq!(stats.files_linked, 0);
+        assert_eq!(
+            fs::metadata(new.join("tool")).unwrap().permissions().mode() & 0o777,
+            0o755
+        );
+        assert_eq!(
+            fs::metadata(old.join("tool")).unwrap().permissions().mode() & 0o777,
+            0o644
+        );
+    }
+
+    #[test]
+    fn removing_either_dir_keeps_the_other_intact() {
+        let td = tempdir().unwrap();
+        let (old, new) = (td.path().join("a"), td.path().join("b"));
+        write(&old.join("f"), b"shared", 0o644);
+        write(&new.join("f"), b"shared", 0o644);
+        dedup_against(&new, &old, &mut HashCache::default()).unwrap();
+
+        fs::remove_dir_all(&old).unwrap();
+        assert_eq!(fs::read(new.join("f")).unwrap(), b"shared");
+    }
+
+    #[test]
+    fn cache_avoids_rehashing_and_survives_reload() {
+        let td = tempdir().unwrap();
+        let (old, new) = (td.path().join("a"), td.path().join("b"));
+        write(&old.join("f"), b"x", 0o644);
+        write(&new.join("f"), b"y", 0o644);
+
+        let mut cache = HashCache::default();
+        assert_eq!(
+            dedup_against(&new, &old, &mut cache).unwrap().files_hashed,
+            2
+        );
+        cache.save(td.path()).unwrap();
+
+        let mut cache = HashCache::load(td.path());
+        assert_eq!(
+            dedup_against(&new, &old, &mut cache).unwrap().files_hashed,
+            0
+        );
+
+        fs::remove_dir_all(&old).unwrap();
+        cache.retain_existing(td.path());
+        assert_eq!(cache.entries.len(), 1);
+    }
+}
@@ -1,26 +1,337 @@
+use crate::config::DesktopConfig;
 use crate::paths::EffectivePaths;
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
 use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
 
-pub fn ensure_desktop_files(eff: &EffectivePaths) -> Result<()> {
-    if !eff.icons_dir.exists() {
-        fs::create_dir_all(&eff.icons_dir)?;
+/// Keys we always write in `[Desktop Entry]`; extra_entries may not repeat them.
+const MANAGED_KEYS: &[&str] = &[
+    "Name",
+    "Comment",
+    "Exec",
+    "Terminal",
+    "Type",
+    "Icon",
+    "Categories",
+    "StartupWMClass",
+    "Actions",
+];
+
+/// True if the desktop entry exists and is exactly what `desktop` renders now
+/// (same `current`, name, Exec arguments, actions and extra entries).
+pub fn desktop_entry_is_fresh(eff: &EffectivePaths, desktop: &DesktopConfig) -> bool {
+    let Ok(content) = fs::read_to_string(&eff.desktop_file) else {
+        return false;
+    };
+    render_desktop_entry(eff, desktop).is_ok_and(|rendered| rendered == content)
+}
+
+/// What the entry runs: the Windsurf executable found through `current` (the
+/// `Windsurf` dir it sits in while nothing is installed), or with
+/// `install.arch_suffix` the shim, which picks the machine's own `current`.
+fn exec_path(eff: &EffectivePaths) -> PathBuf {
+    match eff.arch {
+        Some(_) => eff.bin_shim.clone(),
+        None => crate::util::find_windsurf_executable(&eff.current_symlink)
+            .unwrap_or_else(|| eff.current_symlink.join("Windsurf")),
+    }
+}
+
+/// The program of the `Exec=` line of the `[Desktop Entry]` group.
+fn entry_exec(content: &str) -> Option<PathBuf> {
+    entry_value(content, "Exec")?
+        .split_whitespace()
+        .next()
+        .map(PathBuf::from)
+}
+
+/// `key`'s value in the `[Desktop Entry]` group (the actions come after it).
+fn entry_value<'a>(content: &'a str, key: &str) -> Option<&'a str> {
+    content
+        .lines()
+        .skip_while(|l| l.trim() != "[Desktop Entry]")
+        .skip(1)
+        .take_while(|l| !l.starts_with('['))
+        .find_map(|l| l.strip_prefix(key)?.trim_start().strip_prefix('='))
+        .map(str::trim)
+}
+
+pub fn ensure_desktop_files(eff: &EffectivePaths, desktop: &DesktopConfig) -> Result<()> {
+    // no HOME nor XDG_DATA_HOME: resolve_paths left no place for it
+    if eff.desktop_file.as_os_str().is_empty() {
+        return Err(crate::paths::no_desktop_dir());
     }
+    // Validate before touching the filesystem
+    let content = render_desktop_entry(eff, desktop)?;
 
     // icon is optional; users may add their own. We just ensure the dir exists.
     // If you want to install an icon file, write it to eff.icons_dir.join("windsurf.png").
+    fs::create_dir_all(&eff.icons_dir)
+        .with_context(|| format!("creating {}", eff.icons_dir.display()))
+        .map_err(|e| explain_write_error(e, &eff.icons_dir))?;
 
     let desktop_dir = eff.desktop_file.parent().unwrap();
-    if !desktop_dir.exists() {
-        fs::create_dir_all(desktop_dir)?;
+    fs::create_dir_all(desktop_dir)
+        .with_context(|| format!("creating {}", desktop_dir.display()))
+        .and_then(|_| crate::util::atomic_write_executable(&eff.desktop_file, content.as_bytes()))
+        .map_err(|e| explain_write_error(e, desktop_dir))?;
+
+    // read it back: a file the desktop never sees is worse than an error
+    if fs::read_to_string(&eff.desktop_file).ok().as_deref() != Some(content.as_str()) {
+        bail!(
+            "{} does not hold the desktop entry just written",
+            eff.desktop_file.display()
+        );
+    }
+    Ok(())
+}
+
+/// `e` with the directory named on top when writing there was refused
+/// (permissions, or a symlink into a read-only place); other errors as they are.
+fn explain_write_error(e: anyhow::Error, dir: &Path) -> anyhow::Error {
+    use std::io::ErrorKind;
+    let refused = e
+        .chain()
+        .filter_map(|c| c.downcast_ref::<std::io::Error>())
+        .any(|io| {
+            matches!(
+                io.kind(),
+                ErrorKind::PermissionDenied | ErrorKind::ReadOnlyFilesystem
+            )
+        });
+    if !refused {
+        return e;
+    }
+    let shown = match fs::canonicalize(dir) {
+        Ok(real) if real != dir => format!("{} (a link to {})", dir.display(), real.display()),
+        _ => dir.display().to_string(),
+    };
+    e.context(format!(
+        "cannot write the desktop entry: {} is not writable\nFix its permissions, or set install.desktop_integration = false.",
+        shown
+    ))
+}
+
+/// What `status` and `doctor` find of the desktop entry: is it there, does its
+/// Exec run our `current`, is its icon installed, will menus pick it up.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DesktopStatus {
+    pub file: PathBuf,
+    pub exists: bool,
+    /// The program of its Exec line
+    pub exec: Option<PathBuf>,
+    /// Where that resolves, when it is an executable file
+    pub exec_target: Option<PathBuf>,
+    /// Exec is what windman would write now (it runs `current`)
+    pub fresh: bool,
+    /// Its Icon value (a theme name or a path)
+    pub icon: Option<String>,
+    /// The icon file found for it
+    pub icon_file: Option<PathBuf>,
+    /// `update-desktop-database` is on PATH
+    pub update_desktop_database: bool,
+}
+
+impl DesktopStatus {
+    /// What keeps the menu entry from working (`doctor` fails on these).
+    pub fn problems(&self) -> Vec<String> {
+        let file = self.file.display();
+        let Some(exec) = &self.exec else {
+            return vec![match self.exists {
+                false => format!("desktop entry {} is missing", file),
+                true => format!("desktop entry {} has no Exec line", file),
+            }];
+        };
+        let mut problems = Vec::new();
+        if self.exec_target.is_none() {
+            problems.push(format!(
+                "desktop entry {} runs {}, which is not an executable file",
+                file,
+                exec.display()
+            ));
+        }
+        if !self.fresh {
+            problems.push(format!("desktop entry {} is outdated", file));
+        }
+        problems
+    }
+
+    /// What makes it look worse without breaking it.
+    pub fn notes(&self) -> Vec<String> {
+        let mut notes = Vec::new();
+        if let Some(icon) = self.icon.as_ref().filter(|_| self.icon_file.is_none()) {
+            notes.push(format!(
+                "icon {:?} not found; menus show a generic one",
+                icon
+            ));
+        }
+        if self.exists && !self.update_desktop_database {
+            notes.push(
+                "update-desktop-database not found; some menus only pick up changes at the next login"
+                    .to_string(),
+            );
+        }
+        notes
+    }
+}
+
+/// Inspect the desktop entry of `eff` (reads only).
+pub fn integration_status(eff: &EffectivePaths) -> DesktopStatus {
+    let content = fs::read_to_string(&eff.desktop_file).ok();
+    let exec = content.as_deref().and_then(entry_exec);
+    let exec_target = exec
+        .as_deref()
+        .filter(|p| {
+            fs::metadata(p).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        })
+        .and_then(|p| fs::canonicalize(p).ok());
+    let icon = content
+        .as_deref()
+        .and_then(|c| entry_value(c, "Icon"))
+        .filter(|i| !i.is_empty())
+        .map(str::to_string);
+    let icon_file = icon.as_deref().and_then(|i| find_icon(eff, i));
+    DesktopStatus {
+        file: eff.desktop_file.clone(),
+        exists: content.is_some(),
+        fresh: exec.is_some() && exec == Some(exec_path(eff)),
+        exec,
+        exec_target,
+        icon,
+        icon_file,
+        update_desktop_database: crate::util::find_in_path("update-desktop-database").is_some(),
     }
+}
 
-    let exec_path = eff.current_symlink.join("Windsurf");
-    let content = format!(
-        "[Desktop Entry]\nName=Windsurf\nComment=AI IDE by Codeium\nExec={} %U\nTerminal=false\nType=Application\nIcon=windsurf\nCategories=Development;IDE;\nStartupWMClass=Windsurf\n",
-        exec_path.display()
+/// The file for an Icon value: the path itself, else `<icon>.png|svg|xpm` in
+/// icons_dir (any theme and size under it) or /usr/share/pixmaps.
+fn find_icon(eff: &EffectivePaths, icon: &str) -> Option<PathBuf> {
+    let path = Path::new(icon);
+    if path.is_absolute() {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+    let is_icon = |p: &Path| {
+        p.file_stem().is_some_and(|s| s == icon)
+            && p.extension()
+                .is_some_and(|e| e == "png" || e == "svg" || e == "xpm")
+    };
+    [eff.icons_dir.as_path(), Path::new("/usr/share/pixmaps")]
+        .into_iter()
+        .flat_map(|dir| {
+            walkdir::WalkDir::new(dir)
+                .max_depth(4)
+                .into_iter()
+                .flatten()
+        })
+        .map(|e| e.into_path())
+        .find(|p| is_icon(p) && p.is_file())
+}
+
+/// Render the .desktop file for `[desktop]`; the default config gives the historical entry.
+pub fn render_desktop_entry(eff: &EffectivePaths, desktop: &DesktopConfig) -> Result<String> {
+    validate(desktop)?;
+
+    let exec_path = exec_path(eff);
+    let exec = match desktop.exec_args.trim() {
+        "" => exec_path.display().to_string(),
+        args => format!("{} {}", exec_path.display(), args),
+    };
+    // Per-profile display name so side-by-side installs are distinguishable in menus
+    let base_name = desktop.name.as_deref().unwrap_or("Windsurf");
+    let name = match &eff.profile {
+        Some(p) => format!("{} ({})", base_name, p),
+        None => base_name.to_string(),
+    };
+
+    let mut out = format!(
+        "[Desktop Entry]\nName={}\nComment=AI IDE by Codeium\nExec={} %U\nTerminal=false\nType=Application\nIcon=windsurf\nCategories=Development;IDE;\nStartupWMClass=Windsurf\n",
+        name, exec
     );
-    fs::write(&eff.desktop_file, content)?;
+    for line in &desktop.extra_entries {
+        out.push_str(line.trim());
+        out.push('\n');
+    }
+    if !desktop.actions.is_empty() {
+        let ids: Vec<String> = desktop.actions.iter().map(|a| action_id(&a.name)).collect();
+        out.push_str(&format!("Actions={};\n", ids.join(";")));
+        for (a, id) in desktop.actions.iter().zip(&ids) {
+            out.push_str(&format!(
+                "\n[Desktop Action {}]\nName={}\nExec={} {}\n",
+                id,
+                a.name,
+                exec,
+                a.exec.trim()
+            ));
+        }
+    }
+    Ok(out)
+}
+
+/// "New Window" -> "new-window"
+fn action_id(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_ascii_lowercase())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Reject values that would break the `[Desktop Entry]` group syntax.
+fn validate(desktop: &DesktopConfig) -> Result<()> {
+    let single_line = |what: &str, v: &str| -> Result<()> {
+        if v.contains('\n') || v.contains('\r') {
+            bail!("desktop.{} must be a single line", what);
+        }
+        Ok(())
+    };
+    if let Some(n) = &desktop.name {
+        single_line("name", n)?;
+        if n.trim().is_empty() {
+            bail!("desktop.name must not be empty");
+        }
+    }
+    single_line("exec_args", &desktop.exec_args)?;
+
+    for line in &desktop.extra_entries {
+        single_line("extra_entries", line)?;
+        let Some((key, _)) = line.trim().split_once('=') else {
+            bail!("desktop.extra_entries: expected Key=Value, got {:?}", line);
+        };
+        let key = key.trim_end();
+        // Key or Key[locale]
+        let bare = key.split('[').next().unwrap_or(key);
+        let valid = !bare.is_empty()
+            && bare.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            && (bare.len() == key.len() || key.ends_with(']'));
+        if !valid {
+            bail!("desktop.extra_entries: invalid key {:?} in {:?}", key, line);
+        }
+        if MANAGED_KEYS.contains(&bare) {
+            bail!(
+                "desktop.extra_entries: {} is managed by windman (use desktop.name/exec_args/actions)",
+                bare
+            );
+        }
+    }
+
+    let mut seen = Vec::new();
+    for a in &desktop.actions {
+        single_line("actions.name", &a.name)?;
+        single_line("actions.exec", &a.exec)?;
+        let id = action_id(&a.name);
+        if id.is_empty() {
+            bail!(
+                "desktop.actions: name {:?} needs at least one letter or digit",
+                a.name
+            );
+        }
+        if seen.contains(&id) {
+            bail!("desktop.actions: duplicate action {:?}", a.name);
+        }
+        seen.push(id);
+    }
     Ok(())
 }
 
@@ -53,9 +364,13 @@ mod tests {
             bin_shim: base.join("bin/windsurf"),
             desktop_file: base.join("share/applications/windsurf.desktop"),
             icons_dir: base.join("share/icons/hicolor/512x512/apps"),
+            cache_dir: base.join("cache"),
+            state_dir: base.join("state"),
+            profile: None,
+            arch: None,
         };
 
-        super::ensure_desktop_files(&eff).unwrap();
+        super::ensure_desktop_files(&eff, &Default::default()).unwrap();
         let desktop = fs::read_to_string(&eff.desktop_file).unwrap();
         assert!(desktop.contains("Name=Windsurf"));
         assert!(desktop.contains("Exec="));
@@ -63,5 +378,168 @@ mod tests {
         // L'Exec doit pointer vers current/Windsurf
         let exec_line = desktop.lines().find(|l| l.starts_with("Exec=")).unwrap();
         assert!(exec_line.contains("current/Windsurf"));
+        assert!(super::desktop_entry_is_fresh(&eff, &Default::default()));
+
+        // same program, other settings: the entry is rewritten
+        let cfg = desktop_cfg("[desktop]\nexec_args = \"--ozone-platform-hint=auto\"\n");
+        assert!(!super::desktop_entry_is_fresh(&eff, &cfg));
+        super::ensure_desktop_files(&eff, &cfg).unwrap();
+        assert!(super::desktop_entry_is_fresh(&eff, &cfg));
+    }
+
+    fn desktop_cfg(toml_src: &str) -> crate::config::DesktopConfig {
+        #[derive(serde::Deserialize)]
+        struct Wrap {
+            desktop: crate::config::DesktopConfig,
+        }
+        toml::from_str::<Wrap>(toml_src).unwrap().desktop
+    }
+
+    #[test]
+    fn default_entry_matches_golden() {
+        let eff = EffectivePaths::for_test(std::path::Path::new("/p"));
+        let got = super::render_desktop_entry(&eff, &Default::default()).unwrap();
+        assert_eq!(got, include_str!("../tests/golden/desktop_default.desktop"));
+    }
+
+    #[test]
+    fn customized_entry_matches_golden() {
+        let eff = EffectivePaths::for_test(std::path::Path::new("/p"));
+        let cfg = desktop_cfg(
+            r#"
+            [desktop]
+            name = "Windsurf Team"
+            exec_args = "--ozone-platform-hint=auto"
+            extra_entries = ["Keywords=ai;editor;", "MimeType=text/plain;"]
+            actions = [{ name = "New Window", exec = "--new-window" }]
+            "#,
+        );
+        let got = super::render_desktop_entry(&eff, &cfg).unwrap();
+        assert_eq!(got, include_str!("../tests/golden/desktop_custom.desktop"));
+    }
+
+    /// `<base>/1.0.0` with an executable `Windsurf/bin/windsurf`, current on it.
+    fn installed(base: &std::path::Path) -> EffectivePaths {
+        let bin = base.join("1.0.0/Windsurf/bin");
+        fs::create_dir_all(&bin).unwrap();
+        crate::util::atomic_write_executable(&bin.join("windsurf"), b"#!/bin/sh\n").unwrap();
+        symlink(base.join("1.0.0"), base.join("current")).unwrap();
+        EffectivePaths::for_test(base)
+    }
+
+    #[test]
+    fn status_of_a_written_entry_resolves_exec_and_icon() {
+        let td = tempdir().unwrap();
+        let eff = installed(td.path());
+        assert_eq!(
+            super::integration_status(&eff).problems(),
+            [format!(
+                "desktop entry {} is missing",
+                eff.desktop_file.display()
+            )]
+        );
+
+        super::ensure_desktop_files(&eff, &Default::default()).unwrap();
+        let st = super::integration_status(&eff);
+        assert!(st.problems().is_empty(), "{:?}", st);
+        assert_eq!(
+            st.exec,
+            Some(eff.current_symlink.join("Windsurf/bin/windsurf"))
+        );
+        assert_eq!(
+            st.exec_target,
+            Some(fs::canonicalize(td.path().join("1.0.0/Windsurf/bin/windsurf")).unwrap())
+        );
+        assert_eq!(st.icon.as_deref(), Some("windsurf"));
+        assert!(st
+            .notes()
+            .iter()
+            .any(|n| n.contains("icon \"windsurf\" not found")));
+
+        let icon = eff.icons_dir.join("hicolor/512x512/apps/windsurf.png");
+        fs::create_dir_all(icon.parent().unwrap()).unwrap();
+        fs::write(&icon, b"png").unwrap();
+        assert_eq!(super::integration_status(&eff).icon_file, Some(icon));
+    }
+
+    #[test]
+    fn status_flags_a_dangling_or_outdated_exec() {
+        let td = tempdir().unwrap();
+        let eff = installed(td.path());
+        super::ensure_desktop_files(&eff, &Default::default()).unwrap();
+
+        // the version current runs was deleted
+        fs::remove_dir_all(td.path().join("1.0.0")).unwrap();
+        let problems = super::integration_status(&eff).problems();
+        assert_eq!(problems.len(), 2, "{:?}", problems);
+        assert!(problems[0].contains("which is not an executable file"));
+
+        // written for another prefix
+        let other = tempdir().unwrap();
+        let moved = installed(other.path());
+        let entry = fs::read_to_string(&eff.desktop_file).unwrap();
+        fs::create_dir_all(moved.desktop_file.parent().unwrap()).unwrap();
+        fs::write(&moved.desktop_file, entry).unwrap();
+        let st = super::integration_status(&moved);
+        assert!(!st.fresh);
+        assert_eq!(
+            st.problems(),
+            [
+                format!(
+                    "desktop entry {} runs {}, which is not an executable file",
+                    moved.desktop_file.display(),
+                    eff.current_symlink.join("Windsurf/bin/windsurf").display()
+                ),
+                format!("desktop entry {} is outdated", moved.desktop_file.display())
+            ]
+        );
+
+        fs::write(&moved.desktop_file, "[Desktop Entry]\nName=Windsurf\n").unwrap();
+        assert!(super::integration_status(&moved).problems()[0].ends_with("has no Exec line"));
+    }
+
+    #[test]
+    fn a_refused_write_names_the_directory_behind_the_link() {
+        let td = tempdir().unwrap();
+        let real = td.path().join("ro");
+        fs::create_dir_all(&real).unwrap();
+        let link = td.path().join("applications");
+        symlink(&real, &link).unwrap();
+        let e = anyhow::Error::new(std::io::Error::from(std::io::ErrorKind::PermissionDenied))
+            .context("creating x");
+        let msg = format!("{:#}", super::explain_write_error(e, &link));
+        assert!(
+            msg.starts_with(&format!(
+                "cannot write the desktop entry: {} (a link to {}) is not writable",
+                link.display(),
+                real.display()
+            )),
+            "{}",
+            msg
+        );
+        // anything else is left as it is
+        let other = anyhow::anyhow!("bad");
+        assert_eq!(super::explain_write_error(other, &link).to_string(), "bad");
+    }
+
+    #[test]
+    fn rejects_lines_that_break_the_group() {
+        let eff = EffectivePaths::for_test(std::path::Path::new("/p"));
+        for bad in [
+            r#"extra_entries = ["[Desktop Action x]"]"#,
+            r#"extra_entries = ["NoEquals"]"#,
+            r#"extra_entries = ["Exec=/bin/sh"]"#,
+            r#"extra_entries = ["Key=a\nName=b"]"#,
+            r#"exec_args = "--x\n[Other]""#,
+            r#"actions = [{ name = "!!", exec = "" }]"#,
+            r#"actions = [{ name = "New", exec = "" }, { name = "new", exec = "" }]"#,
+        ] {
+            let cfg = desktop_cfg(&format!("[desktop]\n{}\n", bad));
+            assert!(
+                super::render_desktop_entry(&eff, &cfg).is_err(),
+                "accepted {}",
+                bad
+            );
+        }
     }
 }
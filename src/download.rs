@@ -1,41 +1,347 @@
+use crate::error::WindmanError;
+use crate::progress::{Progress, ProgressMode};
 use anyhow::{Context, Result};
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::HumanBytes;
 use reqwest::blocking::Client;
-use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
+use sha2::{Digest, Sha256};
 use std::{
     fs::{self, File},
     io::{Read, Write},
     path::{Path, PathBuf},
-    time::Duration,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
 };
 
 const DEFAULT_TIMEOUT_SECS: u64 = 30;
 
-/// Build a blocking reqwest client with default headers and a timeout.
+/// Bytes received by every download of this process (the operations log reports it).
+static BYTES_DOWNLOADED: AtomicU64 = AtomicU64::new(0);
+
+pub fn bytes_downloaded() -> u64 {
+    BYTES_DOWNLOADED.load(Ordering::Relaxed)
+}
+
+/// Build a blocking reqwest client with the configured headers and a timeout.
 fn build_client(timeout_secs: u64) -> Result<Client> {
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        USER_AGENT,
-        HeaderValue::from_static("Windman/0.1 (+https://github.com/your-org/windman)"),
-    );
+    crate::http::client(Duration::from_secs(timeout_secs))
+}
+
+/// Sees every byte of a download as it arrives (e.g. a signature verifier).
+pub trait Tap {
+    fn update(&mut self, bytes: &[u8]);
+}
+
+impl Tap for () {
+    fn update(&mut self, _: &[u8]) {}
+}
+
+impl<T: Tap> Tap for Option<T> {
+    fn update(&mut self, bytes: &[u8]) {
+        if let Some(t) = self {
+            t.update(bytes);
+        }
+    }
+}
+
+/// The transfer broke while reading the body (as opposed to bad data).
+#[derive(Debug, thiserror::Error)]
+#[error("download interrupted: {0}")]
+pub struct Interrupted(String);
+
+/// True when `e` comes from a broken transfer: worth retrying.
+pub fn is_interrupted(e: &anyhow::Error) -> bool {
+    e.chain().any(|c| {
+        c.is::<Interrupted>()
+            || c.downcast_ref::<std::io::Error>()
+                .and_then(|io| io.get_ref())
+                .is_some_and(|inner| inner.is::<Interrupted>())
+    })
+}
+
+/// Where a download body really came from, once redirects were followed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Origin {
+    /// URL of the response that sent the body
+    pub final_url: String,
+    /// `filename` of its `Content-Disposition` header
+    pub disposition: Option<String>,
+}
+
+impl Origin {
+    fn of(resp: &reqwest::blocking::Response) -> Self {
+        let disposition = resp
+            .headers()
+            .get(reqwest::header::CONTENT_DISPOSITION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(disposition_filename);
+        Self {
+            final_url: resp.url().to_string(),
+            disposition,
+        }
+    }
+
+    /// The name the server gives the file: the `Content-Disposition` one, else
+    /// the last segment of the final URL when it names an archive (a CDN
+    /// signer's UUID does not). None when neither says anything useful.
+    pub fn file_name(&self) -> Option<String> {
+        self.disposition.clone().or_else(|| {
+            let path = self.final_url.split(['?', '#']).next()?;
+            let name = crate::util::percent_decode(path.rsplit('/').next()?);
+            crate::cache::is_archive(&name).then_some(name)
+        })
+    }
+}
+
+/// `filename*` (RFC 5987, percent-encoded) or `filename` of a
+/// `Content-Disposition` value. Only the last path segment is kept: the name
+/// ends up in the cache dir.
+pub fn disposition_filename(value: &str) -> Option<String> {
+    let params: Vec<(String, &str)> = value
+        .split(';')
+        .skip(1)
+        .filter_map(|p| p.split_once('='))
+        .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim()))
+        .collect();
+    let param = |key: &str| params.iter().find(|(k, _)| k == key).map(|(_, v)| *v);
+    let name = match (param("filename*"), param("filename")) {
+        // charset'language'value
+        (Some(ext), _) => crate::util::percent_decode(ext.splitn(3, '\'').last()?),
+        (None, Some(plain)) => plain.trim_matches('"').to_string(),
+        (None, None) => return None,
+    };
+    let name = name.rsplit(['/', '\\']).next()?.trim();
+    (!name.is_empty() && name != "." && name != "..").then(|| name.to_string())
+}
+
+/// A finished download.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Downloaded {
+    /// hex SHA-256 of the body
+    pub sha256: String,
+    pub origin: Origin,
+}
+
+/// A download body being read, wherever it goes (a file, or the extractor with
+/// `update --stream`). Everything read is hashed, counted, shown on the
+/// progress bar and handed to `tap`, so the checksum is known as soon as the
+/// body ends (no second read).
+pub struct DownloadStream<R: Read = reqwest::blocking::Response, T: Tap = ()> {
+    inner: R,
+    hasher: Sha256,
+    tap: T,
+    pb: Progress,
+    report: Report,
+    started: Instant,
+    downloaded: u64,
+    len: Option<u64>,
+    origin: Origin,
+}
+
+impl<R: Read, T: Tap> DownloadStream<R, T> {
+    /// `len` sizes the bar (a spinner when unknown).
+    fn new(inner: R, len: Option<u64>, report: Report, tap: T) -> Result<Self> {
+        let pb = Progress::new(
+            report.progress,
+            "downloading",
+            len,
+            "{bar} {percent:>3}% {bytes}/{total_bytes} {bytes_per_sec} eta {eta}",
+        )?;
+        Ok(Self {
+            inner,
+            hasher: Sha256::new(),
+            tap,
+            pb,
+            report,
+            started: Instant::now(),
+            downloaded: 0,
+            len,
+            origin: Origin::default(),
+        })
+    }
+
+    /// The size the server announced (Content-Length), if any.
+    pub fn expected_len(&self) -> Option<u64> {
+        self.len
+    }
+
+    /// Where the body comes from (redirects followed).
+    pub fn origin(&self) -> &Origin {
+        &self.origin
+    }
+
+    /// Clear the bar, print the summary line (unless silent) and return the
+    /// hex SHA-256 of everything read, with the tap.
+    pub fn finish(self) -> (String, T) {
+        self.pb.finish_and_clear();
+        if self.report.summary {
+            println!("{}", summary_line(self.downloaded, self.started.elapsed()));
+        }
+        (hex::encode(self.hasher.finalize()), self.tap)
+    }
+}
+
+impl<R: Read, T: Tap> Read for DownloadStream<R, T> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self
+            .inner
+            .read(buf)
+            .map_err(|e| std::io::Error::other(Interrupted(e.to_string())))?;
+        self.hasher.update(&buf[..n]);
+        self.tap.update(&buf[..n]);
+        self.downloaded += n as u64;
+        BYTES_DOWNLOADED.fetch_add(n as u64, Ordering::Relaxed);
+        self.pb.set_position(self.downloaded);
+        Ok(n)
+    }
+}
 
-    let client = Client::builder()
-        .default_headers(headers)
-        .timeout(Duration::from_secs(timeout_secs))
-        .build()
-        .context("building HTTP client")?;
-    Ok(client)
+/// An archive piped in (`install --tar -`) read like a download body: hashed
+/// on the way, with a spinner while it lasts, but no summary line.
+pub fn read_piped<R: Read>(input: R, progress: ProgressMode) -> Result<DownloadStream<R>> {
+    DownloadStream::new(
+        input,
+        None,
+        Report {
+            progress,
+            summary: false,
+        },
+        (),
+    )
 }
 
+/// How much a download reports while running and when done.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Report {
+    progress: ProgressMode,
+    /// "Downloaded ... in ...s" on stdout at the end
+    summary: bool,
+}
+
+/// Nothing at all (small auxiliary files).
+const SILENT: Report = Report {
+    progress: ProgressMode::Hidden,
+    summary: false,
+};
+
 /// Download `url` to `dest`, with optional timeout override (in seconds).
-/// Writes atomically: to `dest.part` then renames to `dest` at the end.
+/// Writes atomically: to `dest.part.<pid>-<nanos>-<n>` (unique per process and call, so
+/// runs sharing a cache never write the same file) then renames to `dest` at the end.
+/// Returns the hex SHA-256 of the downloaded bytes and where they came from.
+/// Shows `progress` while running and prints a one-line summary when done.
 pub fn download_to_file_with_timeout(
     url: &str,
     dest: &Path,
     timeout_override: Option<u64>,
-) -> Result<()> {
-    let timeout = timeout_override.unwrap_or(DEFAULT_TIMEOUT_SECS);
+    progress: ProgressMode,
+) -> Result<Downloaded> {
+    let report = Report {
+        progress,
+        summary: true,
+    };
+    download(url, dest, timeout_override, report, None)
+}
+
+/// `download_to_file_with_timeout` for an archive about to be installed:
+/// `check_room` gets the announced size (Content-Length) before anything is
+/// written and may refuse the download.
+pub fn download_archive(
+    url: &str,
+    dest: &Path,
+    progress: ProgressMode,
+    check_room: &dyn Fn(u64) -> Result<()>,
+) -> Result<Downloaded> {
+    let report = Report {
+        progress,
+        summary: true,
+    };
+    download(url, dest, None, report, Some(check_room))
+}
 
+/// Like `download_to_file_with_timeout`, without any output (signatures and other small files).
+pub fn download_small_file(
+    url: &str,
+    dest: &Path,
+    timeout_override: Option<u64>,
+) -> Result<String> {
+    Ok(download(url, dest, timeout_override, SILENT, None)?.sha256)
+}
+
+/// Start downloading `url` without saving it: the caller reads the body (see
+/// `DownloadStream`). Same output rules as `download_to_file_with_timeout`.
+pub fn stream<T: Tap>(
+    url: &str,
+    timeout_override: Option<u64>,
+    progress: ProgressMode,
+    tap: T,
+) -> Result<DownloadStream<reqwest::blocking::Response, T>> {
+    let report = Report {
+        progress,
+        summary: true,
+    };
+    open(url, timeout_override, report, tap)
+}
+
+/// Download `url` without keeping it: its SHA-256 and size. Only `progress`
+/// is shown, stdout stays clean.
+pub fn hash_remote(
+    url: &str,
+    timeout_override: Option<u64>,
+    progress: ProgressMode,
+) -> Result<(String, u64)> {
+    let report = Report {
+        progress,
+        summary: false,
+    };
+    let mut s = open(url, timeout_override, report, ())?;
+    let n =
+        std::io::copy(&mut s, &mut std::io::sink()).with_context(|| format!("reading {}", url))?;
+    Ok((s.finish().0, n))
+}
+
+fn open<T: Tap>(
+    url: &str,
+    timeout_override: Option<u64>,
+    report: Report,
+    tap: T,
+) -> Result<DownloadStream<reqwest::blocking::Response, T>> {
+    let client = build_client(timeout_override.unwrap_or(DEFAULT_TIMEOUT_SECS))?;
+    let resp = crate::http::send(client.get(url))?;
+
+    let status = resp.status();
+    if !status.is_success() {
+        anyhow::bail!("unexpected status {} for {}", status, url);
+    }
+    // the size, like the name, is the final response's (not a redirect's)
+    let len = resp.content_length();
+    let origin = Origin::of(&resp);
+    let mut stream = DownloadStream::new(resp, len, report, tap)?;
+    stream.origin = origin;
+    Ok(stream)
+}
+
+/// "Downloaded 612.40 MiB in 41.0s (14.94 MiB/s)"
+fn summary_line(bytes: u64, elapsed: Duration) -> String {
+    let secs = elapsed.as_secs_f64();
+    let rate = if secs > 0.0 {
+        (bytes as f64 / secs) as u64
+    } else {
+        bytes
+    };
+    format!(
+        "Downloaded {} in {:.1}s ({}/s)",
+        HumanBytes(bytes),
+        secs,
+        HumanBytes(rate)
+    )
+}
+
+fn download(
+    url: &str,
+    dest: &Path,
+    timeout_override: Option<u64>,
+    report: Report,
+    check_room: Option<&dyn Fn(u64) -> Result<()>>,
+) -> Result<Downloaded> {
     // Ensure parent directory exists
     let parent: PathBuf = dest
         .parent()
@@ -43,66 +349,231 @@ pub fn download_to_file_with_timeout(
         .unwrap_or_else(|| PathBuf::from("."));
     if !parent.exists() {
         fs::create_dir_all(&parent)
-            .with_context(|| format!("creating parent directory {}", parent.display()))?;
+            .map_err(WindmanError::io("creating parent directory", &parent))?;
     }
 
     // Temp file in same directory for atomic rename at the end
-    let temp_path = dest.with_extension("part");
-    let client = build_client(timeout)?;
+    let temp_path = part_path(dest);
+    let mut body = open(url, timeout_override, report, ())?;
+    if let (Some(check), Some(len)) = (check_room, body.len) {
+        if let Err(e) = check(len) {
+            body.pb.finish_and_clear();
+            return Err(e);
+        }
+    }
+    let mut out =
+        File::create(&temp_path).map_err(WindmanError::io("creating temp file", &temp_path))?;
 
-    let resp = client
-        .get(url)
-        .send()
-        .with_context(|| format!("GET {}", url))?;
+    let mut buf = [0u8; 64 * 1024];
+    let copied: Result<()> = (|| loop {
+        crate::cancel::check()?;
+        let n = body.read(&mut buf)?;
+        if n == 0 {
+            return Ok(out.flush()?);
+        }
+        out.write_all(&buf[..n])?;
+    })();
 
-    let status = resp.status();
-    if !status.is_success() {
-        anyhow::bail!("unexpected status {} for {}", status, url);
+    if let Err(e) = copied {
+        // no resume support: a partial file is useless, don't leave it behind
+        body.pb.finish_and_clear();
+        drop(out);
+        discard_partial(&temp_path);
+        return Err(e);
     }
+    drop(out);
+    let origin = body.origin.clone();
+    let (sha256, ()) = body.finish();
 
-    // Progress (bar when Content-Length is known, spinner otherwise)
-    let len = resp.content_length();
-    let pb = match len {
-        Some(total) => {
-            let pb = ProgressBar::new(total);
-            pb.set_style(
-                ProgressStyle::with_template("{bar} {bytes}/{total_bytes} {eta}")?
-                    .progress_chars("#>-"),
+    // Atomic rename to final destination
+    fs::rename(&temp_path, dest).map_err(WindmanError::io(
+        &format!("renaming {} ->", temp_path.display()),
+        dest,
+    ))?;
+    Ok(Downloaded { sha256, origin })
+}
+
+/// `<dest>.part.<pid>-<nanos>-<n>`, next to `dest`.
+fn part_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(crate::util::unique_temp_name(".part."));
+    dest.with_file_name(name)
+}
+
+/// Remove an interrupted download's `.part` file (missing file is fine).
+pub(crate) fn discard_partial(part: &Path) {
+    let _ = fs::remove_file(part);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_line_reports_size_time_and_rate() {
+        assert_eq!(
+            summary_line(30 * 1024 * 1024, Duration::from_secs(2)),
+            "Downloaded 30.00 MiB in 2.0s (15.00 MiB/s)"
+        );
+        assert_eq!(
+            summary_line(512, Duration::ZERO),
+            "Downloaded 512 B in 0.0s (512 B/s)"
+        );
+    }
+
+    /// Answer one request per response, in order.
+    fn serve(responses: Vec<Vec<u8>>) -> String {
+        use std::io::Read;
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for resp in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let _ = stream.read(&mut [0u8; 4096]);
+                let _ = stream.write_all(&resp);
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    #[test]
+    fn redirects_are_followed_and_the_served_name_is_reported() {
+        let redirect = |to: &str| {
+            format!(
+                "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                to
+            )
+            .into_bytes()
+        };
+        let body = |headers: &str| {
+            format!(
+                "HTTP/1.1 200 OK\r\n{}Content-Length: 5\r\nConnection: close\r\n\r\nhello",
+                headers
+            )
+            .into_bytes()
+        };
+        let named =
+            "Content-Disposition: attachment; filename=\"Windsurf-linux-x64-1.13.0.tar.gz\"\r\n";
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("download.tar.gz");
+
+        for (to, headers, name) in [
+            // a CDN signer's UUID says nothing
+            ("/signed/7c1e2f4a-98d1?sig=x", "", None),
+            (
+                "/cdn/Windsurf-linux-x64-1.13.1.tar.gz?sig=x",
+                "",
+                Some("Windsurf-linux-x64-1.13.1.tar.gz"),
+            ),
+            (
+                "/signed/7c1e2f4a-98d1?sig=x",
+                named,
+                Some("Windsurf-linux-x64-1.13.0.tar.gz"),
+            ),
+        ] {
+            let base = serve(vec![redirect(to), body(headers)]);
+            let done = download_to_file_with_timeout(
+                &format!("{}/latest", base),
+                &dest,
+                Some(5),
+                ProgressMode::Hidden,
+            )
+            .unwrap();
+            assert_eq!(fs::read(&dest).unwrap(), b"hello");
+            assert_eq!(
+                done.sha256,
+                "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
             );
-            pb
+            assert_eq!(done.origin.final_url, format!("{}{}", base, to));
+            assert_eq!(done.origin.file_name().as_deref(), name, "{}", to);
         }
-        None => {
-            let pb = ProgressBar::new_spinner();
-            pb.set_style(ProgressStyle::with_template(
-                "{spinner} {bytes} downloaded",
-            )?);
-            pb.enable_steady_tick(Duration::from_millis(120));
-            pb
-        }
-    };
+    }
 
-    let mut reader = resp;
-    let mut out = File::create(&temp_path)
-        .with_context(|| format!("creating temp file {}", temp_path.display()))?;
+    #[test]
+    fn content_disposition_names_are_decoded_and_kept_to_one_segment() {
+        for (header, name) in [
+            ("attachment; filename=\"a.tar.gz\"", Some("a.tar.gz")),
+            ("attachment; filename=a.tar.gz", Some("a.tar.gz")),
+            (
+                "attachment; filename=\"x\"; filename*=UTF-8''Windsurf%20linux%201.13.0.tar.gz",
+                Some("Windsurf linux 1.13.0.tar.gz"),
+            ),
+            (
+                "attachment; filename=\"../../etc/a.tar.gz\"",
+                Some("a.tar.gz"),
+            ),
+            ("attachment; filename=\"..\"", None),
+            ("attachment", None),
+            ("inline; filename=\"\"", None),
+        ] {
+            assert_eq!(disposition_filename(header).as_deref(), name, "{}", header);
+        }
+    }
 
-    let mut buf = [0u8; 64 * 1024];
-    let mut downloaded: u64 = 0;
+    #[test]
+    fn discard_partial_removes_file_and_tolerates_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let part = dir.path().join("windsurf.tar.part");
+        fs::write(&part, b"half").unwrap();
+        discard_partial(&part);
+        assert!(!part.exists());
+        discard_partial(&part);
+    }
 
-    loop {
-        let n = reader.read(&mut buf)?;
-        if n == 0 {
-            break;
+    #[test]
+    fn stream_hashes_and_taps_what_it_reads() {
+        struct Count(usize);
+        impl Tap for Count {
+            fn update(&mut self, bytes: &[u8]) {
+                self.0 += bytes.len();
+            }
         }
-        out.write_all(&buf[..n])?;
-        downloaded += n as u64;
-        pb.set_position(downloaded);
+        let mut body =
+            DownloadStream::new(&b"hello world"[..], Some(11), SILENT, Count(0)).unwrap();
+        let mut out = Vec::new();
+        std::io::copy(&mut body, &mut out).unwrap();
+        let (digest, tap) = body.finish();
+        assert_eq!(out, b"hello world");
+        assert_eq!(tap.0, 11);
+        assert_eq!(
+            digest,
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+        );
     }
 
-    pb.finish_and_clear();
-
-    // Atomic rename to final destination
-    fs::rename(&temp_path, dest)
-        .with_context(|| format!("renaming {} -> {}", temp_path.display(), dest.display()))?;
+    #[test]
+    fn a_broken_body_is_reported_as_interrupted() {
+        struct Broken;
+        impl Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::ConnectionReset,
+                    "reset",
+                ))
+            }
+        }
+        let mut body = DownloadStream::new(Broken, None, SILENT, ()).unwrap();
+        let e = anyhow::Error::from(body.read(&mut [0u8; 8]).unwrap_err()).context("extracting");
+        assert!(is_interrupted(&e));
+        assert!(!is_interrupted(&anyhow::anyhow!("bad signature")));
+    }
 
-    Ok(())
+    #[test]
+    fn an_unwritable_destination_fails_as_io_before_any_request() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("file"), "").unwrap();
+        let dest = dir.path().join("file/sub/download.tar.gz");
+        // nothing listens there: reaching the network would fail differently
+        let err = download_to_file_with_timeout(
+            "http://127.0.0.1:9/latest",
+            &dest,
+            Some(1),
+            ProgressMode::Hidden,
+        )
+        .unwrap_err();
+        match err.downcast_ref::<WindmanError>() {
+            Some(WindmanError::Io { path, .. }) => assert_eq!(*path, dir.path().join("file/sub")),
+            other => panic!("{:?}", other),
+        }
+    }
 }
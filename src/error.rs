@@ -0,0 +1,167 @@
+use std::path::{Path, PathBuf};
+
+/// Failures of the core modules that callers tell apart, by matching on them
+/// rather than on message text. Like `InstallError` they travel inside
+/// `anyhow::Error` (found again with `downcast_ref`); `main` turns them into
+/// exit codes with `exit_code`.
+#[derive(Debug, thiserror::Error)]
+pub enum WindmanError {
+    /// No HTTP answer at all: DNS, connection, TLS or timeout
+    #[error("{request}")]
+    Network {
+        /// e.g. "GET https://windsurf-stable.codeium.com/api/update/..."
+        request: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    /// A signature or a recorded checksum does not match the data
+    #[error("verification failed for {what}: {reason}")]
+    VerificationFailed { what: String, reason: String },
+    /// The version is not among those installed under `dir`
+    #[error(
+        "version '{requested}' not found under {}.\nAvailable: {}",
+        dir.display(),
+        if available.is_empty() { "<none>".to_string() } else { available.join(", ") }
+    )]
+    VersionNotFound {
+        requested: String,
+        dir: PathBuf,
+        /// Installed versions, sorted
+        available: Vec<String>,
+    },
+    /// Nothing to run: the pinned or asked-for version (or any version) is not installed
+    #[error("{}", not_installed(version.as_deref(), pinned_by.as_ref()))]
+    NotInstalled {
+        version: Option<String>,
+        /// The `.windsurf-version` that asked for `version`
+        pinned_by: Option<PathBuf>,
+    },
+    /// The archive cannot be installed as it is
+    #[error("{archive}: {reason}")]
+    ArchiveInvalid { archive: String, reason: String },
+    /// `update` has nothing to install: current is the latest release, or was
+    /// installed from its very download
+    #[error(
+        "Already up to date (local: {local}, {}).",
+        match from_download {
+            Some(url) => format!("installed from the latest download {}", url),
+            None => format!("latest: {}", latest),
+        }
+    )]
+    AlreadyUpToDate {
+        local: String,
+        latest: String,
+        /// The URL current came from when no version numbers compare
+        from_download: Option<String>,
+    },
+    /// Another process holds a lock windman needs (a download into the cache)
+    #[error("{} is held by process {pid} (a download in progress).\nRetry once it is done.", lock.display())]
+    LockHeld { pid: u32, lock: PathBuf },
+    /// A file could not be read or written
+    #[error("{action} {}", path.display())]
+    Io {
+        /// e.g. "creating temp file"
+        action: String,
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+fn not_installed(version: Option<&str>, pinned_by: Option<&PathBuf>) -> String {
+    let Some(version) = version else {
+        return "no version pinned and Windsurf is not installed.\nRun `windman update` to install the latest release.".to_string();
+    };
+    let origin = match pinned_by {
+        Some(f) => format!(" (pinned by {})", f.display()),
+        None => String::new(),
+    };
+    format!(
+        "Windsurf {} is not installed{}.\nInstall it first, e.g. `windman install --tar Windsurf-linux-x64-{}.tar.gz`, or see `windman list`.",
+        version, origin, version
+    )
+}
+
+impl WindmanError {
+    /// For `map_err`: an I/O failure while `action` on `path`.
+    pub fn io(action: &str, path: &Path) -> impl FnOnce(std::io::Error) -> Self {
+        let action = action.to_string();
+        let path = path.to_path_buf();
+        move |source| WindmanError::Io {
+            action,
+            path,
+            source,
+        }
+    }
+
+    /// What the process exits with when this ends a command.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            WindmanError::Network { .. } => crate::EXIT_NETWORK,
+            WindmanError::VerificationFailed { .. } => crate::EXIT_VERIFICATION_FAILED,
+            WindmanError::VersionNotFound { .. } | WindmanError::NotInstalled { .. } => {
+                crate::EXIT_NOT_INSTALLED
+            }
+            WindmanError::ArchiveInvalid { .. } => crate::EXIT_ARCHIVE_INVALID,
+            WindmanError::AlreadyUpToDate { .. } => crate::EXIT_UP_TO_DATE,
+            // any other failure
+            WindmanError::LockHeld { .. } | WindmanError::Io { .. } => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_list_what_is_there_and_say_what_to_do() {
+        let missing = WindmanError::VersionNotFound {
+            requested: "1.9.9".into(),
+            dir: "/opt/ws/versions".into(),
+            available: vec!["1.12.7".into(), "1.13.0".into()],
+        };
+        assert_eq!(
+            missing.to_string(),
+            "version '1.9.9' not found under /opt/ws/versions.\nAvailable: 1.12.7, 1.13.0"
+        );
+        let none = WindmanError::VersionNotFound {
+            requested: "1.9.9".into(),
+            dir: "/opt/ws/versions".into(),
+            available: vec![],
+        };
+        assert!(none.to_string().ends_with("Available: <none>"));
+
+        let pinned = WindmanError::NotInstalled {
+            version: Some("1.12.7".into()),
+            pinned_by: Some("/src/app/.windsurf-version".into()),
+        };
+        assert!(pinned.to_string().starts_with(
+            "Windsurf 1.12.7 is not installed (pinned by /src/app/.windsurf-version).\n"
+        ));
+        assert_eq!(pinned.exit_code(), missing.exit_code());
+
+        let same = WindmanError::AlreadyUpToDate {
+            local: "1.12.7".into(),
+            latest: "1.12.7".into(),
+            from_download: None,
+        };
+        assert_eq!(
+            same.to_string(),
+            "Already up to date (local: 1.12.7, latest: 1.12.7)."
+        );
+        assert_eq!(same.exit_code(), crate::EXIT_UP_TO_DATE);
+    }
+
+    #[test]
+    fn io_errors_name_the_path_and_keep_the_cause() {
+        let e = WindmanError::io("reading", Path::new("/x/product.json"))(std::io::Error::from(
+            std::io::ErrorKind::NotFound,
+        ));
+        assert_eq!(e.to_string(), "reading /x/product.json");
+        let e = anyhow::Error::new(e);
+        assert!(e
+            .chain()
+            .any(|c| c.downcast_ref::<std::io::Error>().is_some()));
+    }
+}
@@ -0,0 +1,152 @@
+use anyhow::{bail, Context, Result};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// zstd level for exports: a good deal smaller than gzip, still fast to pack.
+const EXPORT_ZSTD_LEVEL: i32 = 9;
+
+/// What `export_version` wrote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exported {
+    pub archive: PathBuf,
+    /// `<archive>.sha256`, in `sha256sum` format
+    pub sidecar: PathBuf,
+    pub sha256: String,
+    pub entries: u64,
+}
+
+/// Sidecar checksum file next to an archive.
+pub fn sidecar_path(archive: &Path) -> PathBuf {
+    let mut s = archive.as_os_str().to_os_string();
+    s.push(".sha256");
+    PathBuf::from(s)
+}
+
+/// Pack an installed version dir into a `.tar.zst` that `install --tar` accepts.
+/// Entries are sorted and their owner and mtime normalized, so the same dir
+/// always gives the same archive. The per-install metadata file and the kept
+/// archive are left out (install writes fresh ones).
+pub fn export_version(version_dir: &Path, out: &Path) -> Result<Exported> {
+    let part = out.with_file_name(format!(
+        ".{}.part",
+        out.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    let entries = match write_archive(version_dir, &part) {
+        Ok(n) => n,
+        Err(e) => {
+            let _ = fs::remove_file(&part);
+            return Err(e);
+        }
+    };
+    fs::rename(&part, out).with_context(|| format!("writing {}", out.display()))?;
+
+    let sha256 = crate::meta::sha256_file(out)?;
+    let sidecar = sidecar_path(out);
+    fs::write(
+        &sidecar,
+        format!(
+            "{}  {}\n",
+            sha256,
+            out.file_name().unwrap_or_default().to_string_lossy()
+        ),
+    )
+    .with_context(|| format!("writing {}", sidecar.display()))?;
+    Ok(Exported {
+        archive: out.to_path_buf(),
+        sidecar,
+        sha256,
+        entries,
+    })
+}
+
+/// Copy the archive kept at install (`--keep-archive`) to `out` instead of
+/// packing the dir again, after checking it still has the checksum recorded
+/// then (`expected`).
+pub fn export_artifact(artifact: &Path, expected: Option<&str>, out: &Path) -> Result<Exported> {
+    let sha256 = crate::meta::sha256_file(artifact)?;
+    if let Some(want) = expected {
+        if !sha256.eq_ignore_ascii_case(want) {
+            bail!(
+                "kept archive {} does not match the checksum recorded at install (use --repack)",
+                artifact.display()
+            );
+        }
+    }
+    fs::copy(artifact, out)
+        .with_context(|| format!("copying {} to {}", artifact.display(), out.display()))?;
+    crate::cache::record_checksum(out, &sha256)?;
+    Ok(Exported {
+        archive: out.to_path_buf(),
+        sidecar: sidecar_path(out),
+        sha256,
+        entries: 0,
+    })
+}
+
+fn write_archive(version_dir: &Path, out: &Path) -> Result<u64> {
+    let file = File::create(out).with_context(|| format!("creating {}", out.display()))?;
+    let enc = zstd::Encoder::new(BufWriter::new(file), EXPORT_ZSTD_LEVEL)?;
+    let mut builder = tar::Builder::new(enc);
+    builder.mode(tar::HeaderMode::Deterministic);
+    builder.follow_symlinks(false);
+
+    let mut entries = 0;
+    for entry in WalkDir::new(version_dir)
+        .min_depth(1)
+        .follow_links(false)
+        .sort_by_file_name()
+    {
+        crate::cancel::check()?;
+        let entry = entry.with_context(|| format!("walking {}", version_dir.display()))?;
+        let rel = entry
+            .path()
+            .strip_prefix(version_dir)
+            .expect("walkdir stays under root");
+        if rel == Path::new(crate::meta::META_FILE) || rel.starts_with(crate::meta::ARTIFACT_DIR) {
+            continue;
+        }
+        builder
+            .append_path_with_name(entry.path(), rel)
+            .with_context(|| format!("adding {}", entry.path().display()))?;
+        entries += 1;
+    }
+    let mut w = builder.into_inner()?.finish()?;
+    w.flush()?;
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::tempdir;
+
+    #[test]
+    fn same_dir_gives_the_same_archive() {
+        let td = tempdir().unwrap();
+        let dir = td.path().join("1.0.0");
+        fs::create_dir_all(dir.join("Windsurf/bin")).unwrap();
+        fs::write(dir.join("Windsurf/bin/windsurf"), b"#!").unwrap();
+        fs::set_permissions(
+            dir.join("Windsurf/bin/windsurf"),
+            fs::Permissions::from_mode(0o755),
+        )
+        .unwrap();
+        fs::write(dir.join(crate::meta::META_FILE), b"{}").unwrap();
+
+        let a = export_version(&dir, &td.path().join("a.tar.zst")).unwrap();
+        // touch everything: mtimes must not leak into the archive
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        fs::write(dir.join("Windsurf/bin/windsurf"), b"#!").unwrap();
+        let b = export_version(&dir, &td.path().join("b.tar.zst")).unwrap();
+
+        assert_eq!(a.sha256, b.sha256);
+        assert_eq!(a.entries, 3);
+        assert_eq!(
+            fs::read_to_string(&a.sidecar).unwrap(),
+            format!("{}  a.tar.zst\n", a.sha256)
+        );
+    }
+}
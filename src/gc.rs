@@ -0,0 +1,326 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::paths::EffectivePaths;
+use crate::util::dir_size;
+
+/// Staging dirs younger than this may belong to an install still running.
+pub const STAGING_MIN_AGE: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    Versions,
+    OverBudget,
+    Staging,
+    Cache,
+    Trash,
+    Logs,
+}
+
+impl Category {
+    pub fn label(self) -> &'static str {
+        match self {
+            Category::Versions => "old versions",
+            Category::OverBudget => "over size limit",
+            Category::Staging => "staging leftovers",
+            Category::Cache => "cached downloads",
+            Category::Trash => "expired trash",
+            Category::Logs => "old log entries",
+        }
+    }
+}
+
+/// One path `gc` removes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    pub category: Category,
+    pub path: PathBuf,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Policy {
+    /// `[install]`: the prune policy (`keep`, `keep_per_minor`,
+    /// `protect_history`, `max_total_size`, ...) and `trash_grace_days`
+    pub install: crate::config::InstallConfig,
+    /// Also drop the cached tarball of the current version
+    pub aggressive: bool,
+}
+
+/// Everything a `gc` run at `now` removes, in removal order. Dry-run prints
+/// exactly this list and a real run hands it to `apply`.
+pub fn plan(eff: &EffectivePaths, policy: &Policy, now: DateTime<Utc>) -> Result<Vec<Item>> {
+    let item = |category, path: PathBuf| Item {
+        category,
+        bytes: dir_size(&path),
+        path,
+    };
+    let mut items = Vec::new();
+
+    // 1) versions, with the update policy; current, the rollback target and
+    //    versions newer than current survive unless the size limit needs them
+    let current = crate::util::resolve_symlink(&eff.current_symlink);
+    let decisions =
+        crate::prune::PrunePlan::for_install(eff, &policy.install, policy.install.keep)?.decide();
+    let over = crate::prune::over_budget(&decisions);
+    for p in crate::prune::removals(&decisions) {
+        let category = if over.contains(&p) {
+            Category::OverBudget
+        } else {
+            Category::Versions
+        };
+        items.push(item(category, p));
+    }
+
+    // 2) staging dirs left by interrupted installs and builds set aside
+    //    after a failed smoke test
+    if let Ok(rd) = fs::read_dir(&eff.versions_dir) {
+        let mut staging: Vec<PathBuf> = rd
+            .flatten()
+            .filter(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                name.starts_with(".staging-") || name.starts_with(".failed-")
+            })
+            .filter(|e| {
+                e.metadata()
+                    .and_then(|m| m.modified())
+                    .map(|t| {
+                        (now - DateTime::<Utc>::from(t))
+                            .to_std()
+                            .is_ok_and(|age| age >= STAGING_MIN_AGE)
+                    })
+                    .unwrap_or(false)
+            })
+            .map(|e| e.path())
+            .collect();
+        staging.sort();
+        items.extend(staging.into_iter().map(|p| item(Category::Staging, p)));
+    }
+
+    // 3) downloads of versions that are not installed once the prune is done
+    let installed: BTreeSet<String> = installed_names(&eff.versions_dir)
+        .into_iter()
+        .filter(|n| {
+            !items.iter().any(|i| {
+                matches!(i.category, Category::Versions | Category::OverBudget)
+                    && i.path.ends_with(n)
+            })
+        })
+        .collect();
+    let current_name = current
+        .as_deref()
+        .and_then(Path::file_name)
+        .map(|n| n.to_string_lossy().to_string());
+    for dir in crate::cache::version_dirs(&eff.cache_dir) {
+        let name = dir.file_name().unwrap().to_string_lossy().to_string();
+        let is_current = current_name.as_deref() == Some(name.as_str());
+        if !installed.contains(&name) || (policy.aggressive && is_current) {
+            items.push(item(Category::Cache, dir));
+        }
+    }
+
+    // 4) trash past its grace period
+    let expired = crate::trash::expired(&eff.versions_dir, Some(policy.install.trash_grace()), now);
+    items.extend(expired.into_iter().map(|e| item(Category::Trash, e.path)));
+
+    // 5) the oldest operations log entries past the size cap
+    let excess = crate::audit::excess(&eff.state_dir, crate::audit::MAX_LOG_BYTES);
+    if excess > 0 {
+        items.push(Item {
+            category: Category::Logs,
+            path: crate::audit::log_path(&eff.state_dir),
+            bytes: excess,
+        });
+    }
+
+    Ok(items)
+}
+
+/// Remove what `plan` returned. Versions go through the prune helper (and so
+/// honor `to_trash`), the log is trimmed and everything else, versions over
+/// the size limit included, is deleted.
+pub fn apply(eff: &EffectivePaths, items: &[Item], to_trash: bool) -> Result<()> {
+    let versions: Vec<PathBuf> = items
+        .iter()
+        .filter(|i| i.category == Category::Versions)
+        .map(|i| i.path.clone())
+        .collect();
+    crate::prune::remove_versions(&eff.versions_dir, &versions, to_trash)?;
+    for i in items.iter().filter(|i| i.category != Category::Versions) {
+        if i.category == Category::Logs {
+            crate::audit::trim(&eff.state_dir, crate::audit::MAX_LOG_BYTES)?;
+            continue;
+        }
+        let root = match i.category {
+            Category::Cache => eff.cache_dir.clone(),
+            Category::Trash => crate::trash::trash_dir(&eff.versions_dir),
+            _ => eff.versions_dir.clone(),
+        };
+        crate::util::remove_dir_within(&root, &i.path)?;
+    }
+    Ok(())
+}
+
+/// (category, item count, bytes) for each category present in `items`.
+pub fn summary(items: &[Item]) -> Vec<(Category, usize, u64)> {
+    let mut out: Vec<(Category, usize, u64)> = Vec::new();
+    for i in items {
+        match out.iter_mut().find(|(c, _, _)| *c == i.category) {
+            Some((_, n, bytes)) => {
+                *n += 1;
+                *bytes += i.bytes;
+            }
+            None => out.push((i.category, 1, i.bytes)),
+        }
+    }
+    out.sort_by_key(|(c, _, _)| *c);
+    out
+}
+
+fn installed_names(versions_dir: &Path) -> Vec<String> {
+    let Ok(rd) = fs::read_dir(versions_dir) else {
+        return Vec::new();
+    };
+    rd.flatten()
+        .filter(|e| e.path().is_dir())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .filter(|n| crate::util::is_version_dir_name(n))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{thread, time::Duration as StdDuration};
+    use tempfile::tempdir;
+
+    fn mkfile(p: &Path, len: usize) {
+        fs::create_dir_all(p.parent().unwrap()).unwrap();
+        fs::write(p, vec![0u8; len]).unwrap();
+    }
+
+    /// Versions 1.0.0 .. 1.0.3 (oldest to newest), current -> 1.0.3,
+    /// a cached tarball for each of them plus one for 0.9.0 (long gone).
+    fn populated(prefix: &Path) -> EffectivePaths {
+        let eff = EffectivePaths::for_test(prefix);
+        for v in ["1.0.0", "1.0.1", "1.0.2", "1.0.3"] {
+            mkfile(&eff.versions_dir.join(v).join("Windsurf/f"), 100);
+            thread::sleep(StdDuration::from_millis(10));
+        }
+        for v in ["0.9.0", "1.0.0", "1.0.3"] {
+            mkfile(
+                &eff.cache_dir
+                    .join("downloads/stable")
+                    .join(v)
+                    .join("w.tar.gz"),
+                10,
+            );
+        }
+        std::os::unix::fs::symlink(eff.versions_dir.join("1.0.3"), &eff.current_symlink).unwrap();
+        eff
+    }
+
+    fn policy(keep: usize, aggressive: bool) -> Policy {
+        let mut install = crate::config::Config::default().install;
+        install.keep = keep;
+        install.keep_per_minor = false;
+        install.keep_patterns.clear();
+        install.protect_history = 0;
+        install.prune_to_trash = false;
+        install.max_total_size = None;
+        install.trash_grace_days = 7;
+        Policy {
+            install,
+            aggressive,
+        }
+    }
+
+    fn names(items: &[Item], category: Category) -> Vec<String> {
+        items
+            .iter()
+            .filter(|i| i.category == category)
+            .map(|i| i.path.file_name().unwrap().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn plan_covers_every_category_and_dry_run_matches_the_real_run() {
+        let td = tempdir().unwrap();
+        let eff = populated(td.path());
+        let staging = eff.versions_dir.join(".staging-20250101000000");
+        mkfile(&staging.join("x"), 5);
+        let old_trash = eff.versions_dir.join(".trash/20200101000000-0.8.0");
+        mkfile(&old_trash.join("x"), 7);
+        let later = Utc::now() + chrono::Duration::hours(2);
+
+        let items = plan(&eff, &policy(0, false), later).unwrap();
+        // keep = 0: current and the rollback target (1.0.2) survive
+        assert_eq!(names(&items, Category::Versions), ["1.0.1", "1.0.0"]);
+        assert_eq!(
+            names(&items, Category::Staging),
+            [".staging-20250101000000"]
+        );
+        // 1.0.0 is pruned in the same run, so its tarball goes too
+        assert_eq!(names(&items, Category::Cache), ["0.9.0", "1.0.0"]);
+        assert_eq!(names(&items, Category::Trash), ["20200101000000-0.8.0"]);
+        assert_eq!(
+            summary(&items),
+            [
+                (Category::Versions, 2, 200),
+                (Category::Staging, 1, 5),
+                (Category::Cache, 2, 20),
+                (Category::Trash, 1, 7),
+            ]
+        );
+
+        apply(&eff, &items, false).unwrap();
+        for i in &items {
+            assert!(!i.path.exists(), "{} left behind", i.path.display());
+        }
+        assert!(eff.versions_dir.join("1.0.2").exists());
+        assert!(eff.versions_dir.join("1.0.3").exists());
+        assert!(eff.cache_dir.join("downloads/stable/1.0.3").exists());
+        assert!(plan(&eff, &policy(0, false), later).unwrap().is_empty());
+    }
+
+    #[test]
+    fn aggressive_drops_the_current_tarball_and_fresh_staging_is_kept() {
+        let td = tempdir().unwrap();
+        let eff = populated(td.path());
+        let staging = eff.versions_dir.join(".staging-20990101000000");
+        mkfile(&staging.join("x"), 5);
+
+        let items = plan(&eff, &policy(5, true), Utc::now()).unwrap();
+
+        assert!(names(&items, Category::Versions).is_empty());
+        assert_eq!(names(&items, Category::Cache), ["0.9.0", "1.0.3"]);
+        assert!(names(&items, Category::Staging).is_empty());
+    }
+
+    #[test]
+    fn an_oversized_operations_log_is_trimmed_not_deleted() {
+        let td = tempdir().unwrap();
+        let eff = EffectivePaths::for_test(td.path());
+        let log = crate::audit::log_path(&eff.state_dir);
+        let line = format!("{}\n", "x".repeat(1023));
+        let lines = crate::audit::MAX_LOG_BYTES as usize / line.len() + 3;
+        mkfile(&log, 0);
+        fs::write(&log, line.repeat(lines)).unwrap();
+
+        let items = plan(&eff, &policy(5, false), Utc::now()).unwrap();
+        assert_eq!(summary(&items), [(Category::Logs, 1, 3 * 1024)]);
+
+        apply(&eff, &items, false).unwrap();
+        assert_eq!(
+            fs::metadata(&log).unwrap().len(),
+            crate::audit::MAX_LOG_BYTES
+        );
+        assert!(plan(&eff, &policy(5, false), Utc::now())
+            .unwrap()
+            .is_empty());
+    }
+}
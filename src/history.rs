@@ -0,0 +1,148 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Switches of `current`, kept in the versions dir (hidden, so never taken for a version).
+pub const HISTORY_FILE: &str = ".windman-history.json";
+
+/// Switches remembered; older ones are dropped.
+const MAX_ENTRIES: usize = 50;
+
+/// One change of `current`, from one version dir name to another.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Switch {
+    /// None for the very first install
+    pub from: Option<String>,
+    pub to: String,
+    pub at: DateTime<Utc>,
+}
+
+/// `done` are switches in effect (newest last), `undone` those reverted by
+/// `rollback` and available to `rollforward` (most recently undone last).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct History {
+    #[serde(default)]
+    pub done: Vec<Switch>,
+    #[serde(default)]
+    pub undone: Vec<Switch>,
+}
+
+impl History {
+    pub fn load(versions_dir: &Path) -> Self {
+        fs::read_to_string(versions_dir.join(HISTORY_FILE))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, versions_dir: &Path) -> Result<()> {
+        let p = versions_dir.join(HISTORY_FILE);
+        fs::write(&p, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("writing {}", p.display()))
+    }
+
+    /// A new switch (install, update, use): like any new edit, it drops the redo stack.
+    pub fn push(&mut self, from: Option<&str>, to: &str) {
+        self.done.push(Switch {
+            from: from.map(str::to_string),
+            to: to.to_string(),
+            at: Utc::now(),
+        });
+        if self.done.len() > MAX_ENTRIES {
+            self.done.drain(..self.done.len() - MAX_ENTRIES);
+        }
+        self.undone.clear();
+    }
+}
+
+/// Record a switch; best-effort, a failure only warns (the switch itself happened).
+pub fn record(versions_dir: &Path, from: Option<&str>, to: &str) {
+    if from == Some(to) {
+        return;
+    }
+    let mut h = History::load(versions_dir);
+    h.push(from, to);
+    if let Err(e) = h.save(versions_dir) {
+        crate::ui::warn(format_args!("switch history not saved: {:#}", e));
+    }
+}
+
+/// Version dirs current was switched away from in the last `n` switches,
+/// newest first: where successive rollbacks go.
+pub fn recent_sources(versions_dir: &Path, n: usize) -> Vec<std::path::PathBuf> {
+    History::load(versions_dir)
+        .done
+        .iter()
+        .rev()
+        .take(n)
+        .filter_map(|s| s.from.as_deref())
+        .map(|f| versions_dir.join(f))
+        .collect()
+}
+
+/// Name of the version dir `current` points to.
+pub fn current_version_name(current_symlink: &Path) -> Option<String> {
+    crate::util::resolve_symlink(current_symlink)?
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn push_clears_redo_and_caps_length() {
+        let mut h = History::default();
+        h.undone.push(Switch {
+            from: Some("1.0.0".into()),
+            to: "1.0.1".into(),
+            at: Utc::now(),
+        });
+        h.push(Some("1.0.0"), "1.0.2");
+        assert!(h.undone.is_empty());
+
+        for i in 0..MAX_ENTRIES + 5 {
+            h.push(None, &format!("1.0.{}", i));
+        }
+        assert_eq!(h.done.len(), MAX_ENTRIES);
+        assert_eq!(
+            h.done.last().unwrap().to,
+            format!("1.0.{}", MAX_ENTRIES + 4)
+        );
+    }
+
+    #[test]
+    fn record_round_trips_and_skips_no_op_switches() {
+        let td = tempdir().unwrap();
+        record(td.path(), None, "1.0.0");
+        record(td.path(), Some("1.0.0"), "1.0.0");
+        record(td.path(), Some("1.0.0"), "1.0.1");
+
+        let h = History::load(td.path());
+        let pairs: Vec<_> = h
+            .done
+            .iter()
+            .map(|s| (s.from.as_deref(), s.to.as_str()))
+            .collect();
+        assert_eq!(pairs, [(None, "1.0.0"), (Some("1.0.0"), "1.0.1")]);
+    }
+
+    #[test]
+    fn recent_sources_are_the_froms_of_the_last_switches() {
+        let td = tempdir().unwrap();
+        record(td.path(), None, "1.0.0");
+        record(td.path(), Some("1.0.0"), "1.0.1");
+        record(td.path(), Some("1.0.1"), "1.0.2");
+        assert_eq!(
+            recent_sources(td.path(), 2),
+            [td.path().join("1.0.1"), td.path().join("1.0.0")]
+        );
+        // the first install came from nothing
+        assert_eq!(recent_sources(td.path(), 5).len(), 2);
+        assert!(recent_sources(td.path(), 0).is_empty());
+    }
+}
@@ -0,0 +1,555 @@
+use crate::config::NetworkConfig;
+use crate::error::WindmanError;
+use anyhow::{Context, Result};
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT};
+use reqwest::{Certificate, Proxy, Url};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+pub const DEFAULT_USER_AGENT: &str = concat!("windman/", env!("CARGO_PKG_VERSION"));
+
+/// Shown instead of the value of a secret-looking header.
+pub const REDACTED: &str = "<redacted>";
+
+/// Headers of every client built from now on (`configure`).
+static HEADERS: Mutex<Option<HeaderMap>> = Mutex::new(None);
+
+/// `network.proxy_url` when `proxy_enabled` (`configure`).
+static PROXY: Mutex<Option<String>> = Mutex::new(None);
+
+/// What HTTPS servers are checked against, with the certificates it adds
+/// (`configure`).
+static TRUST: Mutex<(Trust, Vec<Certificate>)> = Mutex::new((Trust::Builtin, Vec::new()));
+
+/// The certificates HTTPS servers must chain to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Trust {
+    /// The Mozilla roots built into windman
+    Builtin,
+    /// Those and the `certs` certificates of a PEM bundle (`network.ca_bundle`,
+    /// else `SSL_CERT_FILE`)
+    Bundle {
+        path: PathBuf,
+        from: &'static str,
+        certs: usize,
+    },
+    /// `network.danger_accept_invalid_certs`: nothing is checked
+    AcceptInvalid,
+}
+
+impl std::fmt::Display for Trust {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Trust::Builtin => write!(f, "built-in Mozilla roots"),
+            Trust::Bundle { path, from, certs } => write!(
+                f,
+                "built-in roots + {} ({} certificate{}, from {})",
+                path.display(),
+                certs,
+                if *certs == 1 { "" } else { "s" },
+                from
+            ),
+            Trust::AcceptInvalid => write!(
+                f,
+                "NONE: certificates are not checked (network.danger_accept_invalid_certs)"
+            ),
+        }
+    }
+}
+
+/// Send `[network] user_agent` and `extra_headers` with every request made
+/// after this (update API, releases page, downloads), through the configured
+/// proxy if there is one, trusting what `trust_from` says.
+pub fn configure(network: &NetworkConfig) -> Result<()> {
+    *HEADERS.lock().unwrap() = Some(default_headers(network)?);
+    *PROXY.lock().unwrap() = network
+        .proxy_url
+        .as_deref()
+        .map(str::trim)
+        .filter(|u| network.proxy_enabled && !u.is_empty())
+        .map(str::to_string);
+    let trust = trust_from(
+        network,
+        std::env::var_os("SSL_CERT_FILE").map(PathBuf::from),
+    )?;
+    if trust.0 == Trust::AcceptInvalid {
+        crate::ui::warn(
+            "network.danger_accept_invalid_certs is on: HTTPS certificates are NOT checked.\n\
+             Anyone between you and the server can hand you a tampered Windsurf; \
+             set network.ca_bundle to your proxy's root CA instead.",
+        );
+    }
+    *TRUST.lock().unwrap() = trust;
+    Ok(())
+}
+
+/// The trust `configure` sets up: `danger_accept_invalid_certs`, else the
+/// `ca_bundle` file, else `ssl_cert_file` (the `SSL_CERT_FILE` variable), else
+/// the built-in roots. A bundle that cannot be read or holds no certificate is
+/// an error naming where it was set.
+pub fn trust_from(
+    network: &NetworkConfig,
+    ssl_cert_file: Option<PathBuf>,
+) -> Result<(Trust, Vec<Certificate>)> {
+    if network.danger_accept_invalid_certs {
+        return Ok((Trust::AcceptInvalid, Vec::new()));
+    }
+    let configured = network
+        .ca_bundle
+        .as_deref()
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(|p| crate::paths::expand_vars(p).map(PathBuf::from))
+        .transpose()
+        .context("network.ca_bundle")?;
+    let (path, from) = match (
+        configured,
+        ssl_cert_file.filter(|p| !p.as_os_str().is_empty()),
+    ) {
+        (Some(p), _) => (p, "network.ca_bundle"),
+        (None, Some(p)) => (p, "SSL_CERT_FILE"),
+        (None, None) => return Ok((Trust::Builtin, Vec::new())),
+    };
+    let certs = load_bundle(&path).with_context(|| from.to_string())?;
+    let trust = Trust::Bundle {
+        path,
+        from,
+        certs: certs.len(),
+    };
+    Ok((trust, certs))
+}
+
+/// The certificates of a PEM file (at least one).
+pub fn load_bundle(path: &Path) -> Result<Vec<Certificate>> {
+    let pem = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    let certs = Certificate::from_pem_bundle(&pem)
+        .with_context(|| format!("{} is not a PEM certificate bundle", path.display()))?;
+    if certs.is_empty() {
+        anyhow::bail!("{} holds no PEM certificate", path.display());
+    }
+    Ok(certs)
+}
+
+/// The trust in effect (`configure`), for `remote check`.
+pub fn trust() -> Trust {
+    TRUST.lock().unwrap().0.clone()
+}
+
+/// The User-Agent (configured or windman's) and the extra headers. Fails on
+/// a name or value HTTP does not allow.
+pub fn default_headers(network: &NetworkConfig) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    let ua = network.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT);
+    let ua = HeaderValue::from_str(ua)
+        .with_context(|| format!("user_agent: '{}' is not a valid header value", ua))?;
+    headers.insert(USER_AGENT, ua);
+    for (name, value) in &network.extra_headers {
+        let n = HeaderName::from_bytes(name.as_bytes())
+            .with_context(|| format!("extra_headers: '{}' is not a valid header name", name))?;
+        let mut v = HeaderValue::from_str(value)
+            .with_context(|| format!("extra_headers.{}: not a valid header value", name))?;
+        v.set_sensitive(is_secret(name));
+        headers.insert(n, v);
+    }
+    Ok(headers)
+}
+
+/// A client sending the configured headers (windman's User-Agent alone when
+/// `configure` was not called).
+pub fn client(timeout: Duration) -> Result<Client> {
+    client_with(sent_headers(), timeout)
+}
+
+pub fn client_with(headers: HeaderMap, timeout: Duration) -> Result<Client> {
+    let mut builder = Client::builder()
+        .default_headers(headers)
+        // replaces reqwest's own reading of the environment, so that what
+        // `proxy_for` reports is what is used
+        .proxy(Proxy::custom(proxy_for))
+        .timeout(timeout);
+    let (trust, certs) = &*TRUST.lock().unwrap();
+    for cert in certs {
+        builder = builder.add_root_certificate(cert.clone());
+    }
+    if *trust == Trust::AcceptInvalid {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    builder.build().context("building HTTP client")
+}
+
+/// The proxy a request to `url` goes through, if any: `network.proxy_url`
+/// (when `proxy_enabled`), else `HTTPS_PROXY`/`HTTP_PROXY` by scheme, else
+/// `ALL_PROXY`; none for hosts listed in `NO_PROXY`. `http://`, `https://`,
+/// `socks5://` (names resolved here) and `socks5h://` (by the proxy) work.
+pub fn proxy_for(url: &Url) -> Option<String> {
+    let configured = PROXY.lock().unwrap().clone();
+    proxy_from(url, configured.as_deref(), |k| std::env::var(k).ok())
+}
+
+fn proxy_from(
+    url: &Url,
+    configured: Option<&str>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Option<String> {
+    let var = |name: &str| {
+        env(name)
+            .or_else(|| env(&name.to_ascii_lowercase()))
+            .filter(|v| !v.trim().is_empty())
+    };
+    let host = url
+        .host_str()?
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    if var("NO_PROXY").is_some_and(|list| no_proxy_matches(&list, host)) {
+        return None;
+    }
+    let by_scheme = match url.scheme() {
+        "https" => var("HTTPS_PROXY"),
+        "http" => var("HTTP_PROXY"),
+        _ => None,
+    };
+    configured
+        .map(str::to_string)
+        .or(by_scheme)
+        .or_else(|| var("ALL_PROXY"))
+        .map(|p| p.trim().to_string())
+}
+
+/// `NO_PROXY` entries: `*`, or a host name matching itself and its subdomains
+/// (a leading dot is optional), or an IP address.
+fn no_proxy_matches(list: &str, host: &str) -> bool {
+    list.split(',').map(str::trim).any(|entry| {
+        let entry = entry.trim_start_matches('.');
+        entry == "*"
+            || (!entry.is_empty()
+                && (host.eq_ignore_ascii_case(entry)
+                    || host
+                        .to_ascii_lowercase()
+                        .ends_with(&format!(".{}", entry.to_ascii_lowercase()))))
+    })
+}
+
+/// A proxy URL for display: the password, if any, redacted.
+pub fn describe_proxy(proxy: &str) -> String {
+    match Url::parse(proxy).ok().as_ref().and_then(Url::password) {
+        Some(pw) => proxy.replacen(&format!(":{}@", pw), &format!(":{}@", REDACTED), 1),
+        None => proxy.to_string(),
+    }
+}
+
+/// Send a request; the error names the method and URL. When a `socks5://`
+/// proxy is in use and the host name does not resolve here, it says to let
+/// the proxy resolve it (`socks5h://`).
+pub fn send(req: RequestBuilder) -> Result<Response> {
+    let (client, req) = req.build_split();
+    let req = req.context("building HTTP request")?;
+    let what = format!("{} {}", req.method(), req.url());
+    let url = req.url().clone();
+    client.execute(req).map_err(|e| {
+        let hint = socks_dns_hint(&url, proxy_for(&url).as_deref(), &e)
+            .or_else(|| untrusted_cert_hint(&e));
+        let err = anyhow::Error::new(WindmanError::Network {
+            request: what,
+            source: e,
+        });
+        match hint {
+            Some(h) => err.context(h),
+            None => err,
+        }
+    })
+}
+
+fn socks_dns_hint(url: &Url, proxy: Option<&str>, err: &dyn std::error::Error) -> Option<String> {
+    let host = url.domain()?;
+    if !proxy?.trim().to_ascii_lowercase().starts_with("socks5://") {
+        return None;
+    }
+    let mut source = Some(err);
+    while let Some(e) = source {
+        let msg = e.to_string().to_ascii_lowercase();
+        if msg.contains("dns") || msg.contains("lookup address") || msg.contains("resolve") {
+            return Some(format!(
+                "{} does not resolve on this machine: a socks5:// proxy gets addresses \
+                 resolved here; use socks5h:// to have the proxy resolve names",
+                host
+            ));
+        }
+        source = e.source();
+    }
+    None
+}
+
+/// The server's certificate did not chain to anything trusted: say how to
+/// trust a corporate root CA.
+fn untrusted_cert_hint(err: &(dyn std::error::Error + 'static)) -> Option<String> {
+    std::iter::successors(Some(err), |e| e.source())
+        .any(|e| e.to_string().contains("certificate"))
+        .then(|| {
+            format!(
+                "the server's TLS certificate is not trusted ({}). Behind a TLS-intercepting \
+                 proxy, set network.ca_bundle (or SSL_CERT_FILE) to its root CA (PEM)",
+                trust()
+            )
+        })
+}
+
+/// A request refused the server's certificate (`send` said how to trust it):
+/// the service is not down, this machine does not trust it.
+pub fn is_untrusted_cert(e: &anyhow::Error) -> bool {
+    matches!(e.downcast_ref::<WindmanError>(), Some(WindmanError::Network { source, .. })
+        if untrusted_cert_hint(source).is_some())
+}
+
+/// What every request carries besides its own headers.
+pub fn sent_headers() -> HeaderMap {
+    HEADERS.lock().unwrap().clone().unwrap_or_else(|| {
+        let mut h = HeaderMap::new();
+        h.insert(USER_AGENT, HeaderValue::from_static(DEFAULT_USER_AGENT));
+        h
+    })
+}
+
+/// Headers whose value is a credential, going by the name: `Authorization`,
+/// `Cookie`, or anything mentioning a token, secret, key, password or session.
+pub fn is_secret(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.contains("auth")
+        || name.contains("cookie")
+        || ["token", "secret", "key", "password", "passwd", "session"]
+            .iter()
+            .any(|w| name.contains(w))
+}
+
+/// `value`, or `REDACTED` for a secret-looking header.
+pub fn redact<'a>(name: &str, value: &'a str) -> &'a str {
+    if is_secret(name) {
+        REDACTED
+    } else {
+        value
+    }
+}
+
+/// `name: value` of each header, secrets redacted (for `--verbose`).
+pub fn describe(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .iter()
+        .map(|(n, v)| {
+            let v = v.to_str().unwrap_or("(binary)");
+            format!("{}: {}", n, redact(n.as_str(), v))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    fn network(ua: Option<&str>, extra: &[(&str, &str)]) -> NetworkConfig {
+        let mut n = crate::config::Config::default().network;
+        n.user_agent = ua.map(str::to_string);
+        n.extra_headers = extra
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        n
+    }
+
+    #[test]
+    fn configured_headers_are_sent() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 4096];
+            let n = stream.read(&mut buf).unwrap();
+            let _ = stream.write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n");
+            String::from_utf8_lossy(&buf[..n]).to_lowercase()
+        });
+
+        let headers = default_headers(&network(
+            Some("acme-updater/2 (ops@acme.test)"),
+            &[("X-Corp-Token", "s3cr3t"), ("X-Team", "desktop")],
+        ))
+        .unwrap();
+        let client = client_with(headers, Duration::from_secs(5)).unwrap();
+        client.get(&url).send().unwrap();
+
+        let request = server.join().unwrap();
+        assert!(
+            request.contains("user-agent: acme-updater/2 (ops@acme.test)\r\n"),
+            "{}",
+            request
+        );
+        assert!(request.contains("x-corp-token: s3cr3t\r\n"), "{}", request);
+        assert!(request.contains("x-team: desktop\r\n"), "{}", request);
+    }
+
+    #[test]
+    fn secret_looking_values_are_redacted() {
+        let headers = default_headers(&network(
+            None,
+            &[("X-Corp-Token", "s3cr3t"), ("X-Team", "d")],
+        ))
+        .unwrap();
+        assert_eq!(
+            describe(&headers),
+            [
+                format!("user-agent: {}", DEFAULT_USER_AGENT),
+                "x-corp-token: <redacted>".to_string(),
+                "x-team: d".to_string(),
+            ]
+        );
+        for name in [
+            "Authorization",
+            "Proxy-Authorization",
+            "Cookie",
+            "X-Api-Key",
+        ] {
+            assert!(is_secret(name), "{}", name);
+        }
+        assert!(!is_secret("X-Request-Id"));
+    }
+
+    #[test]
+    fn configured_proxy_then_scheme_then_all_proxy_unless_no_proxy() {
+        let url = |u: &str| Url::parse(u).unwrap();
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |k: &str| {
+                vars.iter()
+                    .find(|(n, _)| *n == k)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+        let lab = env(&[
+            ("ALL_PROXY", "socks5h://127.0.0.1:1080"),
+            ("https_proxy", "http://corp:3128"),
+            ("NO_PROXY", "localhost, .internal.test"),
+        ]);
+        let via = |u: &str, configured| proxy_from(&url(u), configured, lab);
+
+        assert_eq!(
+            via("https://windsurf.com/", None).as_deref(),
+            Some("http://corp:3128")
+        );
+        assert_eq!(
+            via("http://windsurf.com/", None).as_deref(),
+            Some("socks5h://127.0.0.1:1080")
+        );
+        assert_eq!(
+            via("https://windsurf.com/", Some("socks5://10.0.0.1:1080")).as_deref(),
+            Some("socks5://10.0.0.1:1080")
+        );
+        assert_eq!(via("http://localhost:8080/", None), None);
+        assert_eq!(
+            via("https://mirror.internal.test/a", Some("http://p:1")),
+            None
+        );
+        assert_eq!(
+            via("https://notinternal.test/", None).as_deref(),
+            Some("http://corp:3128")
+        );
+        assert_eq!(
+            proxy_from(&url("https://windsurf.com/"), None, env(&[])),
+            None
+        );
+
+        assert_eq!(
+            describe_proxy("socks5h://lab:pw@127.0.0.1:1080"),
+            "socks5h://lab:<redacted>@127.0.0.1:1080"
+        );
+    }
+
+    #[test]
+    fn a_name_a_socks5_proxy_cannot_use_suggests_socks5h() {
+        let url = Url::parse("https://windsurf.com/editor/releases").unwrap();
+        let dns = std::io::Error::other("dns error: failed to lookup address information");
+        let hint = socks_dns_hint(&url, Some("socks5://127.0.0.1:1080"), &dns).unwrap();
+        assert!(hint.contains("use socks5h://"), "{}", hint);
+        assert!(hint.starts_with("windsurf.com "), "{}", hint);
+
+        assert_eq!(
+            socks_dns_hint(&url, Some("socks5h://127.0.0.1:1080"), &dns),
+            None
+        );
+        assert_eq!(socks_dns_hint(&url, None, &dns), None);
+        let refused = std::io::Error::other("connection refused");
+        assert_eq!(
+            socks_dns_hint(&url, Some("socks5://127.0.0.1:1080"), &refused),
+            None
+        );
+    }
+
+    #[test]
+    fn no_answer_is_a_network_error() {
+        // bound, then closed: nothing listens there
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let client = client(Duration::from_secs(5)).unwrap();
+        let err = send(client.get(format!("http://127.0.0.1:{}/api", port))).unwrap_err();
+        match err.downcast_ref::<WindmanError>() {
+            Some(WindmanError::Network { request, .. }) => {
+                assert_eq!(request, &format!("GET http://127.0.0.1:{}/api", port))
+            }
+            other => panic!("expected Network, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn ca_bundle_then_ssl_cert_file_unless_nothing_is_checked() {
+        let ca = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/tls/ca.pem");
+        let mut n = network(None, &[]);
+        assert_eq!(trust_from(&n, None).unwrap().0, Trust::Builtin);
+
+        let (trust, certs) = trust_from(&n, Some(ca.into())).unwrap();
+        assert_eq!(
+            trust,
+            Trust::Bundle {
+                path: ca.into(),
+                from: "SSL_CERT_FILE",
+                certs: 1
+            }
+        );
+        assert_eq!(certs.len(), 1);
+
+        n.ca_bundle = Some(ca.to_string());
+        let (trust, _) = trust_from(&n, Some("/nonexistent.pem".into())).unwrap();
+        assert!(trust
+            .to_string()
+            .ends_with("(1 certificate, from network.ca_bundle)"));
+
+        n.danger_accept_invalid_certs = true;
+        assert_eq!(trust_from(&n, None).unwrap().0, Trust::AcceptInvalid);
+    }
+
+    #[test]
+    fn a_bundle_without_certificates_is_refused() {
+        let td = tempfile::tempdir().unwrap();
+        let empty = td.path().join("empty.pem");
+        std::fs::write(&empty, "not a certificate\n").unwrap();
+        let mut n = network(None, &[]);
+        n.ca_bundle = Some(empty.display().to_string());
+        let err = format!("{:#}", trust_from(&n, None).unwrap_err());
+        assert!(err.starts_with("network.ca_bundle: "), "{}", err);
+        assert!(err.contains("holds no PEM certificate"), "{}", err);
+
+        let err = format!(
+            "{:#}",
+            trust_from(&network(None, &[]), Some(td.path().join("missing.pem"))).unwrap_err()
+        );
+        assert!(err.starts_with("SSL_CERT_FILE: reading "), "{}", err);
+    }
+
+    #[test]
+    fn invalid_names_and_values_are_refused() {
+        let err = default_headers(&network(None, &[("X Bad", "v")])).unwrap_err();
+        assert!(err.to_string().contains("'X Bad'"), "{}", err);
+        assert!(default_headers(&network(None, &[("X-Ok", "line\nbreak")])).is_err());
+        assert!(default_headers(&network(Some("ua\r\n"), &[])).is_err());
+    }
+}
@@ -0,0 +1,305 @@
+use indicatif::HumanBytes;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Files from this size on get their size printed next to their name.
+pub const SHOW_SIZE_FROM: u64 = 1 << 20;
+/// A directory of more entries than this is summarized, not listed.
+pub const BIG_DIR_ENTRIES: usize = 100;
+/// Directories always summarized: their entries say nothing about the layout.
+const SUMMARIZED_DIRS: &[&str] = &["node_modules"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    Dir,
+    File,
+    Symlink,
+}
+
+/// One entry of the tree. A directory's `files` and `bytes` count everything
+/// under it, listed or not.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Node {
+    pub name: String,
+    pub kind: Kind,
+    pub bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub files: Option<u64>,
+    /// Where a symlink points
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// A directory whose entries are not listed: too deep or too big
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub summarized: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Node>,
+}
+
+/// What `inspect` shows of an installed version.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Inspection {
+    pub version: String,
+    pub path: PathBuf,
+    pub files: u64,
+    pub bytes: u64,
+    /// The executable the shim runs, relative to `path`
+    pub executable: Option<PathBuf>,
+    /// The product.json the version is read from, relative to `path`
+    pub product_json: Option<PathBuf>,
+    /// The entries of `path`
+    pub tree: Vec<Node>,
+}
+
+/// The tree of `dir` (the install of `version`) down to `depth` levels;
+/// directories of more than `big_dir` entries are summarized at any level.
+pub fn inspect(version: &str, dir: &Path, depth: usize, big_dir: usize) -> Inspection {
+    let (files, bytes) = crate::util::dir_usage(dir);
+    let relative = |p: PathBuf| p.strip_prefix(dir).map(Path::to_path_buf).unwrap_or(p);
+    Inspection {
+        version: version.to_string(),
+        path: dir.to_path_buf(),
+        files,
+        bytes,
+        executable: crate::util::find_windsurf_executable(dir).map(relative),
+        product_json: crate::version::find_product_json(dir).map(relative),
+        tree: entries(dir, depth, big_dir),
+    }
+}
+
+/// The entries of `dir`, directories first, then by name.
+fn entries(dir: &Path, depth: usize, big_dir: usize) -> Vec<Node> {
+    let Ok(read) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut nodes: Vec<Node> = read
+        .filter_map(|e| e.ok())
+        .filter_map(|e| node(&e.path(), depth, big_dir))
+        .collect();
+    nodes.sort_by(|a, b| {
+        (a.kind != Kind::Dir)
+            .cmp(&(b.kind != Kind::Dir))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    nodes
+}
+
+fn node(path: &Path, depth: usize, big_dir: usize) -> Option<Node> {
+    let meta = fs::symlink_metadata(path).ok()?;
+    let name = path.file_name()?.to_string_lossy().into_owned();
+    let mut node = Node {
+        name,
+        kind: Kind::File,
+        bytes: 0,
+        files: None,
+        target: None,
+        summarized: false,
+        children: Vec::new(),
+    };
+    if meta.file_type().is_symlink() {
+        node.kind = Kind::Symlink;
+        node.target = fs::read_link(path)
+            .ok()
+            .map(|t| t.to_string_lossy().into_owned());
+    } else if meta.is_dir() {
+        let (files, bytes) = crate::util::dir_usage(path);
+        node.kind = Kind::Dir;
+        node.files = Some(files);
+        node.bytes = bytes;
+        let listed = fs::read_dir(path).map(|d| d.count()).unwrap_or(0);
+        if depth <= 1 || listed > big_dir || SUMMARIZED_DIRS.contains(&node.name.as_str()) {
+            node.summarized = listed > 0;
+        } else {
+            node.children = entries(path, depth - 1, big_dir);
+        }
+    } else {
+        node.bytes = meta.len();
+    }
+    Some(node)
+}
+
+/// 12431 as "12,431".
+fn count(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn plural(n: u64, what: &str) -> String {
+    format!("{} {}{}", count(n), what, if n == 1 { "" } else { "s" })
+}
+
+/// The tree as `inspect` prints it: a heading, where the executable and
+/// product.json are, then one line per entry, the detected files marked.
+pub fn render(i: &Inspection) -> Vec<String> {
+    let shown = |p: &Option<PathBuf>| {
+        p.as_ref()
+            .map(|p| p.display().to_string())
+            .unwrap_or("<not found>".to_string())
+    };
+    let mut lines = vec![
+        format!(
+            "{} ({}, {}, {})",
+            i.version,
+            i.path.display(),
+            HumanBytes(i.bytes),
+            plural(i.files, "file")
+        ),
+        format!("Executable   : {}", shown(&i.executable)),
+        format!("product.json : {}", shown(&i.product_json)),
+        String::new(),
+    ];
+    let marks = [
+        (i.executable.as_deref(), "executable"),
+        (i.product_json.as_deref(), "product.json"),
+    ];
+    tree_lines(&i.tree, Path::new(""), "", &marks, &mut lines);
+    lines
+}
+
+fn tree_lines(
+    nodes: &[Node],
+    parent: &Path,
+    prefix: &str,
+    marks: &[(Option<&Path>, &str)],
+    out: &mut Vec<String>,
+) {
+    for (n, node) in nodes.iter().enumerate() {
+        let last = n + 1 == nodes.len();
+        let rel = parent.join(&node.name);
+        let mut line = format!(
+            "{}{}{}",
+            prefix,
+            if last { "└── " } else { "├── " },
+            node.name
+        );
+        match node.kind {
+            Kind::Dir => {
+                line.push('/');
+                if node.summarized {
+                    line += &format!(
+                        "  … {}, {}",
+                        plural(node.files.unwrap_or(0), "file"),
+                        HumanBytes(node.bytes)
+                    );
+                }
+            }
+            Kind::Symlink => {
+                line += &format!(" -> {}", node.target.as_deref().unwrap_or("?"));
+            }
+            Kind::File if node.bytes >= SHOW_SIZE_FROM => {
+                line += &format!("  ({})", HumanBytes(node.bytes));
+            }
+            Kind::File => {}
+        }
+        for (path, what) in marks {
+            if *path == Some(rel.as_path()) {
+                line += &format!(
+                    "  {}",
+                    crate::ui::paint(crate::ui::HIGHLIGHT, format!("← {}", what))
+                );
+            }
+        }
+        out.push(line);
+        let child_prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+        tree_lines(&node.children, &rel, &child_prefix, marks, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Windsurf/bin/windsurf, resources/app/{product.json, node_modules/a/ and
+    /// locales/, of `files` files each}, a big file and a symlink.
+    fn layout(root: &Path, files: usize) {
+        let app = root.join("Windsurf/resources/app");
+        fs::create_dir_all(app.join("node_modules/a")).unwrap();
+        fs::create_dir_all(app.join("locales")).unwrap();
+        for n in 0..files {
+            fs::write(app.join(format!("node_modules/a/{}.js", n)), "x").unwrap();
+            fs::write(app.join(format!("locales/{}.pak", n)), "x").unwrap();
+        }
+        fs::write(app.join("product.json"), r#"{"windsurfVersion":"1.2.3"}"#).unwrap();
+        fs::create_dir_all(root.join("Windsurf/bin")).unwrap();
+        let exe = root.join("Windsurf/bin/windsurf");
+        fs::write(&exe, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&exe, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(root.join("Windsurf/big.pak"), vec![0u8; 3 << 20]).unwrap();
+        std::os::unix::fs::symlink("bin/windsurf", root.join("Windsurf/launcher")).unwrap();
+    }
+
+    fn plain(lines: Vec<String>) -> String {
+        anstream::adapter::strip_str(&lines.join("\n")).to_string()
+    }
+
+    #[test]
+    fn tree_lists_dirs_first_and_summarizes_big_dirs() {
+        let td = tempfile::tempdir().unwrap();
+        layout(td.path(), 6);
+        let i = inspect("1.2.3", td.path(), 4, 5);
+        assert_eq!(i.files, 15);
+        assert_eq!(
+            i.executable.as_deref(),
+            Some(Path::new("Windsurf/bin/windsurf"))
+        );
+        assert_eq!(
+            i.product_json.as_deref(),
+            Some(Path::new("Windsurf/resources/app/product.json"))
+        );
+        let text = plain(render(&i));
+        let tree = text.split_once("\n\n").unwrap().1;
+        assert_eq!(
+            tree,
+            "└── Windsurf/
+    ├── bin/
+    │   └── windsurf  ← executable
+    ├── resources/
+    │   └── app/
+    │       ├── locales/  … 6 files, 6 B
+    │       ├── node_modules/  … 6 files, 6 B
+    │       └── product.json  ← product.json
+    ├── big.pak  (3.00 MiB)
+    └── launcher -> bin/windsurf"
+        );
+    }
+
+    #[test]
+    fn depth_cuts_the_tree_and_json_keeps_the_counts() {
+        let td = tempfile::tempdir().unwrap();
+        layout(td.path(), 2);
+        let i = inspect("1.2.3", td.path(), 2, BIG_DIR_ENTRIES);
+        let text = plain(render(&i));
+        assert!(
+            text.contains("├── resources/  … 5 files, 31 B\n"),
+            "{}",
+            text
+        );
+        assert!(!text.contains("product.json  ←"), "{}", text);
+        // the detected files are named even when the tree does not reach them
+        assert!(
+            text.contains("product.json : Windsurf/resources/app/product.json"),
+            "{}",
+            text
+        );
+
+        let v = serde_json::to_value(&i).unwrap();
+        let windsurf = &v["tree"][0];
+        assert_eq!(windsurf["kind"], "dir");
+        assert_eq!(windsurf["files"], 7);
+        let resources = &windsurf["children"][1];
+        assert_eq!(resources["name"], "resources");
+        assert_eq!(resources["summarized"], true);
+        assert!(resources.get("children").is_none());
+        assert_eq!(count(12431), "12,431");
+        assert_eq!(count(999), "999");
+    }
+}
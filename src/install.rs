@@ -1,126 +1,1213 @@
+use crate::error::WindmanError;
 use anyhow::{bail, Context, Result};
 use flate2::read::GzDecoder;
+use indicatif::{HumanBytes, HumanDuration};
 use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::CommandExt;
 use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
 use tar::Archive;
 
+use crate::config::ExtractFsync;
 use crate::paths::EffectivePaths;
+use crate::progress::{Progress, ProgressMode};
+use crate::report::Verification;
 use crate::util::{atomic_symlink_switch, timestamp_version, write_shim};
-/// Install from a .tar.gz path. Returns the resolved version string used.
-pub fn install_from_tar(tar_path: &str, eff: &EffectivePaths) -> Result<String> {
+
+/// Per-run knobs for `install_from_tar` (CLI flags, not persisted config).
+#[derive(Debug, Clone, Default)]
+pub struct InstallOptions {
+    /// Suppress summaries
+    pub quiet: bool,
+    /// How extraction shows progress
+    pub progress: ProgressMode,
+    /// Install archives without a Windsurf executable or whose executable targets
+    /// another CPU architecture, and replace an existing version dir whose content differs
+    pub force: bool,
+    /// Run this check on the new version before switching current (None = skip)
+    pub smoke_test: Option<SmokeTest>,
+    /// Origin recorded in the version metadata (None = the tarball path)
+    pub source: Option<String>,
+    /// SHA-256 already computed while downloading (None = hash the tarball)
+    pub sha256: Option<String>,
+    /// Print phase timings
+    pub verbose: bool,
+    /// Switch current to a lower version without asking
+    pub allow_downgrade: bool,
+    /// Update notice the shim runs before launching (install.shim_update_notice)
+    pub shim_notice: Option<crate::util::ShimNotice>,
+    /// Do not write the shim (install.manage_shim = false, `--no-shim`)
+    pub no_shim: bool,
+    /// Refuse, instead of warning about, a build needing a newer glibc than
+    /// the system's (install.strict_compat_check)
+    pub strict_compat_check: bool,
+    /// Keep the archive in `<version dir>/.artifact/` (install.keep_archive)
+    pub keep_archive: bool,
+    /// Stop once the version dir is in place: current, the switch history and
+    /// the shim are left alone (`install --no-activate`, `--target-dir`)
+    pub no_activate: bool,
+    /// Versions refused unless `force` (`windman block`)
+    pub blocked: std::collections::BTreeSet<String>,
+    /// Refuse, instead of warning, to switch current while Windsurf runs from
+    /// another version (`--require-closed`, install.block_if_running)
+    pub require_closed: bool,
+    /// Expected extracted size over the archive size, for the message when
+    /// extraction runs out of space (install.extract_space_factor; 0 = unknown)
+    pub extract_space_factor: f64,
+    /// Release channel recorded in the metadata (None: not from a channel)
+    pub channel: Option<String>,
+    /// What is flushed to disk before the version moves into place
+    /// (install.extract_fsync)
+    pub extract_fsync: ExtractFsync,
+    /// URL the download ended at, recorded in the metadata (None: not downloaded)
+    pub final_url: Option<String>,
+    /// How the caller checked the archive (None: recorded as unsigned, unless
+    /// a `.sha256` next to it matched)
+    pub verification: Option<Verification>,
+}
+
+/// Command run against a freshly extracted version; must exit 0 within `timeout`.
+#[derive(Debug, Clone)]
+pub struct SmokeTest {
+    /// Shell command; empty means `{exe} --version`
+    pub cmd: String,
+    pub timeout: Duration,
+}
+
+/// Install failures callers may want to tell apart (e.g. for exit codes).
+#[derive(Debug, thiserror::Error)]
+pub enum InstallError {
+    #[error("smoke test failed for {version}: {reason}\nThe extracted build was set aside at {dir} for debugging; current was not changed.")]
+    SmokeTestFailed {
+        version: String,
+        dir: PathBuf,
+        reason: String,
+    },
+    #[error("version {version} is already installed at {dir} from a different archive\nPass --force to replace it.")]
+    VersionExists { version: String, dir: PathBuf },
+    #[error("refusing to downgrade {from} → {to}\nPass --allow-downgrade to switch anyway.")]
+    DowngradeRefused { from: String, to: String },
+    #[error("Windsurf {version} is blocked\nPass --force to install it anyway, or `windman unblock {version}`.")]
+    Blocked { version: String },
+    #[error("Windsurf (pid {pid}) is running from {running}; current was not switched to {version}\nClose it, then run `windman use {version}`.")]
+    StillRunning {
+        pid: u32,
+        running: String,
+        version: String,
+    },
+    #[error("{file} needs GLIBC_{needs} but the system glibc ({libc}) is {system}; this build would not start here\nKeep the current version, or pass --force to install anyway.")]
+    GlibcTooOld {
+        file: PathBuf,
+        needs: crate::compat::GlibcVersion,
+        libc: PathBuf,
+        system: crate::compat::GlibcVersion,
+    },
+}
+
+/// True when `to` is a lower semver than `from` (names that are not semver never are).
+pub fn is_downgrade(from: &str, to: &str) -> bool {
+    match (semver::Version::parse(from), semver::Version::parse(to)) {
+        (Ok(from), Ok(to)) => to < from,
+        _ => false,
+    }
+}
+
+/// Gate for anything about to switch current from `from` to `to`: a downgrade is
+/// announced and only goes ahead with `allow` or a yes (`confirm`, so also
+/// `--assume-yes`). Without a terminal it is refused, `util::NotInteractive` as the cause.
+/// Running Windsurf instances: the lowest PID running from each version dir
+/// (one instance has many processes), by version.
+pub fn running_instances(versions_dir: &Path) -> Vec<(u32, String)> {
+    let Ok(root) = fs::canonicalize(versions_dir) else {
+        return Vec::new();
+    };
+    let mut out: Vec<(u32, String)> = Vec::new();
+    for (pid, exe) in crate::util::processes_running_from(&root) {
+        let Some(version) = exe.strip_prefix(&root).ok().and_then(|p| p.iter().next()) else {
+            continue;
+        };
+        let version = version.to_string_lossy().to_string();
+        // staging and trash dirs are not versions
+        if !version.starts_with('.') && !out.iter().any(|(_, v)| *v == version) {
+            out.push((pid, version));
+        }
+    }
+    out
+}
+
+/// Before switching current to `version`: Windsurf running from another
+/// version may crash when it loads resources through the changed path. Warns,
+/// or refuses with `require_closed`.
+pub fn guard_running(versions_dir: &Path, version: &str, require_closed: bool) -> Result<()> {
+    for (pid, running) in running_instances(versions_dir) {
+        if running == version {
+            continue;
+        }
+        if require_closed {
+            return Err(InstallError::StillRunning {
+                pid,
+                running,
+                version: version.to_string(),
+            }
+            .into());
+        }
+        crate::ui::warn(format_args!(
+            "Windsurf (pid {}) is running from {}; it may misbehave until restarted",
+            pid, running
+        ));
+    }
+    Ok(())
+}
+
+pub fn guard_downgrade(from: Option<&str>, to: &str, allow: bool) -> Result<()> {
+    let Some(from) = from.filter(|f| is_downgrade(f, to)) else {
+        return Ok(());
+    };
+    eprintln!("downgrading {} → {}", from, to);
+    let refused = InstallError::DowngradeRefused {
+        from: from.to_string(),
+        to: to.to_string(),
+    };
+    if allow {
+        return Ok(());
+    }
+    match crate::util::confirm("Switch current to the older version?") {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(refused.into()),
+        Err(e) => Err(e.context(refused)),
+    }
+}
+
+/// Read buffer for the compressed stream during extraction.
+const EXTRACT_BUF_SIZE: usize = 1024 * 1024;
+
+/// What `extract_tar_to_dir` unpacked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExtractStats {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// Install from a .tar.gz or .tar.zst path. Returns the resolved version string used.
+pub fn install_from_tar(
+    tar_path: &str,
+    eff: &EffectivePaths,
+    opts: &InstallOptions,
+) -> Result<String> {
+    fs::create_dir_all(&eff.versions_dir)
+        .with_context(|| format!("creating {}", eff.versions_dir.display()))?;
+
+    // A `<archive>.sha256` next to the archive (as `export` writes) must match
+    let mut sha256 = opts.sha256.clone();
+    let mut verification = opts.verification;
+    let sidecar = crate::export::sidecar_path(Path::new(tar_path));
+    if sidecar.is_file() {
+        let expected = fs::read_to_string(&sidecar)
+            .with_context(|| format!("reading {}", sidecar.display()))?;
+        let expected = expected.split_whitespace().next().unwrap_or_default();
+        let actual = crate::meta::sha256_file(Path::new(tar_path))?;
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(WindmanError::VerificationFailed {
+                what: tar_path.to_string(),
+                reason: format!(
+                    "checksum mismatch: {} says {}, archive is {}",
+                    sidecar.display(),
+                    expected,
+                    actual
+                ),
+            }
+            .into());
+        }
+        sha256 = Some(actual);
+        verification = verification.or(Some(Verification::Checksum));
+    }
+
+    // Extract the archive
+    let staging = new_staging(eff)?;
+    let started = Instant::now();
+    let stats = match extract_tar_to_dir(tar_path, &staging, opts.progress) {
+        Ok(stats) => stats,
+        Err(e) => {
+            discard_staging(&staging);
+            if is_truncation_error(&e) {
+                return Err(e.context(WindmanError::ArchiveInvalid {
+                    archive: tar_path.to_string(),
+                    reason: "the archive ends early (an incomplete download?)".to_string(),
+                }));
+            }
+            let need = fs::metadata(tar_path)
+                .ok()
+                .filter(|_| opts.extract_space_factor > 0.0)
+                .map(|m| (m.len() as f64 * opts.extract_space_factor) as u64);
+            return Err(crate::space::explain_extract_error(
+                e,
+                &eff.versions_dir,
+                need,
+            ));
+        }
+    };
+    report_extracted(&stats, started, opts);
+
+    let sha256 = match sha256 {
+        Some(d) => Some(d),
+        None => crate::meta::sha256_file(Path::new(tar_path)).ok(),
+    };
+    let opts = InstallOptions {
+        verification,
+        ..opts.clone()
+    };
+    install_staged(&staging, tar_path, sha256, eff, &opts)
+}
+
+/// Install from an archive read once, front to back (the download body with
+/// `update --stream`): nothing but the staging dir is written. `name` stands in
+/// for the file name (version guess, messages). `finish` gets the input back
+/// once it is read to the end and returns the archive's SHA-256, or an error
+/// (e.g. a bad signature) that drops the staging dir before anything moves.
+pub fn install_from_stream<R: Read>(
+    mut input: R,
+    name: &str,
+    eff: &EffectivePaths,
+    opts: &InstallOptions,
+    finish: impl FnOnce(R) -> Result<String>,
+) -> Result<String> {
     fs::create_dir_all(&eff.versions_dir)
         .with_context(|| format!("creating {}", eff.versions_dir.display()))?;
+    let staging = new_staging(eff)?;
+    let started = Instant::now();
+    let unpacked =
+        unpack_archive(&mut input, name, &staging, &Progress::hidden()).and_then(|stats| {
+            // whatever follows the tar end still counts for the checksum
+            std::io::copy(&mut input, &mut std::io::sink())
+                .with_context(|| format!("reading {}", name))?;
+            Ok(stats)
+        });
+    let sha256 = match unpacked.and_then(|stats| Ok((stats, finish(input)?))) {
+        Ok((stats, sha256)) => {
+            report_extracted(&stats, started, opts);
+            sha256
+        }
+        Err(e) => {
+            discard_staging(&staging);
+            return Err(crate::space::explain_extract_error(
+                e,
+                &eff.versions_dir,
+                None,
+            ));
+        }
+    };
+    install_staged(&staging, name, Some(sha256), eff, opts)
+}
 
-    // Staging dir (atomic move later)
+/// A fresh `.staging-<timestamp>` dir in the versions dir (moved into place later).
+fn new_staging(eff: &EffectivePaths) -> Result<PathBuf> {
     let staging = eff
         .versions_dir
         .join(format!(".staging-{}", timestamp_version()));
-    if staging.exists() {
-        fs::remove_dir_all(&staging).ok();
+    crate::util::remove_dir_within(&eff.versions_dir, &staging).ok();
+    fs::create_dir_all(&staging)
+        .with_context(|| format!("creating {}", staging.display()))
+        .map_err(|e| crate::space::explain_extract_error(e, &eff.versions_dir, None))?;
+    Ok(staging)
+}
+
+fn report_extracted(stats: &ExtractStats, started: Instant, opts: &InstallOptions) {
+    if !opts.quiet {
+        println!(
+            "Extracted {} files ({}) in {}",
+            stats.files,
+            HumanBytes(stats.bytes),
+            HumanDuration(started.elapsed())
+        );
     }
-    fs::create_dir_all(&staging)?;
+    if opts.verbose {
+        eprintln!(
+            "[windman] extracted in {:.1}s",
+            started.elapsed().as_secs_f64()
+        );
+    }
+}
 
-    // Extract tar.gz
-    extract_tar_to_dir(tar_path, &staging)?;
+/// Everything past extraction: checks, version name, metadata, move into
+/// place, smoke test, switch of current and shim. `archive` is the archive
+/// path or name (version guess, messages, default source).
+fn install_staged(
+    staging: &Path,
+    archive: &str,
+    sha256: Option<String>,
+    eff: &EffectivePaths,
+    opts: &InstallOptions,
+) -> Result<String> {
+    // Refuse archives that are not a Windsurf build, or built for another
+    // architecture, before anything moves
+    if !opts.force {
+        let checked = crate::util::check_windsurf_layout(staging, archive)
+            .and_then(|()| crate::util::check_host_arch(staging))
+            .and_then(|()| {
+                check_glibc(
+                    staging,
+                    crate::compat::system_glibc(),
+                    opts.strict_compat_check,
+                )
+            });
+        if let Err(e) = checked {
+            discard_staging(staging);
+            return Err(e);
+        }
+    }
 
     // mémoriser la current avant bascule (si elle existe)
-    let _previous_current = std::fs::read_link(&eff.current_symlink).ok();
+    let previous_current = crate::history::current_version_name(&eff.current_symlink);
 
     // Determine version:
     // 1) from tar filename
-    let ver_from_filename = extract_version_from_filename(tar_path);
+    let ver_from_filename = extract_version_from_filename(archive);
 
     // 2) from product.json if present
-    let ver_from_product = detect_version_from_product_json(&staging).ok();
+    let ver_from_product = detect_version_from_product_json(staging).ok();
 
     // 3) choose dir name
-    let version = ver_from_filename
-        .or(ver_from_product)
-        .unwrap_or_else(timestamp_version);
+    if let (Some(f), Some(p)) = (&ver_from_filename, &ver_from_product) {
+        if !same_release(f, p) && !opts.quiet {
+            crate::ui::warn(format_args!(
+                "archive name says {} but product.json says {}; using {}",
+                f, p, f
+            ));
+        }
+    }
+    let version =
+        pick_version_name(ver_from_filename, ver_from_product).unwrap_or_else(timestamp_version);
 
     let final_dir = eff.versions_dir.join(&version);
 
-    // If target exists already, remove it before rename (overwrite)
+    if !opts.force && opts.blocked.contains(&version) {
+        discard_staging(staging);
+        return Err(InstallError::Blocked { version }.into());
+    }
+    if !opts.no_activate {
+        if let Err(e) = guard_downgrade(previous_current.as_deref(), &version, opts.allow_downgrade)
+        {
+            discard_staging(staging);
+            return Err(e);
+        }
+    }
+
+    // An existing dir of the same name is only replaced with --force; the same
+    // archive installed again reuses it as is
+    let mut reuse_existing = false;
     if final_dir.exists() {
-        fs::remove_dir_all(&final_dir)
-            .with_context(|| format!("removing pre-existing {}", final_dir.display()))?;
+        let same_archive =
+            sha256.is_some() && crate::meta::read(&final_dir).and_then(|m| m.sha256) == sha256;
+        if opts.force {
+            crate::util::remove_dir_within(&eff.versions_dir, &final_dir)
+                .with_context(|| format!("removing pre-existing {}", final_dir.display()))?;
+        } else if same_archive {
+            reuse_existing = true;
+        } else {
+            discard_staging(staging);
+            return Err(InstallError::VersionExists {
+                version,
+                dir: final_dir,
+            }
+            .into());
+        }
+    }
+
+    // Record where this version came from
+    let source = match &opts.source {
+        Some(s) => s.clone(),
+        None => fs::canonicalize(archive)
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|_| archive.to_string()),
+    };
+    crate::meta::write(
+        staging,
+        &crate::meta::VersionMeta {
+            version: version.clone(),
+            installed_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            source,
+            sha256: sha256.clone(),
+            artifact: None,
+            channel: opts.channel.clone(),
+            final_url: opts.final_url.clone(),
+            verification: Some(opts.verification.unwrap_or(Verification::Unsigned)),
+            windman_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            hostname: crate::util::hostname(),
+        },
+    )?;
+
+    // The verified archive goes along with the version (--keep-archive)
+    if opts.keep_archive {
+        let dir = if reuse_existing { &final_dir } else { staging };
+        match (Path::new(archive).is_file(), &sha256) {
+            (true, Some(sum)) => {
+                if let Err(e) = crate::meta::keep_artifact(dir, Path::new(archive), sum) {
+                    discard_staging(staging);
+                    return Err(e);
+                }
+            }
+            _ => crate::ui::warn("--keep-archive: no archive file to keep (streamed install)"),
+        }
+    }
+
+    // Last point where Ctrl-C aborts: past the move the install runs to completion,
+    // so 'current' only ever switches to a complete dir
+    if let Err(e) = crate::cancel::check() {
+        discard_staging(staging);
+        return Err(e);
+    }
+
+    // Move staging -> final (copy fallback if the prefix moved to another filesystem)
+    if reuse_existing {
+        discard_staging(staging);
+    } else {
+        let started = Instant::now();
+        if let Err(e) = sync_staged(staging, opts.extract_fsync) {
+            discard_staging(staging);
+            return Err(e);
+        }
+        crate::util::move_dir_robust(staging, &final_dir)?;
+        if opts.extract_fsync != ExtractFsync::None {
+            crate::util::fsync_path(&eff.versions_dir)?;
+        }
+        if opts.verbose {
+            let synced = match opts.extract_fsync {
+                ExtractFsync::None => "none",
+                ExtractFsync::Dir => "dir",
+                ExtractFsync::All => "all",
+            };
+            eprintln!(
+                "[windman] synced (extract_fsync = {}) and moved into place in {:.1}s",
+                synced,
+                started.elapsed().as_secs_f64()
+            );
+        }
     }
 
-    // Move staging -> final
-    fs::rename(&staging, &final_dir)
-        .with_context(|| format!("moving {} -> {}", staging.display(), final_dir.display()))?;
+    // Smoke test before exposing the new version through 'current'. A new
+    // build that fails it leaves the version dirs, so that neither `list`,
+    // `rollback` nor prune ever takes it for an installed version
+    if let Some(st) = &opts.smoke_test {
+        if let Err(reason) = run_smoke_test(&final_dir, st) {
+            let dir = if reuse_existing {
+                final_dir
+            } else {
+                set_aside_failed(&eff.versions_dir, &final_dir, &version)
+            };
+            return Err(InstallError::SmokeTestFailed {
+                version,
+                dir,
+                reason,
+            }
+            .into());
+        }
+    }
+
+    if opts.no_activate {
+        return Ok(version);
+    }
+
+    guard_running(&eff.versions_dir, &version, opts.require_closed)?;
 
     // Update 'current' symlink atomically
+    crate::version::forget_cached_version(&eff.state_dir, &final_dir);
     atomic_symlink_switch(&final_dir, &eff.current_symlink)?;
+    crate::history::record(&eff.versions_dir, previous_current.as_deref(), &version);
 
     // Ensure bin dir exists and write shim
-    fs::create_dir_all(&eff.bin_dir)?;
-    write_shim(&eff.bin_shim, &eff.current_symlink)?;
+    if !opts.no_shim {
+        fs::create_dir_all(&eff.bin_dir)?;
+        write_shim(&eff.bin_shim, &eff.shim_link(), opts.shim_notice.as_ref())?;
+    }
 
     Ok(version)
 }
 
-pub fn rollback(eff: &EffectivePaths) -> Result<()> {
+/// What `rollback` would do: the switch it undoes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RollbackPlan {
+    /// The switch undone; its `from` becomes current
+    pub undone: crate::history::Switch,
+    /// It is the last recorded switch (else the newest other version by mtime)
+    pub from_history: bool,
+}
+
+impl RollbackPlan {
+    /// The version current goes back to.
+    pub fn target(&self) -> &str {
+        self.undone.from.as_deref().unwrap_or_default()
+    }
+
+    /// Why `target` was picked, for `rollback --dry-run`.
+    pub fn reason(&self) -> String {
+        if self.from_history {
+            format!(
+                "undoes the switch {} -> {} recorded {}",
+                self.target(),
+                self.undone.to,
+                self.undone
+                    .at
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M")
+            )
+        } else {
+            "newest other version by mtime (no recorded switch to undo)".to_string()
+        }
+    }
+}
+
+/// Where `rollback` goes: the last switch recorded in the history; without
+/// one (or when its `from` is gone), the newest other version by mtime.
+/// Touches nothing.
+pub fn rollback_plan(eff: &EffectivePaths) -> Result<RollbackPlan> {
     use std::fs;
+    let cur_target = crate::util::resolve_symlink(&eff.current_symlink)
+        .with_context(|| format!("reading {}", eff.current_symlink.display()))?;
+    let cur_name = cur_target
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let history = crate::history::History::load(&eff.versions_dir);
+    if let Some(last) = history.done.last().filter(|s| {
+        s.to == cur_name
+            && s.from
+                .as_ref()
+                .is_some_and(|f| eff.versions_dir.join(f).is_dir())
+    }) {
+        return Ok(RollbackPlan {
+            undone: last.clone(),
+            from_history: true,
+        });
+    }
     // List versions
     let mut dirs = list_version_dirs(&eff.versions_dir)?;
-    if dirs.len() < 2 {
-        bail!("no previous version to roll back to");
-    }
-    // current target
-    let cur_target = fs::read_link(&eff.current_symlink)
-        .with_context(|| format!("reading {}", eff.current_symlink.display()))?;
     // Remove current from list
-    dirs.retain(|p| p != &cur_target);
+    dirs.retain(|p| fs::canonicalize(p).ok().as_ref() != Some(&cur_target));
     // Pick the most recent by mtime
     dirs.sort_by_key(|p| fs::metadata(p).and_then(|m| m.modified()).ok());
-    let prev = dirs.pop().unwrap();
-    atomic_symlink_switch(&prev, &eff.current_symlink)?;
-    println!(
-        "Rolled back to {}",
-        prev.file_name().unwrap().to_string_lossy()
-    );
-    Ok(())
+    let Some(prev) = dirs.pop() else {
+        bail!("no previous version to roll back to");
+    };
+    Ok(RollbackPlan {
+        undone: crate::history::Switch {
+            from: Some(prev.file_name().unwrap().to_string_lossy().to_string()),
+            to: cur_name,
+            at: chrono::Utc::now(),
+        },
+        from_history: false,
+    })
+}
+
+/// Carry out `rollback_plan`. Either way the undone switch is kept for
+/// `rollforward`. Returns the version now current.
+pub fn rollback(eff: &EffectivePaths) -> Result<String> {
+    let plan = rollback_plan(eff)?;
+    let prev = plan.target().to_string();
+    atomic_symlink_switch(&eff.versions_dir.join(&prev), &eff.current_symlink)?;
+    let mut history = crate::history::History::load(&eff.versions_dir);
+    if plan.from_history {
+        history.done.pop();
+    }
+    history.undone.push(plan.undone);
+    if let Err(e) = history.save(&eff.versions_dir) {
+        crate::ui::warn(format_args!("switch history not saved: {:#}", e));
+    }
+    println!("Rolled back to {}", prev);
+    Ok(prev)
+}
+
+/// Where `rollback` can go, in the order it considers them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RollbackCandidate {
+    pub name: String,
+    /// When current was switched away from it; None when only picked by mtime
+    pub left_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub on_disk: bool,
+}
+
+/// The versions successive rollbacks would land on: first the chain of
+/// recorded switches back from current, then the other version dirs, newest
+/// mtime first.
+pub fn rollback_candidates(eff: &EffectivePaths) -> Result<Vec<RollbackCandidate>> {
+    let current = crate::history::current_version_name(&eff.current_symlink);
+    let mut out: Vec<RollbackCandidate> = Vec::new();
+    let mut at = current.clone();
+    for s in crate::history::History::load(&eff.versions_dir)
+        .done
+        .iter()
+        .rev()
+    {
+        if at.as_deref() != Some(s.to.as_str()) {
+            break;
+        }
+        let Some(from) = &s.from else {
+            break;
+        };
+        if Some(from) != current.as_ref() && !out.iter().any(|c| &c.name == from) {
+            out.push(RollbackCandidate {
+                name: from.clone(),
+                left_at: Some(s.at),
+                on_disk: eff.versions_dir.join(from).is_dir(),
+            });
+        }
+        at = Some(from.clone());
+    }
+    let mut dirs = list_version_dirs(&eff.versions_dir)?;
+    dirs.sort_by_key(|p| std::cmp::Reverse(fs::metadata(p).and_then(|m| m.modified()).ok()));
+    for p in dirs {
+        let name = p.file_name().unwrap().to_string_lossy().to_string();
+        if Some(&name) != current.as_ref() && !out.iter().any(|c| c.name == name) {
+            out.push(RollbackCandidate {
+                name,
+                left_at: None,
+                on_disk: true,
+            });
+        }
+    }
+    Ok(out)
 }
 
-pub fn uninstall_all(eff: &EffectivePaths, purge: bool) -> Result<()> {
-    // Remove symlink & shim
-    let _ = fs::remove_file(&eff.current_symlink);
-    let _ = fs::remove_file(&eff.bin_shim);
+/// Redo the switch the last `rollback` undid. Returns the version now current.
+pub fn rollforward(eff: &EffectivePaths) -> Result<String> {
+    let mut history = crate::history::History::load(&eff.versions_dir);
+    let Some(redo) = history.undone.pop() else {
+        bail!("nothing to roll forward (no rollback since the last switch)");
+    };
+    let current = crate::history::current_version_name(&eff.current_symlink);
+    if current != redo.from {
+        bail!(
+            "cannot roll forward: the last rollback left {} current, but current is now {}",
+            redo.from.as_deref().unwrap_or("nothing"),
+            current.as_deref().unwrap_or("unset")
+        );
+    }
+    let target = eff.versions_dir.join(&redo.to);
+    if !target.is_dir() {
+        bail!("cannot roll forward: {} is no longer installed", redo.to);
+    }
+    atomic_symlink_switch(&target, &eff.current_symlink)?;
+    let to = redo.to.clone();
+    history.done.push(redo);
+    if let Err(e) = history.save(&eff.versions_dir) {
+        crate::ui::warn(format_args!("switch history not saved: {:#}", e));
+    }
+    println!("Rolled forward to {}", to);
+    Ok(to)
+}
+
+/// One path `uninstall` removes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Removal {
+    /// A file or symlink
+    File(PathBuf),
+    /// A directory and everything in it
+    Tree(PathBuf),
+    /// A directory left empty by the removals before it
+    EmptyDir(PathBuf),
+}
+
+impl Removal {
+    pub fn path(&self) -> &Path {
+        match self {
+            Removal::File(p) | Removal::Tree(p) | Removal::EmptyDir(p) => p,
+        }
+    }
+
+    /// Disk space freed (nothing for a symlink).
+    pub fn bytes(&self) -> u64 {
+        match self {
+            Removal::File(p) => fs::symlink_metadata(p)
+                .ok()
+                .filter(|m| m.is_file())
+                .map_or(0, |m| m.len()),
+            Removal::Tree(p) => crate::util::dir_size(p),
+            Removal::EmptyDir(_) => 0,
+        }
+    }
+}
+
+/// What `uninstall_all` removes, in order, among the paths that exist: the
+/// current symlink, the shims (a `windsurf` in bin_dir windman did not write
+/// stays) and the versions dir; with `purge` the desktop
+/// entry, what windman keeps in the cache dir (see `cache_removals`) and
+/// (unless `config` is None) the windman config file. Windsurf's own user data (~/.config/Windsurf, ~/.windsurf) is never
+/// part of it. Touches nothing.
+pub fn uninstall_plan(
+    eff: &EffectivePaths,
+    purge: bool,
+    config: Option<&crate::config::ConfigPaths>,
+) -> Vec<Removal> {
+    let exists = |p: &Path| fs::symlink_metadata(p).is_ok();
+    let mut plan = Vec::new();
+
+    // symlink & shims
+    let shim = Some(eff.bin_shim.clone()).filter(|p| crate::util::is_windman_shim(p));
+    let files = std::iter::once(eff.current_symlink.clone())
+        .chain(shim)
+        .chain(crate::shims::list(eff).into_iter().map(|e| e.path));
+    plan.extend(files.filter(|p| exists(p)).map(Removal::File));
 
-    // Remove versions dir
     if eff.versions_dir.exists() {
-        fs::remove_dir_all(&eff.versions_dir)?;
+        plan.push(Removal::Tree(eff.versions_dir.clone()));
     }
 
     if purge {
-        // Remove desktop files, icons etc. (best-effort)
-        let _ = fs::remove_file(&eff.desktop_file);
-        if eff.icons_dir.exists() {
-            let _ = fs::remove_dir_all(&eff.icons_dir);
+        // Desktop entry and our icon only: icons_dir is shared with other applications
+        for p in [eff.desktop_file.clone(), eff.icons_dir.join("windsurf.png")] {
+            if exists(&p) {
+                plan.push(Removal::File(p));
+            }
+        }
+        plan.extend(cache_removals(eff));
+        if let Some(cfg) = config {
+            if exists(&cfg.file) {
+                plan.push(Removal::File(cfg.file.clone()));
+            }
+            // only if then empty: a --config file may live in a directory we don't own
+            let emptied =
+                fs::read_dir(&cfg.dir).is_ok_and(|rd| rd.flatten().all(|e| e.path() == cfg.file));
+            if emptied {
+                plan.push(Removal::EmptyDir(cfg.dir.clone()));
+            }
         }
     }
-    Ok(())
+    plan
+}
+
+/// Names of the files windman keeps per channel in the cache dir.
+const CHANNEL_CACHE_FILES: &[&str] = &[crate::check::CACHE_FILE, crate::remote::HTTP_CACHE_FILE];
+
+/// What of the cache dir windman wrote: `downloads/`, `stdin/`, the fallback
+/// `state/`, each channel's check and HTTP cache files, then the dirs these
+/// leave empty. The cache dir may be a shared one (`WINDMAN_CACHE_DIR`,
+/// `paths.cache_dir`): anything else in it stays, and so does the dir.
+fn cache_removals(eff: &EffectivePaths) -> Vec<Removal> {
+    let Ok(rd) = fs::read_dir(&eff.cache_dir) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<fs::DirEntry> = rd.flatten().collect();
+    entries.sort_by_key(|e| e.file_name());
+    let mut trees = vec!["downloads", "stdin"];
+    if eff.state_dir == eff.cache_dir.join("state") {
+        trees.push("state");
+    }
+    let mut plan = Vec::new();
+    let mut emptied = true;
+    for ent in entries {
+        let path = ent.path();
+        let name = ent.file_name().to_string_lossy().to_string();
+        let is_dir = ent.file_type().is_ok_and(|t| t.is_dir());
+        if is_dir && trees.contains(&name.as_str()) {
+            plan.push(Removal::Tree(path));
+            continue;
+        }
+        let ours: Vec<PathBuf> = CHANNEL_CACHE_FILES
+            .iter()
+            .map(|f| path.join(f))
+            .filter(|f| f.is_file())
+            .collect();
+        if !is_dir || ours.is_empty() {
+            emptied = false;
+            continue;
+        }
+        let channel_emptied =
+            fs::read_dir(&path).is_ok_and(|rd| rd.flatten().all(|e| ours.contains(&e.path())));
+        plan.extend(ours.into_iter().map(Removal::File));
+        if channel_emptied {
+            plan.push(Removal::EmptyDir(path));
+        } else {
+            emptied = false;
+        }
+    }
+    if emptied {
+        plan.push(Removal::EmptyDir(eff.cache_dir.clone()));
+    }
+    plan
+}
+
+/// Carry out `uninstall_plan`. Best-effort past the versions dir. Returns
+/// every path actually removed.
+pub fn uninstall_all(
+    eff: &EffectivePaths,
+    purge: bool,
+    config: Option<&crate::config::ConfigPaths>,
+) -> Result<Vec<PathBuf>> {
+    let mut removed = Vec::new();
+    for r in uninstall_plan(eff, purge, config) {
+        let done = match &r {
+            Removal::File(p) => fs::remove_file(p).is_ok(),
+            // entry by entry: a symlink named like a version is unlinked, not followed
+            Removal::Tree(p) if *p == eff.versions_dir => {
+                crate::util::clear_and_remove_dir(p)?;
+                true
+            }
+            Removal::Tree(p) => crate::util::clear_and_remove_dir(p).is_ok(),
+            Removal::EmptyDir(p) => fs::remove_dir(p).is_ok(),
+        };
+        if done {
+            removed.push(r.path().to_path_buf());
+        }
+    }
+    // forget the extra shims (their files went above)
+    removed.extend(crate::shims::remove_all(eff));
+    Ok(removed)
 }
 
 // ---------------- helpers ----------------
 
-fn extract_tar_to_dir(tar_path: &str, dest: &Path) -> Result<()> {
+/// Warn, or with `strict` refuse, when an ELF file of the build needs a newer
+/// glibc than `system` (None, e.g. on musl: nothing to check).
+fn check_glibc(
+    staging: &Path,
+    system: Option<(PathBuf, crate::compat::GlibcVersion)>,
+    strict: bool,
+) -> Result<()> {
+    let Some((libc, system)) = system else {
+        return Ok(());
+    };
+    let Some(worst) = crate::compat::missing_glibc(staging, &system)
+        .into_iter()
+        .next()
+    else {
+        return Ok(());
+    };
+    let err = InstallError::GlibcTooOld {
+        file: worst
+            .file
+            .strip_prefix(staging)
+            .unwrap_or(&worst.file)
+            .to_path_buf(),
+        needs: worst.needs,
+        libc,
+        system,
+    };
+    if strict {
+        return Err(err.into());
+    }
+    let msg = err.to_string();
+    crate::ui::warn(msg.lines().next().unwrap_or_default());
+    crate::ui::warn("installing anyway (install.strict_compat_check = true refuses such builds)");
+    Ok(())
+}
+
+/// Run the smoke test inside `dir`. Err carries a human-readable reason.
+fn run_smoke_test(dir: &Path, st: &SmokeTest) -> std::result::Result<(), String> {
+    let exe = crate::util::find_windsurf_executable(dir);
+    let cmd = if st.cmd.trim().is_empty() {
+        let exe = exe.ok_or_else(|| "no Windsurf executable found".to_string())?;
+        format!("'{}' --version", exe.display())
+    } else {
+        st.cmd
+            .replace(
+                "{exe}",
+                &exe.map(|p| p.display().to_string()).unwrap_or_default(),
+            )
+            .replace("{dir}", &dir.display().to_string())
+    };
+
+    // a process group of its own: `bin/windsurf` is a script starting
+    // Electron, which a timeout must stop along with the `sh -c`
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(&cmd)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()
+        .map_err(|e| format!("cannot run `{}`: {}", cmd, e))?;
+
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return Ok(()),
+            Ok(Some(status)) => return Err(format!("`{}` exited with {}", cmd, status)),
+            Ok(None) if started.elapsed() >= st.timeout => {
+                // the group's id is the PID of its leader, the `sh`
+                unsafe { libc::killpg(child.id() as libc::pid_t, libc::SIGKILL) };
+                let _ = child.wait();
+                return Err(format!("`{}` timed out after {:?}", cmd, st.timeout));
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(50)),
+            Err(e) => return Err(format!("waiting for `{}`: {}", cmd, e)),
+        }
+    }
+}
+
+/// What the `tar` crate says (as `io::ErrorKind::Other`) when the archive
+/// inside the compressed stream ends early.
+const TAR_TRUNCATION_MESSAGES: &[&str] = &[
+    "failed to read entire block",
+    "unexpected EOF during skip",
+    "failed to write entire file",
+];
+
+/// True when extracting failed because the archive ends early, as a partial
+/// download does: the gzip or zstd stream stops mid-way, or the tar inside
+/// it does. Other corruption (bad deflate data, a wrong checksum) is not this.
+pub fn is_truncation_error(e: &anyhow::Error) -> bool {
+    e.chain()
+        .filter_map(|c| c.downcast_ref::<std::io::Error>())
+        .any(|io| {
+            io.kind() == std::io::ErrorKind::UnexpectedEof
+                || TAR_TRUNCATION_MESSAGES.contains(&io.to_string().as_str())
+        })
+}
+
+/// zstd frame magic; gzip is assumed otherwise.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Extract a .tar.gz or .tar.zst (told apart by content, not name) into `dest`,
+/// entry by entry, with progress over the compressed bytes read.
+fn extract_tar_to_dir(tar_path: &str, dest: &Path, progress: ProgressMode) -> Result<ExtractStats> {
     let file = File::open(tar_path).with_context(|| format!("opening {}", tar_path))?;
-    let dec = GzDecoder::new(file);
+    let total = file.metadata().map(|m| m.len()).unwrap_or(0);
+
+    let pb = Progress::new(
+        progress,
+        "extracting",
+        Some(total),
+        "extracting {bar} {bytes}/{total_bytes} {msg}",
+    )?;
+    let stats = unpack_archive(pb.wrap_read(file), tar_path, dest, &pb);
+    pb.finish_and_clear();
+    stats
+}
+
+/// Unpack a .tar.gz or .tar.zst (told apart by their magic bytes) from `input`
+/// into `dest`; `pb` gets the file count. `name` is for messages.
+fn unpack_archive<'a>(
+    input: impl Read + 'a,
+    name: &str,
+    dest: &Path,
+    pb: &Progress,
+) -> Result<ExtractStats> {
+    // Large buffer: the decoder otherwise issues many small reads on big tarballs
+    let mut input = BufReader::with_capacity(EXTRACT_BUF_SIZE, input);
+    let is_zstd = input
+        .fill_buf()
+        .with_context(|| format!("reading {}", name))?
+        .starts_with(&ZSTD_MAGIC);
+    let dec: Box<dyn Read + 'a> = if is_zstd {
+        Box::new(zstd::Decoder::with_buffer(input)?)
+    } else {
+        Box::new(GzDecoder::new(input))
+    };
     let mut ar = Archive::new(dec);
-    ar.unpack(dest)
-        .with_context(|| format!("extracting to {}", dest.display()))?;
+    let mut stats = ExtractStats::default();
+    // Directory modes and mtimes go last, deepest first: a 0500 dir must not
+    // block its own content, and writing into a dir changes its mtime
+    let mut dirs = Vec::new();
+    for entry in ar.entries().with_context(|| format!("reading {}", name))? {
+        crate::cancel::check()?;
+        let mut entry = entry.with_context(|| format!("reading entry in {}", name))?;
+        let header = entry.header();
+        let kind = header.entry_type();
+        let mode = header.mode().unwrap_or(0o644) & ARCHIVE_MODE_BITS;
+        let mtime = header.mtime().ok();
+        let rel = entry.path()?.into_owned();
+        // `..` entries are skipped, as `unpack_in` does
+        let Some(path) = path_in(dest, &rel) else {
+            continue;
+        };
+        match kind {
+            tar::EntryType::Directory => {
+                refuse_links_on(dest, &path, &rel, name)?;
+                fs::create_dir_all(&path)
+                    .with_context(|| format!("creating {}", path.display()))?;
+                dirs.push((path, mode, mtime));
+            }
+            tar::EntryType::Symlink => {
+                let target = entry
+                    .link_name()?
+                    .with_context(|| format!("{}: symlink without a target", rel.display()))?
+                    .into_owned();
+                unpack_symlink(dest, &rel, &target, name)?;
+            }
+            _ => {
+                let size = entry.size();
+                entry
+                    .unpack_in(dest)
+                    .with_context(|| format!("extracting to {}", dest.display()))?;
+                if kind.is_file() {
+                    // tar already set the mode as archived (no umask, exec bits
+                    // kept) and the mtime through the open file: nothing more
+                    // to do by path, which costs a lookup per file on NFS
+                    stats.files += 1;
+                    stats.bytes += size;
+                    pb.set_message(format!("{} files", stats.files));
+                }
+            }
+        }
+    }
+    for (dir, mode, mtime) in dirs.into_iter().rev() {
+        // a later entry may not have swapped a link in: still checked
+        let rel = dir.strip_prefix(dest).unwrap_or(&dir).to_path_buf();
+        refuse_links_on(dest, &dir, &rel, name)?;
+        set_mtime(&dir, mtime)?;
+        fs::set_permissions(&dir, fs::Permissions::from_mode(mode))
+            .with_context(|| format!("setting the mode of {}", dir.display()))?;
+    }
+    Ok(stats)
+}
+
+/// Permission bits taken from archive entries (setuid, setgid and sticky are dropped).
+const ARCHIVE_MODE_BITS: u32 = 0o777;
+
+/// Where archive path `rel` lands under `dest`: leading `/` and `.` dropped,
+/// None when it climbs with `..`.
+fn path_in(dest: &Path, rel: &Path) -> Option<PathBuf> {
+    use std::path::Component;
+    let mut out = dest.to_path_buf();
+    for c in rel.components() {
+        match c {
+            Component::Normal(part) => out.push(part),
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+            Component::ParentDir => return None,
+        }
+    }
+    Some(out)
+}
+
+/// Refuse archive entry `rel` (at `path` under `dest`) when `path` or one of
+/// its dirs below `dest` is a symlink an earlier entry made: creating or
+/// chmodding through it could reach outside `dest` (`x -> .`, `x/y -> ..`,
+/// ... each lexically inside). `archive` is for the error.
+fn refuse_links_on(dest: &Path, path: &Path, rel: &Path, archive: &str) -> Result<()> {
+    let Ok(below) = path.strip_prefix(dest) else {
+        return Ok(());
+    };
+    let mut at = dest.to_path_buf();
+    for part in below.components() {
+        at.push(part);
+        match fs::symlink_metadata(&at) {
+            Ok(m) if m.file_type().is_symlink() => {
+                return Err(WindmanError::ArchiveInvalid {
+                    archive: archive.to_string(),
+                    reason: format!(
+                        "entry {} goes through the link {}",
+                        rel.display(),
+                        at.strip_prefix(dest).unwrap_or(&at).display()
+                    ),
+                }
+                .into())
+            }
+            Ok(_) => {}
+            // nothing below a missing component exists yet
+            Err(_) => break,
+        }
+    }
     Ok(())
 }
 
+/// Recreate the archive's symlink `rel` -> `target` under `dest`, as is
+/// (relative). Refused when the target is absolute or leaves the archive, or
+/// when one of the dirs of `rel` is itself a link (see `refuse_links_on`).
+/// `archive` is for the error.
+fn unpack_symlink(dest: &Path, rel: &Path, target: &Path, archive: &str) -> Result<()> {
+    use std::path::Component;
+    let parent = rel.parent().unwrap_or(Path::new(""));
+    let mut depth = 0usize;
+    for c in parent.join(target).components() {
+        match c {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => depth -= 1,
+            _ => {
+                return Err(WindmanError::ArchiveInvalid {
+                    archive: archive.to_string(),
+                    reason: format!(
+                        "entry {} links outside the archive ({})",
+                        rel.display(),
+                        target.display()
+                    ),
+                }
+                .into())
+            }
+        }
+    }
+    let Some(link) = path_in(dest, rel) else {
+        return Ok(());
+    };
+    if let Some(dir) = link.parent() {
+        refuse_links_on(dest, dir, rel, archive)?;
+        fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    }
+    // a later entry replaces an earlier one, as tar does
+    if fs::symlink_metadata(&link).is_ok_and(|m| !m.is_dir()) {
+        fs::remove_file(&link).with_context(|| format!("replacing {}", link.display()))?;
+    }
+    std::os::unix::fs::symlink(target, &link)
+        .with_context(|| format!("creating link {}", link.display()))
+}
+
+/// Flush `staging` as `install.extract_fsync` says before it is renamed into
+/// place: a crash then leaves the staging dir or a complete version, never a
+/// version dir with files still in flight.
+fn sync_staged(staging: &Path, mode: ExtractFsync) -> Result<()> {
+    match mode {
+        ExtractFsync::None => Ok(()),
+        ExtractFsync::Dir => {
+            crate::util::syncfs(staging)?;
+            crate::util::fsync_path(staging)
+        }
+        // entries before their dir, the staging dir last
+        ExtractFsync::All => walkdir::WalkDir::new(staging)
+            .contents_first(true)
+            .into_iter()
+            .filter(|e| !e.as_ref().is_ok_and(|e| e.path_is_symlink()))
+            .try_for_each(|e| crate::util::fsync_path(e?.path())),
+    }
+}
+
+/// Set the mtime of an extracted file or dir to the archived one (if any).
+fn set_mtime(path: &Path, mtime: Option<u64>) -> Result<()> {
+    let Some(secs) = mtime else {
+        return Ok(());
+    };
+    let t = std::time::UNIX_EPOCH + Duration::from_secs(secs);
+    File::open(path)
+        .and_then(|f| f.set_modified(t))
+        .with_context(|| format!("setting the mtime of {}", path.display()))
+}
+
+/// Remove a staging dir left by an aborted install (missing dir is fine).
+pub(crate) fn discard_staging(staging: &Path) {
+    let _ = crate::util::remove_dir_within(staging.parent().unwrap_or(staging), staging);
+}
+
+/// Move a build that failed its smoke test to `.failed-<version>` in
+/// `versions_dir`, replacing the one of an earlier attempt. Returns where it
+/// is (still `dir` when it could not be moved).
+fn set_aside_failed(versions_dir: &Path, dir: &Path, version: &str) -> PathBuf {
+    let failed = versions_dir.join(format!(".failed-{}", version));
+    let moved = crate::util::remove_dir_within(versions_dir, &failed)
+        .and_then(|()| fs::rename(dir, &failed).map_err(Into::into));
+    match moved {
+        Ok(()) => failed,
+        Err(e) => {
+            crate::ui::warn(format_args!(
+                "could not set {} aside: {:#}",
+                dir.display(),
+                e
+            ));
+            dir.to_path_buf()
+        }
+    }
+}
+
 fn list_version_dirs(base: &Path) -> Result<Vec<PathBuf>> {
     let mut out = Vec::new();
     if base.exists() {
         for ent in fs::read_dir(base)? {
             let ent = ent?;
             let p = ent.path();
-            if p.file_name().map(|n| n == "current").unwrap_or(false) {
+            if !crate::util::is_version_dir_name(&ent.file_name().to_string_lossy()) {
                 continue;
             }
             if p.is_dir() {
@@ -131,49 +1218,54 @@ fn list_version_dirs(base: &Path) -> Result<Vec<PathBuf>> {
     Ok(out)
 }
 
-fn extract_version_from_filename(path: &str) -> Option<String> {
+/// Full semver, prerelease and build metadata included.
+const SEMVER_RE: &str =
+    r"\d+\.\d+\.\d+(?:-[0-9A-Za-z-]+(?:\.[0-9A-Za-z-]+)*)?(?:\+[0-9A-Za-z-]+(?:\.[0-9A-Za-z-]+)*)?";
+
+pub(crate) fn extract_version_from_filename(path: &str) -> Option<String> {
     let name = std::path::Path::new(path).file_name()?.to_string_lossy();
-    let re = regex::Regex::new(r"(\d+\.\d+\.\d+)").ok()?;
-    let caps = re.captures(&name)?;
-    Some(caps.get(1)?.as_str().to_string())
+    let stem = name
+        .strip_suffix(".tar.gz")
+        .or_else(|| name.strip_suffix(".tgz"))
+        .or_else(|| name.strip_suffix(".tar.zst"))
+        .unwrap_or(&name);
+    let re = regex::Regex::new(SEMVER_RE).ok()?;
+    Some(re.find(stem)?.as_str().to_string())
 }
 
-fn detect_version_from_product_json(extracted_root: &Path) -> Result<String> {
-    // Find product.json under **/resources/app/product.json (common layout)
-    // We'll scan one level or two deep to be safe.
-    let mut candidate: Option<PathBuf> = None;
-
-    // Typical layout: extracted_root/<some-root>/resources/app/product.json
-    for entry in walkdir::WalkDir::new(extracted_root)
-        .min_depth(1)
-        .max_depth(4)
-    {
-        let entry = entry?;
-        let p = entry.path();
-        if p.file_name().map(|n| n == "product.json").unwrap_or(false) {
-            candidate = Some(p.to_path_buf());
-            break;
-        }
+/// Same major.minor.patch (prerelease and build metadata aside).
+fn same_release(a: &str, b: &str) -> bool {
+    match (semver::Version::parse(a), semver::Version::parse(b)) {
+        (Ok(a), Ok(b)) => (a.major, a.minor, a.patch) == (b.major, b.minor, b.patch),
+        _ => a == b,
     }
-
-    let p = candidate.ok_or_else(|| anyhow::anyhow!("product.json not found"))?;
-    let data = std::fs::read_to_string(&p).with_context(|| format!("reading {}", p.display()))?;
-    let v = parse_windsurf_version_from_product(&data)
-        .ok_or_else(|| anyhow::anyhow!("windsurfVersion not found in product.json"))?;
-    Ok(v)
 }
 
-fn parse_windsurf_version_from_product(s: &str) -> Option<String> {
-    let v = json_extract_field(s, "windsurfVersion")?;
-    Some(v)
+/// Dir name for a new version. The archive name wins, except when product.json
+/// names the same release more precisely (`1.12.11` vs `1.12.11-nightly.3`).
+/// Names that are not usable as a dir are ignored.
+fn pick_version_name(
+    from_filename: Option<String>,
+    from_product: Option<String>,
+) -> Option<String> {
+    let from_filename = from_filename.filter(|v| crate::util::is_valid_version_name(v));
+    let from_product = from_product.filter(|v| crate::util::is_valid_version_name(v));
+    match (from_filename, from_product) {
+        (Some(f), Some(p)) if same_release(&f, &p) && p.len() > f.len() => Some(p),
+        (Some(f), _) => Some(f),
+        (None, p) => p,
+    }
 }
 
-/// extract a top-level string field from a JSON blob without full serde (lenient)
-fn json_extract_field(s: &str, field: &str) -> Option<String> {
-    // very lenient regex just to fish out "field":"value"
-    let re = regex::Regex::new(&format!(r#""{}\s*"\s*:\s*"(.*?)""#, regex::escape(field))).ok()?;
-    let caps = re.captures(s)?;
-    Some(caps.get(1)?.as_str().to_string())
+/// The version named by the extracted tree's product.json, read the way
+/// `status` reads it (`version::version_from_product_json`).
+fn detect_version_from_product_json(extracted_root: &Path) -> Result<String> {
+    let p = crate::version::find_product_json(extracted_root)
+        .ok_or_else(|| anyhow::anyhow!("product.json not found"))?;
+    let data = std::fs::read_to_string(&p).with_context(|| format!("reading {}", p.display()))?;
+    crate::version::version_from_product_json(&data).ok_or_else(|| {
+        anyhow::anyhow!("no windsurfVersion, productVersion or version in product.json")
+    })
 }
 
 #[cfg(test)]
@@ -184,6 +1276,11 @@ mod tests_install_from_tar_names_dir_by_version {
 
     // Crée un tar.gz minimal: Windsurf/bin/windsurf + Windsurf/resources/app/product.json
     fn make_fake_windsurf_tar(path: &Path, version: &str) {
+        let prod_json = format!(r#"{{ "windsurfVersion":"{}" }}"#, version);
+        make_fake_windsurf_tar_with(path, &prod_json);
+    }
+
+    fn make_fake_windsurf_tar_with(path: &Path, prod_json: &str) {
         let tarfile = File::create(path).unwrap();
         let enc = flate2::write::GzEncoder::new(tarfile, flate2::Compression::default());
         let mut builder = tar::Builder::new(enc);
@@ -198,7 +1295,6 @@ mod tests_install_from_tar_names_dir_by_version {
         bin_hdr.set_cksum();
         builder.append(&bin_hdr, &b"#!"[..]).unwrap();
 
-        let prod_json = format!(r#"{{ "windsurfVersion":"{}" }}"#, version);
         let mut prod_hdr = tar::Header::new_gnu();
         prod_hdr.set_path(prod_path).unwrap();
         prod_hdr.set_mode(0o644);
@@ -223,6 +1319,10 @@ mod tests_install_from_tar_names_dir_by_version {
             bin_shim: prefix.join("bin/windsurf"),
             desktop_file: prefix.join("share/applications/windsurf.desktop"),
             icons_dir: prefix.join("share/icons"),
+            cache_dir: prefix.join("cache"),
+            state_dir: prefix.join("state"),
+            profile: None,
+            arch: None,
         };
 
         std::fs::create_dir_all(&eff.versions_dir).unwrap();
@@ -230,7 +1330,12 @@ mod tests_install_from_tar_names_dir_by_version {
         let tar_path = tmp.path().join("Windsurf-linux-x64-2.3.4.tar.gz");
         make_fake_windsurf_tar(&tar_path, "2.3.4");
 
-        let ver = super::install_from_tar(tar_path.to_string_lossy().as_ref(), &eff).unwrap();
+        let opts = InstallOptions {
+            quiet: true,
+            ..Default::default()
+        };
+        let ver =
+            super::install_from_tar(tar_path.to_string_lossy().as_ref(), &eff, &opts).unwrap();
         assert_eq!(ver, "2.3.4");
 
         // current -> .../2.3.4
@@ -239,5 +1344,1113 @@ mod tests_install_from_tar_names_dir_by_version {
 
         // Shim créé
         assert!(eff.bin_shim.is_file());
+
+        // Métadonnées: source = chemin du tar, checksum présent
+        let meta = crate::meta::read(&eff.versions_dir.join("2.3.4")).unwrap();
+        assert_eq!(meta.version, "2.3.4");
+        assert!(meta.source.ends_with("Windsurf-linux-x64-2.3.4.tar.gz"));
+        assert_eq!(meta.sha256.unwrap().len(), 64);
+    }
+
+    #[test]
+    fn product_json_with_only_version_names_the_dir_like_status_reads_it() {
+        let tmp = tempdir().unwrap();
+        let prefix = tmp.path().to_path_buf();
+        let eff = crate::paths::EffectivePaths::for_test(&prefix);
+        std::fs::create_dir_all(&eff.versions_dir).unwrap();
+
+        // no version in the archive name either
+        let tar_path = tmp.path().join("custom-build.tar.gz");
+        make_fake_windsurf_tar_with(&tar_path, r#"{ "version":"1.12.9" }"#);
+        let opts = InstallOptions {
+            quiet: true,
+            ..Default::default()
+        };
+        let ver =
+            super::install_from_tar(tar_path.to_string_lossy().as_ref(), &eff, &opts).unwrap();
+        assert_eq!(ver, "1.12.9");
+        assert!(eff.versions_dir.join("1.12.9").is_dir());
+
+        let local = crate::version::detect_local_version_with(&eff, false).unwrap();
+        assert_eq!(local.as_deref(), Some("1.12.9"));
+        let latest = semver::Version::parse("1.12.9").unwrap();
+        assert!(crate::version::is_up_to_date(&ver, &latest));
+    }
+
+    /// (relative path, mode, content) of every entry, metadata file aside.
+    fn tree(dir: &Path) -> Vec<(PathBuf, u32, Vec<u8>)> {
+        use std::os::unix::fs::PermissionsExt;
+        walkdir::WalkDir::new(dir)
+            .min_depth(1)
+            .sort_by_file_name()
+            .into_iter()
+            .map(|e| e.unwrap())
+            .filter(|e| e.file_name() != crate::meta::META_FILE)
+            .map(|e| {
+                let content = if e.file_type().is_file() {
+                    fs::read(e.path()).unwrap()
+                } else {
+                    Vec::new()
+                };
+                let mode = e.metadata().unwrap().permissions().mode();
+                (
+                    e.path().strip_prefix(dir).unwrap().to_path_buf(),
+                    mode,
+                    content,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn export_round_trips_through_install() {
+        let tmp = tempdir().unwrap();
+        let prefix = tmp.path().join("prefix");
+        let eff = crate::paths::EffectivePaths::for_test(&prefix);
+        let opts = InstallOptions {
+            quiet: true,
+            ..Default::default()
+        };
+        let tar_path = tmp.path().join("Windsurf-linux-x64-2.3.4.tar.gz");
+        make_fake_windsurf_tar(&tar_path, "2.3.4");
+        install_from_tar(tar_path.to_str().unwrap(), &eff, &opts).unwrap();
+        let dir = eff.versions_dir.join("2.3.4");
+        crate::notes::write(&dir, "# Windsurf 2.3.4\n").unwrap();
+        let before = tree(&dir);
+
+        let export = tmp.path().join("windsurf-2.3.4.tar.zst");
+        let done = crate::export::export_version(&dir, &export).unwrap();
+        assert!(done.sidecar.is_file());
+
+        fs::remove_dir_all(&prefix).unwrap();
+        let ver = install_from_tar(export.to_str().unwrap(), &eff, &opts).unwrap();
+        assert_eq!(ver, "2.3.4");
+        assert_eq!(tree(&dir), before);
+        let meta = crate::meta::read(&dir).unwrap();
+        assert_eq!(meta.sha256.as_deref(), Some(done.sha256.as_str()));
+
+        // a sidecar that does not match refuses the install
+        fs::remove_dir_all(&prefix).unwrap();
+        fs::write(&done.sidecar, format!("{}  x\n", "0".repeat(64))).unwrap();
+        let err = install_from_tar(export.to_str().unwrap(), &eff, &opts).unwrap_err();
+        assert!(err.to_string().contains("checksum mismatch"), "{}", err);
+        assert!(matches!(
+            err.downcast_ref::<WindmanError>(),
+            Some(WindmanError::VerificationFailed { .. })
+        ));
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn stream_install_checks_before_anything_moves() {
+        let tmp = tempdir().unwrap();
+        let prefix = tmp.path().to_path_buf();
+        let eff = crate::paths::EffectivePaths::for_test(&prefix);
+        let opts = InstallOptions {
+            quiet: true,
+            source: Some("https://example.invalid/Windsurf-linux-x64-2.3.5.tar.gz".into()),
+            ..Default::default()
+        };
+        let archive = |v: &str| {
+            let p = tmp.path().join(format!("{}.tar.gz", v));
+            make_fake_windsurf_tar(&p, v);
+            fs::read(p).unwrap()
+        };
+        let (v1, v2) = (archive("2.3.4"), archive("2.3.5"));
+        let v1_sha = crate::meta::sha256_file(&tmp.path().join("2.3.4.tar.gz")).unwrap();
+
+        let ver = install_from_stream(
+            &v1[..],
+            "Windsurf-linux-x64-2.3.4.tar.gz",
+            &eff,
+            &opts,
+            |rest| {
+                // handed back fully read
+                assert!(rest.is_empty());
+                Ok(v1_sha.clone())
+            },
+        )
+        .unwrap();
+        assert_eq!(ver, "2.3.4");
+        let meta = crate::meta::read(&eff.versions_dir.join("2.3.4")).unwrap();
+        assert_eq!(meta.sha256.as_deref(), Some(v1_sha.as_str()));
+        assert!(meta.source.starts_with("https://"));
+
+        // verification fails once the body is read: no dir, no staging, current kept
+        let err = install_from_stream(
+            &v2[..],
+            "Windsurf-linux-x64-2.3.5.tar.gz",
+            &eff,
+            &opts,
+            |_| Err(anyhow::anyhow!("SIGNATURE VERIFICATION FAILED")),
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "SIGNATURE VERIFICATION FAILED");
+        assert!(!eff.versions_dir.join("2.3.5").exists());
+        let leftovers: Vec<_> = fs::read_dir(&eff.versions_dir)
+            .unwrap()
+            .flatten()
+            .filter(|e| e.file_name().to_string_lossy().starts_with(".staging-"))
+            .collect();
+        assert!(leftovers.is_empty());
+        let cur = fs::read_link(&eff.current_symlink).unwrap();
+        assert_eq!(cur.file_name().unwrap().to_string_lossy(), "2.3.4");
+    }
+
+    #[test]
+    fn random_tar_is_refused_and_leaves_the_install_alone() {
+        let tmp = tempdir().unwrap();
+        let prefix = tmp.path().to_path_buf();
+        let eff = crate::paths::EffectivePaths::for_test(&prefix);
+        let mut opts = InstallOptions {
+            quiet: true,
+            ..Default::default()
+        };
+        let good = tmp.path().join("Windsurf-linux-x64-2.3.4.tar.gz");
+        make_fake_windsurf_tar(&good, "2.3.4");
+        install_from_tar(good.to_str().unwrap(), &eff, &opts).unwrap();
+
+        // some other project's tarball: a README and a non-executable "windsurf"
+        let junk = tmp.path().join("photos.tar.gz");
+        let enc = flate2::write::GzEncoder::new(
+            File::create(&junk).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut builder = tar::Builder::new(enc);
+        for (path, mode, body) in [
+            ("photos/README", 0o644, &b"holiday pictures"[..]),
+            ("windsurf", 0o644, &b"not a program"[..]),
+        ] {
+            let mut hdr = tar::Header::new_gnu();
+            hdr.set_path(path).unwrap();
+            hdr.set_mode(mode);
+            hdr.set_size(body.len() as u64);
+            hdr.set_cksum();
+            builder.append(&hdr, body).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let err = install_from_tar(junk.to_str().unwrap(), &eff, &opts).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<WindmanError>(),
+            Some(WindmanError::ArchiveInvalid { .. })
+        ));
+        let err = err.to_string();
+        assert!(
+            err.contains("archive does not look like a Windsurf build"),
+            "{}",
+            err
+        );
+        assert!(err.contains("photos.tar.gz"), "{}", err);
+        assert!(
+            fs::read_dir(&eff.versions_dir).unwrap().all(|e| !e
+                .unwrap()
+                .file_name()
+                .to_string_lossy()
+                .starts_with(".staging-")),
+            "staging dir left behind"
+        );
+        assert_eq!(list_version_dirs(&eff.versions_dir).unwrap().len(), 1);
+        assert_eq!(
+            crate::history::current_version_name(&eff.current_symlink).as_deref(),
+            Some("2.3.4")
+        );
+
+        // --force takes exotic layouts as they are
+        opts.force = true;
+        let ver = install_from_tar(junk.to_str().unwrap(), &eff, &opts).unwrap();
+        assert!(eff.versions_dir.join(&ver).join("photos/README").is_file());
+    }
+
+    #[test]
+    fn install_refuses_a_downgrade_unless_allowed() {
+        let tmp = tempdir().unwrap();
+        let prefix = tmp.path().to_path_buf();
+        let eff = crate::paths::EffectivePaths::for_test(&prefix);
+        let mut opts = InstallOptions {
+            quiet: true,
+            ..Default::default()
+        };
+        for v in ["2.3.5", "2.3.4"] {
+            make_fake_windsurf_tar(
+                &tmp.path().join(format!("Windsurf-linux-x64-{}.tar.gz", v)),
+                v,
+            );
+        }
+        let tar = |v: &str| {
+            tmp.path()
+                .join(format!("Windsurf-linux-x64-{}.tar.gz", v))
+                .to_string_lossy()
+                .to_string()
+        };
+        install_from_tar(&tar("2.3.5"), &eff, &opts).unwrap();
+
+        // no terminal under test: the prompt is refused
+        let err = install_from_tar(&tar("2.3.4"), &eff, &opts).unwrap_err();
+        assert!(
+            matches!(
+                err.downcast_ref::<InstallError>(),
+                Some(InstallError::DowngradeRefused { from, to }) if from == "2.3.5" && to == "2.3.4"
+            ),
+            "{:#}",
+            err
+        );
+        assert!(!eff.versions_dir.join("2.3.4").exists());
+        assert_eq!(
+            crate::history::current_version_name(&eff.current_symlink).as_deref(),
+            Some("2.3.5")
+        );
+        assert!(list_version_dirs(&eff.versions_dir)
+            .unwrap()
+            .iter()
+            .all(|p| p.ends_with("2.3.5")));
+
+        opts.allow_downgrade = true;
+        assert_eq!(
+            install_from_tar(&tar("2.3.4"), &eff, &opts).unwrap(),
+            "2.3.4"
+        );
+    }
+
+    #[test]
+    fn downgrades_follow_semver_precedence() {
+        assert!(is_downgrade("1.13.0", "1.12.9"));
+        assert!(is_downgrade("1.13.0", "1.13.0-rc.1"));
+        assert!(!is_downgrade("1.12.9", "1.13.0"));
+        assert!(!is_downgrade("1.13.0", "1.13.0"));
+        assert!(!is_downgrade("20250101000000", "1.0.0"));
+        guard_downgrade(None, "1.0.0", false).unwrap();
+        guard_downgrade(Some("2.0.0"), "1.0.0", true).unwrap();
+        assert!(guard_downgrade(Some("2.0.0"), "1.0.0", false).is_err());
+    }
+
+    fn smoke(cmd: &str, timeout_ms: u64) -> InstallOptions {
+        InstallOptions {
+            quiet: true,
+            smoke_test: Some(SmokeTest {
+                cmd: cmd.to_string(),
+                timeout: Duration::from_millis(timeout_ms),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn no_activate_leaves_current_history_and_shim_alone() {
+        let tmp = tempdir().unwrap();
+        let eff = EffectivePaths::for_test(tmp.path());
+        let old = tmp.path().join("Windsurf-linux-x64-2.3.4.tar.gz");
+        let new = tmp.path().join("Windsurf-linux-x64-2.4.0.tar.gz");
+        make_fake_windsurf_tar(&old, "2.3.4");
+        make_fake_windsurf_tar(&new, "2.4.0");
+        let quiet = InstallOptions {
+            quiet: true,
+            ..Default::default()
+        };
+        install_from_tar(old.to_str().unwrap(), &eff, &quiet).unwrap();
+        fs::remove_file(&eff.bin_shim).unwrap();
+
+        let staged = InstallOptions {
+            no_activate: true,
+            ..quiet
+        };
+        assert_eq!(
+            install_from_tar(new.to_str().unwrap(), &eff, &staged).unwrap(),
+            "2.4.0"
+        );
+        assert!(eff
+            .versions_dir
+            .join("2.4.0/Windsurf/bin/windsurf")
+            .is_file());
+        assert_eq!(
+            crate::history::current_version_name(&eff.current_symlink).as_deref(),
+            Some("2.3.4")
+        );
+        assert!(!eff.bin_shim.exists());
+        let history = crate::history::History::load(&eff.versions_dir);
+        assert!(history.done.iter().all(|s| s.to != "2.4.0"));
+    }
+
+    #[test]
+    fn blocked_version_needs_force() {
+        let tmp = tempdir().unwrap();
+        let eff = EffectivePaths::for_test(tmp.path());
+        let tar = tmp.path().join("Windsurf-linux-x64-2.3.4.tar.gz");
+        make_fake_windsurf_tar(&tar, "2.3.4");
+        let blocked = InstallOptions {
+            quiet: true,
+            blocked: ["2.3.4".to_string()].into(),
+            ..Default::default()
+        };
+        let err = install_from_tar(tar.to_str().unwrap(), &eff, &blocked).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<InstallError>(),
+            Some(InstallError::Blocked { .. })
+        ));
+        assert!(!eff.versions_dir.join("2.3.4").exists());
+        assert!(fs::symlink_metadata(&eff.current_symlink).is_err());
+
+        let forced = InstallOptions {
+            force: true,
+            ..blocked
+        };
+        install_from_tar(tar.to_str().unwrap(), &eff, &forced).unwrap();
+        assert_eq!(
+            crate::history::current_version_name(&eff.current_symlink).as_deref(),
+            Some("2.3.4")
+        );
+    }
+
+    #[test]
+    fn require_closed_refuses_to_switch_away_from_a_running_version() {
+        let tmp = tempdir().unwrap();
+        let eff = EffectivePaths::for_test(tmp.path());
+        let tar = tmp.path().join("Windsurf-linux-x64-1.0.0.tar.gz");
+        make_fake_windsurf_tar(&tar, "1.0.0");
+        let quiet = InstallOptions {
+            quiet: true,
+            ..Default::default()
+        };
+        install_from_tar(tar.to_str().unwrap(), &eff, &quiet).unwrap();
+        // "Windsurf" 1.0.0 is open
+        let exe = eff.versions_dir.join("1.0.0/Windsurf/windsurf-helper");
+        fs::copy("/bin/sleep", &exe).unwrap();
+        let mut open = std::process::Command::new(&exe).arg("30").spawn().unwrap();
+        assert_eq!(
+            running_instances(&eff.versions_dir),
+            vec![(open.id(), "1.0.0".to_string())]
+        );
+
+        let tar = tmp.path().join("Windsurf-linux-x64-1.1.0.tar.gz");
+        make_fake_windsurf_tar(&tar, "1.1.0");
+        let strict = InstallOptions {
+            require_closed: true,
+            ..quiet.clone()
+        };
+        let err = install_from_tar(tar.to_str().unwrap(), &eff, &strict).unwrap_err();
+        let still_running = matches!(
+            err.downcast_ref::<InstallError>(),
+            Some(InstallError::StillRunning { pid, running, version })
+                if *pid == open.id() && running == "1.0.0" && version == "1.1.0"
+        );
+        // reinstalling the running version switches nothing away from it
+        let same = guard_running(&eff.versions_dir, "1.0.0", true);
+        open.kill().unwrap();
+        open.wait().unwrap();
+        assert!(still_running, "{:#}", err);
+        assert!(same.is_ok());
+        assert!(eff.versions_dir.join("1.1.0").is_dir());
+        assert_eq!(
+            crate::history::current_version_name(&eff.current_symlink).as_deref(),
+            Some("1.0.0")
+        );
+    }
+
+    #[test]
+    fn failing_smoke_test_keeps_dir_and_current() {
+        let tmp = tempdir().unwrap();
+        let eff = EffectivePaths::for_test(tmp.path());
+        let tar_path = tmp.path().join("Windsurf-linux-x64-2.3.4.tar.gz");
+        make_fake_windsurf_tar(&tar_path, "2.3.4");
+
+        let err =
+            install_from_tar(tar_path.to_str().unwrap(), &eff, &smoke("exit 3", 5000)).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<InstallError>(),
+            Some(InstallError::SmokeTestFailed { dir, .. }) if dir.ends_with(".failed-2.3.4")
+        ));
+        assert!(
+            eff.versions_dir.join(".failed-2.3.4/Windsurf").is_dir(),
+            "kept for debugging"
+        );
+        assert!(!eff.versions_dir.join("2.3.4").exists());
+        assert!(std::fs::symlink_metadata(&eff.current_symlink).is_err());
+    }
+
+    #[test]
+    fn rollback_never_picks_a_build_that_failed_its_smoke_test() {
+        let tmp = tempdir().unwrap();
+        let eff = EffectivePaths::for_test(tmp.path());
+        fs::create_dir_all(eff.versions_dir.join("0.9.0/Windsurf")).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        for (v, opts) in [
+            ("1.0.0", smoke("true", 5000)),
+            ("1.1.0", smoke("exit 3", 5000)),
+        ] {
+            let tar = tmp.path().join(format!("Windsurf-linux-x64-{}.tar.gz", v));
+            make_fake_windsurf_tar(&tar, v);
+            let _ = install_from_tar(tar.to_str().unwrap(), &eff, &opts);
+        }
+        assert_eq!(
+            crate::history::current_version_name(&eff.current_symlink).as_deref(),
+            Some("1.0.0")
+        );
+        // the failed build is the newest dir, yet no candidate
+        assert_eq!(rollback_plan(&eff).unwrap().target(), "0.9.0");
+        assert!(rollback_candidates(&eff)
+            .unwrap()
+            .iter()
+            .all(|c| !c.name.contains("1.1.0")));
+        assert!(list_version_dirs(&eff.versions_dir)
+            .unwrap()
+            .iter()
+            .all(|p| !p.ends_with(".failed-1.1.0")));
+    }
+
+    #[test]
+    fn glibc_too_old_warns_or_with_strict_refuses() {
+        use crate::compat::GlibcVersion;
+        let tmp = tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("Windsurf")).unwrap();
+        fs::write(
+            tmp.path().join("Windsurf/windsurf"),
+            include_bytes!("../tests/fixtures/elf/needs_glibc_2.34"),
+        )
+        .unwrap();
+        let glibc = |v: &str| {
+            let v = GlibcVersion::from_node(&format!("GLIBC_{}", v)).unwrap();
+            Some((PathBuf::from("/lib/libc.so.6"), v))
+        };
+
+        let err = check_glibc(tmp.path(), glibc("2.31"), true).unwrap_err();
+        assert!(
+            err.to_string().starts_with(
+                "Windsurf/windsurf needs GLIBC_2.34 but the system glibc (/lib/libc.so.6) is 2.31"
+            ),
+            "{}",
+            err
+        );
+        check_glibc(tmp.path(), glibc("2.31"), false).unwrap();
+        check_glibc(tmp.path(), glibc("2.35"), true).unwrap();
+        // no glibc (musl): skipped
+        check_glibc(tmp.path(), None, true).unwrap();
+    }
+
+    #[test]
+    fn smoke_test_times_out_and_passes() {
+        let tmp = tempdir().unwrap();
+        let st = SmokeTest {
+            cmd: "sleep 5".to_string(),
+            timeout: Duration::from_millis(100),
+        };
+        let err = run_smoke_test(tmp.path(), &st).unwrap_err();
+        assert!(err.contains("timed out"), "{}", err);
+
+        // what the command started goes too, not only the `sh -c`
+        let st = SmokeTest {
+            cmd: "sleep 30 & echo $! > app.pid; wait".to_string(),
+            timeout: Duration::from_millis(300),
+        };
+        run_smoke_test(tmp.path(), &st).unwrap_err();
+        let pid = fs::read_to_string(tmp.path().join("app.pid")).unwrap();
+        let stat = format!("/proc/{}/stat", pid.trim());
+        let deadline = Instant::now() + Duration::from_secs(5);
+        // gone, or a zombie until whoever adopted it reaps it
+        while fs::read_to_string(&stat).is_ok_and(|s| !s.contains(") Z ")) {
+            assert!(Instant::now() < deadline, "{} still runs", pid.trim());
+            std::thread::sleep(Duration::from_millis(20));
+        }
+
+        let eff = EffectivePaths::for_test(tmp.path());
+        let tar_path = tmp.path().join("Windsurf-linux-x64-2.3.5.tar.gz");
+        make_fake_windsurf_tar(&tar_path, "2.3.5");
+        install_from_tar(
+            tar_path.to_str().unwrap(),
+            &eff,
+            &smoke("test -d {dir}/Windsurf", 5000),
+        )
+        .unwrap();
+        assert!(eff.current_symlink.exists());
+    }
+
+    #[test]
+    fn existing_version_dir_is_only_replaced_by_same_archive_or_force() {
+        let tmp = tempdir().unwrap();
+        let eff = EffectivePaths::for_test(tmp.path());
+        let quiet = InstallOptions {
+            quiet: true,
+            ..Default::default()
+        };
+        let tar_path = tmp.path().join("Windsurf-linux-x64-2.3.4.tar.gz");
+        make_fake_windsurf_tar(&tar_path, "2.3.4");
+        install_from_tar(tar_path.to_str().unwrap(), &eff, &quiet).unwrap();
+        let dir = eff.versions_dir.join("2.3.4");
+        fs::write(dir.join("marker"), "kept").unwrap();
+
+        // same archive again: the existing dir is reused untouched
+        install_from_tar(tar_path.to_str().unwrap(), &eff, &quiet).unwrap();
+        assert!(dir.join("marker").exists());
+
+        // another archive claiming the same version is refused
+        let other = tmp.path().join("other");
+        fs::create_dir_all(&other).unwrap();
+        let other_tar = other.join("Windsurf-linux-x64-2.3.4.tar.gz");
+        make_fake_windsurf_tar(&other_tar, "2.3.5");
+        let err = install_from_tar(other_tar.to_str().unwrap(), &eff, &quiet).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<InstallError>(),
+            Some(InstallError::VersionExists { .. })
+        ));
+        assert!(dir.join("marker").exists());
+        let staging_left = fs::read_dir(&eff.versions_dir)
+            .unwrap()
+            .flatten()
+            .any(|e| e.file_name().to_string_lossy().starts_with(".staging-"));
+        assert!(!staging_left);
+
+        // --force replaces it
+        let forced = InstallOptions {
+            force: true,
+            ..quiet.clone()
+        };
+        install_from_tar(other_tar.to_str().unwrap(), &eff, &forced).unwrap();
+        assert!(!dir.join("marker").exists());
+        let meta = crate::meta::read(&dir).unwrap();
+        assert!(meta
+            .source
+            .ends_with("other/Windsurf-linux-x64-2.3.4.tar.gz"));
+    }
+
+    #[test]
+    fn version_names_keep_prerelease_and_build_metadata() {
+        assert_eq!(
+            extract_version_from_filename("/t/Windsurf-linux-x64-1.12.11.tar.gz").as_deref(),
+            Some("1.12.11")
+        );
+        assert_eq!(
+            extract_version_from_filename("Windsurf-linux-x64-1.13.0-nightly.3+b7.tgz").as_deref(),
+            Some("1.13.0-nightly.3+b7")
+        );
+
+        let pick = |f: Option<&str>, p: Option<&str>| {
+            pick_version_name(f.map(String::from), p.map(String::from))
+        };
+        // product.json refines the same release
+        assert_eq!(
+            pick(Some("1.12.11"), Some("1.12.11-nightly.3")).as_deref(),
+            Some("1.12.11-nightly.3")
+        );
+        // otherwise the archive name wins
+        assert_eq!(
+            pick(Some("1.12.11"), Some("1.12.10")).as_deref(),
+            Some("1.12.11")
+        );
+        // unusable names are ignored
+        assert_eq!(pick(None, Some("../../etc")), None);
+        assert_eq!(pick(None, Some("1.2.3")).as_deref(), Some("1.2.3"));
+    }
+
+    /// `Windsurf/f0..f2`, 20000 bytes each, as a plain tar.
+    fn three_file_tar() -> Vec<u8> {
+        let mut b = tar::Builder::new(Vec::new());
+        for i in 0..3u32 {
+            let data: Vec<u8> = (0..20000u32).map(|x| (x * 7 + i) as u8).collect();
+            let mut h = tar::Header::new_gnu();
+            h.set_path(format!("Windsurf/f{}", i)).unwrap();
+            h.set_mode(0o644);
+            h.set_size(data.len() as u64);
+            h.set_cksum();
+            b.append(&h, &data[..]).unwrap();
+        }
+        b.into_inner().unwrap()
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut enc = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut enc, data).unwrap();
+        enc.finish().unwrap()
+    }
+
+    #[test]
+    fn truncated_archives_are_told_apart_from_other_failures() {
+        let td = tempdir().unwrap();
+        let raw = three_file_tar();
+        let (gz, zst) = (gzip(&raw), zstd::encode_all(&raw[..], 3).unwrap());
+        let extract = |bytes: &[u8]| {
+            let archive = td.path().join("w.tar.gz");
+            fs::write(&archive, bytes).unwrap();
+            let dest = tempdir().unwrap();
+            extract_tar_to_dir(archive.to_str().unwrap(), dest.path(), ProgressMode::Hidden)
+                .unwrap_err()
+        };
+        let cut = |data: &[u8], at: usize| data[..at].to_vec();
+
+        let truncated = [
+            // the compressed stream stops: flate2 and zstd report UnexpectedEof
+            cut(&gz, 10),
+            cut(&gz, gz.len() / 2),
+            cut(&zst, zst.len() / 2),
+            cut(&zst, zst.len() - 1),
+            // a complete gzip of a partial tar: in a header, in a file's data,
+            // past a file's data while skipping to the next header
+            gzip(&raw[..100]),
+            gzip(&raw[..10000]),
+            gzip(&raw[..20600]),
+        ];
+        for (i, bytes) in truncated.iter().enumerate() {
+            let e = extract(bytes);
+            assert!(is_truncation_error(&e), "case {}: {:#}", i, e);
+        }
+
+        let mut corrupt = gz.clone();
+        for b in &mut corrupt[20..60] {
+            *b = !*b;
+        }
+        let e = extract(&corrupt);
+        assert!(!is_truncation_error(&e), "{:#}", e);
+        assert!(!is_truncation_error(&anyhow::anyhow!(
+            "unexpected status 404"
+        )));
+    }
+
+    #[test]
+    fn a_truncated_tarball_fails_as_an_invalid_archive() {
+        let tmp = tempdir().unwrap();
+        let eff = EffectivePaths::for_test(tmp.path());
+        let tar_path = tmp.path().join("Windsurf-linux-x64-2.3.4.tar.gz");
+        make_fake_windsurf_tar(&tar_path, "2.3.4");
+        let full = fs::read(&tar_path).unwrap();
+        fs::write(&tar_path, &full[..full.len() / 2]).unwrap();
+
+        let opts = InstallOptions {
+            quiet: true,
+            ..Default::default()
+        };
+        let err = install_from_tar(tar_path.to_str().unwrap(), &eff, &opts).unwrap_err();
+        assert!(is_truncation_error(&err));
+        assert!(matches!(
+            err.downcast_ref::<WindmanError>(),
+            Some(WindmanError::ArchiveInvalid { .. })
+        ));
+        assert!(format!("{:#}", err).contains("ends early"), "{:#}", err);
+        assert!(std::fs::symlink_metadata(&eff.current_symlink).is_err());
+    }
+
+    #[test]
+    fn extract_reports_file_count_and_bytes() {
+        let tmp = tempdir().unwrap();
+        let tar_path = tmp.path().join("Windsurf-linux-x64-1.0.0.tar.gz");
+        make_fake_windsurf_tar(&tar_path, "1.0.0");
+        let dest = tmp.path().join("out");
+        fs::create_dir_all(&dest).unwrap();
+
+        let stats =
+            extract_tar_to_dir(tar_path.to_str().unwrap(), &dest, ProgressMode::Hidden).unwrap();
+        let prod_len = r#"{ "windsurfVersion":"1.0.0" }"#.len() as u64;
+        assert_eq!(stats.files, 2);
+        assert_eq!(stats.bytes, 2 + prod_len);
+        assert!(dest.join("Windsurf/bin/windsurf").is_file());
+    }
+
+    #[test]
+    fn failed_extraction_leaves_no_staging_dir() {
+        let tmp = tempdir().unwrap();
+        let eff = EffectivePaths::for_test(&tmp.path().join("prefix"));
+        let tar_path = tmp.path().join("Windsurf-linux-x64-1.0.0.tar.gz");
+        fs::write(&tar_path, b"not a gzip stream").unwrap();
+
+        let res = install_from_tar(
+            tar_path.to_str().unwrap(),
+            &eff,
+            &InstallOptions {
+                quiet: true,
+                ..Default::default()
+            },
+        );
+
+        assert!(res.is_err());
+        let leftovers: Vec<_> = fs::read_dir(&eff.versions_dir)
+            .unwrap()
+            .flatten()
+            .map(|e| e.file_name())
+            .collect();
+        assert!(leftovers.is_empty(), "{:?}", leftovers);
+        assert!(!eff.current_symlink.exists());
+    }
+
+    #[test]
+    fn discard_staging_removes_tree() {
+        let tmp = tempdir().unwrap();
+        let staging = tmp.path().join(".staging-1");
+        fs::create_dir_all(staging.join("Windsurf/bin")).unwrap();
+        fs::write(staging.join("Windsurf/bin/windsurf"), b"x").unwrap();
+        discard_staging(&staging);
+        assert!(!staging.exists());
+        discard_staging(&staging);
+    }
+}
+
+#[cfg(test)]
+mod tests_uninstall {
+    use super::*;
+    use crate::config::ConfigPaths;
+    use tempfile::tempdir;
+
+    /// A temp HOME with an install, desktop entry, cache, config and Windsurf user data.
+    fn populated_home(home: &Path) -> (EffectivePaths, ConfigPaths) {
+        let eff = EffectivePaths {
+            prefix_dir: home.join(".local/opt/windsurf"),
+            versions_dir: home.join(".local/opt/windsurf"),
+            current_symlink: home.join(".local/opt/windsurf/current"),
+            bin_dir: home.join(".local/bin"),
+            bin_shim: home.join(".local/bin/windsurf"),
+            desktop_file: home.join(".local/share/applications/windsurf.desktop"),
+            icons_dir: home.join(".local/share/icons"),
+            cache_dir: home.join(".cache/windman"),
+            state_dir: home.join(".local/state/windman"),
+            profile: None,
+            arch: None,
+        };
+        let cfg = ConfigPaths {
+            dir: home.join(".config/windman"),
+            file: home.join(".config/windman/windman.toml"),
+        };
+        fs::create_dir_all(eff.versions_dir.join("1.0.0")).unwrap();
+        std::os::unix::fs::symlink(eff.versions_dir.join("1.0.0"), &eff.current_symlink).unwrap();
+        crate::util::write_shim(&eff.bin_shim, &eff.current_symlink, None).unwrap();
+        for f in [
+            &eff.desktop_file,
+            &eff.icons_dir.join("windsurf.png"),
+            &eff.icons_dir.join("other-app.png"),
+            &eff.cache_dir.join("downloads/stable/1.0.0/w.tar.gz"),
+            &cfg.file,
+            &home.join(".config/Windsurf/User/settings.json"),
+            &home.join(".windsurf/extensions/ext.json"),
+        ] {
+            fs::create_dir_all(f.parent().unwrap()).unwrap();
+            fs::write(f, b"x").unwrap();
+        }
+        (eff, cfg)
+    }
+
+    fn survivors(home: &Path) -> Vec<String> {
+        let mut out: Vec<String> = walkdir::WalkDir::new(home)
+            .into_iter()
+            .flatten()
+            .filter(|e| !e.file_type().is_dir())
+            .map(|e| e.path().strip_prefix(home).unwrap().display().to_string())
+            .collect();
+        out.sort();
+        out
+    }
+
+    const USER_DATA: [&str; 2] = [
+        ".config/Windsurf/User/settings.json",
+        ".windsurf/extensions/ext.json",
+    ];
+
+    #[test]
+    fn plain_uninstall_keeps_desktop_cache_and_config() {
+        let td = tempdir().unwrap();
+        let (eff, cfg) = populated_home(td.path());
+        uninstall_all(&eff, false, Some(&cfg)).unwrap();
+        let mut want = vec![
+            ".cache/windman/downloads/stable/1.0.0/w.tar.gz",
+            ".config/windman/windman.toml",
+            ".local/share/applications/windsurf.desktop",
+            ".local/share/icons/other-app.png",
+            ".local/share/icons/windsurf.png",
+        ];
+        want.extend(USER_DATA);
+        want.sort();
+        assert_eq!(survivors(td.path()), want);
+    }
+
+    #[test]
+    fn purge_removes_config_and_cache_but_not_user_data() {
+        let td = tempdir().unwrap();
+        let (eff, cfg) = populated_home(td.path());
+        let removed = uninstall_all(&eff, true, Some(&cfg)).unwrap();
+        let mut want = vec![".local/share/icons/other-app.png"];
+        want.extend(USER_DATA);
+        want.sort();
+        assert_eq!(survivors(td.path()), want);
+        assert!(removed.contains(&cfg.dir));
+        assert!(removed.contains(&eff.cache_dir));
+    }
+
+    #[test]
+    fn purge_leaves_what_else_lives_in_a_shared_cache_dir() {
+        let td = tempdir().unwrap();
+        let (eff, cfg) = populated_home(td.path());
+        // WINDMAN_CACHE_DIR / paths.cache_dir pointed at a shared scratch disk
+        let eff = EffectivePaths {
+            cache_dir: td.path().join("scratch"),
+            ..eff
+        };
+        for f in [
+            "downloads/stable/1.0.0/w.tar.gz",
+            "stable/check.json",
+            "stable/latest-http.json",
+            "next/check.json",
+            "next/notes.txt",
+            "someone-else/data.bin",
+            "README",
+        ] {
+            let f = eff.cache_dir.join(f);
+            fs::create_dir_all(f.parent().unwrap()).unwrap();
+            fs::write(f, b"x").unwrap();
+        }
+        uninstall_all(&eff, true, Some(&cfg)).unwrap();
+
+        let mut left: Vec<String> = walkdir::WalkDir::new(&eff.cache_dir)
+            .into_iter()
+            .flatten()
+            .map(|e| {
+                let rel = e.path().strip_prefix(&eff.cache_dir).unwrap();
+                rel.to_string_lossy().into_owned()
+            })
+            .collect();
+        left.sort();
+        assert_eq!(
+            left,
+            [
+                "",
+                "README",
+                "next",
+                "next/notes.txt",
+                "someone-else",
+                "someone-else/data.bin"
+            ]
+        );
+    }
+
+    #[test]
+    fn symlinked_version_pointing_outside_is_unlinked_not_followed() {
+        let td = tempdir().unwrap();
+        let (eff, cfg) = populated_home(td.path());
+        // named like a version, pointing at the home dir itself
+        std::os::unix::fs::symlink(td.path(), eff.versions_dir.join("0.9.0")).unwrap();
+        uninstall_all(&eff, true, Some(&cfg)).unwrap();
+        let mut want = vec![".local/share/icons/other-app.png"];
+        want.extend(USER_DATA);
+        want.sort();
+        assert_eq!(survivors(td.path()), want);
+    }
+
+    #[test]
+    fn uninstall_plan_touches_nothing_and_lists_what_is_removed() {
+        let td = tempdir().unwrap();
+        let (eff, cfg) = populated_home(td.path());
+        let before = survivors(td.path());
+
+        let plan = uninstall_plan(&eff, true, Some(&cfg));
+        assert_eq!(survivors(td.path()), before);
+        assert!(plan.contains(&Removal::Tree(eff.versions_dir.clone())));
+        assert!(plan.contains(&Removal::EmptyDir(cfg.dir.clone())));
+        let downloads = eff.cache_dir.join("downloads");
+        let cache = plan.iter().find(|r| r.path() == downloads).unwrap();
+        assert_eq!(cache.bytes(), 1);
+        assert!(plan.contains(&Removal::EmptyDir(eff.cache_dir.clone())));
+
+        let removed = uninstall_all(&eff, true, Some(&cfg)).unwrap();
+        let planned: Vec<PathBuf> = plan.iter().map(|r| r.path().to_path_buf()).collect();
+        assert_eq!(removed, planned);
+    }
+
+    #[test]
+    fn rollback_plan_says_whether_it_follows_history_or_mtime() {
+        let td = tempdir().unwrap();
+        let (eff, _cfg) = populated_home(td.path());
+        fs::create_dir_all(eff.versions_dir.join("1.1.0")).unwrap();
+        fs::create_dir_all(eff.versions_dir.join("0.9.0")).unwrap();
+        // current (1.0.0) was never recorded: the newest other dir
+        let plan = rollback_plan(&eff).unwrap();
+        assert!(!plan.from_history);
+        assert_eq!(plan.target(), "0.9.0");
+        assert!(plan.reason().contains("mtime"), "{}", plan.reason());
+
+        atomic_symlink_switch(&eff.versions_dir.join("1.1.0"), &eff.current_symlink).unwrap();
+        crate::history::record(&eff.versions_dir, Some("1.0.0"), "1.1.0");
+        let before = survivors(td.path());
+        let plan = rollback_plan(&eff).unwrap();
+        assert!(plan.from_history);
+        assert_eq!(plan.target(), "1.0.0");
+        assert!(plan
+            .reason()
+            .starts_with("undoes the switch 1.0.0 -> 1.1.0"));
+        assert_eq!(survivors(td.path()), before);
+        assert_eq!(
+            crate::history::current_version_name(&eff.current_symlink).as_deref(),
+            Some("1.1.0")
+        );
+    }
+
+    #[test]
+    fn purge_with_keep_config_retains_toml() {
+        let td = tempdir().unwrap();
+        let (eff, _cfg) = populated_home(td.path());
+        uninstall_all(&eff, true, None).unwrap();
+        let mut want = vec![
+            ".config/windman/windman.toml",
+            ".local/share/icons/other-app.png",
+        ];
+        want.extend(USER_DATA);
+        want.sort();
+        assert_eq!(survivors(td.path()), want);
+    }
+}
+
+#[cfg(test)]
+mod tests_unpack_archive {
+    use super::*;
+    use std::os::unix::fs::MetadataExt;
+    use tempfile::tempdir;
+
+    /// Archived mtime of every entry below.
+    const MTIME: u64 = 1_727_000_000;
+
+    /// A gzipped tar of `entries`: (path, type, mode, symlink target or file data).
+    fn tarball(entries: &[(&str, tar::EntryType, u32, &str)]) -> Vec<u8> {
+        let enc = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        let mut b = tar::Builder::new(enc);
+        for (path, kind, mode, data) in entries {
+            let mut h = tar::Header::new_gnu();
+            h.set_entry_type(*kind);
+            h.set_mode(*mode);
+            h.set_mtime(MTIME);
+            if *kind == tar::EntryType::Symlink {
+                h.set_size(0);
+                b.append_link(&mut h, path, data).unwrap();
+            } else {
+                let data = if kind.is_file() { data.as_bytes() } else { &[] };
+                h.set_size(data.len() as u64);
+                b.append_data(&mut h, path, data).unwrap();
+            }
+        }
+        b.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn unpack(data: &[u8], dest: &Path) -> Result<ExtractStats> {
+        unpack_archive(data, "test.tar.gz", dest, &Progress::hidden())
+    }
+
+    #[test]
+    fn every_fsync_mode_syncs_read_only_entries_and_skips_symlinks() {
+        use tar::EntryType::{Directory, Regular, Symlink};
+        let data = tarball(&[
+            ("Windsurf/readonly/", Directory, 0o555, ""),
+            ("Windsurf/readonly/file", Regular, 0o444, "r"),
+            ("Windsurf/dangling", Symlink, 0o777, "missing"),
+        ]);
+        let td = tempdir().unwrap();
+        unpack(&data, td.path()).unwrap();
+        for mode in [ExtractFsync::None, ExtractFsync::Dir, ExtractFsync::All] {
+            sync_staged(td.path(), mode).unwrap();
+        }
+        let missing = td.path().join("gone");
+        assert!(sync_staged(&missing, ExtractFsync::None).is_ok());
+        assert!(sync_staged(&missing, ExtractFsync::Dir).is_err());
+    }
+
+    #[test]
+    fn modes_mtimes_and_relative_symlinks_survive_extraction() {
+        use tar::EntryType::{Directory, Regular, Symlink};
+        let bin = "Windsurf/resources/app/node_modules/.bin";
+        let data = tarball(&[
+            (
+                "Windsurf/resources/app/node_modules/tool/",
+                Directory,
+                0o755,
+                "",
+            ),
+            (
+                "Windsurf/resources/app/node_modules/tool/cli.js",
+                Regular,
+                0o755,
+                "#!/usr/bin/env node\n",
+            ),
+            (&format!("{}/tool", bin), Symlink, 0o777, "../tool/cli.js"),
+            ("Windsurf/private/", Directory, 0o700, ""),
+            ("Windsurf/private/key", Regular, 0o600, "k"),
+            ("Windsurf/readonly/", Directory, 0o555, ""),
+            ("Windsurf/readonly/file", Regular, 0o444, "r"),
+        ]);
+        let td = tempdir().unwrap();
+        let stats = unpack(&data, td.path()).unwrap();
+        assert_eq!(stats.files, 3);
+
+        let root = td.path().join("Windsurf");
+        let mode = |p: &str| fs::symlink_metadata(root.join(p)).unwrap().mode() & 0o7777;
+        assert_eq!(mode("resources/app/node_modules/tool/cli.js"), 0o755);
+        assert_eq!(mode("private"), 0o700);
+        assert_eq!(mode("private/key"), 0o600);
+        assert_eq!(mode("readonly"), 0o555);
+        assert_eq!(mode("readonly/file"), 0o444);
+
+        let link = td.path().join(bin).join("tool");
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_link(&link).unwrap(), Path::new("../tool/cli.js"));
+        assert_eq!(fs::read_to_string(&link).unwrap(), "#!/usr/bin/env node\n");
+
+        for p in [
+            "private",
+            "private/key",
+            "resources/app/node_modules/tool/cli.js",
+        ] {
+            assert_eq!(
+                fs::metadata(root.join(p)).unwrap().mtime() as u64,
+                MTIME,
+                "{}",
+                p
+            );
+        }
+        // writable again, so the tempdir can go
+        fs::set_permissions(root.join("readonly"), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn symlinks_leaving_the_archive_are_refused() {
+        use tar::EntryType::Symlink;
+        for target in ["/etc/passwd", "../../outside", "a/../../../x"] {
+            let data = tarball(&[("Windsurf/link", Symlink, 0o777, target)]);
+            let td = tempdir().unwrap();
+            let err = unpack(&data, &td.path().join("staging")).unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<WindmanError>(),
+                Some(WindmanError::ArchiveInvalid { .. })
+            ));
+            assert!(
+                err.to_string().contains("links outside the archive"),
+                "{}: {}",
+                target,
+                err
+            );
+        }
+        // climbing back into the archive is fine
+        let data = tarball(&[("Windsurf/bin/link", Symlink, 0o777, "../lib/../bin/x")]);
+        let td = tempdir().unwrap();
+        unpack(&data, td.path()).unwrap();
+    }
+
+    #[test]
+    fn entries_through_links_of_earlier_entries_are_refused() {
+        use tar::EntryType::{Directory, Symlink};
+        // each link is inside the archive on its own; chained they climb out
+        let chain = [
+            ("W/x", Symlink, 0o777, "."),
+            ("W/x/y", Symlink, 0o777, ".."),
+            ("W/x/y/z", Symlink, 0o777, ".."),
+        ];
+        for last in [
+            ("W/x/y/z/PWNED/", Directory, 0o755, ""),
+            ("W/x/y/z/PWNED", Symlink, 0o777, "a"),
+        ] {
+            let mut entries = chain.to_vec();
+            entries.push(last);
+            let td = tempdir().unwrap();
+            let staging = td.path().join("versions/staging");
+            let err = unpack(&tarball(&entries), &staging).unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<WindmanError>(),
+                Some(WindmanError::ArchiveInvalid { .. })
+            ));
+            assert!(err.to_string().contains("goes through the link"), "{}", err);
+            assert!(!td.path().join("versions/PWNED").exists());
+            assert!(!td.path().join("PWNED").exists());
+        }
     }
 }
@@ -1,19 +1,94 @@
-use anyhow::Result;
+use std::process::ExitCode;
 
+mod alias;
+mod audit;
+mod blocklist;
+mod cache;
+mod cancel;
+mod check;
 mod cli;
+mod compat;
 mod config;
+mod dedup;
 mod desktop;
 mod download;
+mod error;
+mod export;
+mod gc;
+mod history;
+mod http;
+mod inspect;
 mod install;
+mod manifest;
+mod manpage;
+mod meta;
+mod migrate;
+mod notes;
+mod notify;
 mod paths;
+mod progress;
+mod project;
+mod provenance;
 mod prune;
 mod remote;
+mod report;
+mod shell;
+mod shims;
+mod space;
+mod staged;
+mod timer;
+mod trash;
+mod ui;
 mod util;
+mod verify;
 mod version;
 
 use cli::Cli;
 
-fn main() -> Result<()> {
+/// Exit code when the new version failed its smoke test (current left untouched).
+const EXIT_SMOKE_TEST_FAILED: u8 = 3;
+/// Exit code when a confirmation was needed without a terminal and without `--assume-yes`.
+const EXIT_NOT_INTERACTIVE: u8 = 4;
+/// Exit code when a server could not be reached (`WindmanError::Network`).
+const EXIT_NETWORK: u8 = 5;
+/// Exit code when a signature or checksum did not match (`WindmanError::VerificationFailed`).
+const EXIT_VERIFICATION_FAILED: u8 = 6;
+/// Exit code when the version asked for is not installed.
+const EXIT_NOT_INSTALLED: u8 = 7;
+/// Exit code when an archive cannot be installed as it is (`WindmanError::ArchiveInvalid`).
+const EXIT_ARCHIVE_INVALID: u8 = 8;
+/// Exit code of `update` when every target was already up to date (nothing installed).
+const EXIT_UP_TO_DATE: u8 = 10;
+/// Exit code after Ctrl-C (128 + SIGINT), once partial files were cleaned up.
+const EXIT_INTERRUPTED: u8 = 130;
+
+fn main() -> ExitCode {
     let cli = Cli::parse();
-    cli.run()
+    ui::set_color(cli.color);
+    util::set_assume_yes(cli.assume_yes);
+    if let Err(e) = cancel::install_handler() {
+        ui::warn(format_args!("{:#}", e));
+    }
+    match cli.run() {
+        Ok(code) => code,
+        Err(e) if e.is::<cancel::Cancelled>() => {
+            eprintln!("Interrupted.");
+            ExitCode::from(EXIT_INTERRUPTED)
+        }
+        Err(e) => {
+            anstream::eprintln!("{} {:?}", ui::paint(ui::ERROR, "Error:"), e);
+            if e.downcast_ref::<util::NotInteractive>().is_some() {
+                return ExitCode::from(EXIT_NOT_INTERACTIVE);
+            }
+            if let Some(err) = e.downcast_ref::<error::WindmanError>() {
+                return ExitCode::from(err.exit_code());
+            }
+            match e.downcast_ref::<install::InstallError>() {
+                Some(install::InstallError::SmokeTestFailed { .. }) => {
+                    ExitCode::from(EXIT_SMOKE_TEST_FAILED)
+                }
+                Some(_) | None => ExitCode::FAILURE,
+            }
+        }
+    }
 }
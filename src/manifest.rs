@@ -0,0 +1,301 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::progress::ProgressMode;
+
+/// Bumped only when a key changes meaning or goes away; mirroring scripts
+/// check it before reading `entries`.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// `windman remote manifest`: the latest release of each channel/arch.
+#[derive(Debug, Clone, Serialize)]
+pub struct Manifest {
+    pub schema: u32,
+    pub generated_at: DateTime<Utc>,
+    pub entries: Vec<Entry>,
+}
+
+/// One channel/arch. Every key is always present (`null` when unknown), so
+/// consumers never have to tell "missing" from "not obtainable".
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Entry {
+    pub channel: String,
+    pub arch: String,
+    pub version: Option<String>,
+    pub url: Option<String>,
+    /// From a HEAD request on `url`
+    pub content_length: Option<u64>,
+    /// Published by the API, or computed with `--verify`
+    pub sha256: Option<String>,
+    /// `sha256` comes from downloading the artifact
+    pub verified: bool,
+    pub error: Option<String>,
+}
+
+impl Entry {
+    fn new(channel: &str, arch: &str) -> Self {
+        Self {
+            channel: channel.to_string(),
+            arch: arch.to_string(),
+            version: None,
+            url: None,
+            content_length: None,
+            sha256: None,
+            verified: false,
+            error: None,
+        }
+    }
+}
+
+/// Query `endpoint(arch, channel)` for each `(channel, arch)` and probe the
+/// download it names. With `verify`, download and hash each artifact too. A
+/// failure stays in its entry (`error`), the others are still filled in.
+pub fn build(
+    targets: &[(String, String)],
+    endpoint: impl Fn(&str, &str) -> String,
+    verify: bool,
+    progress: ProgressMode,
+    timeout_secs: Option<u64>,
+) -> Result<Manifest> {
+    let client = crate::remote::build_client(timeout_secs)?;
+    let entries = targets
+        .iter()
+        .map(|(channel, arch)| {
+            let mut e = Entry::new(channel, arch);
+            if let Err(err) = fill(
+                &mut e,
+                &client,
+                &endpoint(arch, channel),
+                verify,
+                progress,
+                timeout_secs,
+            ) {
+                e.error = Some(format!("{:#}", err));
+            }
+            e
+        })
+        .collect();
+    Ok(Manifest {
+        schema: SCHEMA_VERSION,
+        generated_at: Utc::now(),
+        entries,
+    })
+}
+
+fn fill(
+    e: &mut Entry,
+    client: &reqwest::blocking::Client,
+    endpoint: &str,
+    verify: bool,
+    progress: ProgressMode,
+    timeout_secs: Option<u64>,
+) -> Result<()> {
+    let info = crate::remote::try_latest_via_api(client, endpoint)?.parsed?;
+    e.version = Some(info.version);
+    e.url = Some(info.url.clone());
+    e.sha256 = info.sha256;
+
+    let head = crate::remote::head(client, &info.url)?;
+    if !head.ok() {
+        anyhow::bail!("HTTP {} for {}", head.status, info.url);
+    }
+    e.content_length = head.content_length;
+
+    if verify {
+        if progress != ProgressMode::Hidden {
+            eprintln!("Verifying {} {} {}", e.channel, e.arch, info.url);
+        }
+        let (digest, size) = crate::download::hash_remote(&info.url, timeout_secs, progress)?;
+        if let Some(published) = &e.sha256 {
+            if !published.eq_ignore_ascii_case(&digest) {
+                anyhow::bail!(
+                    "checksum mismatch: the API publishes {}, the download hashes to {}",
+                    published,
+                    digest
+                );
+            }
+        }
+        if let Some(len) = e.content_length {
+            if len != size {
+                anyhow::bail!(
+                    "size mismatch: HEAD says {} bytes, downloaded {}",
+                    len,
+                    size
+                );
+            }
+        }
+        e.content_length = Some(size);
+        e.sha256 = Some(digest);
+        e.verified = true;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    /// Answer each connection with the next canned response; `{base}` in a
+    /// response is replaced by the server's own address.
+    fn mock_server(responses: Vec<String>) -> String {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let b = base.clone();
+        std::thread::spawn(move || {
+            for resp in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buf = [0u8; 4096];
+                let _ = stream.read(&mut buf);
+                let _ = stream.write_all(resp.replace("{base}", &b).as_bytes());
+            }
+        });
+        base
+    }
+
+    fn http(status: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    }
+
+    fn api(version: &str, extra: &str) -> String {
+        // no Content-Length: the URL grows once `{base}` is substituted
+        let body = format!(
+            "{{\"version\":\"{}\",\"url\":\"{{base}}/Windsurf-{}.tar.gz\"{}}}",
+            version, version, extra
+        );
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nConnection: close\r\n\r\n{}",
+            body
+        )
+    }
+
+    fn head(len: usize) -> String {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            len
+        )
+    }
+
+    const ARTIFACT: &str = "not really a tarball";
+
+    fn targets(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(c, a)| (c.to_string(), a.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn entries_serialize_with_every_key() {
+        let m = Manifest {
+            schema: SCHEMA_VERSION,
+            generated_at: "2025-10-01T10:00:00Z".parse().unwrap(),
+            entries: vec![
+                Entry {
+                    version: Some("1.12.11".into()),
+                    url: Some("https://x.invalid/w.tar.gz".into()),
+                    content_length: Some(42),
+                    ..Entry::new("stable", "linux-x64")
+                },
+                Entry {
+                    error: Some("HTTP 503".into()),
+                    ..Entry::new("next", "linux-arm64")
+                },
+            ],
+        };
+        let v = serde_json::to_value(&m).unwrap();
+        assert_eq!(
+            v,
+            serde_json::json!({
+                "schema": 1,
+                "generated_at": "2025-10-01T10:00:00Z",
+                "entries": [
+                    {
+                        "channel": "stable", "arch": "linux-x64", "version": "1.12.11",
+                        "url": "https://x.invalid/w.tar.gz", "content_length": 42,
+                        "sha256": null, "verified": false, "error": null
+                    },
+                    {
+                        "channel": "next", "arch": "linux-arm64", "version": null,
+                        "url": null, "content_length": null,
+                        "sha256": null, "verified": false, "error": "HTTP 503"
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn probes_each_target_and_keeps_failures_in_their_entry() {
+        let base = mock_server(vec![
+            api("1.12.11", ""),
+            head(ARTIFACT.len()),
+            http("503 Service Unavailable", "down"),
+        ]);
+        let m = build(
+            &targets(&[("stable", "linux-x64"), ("stable", "linux-arm64")]),
+            |arch, channel| format!("{}/api/{}/{}", base, arch, channel),
+            false,
+            ProgressMode::Hidden,
+            Some(5),
+        )
+        .unwrap();
+
+        assert_eq!(m.schema, SCHEMA_VERSION);
+        let x64 = &m.entries[0];
+        assert_eq!(x64.version.as_deref(), Some("1.12.11"));
+        assert_eq!(
+            x64.url.as_deref(),
+            Some(format!("{}/Windsurf-1.12.11.tar.gz", base).as_str())
+        );
+        assert_eq!(x64.content_length, Some(ARTIFACT.len() as u64));
+        assert_eq!((x64.sha256.as_deref(), x64.verified), (None, false));
+        assert!(x64.error.is_none());
+
+        let arm = &m.entries[1];
+        assert_eq!(arm.arch, "linux-arm64");
+        assert!(arm.version.is_none());
+        assert!(
+            arm.error.as_deref().unwrap().contains("503"),
+            "{:?}",
+            arm.error
+        );
+    }
+
+    #[test]
+    fn verify_hashes_the_artifact_and_checks_the_published_sum() {
+        let digest = hex::encode(Sha256::digest(ARTIFACT));
+        let base = mock_server(vec![
+            api("1.12.11", ""),
+            head(ARTIFACT.len()),
+            http("200 OK", ARTIFACT),
+            api("1.13.0", &format!(",\"sha256hash\":\"{}\"", "0".repeat(64))),
+            head(ARTIFACT.len()),
+            http("200 OK", ARTIFACT),
+        ]);
+        let m = build(
+            &targets(&[("stable", "linux-x64"), ("next", "linux-x64")]),
+            |arch, channel| format!("{}/api/{}/{}", base, arch, channel),
+            true,
+            ProgressMode::Hidden,
+            Some(5),
+        )
+        .unwrap();
+
+        let ok = &m.entries[0];
+        assert_eq!(ok.sha256.as_deref(), Some(digest.as_str()));
+        assert!(ok.verified && ok.error.is_none());
+
+        let bad = &m.entries[1];
+        assert!(!bad.verified);
+        let err = bad.error.as_deref().unwrap();
+        assert!(err.contains("checksum mismatch"), "{}", err);
+    }
+}
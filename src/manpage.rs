@@ -0,0 +1,245 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One page per visible command: `windman.1`, then `windman-<path>.1` for every
+/// subcommand (`windman-config-show.1`), sorted by file name.
+pub fn render_all(cmd: &clap::Command) -> Vec<(String, String)> {
+    let version = cmd.get_version().unwrap_or_default().to_string();
+    let mut pages = Vec::new();
+    collect(cmd, &[], &version, &mut pages);
+    pages.sort();
+    pages
+}
+
+fn collect(cmd: &clap::Command, path: &[&str], version: &str, out: &mut Vec<(String, String)>) {
+    let name = page_name(path);
+    out.push((format!("{}.1", name), render(cmd, path, version)));
+    for sub in visible_subcommands(cmd) {
+        let mut sub_path = path.to_vec();
+        sub_path.push(sub.get_name());
+        collect(sub, &sub_path, version, out);
+    }
+}
+
+/// Write every page into `dir` (created if needed); the paths written, in order.
+pub fn write_all(cmd: &clap::Command, dir: &Path) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    let mut written = Vec::new();
+    for (file, page) in render_all(cmd) {
+        let p = dir.join(file);
+        fs::write(&p, page).with_context(|| format!("writing {}", p.display()))?;
+        written.push(p);
+    }
+    Ok(written)
+}
+
+fn visible_subcommands(cmd: &clap::Command) -> impl Iterator<Item = &clap::Command> {
+    cmd.get_subcommands().filter(|s| !s.is_hide_set())
+}
+
+/// `windman`, `windman-install`, `windman-config-show`.
+fn page_name(path: &[&str]) -> String {
+    std::iter::once("windman")
+        .chain(path.iter().copied())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Text as roff reads it: backslashes and dashes escaped, a line starting with
+/// `.` or `'` kept from being taken as a request.
+fn escape(s: &str) -> String {
+    s.lines()
+        .map(|l| {
+            let l = l.replace('\\', "\\e").replace('-', "\\-");
+            if l.starts_with('.') || l.starts_with('\'') {
+                format!("\\&{}", l)
+            } else {
+                l
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Help text as paragraphs (`.PP` between blank-line separated blocks).
+fn paragraphs(text: &str) -> String {
+    text.split("\n\n")
+        .map(|p| escape(p.trim()))
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n.PP\n")
+}
+
+fn render(cmd: &clap::Command, path: &[&str], version: &str) -> String {
+    let name = page_name(path);
+    let mut page = format!(
+        ".TH {} 1 \"\" \"windman {}\" \"Windman Manual\"\n",
+        name.to_uppercase().replace('-', "\\-"),
+        version
+    );
+    let about = cmd.get_about().map(|a| a.to_string()).unwrap_or_default();
+    page += &format!(".SH NAME\n{} \\- {}\n", escape(&name), escape(&about));
+
+    let args: Vec<&clap::Arg> = cmd.get_arguments().filter(|a| !a.is_hide_set()).collect();
+    page += ".SH SYNOPSIS\n";
+    page += &format!(
+        "\\fBwindman{}\\fR",
+        path.iter().map(|p| format!(" {}", p)).collect::<String>()
+    );
+    if args.iter().any(|a| !a.is_positional()) {
+        page += " [\\fIOPTIONS\\fR]";
+    }
+    for a in args.iter().filter(|a| a.is_positional()) {
+        let value = value_name(a);
+        if a.is_required_set() {
+            page += &format!(" \\fI{}\\fR", value);
+        } else {
+            page += &format!(" [\\fI{}\\fR]", value);
+        }
+    }
+    if visible_subcommands(cmd).next().is_some() {
+        page += " \\fICOMMAND\\fR";
+    }
+    page += "\n";
+
+    let long_about = cmd.get_long_about().map(|a| a.to_string());
+    if let Some(text) = long_about.or(Some(about)).filter(|t| !t.is_empty()) {
+        page += &format!(".SH DESCRIPTION\n{}\n", paragraphs(&text));
+    }
+
+    if !args.is_empty() {
+        page += ".SH OPTIONS\n";
+        for a in &args {
+            page += &format!(".TP\n{}\n", arg_label(a));
+            let help = a.get_long_help().or(a.get_help()).map(|h| h.to_string());
+            let mut help = paragraphs(&help.unwrap_or_default());
+            if let Some(env) = a.get_env() {
+                help += &format!("\n[env: {}]", escape(&env.to_string_lossy()));
+            }
+            let possible: Vec<String> = a
+                .get_possible_values()
+                .iter()
+                .filter(|v| !v.is_hide_set())
+                .map(|v| v.get_name().to_string())
+                .collect();
+            if !possible.is_empty() && a.get_action().takes_values() {
+                help += &format!("\n[possible values: {}]", escape(&possible.join(", ")));
+            }
+            page += &format!("{}\n", help.trim_start_matches('\n'));
+        }
+    }
+
+    if visible_subcommands(cmd).next().is_some() {
+        page += ".SH COMMANDS\n";
+        for sub in visible_subcommands(cmd) {
+            let mut sub_path = path.to_vec();
+            sub_path.push(sub.get_name());
+            let about = sub.get_about().map(|a| a.to_string()).unwrap_or_default();
+            page += &format!(
+                ".TP\n\\fB{}\\fR(1)\n{}\n",
+                escape(&page_name(&sub_path)),
+                escape(&about)
+            );
+        }
+    }
+
+    if let Some(after) = cmd.get_after_long_help().or(cmd.get_after_help()) {
+        let after = after.to_string();
+        let examples = after.strip_prefix("Examples:\n").unwrap_or(&after);
+        page += &format!(".SH EXAMPLES\n.nf\n{}\n.fi\n", escape(examples.trim_end()));
+    }
+
+    if !path.is_empty() {
+        page += ".SH SEE ALSO\n\\fBwindman\\fR(1)";
+        if path.len() > 1 {
+            page += &format!(
+                ", \\fB{}\\fR(1)",
+                escape(&page_name(&path[..path.len() - 1]))
+            );
+        }
+        page += "\n";
+    }
+    page
+}
+
+fn value_name(a: &clap::Arg) -> String {
+    match a.get_value_names() {
+        Some(names) if !names.is_empty() => names
+            .iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join(" "),
+        _ => a.get_id().as_str().to_uppercase(),
+    }
+}
+
+/// `\fB\-y\fR, \fB\-\-assume\-yes\fR`, with ` \fIDIR\fR` when it takes a value.
+fn arg_label(a: &clap::Arg) -> String {
+    if a.is_positional() {
+        return format!("\\fI{}\\fR", escape(&value_name(a)));
+    }
+    let mut names = Vec::new();
+    if let Some(s) = a.get_short() {
+        names.push(format!("\\fB\\-{}\\fR", s));
+    }
+    if let Some(l) = a.get_long() {
+        names.push(format!("\\fB\\-\\-{}\\fR", escape(l)));
+    }
+    let mut label = names.join(", ");
+    if a.get_action().takes_values() {
+        label += &format!(" \\fI{}\\fR", escape(&value_name(a)));
+    }
+    label
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn pages_cover_visible_commands_and_are_deterministic() {
+        let cmd = crate::cli::Cli::command();
+        let pages = render_all(&cmd);
+        let names: Vec<&str> = pages.iter().map(|(n, _)| n.as_str()).collect();
+        for expected in [
+            "windman.1",
+            "windman-install.1",
+            "windman-update.1",
+            "windman-config-show.1",
+        ] {
+            assert!(names.contains(&expected), "{} missing", expected);
+        }
+        assert!(
+            !names.iter().any(|n| n.starts_with("windman-dev-")),
+            "hidden"
+        );
+        assert_eq!(pages, render_all(&crate::cli::Cli::command()));
+
+        let install = &pages
+            .iter()
+            .find(|(n, _)| n == "windman-install.1")
+            .unwrap()
+            .1;
+        assert!(install.starts_with(".TH WINDMAN\\-INSTALL 1 \"\" \"windman "));
+        assert!(
+            install.contains(".TP\n\\fB\\-\\-tar\\fR \\fIFILE\\fR\n"),
+            "{}",
+            install
+        );
+        assert!(install.contains(".SH EXAMPLES\n.nf\n"), "{}", install);
+        let root = &pages.iter().find(|(n, _)| n == "windman.1").unwrap().1;
+        assert!(root.contains("\\fBwindman\\-install\\fR(1)"), "{}", root);
+        assert!(root.contains("[env: WINDMAN_PROFILE]"), "{}", root);
+    }
+
+    #[test]
+    fn roff_requests_and_escapes_are_neutralized() {
+        assert_eq!(
+            escape(".hidden\n'quoted\na\\b-c"),
+            "\\&.hidden\n\\&'quoted\na\\eb\\-c"
+        );
+        assert_eq!(paragraphs("one\n\n\ntwo"), "one\n.PP\ntwo");
+    }
+}
@@ -0,0 +1,161 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Per-version metadata file, written at the root of each version dir.
+pub const META_FILE: &str = ".windman-meta.json";
+
+/// Dir of a version holding the archive it was installed from (`--keep-archive`).
+pub const ARTIFACT_DIR: &str = ".artifact";
+
+/// What Windman knows about how a version got installed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionMeta {
+    pub version: String,
+    /// RFC 3339 timestamp (UTC)
+    pub installed_at: String,
+    /// Download URL, or absolute path of the local tarball
+    pub source: String,
+    /// SHA-256 of the archive, hex
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// File name of the archive kept in `.artifact/` (`--keep-archive`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<String>,
+    /// Release channel it came from (`update`); None for local archives and
+    /// versions installed before channels were recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// URL the download ended at once redirects were followed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub final_url: Option<String>,
+    /// How the archive was checked before it was installed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification: Option<crate::report::Verification>,
+    /// Version of the windman that installed it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub windman_version: Option<String>,
+    /// Host name of the machine it was installed on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+}
+
+pub fn write(version_dir: &Path, meta: &VersionMeta) -> Result<()> {
+    let p = version_dir.join(META_FILE);
+    let s = serde_json::to_string_pretty(meta)?;
+    fs::write(&p, s).with_context(|| format!("writing {}", p.display()))
+}
+
+/// Read the metadata of a version dir; None for versions installed before metadata existed.
+pub fn read(version_dir: &Path) -> Option<VersionMeta> {
+    let s = fs::read_to_string(version_dir.join(META_FILE)).ok()?;
+    serde_json::from_str(&s).ok()
+}
+
+/// The archive kept in `version_dir` at install, if it is still there.
+pub fn artifact(version_dir: &Path) -> Option<PathBuf> {
+    let name = read(version_dir)?.artifact?;
+    let p = version_dir.join(ARTIFACT_DIR).join(name);
+    p.is_file().then_some(p)
+}
+
+/// Put `archive` in `<version_dir>/.artifact/` (hardlinked when on the same
+/// filesystem, else copied) with a `.sha256` next to it, and record it in the
+/// version's metadata. Returns where it went.
+pub fn keep_artifact(version_dir: &Path, archive: &Path, sha256: &str) -> Result<PathBuf> {
+    let name = archive
+        .file_name()
+        .with_context(|| format!("{} has no file name", archive.display()))?;
+    let dir = version_dir.join(ARTIFACT_DIR);
+    fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+    let kept = dir.join(name);
+    let _ = fs::remove_file(&kept);
+    if fs::hard_link(archive, &kept).is_err() {
+        fs::copy(archive, &kept)
+            .with_context(|| format!("copying {} to {}", archive.display(), kept.display()))?;
+    }
+    crate::cache::record_checksum(&kept, sha256)?;
+
+    let mut meta = read(version_dir).unwrap_or_default();
+    meta.artifact = Some(name.to_string_lossy().to_string());
+    write(version_dir, &meta)?;
+    Ok(kept)
+}
+
+/// SHA-256 of a file, hex-encoded.
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut f = File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = f.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn roundtrip_and_missing() {
+        let td = tempdir().unwrap();
+        assert_eq!(read(td.path()), None);
+
+        let m = VersionMeta {
+            version: "1.2.3".into(),
+            installed_at: "2025-01-01T00:00:00Z".into(),
+            source: "https://example.invalid/Windsurf-1.2.3.tar.gz".into(),
+            sha256: Some("00".into()),
+            artifact: None,
+            channel: Some("next".into()),
+            final_url: Some("https://cdn.example.invalid/Windsurf-1.2.3.tar.gz".into()),
+            verification: Some(crate::report::Verification::Signature),
+            windman_version: Some("0.9.0".into()),
+            hostname: Some("build-01".into()),
+        };
+        write(td.path(), &m).unwrap();
+        assert_eq!(read(td.path()), Some(m));
+    }
+
+    #[test]
+    fn kept_artifact_is_recorded_next_to_its_checksum() {
+        let td = tempdir().unwrap();
+        let version_dir = td.path().join("1.2.3");
+        fs::create_dir_all(&version_dir).unwrap();
+        let archive = td.path().join("Windsurf-1.2.3.tar.gz");
+        fs::write(&archive, b"tarball").unwrap();
+        assert_eq!(artifact(&version_dir), None);
+
+        let kept = keep_artifact(&version_dir, &archive, "ab").unwrap();
+        assert_eq!(kept, version_dir.join(".artifact/Windsurf-1.2.3.tar.gz"));
+        assert_eq!(fs::read(&kept).unwrap(), b"tarball");
+        assert_eq!(
+            fs::read_to_string(crate::export::sidecar_path(&kept)).unwrap(),
+            "ab  Windsurf-1.2.3.tar.gz\n"
+        );
+        assert_eq!(artifact(&version_dir), Some(kept.clone()));
+
+        fs::remove_file(&kept).unwrap();
+        assert_eq!(artifact(&version_dir), None);
+    }
+
+    #[test]
+    fn sha256_file_matches_known_digest() {
+        let td = tempdir().unwrap();
+        let p = td.path().join("f");
+        fs::write(&p, b"abc").unwrap();
+        assert_eq!(
+            sha256_file(&p).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
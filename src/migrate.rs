@@ -0,0 +1,140 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+
+use crate::config::DesktopConfig;
+use crate::paths::EffectivePaths;
+use crate::util::ShimNotice;
+
+/// Move every installed version from `old`'s prefix to `new`'s, point `current`
+/// and the shims at the new place (the main one unless `manage_shim` is false)
+/// and (when `desktop` is given) rewrite the desktop entry. The config is left to the caller. Returns the version that is
+/// current afterwards, if any.
+pub fn migrate_prefix(
+    old: &EffectivePaths,
+    new: &EffectivePaths,
+    desktop: Option<&DesktopConfig>,
+    manage_shim: bool,
+    notice: Option<&ShimNotice>,
+) -> Result<Option<String>> {
+    let (from, to) = (&old.versions_dir, &new.versions_dir);
+    if from == to {
+        bail!("{} is already the install prefix", to.display());
+    }
+    if to.starts_with(from) || from.starts_with(to) {
+        bail!(
+            "cannot move {} to {}: one is inside the other",
+            from.display(),
+            to.display()
+        );
+    }
+    if to.exists() {
+        let empty = fs::read_dir(to)
+            .with_context(|| format!("reading {}", to.display()))?
+            .next()
+            .is_none();
+        if !empty {
+            bail!("{} already exists and is not empty", to.display());
+        }
+        fs::remove_dir(to).with_context(|| format!("removing {}", to.display()))?;
+    }
+
+    let current = crate::history::current_version_name(&old.current_symlink);
+    if from.exists() {
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("creating {}", parent.display()))?;
+        }
+        crate::util::move_dir_robust(from, to)?;
+    } else {
+        fs::create_dir_all(to).with_context(|| format!("creating {}", to.display()))?;
+    }
+
+    // `current` is an absolute link into the old prefix: re-create it
+    if let Some(v) = &current {
+        crate::util::atomic_symlink_switch(&to.join(v), &new.current_symlink)?;
+    }
+    if manage_shim && (current.is_some() || old.bin_shim.exists()) {
+        crate::util::write_shim(&new.bin_shim, &new.shim_link(), notice)?;
+    }
+    for w in crate::shims::refresh(new) {
+        crate::ui::warn(w);
+    }
+    if let Some(d) = desktop {
+        crate::desktop::ensure_desktop_files(new, d)?;
+    }
+    Ok(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    fn eff_in(prefix: &Path, root: &Path) -> EffectivePaths {
+        EffectivePaths {
+            prefix_dir: prefix.to_path_buf(),
+            versions_dir: prefix.to_path_buf(),
+            current_symlink: prefix.join("current"),
+            ..EffectivePaths::for_test(root)
+        }
+    }
+
+    #[test]
+    fn moves_versions_and_repoints_current_shim_and_desktop_entry() {
+        let td = tempdir().unwrap();
+        let old = eff_in(&td.path().join("opt/windsurf"), td.path());
+        let new = eff_in(&td.path().join("apps/windsurf"), td.path());
+        for v in ["1.0.0", "1.0.1"] {
+            fs::create_dir_all(old.versions_dir.join(v).join("Windsurf")).unwrap();
+        }
+        crate::util::atomic_symlink_switch(&old.versions_dir.join("1.0.1"), &old.current_symlink)
+            .unwrap();
+        crate::util::write_shim(&old.bin_shim, &old.current_symlink, None).unwrap();
+        crate::desktop::ensure_desktop_files(&old, &Default::default()).unwrap();
+
+        let current = migrate_prefix(&old, &new, Some(&Default::default()), true, None).unwrap();
+
+        assert_eq!(current.as_deref(), Some("1.0.1"));
+        assert!(!old.versions_dir.exists());
+        assert!(new.versions_dir.join("1.0.0").is_dir());
+        assert_eq!(
+            crate::util::resolve_symlink(&new.current_symlink),
+            fs::canonicalize(new.versions_dir.join("1.0.1")).ok()
+        );
+        assert!(crate::util::shim_points_to(
+            &new.bin_shim,
+            &new.current_symlink
+        ));
+        assert_eq!(
+            crate::util::stale_shim_target(&new.bin_shim, &new.current_symlink),
+            None
+        );
+        assert!(crate::desktop::desktop_entry_is_fresh(
+            &new,
+            &Default::default()
+        ));
+    }
+
+    #[test]
+    fn refuses_non_empty_or_nested_targets() {
+        let td = tempdir().unwrap();
+        let old = eff_in(&td.path().join("opt/windsurf"), td.path());
+        fs::create_dir_all(old.versions_dir.join("1.0.0")).unwrap();
+
+        let busy = eff_in(&td.path().join("busy"), td.path());
+        fs::create_dir_all(busy.versions_dir.join("stuff")).unwrap();
+        let err = migrate_prefix(&old, &busy, None, true, None)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("not empty"), "{}", err);
+
+        let nested = eff_in(&old.versions_dir.join("sub"), td.path());
+        let err = migrate_prefix(&old, &nested, None, true, None)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("inside"), "{}", err);
+
+        // nothing moved
+        assert!(old.versions_dir.join("1.0.0").is_dir());
+    }
+}
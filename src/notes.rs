@@ -0,0 +1,397 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+
+use crate::config::ChangelogRender;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Release notes kept at the root of each version dir.
+pub const NOTES_FILE: &str = ".release-notes.md";
+
+/// First line of a placeholder written when the notes could not be fetched.
+const UNAVAILABLE_MARKER: &str = "<!-- windman: release notes unavailable -->";
+
+/// One version's section of the releases page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseNotes {
+    pub version: String,
+    /// Plain text / Markdown rendering of the section
+    pub body: String,
+}
+
+/// What is stored for a version dir (`info` reports it).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotesStatus {
+    Stored,
+    /// Only a placeholder: the notes were unavailable at install time
+    Unavailable,
+    Missing,
+}
+
+/// Every `<h2>X.Y.Z</h2>` section of the releases page, newest first (page order).
+pub fn parse_releases(html: &str) -> Vec<ReleaseNotes> {
+    let re = Regex::new(r"(?is)<h2[^>]*>\s*([0-9]+\.[0-9]+\.[0-9]+)\s*</h2>").unwrap();
+    let heads: Vec<_> = re.captures_iter(html).collect();
+    heads
+        .iter()
+        .enumerate()
+        .map(|(i, caps)| {
+            let start = caps.get(0).unwrap().end();
+            let end = heads
+                .get(i + 1)
+                .map_or(html.len(), |next| next.get(0).unwrap().start());
+            ReleaseNotes {
+                version: caps[1].to_string(),
+                body: html_to_text(&html[start..end]),
+            }
+        })
+        .collect()
+}
+
+/// The section for `version`, if the page has one.
+pub fn section_for(html: &str, version: &str) -> Option<ReleaseNotes> {
+    parse_releases(html)
+        .into_iter()
+        .find(|n| n.version == version)
+}
+
+/// Sections newer than `local` (all of them when `local` is unknown).
+pub fn newer_than(html: &str, local: Option<&str>) -> Vec<ReleaseNotes> {
+    let local = local.and_then(|l| semver::Version::parse(l).ok());
+    parse_releases(html)
+        .into_iter()
+        .filter(|n| match (&local, semver::Version::parse(&n.version)) {
+            (Some(l), Ok(v)) => &v > l,
+            _ => true,
+        })
+        .collect()
+}
+
+/// Good-enough HTML to Markdown: list items become bullets, block ends become
+/// newlines, every other tag is dropped and common entities are decoded.
+fn html_to_text(html: &str) -> String {
+    let html = Regex::new(r"(?is)<(script|style)[^>]*>.*?</(script|style)>")
+        .unwrap()
+        .replace_all(html, "");
+    let html = Regex::new(r"(?i)<li[^>]*>")
+        .unwrap()
+        .replace_all(&html, "\n- ");
+    let html = Regex::new(r"(?i)<h[3-6][^>]*>")
+        .unwrap()
+        .replace_all(&html, "\n\n### ");
+    let html = Regex::new(r"(?i)<br\s*/?>|</(p|div|ul|ol|h[1-6])>")
+        .unwrap()
+        .replace_all(&html, "\n");
+    let html = Regex::new(r"(?s)<[^>]*>").unwrap().replace_all(&html, "");
+    let text = html
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+
+    // trim every line and collapse runs of blank lines
+    let mut out = String::new();
+    let mut blank = true;
+    for line in text
+        .lines()
+        .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+    {
+        if line.is_empty() {
+            if !blank {
+                out.push('\n');
+            }
+            blank = true;
+        } else {
+            out.push_str(&line);
+            out.push('\n');
+            blank = false;
+        }
+    }
+    out.trim_end().to_string()
+}
+
+/// The sections `changelog` prints: newer than `local`, at most `max` of them
+/// (newest first). Errors when the page has no release sections at all, which
+/// means `url` is not a releases page rather than that nothing is new.
+pub fn changelog(
+    html: &str,
+    url: &str,
+    local: Option<&str>,
+    max: Option<usize>,
+) -> Result<Vec<ReleaseNotes>> {
+    if parse_releases(html).is_empty() {
+        anyhow::bail!(
+            "no release notes found at {} (changelog.source_url must be a releases page with one <h2>X.Y.Z</h2> heading per version)",
+            url
+        );
+    }
+    let mut newer = newer_than(html, local);
+    newer.truncate(max.unwrap_or(usize::MAX));
+    Ok(newer)
+}
+
+/// One `changelog` section as printed: `# Windsurf X.Y.Z` and its notes, the
+/// Markdown styled for the terminal or stripped to plain text.
+pub fn render_section(notes: &ReleaseNotes, render: ChangelogRender) -> String {
+    let md = format!("# Windsurf {}\n\n{}\n", notes.version, notes.body);
+    let mut out = String::new();
+    for line in md.lines() {
+        let heading = line.trim_start_matches('#');
+        let bullet = line.strip_prefix("- ").or_else(|| line.strip_prefix("* "));
+        let rendered = match (render, bullet) {
+            _ if heading.len() < line.len() && heading.starts_with(' ') => {
+                let text = inline(heading.trim(), ChangelogRender::Plain);
+                match render {
+                    ChangelogRender::Markdown => crate::ui::paint(crate::ui::HEADING, text),
+                    ChangelogRender::Plain => text,
+                }
+            }
+            (ChangelogRender::Markdown, Some(item)) => format!("  • {}", inline(item, render)),
+            (ChangelogRender::Plain, Some(item)) => format!("- {}", inline(item, render)),
+            (_, None) => inline(line, render),
+        };
+        out.push_str(&rendered);
+        out.push('\n');
+    }
+    out
+}
+
+/// Inline Markdown of one line: links become `text (url)`; `**bold**` is
+/// styled (Markdown) or unwrapped (plain), as are `` `code` `` spans.
+fn inline(line: &str, render: ChangelogRender) -> String {
+    let line = Regex::new(r"\[([^\]]+)\]\(([^)\s]+)\)")
+        .unwrap()
+        .replace_all(line, "$1 ($2)");
+    let bold = Regex::new(r"\*\*([^*]+)\*\*|__([^_]+)__").unwrap();
+    let code = Regex::new(r"`([^`]+)`").unwrap();
+    match render {
+        ChangelogRender::Markdown => {
+            let line = bold.replace_all(&line, |c: &regex::Captures| {
+                let text = c.get(1).or(c.get(2)).map_or("", |m| m.as_str());
+                crate::ui::paint(crate::ui::NOTICE, text)
+            });
+            code.replace_all(&line, |c: &regex::Captures| {
+                crate::ui::paint(crate::ui::CODE, &c[1])
+            })
+            .into_owned()
+        }
+        ChangelogRender::Plain => {
+            let line = bold.replace_all(&line, "$1$2");
+            code.replace_all(&line, "$1").into_owned()
+        }
+    }
+}
+
+pub fn notes_path(version_dir: &Path) -> PathBuf {
+    version_dir.join(NOTES_FILE)
+}
+
+/// Markdown stored for a version: its section, or a placeholder saying why not.
+pub fn render(version: &str, notes: std::result::Result<&ReleaseNotes, &str>) -> String {
+    match notes {
+        Ok(n) => format!("# Windsurf {}\n\n{}\n", version, n.body),
+        Err(reason) => format!(
+            "{}\n# Windsurf {}\n\nRelease notes were unavailable when this version was installed ({}).\n",
+            UNAVAILABLE_MARKER, version, reason
+        ),
+    }
+}
+
+pub fn write(version_dir: &Path, content: &str) -> Result<()> {
+    let p = notes_path(version_dir);
+    fs::write(&p, content).with_context(|| format!("writing {}", p.display()))
+}
+
+pub fn read(version_dir: &Path) -> Option<String> {
+    fs::read_to_string(notes_path(version_dir)).ok()
+}
+
+pub fn status(version_dir: &Path) -> NotesStatus {
+    match read(version_dir) {
+        Some(s) if s.starts_with(UNAVAILABLE_MARKER) => NotesStatus::Unavailable,
+        Some(_) => NotesStatus::Stored,
+        None => NotesStatus::Missing,
+    }
+}
+
+/// Store the notes of `version` from an already fetched releases page (or the
+/// fetch error). Never fails: a placeholder is written when the section is
+/// missing, and a write error is only reported.
+pub fn store(version_dir: &Path, version: &str, page: &Result<String>) -> NotesStatus {
+    let section = page
+        .as_ref()
+        .ok()
+        .and_then(|html| section_for(html, version));
+    let content = match (&section, page) {
+        (Some(n), _) => render(version, Ok(n)),
+        (None, Ok(_)) => render(version, Err("not listed on the releases page")),
+        (None, Err(e)) => render(version, Err(&format!("{:#}", e))),
+    };
+    match write(version_dir, &content) {
+        Ok(()) => status(version_dir),
+        Err(e) => {
+            crate::ui::warn(format_args!("{:#}", e));
+            NotesStatus::Missing
+        }
+    }
+}
+
+/// Notes for `version`: the stored file, else fetched through `fetch_page` (and
+/// then stored). A stored placeholder is retried and shown only when offline.
+pub fn load_or_fetch(
+    version_dir: &Path,
+    version: &str,
+    fetch_page: impl FnOnce() -> Result<String>,
+) -> Result<String> {
+    let stored = read(version_dir);
+    if let Some(s) = stored
+        .as_ref()
+        .filter(|s| !s.starts_with(UNAVAILABLE_MARKER))
+    {
+        return Ok(s.clone());
+    }
+    match fetch_page() {
+        Ok(html) => {
+            let n = section_for(&html, version).with_context(|| {
+                format!("no release notes for {} on the releases page", version)
+            })?;
+            let content = render(version, Ok(&n));
+            // best-effort: a read-only version dir still gets its notes printed
+            let _ = write(version_dir, &content);
+            Ok(content)
+        }
+        Err(e) => match stored {
+            Some(placeholder) => Ok(placeholder),
+            None => Err(e.context(format!(
+                "release notes for {} are not stored and could not be fetched",
+                version
+            ))),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use tempfile::tempdir;
+
+    const PAGE: &str = r#"<html><body>
+        <h2 class="v">1.12.11</h2>
+        <p>Faster <b>indexing</b> &amp; search.</p>
+        <ul><li>Fix crash on start</li><li>New &quot;Cascade&quot; panel</li></ul>
+        <h2>1.12.10</h2>
+        <h3>Fixes</h3><ul><li>Proxy auth</li></ul>
+        <h2>1.12.9</h2><p>Initial</p>
+        </body></html>"#;
+
+    #[test]
+    fn parses_sections_into_markdown() {
+        let all = parse_releases(PAGE);
+        let versions: Vec<_> = all.iter().map(|n| n.version.as_str()).collect();
+        assert_eq!(versions, ["1.12.11", "1.12.10", "1.12.9"]);
+        assert_eq!(
+            all[0].body,
+            "Faster indexing & search.\n\n- Fix crash on start\n- New \"Cascade\" panel"
+        );
+        assert_eq!(all[1].body, "### Fixes\n\n- Proxy auth");
+        assert_eq!(section_for(PAGE, "1.12.9").unwrap().body, "Initial");
+        assert!(section_for(PAGE, "2.0.0").is_none());
+    }
+
+    #[test]
+    fn newer_than_filters_by_semver() {
+        let newer: Vec<_> = newer_than(PAGE, Some("1.12.10"))
+            .into_iter()
+            .map(|n| n.version)
+            .collect();
+        assert_eq!(newer, ["1.12.11"]);
+        assert_eq!(newer_than(PAGE, None).len(), 3);
+    }
+
+    #[test]
+    fn changelog_limits_entries_and_rejects_pages_without_releases() {
+        let shown: Vec<_> = changelog(PAGE, "u", None, Some(2))
+            .unwrap()
+            .into_iter()
+            .map(|n| n.version)
+            .collect();
+        assert_eq!(shown, ["1.12.11", "1.12.10"]);
+        assert!(changelog(PAGE, "u", Some("1.12.11"), None)
+            .unwrap()
+            .is_empty());
+        let err = changelog("<html>Not found</html>", "https://x.invalid/r", None, None);
+        assert!(format!("{:#}", err.unwrap_err())
+            .starts_with("no release notes found at https://x.invalid/r"),);
+    }
+
+    #[test]
+    fn sections_render_plain_or_styled() {
+        let notes = ReleaseNotes {
+            version: "1.12.11".into(),
+            body: "### Fixes\n\n- **Proxy** auth, see [docs](https://x.invalid/d)\nUse `--flag`"
+                .into(),
+        };
+        assert_eq!(
+            render_section(&notes, ChangelogRender::Plain),
+            "Windsurf 1.12.11\n\nFixes\n\n- Proxy auth, see docs (https://x.invalid/d)\nUse --flag\n"
+        );
+        assert_eq!(
+            render_section(&notes, ChangelogRender::Markdown),
+            "\x1b[1mWindsurf 1.12.11\x1b[0m\n\n\x1b[1mFixes\x1b[0m\n\n  • \x1b[1mProxy\x1b[0m auth, see docs (https://x.invalid/d)\nUse \x1b[36m--flag\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn store_writes_notes_or_a_placeholder() {
+        let td = tempdir().unwrap();
+        assert_eq!(status(td.path()), NotesStatus::Missing);
+
+        assert_eq!(
+            store(td.path(), "1.12.11", &Ok(PAGE.to_string())),
+            NotesStatus::Stored
+        );
+        let stored = read(td.path()).unwrap();
+        assert!(stored.starts_with("# Windsurf 1.12.11\n\nFaster indexing"));
+
+        assert_eq!(
+            store(td.path(), "1.12.11", &Err(anyhow!("offline"))),
+            NotesStatus::Unavailable
+        );
+        assert!(read(td.path()).unwrap().contains("(offline)"));
+
+        assert_eq!(
+            store(td.path(), "9.9.9", &Ok(PAGE.to_string())),
+            NotesStatus::Unavailable
+        );
+
+        // a vanished version dir is reported, not an error
+        assert_eq!(
+            store(&td.path().join("gone"), "1.12.11", &Ok(PAGE.to_string())),
+            NotesStatus::Missing
+        );
+    }
+
+    #[test]
+    fn load_or_fetch_prefers_stored_and_retries_placeholders() {
+        let td = tempdir().unwrap();
+        let err = load_or_fetch(td.path(), "1.12.11", || Err(anyhow!("offline"))).unwrap_err();
+        assert!(format!("{:#}", err).contains("not stored"));
+
+        store(td.path(), "1.12.11", &Err(anyhow!("offline")));
+        // still offline: the placeholder is shown
+        let got = load_or_fetch(td.path(), "1.12.11", || Err(anyhow!("offline"))).unwrap();
+        assert!(got.contains("unavailable"));
+
+        // back online: fetched, stored, then served without the network
+        let got = load_or_fetch(td.path(), "1.12.11", || Ok(PAGE.to_string())).unwrap();
+        assert!(got.contains("Fix crash on start"));
+        assert_eq!(status(td.path()), NotesStatus::Stored);
+        let again = load_or_fetch(td.path(), "1.12.11", || panic!("fetched")).unwrap();
+        assert_eq!(again, got);
+    }
+}
@@ -0,0 +1,130 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// How insistent a notification is (`notify-send --urgency`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Urgency {
+    Normal,
+    Critical,
+}
+
+impl Urgency {
+    fn as_str(self) -> &'static str {
+        match self {
+            Urgency::Normal => "normal",
+            Urgency::Critical => "critical",
+        }
+    }
+}
+
+/// Where `update --notify` reports to.
+pub trait Notifier {
+    fn send(&mut self, summary: &str, body: Option<&str>, urgency: Urgency);
+}
+
+/// The desktop's notification daemon, through `notify-send`. Best effort: without
+/// a D-Bus session (SSH, headless) or without `notify-send`, nothing happens.
+pub struct Desktop;
+
+impl Notifier for Desktop {
+    fn send(&mut self, summary: &str, body: Option<&str>, urgency: Urgency) {
+        if !has_session_bus(|k| std::env::var(k).ok()) {
+            return;
+        }
+        let mut cmd = Command::new("notify-send");
+        cmd.args(["--app-name=windman", "--urgency", urgency.as_str(), summary]);
+        cmd.args(body);
+        let _ = cmd.stdout(Stdio::null()).stderr(Stdio::null()).spawn();
+    }
+}
+
+/// A D-Bus session bus to reach the notification daemon on:
+/// `DBUS_SESSION_BUS_ADDRESS`, or the systemd user bus at `$XDG_RUNTIME_DIR/bus`.
+pub fn has_session_bus(env: impl Fn(&str) -> Option<String>) -> bool {
+    env("DBUS_SESSION_BUS_ADDRESS").is_some_and(|a| !a.is_empty())
+        || env("XDG_RUNTIME_DIR").is_some_and(|d| Path::new(&d).join("bus").exists())
+}
+
+/// Report how updating `what` ("Windsurf", "Windsurf (work)") went: the new
+/// version, nothing when it was up to date, the first line of the error (critical).
+pub fn update_outcome(n: &mut dyn Notifier, what: &str, outcome: &anyhow::Result<Option<String>>) {
+    match outcome {
+        Ok(Some(ver)) => n.send(
+            &format!("{} updated to {}", what, ver),
+            None,
+            Urgency::Normal,
+        ),
+        Ok(None) => {}
+        Err(e) => {
+            let msg = format!("{:#}", e);
+            n.send(
+                &format!("{} update failed", what),
+                msg.lines().next(),
+                Urgency::Critical,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder(Vec<(String, Option<String>, Urgency)>);
+
+    impl Notifier for Recorder {
+        fn send(&mut self, summary: &str, body: Option<&str>, urgency: Urgency) {
+            self.0
+                .push((summary.to_string(), body.map(str::to_string), urgency));
+        }
+    }
+
+    #[test]
+    fn updates_and_failures_are_reported_and_no_ops_are_not() {
+        let mut r = Recorder::default();
+        update_outcome(&mut r, "Windsurf", &Ok(Some("1.13.0".into())));
+        update_outcome(&mut r, "Windsurf (work)", &Ok(None));
+        let err = anyhow::anyhow!("HTTP 503\nretry later").context("querying the update API");
+        update_outcome(&mut r, "Windsurf (work)", &Err(err));
+        assert_eq!(
+            r.0,
+            [
+                (
+                    "Windsurf updated to 1.13.0".to_string(),
+                    None,
+                    Urgency::Normal
+                ),
+                (
+                    "Windsurf (work) update failed".to_string(),
+                    Some("querying the update API: HTTP 503".to_string()),
+                    Urgency::Critical
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn no_session_bus_without_address_or_runtime_socket() {
+        let td = tempfile::tempdir().unwrap();
+        let runtime = td.path().to_string_lossy().to_string();
+        let env = |vars: Vec<(&'static str, String)>| {
+            move |k: &str| vars.iter().find(|(n, _)| *n == k).map(|(_, v)| v.clone())
+        };
+        assert!(!has_session_bus(env(vec![])));
+        assert!(!has_session_bus(env(vec![(
+            "DBUS_SESSION_BUS_ADDRESS",
+            String::new()
+        )])));
+        assert!(!has_session_bus(env(vec![(
+            "XDG_RUNTIME_DIR",
+            runtime.clone()
+        )])));
+        assert!(has_session_bus(env(vec![(
+            "DBUS_SESSION_BUS_ADDRESS",
+            "unix:path=/run/user/1000/bus".into()
+        )])));
+        std::fs::write(td.path().join("bus"), "").unwrap();
+        assert!(has_session_bus(env(vec![("XDG_RUNTIME_DIR", runtime)])));
+    }
+}
@@ -1,9 +1,14 @@
-use anyhow::Result;
-use directories::BaseDirs;
-use shellexpand::tilde;
-use std::path::{PathBuf};
+use anyhow::{anyhow, bail, Context, Result};
+use std::path::{Component, Path, PathBuf};
 
-use crate::config::Config;
+use crate::config::{Config, Scope};
+
+/// System-scope locations (used instead of XDG/home dirs).
+const SYSTEM_OPT_DIR: &str = "/opt";
+const SYSTEM_BIN_DIR: &str = "/usr/local/bin";
+const SYSTEM_DATA_DIR: &str = "/usr/share";
+const SYSTEM_CACHE_DIR: &str = "/var/cache/windman";
+const SYSTEM_STATE_DIR: &str = "/var/lib/windman";
 
 /// All resolved (expanded) paths Windman uses at runtime.
 #[derive(Debug, Clone)]
@@ -22,34 +27,260 @@ pub struct EffectivePaths {
     pub desktop_file: PathBuf,
     /// Icons base dir (e.g. ~/.local/share/icons)
     pub icons_dir: PathBuf,
+    /// Download cache (e.g. ~/.cache/windman, /var/cache/windman in system scope)
+    pub cache_dir: PathBuf,
+    /// Logs worth keeping (e.g. ~/.local/state/windman, /var/lib/windman in system scope)
+    pub state_dir: PathBuf,
+    /// Selected profile name (None = default `[install]` section)
+    pub profile: Option<String>,
+    /// Machine the versions dir and `current` are namespaced for
+    /// (`install.arch_suffix`), e.g. "x86_64"
+    pub arch: Option<String>,
+}
+
+/// What the shim puts in place of the machine name: its own, at run time.
+pub const SHIM_MACHINE: &str = "$(uname -m)";
+
+impl EffectivePaths {
+    /// The same paths namespaced for `arch`: `<prefix>/<arch>/<version>` and
+    /// `<prefix>/current-<arch>`.
+    pub fn for_arch(&self, arch: &str) -> Self {
+        Self {
+            versions_dir: self.prefix_dir.join(arch),
+            current_symlink: self.prefix_dir.join(format!("current-{}", arch)),
+            arch: Some(arch.to_string()),
+            ..self.clone()
+        }
+    }
+
+    /// The `current` the shim follows: with `install.arch_suffix`, the one of
+    /// the machine it runs on.
+    pub fn shim_link(&self) -> PathBuf {
+        match self.arch {
+            Some(_) => self.prefix_dir.join(format!("current-{}", SHIM_MACHINE)),
+            None => self.current_symlink.clone(),
+        }
+    }
+
+    /// A user-scope layout under `root` for unit tests: versions in
+    /// `root/versions`, `current` and the shim's `bin` in `root`, desktop
+    /// files under `root/share`.
+    #[cfg(test)]
+    pub fn for_test(root: &Path) -> Self {
+        Self {
+            prefix_dir: root.to_path_buf(),
+            versions_dir: root.join("versions"),
+            current_symlink: root.join("current"),
+            bin_dir: root.join("bin"),
+            bin_shim: root.join("bin/windsurf"),
+            desktop_file: root.join("share/applications/windsurf.desktop"),
+            icons_dir: root.join("share/icons"),
+            cache_dir: root.join("cache"),
+            state_dir: root.join("state"),
+            profile: None,
+            arch: None,
+        }
+    }
+
+    /// Machines with a `current-<arch>` link under the prefix, sorted.
+    pub fn installed_arches(&self) -> Vec<String> {
+        let Ok(rd) = std::fs::read_dir(&self.prefix_dir) else {
+            return Vec::new();
+        };
+        let mut out: Vec<String> = rd
+            .flatten()
+            .filter_map(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                name.strip_prefix("current-").map(str::to_string)
+            })
+            .filter(|a| self.prefix_dir.join(a).is_dir())
+            .collect();
+        out.sort();
+        out
+    }
+}
+
+/// `uname -m` of this machine (what the shim sees), else the build target's.
+pub fn machine() -> String {
+    std::process::Command::new("uname")
+        .arg("-m")
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .filter(|m| !m.is_empty())
+        .unwrap_or_else(|| std::env::consts::ARCH.to_string())
+}
+
+/// `$HOME`, when set. Unlike `directories`, no fallback on the passwd entry:
+/// in a container without HOME, that is seldom where windman's files belong.
+pub fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .filter(|h| !h.is_empty())
+        .map(PathBuf::from)
+}
+
+/// `home_dir` as shellexpand takes it.
+fn home_str() -> Option<String> {
+    home_dir().map(|h| h.to_string_lossy().into_owned())
+}
+
+/// An XDG base directory: `$var` when it is an absolute path, else
+/// `$HOME/<default>`; None without either.
+fn xdg_dir(var: &str, default: &str) -> Option<PathBuf> {
+    std::env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| home_dir().map(|h| h.join(default)))
+}
+
+/// The config, cache and state dirs of windman (`~/.config/windman`, ...).
+pub fn project_dir(kind: XdgDir) -> Option<PathBuf> {
+    let (var, default) = match kind {
+        XdgDir::Config => ("XDG_CONFIG_HOME", ".config"),
+        XdgDir::Cache => ("XDG_CACHE_HOME", ".cache"),
+        XdgDir::State => ("XDG_STATE_HOME", ".local/state"),
+    };
+    xdg_dir(var, default).map(|d| d.join("windman"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XdgDir {
+    Config,
+    Cache,
+    State,
+}
+
+/// Err for a location that defaults to somewhere under $HOME while HOME is
+/// not set (a minimal container): what to pass instead.
+pub fn no_home(what: &str, instead: &str) -> anyhow::Error {
+    anyhow!(
+        "{} defaults to a place under $HOME, but HOME is not set.\nSet HOME, or {}.",
+        what,
+        instead
+    )
+}
+
+/// Whether `p` starts with a `~` that HOME is needed for, and HOME is not set.
+pub fn lacks_home(p: &str) -> bool {
+    (p == "~" || p.starts_with("~/")) && home_dir().is_none()
+}
+
+/// Err for a desktop entry with no place to go (no HOME, no XDG_DATA_HOME).
+pub fn no_desktop_dir() -> anyhow::Error {
+    no_home(
+        "The desktop entry",
+        "set XDG_DATA_HOME, or do without it (--no-desktop, or install.desktop_integration = false)",
+    )
+}
+
+/// A leading `~` of `p` is HOME; an error when HOME is not set rather than a
+/// dir literally named `~`.
+fn tilde_needs_home(p: &str) -> Result<()> {
+    if lacks_home(p) {
+        bail!(
+            "'{}': HOME is not set, so ~ cannot be expanded (give an absolute path)",
+            p
+        );
+    }
+    Ok(())
+}
+
+/// Expand a leading ~ only (a path given as is, e.g. `--config`).
+pub fn expand_tilde(p: &str) -> Result<String> {
+    tilde_needs_home(p)?;
+    Ok(shellexpand::tilde_with_context(p, home_str).into_owned())
+}
+
+/// Expand ~, $VAR and ${VAR}; an undefined variable is an error, not a literal `$VAR` dir.
+pub fn expand_vars(p: &str) -> Result<String> {
+    tilde_needs_home(p)?;
+    shellexpand::full_with_context(p, home_str, |v| std::env::var(v).map(Some))
+        .map(|s| s.into_owned())
+        .map_err(|e| anyhow!("'{}': environment variable ${} is not set", p, e.var_name))
+}
+
+/// Expand a configured path (see `expand_vars`)
+fn expand(p: &str) -> Result<PathBuf> {
+    Ok(PathBuf::from(expand_vars(p)?))
+}
+
+/// `--prefix`, `--bin-dir` and other directory arguments: expand variables, then
+/// make a relative path absolute against the current directory, here at parse
+/// time rather than wherever it ends up being used.
+pub fn parse_dir_arg(p: &str) -> Result<String> {
+    let path = PathBuf::from(expand_vars(p)?);
+    let abs = if path.is_absolute() {
+        path
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+    Ok(normalize(&abs).to_string_lossy().into_owned())
+}
+
+/// Drop `.` and resolve `..` lexically (the dir may not exist yet).
+fn normalize(p: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for c in p.components() {
+        match c {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            c => out.push(c),
+        }
+    }
+    out
 }
 
-/// Expand a path that may contain ~
-fn expand(p: &str) -> PathBuf {
-    PathBuf::from(tilde(p).into_owned())
+/// Map the userland defaults (~/.local/opt/..., ~/.local/bin) to their system equivalents.
+fn to_system_path(p: &str) -> String {
+    if let Some(rest) = p.strip_prefix("~/.local/opt/") {
+        format!("{}/{}", SYSTEM_OPT_DIR, rest)
+    } else if p == "~/.local/bin" {
+        SYSTEM_BIN_DIR.to_string()
+    } else {
+        p.to_string()
+    }
 }
 
-/// Compute effective paths from config (expands ~, fills XDG locations).
-pub fn resolve_paths(cfg: &Config) -> Result<EffectivePaths> {
-    let prefix_dir = expand(&cfg.install.prefix_dir);
+/// Compute effective paths from config (expands ~ and variables, fills XDG locations).
+/// `profile` is informational here: the profile must already be merged via `Config::apply_profile`.
+pub fn resolve_paths(cfg: &Config, profile: Option<&str>) -> Result<EffectivePaths> {
+    let eff = match cfg.install.scope {
+        Scope::System => resolve_system_paths(cfg, profile)?,
+        Scope::User => resolve_user_paths(cfg, profile)?,
+    };
+    Ok(match cfg.install.arch_suffix {
+        true => eff.for_arch(&machine()),
+        false => eff,
+    })
+}
+
+/// Without HOME, every location must come from a flag, the config or the
+/// environment: the state then goes next to the cache, and without
+/// XDG_DATA_HOME the desktop paths are empty (writing the entry is an error).
+fn resolve_user_paths(cfg: &Config, profile: Option<&str>) -> Result<EffectivePaths> {
+    let prefix_dir = expand(&cfg.install.prefix_dir).context("install.prefix_dir (or --prefix)")?;
     let versions_dir = prefix_dir.clone();
     let current_symlink = prefix_dir.join("current");
 
-    let bin_dir = expand(&cfg.install.bin_dir);
-    let bin_shim = bin_dir.join("windsurf");
+    let bin_dir = expand(&cfg.install.bin_dir).context("install.bin_dir (or --bin-dir)")?;
+    let bin_shim = bin_dir.join(&cfg.install.bin_name);
 
     // XDG data (for desktop file + icons)
-    let base = BaseDirs::new();
-    // Fallback to ~/.local/share if XDG can't be resolved (very rare)
-    let data_dir = base
-        .map(|b| b.data_local_dir().to_path_buf())
-        .unwrap_or_else(|| {
-            let home = std::env::var_os("HOME").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("~"));
-            home.join(".local/share")
-        });
-
-    let desktop_file = data_dir.join("applications/windsurf.desktop");
-    let icons_dir = data_dir.join("icons");
+    let (desktop_file, icons_dir) = match user_data_dir() {
+        Some(data_dir) => (
+            data_dir
+                .join("applications")
+                .join(format!("{}.desktop", cfg.install.bin_name)),
+            data_dir.join("icons"),
+        ),
+        None => (PathBuf::new(), PathBuf::new()),
+    };
+
+    let cache_dir = cache_dir(cfg)?;
+    let state_dir = project_dir(XdgDir::State).unwrap_or_else(|| cache_dir.join("state"));
 
     Ok(EffectivePaths {
         prefix_dir,
@@ -59,5 +290,257 @@ pub fn resolve_paths(cfg: &Config) -> Result<EffectivePaths> {
         bin_shim,
         desktop_file,
         icons_dir,
+        cache_dir,
+        state_dir,
+        profile: profile.map(str::to_string),
+        arch: None,
+    })
+}
+
+/// XDG data dir (~/.local/share).
+fn user_data_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_DATA_HOME", ".local/share")
+}
+
+/// The download and check cache: `WINDMAN_CACHE_DIR` (a shared disk, tests),
+/// else `paths.cache_dir`, else ~/.cache/windman (/var/cache/windman in system scope).
+pub fn cache_dir(cfg: &Config) -> Result<PathBuf> {
+    cache_dir_from(cfg, std::env::var_os("WINDMAN_CACHE_DIR"))
+}
+
+fn cache_dir_from(cfg: &Config, env: Option<std::ffi::OsString>) -> Result<PathBuf> {
+    if let Some(dir) = env.filter(|v| !v.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+    if let Some(dir) = &cfg.paths.cache_dir {
+        return expand(dir).context("paths.cache_dir");
+    }
+    match cfg.install.scope {
+        Scope::System => Ok(PathBuf::from(SYSTEM_CACHE_DIR)),
+        Scope::User => project_dir(XdgDir::Cache).ok_or_else(|| {
+            no_home(
+                "The download cache",
+                "set WINDMAN_CACHE_DIR (or paths.cache_dir)",
+            )
+        }),
+    }
+}
+
+/// System scope: /opt + /usr/local/bin + /usr/share, and nothing under $HOME.
+fn resolve_system_paths(cfg: &Config, profile: Option<&str>) -> Result<EffectivePaths> {
+    let prefix_dir = expand(&to_system_path(&cfg.install.prefix_dir))?;
+    let bin_dir = expand(&to_system_path(&cfg.install.bin_dir))?;
+    let data_dir = PathBuf::from(SYSTEM_DATA_DIR);
+
+    if let Some(home) = home_dir() {
+        for p in [&prefix_dir, &bin_dir] {
+            if p.starts_with(&home) {
+                bail!(
+                    "{} is inside your home directory, which is not allowed with system scope.\n\
+                     Use a system path (e.g. /opt/windsurf) or switch back to scope = \"user\".",
+                    p.display()
+                );
+            }
+        }
+    }
+
+    Ok(EffectivePaths {
+        versions_dir: prefix_dir.clone(),
+        current_symlink: prefix_dir.join("current"),
+        bin_shim: bin_dir.join(&cfg.install.bin_name),
+        desktop_file: data_dir
+            .join("applications")
+            .join(format!("{}.desktop", cfg.install.bin_name)),
+        icons_dir: data_dir.join("icons"),
+        cache_dir: cache_dir(cfg)?,
+        state_dir: PathBuf::from(SYSTEM_STATE_DIR),
+        prefix_dir,
+        bin_dir,
+        profile: profile.map(str::to_string),
+        arch: None,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_changes_shim_and_desktop_names() {
+        let mut cfg = Config::default();
+        cfg.profiles.insert(
+            "next".to_string(),
+            crate::config::ProfileConfig {
+                channel: Some("next".to_string()),
+                ..Default::default()
+            },
+        );
+        cfg.apply_profile("next").unwrap();
+        let eff = resolve_paths(&cfg, Some("next")).unwrap();
+
+        assert_eq!(cfg.install.channel, "next");
+        assert_eq!(eff.bin_shim.file_name().unwrap(), "windsurf-next");
+        assert_eq!(
+            eff.desktop_file.file_name().unwrap(),
+            "windsurf-next.desktop"
+        );
+        assert!(eff.prefix_dir.to_string_lossy().ends_with("windsurf-next"));
+        assert_eq!(eff.profile.as_deref(), Some("next"));
+    }
+
+    #[test]
+    fn system_scope_uses_system_locations() {
+        let mut cfg = Config::default();
+        cfg.install.scope = Scope::System;
+        let eff = resolve_paths(&cfg, None).unwrap();
+
+        assert_eq!(eff.prefix_dir, PathBuf::from("/opt/windsurf"));
+        assert_eq!(eff.bin_shim, PathBuf::from("/usr/local/bin/windsurf"));
+        assert_eq!(
+            eff.desktop_file,
+            PathBuf::from("/usr/share/applications/windsurf.desktop")
+        );
+        assert_eq!(eff.icons_dir, PathBuf::from("/usr/share/icons"));
+        assert_eq!(eff.cache_dir, PathBuf::from("/var/cache/windman"));
+    }
+
+    #[test]
+    fn cache_dir_env_then_config_then_default() {
+        let mut cfg = Config::default();
+        cfg.install.scope = Scope::System;
+        assert_eq!(
+            cache_dir_from(&cfg, None).unwrap(),
+            PathBuf::from("/var/cache/windman")
+        );
+
+        std::env::set_var("WINDMAN_TEST_SCRATCH", "/scratch/me");
+        cfg.paths.cache_dir = Some("$WINDMAN_TEST_SCRATCH/windman".to_string());
+        assert_eq!(
+            cache_dir_from(&cfg, None).unwrap(),
+            PathBuf::from("/scratch/me/windman")
+        );
+        assert_eq!(
+            cache_dir_from(&cfg, Some("/tmp/ci-cache".into())).unwrap(),
+            PathBuf::from("/tmp/ci-cache")
+        );
+        // set but empty: as if unset
+        assert_eq!(
+            cache_dir_from(&cfg, Some("".into())).unwrap(),
+            PathBuf::from("/scratch/me/windman")
+        );
+    }
+
+    #[test]
+    fn system_scope_rejects_paths_in_home() {
+        let mut cfg = Config::default();
+        cfg.install.scope = Scope::System;
+        cfg.install.bin_dir = "~/bin".to_string();
+        let err = resolve_paths(&cfg, None).unwrap_err().to_string();
+        assert!(err.contains("inside your home directory"), "{}", err);
+    }
+
+    #[test]
+    fn config_paths_expand_variables() {
+        std::env::set_var("WINDMAN_TEST_OPT", "/srv/opt");
+        let mut cfg = Config::default();
+        cfg.install.prefix_dir = "$WINDMAN_TEST_OPT/windsurf".to_string();
+        cfg.install.bin_dir = "${WINDMAN_TEST_OPT}/bin".to_string();
+        let eff = resolve_paths(&cfg, None).unwrap();
+        assert_eq!(eff.prefix_dir, PathBuf::from("/srv/opt/windsurf"));
+        assert_eq!(eff.bin_dir, PathBuf::from("/srv/opt/bin"));
+
+        cfg.install.prefix_dir = "$WINDMAN_TEST_UNSET_1087/windsurf".to_string();
+        let err = format!("{:#}", resolve_paths(&cfg, None).unwrap_err());
+        assert_eq!(
+            err,
+            "install.prefix_dir (or --prefix): '$WINDMAN_TEST_UNSET_1087/windsurf': environment variable $WINDMAN_TEST_UNSET_1087 is not set"
+        );
+    }
+
+    #[test]
+    fn dir_args_are_expanded_and_made_absolute() {
+        std::env::set_var("WINDMAN_TEST_ARG", "/data");
+        let home = std::env::var("HOME").unwrap();
+        let cwd = std::env::current_dir().unwrap();
+
+        assert_eq!(parse_dir_arg("$WINDMAN_TEST_ARG/ws").unwrap(), "/data/ws");
+        assert_eq!(
+            parse_dir_arg("${WINDMAN_TEST_ARG}/ws/").unwrap(),
+            "/data/ws"
+        );
+        assert_eq!(
+            parse_dir_arg("~/opt/ws").unwrap(),
+            format!("{}/opt/ws", home)
+        );
+        assert_eq!(
+            parse_dir_arg("./ws-test").unwrap(),
+            cwd.join("ws-test").to_string_lossy()
+        );
+        assert_eq!(
+            parse_dir_arg("a/../b/./c").unwrap(),
+            cwd.join("b/c").to_string_lossy()
+        );
+        let err = parse_dir_arg("$WINDMAN_TEST_UNSET_1087/ws")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("$WINDMAN_TEST_UNSET_1087 is not set"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn unknown_profile_lists_defined_ones() {
+        let mut cfg = Config::default();
+        cfg.profiles.insert("next".to_string(), Default::default());
+        let err = cfg.apply_profile("beta").unwrap_err().to_string();
+        assert!(err.contains("unknown profile 'beta'"));
+        assert!(err.contains("next"));
+    }
+
+    #[test]
+    fn arch_suffix_namespaces_versions_and_current() {
+        let eff = resolve_paths(&Config::default(), None).unwrap();
+        assert_eq!(eff.shim_link(), eff.current_symlink);
+
+        for arch in ["x86_64", "aarch64"] {
+            let a = eff.for_arch(arch);
+            assert_eq!(a.versions_dir, eff.prefix_dir.join(arch));
+            assert_eq!(
+                a.current_symlink,
+                eff.prefix_dir.join(format!("current-{}", arch))
+            );
+            assert_eq!(a.bin_shim, eff.bin_shim);
+            // one shim for every machine, picking its own current at run time
+            assert_eq!(a.shim_link(), eff.prefix_dir.join("current-$(uname -m)"));
+        }
+
+        let mut cfg = Config::default();
+        cfg.install.arch_suffix = true;
+        let eff = resolve_paths(&cfg, None).unwrap();
+        assert_eq!(eff.arch, Some(machine()));
+    }
+
+    #[test]
+    fn installed_arches_need_a_link_and_a_versions_dir() {
+        let td = tempfile::tempdir().unwrap();
+        let eff = resolve_paths(&Config::default(), None)
+            .unwrap()
+            .for_arch("x86_64");
+        let eff = EffectivePaths {
+            prefix_dir: td.path().to_path_buf(),
+            ..eff
+        };
+        for arch in ["x86_64", "aarch64"] {
+            std::fs::create_dir_all(td.path().join(arch)).unwrap();
+            std::os::unix::fs::symlink(
+                td.path().join(arch),
+                td.path().join(format!("current-{}", arch)),
+            )
+            .unwrap();
+        }
+        std::os::unix::fs::symlink("/nowhere", td.path().join("current-riscv64")).unwrap();
+        assert_eq!(eff.installed_arches(), ["aarch64", "x86_64"]);
+    }
+}
@@ -0,0 +1,237 @@
+use anyhow::Result;
+use indicatif::{HumanBytes, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::io::{IsTerminal, Read};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// In `Lines` mode, a status line at least this often...
+const LINE_INTERVAL: Duration = Duration::from_secs(5);
+/// ...and at every step of this many percent.
+const LINE_STEP_PERCENT: u64 = 10;
+
+/// How downloads and extraction show progress while they run. The summary
+/// lines printed once they are done are not affected (see `--quiet`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProgressMode {
+    /// Live bar redrawn on stderr
+    Bar,
+    /// A plain status line every 5s or 10%, for CI logs and dumb terminals
+    Lines,
+    /// Nothing until done
+    #[default]
+    Hidden,
+}
+
+impl ProgressMode {
+    /// `--quiet` hides progress; `--no-progress` (or WINDMAN_NO_PROGRESS),
+    /// `TERM=dumb`, `NO_COLOR` or a stderr that is not a terminal get status
+    /// lines; anything else gets the bar. `env` reads an environment variable.
+    pub fn resolve(
+        quiet: bool,
+        no_progress: bool,
+        env: impl Fn(&str) -> Option<String>,
+        stderr_is_terminal: bool,
+    ) -> Self {
+        let set = |k: &str| env(k).is_some_and(|v| !v.is_empty());
+        if quiet {
+            Self::Hidden
+        } else if no_progress
+            || env("TERM").as_deref() == Some("dumb")
+            || set("NO_COLOR")
+            || !stderr_is_terminal
+        {
+            Self::Lines
+        } else {
+            Self::Bar
+        }
+    }
+
+    /// `resolve` against this process' environment and stderr.
+    pub fn detect(quiet: bool, no_progress: bool) -> Self {
+        Self::resolve(
+            quiet,
+            no_progress,
+            |k| std::env::var(k).ok(),
+            std::io::stderr().is_terminal(),
+        )
+    }
+}
+
+/// Progress over a byte count: the indicatif bar in `Bar` mode, status lines
+/// ("downloading: 120.0 MiB / 600.0 MiB (20%)") in `Lines` mode.
+pub struct Progress {
+    pb: ProgressBar,
+    lines: Option<Lines>,
+}
+
+struct Lines {
+    label: String,
+    total: Option<u64>,
+    /// When the last line was printed and the percentage it showed
+    last: Mutex<(Instant, u64)>,
+}
+
+impl Progress {
+    /// `template` styles the bar; a spinner stands in when `total` is unknown.
+    pub fn new(
+        mode: ProgressMode,
+        label: &str,
+        total: Option<u64>,
+        template: &str,
+    ) -> Result<Self> {
+        let pb = match total {
+            Some(total) => {
+                let pb = ProgressBar::new(total);
+                pb.set_style(ProgressStyle::with_template(template)?.progress_chars("#>-"));
+                pb
+            }
+            None => {
+                let pb = ProgressBar::new_spinner();
+                pb.set_style(ProgressStyle::with_template(
+                    "{spinner} {bytes} ({bytes_per_sec})",
+                )?);
+                pb
+            }
+        };
+        if mode == ProgressMode::Bar {
+            if total.is_none() {
+                pb.enable_steady_tick(Duration::from_millis(120));
+            }
+        } else {
+            pb.set_draw_target(ProgressDrawTarget::hidden());
+        }
+        let lines = (mode == ProgressMode::Lines).then(|| Lines {
+            label: label.to_string(),
+            total,
+            last: Mutex::new((Instant::now(), 0)),
+        });
+        Ok(Self { pb, lines })
+    }
+
+    /// Shows nothing (e.g. a stream whose size is tracked elsewhere).
+    pub fn hidden() -> Self {
+        Self {
+            pb: ProgressBar::hidden(),
+            lines: None,
+        }
+    }
+
+    pub fn set_position(&self, pos: u64) {
+        self.pb.set_position(pos);
+        let Some(l) = &self.lines else {
+            return;
+        };
+        let mut last = l.last.lock().unwrap();
+        let percent = l.total.filter(|&t| t > 0).map(|t| pos * 100 / t);
+        if line_due(last.0.elapsed(), percent, last.1) {
+            eprintln!("{}", status_line(&l.label, pos, l.total));
+            *last = (Instant::now(), percent.unwrap_or(last.1));
+        }
+    }
+
+    pub fn inc(&self, n: u64) {
+        self.set_position(self.pb.position() + n);
+    }
+
+    pub fn set_message(&self, msg: String) {
+        self.pb.set_message(msg);
+    }
+
+    pub fn finish_and_clear(&self) {
+        self.pb.finish_and_clear();
+    }
+
+    /// `r`, advancing the progress by every byte read.
+    pub fn wrap_read<R: Read>(&self, r: R) -> ProgressRead<'_, R> {
+        ProgressRead {
+            inner: r,
+            progress: self,
+        }
+    }
+}
+
+pub struct ProgressRead<'a, R> {
+    inner: R,
+    progress: &'a Progress,
+}
+
+impl<R: Read> Read for ProgressRead<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.progress.inc(n as u64);
+        Ok(n)
+    }
+}
+
+/// A new status line is due after `LINE_INTERVAL`, or once the percentage
+/// reaches the next `LINE_STEP_PERCENT` step past the last one shown.
+fn line_due(since_last: Duration, percent: Option<u64>, last_percent: u64) -> bool {
+    since_last >= LINE_INTERVAL
+        || percent.is_some_and(|p| p / LINE_STEP_PERCENT > last_percent / LINE_STEP_PERCENT)
+}
+
+fn status_line(label: &str, pos: u64, total: Option<u64>) -> String {
+    match total.filter(|&t| t > 0) {
+        Some(t) => format!(
+            "{}: {} / {} ({}%)",
+            label,
+            HumanBytes(pos),
+            HumanBytes(t),
+            pos * 100 / t
+        ),
+        None => format!("{}: {}", label, HumanBytes(pos)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(quiet: bool, no_progress: bool, vars: &[(&str, &str)], tty: bool) -> ProgressMode {
+        let vars: Vec<(String, String)> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        ProgressMode::resolve(
+            quiet,
+            no_progress,
+            |k| vars.iter().find(|(n, _)| n == k).map(|(_, v)| v.clone()),
+            tty,
+        )
+    }
+
+    #[test]
+    fn mode_follows_flags_environment_and_terminal() {
+        use ProgressMode::*;
+        // interactive terminal: unchanged
+        assert_eq!(
+            resolve(false, false, &[("TERM", "xterm-256color")], true),
+            Bar
+        );
+        assert_eq!(resolve(false, true, &[], true), Lines);
+        assert_eq!(resolve(false, false, &[("TERM", "dumb")], true), Lines);
+        assert_eq!(resolve(false, false, &[("NO_COLOR", "1")], true), Lines);
+        // NO_COLOR only counts when non-empty
+        assert_eq!(resolve(false, false, &[("NO_COLOR", "")], true), Bar);
+        assert_eq!(resolve(false, false, &[], false), Lines);
+        // --quiet wins over everything
+        assert_eq!(resolve(true, true, &[("TERM", "dumb")], true), Hidden);
+        assert_eq!(resolve(true, false, &[], true), Hidden);
+    }
+
+    #[test]
+    fn lines_every_ten_percent_or_five_seconds() {
+        let short = Duration::from_millis(100);
+        assert!(!line_due(short, Some(9), 0));
+        assert!(line_due(short, Some(10), 0));
+        assert!(!line_due(short, Some(19), 10));
+        assert!(line_due(short, Some(35), 10));
+        assert!(!line_due(short, None, 0));
+        assert!(line_due(LINE_INTERVAL, None, 0));
+        assert_eq!(
+            status_line("downloading", 512, Some(2048)),
+            "downloading: 512 B / 2.00 KiB (25%)"
+        );
+        assert_eq!(status_line("downloading", 512, None), "downloading: 512 B");
+    }
+}
@@ -0,0 +1,104 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Per-project version pin, looked up from the cwd upwards (like `.nvmrc`).
+pub const VERSION_FILE: &str = ".windsurf-version";
+
+/// Nearest `.windsurf-version` in `start` or one of its ancestors.
+pub fn find_version_file(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|d| d.join(VERSION_FILE))
+        .find(|p| p.is_file())
+}
+
+/// The version in a pin file: first line that is not blank or a `#` comment.
+pub fn parse_version_file(content: &str) -> Result<String> {
+    let Some(line) = content
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !l.starts_with('#'))
+    else {
+        bail!("no version found");
+    };
+    if !crate::util::is_valid_version_name(line) {
+        bail!("invalid version {:?}", line);
+    }
+    Ok(line.to_string())
+}
+
+/// Version pinned for `start` and the file that pinned it, if any.
+pub fn pinned_version(start: &Path) -> Result<Option<(String, PathBuf)>> {
+    let Some(file) = find_version_file(start) else {
+        return Ok(None);
+    };
+    let content =
+        fs::read_to_string(&file).with_context(|| format!("reading {}", file.display()))?;
+    let version = parse_version_file(&content).with_context(|| format!("in {}", file.display()))?;
+    Ok(Some((version, file)))
+}
+
+/// Write `dir/.windsurf-version`. Returns its path.
+pub fn write_version_file(dir: &Path, version: &str) -> Result<PathBuf> {
+    parse_version_file(version)?;
+    let file = dir.join(VERSION_FILE);
+    fs::write(&file, format!("{}\n", version))
+        .with_context(|| format!("writing {}", file.display()))?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn finds_nearest_file_walking_up() {
+        let td = tempdir().unwrap();
+        let root = td.path();
+        let deep = root.join("a/b/c");
+        fs::create_dir_all(&deep).unwrap();
+        assert_eq!(pinned_version(&deep).unwrap(), None);
+
+        write_version_file(root, "1.11.3").unwrap();
+        let (v, file) = pinned_version(&deep).unwrap().unwrap();
+        assert_eq!(v, "1.11.3");
+        assert_eq!(file, root.join(VERSION_FILE));
+
+        // a closer pin wins
+        write_version_file(&root.join("a/b"), "1.12.0").unwrap();
+        assert_eq!(pinned_version(&deep).unwrap().unwrap().0, "1.12.0");
+        assert_eq!(
+            pinned_version(&root.join("a")).unwrap().unwrap().0,
+            "1.11.3"
+        );
+    }
+
+    #[test]
+    fn parses_comments_and_whitespace() {
+        assert_eq!(
+            parse_version_file("# team pin\n\n  1.11.3  \n").unwrap(),
+            "1.11.3"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        for bad in [
+            "",
+            "# only a comment\n",
+            "1.11.3 extra",
+            "../1.11.3",
+            ".hidden",
+            "current",
+        ] {
+            assert!(parse_version_file(bad).is_err(), "accepted {:?}", bad);
+        }
+
+        let td = tempdir().unwrap();
+        fs::write(td.path().join(VERSION_FILE), "not a/version\n").unwrap();
+        let err = pinned_version(td.path()).unwrap_err();
+        assert!(format!("{:#}", err).contains(VERSION_FILE));
+    }
+}
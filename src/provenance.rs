@@ -0,0 +1,200 @@
+use crate::report::Verification;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Where an installed version came from and how it was checked, from its
+/// metadata. Each field is None when unknown (versions installed before it was
+/// recorded).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Provenance {
+    pub version: String,
+    pub path: PathBuf,
+    /// Download URL, or path of the local archive
+    pub source: Option<String>,
+    /// URL the download ended at once redirects were followed
+    pub final_url: Option<String>,
+    pub sha256: Option<String>,
+    pub verification: Option<Verification>,
+    pub windman_version: Option<String>,
+    /// RFC 3339 timestamp (UTC)
+    pub installed_at: Option<String>,
+    pub hostname: Option<String>,
+    pub channel: Option<String>,
+}
+
+/// The provenance of `version`, installed in `dir`.
+pub fn of(version: &str, dir: &Path) -> Provenance {
+    let meta = crate::meta::read(dir).unwrap_or_default();
+    let known = |s: String| (!s.is_empty()).then_some(s);
+    Provenance {
+        version: version.to_string(),
+        path: dir.to_path_buf(),
+        source: known(meta.source),
+        final_url: meta.final_url,
+        sha256: meta.sha256,
+        verification: meta.verification,
+        windman_version: meta.windman_version,
+        installed_at: known(meta.installed_at),
+        hostname: meta.hostname,
+        channel: meta.channel,
+    }
+}
+
+/// The record as `provenance` prints it, "unknown" for what was not recorded.
+pub fn render(p: &Provenance) -> Vec<String> {
+    let shown = |o: &Option<String>| o.clone().unwrap_or("unknown".to_string());
+    let verification = match p.verification {
+        Some(Verification::Signature) => "signature verified",
+        Some(Verification::Checksum) => "checksum matched",
+        Some(Verification::Unsigned) => "not verified (SHA-256 recorded only)",
+        None => "unknown",
+    };
+    vec![
+        format!("Version      : {}", p.version),
+        format!("Path         : {}", p.path.display()),
+        format!("Source       : {}", shown(&p.source)),
+        format!("Final URL    : {}", shown(&p.final_url)),
+        format!("SHA-256      : {}", shown(&p.sha256)),
+        format!("Verification : {}", verification),
+        format!("Channel      : {}", shown(&p.channel)),
+        format!("Installed at : {}", shown(&p.installed_at)),
+        format!("Installed on : {}", shown(&p.hostname)),
+        format!("Installed by : windman {}", shown(&p.windman_version)),
+    ]
+}
+
+/// A minimal CycloneDX 1.5 BOM with the installed version as its one
+/// component; what is unknown is left out.
+pub fn cyclonedx(p: &Provenance) -> serde_json::Value {
+    use serde_json::json;
+    let mut component = json!({
+        "type": "application",
+        "bom-ref": format!("windsurf@{}", p.version),
+        "name": "windsurf",
+        "version": p.version,
+    });
+    if let Some(sha) = &p.sha256 {
+        component["hashes"] = json!([{ "alg": "SHA-256", "content": sha }]);
+    }
+    // a local archive's path is no URL: it goes in the properties only
+    let mut urls: Vec<&String> = [&p.source, &p.final_url]
+        .into_iter()
+        .flatten()
+        .filter(|u| u.contains("://"))
+        .collect();
+    urls.dedup();
+    if !urls.is_empty() {
+        component["externalReferences"] = urls
+            .iter()
+            .map(|url| json!({ "type": "distribution", "url": url }))
+            .collect();
+    }
+    let mut properties =
+        vec![json!({ "name": "windman:path", "value": p.path.display().to_string() })];
+    let verification = p.verification.map(|v| {
+        match v {
+            Verification::Signature => "signature",
+            Verification::Checksum => "checksum",
+            Verification::Unsigned => "unsigned",
+        }
+        .to_string()
+    });
+    for (name, value) in [
+        ("windman:source", &p.source),
+        ("windman:verification", &verification),
+        ("windman:channel", &p.channel),
+        ("windman:hostname", &p.hostname),
+    ] {
+        if let Some(value) = value {
+            properties.push(json!({ "name": name, "value": value }));
+        }
+    }
+    component["properties"] = json!(properties);
+
+    let mut metadata = json!({});
+    if let Some(at) = &p.installed_at {
+        metadata["timestamp"] = json!(at);
+    }
+    if let Some(v) = &p.windman_version {
+        metadata["tools"] = json!({
+            "components": [{ "type": "application", "name": "windman", "version": v }]
+        });
+    }
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "version": 1,
+        "metadata": metadata,
+        "components": [component],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::VersionMeta;
+
+    #[test]
+    fn a_version_without_a_record_is_unknown_not_an_error() {
+        let td = tempfile::tempdir().unwrap();
+        let p = of("1.2.3", td.path());
+        assert_eq!(p.source, None);
+        let lines = render(&p);
+        assert!(lines.contains(&"Source       : unknown".to_string()));
+        assert!(lines.contains(&"Verification : unknown".to_string()));
+
+        let bom = cyclonedx(&p);
+        assert_eq!(bom["components"][0]["version"], "1.2.3");
+        assert!(bom["components"][0].get("hashes").is_none());
+        assert!(bom["metadata"].get("timestamp").is_none());
+    }
+
+    #[test]
+    fn cyclonedx_carries_the_recorded_provenance() {
+        let td = tempfile::tempdir().unwrap();
+        crate::meta::write(
+            td.path(),
+            &VersionMeta {
+                version: "1.2.3".into(),
+                installed_at: "2025-01-01T00:00:00Z".into(),
+                source: "https://example.invalid/Windsurf-1.2.3.tar.gz".into(),
+                sha256: Some("ab".repeat(32)),
+                final_url: Some("https://cdn.example.invalid/x/Windsurf-1.2.3.tar.gz".into()),
+                verification: Some(Verification::Signature),
+                windman_version: Some("0.9.0".into()),
+                hostname: Some("build-01".into()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let bom = cyclonedx(&of("1.2.3", td.path()));
+        assert_eq!(bom["bomFormat"], "CycloneDX");
+        assert_eq!(bom["metadata"]["timestamp"], "2025-01-01T00:00:00Z");
+        assert_eq!(
+            bom["metadata"]["tools"]["components"][0]["version"],
+            "0.9.0"
+        );
+        let c = &bom["components"][0];
+        assert_eq!(c["hashes"][0]["content"], "ab".repeat(32));
+        let urls: Vec<&str> = c["externalReferences"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["url"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            urls,
+            [
+                "https://example.invalid/Windsurf-1.2.3.tar.gz",
+                "https://cdn.example.invalid/x/Windsurf-1.2.3.tar.gz"
+            ]
+        );
+        let props = c["properties"].as_array().unwrap();
+        assert!(props
+            .iter()
+            .any(|p| p["name"] == "windman:verification" && p["value"] == "signature"));
+        assert!(props
+            .iter()
+            .any(|p| p["name"] == "windman:hostname" && p["value"] == "build-01"));
+    }
+}
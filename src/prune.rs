@@ -1,40 +1,488 @@
 use anyhow::Result;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
-/// Prune with an explicit list of paths to preserve (e.g., current and previous_current).
-pub fn prune_old_versions_with_preserve(
-    versions_dir: &Path,
+/// Exceptions to the count-based policy (`install.keep_per_minor`,
+/// `install.keep_patterns`). Versions they match are never pruned and do not
+/// use up any of the `keep` slots.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeepRules {
+    /// Keep the newest install of each major.minor series
+    pub per_minor: bool,
+    /// Globs (`*`, `?`) matched against version dir names, e.g. "1.11.*"
+    pub patterns: Vec<String>,
+}
+
+impl KeepRules {
+    pub fn from_config(install: &crate::config::InstallConfig) -> Self {
+        Self {
+            per_minor: install.keep_per_minor,
+            patterns: install.keep_patterns.clone(),
+        }
+    }
+}
+
+/// `(major, minor)` of a semver version name; None for anything else
+/// (timestamp dirs only ever follow the count policy).
+pub fn minor_series(name: &str) -> Option<(u64, u64)> {
+    let v = semver::Version::parse(name).ok()?;
+    Some((v.major, v.minor))
+}
+
+/// The highest version of each major.minor series among `names`, oldest
+/// series first. Names that are not semver are ignored.
+pub fn newest_per_minor<'a>(names: &[&'a str]) -> Vec<&'a str> {
+    let mut newest: BTreeMap<(u64, u64), &str> = BTreeMap::new();
+    for &name in names {
+        let Some(series) = minor_series(name) else {
+            continue;
+        };
+        let best = newest.entry(series).or_insert(name);
+        if crate::util::compare_version_names(name, best).is_gt() {
+            *best = name;
+        }
+    }
+    newest.into_values().collect()
+}
+
+/// Shell-style glob: `*` matches any run of characters, `?` exactly one.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let (p, n): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    let (mut pi, mut ni) = (0, 0);
+    // where the last `*` was, and how much of `name` it has eaten so far
+    let mut star: Option<(usize, usize)> = None;
+    while ni < n.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == n[ni]) {
+            pi += 1;
+            ni += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ni));
+            pi += 1;
+        } else if let Some((sp, sn)) = star {
+            pi = sp + 1;
+            ni = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+/// Names among `names` that `rules` keep whatever the count policy says, with
+/// the rule that keeps each (the minor series wins over a pattern).
+pub fn exceptions<'a>(names: &[&'a str], rules: &KeepRules) -> BTreeMap<&'a str, Reason> {
+    let mut kept: BTreeMap<&str, Reason> = BTreeMap::new();
+    if rules.per_minor {
+        for name in newest_per_minor(names) {
+            kept.insert(name, Reason::NewestOfSeries);
+        }
+    }
+    for &name in names {
+        if let Some(p) = rules.patterns.iter().find(|p| glob_match(p, name)) {
+            kept.entry(name)
+                .or_insert_with(|| Reason::Pattern(p.clone()));
+        }
+    }
+    kept
+}
+
+/// An installed version as the planner sees it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionEntry {
+    pub name: String,
+    pub path: PathBuf,
+    pub mtime: Option<SystemTime>,
+    /// Never pruned and takes no `keep` slot (no way to pin a version yet)
+    pub pinned: bool,
+    pub is_current: bool,
+    /// What `rollback` would switch to
+    pub is_previous: bool,
+    /// Switched away from recently (`install.protect_history`)
+    pub in_history: bool,
+}
+
+/// Version dirs of `versions_dir` (`current` and hidden dirs skipped), with
+/// every flag unset.
+pub fn scan(versions_dir: &Path) -> Result<Vec<VersionEntry>> {
+    let mut entries = Vec::new();
+    if !versions_dir.exists() {
+        return Ok(entries);
+    }
+    let read = crate::error::WindmanError::io;
+    for ent in fs::read_dir(versions_dir).map_err(read("reading", versions_dir))? {
+        let ent = ent.map_err(read("reading", versions_dir))?;
+        let name = ent.file_name().to_string_lossy().to_string();
+        let path = ent.path();
+        if !crate::util::is_version_dir_name(&name) || !path.is_dir() {
+            continue;
+        }
+        entries.push(VersionEntry {
+            mtime: fs::metadata(&path).and_then(|m| m.modified()).ok(),
+            name,
+            path,
+            pinned: false,
+            is_current: false,
+            is_previous: false,
+            in_history: false,
+        });
+    }
+    Ok(entries)
+}
+
+/// Why a version is kept or removed. The first that applies wins, in this order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reason {
+    Current,
+    Pinned,
+    /// `install.keep_per_minor`
+    NewestOfSeries,
+    /// `install.keep_patterns`
+    Pattern(String),
+    Previous,
+    /// Among the sources of the last switches (`install.protect_history`)
+    RecentSwitch,
+    /// Higher semver than current (after a downgrade)
+    NewerThanCurrent,
+    /// `rank` (0 = newest) among the versions no rule above kept
+    WithinKeep {
+        rank: usize,
+        keep: usize,
+    },
+    BeyondKeep {
+        keep: usize,
+    },
+    /// Kept by the count policy, but the install does not fit in
+    /// `install.max_total_size` with it
+    OverBudget {
+        limit: u64,
+    },
+}
+
+impl std::fmt::Display for Reason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Reason::Current => write!(f, "current version"),
+            Reason::Pinned => write!(f, "pinned"),
+            Reason::NewestOfSeries => write!(f, "newest of its minor series (keep_per_minor)"),
+            Reason::Pattern(p) => write!(f, "matches keep pattern '{}'", p),
+            Reason::Previous => write!(f, "rollback target"),
+            Reason::RecentSwitch => write!(f, "in the recent switch history (protect_history)"),
+            Reason::NewerThanCurrent => write!(f, "newer than current"),
+            Reason::WithinKeep { rank, keep } => {
+                write!(f, "#{} newest, keep = {}", rank + 1, keep)
+            }
+            Reason::BeyondKeep { keep } => {
+                write!(f, "older than the {} kept (keep = {})", keep, keep)
+            }
+            Reason::OverBudget { limit } => write!(
+                f,
+                "over the size limit (max_total_size = {})",
+                indicatif::HumanBytes(*limit)
+            ),
+        }
+    }
+}
+
+/// `install.max_total_size` as the planner applies it: the version dirs and the
+/// trash together must fit in `limit` bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Budget {
+    pub limit: u64,
+    /// Bytes already in the trash
+    pub trash_bytes: u64,
+    /// Versions pruned by count go to the trash and so keep counting
+    pub to_trash: bool,
+}
+
+impl Budget {
+    /// The budget for `versions_dir`, None without a limit.
+    pub fn for_dir(versions_dir: &Path, limit: Option<u64>, to_trash: bool) -> Option<Self> {
+        Some(Self {
+            limit: limit?,
+            trash_bytes: crate::trash::size(versions_dir),
+            to_trash,
+        })
+    }
+}
+
+/// What the planner decided for one version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decision {
+    pub entry: VersionEntry,
+    pub keep: bool,
+    pub reason: Reason,
+}
+
+/// The keep policy in one place: every prune (after install/update, `gc`,
+/// `prune`) builds one of these and applies its decisions.
+///
+/// `keep = N` keeps the N newest version dirs (by mtime). Current, the rollback
+/// target, the versions of the recent switches and versions newer than current
+/// are kept on top when they fall outside those N, but take a slot when they
+/// are among them. Pinned versions
+/// and those matched by the `KeepRules` are kept and take no slot. `keep = 0`
+/// keeps only the protected ones: it never means "disable pruning".
+///
+/// With a `Budget`, the oldest versions the count policy kept are removed on
+/// top until the rest fits; current, pinned versions and the `KeepRules` ones
+/// are never removed for size.
+#[derive(Debug, Clone)]
+pub struct PrunePlan {
+    entries: Vec<VersionEntry>,
     keep: usize,
-    preserve: &[PathBuf],
-) -> Result<()> {
-    // Collect version directories (skip "current")
-    let mut dirs: Vec<PathBuf> = Vec::new();
-    if versions_dir.exists() {
-        for ent in fs::read_dir(versions_dir)? {
-            let ent = ent?;
-            let p = ent.path();
-            if p.file_name().map(|n| n == "current").unwrap_or(false) {
-                continue;
+    rules: KeepRules,
+    protect_newer: bool,
+    budget: Option<Budget>,
+}
+
+impl PrunePlan {
+    pub fn new(entries: Vec<VersionEntry>, keep: usize) -> Self {
+        Self {
+            entries,
+            keep,
+            rules: KeepRules::default(),
+            protect_newer: true,
+            budget: None,
+        }
+    }
+
+    /// Plan over the version dirs of `versions_dir`.
+    pub fn scan(versions_dir: &Path, keep: usize) -> Result<Self> {
+        Ok(Self::new(scan(versions_dir)?, keep))
+    }
+
+    /// The plan `prune`, `gc` and the prune after a switch share: the
+    /// `install` policy over the version dirs, with current, the rollback
+    /// target and the recent switches marked.
+    pub fn for_install(
+        eff: &crate::paths::EffectivePaths,
+        install: &crate::config::InstallConfig,
+        keep: usize,
+    ) -> Result<Self> {
+        let current = crate::util::resolve_symlink(&eff.current_symlink);
+        let previous = crate::install::rollback_plan(eff)
+            .ok()
+            .map(|p| eff.versions_dir.join(p.target()));
+        Ok(Self::scan(&eff.versions_dir, keep)?
+            .rules(&KeepRules::from_config(install))
+            .current(current.as_deref())
+            .previous(previous.as_deref())
+            .recent(&crate::history::recent_sources(
+                &eff.versions_dir,
+                install.protect_history,
+            ))
+            .budget(Budget::for_dir(
+                &eff.versions_dir,
+                install.max_total_bytes(),
+                install.prune_to_trash,
+            )))
+    }
+
+    pub fn rules(mut self, rules: &KeepRules) -> Self {
+        self.rules = rules.clone();
+        self
+    }
+
+    /// Mark the version dir `current` points at. Paths are compared
+    /// canonicalized, so it may be spelled differently (e.g. `./1.0.0`).
+    pub fn current(mut self, current: Option<&Path>) -> Self {
+        Self::mark(&mut self.entries, current, |e| &mut e.is_current);
+        self
+    }
+
+    /// Mark the rollback target (compared like `current`).
+    pub fn previous(mut self, previous: Option<&Path>) -> Self {
+        Self::mark(&mut self.entries, previous, |e| &mut e.is_previous);
+        self
+    }
+
+    /// Mark the versions current was recently switched away from (compared
+    /// like `current`).
+    pub fn recent(mut self, sources: &[PathBuf]) -> Self {
+        for p in sources {
+            Self::mark(&mut self.entries, Some(p), |e| &mut e.in_history);
+        }
+        self
+    }
+
+    /// Keep versions newer than current (default). Off once a downgrade was
+    /// explicitly allowed.
+    pub fn protect_newer(mut self, protect: bool) -> Self {
+        self.protect_newer = protect;
+        self
+    }
+
+    /// Remove the oldest versions beyond what the count policy removes until
+    /// the install fits in `budget` (`install.max_total_size`).
+    pub fn budget(mut self, budget: Option<Budget>) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    fn mark(
+        entries: &mut [VersionEntry],
+        path: Option<&Path>,
+        flag: impl Fn(&mut VersionEntry) -> &mut bool,
+    ) {
+        let Some(path) = path else {
+            return;
+        };
+        let canonical = |p: &Path| fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+        let target = canonical(path);
+        for e in entries.iter_mut() {
+            if canonical(&e.path) == target {
+                *flag(e) = true;
             }
-            if p.is_dir() {
-                dirs.push(p);
+        }
+    }
+
+    /// One decision per entry, newest first. The result depends only on the
+    /// entries, not on the order they were given in.
+    pub fn decide(&self) -> Vec<Decision> {
+        let mut entries = self.entries.clone();
+        entries.sort_by(|a, b| {
+            b.mtime
+                .cmp(&a.mtime)
+                .then_with(|| crate::util::compare_version_names(&b.name, &a.name))
+                .then_with(|| b.name.cmp(&a.name))
+                .then_with(|| b.path.cmp(&a.path))
+        });
+        let current = entries
+            .iter()
+            .find(|e| e.is_current)
+            .map(|e| e.name.clone());
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        let exempt = exceptions(&names, &self.rules);
+
+        let mut rank = 0;
+        let mut decisions = Vec::with_capacity(entries.len());
+        for entry in &entries {
+            let rule = if entry.pinned {
+                Some(Reason::Pinned)
+            } else {
+                exempt.get(entry.name.as_str()).cloned()
+            };
+            let reason = match rule {
+                // exceptions take no slot
+                Some(_) if entry.is_current => Reason::Current,
+                Some(rule) => rule,
+                None => {
+                    rank += 1;
+                    if entry.is_current {
+                        Reason::Current
+                    } else if entry.is_previous {
+                        Reason::Previous
+                    } else if entry.in_history {
+                        Reason::RecentSwitch
+                    } else if self.protect_newer
+                        && current
+                            .as_deref()
+                            .is_some_and(|c| crate::install::is_downgrade(&entry.name, c))
+                    {
+                        Reason::NewerThanCurrent
+                    } else if rank <= self.keep {
+                        Reason::WithinKeep {
+                            rank: rank - 1,
+                            keep: self.keep,
+                        }
+                    } else {
+                        Reason::BeyondKeep { keep: self.keep }
+                    }
+                }
+            };
+            decisions.push(Decision {
+                entry: entry.clone(),
+                keep: !matches!(reason, Reason::BeyondKeep { .. }),
+                reason,
+            });
+        }
+        if let Some(budget) = self.budget {
+            fit_budget(&mut decisions, budget);
+        }
+        decisions
+    }
+}
+
+/// Turn kept versions into `OverBudget` removals, oldest first, until what is
+/// left (plus the trash) fits in `budget.limit`.
+fn fit_budget(decisions: &mut [Decision], budget: Budget) {
+    let sizes: Vec<u64> = decisions
+        .iter()
+        .map(|d| {
+            if d.keep || budget.to_trash {
+                crate::util::dir_size(&d.entry.path)
+            } else {
+                0
             }
+        })
+        .collect();
+    let mut used = budget.trash_bytes + sizes.iter().sum::<u64>();
+    for (d, size) in decisions.iter_mut().zip(&sizes).rev() {
+        if used <= budget.limit {
+            break;
+        }
+        let removable = matches!(
+            d.reason,
+            Reason::WithinKeep { .. }
+                | Reason::Previous
+                | Reason::RecentSwitch
+                | Reason::NewerThanCurrent
+        );
+        if removable {
+            d.keep = false;
+            d.reason = Reason::OverBudget {
+                limit: budget.limit,
+            };
+            used -= size;
         }
     }
+}
+
+/// Bytes the install uses: every version dir, the trash and staging leftovers.
+pub fn usage(versions_dir: &Path) -> u64 {
+    crate::util::dir_size(versions_dir)
+}
 
-    // Sort by mtime desc (newest first)
-    dirs.sort_by_key(|p| fs::metadata(p).and_then(|m| m.modified()).ok());
-    dirs.reverse();
+/// Paths of the versions `decisions` remove, newest first.
+pub fn removals(decisions: &[Decision]) -> Vec<PathBuf> {
+    decisions
+        .iter()
+        .filter(|d| !d.keep)
+        .map(|d| d.entry.path.clone())
+        .collect()
+}
+
+/// Paths of the versions `decisions` remove to fit the size limit, newest first.
+pub fn over_budget(decisions: &[Decision]) -> Vec<PathBuf> {
+    decisions
+        .iter()
+        .filter(|d| matches!(d.reason, Reason::OverBudget { .. }))
+        .map(|d| d.entry.path.clone())
+        .collect()
+}
 
-    // Keep the N newest + any path listed in 'preserve'
-    let mut kept: Vec<PathBuf> = Vec::new();
+/// Remove what `decisions` do not keep. With `to_trash`, pruned dirs are moved
+/// to `<versions_dir>/.trash` instead of deleted, except those removed for size:
+/// in the trash they would still count.
+pub fn apply(versions_dir: &Path, decisions: &[Decision], to_trash: bool) -> Result<()> {
+    let over = over_budget(decisions);
+    let (deleted, pruned): (Vec<PathBuf>, Vec<PathBuf>) = removals(decisions)
+        .into_iter()
+        .partition(|p| over.contains(p));
+    remove_versions(versions_dir, &deleted, false)?;
+    remove_versions(versions_dir, &pruned, to_trash)
+}
+
+/// Delete (or move to trash) the given version dirs.
+pub fn remove_versions(versions_dir: &Path, dirs: &[PathBuf], to_trash: bool) -> Result<()> {
     for d in dirs {
-        let is_preserved = preserve.contains(&d);
-        if kept.len() < keep || is_preserved {
-            kept.push(d);
+        if to_trash {
+            crate::trash::move_to_trash(versions_dir, d)?;
         } else {
-            let _ = fs::remove_dir_all(&d);
+            let _ = crate::util::remove_dir_within(versions_dir, d);
         }
     }
     Ok(())
@@ -47,6 +495,17 @@ mod tests {
     use std::{fs, thread, time::Duration};
     use tempfile::tempdir;
 
+    /// Prune `dir` keeping `keep` plus `current`, versions newer than it not
+    /// protected (as after `--allow-downgrade`).
+    fn prune(dir: &Path, keep: usize, current: &Path, rules: &KeepRules, to_trash: bool) {
+        let plan = PrunePlan::scan(dir, keep)
+            .unwrap()
+            .rules(rules)
+            .current(Some(current))
+            .protect_newer(false);
+        apply(dir, &plan.decide(), to_trash).unwrap();
+    }
+
     #[test]
     fn prune_keeps_n_newest_and_preserves_current() {
         let td = tempdir().unwrap();
@@ -70,11 +529,453 @@ mod tests {
         symlink(&v3, &current).unwrap();
 
         // Préserver la current (v3) et garder N=2 versions au total
-        prune_old_versions_with_preserve(&versions_dir, 2, &[v3.clone()]).unwrap();
+        prune(&versions_dir, 2, &v3, &KeepRules::default(), false);
 
         // v3 (current) doit exister; v2 doit rester (2 plus récentes); v1 supprimée
         assert!(v3.exists(), "latest (and current) should remain");
         assert!(v2.exists(), "second latest should remain");
         assert!(!v1.exists(), "oldest should be pruned");
     }
+
+    /// Three versions with increasing mtime: 1.0.0 (oldest) .. 1.0.2 (newest).
+    fn three_versions(base: &Path) -> (PathBuf, PathBuf, PathBuf) {
+        let v1 = base.join("1.0.0");
+        fs::create_dir_all(&v1).unwrap();
+        thread::sleep(Duration::from_millis(10));
+        let v2 = base.join("1.0.1");
+        fs::create_dir_all(&v2).unwrap();
+        thread::sleep(Duration::from_millis(10));
+        let v3 = base.join("1.0.2");
+        fs::create_dir_all(&v3).unwrap();
+        (v1, v2, v3)
+    }
+
+    #[test]
+    fn keep_zero_keeps_only_preserved() {
+        let td = tempdir().unwrap();
+        let (v1, v2, v3) = three_versions(td.path());
+
+        prune(td.path(), 0, &v2, &KeepRules::default(), false);
+
+        assert!(!v1.exists());
+        assert!(v2.exists(), "current must survive keep=0");
+        assert!(!v3.exists());
+    }
+
+    #[test]
+    fn relative_current_link_is_preserved() {
+        let td = tempdir().unwrap();
+        let (v1, v2, v3) = three_versions(td.path());
+        for target in ["1.0.0", "./1.0.0"] {
+            let current = td.path().join("current");
+            let _ = fs::remove_file(&current);
+            symlink(target, &current).unwrap();
+            let cur = crate::util::resolve_symlink(&current).unwrap();
+
+            prune(td.path(), 0, &cur, &KeepRules::default(), false);
+
+            assert!(v1.exists(), "current ({}) must survive", target);
+        }
+        assert!(!v2.exists());
+        assert!(!v3.exists());
+    }
+
+    #[test]
+    fn preserve_matches_non_canonical_spelling() {
+        let td = tempdir().unwrap();
+        let (v1, _v2, _v3) = three_versions(td.path());
+        let spelled = td.path().join(".").join("1.0.0");
+
+        prune(td.path(), 0, &spelled, &KeepRules::default(), false);
+
+        assert!(v1.exists());
+    }
+
+    #[test]
+    fn prune_to_trash_moves_instead_of_deleting_and_skips_hidden_dirs() {
+        let td = tempdir().unwrap();
+        let (v1, v2, v3) = three_versions(td.path());
+        let staging = td.path().join(".staging-1");
+        fs::create_dir_all(&staging).unwrap();
+
+        prune(td.path(), 1, &v3, &KeepRules::default(), true);
+
+        assert!(!v1.exists() && !v2.exists() && v3.exists());
+        assert!(staging.exists(), "hidden dirs are not versions");
+        let trashed: Vec<String> = crate::trash::list(td.path())
+            .into_iter()
+            .map(|e| e.version)
+            .collect();
+        assert_eq!(trashed.len(), 2);
+        assert!(trashed.contains(&"1.0.0".to_string()));
+    }
+
+    #[test]
+    fn symlinked_version_is_unlinked_and_its_target_survives() {
+        let td = tempdir().unwrap();
+        let (versions, outside) = (td.path().join("opt"), td.path().join("home"));
+        fs::create_dir_all(outside.join("docs")).unwrap();
+        fs::write(outside.join("docs/notes.txt"), b"x").unwrap();
+        fs::create_dir_all(&versions).unwrap();
+        symlink(&outside, versions.join("0.9.0")).unwrap();
+        thread::sleep(Duration::from_millis(10));
+        let (_, _, v3) = three_versions(&versions);
+
+        prune(&versions, 1, &v3, &KeepRules::default(), false);
+
+        assert!(fs::symlink_metadata(versions.join("0.9.0")).is_err());
+        assert!(outside.join("docs/notes.txt").is_file());
+    }
+
+    #[test]
+    fn keep_one_keeps_newest_and_current() {
+        let td = tempdir().unwrap();
+        let (v1, v2, v3) = three_versions(td.path());
+
+        prune(td.path(), 1, &v3, &KeepRules::default(), false);
+
+        assert!(!v1.exists());
+        assert!(!v2.exists());
+        assert!(v3.exists());
+    }
+
+    #[test]
+    fn current_not_newest_is_kept_on_top_of_keep() {
+        let td = tempdir().unwrap();
+        let (v1, v2, v3) = three_versions(td.path());
+
+        // current -> oldest (e.g. after a rollback)
+        prune(td.path(), 1, &v1, &KeepRules::default(), false);
+
+        assert!(v1.exists(), "current (oldest) must survive");
+        assert!(!v2.exists());
+        assert!(v3.exists(), "newest is the one kept by keep=1");
+    }
+
+    #[test]
+    fn versions_newer_than_current_are_kept_unless_downgrade_allowed() {
+        let td = tempdir().unwrap();
+        let (v1, v2, v3) = three_versions(td.path());
+        thread::sleep(Duration::from_millis(10));
+        let stamp = td.path().join("20250101000000");
+        fs::create_dir_all(&stamp).unwrap();
+        thread::sleep(Duration::from_millis(10));
+
+        // current downgraded to 1.0.1; 1.0.0 was touched last
+        fs::remove_dir(&v1).unwrap();
+        fs::create_dir_all(&v1).unwrap();
+        let plan = PrunePlan::scan(td.path(), 1).unwrap().current(Some(&v2));
+        let reasons: Vec<(String, Reason)> = plan
+            .decide()
+            .into_iter()
+            .map(|d| (d.entry.name, d.reason))
+            .collect();
+        assert_eq!(
+            reasons,
+            [
+                ("1.0.0".into(), Reason::WithinKeep { rank: 0, keep: 1 }),
+                ("20250101000000".into(), Reason::BeyondKeep { keep: 1 }),
+                ("1.0.2".into(), Reason::NewerThanCurrent),
+                ("1.0.1".into(), Reason::Current),
+            ]
+        );
+        let allowed = plan.clone().protect_newer(false).decide();
+        assert_eq!(removals(&allowed), [stamp.clone(), v3.clone()]);
+
+        // keep = 1 by mtime alone would drop 1.0.2
+        apply(td.path(), &plan.decide(), false).unwrap();
+        assert!(v3.exists(), "newer than current must survive");
+        assert!(v1.exists() && v2.exists());
+        // not semver, so never "newer": pruned as usual
+        assert!(!stamp.exists());
+    }
+
+    /// Versions of `bytes` bytes each, oldest first.
+    fn sized_versions(base: &Path, names: &[&str], bytes: usize) -> Vec<PathBuf> {
+        names
+            .iter()
+            .map(|n| {
+                let v = base.join(n);
+                fs::create_dir_all(v.join("Windsurf")).unwrap();
+                fs::write(v.join("Windsurf/blob"), vec![0u8; bytes]).unwrap();
+                thread::sleep(Duration::from_millis(10));
+                v
+            })
+            .collect()
+    }
+
+    #[test]
+    fn size_budget_removes_oldest_kept_versions_until_the_rest_fits() {
+        let td = tempdir().unwrap();
+        let v = sized_versions(td.path(), &["1.0.0", "1.0.1", "1.0.2", "1.0.3"], 100);
+        let rules = KeepRules {
+            patterns: vec!["1.0.1".into()],
+            ..KeepRules::default()
+        };
+        let plan = |limit, to_trash| {
+            PrunePlan::scan(td.path(), 2)
+                .unwrap()
+                .rules(&rules)
+                .current(Some(&v[3]))
+                .budget(Budget::for_dir(td.path(), Some(limit), to_trash))
+                .decide()
+        };
+        let reasons = |decisions: Vec<Decision>| -> Vec<(String, Reason)> {
+            decisions
+                .into_iter()
+                .map(|d| (d.entry.name, d.reason))
+                .collect()
+        };
+
+        // the count policy alone keeps everything but 1.0.0 (300 bytes)
+        assert_eq!(removals(&plan(300, false)), [v[0].clone()]);
+        assert_eq!(
+            reasons(plan(250, false)),
+            [
+                ("1.0.3".into(), Reason::Current),
+                ("1.0.2".into(), Reason::OverBudget { limit: 250 }),
+                ("1.0.1".into(), Reason::Pattern("1.0.1".into())),
+                ("1.0.0".into(), Reason::BeyondKeep { keep: 2 }),
+            ]
+        );
+
+        // in the trash, 1.0.0 still counts; nothing else can go
+        apply(td.path(), &plan(250, true), true).unwrap();
+        assert!(!v[0].exists() && !v[2].exists());
+        assert!(v[1].exists() && v[3].exists());
+        let trashed: Vec<String> = crate::trash::list(td.path())
+            .into_iter()
+            .map(|e| e.version)
+            .collect();
+        assert_eq!(trashed, ["1.0.0"], "removed for size: deleted outright");
+        assert_eq!(usage(td.path()), 300);
+        assert!(removals(&plan(250, true)).is_empty());
+    }
+
+    #[test]
+    fn glob_matching() {
+        assert!(glob_match("1.11.*", "1.11.3"));
+        assert!(glob_match("1.11.*", "1.11."));
+        assert!(!glob_match("1.11.*", "1.110.3"));
+        assert!(!glob_match("1.11.*", "1.1.3"));
+        assert!(glob_match("1.?.0", "1.9.0"));
+        assert!(!glob_match("1.?.0", "1.10.0"));
+        assert!(glob_match("*-nightly.*", "1.13.0-nightly.3"));
+        assert!(glob_match("1.12.11", "1.12.11"));
+        assert!(!glob_match("1.12.1", "1.12.11"));
+        assert!(glob_match("2025*", "20250101000000"));
+        assert!(glob_match("**", ""));
+        assert!(!glob_match("", "1.0.0"));
+    }
+
+    #[test]
+    fn newest_per_minor_groups_semver_and_skips_timestamps() {
+        let names = [
+            "1.11.3",
+            "20250101000000",
+            "1.12.0",
+            "1.11.10",
+            "1.12.0-beta.1",
+            "2.0.0",
+            "1.11.9",
+            "not-a-version",
+        ];
+        assert_eq!(minor_series("1.11.10"), Some((1, 11)));
+        assert_eq!(minor_series("20250101000000"), None);
+        assert_eq!(newest_per_minor(&names), ["1.11.10", "1.12.0", "2.0.0"]);
+        assert!(newest_per_minor(&["20250101000000", "20250202000000"]).is_empty());
+    }
+
+    #[test]
+    fn rules_combine_series_and_patterns() {
+        let names = ["1.10.2", "1.10.4", "1.11.1", "1.11.0", "20250101000000"];
+        let none = KeepRules::default();
+        assert!(exceptions(&names, &none).is_empty());
+
+        let per_minor = KeepRules {
+            per_minor: true,
+            patterns: Vec::new(),
+        };
+        assert_eq!(
+            exceptions(&names, &per_minor)
+                .into_keys()
+                .collect::<Vec<_>>(),
+            ["1.10.4", "1.11.1"]
+        );
+
+        let both = KeepRules {
+            per_minor: true,
+            patterns: vec!["1.10.*".into(), "2025*".into()],
+        };
+        assert_eq!(
+            exceptions(&names, &both).into_iter().collect::<Vec<_>>(),
+            [
+                ("1.10.2", Reason::Pattern("1.10.*".into())),
+                ("1.10.4", Reason::NewestOfSeries),
+                ("1.11.1", Reason::NewestOfSeries),
+                ("20250101000000", Reason::Pattern("2025*".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn exceptions_survive_on_top_of_keep() {
+        let td = tempdir().unwrap();
+        // oldest to newest by mtime
+        let dirs: Vec<PathBuf> = ["1.10.4", "1.10.5", "1.11.0", "20250101000000", "1.11.1"]
+            .iter()
+            .map(|v| {
+                let d = td.path().join(v);
+                fs::create_dir_all(&d).unwrap();
+                thread::sleep(Duration::from_millis(10));
+                d
+            })
+            .collect();
+        let rules = KeepRules {
+            per_minor: true,
+            patterns: Vec::new(),
+        };
+
+        let doomed = removals(
+            &PrunePlan::scan(td.path(), 1)
+                .unwrap()
+                .rules(&rules)
+                .decide(),
+        );
+
+        // 1.11.1 (newest of 1.11) and 1.10.5 (of 1.10) are exempt and take no
+        // slot: keep = 1 keeps the timestamp dir, the newest of the rest
+        assert_eq!(doomed, [dirs[2].clone(), dirs[0].clone()]);
+    }
+
+    /// Tiny deterministic PRNG for the property tests below.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self, n: u64) -> u64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 33) % n
+        }
+    }
+
+    /// Random versions (semver and timestamp names, colliding mtimes), at most
+    /// one current and one previous, a few pinned.
+    fn random_entries(rng: &mut Lcg) -> Vec<VersionEntry> {
+        let count = rng.next(9) as usize;
+        let mut names = BTreeMap::new();
+        while names.len() < count {
+            let name = if rng.next(5) == 0 {
+                format!("2025010{}000000", rng.next(10))
+            } else {
+                format!("1.{}.{}", 10 + rng.next(3), rng.next(6))
+            };
+            names.insert(name, ());
+        }
+        let current = rng.next(count as u64 + 1) as usize;
+        let previous = rng.next(count as u64 + 1) as usize;
+        names
+            .into_keys()
+            .enumerate()
+            .map(|(i, name)| VersionEntry {
+                path: PathBuf::from("/v").join(&name),
+                mtime: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(rng.next(5))),
+                pinned: rng.next(6) == 0,
+                is_current: i == current,
+                is_previous: i == previous && i != current,
+                in_history: rng.next(5) == 0,
+                name,
+            })
+            .collect()
+    }
+
+    fn random_rules(rng: &mut Lcg) -> KeepRules {
+        KeepRules {
+            per_minor: rng.next(2) == 0,
+            patterns: match rng.next(3) {
+                0 => vec!["1.11.*".into()],
+                1 => vec!["2025*".into(), "1.?0.1".into()],
+                _ => Vec::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn plan_never_removes_protected_versions_nor_keeps_more_than_asked() {
+        let mut rng = Lcg(7);
+        for _ in 0..500 {
+            let entries = random_entries(&mut rng);
+            let keep = rng.next(4) as usize;
+            let plan = PrunePlan::new(entries.clone(), keep).rules(&random_rules(&mut rng));
+            let decisions = plan.decide();
+
+            assert_eq!(decisions.len(), entries.len());
+            for d in &decisions {
+                let e = &d.entry;
+                if e.is_current || e.pinned || e.is_previous || e.in_history {
+                    assert!(d.keep, "{:?} removed", d);
+                }
+                // with downgrades protected, nothing above current goes either
+                if let Some(cur) = entries.iter().find(|e| e.is_current) {
+                    if crate::install::is_downgrade(&e.name, &cur.name) {
+                        assert!(d.keep, "{:?} is newer than {}", d, cur.name);
+                    }
+                }
+                assert_eq!(d.keep, !matches!(d.reason, Reason::BeyondKeep { .. }));
+            }
+            // the count policy keeps at most `keep` of the unprotected versions
+            let by_count = decisions
+                .iter()
+                .filter(|d| matches!(d.reason, Reason::WithinKeep { .. }))
+                .count();
+            assert!(by_count <= keep, "{} > keep = {}", by_count, keep);
+            // and removes nothing while fewer than `keep` are ranked
+            let ranked = decisions
+                .iter()
+                .filter(|d| {
+                    matches!(
+                        d.reason,
+                        Reason::Current
+                            | Reason::Previous
+                            | Reason::RecentSwitch
+                            | Reason::NewerThanCurrent
+                            | Reason::WithinKeep { .. }
+                            | Reason::BeyondKeep { .. }
+                    ) && !d.entry.pinned
+                })
+                .count();
+            if ranked <= keep {
+                assert!(removals(&decisions).is_empty(), "{:?}", decisions);
+            }
+            // a larger keep never removes more
+            let more = PrunePlan {
+                keep: keep + 1,
+                ..plan.clone()
+            }
+            .decide();
+            for p in removals(&more) {
+                assert!(removals(&decisions).contains(&p));
+            }
+        }
+    }
+
+    #[test]
+    fn plan_does_not_depend_on_input_order() {
+        let mut rng = Lcg(42);
+        for _ in 0..200 {
+            let mut entries = random_entries(&mut rng);
+            let keep = rng.next(4) as usize;
+            let rules = random_rules(&mut rng);
+            let expected = PrunePlan::new(entries.clone(), keep).rules(&rules).decide();
+            for i in (1..entries.len()).rev() {
+                entries.swap(i, rng.next(i as u64 + 1) as usize);
+            }
+            assert_eq!(
+                PrunePlan::new(entries, keep).rules(&rules).decide(),
+                expected
+            );
+        }
+    }
 }
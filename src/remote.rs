@@ -1,61 +1,318 @@
 use anyhow::{anyhow, bail, Context, Result};
 use reqwest::blocking::Client;
-use reqwest::header::{ACCEPT, USER_AGENT};
-use serde::Deserialize;
+use reqwest::header::{ACCEPT, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 use std::{env, time::Duration};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct LatestInfo {
     pub version: String,
     pub url: String, // <- toujours présent en mode API
+    /// SHA-256 of the download, when the API publishes it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+/// Remote failures that callers present differently from generic errors.
+#[derive(Debug, thiserror::Error)]
+pub enum RemoteError {
+    #[error(
+        "The Windsurf update service is unavailable right now ({cause}).\n{}",
+        match fallback_version {
+            Some(v) => format!("The releases page shows {} — try again later, or set WINDMAN_LATEST_ENDPOINT.", v),
+            None => "Try again later, or check `windman remote check`.".to_string(),
+        }
+    )]
+    ApiDown {
+        cause: String,
+        fallback_version: Option<String>,
+    },
+    #[error(
+        "The Windsurf update service is rate limiting requests (HTTP 429); {}",
+        match retry_after {
+            Some(d) => format!("retry in {}.", fmt_wait(*d)),
+            None => "retry in a few minutes.".to_string(),
+        }
+    )]
+    RateLimited { retry_after: Option<Duration> },
+}
+
+/// "45s", "2m", "2m 30s", "1h 05m".
+fn fmt_wait(d: Duration) -> String {
+    let s = d.as_secs();
+    match s {
+        0..=59 => format!("{}s", s),
+        60..=3599 if s.is_multiple_of(60) => format!("{}m", s / 60),
+        60..=3599 => format!("{}m {}s", s / 60, s % 60),
+        _ => format!("{}h {:02}m", s / 3600, s % 3600 / 60),
+    }
+}
+
+/// `Retry-After` as a wait from `now`: delay-seconds or an HTTP date (a date
+/// already past means now).
+fn parse_retry_after(value: &str, now: chrono::DateTime<chrono::Utc>) -> Option<Duration> {
+    let v = value.trim();
+    if let Ok(secs) = v.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(v).ok()?;
+    Some(
+        (at.with_timezone(&chrono::Utc) - now)
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+    )
 }
 
 const DEFAULT_TIMEOUT_SECS: u64 = 15;
-const LINUX_X64_STABLE_LATEST: &str =
-    "https://windsurf-stable.codeium.com/api/update/linux-x64/stable/latest";
+const UPDATE_API_BASE: &str = "https://windsurf-stable.codeium.com/api/update";
+/// Platforms probed by `latest_all` (update API naming).
+pub const LINUX_ARCHES: &[&str] = &["linux-x64", "linux-arm64"];
 const RELEASES_PAGE_URL: &str = "https://windsurf.com/editor/releases";
 
-fn build_client(timeout_secs: Option<u64>) -> Result<Client> {
+pub(crate) fn build_client(timeout_secs: Option<u64>) -> Result<Client> {
     let t = timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS);
-    Client::builder()
-        .timeout(Duration::from_secs(t))
-        .build()
-        .context("building reqwest Client")
+    crate::http::client(Duration::from_secs(t))
 }
 
 #[derive(Debug, Deserialize)]
 struct ApiLatest {
     version: String,
     url: String,
+    #[serde(default, alias = "sha256")]
+    sha256hash: Option<String>,
 }
 
-/// Essaie d’abord l’API officielle (surchargée par WINDMAN_LATEST_ENDPOINT si défini),
-/// qui renvoie {version, url}. C’est notre chemin standard.
-fn try_latest_via_api(client: &Client) -> Result<LatestInfo> {
-    let endpoint = env::var("WINDMAN_LATEST_ENDPOINT")
-        .ok()
-        .filter(|s| !s.is_empty())
-        .unwrap_or_else(|| LINUX_X64_STABLE_LATEST.to_string());
+pub(crate) fn api_endpoint(arch: &str, channel: &str) -> String {
+    format!("{}/{}/{}/latest", UPDATE_API_BASE, arch, channel)
+}
+
+/// Interroge l’API officielle, qui renvoie {version, url}. C’est notre chemin standard.
+pub(crate) fn try_latest_via_api(client: &Client, endpoint: &str) -> Result<ApiResponse> {
+    CachedClient {
+        client: client.clone(),
+        state: None,
+    }
+    .fetch(endpoint)
+}
 
-    let resp = client
-        .get(&endpoint)
-        .header(USER_AGENT, "windman/0.1")
-        .header(ACCEPT, "application/json")
-        .send()
-        .with_context(|| format!("GET {}", endpoint))?;
+/// One answer of the latest endpoint, kept whole for `dev-latest --verbose`.
+#[derive(Debug)]
+pub struct ApiResponse {
+    pub status: u16,
+    /// Caching, retry and rate-limit headers (lowercase name, value as sent)
+    pub headers_of_interest: Vec<(String, String)>,
+    /// Raw body; on a 304, the stored one it stands for
+    pub body: String,
+    /// `body` read as {version, url}, or why this answer gives none
+    pub parsed: Result<LatestInfo>,
+}
 
-    if !resp.status().is_success() {
-        bail!("unexpected status {} for {}", resp.status(), endpoint);
+impl ApiResponse {
+    fn header(&self, name: &str) -> Option<String> {
+        self.headers_of_interest
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.clone())
     }
+}
+
+fn is_header_of_interest(name: &str) -> bool {
+    matches!(
+        name,
+        "content-type"
+            | "cache-control"
+            | "age"
+            | "date"
+            | "etag"
+            | "last-modified"
+            | "retry-after"
+    ) || name.contains("ratelimit")
+        || name.contains("rate-limit")
+}
 
-    let parsed: ApiLatest = resp.json().context("deserializing latest JSON")?;
+/// Corps JSON de l’API -> LatestInfo.
+fn parse_latest_body(body: &str) -> Result<LatestInfo> {
+    let parsed: ApiLatest = serde_json::from_str(body).context("deserializing latest JSON")?;
     if parsed.version.trim().is_empty() || parsed.url.trim().is_empty() {
         bail!("latest API returned empty fields");
     }
-    Ok(LatestInfo {
+    let mut info = LatestInfo {
         version: parsed.version,
         url: parsed.url,
-    })
+        sha256: parsed.sha256hash.filter(|h| h.len() == 64),
+    };
+    // Si la "version" n'est pas clairement un semver, on tente de l'extraire depuis l'URL.
+    let re = regex::Regex::new(r"(\d+\.\d+\.\d+)").unwrap();
+    if !re.is_match(&info.version) {
+        if let Some(cap) = re.captures(&info.url) {
+            info.version = cap.get(1).unwrap().as_str().to_string();
+        }
+    }
+    Ok(info)
+}
+
+/// Last answer of the latest endpoint, replayed on `304 Not Modified`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HttpCacheEntry {
+    pub endpoint: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub body: String,
+    pub fetched_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Validators of the latest endpoint, kept next to `windman check`'s cache.
+pub const HTTP_CACHE_FILE: &str = "latest-http.json";
+
+pub fn http_cache_path(cache_dir: &Path, channel: &str) -> PathBuf {
+    cache_dir.join(channel).join(HTTP_CACHE_FILE)
+}
+
+/// Client for the latest endpoint that sends `If-None-Match`/`If-Modified-Since`
+/// from the stored response and reuses its body on 304. No state = plain GET.
+pub struct CachedClient {
+    client: Client,
+    state: Option<PathBuf>,
+}
+
+impl CachedClient {
+    pub fn new(timeout_secs: Option<u64>, state: Option<PathBuf>) -> Result<Self> {
+        Ok(Self {
+            client: build_client(timeout_secs)?,
+            state,
+        })
+    }
+
+    fn stored(&self, endpoint: &str) -> Option<HttpCacheEntry> {
+        let s = std::fs::read_to_string(self.state.as_ref()?).ok()?;
+        serde_json::from_str::<HttpCacheEntry>(&s)
+            .ok()
+            .filter(|e| e.endpoint == endpoint)
+    }
+
+    pub fn latest(&self, endpoint: &str) -> Result<LatestInfo> {
+        self.fetch(endpoint)?.parsed
+    }
+
+    /// GET `endpoint`. Only transport failures are errors; a bad status or
+    /// body ends up in `parsed`.
+    pub fn fetch(&self, endpoint: &str) -> Result<ApiResponse> {
+        let stored = self.stored(endpoint);
+        let mut req = self.client.get(endpoint).header(ACCEPT, "application/json");
+        if let Some(e) = &stored {
+            if let Some(etag) = &e.etag {
+                req = req.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(lm) = &e.last_modified {
+                req = req.header(IF_MODIFIED_SINCE, lm);
+            }
+        }
+        let resp = crate::http::send(req)?;
+
+        let status = resp.status();
+        let headers_of_interest = resp
+            .headers()
+            .iter()
+            .filter(|(k, _)| is_header_of_interest(k.as_str()))
+            .map(|(k, v)| {
+                (
+                    k.to_string(),
+                    String::from_utf8_lossy(v.as_bytes()).to_string(),
+                )
+            })
+            .collect();
+        let body = resp.text().context("reading latest JSON")?;
+        let mut out = ApiResponse {
+            status: status.as_u16(),
+            headers_of_interest,
+            body,
+            parsed: Err(anyhow!("unexpected status {} for {}", status, endpoint)),
+        };
+
+        match status {
+            reqwest::StatusCode::NOT_MODIFIED => {
+                if let Some(e) = stored {
+                    out.parsed = parse_latest_body(&e.body);
+                    out.body = e.body;
+                }
+            }
+            reqwest::StatusCode::TOO_MANY_REQUESTS => {
+                let retry_after = out
+                    .header("retry-after")
+                    .and_then(|v| parse_retry_after(&v, chrono::Utc::now()));
+                out.parsed = Err(RemoteError::RateLimited { retry_after }.into());
+            }
+            s if s.is_success() => {
+                out.parsed = parse_latest_body(&out.body);
+                if out.parsed.is_ok() {
+                    self.store(endpoint, &out);
+                }
+            }
+            _ => {}
+        }
+        Ok(out)
+    }
+
+    /// Keep the validators and body of a good answer for the next request.
+    fn store(&self, endpoint: &str, resp: &ApiResponse) {
+        let Some(path) = &self.state else {
+            return;
+        };
+        let (etag, last_modified) = (
+            resp.header(ETAG.as_str()),
+            resp.header(LAST_MODIFIED.as_str()),
+        );
+        if etag.is_none() && last_modified.is_none() {
+            return;
+        }
+        let entry = HttpCacheEntry {
+            endpoint: endpoint.to_string(),
+            etag,
+            last_modified,
+            body: resp.body.clone(),
+            fetched_at: chrono::Utc::now(),
+        };
+        // best-effort: an unwritable cache only costs a full download next time
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Ok(json) = serde_json::to_string_pretty(&entry) {
+            let _ = std::fs::write(path, json);
+        }
+    }
+}
+
+/// Latest release for one platform (e.g. "linux-arm64") on `channel`.
+pub fn latest_for(arch: &str, channel: &str, timeout_secs: Option<u64>) -> Result<LatestInfo> {
+    let client = build_client(timeout_secs)?;
+    try_latest_via_api(&client, &api_endpoint(arch, channel))?.parsed
+}
+
+/// Query every platform in `LINUX_ARCHES`, sequentially. One failing
+/// endpoint does not hide the others: each arch carries its own result.
+pub fn latest_all(
+    channel: &str,
+    timeout_secs: Option<u64>,
+) -> BTreeMap<String, Result<LatestInfo>> {
+    LINUX_ARCHES
+        .iter()
+        .map(|arch| (arch.to_string(), latest_for(arch, channel, timeout_secs)))
+        .collect()
+}
+
+/// True when every successful answer reports the same version.
+pub fn versions_agree(results: &BTreeMap<String, Result<LatestInfo>>) -> bool {
+    let mut versions = results
+        .values()
+        .filter_map(|r| r.as_ref().ok())
+        .map(|i| &i.version);
+    match versions.next() {
+        Some(first) => versions.all(|v| v == first),
+        None => true,
+    }
 }
 
 /// Secours : récupère le HTML et lit juste la version (pas l’URL).
@@ -66,58 +323,325 @@ fn latest_version_from_releases_html(html: &str) -> Option<String> {
     Some(caps.get(1)?.as_str().to_string())
 }
 
-pub fn fetch_releases_html(timeout_secs: Option<u64>) -> Result<String> {
-    let client = build_client(timeout_secs)?;
-    let resp = client
-        .get(RELEASES_PAGE_URL)
-        .header(USER_AGENT, "windman/0.1")
-        .header(ACCEPT, "text/html,*/*")
-        .send()
-        .with_context(|| format!("GET {}", RELEASES_PAGE_URL))?;
+/// An attribute value as the browser reads it: `&amp;`, `&quot;`, `&#39;`,
+/// `&#NN;` and `&#xNN;` replaced. Unknown entities are kept.
+fn unescape_html_attr(s: &str) -> String {
+    let re = regex::Regex::new(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[a-zA-Z]+);").expect("valid regex");
+    re.replace_all(s, |c: &regex::Captures| {
+        let e = &c[1];
+        let code = match e.strip_prefix('#') {
+            Some(n) => match n.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => n.parse().ok(),
+            },
+            None => match e {
+                "amp" => Some(u32::from('&')),
+                "quot" => Some(u32::from('"')),
+                "apos" => Some(u32::from('\'')),
+                "lt" => Some(u32::from('<')),
+                "gt" => Some(u32::from('>')),
+                _ => None,
+            },
+        };
+        code.and_then(char::from_u32)
+            .map(String::from)
+            .unwrap_or_else(|| c[0].to_string())
+    })
+    .to_string()
+}
+
+/// Linux x64 tarball links of the releases page, in page order, HTML-unescaped
+/// (`&amp;` in a signed URL's query).
+fn linux_x64_links(html: &str) -> Vec<String> {
+    let re_href = regex::Regex::new(
+        r#"(?i)href\s*=\s*["'](https://[^"'\s]*codeiumdata\.com/[^"'\s]*linux-x64[^"'\s?#]*\.tar\.gz(?:[?#][^"'\s]*)?)["']"#,
+    )
+    .expect("valid regex");
+    re_href
+        .captures_iter(html)
+        .filter_map(|c| c.get(1).map(|m| unescape_html_attr(m.as_str())))
+        .collect()
+}
+
+/// Version in the (percent-decoded) file name of a download link.
+fn link_version(url: &str) -> Option<String> {
+    let re_ver = regex::Regex::new(r"(\d+\.\d+\.\d+)").expect("valid regex");
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let name = crate::util::percent_decode(path.rsplit('/').next().unwrap_or(path));
+    re_ver.captures(&name).map(|c| c[1].to_string())
+}
+
+/// Every release the releases page links a linux-x64 tarball for, newest
+/// first, one per version (`update --next-best` walks it).
+pub fn releases_from_html(html: &str) -> Vec<LatestInfo> {
+    let mut out: Vec<LatestInfo> = Vec::new();
+    for url in linux_x64_links(html) {
+        let Some(version) = link_version(&url) else {
+            continue;
+        };
+        if out.iter().all(|r| r.version != version) {
+            out.push(LatestInfo {
+                version,
+                url,
+                sha256: None,
+            });
+        }
+    }
+    out.sort_by(|a, b| crate::util::compare_version_names(&b.version, &a.version));
+    out
+}
+
+/// Full fallback: the newest linux-x64 tarball linked from the releases page.
+/// The link matching the newest version heading wins; otherwise the first link,
+/// with the version read from its (percent-decoded) file name. None when the
+/// page links no tarball.
+fn latest_from_releases_html(html: &str) -> Option<LatestInfo> {
+    let links = linux_x64_links(html);
+    let url = match latest_version_from_releases_html(html) {
+        Some(v) => links
+            .iter()
+            .find(|l| link_version(l).as_deref() == Some(v.as_str()))
+            .or(links.first())?,
+        None => links.first()?,
+    };
+    Some(LatestInfo {
+        version: link_version(url)?,
+        url: url.to_string(),
+        sha256: None,
+    })
+}
+
+/// The releases page at `url` (`changelog.source_url`).
+pub fn fetch_releases_html(url: &str, timeout_secs: Option<u64>) -> Result<String> {
+    fetch_html(&build_client(timeout_secs)?, url)
+}
+
+fn fetch_html(client: &Client, url: &str) -> Result<String> {
+    let resp = crate::http::send(client.get(url).header(ACCEPT, "text/html,*/*"))?;
     if !resp.status().is_success() {
-        bail!(
-            "unexpected status {} for {}",
-            resp.status(),
-            RELEASES_PAGE_URL
-        );
+        bail!("unexpected status {} for {}", resp.status(), url);
     }
     resp.text().context("reading releases HTML")
 }
 
+/// What a HEAD request tells about a download.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Head {
+    pub status: u16,
+    pub content_length: Option<u64>,
+}
+
+impl Head {
+    pub fn ok(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// HEAD `url`. Any HTTP status is an answer; only transport failures are errors.
+pub(crate) fn head(client: &Client, url: &str) -> Result<Head> {
+    let resp = crate::http::send(client.head(url))?;
+    // from the header: the (empty) body of a HEAD response says nothing
+    let content_length = resp
+        .headers()
+        .get(reqwest::header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok());
+    Ok(Head {
+        status: resp.status().as_u16(),
+        content_length,
+    })
+}
+
+/// One line of `windman remote check`.
+#[derive(Debug, Clone)]
+pub struct ProbeResult {
+    pub name: &'static str,
+    pub ok: bool,
+    pub latency: Duration,
+    pub detail: String,
+    /// The proxy the probe went through (credentials redacted); None: direct
+    pub proxy: Option<String>,
+}
+
+/// Probe the update API, the releases page and (if the API answered) the
+/// download host with a HEAD request. Never fails: each probe reports its own outcome.
+pub fn check_endpoints(
+    api_endpoint: &str,
+    releases_url: &str,
+    timeout_secs: Option<u64>,
+) -> Vec<ProbeResult> {
+    let client = match build_client(timeout_secs) {
+        Ok(c) => c,
+        Err(e) => {
+            return vec![ProbeResult {
+                name: "client",
+                ok: false,
+                latency: Duration::ZERO,
+                detail: format!("{:#}", e),
+                proxy: None,
+            }]
+        }
+    };
+    let mut out = Vec::new();
+    let via = |url: &str| {
+        let url = reqwest::Url::parse(url).ok()?;
+        crate::http::proxy_for(&url).map(|p| crate::http::describe_proxy(&p))
+    };
+
+    let t = Instant::now();
+    let api = try_latest_via_api(&client, api_endpoint).and_then(|r| r.parsed);
+    out.push(ProbeResult {
+        name: "update API",
+        ok: api.is_ok(),
+        latency: t.elapsed(),
+        detail: match &api {
+            Ok(info) => format!("latest {}", info.version),
+            Err(e) => format!("{:#}", e),
+        },
+        proxy: via(api_endpoint),
+    });
+
+    let t = Instant::now();
+    let page = fetch_html(&client, releases_url);
+    out.push(ProbeResult {
+        name: "releases page",
+        ok: page.is_ok(),
+        latency: t.elapsed(),
+        detail: match &page {
+            Ok(html) => match latest_version_from_releases_html(html) {
+                Some(v) => format!("shows {}", v),
+                None => "reachable (no version found)".to_string(),
+            },
+            Err(e) => format!("{:#}", e),
+        },
+        proxy: via(releases_url),
+    });
+
+    if let Ok(info) = &api {
+        let t = Instant::now();
+        let (ok, detail) = match head(&client, &info.url) {
+            Ok(h) => (h.ok(), format!("HTTP {}", h.status)),
+            Err(e) => (false, format!("{:#}", e)),
+        };
+        out.push(ProbeResult {
+            name: "download host",
+            ok,
+            latency: t.elapsed(),
+            detail,
+            proxy: via(&info.url),
+        });
+    }
+    out
+}
+
+/// Update API endpoint used for this machine (honors WINDMAN_LATEST_ENDPOINT).
+pub fn default_api_endpoint(channel: &str) -> String {
+    env::var("WINDMAN_LATEST_ENDPOINT")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| api_endpoint("linux-x64", channel))
+}
+
+pub fn releases_page_url() -> &'static str {
+    RELEASES_PAGE_URL
+}
+
+/// Download the detached signature for `url` next to `archive`:
+/// tries `<url>.minisig` first, then `<url>.sig`. Returns the local signature path.
+pub fn fetch_signature(url: &str, archive: &Path, timeout_secs: Option<u64>) -> Result<PathBuf> {
+    let mut errors = Vec::new();
+    for ext in ["minisig", "sig"] {
+        let sig_url = format!("{}.{}", url, ext);
+        let mut name = archive.as_os_str().to_owned();
+        name.push(format!(".{}", ext));
+        let dest = PathBuf::from(name);
+        match crate::download::download_small_file(&sig_url, &dest, timeout_secs) {
+            Ok(_) => return Ok(dest),
+            Err(e) => errors.push(format!("{}: {}", sig_url, e)),
+        }
+    }
+    Err(anyhow!("no signature found:\n  {}", errors.join("\n  ")))
+}
+
 /// API publique : renvoie {version, url} via l’API. Si l’API tombe,
 /// on tente d’afficher la version via HTML puis on échoue proprement.
-pub fn latest_stable_linux_x64(timeout_secs: Option<u64>) -> Result<LatestInfo> {
-    let client = build_client(timeout_secs)?;
+/// L’endpoint peut être surchargé par WINDMAN_LATEST_ENDPOINT.
+/// Si l’API tombe, renvoie `RemoteError::ApiDown` (avec la version HTML si trouvée),
+/// sauf si `allow_html_fallback` : le lien de téléchargement de la page suffit alors.
+/// `http_cache` : fichier d’état pour les requêtes conditionnelles (None = toujours tout relire).
+pub fn latest_linux_x64(
+    channel: &str,
+    timeout_secs: Option<u64>,
+    allow_html_fallback: bool,
+    http_cache: Option<PathBuf>,
+) -> Result<LatestInfo> {
+    latest_at(
+        &default_api_endpoint(channel),
+        timeout_secs,
+        allow_html_fallback,
+        http_cache,
+    )
+}
 
-    match try_latest_via_api(&client) {
-        Ok(mut info) => {
-            // Si la "version" n'est pas clairement un semver, on tente de l'extraire depuis l'URL.
-            let re = regex::Regex::new(r"(\d+\.\d+\.\d+)").unwrap();
-            let looks_like_semver = re.is_match(&info.version);
-            if !looks_like_semver {
-                if let Some(cap) = re.captures(&info.url) {
-                    info.version = cap.get(1).unwrap().as_str().to_string();
-                }
-            }
-            Ok(info)
-        }
+/// `latest_linux_x64` against an explicit endpoint (`dev-latest --endpoint`).
+pub fn latest_at(
+    endpoint: &str,
+    timeout_secs: Option<u64>,
+    allow_html_fallback: bool,
+    http_cache: Option<PathBuf>,
+) -> Result<LatestInfo> {
+    latest_from(
+        &CachedClient::new(timeout_secs, http_cache)?,
+        endpoint,
+        RELEASES_PAGE_URL,
+        allow_html_fallback,
+    )
+}
+
+fn latest_from(
+    api: &CachedClient,
+    endpoint: &str,
+    releases_url: &str,
+    allow_html_fallback: bool,
+) -> Result<LatestInfo> {
+    match api.latest(endpoint) {
+        Ok(info) => Ok(info),
         Err(api_err) => {
-            // fallback “informative” : on trouve au moins la version HTML pour aider au debug
-            if let Ok(html) = fetch_releases_html(timeout_secs) {
-                if let Some(ver) = latest_version_from_releases_html(&html) {
-                    return Err(anyhow!(
-                        "failed to fetch latest JSON ({}), but releases page shows version {}.\n\
-                         Please try again later or override endpoint via WINDMAN_LATEST_ENDPOINT.",
-                        api_err,
-                        ver
+            let rate_limited = matches!(
+                api_err.downcast_ref::<RemoteError>(),
+                Some(RemoteError::RateLimited { .. })
+            );
+            let html = fetch_html(&api.client, releases_url).ok();
+            if allow_html_fallback {
+                if let Some(info) = html.as_deref().and_then(latest_from_releases_html) {
+                    crate::ui::warn(format_args!(
+                        "update API unavailable ({}); using the download link from the releases page",
+                        short_cause(&api_err)
                     ));
+                    return Ok(info);
                 }
             }
-            Err(anyhow!("failed to fetch latest JSON: {}", api_err))
+            // a 429 says when to retry, which beats "unavailable"; an
+            // untrusted certificate is ours to fix
+            if rate_limited || crate::http::is_untrusted_cert(&api_err) {
+                return Err(api_err);
+            }
+            // fallback “informative” : on trouve au moins la version HTML pour aider au debug
+            let fallback_version = html.as_deref().and_then(latest_version_from_releases_html);
+            Err(RemoteError::ApiDown {
+                cause: short_cause(&api_err),
+                fallback_version,
+            }
+            .into())
         }
     }
 }
 
+/// Innermost cause of an error chain (e.g. "unexpected status 503 ..." rather than the reqwest wall).
+fn short_cause(e: &anyhow::Error) -> String {
+    e.chain().last().map(|c| c.to_string()).unwrap_or_default()
+}
+
 #[cfg(test)]
 pub(crate) fn semver_from_string(s: &str) -> Option<String> {
     let re = regex::Regex::new(r"(\d+\.\d+\.\d+)").ok()?;
@@ -134,6 +658,275 @@ mod tests_semver_pick {
         assert_eq!(semver_from_string(url).as_deref(), Some("1.12.11"));
     }
 
+    fn ok(v: &str) -> Result<LatestInfo> {
+        Ok(LatestInfo {
+            version: v.to_string(),
+            url: format!("https://example.invalid/{}.tar.gz", v),
+            sha256: None,
+        })
+    }
+
+    #[test]
+    fn versions_agree_ignores_failures_and_flags_mismatch() {
+        let mut m = BTreeMap::new();
+        m.insert("linux-x64".to_string(), ok("1.12.11"));
+        m.insert("linux-arm64".to_string(), Err(anyhow!("503")));
+        assert!(versions_agree(&m));
+
+        m.insert("linux-arm64".to_string(), ok("1.12.10"));
+        assert!(!versions_agree(&m));
+    }
+
+    /// Serve `responses.len()` HTTP requests on localhost, one canned response each.
+    fn mock_server(responses: Vec<&'static str>) -> String {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for resp in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buf = [0u8; 4096];
+                let _ = stream.read(&mut buf);
+                let _ = stream.write_all(resp.as_bytes());
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    const HTTP_503: &str =
+        "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    const HTTP_RELEASES: &str = "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: 19\r\nConnection: close\r\n\r\n<h2>1.12.11</h2>   ";
+
+    #[test]
+    fn api_down_is_typed_and_carries_html_fallback() {
+        let base = mock_server(vec![HTTP_503, HTTP_RELEASES]);
+        let err = latest_from(
+            &CachedClient::new(Some(5), None).unwrap(),
+            &format!("{}/api", base),
+            &format!("{}/releases", base),
+            false,
+        )
+        .unwrap_err();
+        match err.downcast_ref::<RemoteError>() {
+            Some(RemoteError::ApiDown {
+                cause,
+                fallback_version,
+            }) => {
+                assert!(cause.contains("503"), "{}", cause);
+                assert_eq!(fallback_version.as_deref(), Some("1.12.11"));
+            }
+            other => panic!("expected ApiDown, got {:?}", other),
+        }
+        assert_eq!(err.to_string().lines().count(), 2);
+    }
+
+    const WITH_LINKS: &str = include_str!("../tests/fixtures/releases_with_links.html");
+    const WITHOUT_LINKS: &str = include_str!("../tests/fixtures/releases_without_links.html");
+
+    #[test]
+    fn html_fallback_extracts_the_newest_linux_x64_tarball() {
+        let info = latest_from_releases_html(WITH_LINKS).unwrap();
+        assert_eq!(info.version, "1.12.11");
+        assert_eq!(
+            info.url,
+            "https://windsurf-stable.codeiumdata.com/linux-x64/stable/5f0b3f9a7c/Windsurf-linux-x64-1.12.11.tar.gz"
+        );
+
+        // no heading: first link, version from its file name
+        let no_heading = WITH_LINKS.replace("<h2", "<h3").replace("</h2>", "</h3>");
+        assert_eq!(
+            latest_from_releases_html(&no_heading).unwrap().version,
+            "1.12.11"
+        );
+    }
+
+    #[test]
+    fn html_fallback_links_are_unescaped_and_their_names_decoded() {
+        let html = r#"<h2>1.13.0</h2>
+            <a href="https://windsurf-stable.codeiumdata.com/linux-x64/stable/ab/Windsurf-linux-x64-1.13.0.tar.gz?Expires=1&amp;Signature=x&#x3D;">"#;
+        let info = latest_from_releases_html(html).unwrap();
+        assert_eq!(
+            info.url,
+            "https://windsurf-stable.codeiumdata.com/linux-x64/stable/ab/Windsurf-linux-x64-1.13.0.tar.gz?Expires=1&Signature=x="
+        );
+        assert_eq!(info.version, "1.13.0");
+
+        let encoded = r#"<a href="https://windsurf-stable.codeiumdata.com/linux-x64/Windsurf-linux-x64%2D1%2E13%2E1.tar.gz">"#;
+        assert_eq!(
+            latest_from_releases_html(encoded).unwrap().version,
+            "1.13.1"
+        );
+        assert_eq!(unescape_html_attr("a&amp;b&bogus;&#38;"), "a&b&bogus;&");
+    }
+
+    #[test]
+    fn releases_are_listed_newest_first_once_each() {
+        let page = format!(
+            "{}<a href=\"https://windsurf-stable.codeiumdata.com/linux-x64/stable/99/Windsurf-linux-x64-1.12.9.tar.gz\">",
+            WITH_LINKS
+        );
+        let got: Vec<String> = releases_from_html(&page)
+            .into_iter()
+            .map(|r| r.version)
+            .collect();
+        assert_eq!(got, ["1.12.11", "1.12.10", "1.12.9"]);
+        assert_eq!(
+            releases_from_html(WITH_LINKS)[1].url,
+            "https://windsurf-stable.codeiumdata.com/linux-x64/stable/1e2d3c4b5a/Windsurf-linux-x64-1.12.10.tar.gz"
+        );
+        assert!(releases_from_html(WITHOUT_LINKS).is_empty());
+    }
+
+    #[test]
+    fn html_fallback_without_links_gives_version_only() {
+        assert!(latest_from_releases_html(WITHOUT_LINKS).is_none());
+        assert_eq!(
+            latest_version_from_releases_html(WITHOUT_LINKS).as_deref(),
+            Some("1.12.11")
+        );
+    }
+
+    #[test]
+    fn html_fallback_drives_install_only_when_allowed() {
+        let page: &'static str = Box::leak(
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                WITH_LINKS.len(),
+                WITH_LINKS
+            )
+            .into_boxed_str(),
+        );
+        let base = mock_server(vec![HTTP_503, page, HTTP_503, page]);
+        let api = format!("{}/api", base);
+        let releases = format!("{}/releases", base);
+
+        let client = CachedClient::new(Some(5), None).unwrap();
+        let info = latest_from(&client, &api, &releases, true).unwrap();
+        assert_eq!(info.version, "1.12.11");
+        assert!(info.url.ends_with("Windsurf-linux-x64-1.12.11.tar.gz"));
+
+        let err = latest_from(&client, &api, &releases, false).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RemoteError>(),
+            Some(RemoteError::ApiDown { .. })
+        ));
+    }
+
+    /// Like `mock_server`, but also hands back each raw request (lowercased).
+    fn recording_server(
+        responses: Vec<&'static str>,
+    ) -> (String, std::sync::mpsc::Receiver<String>) {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for resp in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buf = [0u8; 4096];
+                let n = stream.read(&mut buf).unwrap_or(0);
+                let _ = tx.send(String::from_utf8_lossy(&buf[..n]).to_lowercase());
+                let _ = stream.write_all(resp.as_bytes());
+            }
+        });
+        (format!("http://{}", addr), rx)
+    }
+
+    const HTTP_LATEST_ETAG: &str = "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nETag: \"v1\"\r\nLast-Modified: Wed, 01 Oct 2025 10:00:00 GMT\r\nContent-Length: 71\r\nConnection: close\r\n\r\n{\"version\":\"1.12.11\",\"url\":\"https://x.invalid/Windsurf-1.12.11.tar.gz\"}";
+    const HTTP_304: &str = "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n";
+
+    #[test]
+    fn cached_client_sends_validators_and_replays_304() {
+        let td = tempfile::tempdir().unwrap();
+        let state = http_cache_path(td.path(), "stable");
+        let (base, requests) = recording_server(vec![HTTP_LATEST_ETAG, HTTP_304, HTTP_LATEST_ETAG]);
+        let endpoint = format!("{}/api", base);
+        let cached = CachedClient::new(Some(5), Some(state.clone())).unwrap();
+
+        let first = cached.latest(&endpoint).unwrap();
+        assert_eq!(first.version, "1.12.11");
+        assert!(!requests.recv().unwrap().contains("if-none-match"));
+        let stored: HttpCacheEntry =
+            serde_json::from_str(&std::fs::read_to_string(&state).unwrap()).unwrap();
+        assert_eq!(stored.etag.as_deref(), Some("\"v1\""));
+
+        // 304: the stored body answers
+        let second = cached.latest(&endpoint).unwrap();
+        assert_eq!(second.url, first.url);
+        let req = requests.recv().unwrap();
+        assert!(req.contains("if-none-match: \"v1\""), "{}", req);
+        assert!(
+            req.contains("if-modified-since: wed, 01 oct 2025 10:00:00 gmt"),
+            "{}",
+            req
+        );
+
+        // --refresh: no state, no validators
+        let fresh = CachedClient::new(Some(5), None).unwrap();
+        fresh.latest(&endpoint).unwrap();
+        assert!(!requests.recv().unwrap().contains("if-none-match"));
+    }
+
+    #[test]
+    fn check_endpoints_reports_each_probe() {
+        let base = mock_server(vec![HTTP_503, HTTP_503]);
+        let results = check_endpoints(
+            &format!("{}/api", base),
+            &format!("{}/releases", base),
+            Some(5),
+        );
+        // API down => no download-host probe
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| !r.ok));
+        assert!(results[0].detail.contains("503"), "{}", results[0].detail);
+    }
+
+    const HTTP_429: &str = "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 150\r\nX-RateLimit-Remaining: 0\r\nX-Request-Id: abc\r\nContent-Length: 21\r\nConnection: close\r\n\r\n{\"error\":\"slow down\"}";
+
+    #[test]
+    fn rate_limit_keeps_headers_and_says_when_to_retry() {
+        let base = mock_server(vec![HTTP_429, HTTP_429, HTTP_503]);
+        let endpoint = format!("{}/api", base);
+        let client = CachedClient::new(Some(5), None).unwrap();
+
+        let resp = client.fetch(&endpoint).unwrap();
+        assert_eq!(resp.status, 429);
+        assert_eq!(resp.body, "{\"error\":\"slow down\"}");
+        assert_eq!(resp.header("retry-after").as_deref(), Some("150"));
+        assert_eq!(resp.header("x-ratelimit-remaining").as_deref(), Some("0"));
+        assert!(resp.header("x-request-id").is_none());
+        match resp.parsed.unwrap_err().downcast_ref::<RemoteError>() {
+            Some(RemoteError::RateLimited { retry_after }) => {
+                assert_eq!(*retry_after, Some(Duration::from_secs(150)))
+            }
+            other => panic!("expected RateLimited, got {:?}", other),
+        }
+
+        // not turned into "unavailable" by the releases-page fallback
+        let err = latest_from(&client, &endpoint, &format!("{}/releases", base), false)
+            .unwrap_err()
+            .to_string();
+        assert!(err.ends_with("retry in 2m 30s."), "{}", err);
+    }
+
+    #[test]
+    fn retry_after_accepts_seconds_and_http_dates() {
+        let now = "2025-10-01T10:00:00Z".parse().unwrap();
+        assert_eq!(parse_retry_after("90", now), Some(Duration::from_secs(90)));
+        assert_eq!(
+            parse_retry_after("Wed, 01 Oct 2025 10:05:00 GMT", now),
+            Some(Duration::from_secs(300))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 01 Oct 2025 09:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+        assert_eq!(fmt_wait(Duration::from_secs(45)), "45s");
+        assert_eq!(fmt_wait(Duration::from_secs(120)), "2m");
+        assert_eq!(fmt_wait(Duration::from_secs(3900)), "1h 05m");
+    }
+
     #[test]
     fn picks_semver_from_text() {
         let s = "latest = 0.9.4 (build 42)";
@@ -0,0 +1,224 @@
+use indicatif::HumanBytes;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// How far `update` got; on failure, the step it failed in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    /// Asking the update API and comparing with what is installed
+    #[default]
+    Check,
+    /// Fetching the archive
+    Download,
+    /// Checking its signature
+    Verify,
+    /// Extracting it, checking it and switching current
+    Install,
+    /// The desktop entry
+    Desktop,
+    /// Removing old versions
+    Prune,
+    /// All of it went through
+    Done,
+}
+
+/// What an `update` run came to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// A new version is installed and current
+    Updated,
+    /// A new version is installed, not active (`--stage-only`)
+    Staged,
+    /// Nothing newer (or nothing comparable) to install
+    UpToDate,
+    /// The latest release is blocked and nothing else was installed
+    Blocked,
+    /// `--dry-run`: nothing was changed
+    DryRun,
+    Failed,
+}
+
+/// How the downloaded archive was checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verification {
+    /// Its signature matched `security.public_key`
+    Signature,
+    /// Its SHA-256 matched one given beforehand (`--sha256`, a `.sha256` file)
+    Checksum,
+    /// `security.signature_verification` is off: its SHA-256 is only recorded
+    Unsigned,
+}
+
+/// What happened to the desktop entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DesktopAction {
+    Updated,
+    /// `--no-desktop`, or `install.desktop_integration = false`
+    Skipped,
+}
+
+/// A version `update` pruned.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Pruned {
+    pub version: String,
+    pub bytes: u64,
+    /// Moved to the trash (`install.prune_to_trash`) rather than deleted
+    pub to_trash: bool,
+}
+
+/// The result of one `update` run, filled in as each step completes:
+/// `update --json` prints it, the closing line for humans comes from it.
+/// Every key is always present (`null` when the run did not get there).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UpdateReport {
+    pub profile: Option<String>,
+    pub outcome: Outcome,
+    pub step: Step,
+    /// Current before the update
+    pub previous_version: Option<String>,
+    pub latest_version: Option<String>,
+    /// The version installed (current, unless staged)
+    pub new_version: Option<String>,
+    pub url: Option<String>,
+    pub sha256: Option<String>,
+    pub bytes_downloaded: u64,
+    pub verification: Option<Verification>,
+    pub desktop: Option<DesktopAction>,
+    pub pruned: Vec<Pruned>,
+    pub duration_ms: u64,
+    pub error: Option<String>,
+}
+
+impl UpdateReport {
+    /// A run that has not got anywhere yet (`Failed` until it says otherwise).
+    pub fn new(profile: Option<String>) -> Self {
+        UpdateReport {
+            profile,
+            outcome: Outcome::Failed,
+            step: Step::Check,
+            previous_version: None,
+            latest_version: None,
+            new_version: None,
+            url: None,
+            sha256: None,
+            bytes_downloaded: 0,
+            verification: None,
+            desktop: None,
+            pruned: Vec::new(),
+            duration_ms: 0,
+            error: None,
+        }
+    }
+
+    /// Close the report of a run that started at `started` and ended with
+    /// `result` (the step a failure happened in stays as it was).
+    pub fn finish<T>(&mut self, started: Instant, result: &anyhow::Result<T>) {
+        self.duration_ms = started.elapsed().as_millis() as u64;
+        if let Err(e) = result {
+            self.outcome = Outcome::Failed;
+            self.error = Some(format!("{:#}", e));
+        }
+    }
+
+    /// "Updated 1.12.10 → 1.12.11 in 41.2s: 612.40 MiB downloaded, signature
+    /// verified, pruned 1.12.9 (580.00 MiB)"; None unless a version was installed.
+    pub fn summary(&self) -> Option<String> {
+        let new = self.new_version.as_deref()?;
+        let what = match self.outcome {
+            Outcome::Updated => "Updated",
+            Outcome::Staged => "Staged",
+            _ => return None,
+        };
+        let mut details = vec![format!("{} downloaded", HumanBytes(self.bytes_downloaded))];
+        if self.verification == Some(Verification::Signature) {
+            details.push("signature verified".to_string());
+        }
+        if !self.pruned.is_empty() {
+            let pruned: Vec<String> = self
+                .pruned
+                .iter()
+                .map(|p| format!("{} ({})", p.version, HumanBytes(p.bytes)))
+                .collect();
+            details.push(format!("pruned {}", pruned.join(", ")));
+        }
+        Some(format!(
+            "{} {} → {} in {:.1}s: {}",
+            what,
+            self.previous_version.as_deref().unwrap_or("<none>"),
+            new,
+            self.duration_ms as f64 / 1000.0,
+            details.join(", ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn updated() -> UpdateReport {
+        UpdateReport {
+            outcome: Outcome::Updated,
+            step: Step::Done,
+            previous_version: Some("1.12.10".into()),
+            latest_version: Some("1.12.11".into()),
+            new_version: Some("1.12.11".into()),
+            url: Some("https://cdn.invalid/Windsurf-linux-x64-1.12.11.tar.gz".into()),
+            sha256: Some("ab".repeat(32)),
+            bytes_downloaded: 642_150_000,
+            verification: Some(Verification::Signature),
+            desktop: Some(DesktopAction::Updated),
+            pruned: vec![Pruned {
+                version: "1.12.9".into(),
+                bytes: 608_174_080,
+                to_trash: false,
+            }],
+            duration_ms: 41_200,
+            ..UpdateReport::new(None)
+        }
+    }
+
+    #[test]
+    fn json_matches_golden_schema() {
+        let json = serde_json::to_string_pretty(&updated()).unwrap();
+        assert_eq!(
+            format!("{}\n", json),
+            include_str!("../tests/golden/update_report.json")
+        );
+
+        // a failure keeps every key, with the step it stopped at
+        let mut failed = UpdateReport::new(Some("next".into()));
+        failed.step = Step::Verify;
+        failed.finish(
+            Instant::now(),
+            &Err::<(), _>(anyhow::anyhow!("bad signature").context("verifying")),
+        );
+        let v = serde_json::to_value(&failed).unwrap();
+        let keys = |v: &serde_json::Value| {
+            let mut k: Vec<String> = v.as_object().unwrap().keys().cloned().collect();
+            k.sort();
+            k
+        };
+        assert_eq!(keys(&v), keys(&serde_json::to_value(updated()).unwrap()));
+        assert_eq!(v["outcome"], "failed");
+        assert_eq!(v["step"], "verify");
+        assert_eq!(v["error"], "verifying: bad signature");
+    }
+
+    #[test]
+    fn summary_is_built_from_the_report() {
+        assert_eq!(
+            updated().summary().unwrap(),
+            "Updated 1.12.10 → 1.12.11 in 41.2s: 612.40 MiB downloaded, signature verified, pruned 1.12.9 (580.00 MiB)"
+        );
+        let up_to_date = UpdateReport {
+            outcome: Outcome::UpToDate,
+            ..UpdateReport::new(None)
+        };
+        assert_eq!(up_to_date.summary(), None);
+    }
+}
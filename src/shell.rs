@@ -0,0 +1,394 @@
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Shells windman knows where to put things for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    /// The shell named by `$SHELL` (e.g. "/usr/bin/zsh"). `env` reads an
+    /// environment variable.
+    pub fn detect(env: &impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let shell = env("SHELL")
+            .filter(|s| !s.is_empty())
+            .context("SHELL is not set; name the shell (bash, zsh or fish)")?;
+        let name = shell.rsplit('/').next().unwrap_or(&shell);
+        match name {
+            "bash" => Ok(Self::Bash),
+            "zsh" => Ok(Self::Zsh),
+            "fish" => Ok(Self::Fish),
+            _ => bail!(
+                "unsupported shell '{}' (from SHELL); name one of bash, zsh or fish",
+                name
+            ),
+        }
+    }
+}
+
+/// Where a completion script goes, and the shell config line it needs, if any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionTarget {
+    pub path: PathBuf,
+    /// To add to the shell's rc file for the script to be picked up
+    pub rc_line: Option<String>,
+}
+
+fn home(env: &impl Fn(&str) -> Option<String>) -> Result<PathBuf> {
+    env("HOME")
+        .filter(|h| !h.is_empty())
+        .map(PathBuf::from)
+        .context("HOME is not set")
+}
+
+/// `$<var>` when set to an absolute path, else `~/<default>` (XDG base dirs).
+fn xdg(env: &impl Fn(&str) -> Option<String>, var: &str, default: &str) -> Result<PathBuf> {
+    match env(var).map(PathBuf::from).filter(|p| p.is_absolute()) {
+        Some(p) => Ok(p),
+        None => Ok(home(env)?.join(default)),
+    }
+}
+
+/// The user-level completion location of `shell`: bash-completion's and
+/// fish's XDG dirs; for zsh, the first `$FPATH` dir under `$HOME` (no config
+/// needed), else `~/.zfunc`, which has to be added to `fpath`.
+pub fn completion_target(
+    shell: Shell,
+    env: &impl Fn(&str) -> Option<String>,
+) -> Result<CompletionTarget> {
+    let target = |path: PathBuf| CompletionTarget {
+        path,
+        rc_line: None,
+    };
+    Ok(match shell {
+        Shell::Bash => target(
+            xdg(env, "XDG_DATA_HOME", ".local/share")?.join("bash-completion/completions/windman"),
+        ),
+        Shell::Fish => {
+            target(xdg(env, "XDG_CONFIG_HOME", ".config")?.join("fish/completions/windman.fish"))
+        }
+        Shell::Zsh => {
+            let home = home(env)?;
+            let in_fpath = env("FPATH").and_then(|fpath| {
+                fpath
+                    .split(':')
+                    .map(PathBuf::from)
+                    .find(|d| d.starts_with(&home) && d.is_dir())
+            });
+            match in_fpath {
+                Some(dir) => target(dir.join("_windman")),
+                None => CompletionTarget {
+                    path: home.join(".zfunc/_windman"),
+                    rc_line: Some(
+                        "fpath=(~/.zfunc $fpath); autoload -Uz compinit && compinit".to_string(),
+                    ),
+                },
+            }
+        }
+    })
+}
+
+/// Single-quoted for sh (bash, zsh).
+fn sh_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Single-quoted for fish, where `\` and `'` are escaped inside quotes.
+fn fish_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\\', r"\\").replace('\'', r"\'"))
+}
+
+/// `shell` code exporting `vars` and putting `path_dir` first in PATH.
+pub fn env_script(shell: Shell, vars: &[(&str, &Path)], path_dir: &Path) -> String {
+    let mut out = String::new();
+    let path_dir = path_dir.display().to_string();
+    for (name, value) in vars {
+        let value = value.display().to_string();
+        out.push_str(&match shell {
+            Shell::Bash | Shell::Zsh => format!("export {}={}\n", name, sh_quote(&value)),
+            Shell::Fish => format!("set -gx {} {}\n", name, fish_quote(&value)),
+        });
+    }
+    out.push_str(&match shell {
+        Shell::Bash | Shell::Zsh => {
+            format!("export PATH={}\"${{PATH:+:$PATH}}\"\n", sh_quote(&path_dir))
+        }
+        Shell::Fish => format!("set -gx PATH {} $PATH\n", fish_quote(&path_dir)),
+    });
+    out
+}
+
+/// What can follow each command path ("" = `windman` itself, "config show",
+/// ...): its subcommands and long flags, globals included.
+fn completion_table(cmd: &clap::Command) -> Vec<(String, Vec<String>)> {
+    let globals: Vec<String> = cmd
+        .get_arguments()
+        .filter(|a| a.is_global_set() && !a.is_hide_set())
+        .filter_map(|a| a.get_long().map(|l| format!("--{}", l)))
+        .collect();
+    let mut out = Vec::new();
+    walk(cmd, "", &globals, &mut out);
+    out
+}
+
+fn walk(cmd: &clap::Command, path: &str, globals: &[String], out: &mut Vec<(String, Vec<String>)>) {
+    let subs: Vec<&clap::Command> = cmd.get_subcommands().filter(|c| !c.is_hide_set()).collect();
+    let mut words: Vec<String> = subs.iter().map(|c| c.get_name().to_string()).collect();
+    words.extend(
+        cmd.get_arguments()
+            .filter(|a| !a.is_hide_set() && !a.is_global_set())
+            .filter_map(|a| a.get_long().map(|l| format!("--{}", l))),
+    );
+    words.extend(globals.iter().cloned());
+    out.push((path.to_string(), words));
+    for sub in subs {
+        let sub_path = match path {
+            "" => sub.get_name().to_string(),
+            _ => format!("{} {}", path, sub.get_name()),
+        };
+        walk(sub, &sub_path, globals, out);
+    }
+}
+
+/// Long flags that take a value: the word after them is not a subcommand.
+fn value_flags(cmd: &clap::Command, out: &mut Vec<String>) {
+    for a in cmd.get_arguments() {
+        if let (Some(l), true) = (a.get_long(), a.get_action().takes_values()) {
+            let flag = format!("--{}", l);
+            if !out.contains(&flag) {
+                out.push(flag);
+            }
+        }
+    }
+    for sub in cmd.get_subcommands() {
+        value_flags(sub, out);
+    }
+}
+
+/// The completion script of `shell` for `cmd`. Every shell runs the same
+/// logic: walk the words typed so far to the deepest known command path
+/// (skipping flags and their values), then offer what can follow it.
+pub fn completion_script(shell: Shell, cmd: &clap::Command) -> String {
+    let table = completion_table(cmd);
+    let mut flags = Vec::new();
+    value_flags(cmd, &mut flags);
+    let paths = format!(
+        "|{}|",
+        table
+            .iter()
+            .map(|(p, _)| p.as_str())
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<_>>()
+            .join("|")
+    );
+    match shell {
+        Shell::Bash => {
+            let cases: String = table
+                .iter()
+                .map(|(p, words)| format!("        \"{}\") opts=\"{}\" ;;\n", p, words.join(" ")))
+                .collect();
+            format!(
+                r#"# bash completion for windman (generated by `windman completions bash`)
+_windman() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}" cmd="" cand opts="" i
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        case "${{COMP_WORDS[i - 1]}}" in
+            {flags}) continue ;;
+        esac
+        case "${{COMP_WORDS[i]}}" in
+            -*) ;;
+            *)
+                cand="${{cmd:+$cmd }}${{COMP_WORDS[i]}}"
+                case "{paths}" in
+                    *"|$cand|"*) cmd="$cand" ;;
+                esac
+                ;;
+        esac
+    done
+    case "$cmd" in
+{cases}    esac
+    COMPREPLY=($(compgen -W "$opts" -- "$cur"))
+}}
+complete -o default -F _windman windman
+"#,
+                flags = flags.join("|"),
+                paths = paths,
+                cases = cases
+            )
+        }
+        Shell::Zsh => {
+            let cases: String = table
+                .iter()
+                .map(|(p, words)| format!("    \"{}\") opts=({}) ;;\n", p, words.join(" ")))
+                .collect();
+            format!(
+                r#"#compdef windman
+# zsh completion for windman (generated by `windman completions zsh`)
+local cmd="" cand prev="" w
+local -a opts
+for w in "${{(@)words[2,CURRENT-1]}}"; do
+    case "$prev" in
+        {flags}) prev=""; continue ;;
+    esac
+    prev="$w"
+    case "$w" in
+        -*) ;;
+        *)
+            cand="${{cmd:+$cmd }}$w"
+            case "{paths}" in
+                *"|$cand|"*) cmd="$cand" ;;
+            esac
+            ;;
+    esac
+done
+case "$cmd" in
+{cases}esac
+compadd -- "${{opts[@]}}"
+"#,
+                flags = flags.join("|"),
+                paths = paths,
+                cases = cases
+            )
+        }
+        Shell::Fish => {
+            let cases: String = table
+                .iter()
+                .map(|(p, words)| {
+                    format!(
+                        "        case '{}'\n            printf '%s\\n' {}\n",
+                        p,
+                        words.join(" ")
+                    )
+                })
+                .collect();
+            let known = paths
+                .trim_matches('|')
+                .split('|')
+                .map(|p| format!("'{}'", p))
+                .collect::<Vec<_>>()
+                .join(" ");
+            format!(
+                r#"# fish completion for windman (generated by `windman completions fish`)
+function __windman_candidates
+    set -l words (commandline -opc)
+    set -e words[1]
+    set -l cmd ''
+    set -l skip 0
+    for w in $words
+        if test $skip = 1
+            set skip 0
+            continue
+        end
+        switch $w
+            case {flags}
+                set skip 1
+            case '-*'
+            case '*'
+                set -l cand (string trim -- "$cmd $w")
+                if contains -- $cand {known}
+                    set cmd $cand
+                end
+        end
+    end
+    switch "$cmd"
+{cases}    end
+end
+complete -c windman -f -a '(__windman_candidates)'
+"#,
+                flags = flags.join(" "),
+                known = known,
+                cases = cases
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |k| {
+            vars.iter()
+                .find(|(n, _)| *n == k)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    #[test]
+    fn shell_comes_from_the_basename_of_shell() {
+        assert_eq!(
+            Shell::detect(&env(&[("SHELL", "/usr/bin/zsh")])).unwrap(),
+            Shell::Zsh
+        );
+        assert_eq!(
+            Shell::detect(&env(&[("SHELL", "fish")])).unwrap(),
+            Shell::Fish
+        );
+        let err = Shell::detect(&env(&[("SHELL", "/bin/tcsh")])).unwrap_err();
+        assert!(err.to_string().contains("tcsh"), "{}", err);
+        assert!(Shell::detect(&env(&[])).is_err());
+    }
+
+    #[test]
+    fn targets_follow_xdg_and_fpath() {
+        let td = tempfile::tempdir().unwrap();
+        let home = td.path().to_str().unwrap();
+        let t = |shell, vars: &[(&str, &str)]| completion_target(shell, &env(vars)).unwrap();
+
+        assert_eq!(
+            t(Shell::Bash, &[("HOME", home)]).path,
+            td.path()
+                .join(".local/share/bash-completion/completions/windman")
+        );
+        assert_eq!(
+            t(Shell::Fish, &[("HOME", home), ("XDG_CONFIG_HOME", "/xdg")]).path,
+            PathBuf::from("/xdg/fish/completions/windman.fish")
+        );
+        // relative XDG values are ignored, as the spec says
+        assert_eq!(
+            t(Shell::Fish, &[("HOME", home), ("XDG_CONFIG_HOME", "rel")]).path,
+            td.path().join(".config/fish/completions/windman.fish")
+        );
+
+        let zsh = t(
+            Shell::Zsh,
+            &[("HOME", home), ("FPATH", "/usr/share/zsh/functions")],
+        );
+        assert_eq!(zsh.path, td.path().join(".zfunc/_windman"));
+        assert!(zsh.rc_line.unwrap().contains("~/.zfunc"));
+
+        let mine = td.path().join(".zsh/completions");
+        std::fs::create_dir_all(&mine).unwrap();
+        let fpath = format!("/usr/share/zsh/functions:{}", mine.display());
+        let zsh = t(Shell::Zsh, &[("HOME", home), ("FPATH", &fpath)]);
+        assert_eq!(
+            zsh,
+            CompletionTarget {
+                path: mine.join("_windman"),
+                rc_line: None
+            }
+        );
+    }
+
+    #[test]
+    fn table_lists_subcommands_flags_and_globals_per_path() {
+        use clap::CommandFactory;
+        let table = completion_table(&crate::cli::Cli::command());
+        let at = |p: &str| &table.iter().find(|(path, _)| path == p).unwrap().1;
+        assert!(at("").contains(&"install".to_string()));
+        assert!(!at("").contains(&"dev-latest".to_string()), "hidden");
+        assert!(at("install").contains(&"--from-cache".to_string()));
+        assert!(at("install").contains(&"--prefix".to_string()), "global");
+        assert!(at("config").contains(&"migrate".to_string()));
+        assert!(at("config show").contains(&"--check".to_string()));
+
+        let mut flags = Vec::new();
+        value_flags(&crate::cli::Cli::command(), &mut flags);
+        assert!(flags.contains(&"--prefix".to_string()));
+        assert!(!flags.contains(&"--quiet".to_string()));
+    }
+}
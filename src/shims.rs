@@ -0,0 +1,258 @@
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::paths::EffectivePaths;
+
+/// Extra launchers (`shim add`), name -> path inside the version dir. One file
+/// per profile in the state dir, so `use`, `update` and `uninstall` find them all.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Registry {
+    pub shims: BTreeMap<String, String>,
+}
+
+impl Registry {
+    fn path(eff: &EffectivePaths) -> PathBuf {
+        eff.state_dir.join(match &eff.profile {
+            Some(p) => format!("shims-{}.json", p),
+            None => "shims.json".to_string(),
+        })
+    }
+
+    pub fn load(eff: &EffectivePaths) -> Self {
+        let shims = fs::read_to_string(Self::path(eff))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self { shims }
+    }
+
+    fn save(&self, eff: &EffectivePaths) -> Result<()> {
+        let p = Self::path(eff);
+        if self.shims.is_empty() {
+            let _ = fs::remove_file(&p);
+            return Ok(());
+        }
+        fs::create_dir_all(&eff.state_dir)
+            .with_context(|| format!("creating {}", eff.state_dir.display()))?;
+        fs::write(&p, serde_json::to_string_pretty(&self.shims)?)
+            .with_context(|| format!("writing {}", p.display()))
+    }
+}
+
+/// A managed shim as `shim list` shows it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub target: String,
+    pub path: PathBuf,
+    /// The target is an executable in the current version
+    pub available: bool,
+}
+
+fn shim_path(eff: &EffectivePaths, name: &str) -> PathBuf {
+    eff.bin_dir.join(name)
+}
+
+fn target_available(eff: &EffectivePaths, target: &str) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(eff.current_symlink.join(target))
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+/// `bin/helper` stays as is, `./bin/helper` loses its `./`; absolute paths and
+/// `..` are refused (the shim must stay inside the version dir).
+fn normalize_target(target: &str) -> Result<String> {
+    let mut parts = Vec::new();
+    for c in Path::new(target).components() {
+        match c {
+            Component::Normal(p) => parts.push(p.to_string_lossy().to_string()),
+            Component::CurDir => {}
+            _ => bail!(
+                "'{}' must be a path inside the install (e.g. Windsurf/bin/helper)",
+                target
+            ),
+        }
+    }
+    if parts.is_empty() {
+        bail!("empty shim target");
+    }
+    Ok(parts.join("/"))
+}
+
+/// Write a launcher `<bin_dir>/<name>` for `target` (relative to the version
+/// dir) and record it. Refuses to overwrite the main shim or a file windman did
+/// not write. Returns the entry (`available` false is worth a warning).
+pub fn add(eff: &EffectivePaths, name: &str, target: &str) -> Result<Entry> {
+    if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+        bail!("invalid shim name '{}'", name);
+    }
+    let path = shim_path(eff, name);
+    if path == eff.bin_shim {
+        bail!("'{}' is the main Windsurf shim", name);
+    }
+    let target = normalize_target(target)?;
+    let mut reg = Registry::load(eff);
+    if path.exists() && !reg.shims.contains_key(name) {
+        bail!(
+            "{} already exists and was not written by `windman shim add`",
+            path.display()
+        );
+    }
+
+    crate::util::write_command_shim(&path, &eff.shim_link(), &target)?;
+    reg.shims.insert(name.to_string(), target.clone());
+    reg.save(eff)?;
+    Ok(Entry {
+        name: name.to_string(),
+        available: target_available(eff, &target),
+        target,
+        path,
+    })
+}
+
+/// Delete a managed shim and forget it.
+pub fn remove(eff: &EffectivePaths, name: &str) -> Result<PathBuf> {
+    let mut reg = Registry::load(eff);
+    if reg.shims.remove(name).is_none() {
+        bail!("no managed shim named '{}' (see `windman shim list`)", name);
+    }
+    let path = shim_path(eff, name);
+    match fs::remove_file(&path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e).with_context(|| format!("removing {}", path.display())),
+    }
+    reg.save(eff)?;
+    Ok(path)
+}
+
+pub fn list(eff: &EffectivePaths) -> Vec<Entry> {
+    Registry::load(eff)
+        .shims
+        .into_iter()
+        .map(|(name, target)| Entry {
+            path: shim_path(eff, &name),
+            available: target_available(eff, &target),
+            name,
+            target,
+        })
+        .collect()
+}
+
+/// After a switch: re-write managed shims that are missing or point at another
+/// `current`, and report those whose target the current version lacks. Never
+/// fails, the switch itself already happened: problems come back as warnings.
+pub fn refresh(eff: &EffectivePaths) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let version = crate::history::current_version_name(&eff.current_symlink);
+    for e in list(eff) {
+        if !crate::util::shim_points_to(&e.path, &eff.shim_link()) {
+            if let Err(err) = crate::util::write_command_shim(&e.path, &eff.shim_link(), &e.target)
+            {
+                warnings.push(format!("shim '{}' not rewritten: {:#}", e.name, err));
+            }
+        }
+        if !e.available {
+            warnings.push(format!(
+                "shim '{}': {} is not in {} (it fails until you switch to a version that has it)",
+                e.name,
+                e.target,
+                version.as_deref().unwrap_or("the current version")
+            ));
+        }
+    }
+    warnings
+}
+
+/// `uninstall`: delete every managed shim and the registry. Returns the paths removed.
+pub fn remove_all(eff: &EffectivePaths) -> Vec<PathBuf> {
+    let mut removed = Vec::new();
+    for e in list(eff) {
+        if fs::remove_file(&e.path).is_ok() {
+            removed.push(e.path);
+        }
+    }
+    let _ = Registry::default().save(eff);
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::tempdir;
+
+    fn version(eff: &EffectivePaths, v: &str, helper: bool) {
+        let dir = eff.versions_dir.join(v).join("Windsurf/bin");
+        fs::create_dir_all(&dir).unwrap();
+        if helper {
+            fs::write(dir.join("helper"), "#!/bin/sh\necho helper\n").unwrap();
+            fs::set_permissions(dir.join("helper"), fs::Permissions::from_mode(0o755)).unwrap();
+        }
+    }
+
+    #[test]
+    fn add_list_remove_and_refuse_foreign_files() {
+        let td = tempdir().unwrap();
+        let eff = EffectivePaths::for_test(td.path());
+        version(&eff, "1.0.0", true);
+        crate::util::atomic_symlink_switch(&eff.versions_dir.join("1.0.0"), &eff.current_symlink)
+            .unwrap();
+
+        let e = add(&eff, "ws-helper", "./Windsurf/bin/helper").unwrap();
+        assert!(e.available);
+        assert_eq!(e.target, "Windsurf/bin/helper");
+        let out = std::process::Command::new(&e.path).output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&out.stdout), "helper\n");
+        assert_eq!(list(&eff), std::slice::from_ref(&e));
+
+        assert!(add(&eff, "windsurf", "Windsurf/bin/helper").is_err());
+        assert!(add(&eff, "up", "../outside").is_err());
+        fs::write(eff.bin_dir.join("mine"), "x").unwrap();
+        let err = add(&eff, "mine", "Windsurf/bin/helper").unwrap_err();
+        assert!(err.to_string().contains("not written by"), "{}", err);
+
+        assert_eq!(remove(&eff, "ws-helper").unwrap(), e.path);
+        assert!(!e.path.exists());
+        assert!(list(&eff).is_empty());
+        assert!(remove(&eff, "ws-helper").is_err());
+    }
+
+    #[test]
+    fn a_target_missing_in_the_new_version_warns_without_breaking_the_switch() {
+        let td = tempdir().unwrap();
+        let eff = EffectivePaths::for_test(td.path());
+        version(&eff, "1.0.0", true);
+        version(&eff, "1.0.1", false);
+        crate::util::atomic_symlink_switch(&eff.versions_dir.join("1.0.0"), &eff.current_symlink)
+            .unwrap();
+        add(&eff, "ws-helper", "Windsurf/bin/helper").unwrap();
+        assert!(refresh(&eff).is_empty());
+
+        crate::util::atomic_symlink_switch(&eff.versions_dir.join("1.0.1"), &eff.current_symlink)
+            .unwrap();
+        fs::remove_file(eff.bin_dir.join("ws-helper")).unwrap();
+        let warnings = refresh(&eff);
+
+        assert_eq!(
+            crate::history::current_version_name(&eff.current_symlink).as_deref(),
+            Some("1.0.1")
+        );
+        assert_eq!(warnings.len(), 1);
+        assert!(
+            warnings[0].starts_with("shim 'ws-helper': Windsurf/bin/helper is not in 1.0.1"),
+            "{}",
+            warnings[0]
+        );
+        // re-written all the same, it works again once a version has the helper
+        let shim = eff.bin_dir.join("ws-helper");
+        assert!(crate::util::shim_points_to(&shim, &eff.current_symlink));
+        let out = std::process::Command::new(&shim).output().unwrap();
+        assert_eq!(out.status.code(), Some(127));
+
+        assert_eq!(remove_all(&eff), [shim]);
+        assert!(list(&eff).is_empty());
+    }
+}
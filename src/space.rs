@@ -0,0 +1,220 @@
+use crate::config::SpaceCheck;
+use anyhow::Result;
+use indicatif::HumanBytes;
+use std::path::{Path, PathBuf};
+
+/// Room one step of an install takes in `dir` (the download in the cache, the
+/// extracted version under the prefix).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Need {
+    pub dir: PathBuf,
+    pub bytes: u64,
+}
+
+/// A filesystem without room for what an install puts there.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "need ~{} free on {}, only {} available",
+    HumanBytes(*need),
+    mount.display(),
+    HumanBytes(*available)
+)]
+pub struct Shortfall {
+    pub mount: PathBuf,
+    pub need: u64,
+    pub available: u64,
+}
+
+/// The filesystems short of room for `needs`; needs on the same filesystem
+/// add up. `fs` says where a dir's filesystem is mounted and how much is free
+/// there (None: unknown, not checked).
+pub fn shortfalls(needs: &[Need], fs: impl Fn(&Path) -> Option<(PathBuf, u64)>) -> Vec<Shortfall> {
+    let mut totals: Vec<Shortfall> = Vec::new();
+    for n in needs {
+        let Some((mount, available)) = fs(&n.dir) else {
+            continue;
+        };
+        match totals.iter_mut().find(|t| t.mount == mount) {
+            Some(t) => t.need += n.bytes,
+            None => totals.push(Shortfall {
+                mount,
+                need: n.bytes,
+                available,
+            }),
+        }
+    }
+    totals.retain(|t| t.need > t.available);
+    totals
+}
+
+/// The filesystem holding `dir`, as `shortfalls` wants it.
+pub fn on_disk(dir: &Path) -> Option<(PathBuf, u64)> {
+    let available = crate::util::fs_available_bytes(dir).ok()?;
+    Some((crate::util::mount_point(dir), available))
+}
+
+/// Before downloading an archive of `len` bytes into `cache` (None: streamed,
+/// nothing kept) to extract it into `versions_dir`, about `factor` times as
+/// big: each filesystem short of room is an error with `SpaceCheck::Abort`, a
+/// warning with `Warn`.
+pub fn check_download(
+    len: u64,
+    cache: Option<&Path>,
+    versions_dir: &Path,
+    factor: f64,
+    check: SpaceCheck,
+) -> Result<()> {
+    if check == SpaceCheck::Off {
+        return Ok(());
+    }
+    let mut needs = vec![Need {
+        dir: versions_dir.to_path_buf(),
+        bytes: (len as f64 * factor) as u64,
+    }];
+    if let Some(cache) = cache {
+        needs.push(Need {
+            dir: cache.to_path_buf(),
+            bytes: len,
+        });
+    }
+    let short = shortfalls(&needs, on_disk);
+    match (short.first(), check) {
+        (None, _) => Ok(()),
+        (Some(s), SpaceCheck::Abort) => anyhow::bail!(
+            "{}\nFree some space (e.g. `windman gc`), or set install.space_check = \"warn\" to try anyway.",
+            s
+        ),
+        (Some(_), _) => {
+            for s in &short {
+                crate::ui::warn(format_args!("{}; the install may fail", s));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Why a write failed, when it is the filesystem's fault rather than the data's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteFailure {
+    /// ENOSPC or EDQUOT
+    Full,
+    /// EROFS
+    ReadOnly,
+}
+
+/// The `WriteFailure` anywhere in `e`'s chain (the tar crate keeps the kind of
+/// the I/O errors it wraps).
+pub fn write_failure(e: &anyhow::Error) -> Option<WriteFailure> {
+    use std::io::ErrorKind;
+    e.chain()
+        .filter_map(|c| c.downcast_ref::<std::io::Error>())
+        .find_map(|io| match io.kind() {
+            ErrorKind::StorageFull | ErrorKind::QuotaExceeded => Some(WriteFailure::Full),
+            ErrorKind::ReadOnlyFilesystem => Some(WriteFailure::ReadOnly),
+            _ => None,
+        })
+}
+
+/// `e` with what to do about it on top when extracting into `dir` failed
+/// for lack of room (`need`: the expected size, when known) or because the
+/// filesystem is read-only; other errors as they are.
+pub fn explain_extract_error(e: anyhow::Error, dir: &Path, need: Option<u64>) -> anyhow::Error {
+    let Some(failure) = write_failure(&e) else {
+        return e;
+    };
+    let mount = crate::util::mount_point(dir);
+    let advice = match failure {
+        WriteFailure::ReadOnly => format!(
+            "{} is read-only; nothing was installed.\nPoint install.prefix_dir at a writable place.",
+            mount.display()
+        ),
+        WriteFailure::Full => {
+            let available = crate::util::fs_available_bytes(dir).unwrap_or(0);
+            let short = match need {
+                Some(need) => Shortfall {
+                    mount,
+                    need,
+                    available,
+                }
+                .to_string(),
+                None => format!(
+                    "no space left on {} ({} available)",
+                    mount.display(),
+                    HumanBytes(available)
+                ),
+            };
+            format!(
+                "{}; nothing was installed.\nFree some space (e.g. `windman gc`) and try again.",
+                short
+            )
+        }
+    };
+    e.context(advice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn need(dir: &str, bytes: u64) -> Need {
+        Need {
+            dir: dir.into(),
+            bytes,
+        }
+    }
+
+    /// `/home` and `/opt` with 300 and 900 bytes free; anything else unknown.
+    fn mounts(dir: &Path) -> Option<(PathBuf, u64)> {
+        ["/home", "/opt"]
+            .into_iter()
+            .zip([300, 900])
+            .find(|(m, _)| dir.starts_with(m))
+            .map(|(m, free)| (PathBuf::from(m), free))
+    }
+
+    #[test]
+    fn needs_on_one_filesystem_add_up() {
+        let fits = [need("/home/u/.cache", 100), need("/opt/ws", 800)];
+        assert!(shortfalls(&fits, mounts).is_empty());
+
+        let same_fs = [need("/home/u/.cache", 100), need("/home/u/opt", 250)];
+        let short = shortfalls(&same_fs, mounts);
+        assert_eq!(
+            short,
+            [Shortfall {
+                mount: "/home".into(),
+                need: 350,
+                available: 300
+            }]
+        );
+        assert_eq!(
+            Shortfall {
+                mount: "/home".into(),
+                need: 1503238554,
+                available: 300 << 20
+            }
+            .to_string(),
+            "need ~1.40 GiB free on /home, only 300.00 MiB available"
+        );
+
+        let unknown = [need("/mnt/x", u64::MAX)];
+        assert!(shortfalls(&unknown, mounts).is_empty());
+    }
+
+    #[test]
+    fn full_and_read_only_filesystems_are_told_apart() {
+        use std::io::{Error, ErrorKind};
+        let full = anyhow::Error::new(Error::from(ErrorKind::StorageFull)).context("unpacking");
+        assert_eq!(write_failure(&full), Some(WriteFailure::Full));
+        let ro = anyhow::Error::new(Error::from_raw_os_error(libc::EROFS));
+        assert_eq!(write_failure(&ro), Some(WriteFailure::ReadOnly));
+        let other = anyhow::Error::new(Error::from(ErrorKind::UnexpectedEof));
+        assert_eq!(write_failure(&other), None);
+
+        let td = tempfile::tempdir().unwrap();
+        let e = explain_extract_error(full, td.path(), Some(1 << 30));
+        let msg = e.to_string();
+        assert!(msg.starts_with("need ~1.00 GiB free on /"), "{}", msg);
+        assert!(msg.contains("nothing was installed"), "{}", msg);
+    }
+}
@@ -0,0 +1,80 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The version `update --stage-only` left for `activate`, in the state dir.
+pub const STAGED_FILE: &str = "staged.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Staged {
+    pub version: String,
+    /// RFC 3339
+    pub staged_at: String,
+}
+
+pub fn path(state_dir: &Path) -> PathBuf {
+    state_dir.join(STAGED_FILE)
+}
+
+/// What is staged; None when nothing is, or when the staged version dir is gone
+/// from `versions_dir` (removed, or activated by hand with `use`).
+pub fn load(state_dir: &Path, versions_dir: &Path) -> Result<Option<Staged>> {
+    let p = path(state_dir);
+    let staged: Staged = match fs::read_to_string(&p) {
+        Ok(s) => serde_json::from_str(&s).with_context(|| format!("reading {}", p.display()))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("reading {}", p.display())),
+    };
+    Ok(versions_dir
+        .join(&staged.version)
+        .is_dir()
+        .then_some(staged))
+}
+
+/// Record `version` as staged, replacing what was.
+pub fn save(state_dir: &Path, version: &str) -> Result<()> {
+    fs::create_dir_all(state_dir).with_context(|| format!("creating {}", state_dir.display()))?;
+    let staged = Staged {
+        version: version.to_string(),
+        staged_at: chrono::Utc::now().to_rfc3339(),
+    };
+    let p = path(state_dir);
+    fs::write(&p, serde_json::to_string_pretty(&staged)?)
+        .with_context(|| format!("writing {}", p.display()))
+}
+
+/// Forget the staged version once it is active.
+pub fn clear(state_dir: &Path) -> Result<()> {
+    let p = path(state_dir);
+    match fs::remove_file(&p) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("removing {}", p.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn staged_version_round_trips_while_its_dir_exists() {
+        let td = tempdir().unwrap();
+        let (state, versions) = (td.path().join("state"), td.path().join("opt"));
+        assert_eq!(load(&state, &versions).unwrap(), None);
+
+        fs::create_dir_all(versions.join("1.13.0")).unwrap();
+        save(&state, "1.13.0").unwrap();
+        assert_eq!(load(&state, &versions).unwrap().unwrap().version, "1.13.0");
+
+        fs::remove_dir(versions.join("1.13.0")).unwrap();
+        assert_eq!(load(&state, &versions).unwrap(), None, "dir gone");
+
+        clear(&state).unwrap();
+        clear(&state).unwrap();
+        assert!(!path(&state).exists());
+    }
+}
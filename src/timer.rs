@@ -0,0 +1,137 @@
+use anyhow::{Context, Result};
+use directories::BaseDirs;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+pub const SERVICE_NAME: &str = "windman-update.service";
+pub const TIMER_NAME: &str = "windman-update.timer";
+
+/// `~/.config/systemd/user`
+pub fn user_unit_dir() -> Result<PathBuf> {
+    let base = BaseDirs::new().context("cannot determine home directory")?;
+    Ok(base.config_dir().join("systemd").join("user"))
+}
+
+/// ExecStart line for the service: this binary, running every profile unattended.
+pub fn exec_start(exe: &Path, config: Option<&str>) -> String {
+    let mut parts = vec![quote(&exe.display().to_string())];
+    if let Some(c) = config {
+        parts.push("--config".to_string());
+        parts.push(quote(c));
+    }
+    parts.push("update --all-profiles --quiet --notify".to_string());
+    parts.join(" ")
+}
+
+fn quote(s: &str) -> String {
+    if s.contains(char::is_whitespace) || s.contains('"') {
+        format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        s.to_string()
+    }
+}
+
+pub fn service_unit(exec_start: &str) -> String {
+    format!(
+        "[Unit]\n\
+         Description=Update Windsurf (windman)\n\
+         Wants=network-online.target\n\
+         After=network-online.target\n\
+         \n\
+         [Service]\n\
+         Type=oneshot\n\
+         ExecStart={}\n\
+         # nothing to update is not a failure\n\
+         SuccessExitStatus={}\n",
+        exec_start,
+        crate::EXIT_UP_TO_DATE
+    )
+}
+
+pub fn timer_unit() -> String {
+    format!(
+        "[Unit]\n\
+         Description=Daily Windsurf update check (windman)\n\
+         \n\
+         [Timer]\n\
+         OnCalendar=daily\n\
+         RandomizedDelaySec=1h\n\
+         Persistent=true\n\
+         Unit={}\n\
+         \n\
+         [Install]\n\
+         WantedBy=timers.target\n",
+        SERVICE_NAME
+    )
+}
+
+/// Write the service and timer units into `dir`. Returns the written paths.
+pub fn install(dir: &Path, exec_start: &str) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    let mut written = Vec::new();
+    for (name, content) in [
+        (SERVICE_NAME, service_unit(exec_start)),
+        (TIMER_NAME, timer_unit()),
+    ] {
+        let path = dir.join(name);
+        fs::write(&path, content).with_context(|| format!("writing {}", path.display()))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Best-effort `systemctl --user disable --now` of the timer (before its files go away).
+pub fn disable() {
+    let _ = Command::new("systemctl")
+        .args(["--user", "disable", "--now", TIMER_NAME])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+}
+
+/// Delete both units from `dir`. Returns the removed paths.
+pub fn remove(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut removed = Vec::new();
+    for name in [TIMER_NAME, SERVICE_NAME] {
+        let path = dir.join(name);
+        if path.exists() {
+            fs::remove_file(&path).with_context(|| format!("removing {}", path.display()))?;
+            removed.push(path);
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exec_start_quotes_paths_with_spaces() {
+        let line = exec_start(Path::new("/opt/my tools/windman"), Some("/etc/w.toml"));
+        assert_eq!(
+            line,
+            "\"/opt/my tools/windman\" --config /etc/w.toml update --all-profiles --quiet --notify"
+        );
+    }
+
+    #[test]
+    fn install_then_remove_units() {
+        let dir = tempfile::tempdir().unwrap();
+        let units = dir.path().join("systemd/user");
+        let written = install(&units, "/usr/bin/windman update").unwrap();
+        assert_eq!(written.len(), 2);
+
+        let service = fs::read_to_string(units.join(SERVICE_NAME)).unwrap();
+        assert!(service.contains("ExecStart=/usr/bin/windman update\n"));
+        assert!(service.contains(&format!("SuccessExitStatus={}", crate::EXIT_UP_TO_DATE)));
+        let timer = fs::read_to_string(units.join(TIMER_NAME)).unwrap();
+        assert!(timer.contains("WantedBy=timers.target"));
+
+        let removed = remove(&units).unwrap();
+        assert_eq!(removed.len(), 2);
+        assert!(!units.join(SERVICE_NAME).exists());
+        assert!(remove(&units).unwrap().is_empty());
+    }
+}
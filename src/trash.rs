@@ -0,0 +1,166 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::util::{dir_size, move_dir_robust, timestamp_version};
+
+/// Name of the trash dir inside the versions dir.
+pub const TRASH_DIR: &str = ".trash";
+
+/// A version dir sitting in the trash as `<timestamp>-<version>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrashEntry {
+    pub path: PathBuf,
+    pub version: String,
+    pub trashed_at: DateTime<Utc>,
+}
+
+pub fn trash_dir(versions_dir: &Path) -> PathBuf {
+    versions_dir.join(TRASH_DIR)
+}
+
+/// Move a version dir into `<versions_dir>/.trash/<timestamp>-<version>`.
+pub fn move_to_trash(versions_dir: &Path, version_dir: &Path) -> Result<PathBuf> {
+    let version = version_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .context("version dir has no name")?;
+    let trash = trash_dir(versions_dir);
+    fs::create_dir_all(&trash).with_context(|| format!("creating {}", trash.display()))?;
+    let dest = trash.join(format!("{}-{}", timestamp_version(), version));
+    move_dir_robust(version_dir, &dest)?;
+    Ok(dest)
+}
+
+/// Trash entries, newest first. Unrecognized names are ignored.
+pub fn list(versions_dir: &Path) -> Vec<TrashEntry> {
+    let mut out = Vec::new();
+    if let Ok(rd) = fs::read_dir(trash_dir(versions_dir)) {
+        for ent in rd.flatten() {
+            let name = ent.file_name().to_string_lossy().to_string();
+            let Some((ts, version)) = name.split_once('-') else {
+                continue;
+            };
+            let Ok(t) = NaiveDateTime::parse_from_str(ts, "%Y%m%d%H%M%S") else {
+                continue;
+            };
+            out.push(TrashEntry {
+                path: ent.path(),
+                version: version.to_string(),
+                trashed_at: t.and_utc(),
+            });
+        }
+    }
+    out.sort_by_key(|e| std::cmp::Reverse(e.trashed_at));
+    out
+}
+
+/// Move the most recently trashed copy of `version` back into `versions_dir`.
+pub fn restore(versions_dir: &Path, version: &str) -> Result<PathBuf> {
+    let Some(entry) = list(versions_dir)
+        .into_iter()
+        .find(|e| e.version == version)
+    else {
+        bail!("version '{}' is not in the trash", version);
+    };
+    let dest = versions_dir.join(version);
+    if dest.exists() {
+        bail!("{} already exists; remove it first", dest.display());
+    }
+    move_dir_robust(&entry.path, &dest)?;
+    Ok(dest)
+}
+
+/// Delete trash entries (all, or only those trashed more than `older_than` ago).
+/// Returns how many were removed.
+pub fn empty(versions_dir: &Path, older_than: Option<Duration>) -> Result<usize> {
+    let expired = expired(versions_dir, older_than, Utc::now());
+    for e in &expired {
+        crate::util::remove_dir_within(&trash_dir(versions_dir), &e.path)?;
+    }
+    Ok(expired.len())
+}
+
+/// Trash entries `empty` would delete at `now`.
+pub fn expired(
+    versions_dir: &Path,
+    older_than: Option<Duration>,
+    now: DateTime<Utc>,
+) -> Vec<TrashEntry> {
+    list(versions_dir)
+        .into_iter()
+        .filter(|e| match older_than {
+            Some(d) => (now - e.trashed_at)
+                .to_std()
+                .map(|age| age >= d)
+                .unwrap_or(false),
+            None => true,
+        })
+        .collect()
+}
+
+/// Bytes used by the trash.
+pub fn size(versions_dir: &Path) -> u64 {
+    dir_size(&trash_dir(versions_dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn trash_then_restore_recreates_version_dir() {
+        let td = tempdir().unwrap();
+        let v = td.path().join("1.12.9");
+        fs::create_dir_all(v.join("Windsurf")).unwrap();
+        fs::write(v.join("Windsurf/f"), b"x").unwrap();
+
+        move_to_trash(td.path(), &v).unwrap();
+        assert!(!v.exists());
+        let entries = list(td.path());
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].version, "1.12.9");
+        assert_eq!(size(td.path()), 1);
+
+        restore(td.path(), "1.12.9").unwrap();
+        assert!(v.join("Windsurf/f").is_file());
+        assert!(list(td.path()).is_empty());
+        assert!(restore(td.path(), "1.12.9").is_err());
+    }
+
+    #[test]
+    fn empty_respects_older_than() {
+        let td = tempdir().unwrap();
+        let old = trash_dir(td.path()).join("20000101000000-1.0.0");
+        fs::create_dir_all(&old).unwrap();
+        let v = td.path().join("1.0.1");
+        fs::create_dir_all(&v).unwrap();
+        move_to_trash(td.path(), &v).unwrap();
+
+        let week = Duration::from_secs(7 * 24 * 3600);
+        assert_eq!(empty(td.path(), Some(week)).unwrap(), 1);
+        assert!(!old.exists());
+        assert_eq!(list(td.path()).len(), 1);
+        assert_eq!(empty(td.path(), None).unwrap(), 1);
+        assert!(list(td.path()).is_empty());
+    }
+
+    #[test]
+    fn empty_unlinks_a_symlinked_entry_without_following_it() {
+        let td = tempdir().unwrap();
+        let outside = td.path().join("outside");
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(outside.join("keep.txt"), b"x").unwrap();
+        let versions = td.path().join("opt");
+        fs::create_dir_all(trash_dir(&versions)).unwrap();
+        std::os::unix::fs::symlink(&outside, trash_dir(&versions).join("20000101000000-1.0.0"))
+            .unwrap();
+
+        assert_eq!(empty(&versions, None).unwrap(), 1);
+        assert!(list(&versions).is_empty());
+        assert!(outside.join("keep.txt").is_file());
+    }
+}
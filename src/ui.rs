@@ -0,0 +1,62 @@
+//! Output styles, in one place so every command colors the same things alike.
+//! Styled text only goes out through `anstream`, which drops the escape codes
+//! for `--color never`, `NO_COLOR` or an output that is not a terminal
+//! (`--color auto`). `--porcelain` and `--json` output is never styled at all.
+
+use anstyle::{AnsiColor, Style};
+use std::fmt::Display;
+
+/// The current version (`list`).
+pub const CURRENT: Style = AnsiColor::Green.on_default().bold();
+/// The `warning:` prefix.
+pub const WARNING: Style = AnsiColor::Yellow.on_default().bold();
+/// The `Error:` prefix.
+pub const ERROR: Style = AnsiColor::Red.on_default().bold();
+/// An update is available (`check`, the shim's notice).
+pub const NOTICE: Style = Style::new().bold();
+/// Release notes headings (`changelog`).
+pub const HEADING: Style = Style::new().bold();
+/// Code spans in release notes.
+pub const CODE: Style = AnsiColor::Cyan.on_default();
+/// The executable and product.json in a tree (`inspect`).
+pub const HIGHLIGHT: Style = AnsiColor::Cyan.on_default().bold();
+
+/// `--color`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorWhen {
+    /// Color on a terminal, unless NO_COLOR is set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Apply `--color` to everything printed from now on.
+pub fn set_color(when: ColorWhen) {
+    anstream::ColorChoice::write_global(match when {
+        ColorWhen::Auto => anstream::ColorChoice::Auto,
+        ColorWhen::Always => anstream::ColorChoice::Always,
+        ColorWhen::Never => anstream::ColorChoice::Never,
+    });
+}
+
+/// `text` in `style`; print it with `anstream::println!`/`eprintln!` only.
+pub fn paint(style: Style, text: impl Display) -> String {
+    format!("{style}{text}{style:#}")
+}
+
+/// `warning: <msg>` on stderr.
+pub fn warn(msg: impl Display) {
+    anstream::eprintln!("{} {}", paint(WARNING, "warning:"), msg);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paint_wraps_in_the_style_and_resets() {
+        assert_eq!(paint(NOTICE, "1.13.0"), "\x1b[1m1.13.0\x1b[0m");
+        assert_eq!(paint(Style::new(), "plain"), "plain");
+    }
+}
@@ -1,4 +1,6 @@
 use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::{fs, os::unix::fs::symlink, path::Path};
 use std::{io::Write, os::unix::fs::PermissionsExt};
 
@@ -7,60 +9,887 @@ pub fn timestamp_version() -> String {
     t.format("%Y%m%d%H%M%S").to_string()
 }
 
+/// `atomic_symlink_switch` was asked to point a link at nothing.
+#[derive(Debug, thiserror::Error)]
+#[error("cannot point {} at {}: it does not exist", link.display(), target.display())]
+pub struct MissingTarget {
+    pub target: PathBuf,
+    pub link: PathBuf,
+}
+
+/// Temp links and files older than this are left over from a crashed switch
+/// or write.
+const STALE_TMP_AGE: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// Point `link` at `target` through a temp link renamed over it: anyone
+/// following `link` meanwhile sees the old target or the new one. `target`
+/// must exist (`MissingTarget` otherwise).
 pub fn atomic_symlink_switch(target: &Path, link: &Path) -> Result<()> {
+    symlink_switch_with(target, link, || unique_temp_name(".tmp-"))
+}
+
+/// `<prefix><pid>-<nanos>-<counter>`: a temp name unique per process and
+/// call, even when a crashed run of the same (recycled) pid left one behind.
+pub fn unique_temp_name(prefix: &str) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    format!(
+        "{}{}-{}-{}",
+        prefix,
+        std::process::id(),
+        nanos,
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+fn symlink_switch_with(
+    target: &Path,
+    link: &Path,
+    mut temp_name: impl FnMut() -> String,
+) -> Result<()> {
     let parent = link.parent().unwrap();
+    // a relative target is relative to the link's dir
+    if !parent.join(target).exists() {
+        return Err(MissingTarget {
+            target: target.to_path_buf(),
+            link: link.to_path_buf(),
+        }
+        .into());
+    }
     if !parent.exists() {
         fs::create_dir_all(parent)?;
     }
-    let tmp = parent.join(format!(".tmp-{}", std::process::id()));
-    if tmp.exists() {
-        let _ = fs::remove_file(&tmp);
+    sweep_stale_temps(parent, ".tmp-", fs::FileType::is_symlink, STALE_TMP_AGE);
+
+    // a name taken despite the random part: once more with a fresh one
+    let mut tmp = parent.join(temp_name());
+    if let Err(e) = symlink(target, &tmp) {
+        if e.kind() != std::io::ErrorKind::AlreadyExists {
+            return Err(e).context("creating temp symlink");
+        }
+        tmp = parent.join(temp_name());
+        symlink(target, &tmp).context("creating temp symlink")?;
+    }
+    // rename(2) replaces the old link in one step
+    fs::rename(&tmp, link)
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&tmp);
+        })
+        .context("renaming temp symlink into place")?;
+    Ok(())
+}
+
+/// Remove the `<prefix>*` entries of kind `kind` in `dir` older than
+/// `max_age`: left by a switch or write that crashed between creating its
+/// temp entry and renaming it.
+fn sweep_stale_temps(
+    dir: &Path,
+    prefix: &str,
+    kind: fn(&fs::FileType) -> bool,
+    max_age: std::time::Duration,
+) {
+    let Ok(rd) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in rd.flatten() {
+        if !entry.file_name().to_string_lossy().starts_with(prefix) {
+            continue;
+        }
+        let stale = fs::symlink_metadata(entry.path()).is_ok_and(|m| {
+            kind(&m.file_type())
+                && m.modified()
+                    .ok()
+                    .and_then(|t| t.elapsed().ok())
+                    .is_some_and(|age| age >= max_age)
+        });
+        if stale {
+            let _ = fs::remove_file(entry.path());
+        }
     }
-    symlink(target, &tmp).context("creating temp symlink")?;
-    if link.exists() {
-        fs::remove_file(link).ok();
+}
+
+/// Executable locations inside a version dir, in lookup order.
+/// Shared by the shim and `windman which` so both agree on what runs.
+pub const WINDSURF_EXE_CANDIDATES: &[&str] = &[
+    "Windsurf/bin/windsurf",
+    "Windsurf/windsurf",
+    "bin/windsurf",
+    "windsurf",
+];
+
+/// `name` in one of the `PATH` directories, as a shell would find it.
+pub fn find_in_path(name: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|d| d.join(name))
+        .find(|p| {
+            fs::metadata(p)
+                .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+                .unwrap_or(false)
+        })
+}
+
+/// First executable regular file among `WINDSURF_EXE_CANDIDATES` under `dir`.
+pub fn find_windsurf_executable(dir: &Path) -> Option<PathBuf> {
+    WINDSURF_EXE_CANDIDATES
+        .iter()
+        .map(|rel| dir.join(rel))
+        .find(|p| {
+            fs::metadata(p)
+                .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+                .unwrap_or(false)
+        })
+}
+
+/// Err unless `dir` (an extracted archive) holds a Windsurf executable: without
+/// one the shim could only exit 127 at launch.
+pub fn check_windsurf_layout(dir: &Path, archive: &str) -> Result<()> {
+    if find_windsurf_executable(dir).is_some() {
+        return Ok(());
+    }
+    Err(crate::error::WindmanError::ArchiveInvalid {
+        archive: archive.to_string(),
+        reason: format!(
+            "archive does not look like a Windsurf build (no windsurf executable found; tried: {}). Use --force to install it anyway.",
+            WINDSURF_EXE_CANDIDATES.join(", ")
+        ),
+    }
+    .into())
+}
+
+/// CPU architecture read from an ELF header (e_machine).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElfArch {
+    X86,
+    X86_64,
+    Arm,
+    Aarch64,
+    Riscv,
+    Other(u16),
+}
+
+impl ElfArch {
+    fn from_machine(m: u16) -> Self {
+        match m {
+            0x03 => ElfArch::X86,
+            0x3E => ElfArch::X86_64,
+            0x28 => ElfArch::Arm,
+            0xB7 => ElfArch::Aarch64,
+            0xF3 => ElfArch::Riscv,
+            other => ElfArch::Other(other),
+        }
+    }
+
+    /// Architecture this windman binary was built for.
+    pub fn host() -> Self {
+        match std::env::consts::ARCH {
+            "x86" => ElfArch::X86,
+            "x86_64" => ElfArch::X86_64,
+            "arm" => ElfArch::Arm,
+            "aarch64" => ElfArch::Aarch64,
+            "riscv64" => ElfArch::Riscv,
+            _ => ElfArch::Other(0),
+        }
+    }
+}
+
+impl std::fmt::Display for ElfArch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ElfArch::X86 => write!(f, "x86"),
+            ElfArch::X86_64 => write!(f, "x86-64"),
+            ElfArch::Arm => write!(f, "arm"),
+            ElfArch::Aarch64 => write!(f, "aarch64"),
+            ElfArch::Riscv => write!(f, "riscv"),
+            ElfArch::Other(m) => write!(f, "e_machine {:#x}", m),
+        }
+    }
+}
+
+/// Parse the ELF identification + e_machine from the first bytes of a file.
+/// Returns None if the bytes are not an ELF header (e.g. a shell script).
+pub fn elf_arch_from_header(h: &[u8]) -> Option<ElfArch> {
+    if h.len() < 20 || &h[..4] != b"\x7fELF" {
+        return None;
+    }
+    let m = match h[5] {
+        1 => u16::from_le_bytes([h[18], h[19]]),
+        2 => u16::from_be_bytes([h[18], h[19]]),
+        _ => return None,
+    };
+    Some(ElfArch::from_machine(m))
+}
+
+/// Read just enough of `path` to sniff its ELF architecture.
+pub fn elf_arch_of(path: &Path) -> Option<ElfArch> {
+    use std::io::Read;
+    let mut buf = [0u8; 20];
+    let mut f = fs::File::open(path).ok()?;
+    f.read_exact(&mut buf).ok()?;
+    elf_arch_from_header(&buf)
+}
+
+/// Fail if the first ELF executable among the known candidates under `dir`
+/// targets another architecture than the host. Non-ELF layouts pass.
+pub fn check_host_arch(dir: &Path) -> Result<()> {
+    let host = ElfArch::host();
+    for rel in WINDSURF_EXE_CANDIDATES {
+        if let Some(arch) = elf_arch_of(&dir.join(rel)) {
+            if arch != host {
+                anyhow::bail!(
+                    "archive is for {} but this machine is {} (use --force to install anyway)",
+                    arch,
+                    host
+                );
+            }
+            return Ok(());
+        }
     }
-    fs::rename(&tmp, link).context("renaming temp symlink into place")?;
     Ok(())
 }
 
-pub fn write_shim(shim_path: &Path, current_symlink: &Path) -> Result<()> {
+/// The `CURRENT_LINK=` path embedded in a windman shim (None if missing or not a shim).
+pub fn shim_current_link(shim_path: &Path) -> Option<PathBuf> {
+    let content = fs::read_to_string(shim_path).ok()?;
+    content.lines().find_map(|l| {
+        l.trim()
+            .strip_prefix("CURRENT_LINK=\"")?
+            .strip_suffix('"')
+            .map(PathBuf::from)
+    })
+}
+
+/// Second line of every shim windman writes.
+pub const SHIM_MARKER: &str = "# Generated by windman; rewritten on install/update.";
+
+/// True if windman wrote `shim_path`: it carries `SHIM_MARKER`, or (shims
+/// from before the marker) a CURRENT_LINK. Anything else in bin_dir, e.g. a
+/// distro's `windsurf`, is not ours to touch.
+pub fn is_windman_shim(shim_path: &Path) -> bool {
+    fs::read_to_string(shim_path).is_ok_and(|s| {
+        s.lines()
+            .any(|l| l == SHIM_MARKER || l.starts_with("CURRENT_LINK=\""))
+    })
+}
+
+/// True if `shim_path` is a windman shim whose CURRENT_LINK is `current_symlink`.
+pub fn shim_points_to(shim_path: &Path, current_symlink: &Path) -> bool {
+    shim_current_link(shim_path).as_deref() == Some(current_symlink)
+}
+
+/// The old `current` a shim still runs when it was written for another prefix.
+pub fn stale_shim_target(shim_path: &Path, current_symlink: &Path) -> Option<PathBuf> {
+    shim_current_link(shim_path).filter(|l| l != current_symlink)
+}
+
+/// `install.shim_update_notice`: the shim runs `<exe> <args> notify-check`
+/// before launching, unless WINDMAN_NO_NOTICE is set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShimNotice {
+    pub exe: PathBuf,
+    /// Global options the notice runs with (`--config`, `--profile`)
+    pub args: Vec<String>,
+}
+
+impl ShimNotice {
+    fn script(&self) -> String {
+        let exe = sh_quote(&self.exe.display().to_string());
+        let mut cmd = vec![exe.clone()];
+        cmd.extend(self.args.iter().map(|a| sh_quote(a)));
+        cmd.push("notify-check".to_string());
+        format!(
+            "if [ -z \"${{WINDMAN_NO_NOTICE:-}}\" ] && [ -x {} ]; then\n  {} </dev/null || true\nfi\n",
+            exe,
+            cmd.join(" ")
+        )
+    }
+}
+
+/// Single-quoted for sh.
+fn sh_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// The shim runs `windman notify-check` (it was written with a `ShimNotice`).
+pub fn shim_has_notice(shim_path: &Path) -> bool {
+    fs::read_to_string(shim_path).is_ok_and(|s| s.contains(" notify-check "))
+}
+
+pub fn write_shim(
+    shim_path: &Path,
+    current_symlink: &Path,
+    notice: Option<&ShimNotice>,
+) -> Result<()> {
     let current_str = current_symlink.display().to_string();
 
     let script = format!(
         r#"#!/usr/bin/env bash
+{marker}
 set -euo pipefail
 CURRENT_LINK="{current}"
+{notice}ROOT="$(readlink -f "$CURRENT_LINK")"
+for rel in {candidates}; do
+  exe="$ROOT/$rel"
+  if [ -f "$exe" ] && [ -x "$exe" ]; then
+    exec "$exe" "$@"
+  fi
+done
+echo "windman: could not locate Windsurf executable under: $ROOT (tried: {candidates})" >&2
+exit 127
+"#,
+        marker = SHIM_MARKER,
+        current = current_str,
+        notice = notice.map(ShimNotice::script).unwrap_or_default(),
+        candidates = WINDSURF_EXE_CANDIDATES.join(" ")
+    );
+    write_script(shim_path, &script)
+}
+
+/// A launcher for another executable inside the install (`rel`, relative to the
+/// version dir), resolved through `current` like the main shim.
+pub fn write_command_shim(shim_path: &Path, current_symlink: &Path, rel: &str) -> Result<()> {
+    let script = format!(
+        r#"#!/usr/bin/env bash
+{marker}
+set -euo pipefail
+CURRENT_LINK="{current}"
+TARGET="{rel}"
 ROOT="$(readlink -f "$CURRENT_LINK")"
-exe="$ROOT/Windsurf/bin/windsurf"
-if [ -x "$exe" ]; then
+exe="$ROOT/$TARGET"
+if [ -f "$exe" ] && [ -x "$exe" ]; then
   exec "$exe" "$@"
 fi
-# very small fallback
-if [ -x "$ROOT/windsurf" ]; then
-  exec "$ROOT/windsurf" "$@"
-fi
-echo "windman: could not locate Windsurf executable at: $exe" >&2
+echo "windman: $TARGET is not in the current version ($ROOT)" >&2
 exit 127
 "#,
-        current = current_str
+        marker = SHIM_MARKER,
+        current = current_symlink.display(),
+        rel = rel
     );
+    write_script(shim_path, &script)
+}
 
+fn write_script(shim_path: &Path, script: &str) -> Result<()> {
     if let Some(dir) = shim_path.parent() {
         fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
     }
-    let mut f =
-        fs::File::create(shim_path).with_context(|| format!("creating {}", shim_path.display()))?;
-    f.write_all(script.as_bytes())
-        .with_context(|| format!("writing {}", shim_path.display()))?;
-    drop(f);
+    atomic_write_executable(shim_path, script.as_bytes())
+}
+
+/// Start `cmd` in a session of its own (`setsid`), stdin from /dev/null and
+/// stdout/stderr into `log` (truncated), without waiting for it: closing the
+/// terminal or exiting windman leaves it running. Returns its PID.
+pub fn spawn_detached(cmd: &mut std::process::Command, log: &Path) -> Result<u32> {
+    use std::os::unix::process::CommandExt;
+    if let Some(dir) = log.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    }
+    let out = fs::File::create(log).with_context(|| format!("creating {}", log.display()))?;
+    cmd.stdin(std::process::Stdio::null())
+        .stdout(out.try_clone()?)
+        .stderr(out);
+    // SAFETY: setsid is async-signal-safe and touches no memory of the parent
+    unsafe {
+        cmd.pre_exec(|| {
+            if libc::setsid() == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let child = cmd
+        .spawn()
+        .with_context(|| format!("starting {:?}", cmd.get_program()))?;
+    Ok(child.id())
+}
+
+/// The processes whose executable (`/proc/<pid>/exe`) lies inside `dir`, with
+/// that executable, lowest PID first. Those of other users cannot be read and
+/// are left out, as is windman itself.
+pub fn processes_running_from(dir: &Path) -> Vec<(u32, PathBuf)> {
+    let (Ok(dir), Ok(procs)) = (fs::canonicalize(dir), fs::read_dir("/proc")) else {
+        return Vec::new();
+    };
+    let own = std::process::id();
+    let mut found: Vec<(u32, PathBuf)> = procs
+        .flatten()
+        .filter_map(|e| {
+            let pid: u32 = e.file_name().to_str()?.parse().ok()?;
+            let exe = fs::read_link(e.path().join("exe")).ok()?;
+            // an executable deleted since it started (its version pruned)
+            let exe = match exe.to_str().and_then(|s| s.strip_suffix(" (deleted)")) {
+                Some(s) => PathBuf::from(s),
+                None => exe,
+            };
+            (pid != own && exe.starts_with(&dir)).then_some((pid, exe))
+        })
+        .collect();
+    found.sort();
+    found
+}
+
+/// Write `contents` to a temp file next to `path`, make it 0755, then rename
+/// it over `path`: a launcher started meanwhile sees the old file or the new
+/// one, never a missing or half-written one (as `atomic_symlink_switch`).
+pub fn atomic_write_executable(path: &Path, contents: &[u8]) -> Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let prefix = format!(".{}.tmp-", name);
+    sweep_stale_temps(dir, &prefix, fs::FileType::is_file, STALE_TMP_AGE);
+
+    // a name taken despite the unique part: once more with a fresh one
+    let create = |tmp: &Path| {
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(tmp)
+    };
+    let mut tmp = dir.join(unique_temp_name(&prefix));
+    let created = match create(&tmp) {
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            tmp = dir.join(unique_temp_name(&prefix));
+            create(&tmp)
+        }
+        r => r,
+    };
+    let mut f = created.with_context(|| format!("creating {}", tmp.display()))?;
+    let mut write = || -> Result<()> {
+        f.write_all(contents)
+            .and_then(|_| f.sync_all())
+            .with_context(|| format!("writing {}", tmp.display()))?;
+        fs::set_permissions(&tmp, fs::Permissions::from_mode(0o755))?;
+        fs::rename(&tmp, path)
+            .with_context(|| format!("renaming {} to {}", tmp.display(), path.display()))
+    };
+    write().inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+static ASSUME_YES: AtomicBool = AtomicBool::new(false);
+
+/// `-y/--assume-yes`: every `confirm` answers yes without asking.
+pub fn set_assume_yes(yes: bool) {
+    ASSUME_YES.store(yes, Ordering::SeqCst);
+}
+
+/// A question needed an answer but nobody is there to give one.
+#[derive(Debug, thiserror::Error)]
+#[error("\"{question}\" needs an answer but stdin is not a terminal.\nPass -y/--assume-yes to answer yes.")]
+pub struct NotInteractive {
+    pub question: String,
+}
 
-    let mut perms = fs::metadata(shim_path)?.permissions();
-    perms.set_mode(0o755);
-    fs::set_permissions(shim_path, perms)?;
+/// Ask a yes/no question on the terminal; every prompt goes through here.
+/// With `--assume-yes` the answer is yes; without it and without a terminal
+/// (scripts, timers) the question fails with `NotInteractive`.
+pub fn confirm(question: &str) -> Result<bool> {
+    use std::io::{BufRead, IsTerminal};
+    if ASSUME_YES.load(Ordering::SeqCst) {
+        eprintln!("{} [y/N] y (--assume-yes)", question);
+        return Ok(true);
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err(NotInteractive {
+            question: question.to_string(),
+        }
+        .into());
+    }
+    eprint!("{} [y/N] ", question);
+    let _ = std::io::stderr().flush();
+    let mut answer = String::new();
+    if std::io::stdin().lock().read_line(&mut answer).is_err() {
+        return Ok(false);
+    }
+    Ok(matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
+
+/// Ask for a value on the terminal; an empty answer keeps `default`. Like
+/// `confirm`: with `--assume-yes` the default is taken, without a terminal the
+/// question fails with `NotInteractive`.
+pub fn ask(question: &str, default: &str) -> Result<String> {
+    use std::io::{BufRead, IsTerminal};
+    if ASSUME_YES.load(Ordering::SeqCst) {
+        eprintln!("{} [{}] {} (--assume-yes)", question, default, default);
+        return Ok(default.to_string());
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err(NotInteractive {
+            question: question.to_string(),
+        }
+        .into());
+    }
+    eprint!("{} [{}] ", question, default);
+    let _ = std::io::stderr().flush();
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    Ok(match answer.trim() {
+        "" => default.to_string(),
+        a => a.to_string(),
+    })
+}
+
+/// Move a directory tree. Tries `rename` first; if `src` and `dst` are on
+/// different filesystems (EXDEV), falls back to `move_dir_by_copy`.
+pub fn move_dir_robust(src: &Path, dst: &Path) -> Result<()> {
+    move_dir_with(src, dst, |s, d| fs::rename(s, d))
+}
+
+/// `move_dir_robust` trying `rename` first (tests stand in a failing one).
+pub(crate) fn move_dir_with(
+    src: &Path,
+    dst: &Path,
+    rename: impl Fn(&Path, &Path) -> std::io::Result<()>,
+) -> Result<()> {
+    match rename(src, dst) {
+        Ok(()) => Ok(()),
+        Err(e) if e.raw_os_error() == Some(libc::EXDEV) => move_dir_by_copy(src, dst),
+        Err(e) => Err(e).with_context(|| format!("moving {} -> {}", src.display(), dst.display())),
+    }
+}
+
+/// Copy `src` to `dst` preserving permissions, file mtimes and symlinks (not
+/// followed), fsync every file, then remove `src`. `dst` must not exist.
+pub fn move_dir_by_copy(src: &Path, dst: &Path) -> Result<()> {
+    // a symlinked "dir" moves as the link, its target stays where it is
+    if fs::symlink_metadata(src)
+        .with_context(|| format!("reading {}", src.display()))?
+        .file_type()
+        .is_symlink()
+    {
+        let target = fs::read_link(src)?;
+        symlink(&target, dst).with_context(|| format!("creating link {}", dst.display()))?;
+        return fs::remove_file(src).with_context(|| format!("removing {}", src.display()));
+    }
+    let mut dirs = Vec::new();
+    for entry in walkdir::WalkDir::new(src).follow_links(false) {
+        let entry = entry.with_context(|| format!("walking {}", src.display()))?;
+        let rel = entry.path().strip_prefix(src)?;
+        let to = dst.join(rel);
+        let ft = entry.file_type();
+        if ft.is_symlink() {
+            let target = fs::read_link(entry.path())?;
+            symlink(&target, &to).with_context(|| format!("creating link {}", to.display()))?;
+        } else if ft.is_dir() {
+            fs::create_dir(&to).with_context(|| format!("creating {}", to.display()))?;
+            dirs.push((to, entry.metadata()?.permissions()));
+        } else {
+            fs::copy(entry.path(), &to).with_context(|| {
+                format!("copying {} -> {}", entry.path().display(), to.display())
+            })?;
+            let copied = fs::File::open(&to)?;
+            copied
+                .set_modified(entry.metadata()?.modified()?)
+                .with_context(|| format!("setting the mtime of {}", to.display()))?;
+            copied
+                .sync_all()
+                .with_context(|| format!("syncing {}", to.display()))?;
+        }
+    }
+    // Directory modes last, deepest first, so read-only dirs don't block their own copy
+    for (dir, perms) in dirs.into_iter().rev() {
+        fs::set_permissions(&dir, perms)?;
+    }
+    remove_dir_within(src.parent().unwrap_or(src), src)
+}
+
+/// Recursively remove `path`, which must really live inside `root`. A symlink
+/// (or file) is unlinked, never followed; a directory whose canonical path is
+/// not strictly below the canonical `root` (reached through a symlinked
+/// component) is refused. A missing `path` is fine.
+pub fn remove_dir_within(root: &Path, path: &Path) -> Result<()> {
+    let meta = match fs::symlink_metadata(path) {
+        Ok(m) => m,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+    };
+    if !meta.is_dir() {
+        return fs::remove_file(path).with_context(|| format!("removing {}", path.display()));
+    }
+    let real_root =
+        fs::canonicalize(root).with_context(|| format!("resolving {}", root.display()))?;
+    let real = fs::canonicalize(path).with_context(|| format!("resolving {}", path.display()))?;
+    if real == real_root || !real.starts_with(&real_root) {
+        anyhow::bail!(
+            "refusing to remove {}: it resolves to {}, outside {}",
+            path.display(),
+            real.display(),
+            real_root.display()
+        );
+    }
+    fs::remove_dir_all(path).with_context(|| format!("removing {}", path.display()))
+}
+
+/// Remove everything in `dir` (each entry through `remove_dir_within`), then
+/// `dir` itself, or only the link when `dir` is a symlink.
+pub fn clear_and_remove_dir(dir: &Path) -> Result<()> {
+    for ent in fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))? {
+        remove_dir_within(dir, &ent?.path())?;
+    }
+    if fs::symlink_metadata(dir)?.file_type().is_symlink() {
+        fs::remove_file(dir)
+    } else {
+        fs::remove_dir(dir)
+    }
+    .with_context(|| format!("removing {}", dir.display()))
+}
+
+/// Check that `dir` (or its nearest existing ancestor, if it does not exist yet)
+/// is writable by probing with a temporary file.
+pub fn ensure_writable(dir: &Path) -> Result<()> {
+    let mut probe = dir;
+    while !probe.exists() {
+        match probe.parent() {
+            Some(p) => probe = p,
+            None => break,
+        }
+    }
+    tempfile::Builder::new()
+        .prefix(".windman-probe")
+        .tempfile_in(probe)
+        .map(|_| ())
+        .with_context(|| format!("no write permission to {}", probe.display()))
+}
+
+/// Canonical path a symlink points to. Relative targets (`1.12.11`, `./1.12.11`,
+/// `../1.12.11`) are resolved against the link's own directory, not the cwd.
+/// None if `link` is not a symlink or its target does not exist.
+pub fn resolve_symlink(link: &Path) -> Option<PathBuf> {
+    fs::read_link(link).ok()?;
+    fs::canonicalize(link).ok()
+}
+
+/// `s` with its `%XX` escapes decoded (invalid UTF-8 replaced); a `%` not
+/// followed by two hex digits is kept as is.
+pub fn percent_decode(s: &str) -> String {
+    let b = s.as_bytes();
+    let mut out = Vec::with_capacity(b.len());
+    let mut i = 0;
+    while i < b.len() {
+        let hex = b
+            .get(i + 1..i + 3)
+            .filter(|h| h.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|h| std::str::from_utf8(h).ok());
+        match (b[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (c, _) => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// True for entries of the versions dir that are real versions:
+/// not the `current` link and not hidden bookkeeping (.staging-*, .trash).
+pub fn is_version_dir_name(name: &str) -> bool {
+    name != "current" && !name.starts_with('.')
+}
+
+/// True for names usable as a version dir: a version-dir name made only of
+/// semver characters (`1.12.11`, `1.13.0-nightly.3`, `1.13.0+build.7`).
+pub fn is_valid_version_name(name: &str) -> bool {
+    !name.is_empty()
+        && is_version_dir_name(name)
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+' | '_'))
+}
+
+/// Ordering of version dir names: semver precedence (so `1.13.0-beta` < `1.13.0`),
+/// names that are not semver (timestamps) below every semver one, then by name.
+pub fn compare_version_names(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+    match (semver::Version::parse(a), semver::Version::parse(b)) {
+        (Ok(av), Ok(bv)) => av.cmp(&bv),
+        (Ok(_), Err(_)) => Ordering::Greater,
+        (Err(_), Ok(_)) => Ordering::Less,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+/// Parse a short duration like "90s", "30m", "12h", "7d" or "2w".
+pub fn parse_duration(s: &str) -> Result<std::time::Duration> {
+    let s = s.trim();
+    let (num, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let n: u64 = num
+        .parse()
+        .with_context(|| format!("invalid duration '{}' (expected e.g. 7d, 12h, 30m)", s))?;
+    let unit_secs: u64 = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        "w" => 7 * 86400,
+        _ => anyhow::bail!("invalid duration unit in '{}' (use s, m, h, d or w)", s),
+    };
+    let secs = n
+        .checked_mul(unit_secs)
+        .with_context(|| format!("duration too large: '{}'", s))?;
+    Ok(std::time::Duration::from_secs(secs))
+}
+
+/// Parse a size like "3G", "500M" or "1.5T" into bytes. Units are binary
+/// (K = 1024) and case-insensitive, a trailing "B" or "iB" is allowed and a bare
+/// number is bytes.
+pub fn parse_size(s: &str) -> Result<u64> {
+    let s = s.trim();
+    let invalid = || anyhow::anyhow!("invalid size '{}' (expected e.g. 500M, 3G, 1.5T)", s);
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let n: f64 = num.parse().map_err(|_| invalid())?;
+    let unit = unit.trim_start().to_ascii_uppercase();
+    let unit = unit
+        .strip_suffix("IB")
+        .or_else(|| unit.strip_suffix('B'))
+        .unwrap_or(&unit);
+    let shift = match unit {
+        "" => 0,
+        "K" => 10,
+        "M" => 20,
+        "G" => 30,
+        "T" => 40,
+        _ => anyhow::bail!("invalid size unit in '{}' (use K, M, G or T)", s),
+    };
+    let bytes = n * (1u64 << shift) as f64;
+    if !bytes.is_finite() || bytes >= u64::MAX as f64 {
+        return Err(invalid());
+    }
+    Ok(bytes.round() as u64)
+}
+
+/// Total size in bytes of regular files under `dir` (symlinks are not followed).
+pub fn dir_size(dir: &Path) -> u64 {
+    dir_usage(dir).1
+}
+
+/// How many regular files there are under `dir` and their total size in bytes
+/// (symlinks are not followed).
+pub fn dir_usage(dir: &Path) -> (u64, u64) {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .fold((0, 0), |(files, bytes), m| (files + 1, bytes + m.len()))
+}
+
+/// While alive, whatever the process prints to stdout goes to stderr, so a
+/// command can keep stdout for one JSON document (`update --json`).
+pub struct StdoutToStderr {
+    saved: libc::c_int,
+}
+
+impl StdoutToStderr {
+    pub fn new() -> Result<Self> {
+        std::io::stdout().flush()?;
+        // SAFETY: plain descriptor calls; the saved copy is restored on drop
+        let saved = unsafe { libc::dup(libc::STDOUT_FILENO) };
+        if saved < 0 {
+            return Err(std::io::Error::last_os_error()).context("redirecting stdout");
+        }
+        let guard = Self { saved };
+        if unsafe { libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) } < 0 {
+            return Err(std::io::Error::last_os_error()).context("redirecting stdout");
+        }
+        Ok(guard)
+    }
+}
+
+impl Drop for StdoutToStderr {
+    fn drop(&mut self) {
+        let _ = std::io::stdout().flush();
+        unsafe {
+            libc::dup2(self.saved, libc::STDOUT_FILENO);
+            libc::close(self.saved);
+        }
+    }
+}
+
+/// fsync `path`, a file or a directory (its entries).
+pub fn fsync_path(path: &Path) -> Result<()> {
+    fs::File::open(path)
+        .and_then(|f| f.sync_all())
+        .with_context(|| format!("syncing {}", path.display()))
+}
+
+/// Flush everything written to the filesystem holding `path` (syncfs(2)):
+/// one call where an fsync per file would take as many round trips.
+pub fn syncfs(path: &Path) -> Result<()> {
+    use std::os::fd::AsRawFd;
+    let f = fs::File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    // SAFETY: a plain call on a descriptor that stays open across it
+    if unsafe { libc::syncfs(f.as_raw_fd()) } != 0 {
+        return Err(std::io::Error::last_os_error())
+            .with_context(|| format!("syncing the filesystem of {}", path.display()));
+    }
     Ok(())
 }
 
+/// Bytes an unprivileged user can still write on the filesystem holding
+/// `path` (that of its nearest existing ancestor while it does not exist yet).
+pub fn fs_available_bytes(path: &Path) -> std::io::Result<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let c = std::ffi::CString::new(existing_ancestor(path).as_os_str().as_bytes())
+        .map_err(std::io::Error::other)?;
+    // SAFETY: statvfs only writes into `st`, a plain C struct
+    let mut st: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c.as_ptr(), &mut st) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(st.f_bavail as u64 * st.f_frsize as u64)
+}
+
+/// This machine's host name (gethostname(2)); None if it cannot be read.
+pub fn hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    // SAFETY: the length passed is that of `buf`
+    if unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) } != 0 {
+        return None;
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    let name = String::from_utf8_lossy(&buf[..len]).into_owned();
+    (!name.is_empty()).then_some(name)
+}
+
+/// Where the filesystem holding `path` is mounted: its highest existing
+/// ancestor on the same device ("/home" for "~/.cache/windman").
+pub fn mount_point(path: &Path) -> PathBuf {
+    use std::os::unix::fs::MetadataExt;
+    let start = existing_ancestor(path);
+    let start = fs::canonicalize(start).unwrap_or_else(|_| start.to_path_buf());
+    let Ok(dev) = fs::metadata(&start).map(|m| m.dev()) else {
+        return start;
+    };
+    start
+        .ancestors()
+        .take_while(|a| fs::metadata(a).is_ok_and(|m| m.dev() == dev))
+        .last()
+        .unwrap_or(&start)
+        .to_path_buf()
+}
+
+fn existing_ancestor(path: &Path) -> &Path {
+    path.ancestors()
+        .find(|a| a.exists())
+        .unwrap_or(Path::new("."))
+}
+
 /// Best-effort extraction of a version-like name from folder path
 #[cfg(test)]
 pub fn guess_version_from_folder(path: &Path) -> Option<String> {
@@ -118,6 +947,524 @@ mod tests {
         assert_eq!(fs::read_link(&link).unwrap(), v2);
     }
 
+    #[test]
+    fn unique_temp_names_separate_pid_nanos_and_counter() {
+        let a = unique_temp_name(".part.");
+        let b = unique_temp_name(".part.");
+        assert_ne!(a, b);
+        let rest = a
+            .strip_prefix(&format!(".part.{}-", std::process::id()))
+            .unwrap();
+        assert_eq!(rest.split('-').count(), 2, "{}", a);
+        assert!(rest.split('-').all(|n| n.parse::<u64>().is_ok()), "{}", a);
+    }
+
+    #[test]
+    fn symlink_switch_retries_once_on_a_taken_temp_name() {
+        let td = tempdir().unwrap();
+        let v1 = td.path().join("1.0.0");
+        fs::create_dir_all(&v1).unwrap();
+        let link = td.path().join("current");
+        // left by a crashed run whose pid came back
+        symlink(&v1, td.path().join(".tmp-42")).unwrap();
+
+        let mut names = [".tmp-42", ".tmp-43"].into_iter().map(String::from);
+        symlink_switch_with(&v1, &link, || names.next().unwrap()).unwrap();
+        assert_eq!(fs::read_link(&link).unwrap(), v1);
+        assert!(!td.path().join(".tmp-43").exists());
+
+        // taken twice: the error is reported, the link left alone
+        symlink(&v1, td.path().join(".tmp-43")).unwrap();
+        let v2 = td.path().join("1.0.1");
+        fs::create_dir_all(&v2).unwrap();
+        let mut names = [".tmp-42", ".tmp-43"].into_iter().map(String::from);
+        let err = symlink_switch_with(&v2, &link, || names.next().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("creating temp symlink"), "{}", err);
+        assert_eq!(fs::read_link(&link).unwrap(), v1);
+    }
+
+    #[test]
+    fn symlink_switch_refuses_a_missing_target() {
+        let td = tempdir().unwrap();
+        let v1 = td.path().join("1.0.0");
+        fs::create_dir_all(&v1).unwrap();
+        let link = td.path().join("current");
+        atomic_symlink_switch(&v1, &link).unwrap();
+
+        let err = atomic_symlink_switch(&td.path().join("9.9.9"), &link).unwrap_err();
+        assert!(err.downcast_ref::<MissingTarget>().is_some(), "{}", err);
+        assert_eq!(fs::read_link(&link).unwrap(), v1);
+        // relative to the link's dir
+        atomic_symlink_switch(Path::new("1.0.0"), &link).unwrap();
+        assert!(atomic_symlink_switch(Path::new("nope"), &link).is_err());
+    }
+
+    #[test]
+    fn atomic_write_sweeps_stale_temp_files_of_its_name() {
+        let td = tempdir().unwrap();
+        let path = td.path().join("windsurf");
+        let stale = td.path().join(".windsurf.tmp-42");
+        let fresh = td.path().join(".windsurf.tmp-43");
+        for p in [&stale, &fresh] {
+            fs::write(p, "x").unwrap();
+        }
+        fs::File::options()
+            .write(true)
+            .open(&stale)
+            .unwrap()
+            .set_modified(std::time::SystemTime::now() - STALE_TMP_AGE)
+            .unwrap();
+
+        atomic_write_executable(&path, b"#!/bin/sh\n").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"#!/bin/sh\n");
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o755
+        );
+        assert!(!stale.exists());
+        // maybe another write in progress
+        assert!(fresh.exists());
+        assert_eq!(fs::read_dir(td.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn stale_temp_links_are_swept_and_other_files_kept() {
+        let td = tempdir().unwrap();
+        symlink("gone", td.path().join(".tmp-7")).unwrap();
+        fs::write(td.path().join(".tmp-notes"), "mine").unwrap();
+        symlink("elsewhere", td.path().join("keep")).unwrap();
+
+        // a switch in progress elsewhere: too recent to touch
+        sweep_stale_temps(td.path(), ".tmp-", fs::FileType::is_symlink, STALE_TMP_AGE);
+        assert!(fs::symlink_metadata(td.path().join(".tmp-7")).is_ok());
+
+        sweep_stale_temps(
+            td.path(),
+            ".tmp-",
+            fs::FileType::is_symlink,
+            std::time::Duration::ZERO,
+        );
+        assert!(fs::symlink_metadata(td.path().join(".tmp-7")).is_err());
+        assert!(td.path().join(".tmp-notes").is_file());
+        assert!(fs::symlink_metadata(td.path().join("keep")).is_ok());
+    }
+
+    #[test]
+    fn resolve_symlink_handles_relative_targets() {
+        let td = tempdir().unwrap();
+        let base = fs::canonicalize(td.path()).unwrap();
+        let v = base.join("1.0.0");
+        fs::create_dir_all(&v).unwrap();
+        for (i, target) in ["1.0.0", "./1.0.0", "sub/../1.0.0"].iter().enumerate() {
+            fs::create_dir_all(base.join("sub")).unwrap();
+            let link = base.join(format!("link{}", i));
+            std::os::unix::fs::symlink(target, &link).unwrap();
+            assert_eq!(
+                resolve_symlink(&link).as_deref(),
+                Some(v.as_path()),
+                "{}",
+                target
+            );
+        }
+        assert_eq!(resolve_symlink(&v), None);
+    }
+
+    fn make_exe(p: &Path) {
+        fs::create_dir_all(p.parent().unwrap()).unwrap();
+        fs::write(p, b"#!/bin/sh\n").unwrap();
+        fs::set_permissions(p, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn find_windsurf_executable_handles_each_layout() {
+        for rel in WINDSURF_EXE_CANDIDATES {
+            let td = tempdir().unwrap();
+            make_exe(&td.path().join(rel));
+            assert_eq!(
+                find_windsurf_executable(td.path()),
+                Some(td.path().join(rel)),
+                "layout {}",
+                rel
+            );
+        }
+    }
+
+    #[test]
+    fn find_windsurf_executable_prefers_bin_and_skips_non_executables() {
+        let td = tempdir().unwrap();
+        make_exe(&td.path().join("Windsurf/windsurf"));
+        make_exe(&td.path().join("Windsurf/bin/windsurf"));
+        assert_eq!(
+            find_windsurf_executable(td.path()),
+            Some(td.path().join("Windsurf/bin/windsurf"))
+        );
+
+        let td = tempdir().unwrap();
+        fs::create_dir_all(td.path().join("Windsurf/bin")).unwrap();
+        fs::write(td.path().join("Windsurf/bin/windsurf"), b"no x bit").unwrap();
+        assert_eq!(find_windsurf_executable(td.path()), None);
+    }
+
+    /// Minimal 64-bit ELF header up to e_machine.
+    fn elf_header(little_endian: bool, machine: u16) -> Vec<u8> {
+        let mut h = vec![
+            0x7f,
+            b'E',
+            b'L',
+            b'F',
+            2,
+            if little_endian { 1 } else { 2 },
+            1,
+        ];
+        h.resize(18, 0);
+        if little_endian {
+            h.extend_from_slice(&machine.to_le_bytes());
+        } else {
+            h.extend_from_slice(&machine.to_be_bytes());
+        }
+        h
+    }
+
+    #[test]
+    fn elf_arch_from_header_reads_machine() {
+        assert_eq!(
+            elf_arch_from_header(&elf_header(true, 0x3E)),
+            Some(ElfArch::X86_64)
+        );
+        assert_eq!(
+            elf_arch_from_header(&elf_header(true, 0xB7)),
+            Some(ElfArch::Aarch64)
+        );
+        assert_eq!(
+            elf_arch_from_header(&elf_header(false, 0x28)),
+            Some(ElfArch::Arm)
+        );
+        assert_eq!(elf_arch_from_header(b"#!/usr/bin/env bash\nexec x\n"), None);
+        assert_eq!(elf_arch_from_header(b"\x7fELF"), None);
+    }
+
+    #[test]
+    fn check_host_arch_rejects_foreign_elf() {
+        let td = tempdir().unwrap();
+        let foreign = if ElfArch::host() == ElfArch::X86_64 {
+            0xB7
+        } else {
+            0x3E
+        };
+        let exe = td.path().join("Windsurf/windsurf");
+        fs::create_dir_all(exe.parent().unwrap()).unwrap();
+        fs::write(&exe, elf_header(true, foreign)).unwrap();
+
+        let err = check_host_arch(td.path()).unwrap_err().to_string();
+        assert!(err.contains("archive is for"), "{}", err);
+        assert!(err.contains(&ElfArch::host().to_string()), "{}", err);
+    }
+
+    #[test]
+    fn only_shims_windman_wrote_are_recognized() {
+        let td = tempdir().unwrap();
+        let shim = td.path().join("windsurf");
+        assert!(!is_windman_shim(&shim));
+        write_shim(&shim, &td.path().join("current"), None).unwrap();
+        assert!(is_windman_shim(&shim));
+        // a shim from before the marker
+        fs::write(&shim, "#!/bin/sh\nCURRENT_LINK=\"/opt/current\"\n").unwrap();
+        assert!(is_windman_shim(&shim));
+        fs::write(
+            &shim,
+            "#!/bin/sh\nexec /usr/share/windsurf/bin/windsurf \"$@\"\n",
+        )
+        .unwrap();
+        assert!(!is_windman_shim(&shim));
+    }
+
+    #[test]
+    fn shim_points_to_detects_other_current_link() {
+        let td = tempdir().unwrap();
+        let shim = td.path().join("bin/windsurf");
+        assert!(!shim_points_to(&shim, &td.path().join("current")));
+
+        write_shim(&shim, &td.path().join("old/current"), None).unwrap();
+        assert!(!shim_points_to(&shim, &td.path().join("current")));
+        assert!(shim_points_to(&shim, &td.path().join("old/current")));
+
+        // a prefix change leaves the shim on the old `current`
+        assert_eq!(
+            stale_shim_target(&shim, &td.path().join("current")),
+            Some(td.path().join("old/current"))
+        );
+        assert_eq!(
+            stale_shim_target(&shim, &td.path().join("old/current")),
+            None
+        );
+        fs::write(&shim, "#!/bin/sh\nexec windsurf \"$@\"\n").unwrap();
+        assert_eq!(stale_shim_target(&shim, &td.path().join("current")), None);
+    }
+
+    #[test]
+    fn ensure_writable_probes_nearest_existing_ancestor() {
+        let td = tempdir().unwrap();
+        ensure_writable(&td.path().join("not/yet/created")).unwrap();
+        assert!(!td.path().join("not").exists());
+
+        let ro = td.path().join("ro");
+        fs::create_dir_all(&ro).unwrap();
+        fs::set_permissions(&ro, fs::Permissions::from_mode(0o555)).unwrap();
+        // root ignores permission bits; only assert when the probe can fail
+        if fs::write(ro.join("x"), b"").is_err() {
+            assert!(ensure_writable(&ro.join("sub")).is_err());
+        }
+    }
+
+    #[test]
+    fn move_dir_by_copy_preserves_modes_and_symlinks() {
+        let td = tempdir().unwrap();
+        let src = td.path().join("src");
+        make_exe(&src.join("Windsurf/bin/windsurf"));
+        fs::write(src.join("Windsurf/data"), b"payload").unwrap();
+        fs::set_permissions(src.join("Windsurf/data"), fs::Permissions::from_mode(0o640)).unwrap();
+        let old = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        fs::File::open(src.join("Windsurf/data"))
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        symlink("bin/windsurf", src.join("Windsurf/link")).unwrap();
+
+        let dst = td.path().join("dst");
+        move_dir_by_copy(&src, &dst).unwrap();
+
+        assert!(!src.exists());
+        assert_eq!(fs::read(dst.join("Windsurf/data")).unwrap(), b"payload");
+        let mode = |p: &Path| fs::metadata(p).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&dst.join("Windsurf/data")), 0o640);
+        assert_eq!(mode(&dst.join("Windsurf/bin/windsurf")), 0o755);
+        let mtime = fs::metadata(dst.join("Windsurf/data")).unwrap().modified();
+        assert_eq!(mtime.unwrap(), old);
+        assert_eq!(
+            fs::read_link(dst.join("Windsurf/link")).unwrap(),
+            Path::new("bin/windsurf")
+        );
+    }
+
+    #[test]
+    fn remove_dir_within_never_leaves_root() {
+        let td = tempdir().unwrap();
+        let (root, outside) = (td.path().join("opt"), td.path().join("home"));
+        fs::create_dir_all(outside.join("sub")).unwrap();
+        fs::write(outside.join("sub/f"), b"x").unwrap();
+        fs::create_dir_all(root.join("1.0.0/bin")).unwrap();
+        symlink(&outside, root.join("2.0.0")).unwrap();
+
+        // a symlinked entry is unlinked
+        remove_dir_within(&root, &root.join("2.0.0")).unwrap();
+        assert!(outside.join("sub/f").is_file());
+        // a dir reached through a symlinked component is refused
+        symlink(&outside, root.join("link")).unwrap();
+        let err = remove_dir_within(&root, &root.join("link/sub")).unwrap_err();
+        assert!(err.to_string().contains("refusing"), "{}", err);
+        assert!(outside.join("sub/f").is_file());
+        // and so is the root itself
+        assert!(remove_dir_within(&root, &root).is_err());
+
+        remove_dir_within(&root, &root.join("1.0.0")).unwrap();
+        assert!(!root.join("1.0.0").exists());
+        remove_dir_within(&root, &root.join("missing")).unwrap();
+
+        clear_and_remove_dir(&root).unwrap();
+        assert!(!root.exists());
+        assert!(outside.join("sub/f").is_file());
+    }
+
+    #[test]
+    fn move_dir_robust_renames_on_same_fs() {
+        let td = tempdir().unwrap();
+        let src = td.path().join("a");
+        fs::create_dir_all(src.join("x")).unwrap();
+        move_dir_robust(&src, &td.path().join("b")).unwrap();
+        assert!(td.path().join("b/x").is_dir());
+        assert!(!src.exists());
+    }
+
+    #[test]
+    fn version_names_sort_with_prereleases_below_releases() {
+        let mut names = vec![
+            "1.13.0",
+            "20250101-120000",
+            "1.13.0-beta.2",
+            "1.12.11",
+            "1.13.0-beta.10",
+            "1.13.0-alpha",
+        ];
+        names.sort_by(|a, b| compare_version_names(a, b));
+        assert_eq!(
+            names,
+            [
+                "20250101-120000",
+                "1.12.11",
+                "1.13.0-alpha",
+                "1.13.0-beta.2",
+                "1.13.0-beta.10",
+                "1.13.0",
+            ]
+        );
+        assert!(is_valid_version_name("1.13.0-nightly.3+build.7"));
+        for bad in ["", "current", ".staging-1", "1.0/evil", "1.0 beta"] {
+            assert!(!is_valid_version_name(bad), "accepted {:?}", bad);
+        }
+    }
+
+    #[test]
+    fn finds_processes_running_from_a_dir() {
+        let td = tempdir().unwrap();
+        let bin = td.path().join("1.12.9/Windsurf");
+        fs::create_dir_all(&bin).unwrap();
+        let exe = bin.join("windsurf");
+        fs::copy("/bin/sleep", &exe).unwrap();
+        let mut child = std::process::Command::new(&exe).arg("30").spawn().unwrap();
+
+        let found = processes_running_from(td.path());
+        let elsewhere = processes_running_from(&td.path().join("1.13.0"));
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        let exe = fs::canonicalize(&exe).unwrap();
+        assert_eq!(found, vec![(child.id(), exe)]);
+        assert!(elsewhere.is_empty());
+        assert!(processes_running_from(td.path()).is_empty());
+    }
+
+    #[test]
+    fn parse_duration_units() {
+        assert_eq!(parse_duration("7d").unwrap().as_secs(), 7 * 86400);
+        assert_eq!(parse_duration("30m").unwrap().as_secs(), 1800);
+        assert_eq!(parse_duration("90s").unwrap().as_secs(), 90);
+        assert_eq!(parse_duration("2h").unwrap().as_secs(), 7200);
+        assert_eq!(parse_duration(" 15m ").unwrap().as_secs(), 900);
+        assert!(parse_duration("7y").is_err());
+        assert!(parse_duration("d").is_err());
+        let e = parse_duration("99999999999999999d").unwrap_err();
+        assert_eq!(e.to_string(), "duration too large: '99999999999999999d'");
+        assert_eq!(
+            parse_duration("18446744073709551615s").unwrap().as_secs(),
+            u64::MAX
+        );
+    }
+
+    #[test]
+    fn parse_size_units() {
+        assert_eq!(parse_size("3G").unwrap(), 3 << 30);
+        assert_eq!(parse_size("500m").unwrap(), 500 << 20);
+        assert_eq!(parse_size("1.5T").unwrap(), 3 << 39);
+        assert_eq!(parse_size(" 64 KiB ").unwrap(), 64 << 10);
+        assert_eq!(parse_size("2GB").unwrap(), 2 << 30);
+        assert_eq!(parse_size("1234").unwrap(), 1234);
+        assert!(parse_size("3X").is_err());
+        assert!(parse_size("G").is_err());
+        assert!(parse_size("-1G").is_err());
+    }
+
+    #[test]
+    fn free_space_and_mount_point_of_paths_not_created_yet() {
+        let td = tempdir().unwrap();
+        let missing = td.path().join("a/b/c");
+        assert!(fs_available_bytes(&missing).unwrap() > 0);
+        let mount = mount_point(&missing);
+        let real = fs::canonicalize(td.path()).unwrap();
+        assert!(real.starts_with(&mount), "{}", mount.display());
+        assert_eq!(mount_point(Path::new("/")), Path::new("/"));
+    }
+
+    #[test]
+    fn shim_notice_runs_before_launch_unless_disabled() {
+        let td = tempdir().unwrap();
+        let root = td.path().join("1.0.0");
+        fs::create_dir_all(root.join("Windsurf/bin")).unwrap();
+        let exe = root.join("Windsurf/bin/windsurf");
+        fs::write(&exe, "#!/bin/sh\necho launched\n").unwrap();
+        fs::set_permissions(&exe, fs::Permissions::from_mode(0o755)).unwrap();
+        symlink(&root, td.path().join("current")).unwrap();
+        // stands in for windman; the path has a quote to exercise quoting
+        let windman = td.path().join("wind'man");
+        fs::write(&windman, "#!/bin/sh\necho \"notice $*\" >&2\n").unwrap();
+        fs::set_permissions(&windman, fs::Permissions::from_mode(0o755)).unwrap();
+        let shim = td.path().join("bin/windsurf");
+        let notice = ShimNotice {
+            exe: windman.clone(),
+            args: vec!["--profile".into(), "a b".into()],
+        };
+        write_shim(&shim, &td.path().join("current"), Some(&notice)).unwrap();
+        assert!(shim_has_notice(&shim));
+
+        let run = |no_notice: Option<&str>| {
+            let mut cmd = std::process::Command::new(&shim);
+            cmd.env_remove("WINDMAN_NO_NOTICE");
+            if let Some(v) = no_notice {
+                cmd.env("WINDMAN_NO_NOTICE", v);
+            }
+            let out = cmd.output().unwrap();
+            assert!(out.status.success());
+            assert_eq!(String::from_utf8_lossy(&out.stdout), "launched\n");
+            String::from_utf8_lossy(&out.stderr).to_string()
+        };
+        assert_eq!(run(None), "notice --profile a b notify-check\n");
+        assert_eq!(run(Some("1")), "");
+
+        // windman gone: the launch still works
+        fs::remove_file(&windman).unwrap();
+        assert_eq!(run(None), "");
+    }
+
+    #[test]
+    fn arch_shim_runs_the_current_of_the_machine_it_is_on() {
+        let td = tempdir().unwrap();
+        let machine = crate::paths::machine();
+        for arch in [machine.as_str(), "someotherarch"] {
+            let root = td.path().join(arch).join("1.0.0");
+            fs::create_dir_all(root.join("Windsurf/bin")).unwrap();
+            let exe = root.join("Windsurf/bin/windsurf");
+            fs::write(&exe, format!("#!/bin/sh\necho {}\n", arch)).unwrap();
+            fs::set_permissions(&exe, fs::Permissions::from_mode(0o755)).unwrap();
+            symlink(&root, td.path().join(format!("current-{}", arch))).unwrap();
+        }
+        let shim = td.path().join("bin/windsurf");
+        let link = td.path().join("current-$(uname -m)");
+        write_shim(&shim, &link, None).unwrap();
+        assert!(fs::read_to_string(&shim)
+            .unwrap()
+            .contains(&format!("CURRENT_LINK=\"{}\"", link.display())));
+        assert!(shim_points_to(&shim, &link));
+
+        let out = std::process::Command::new(&shim).output().unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&out.stdout),
+            format!("{}\n", machine)
+        );
+    }
+
+    #[test]
+    fn atomic_write_replaces_in_place_and_leaves_no_temp_file() {
+        let td = tempdir().unwrap();
+        let p = td.path().join("windsurf");
+        fs::write(&p, "old").unwrap();
+        fs::set_permissions(&p, fs::Permissions::from_mode(0o644)).unwrap();
+
+        atomic_write_executable(&p, b"#!/bin/sh\nnew\n").unwrap();
+        assert_eq!(fs::read_to_string(&p).unwrap(), "#!/bin/sh\nnew\n");
+        assert_eq!(
+            fs::metadata(&p).unwrap().permissions().mode() & 0o777,
+            0o755
+        );
+        let names: Vec<_> = fs::read_dir(td.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(names, ["windsurf"]);
+
+        // a failed write leaves the previous file and no temp file behind
+        assert!(atomic_write_executable(&td.path().join("missing/x"), b"").is_err());
+        assert_eq!(fs::read_dir(td.path()).unwrap().count(), 1);
+    }
+
     #[test]
     fn write_shim_creates_executable_script() {
         let td = tempdir().unwrap();
@@ -126,7 +1473,7 @@ mod tests {
         fs::create_dir_all(shim.parent().unwrap()).unwrap();
         fs::write(&exec, b"#!/bin/sh\necho hi\n").unwrap();
 
-        write_shim(&shim, &exec).unwrap();
+        write_shim(&shim, &exec, None).unwrap();
         let content = fs::read_to_string(&shim).unwrap();
         assert!(content.contains(exec.to_string_lossy().as_ref()));
 
@@ -0,0 +1,142 @@
+use crate::error::WindmanError;
+use anyhow::{anyhow, bail, Context, Result};
+use minisign_verify::{PublicKey, Signature, StreamVerifier};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// A minisign signature and the key to check it with.
+pub struct SignatureCheck {
+    key: PublicKey,
+    signature: Signature,
+    sig_path: PathBuf,
+}
+
+impl SignatureCheck {
+    /// `public_key` is the base64 key line or the full two-line .pub file content.
+    pub fn load(sig: &Path, public_key: &str) -> Result<Self> {
+        let key = public_key.trim();
+        if key.is_empty() {
+            bail!("no public key configured (set security.public_key or pass --public-key)");
+        }
+        let key = if key.lines().count() > 1 {
+            PublicKey::decode(key)
+        } else {
+            PublicKey::from_base64(key)
+        }
+        .map_err(|e| anyhow!("invalid public key: {}", e))?;
+
+        let sig_txt =
+            fs::read_to_string(sig).with_context(|| format!("reading {}", sig.display()))?;
+        let signature =
+            Signature::decode(&sig_txt).map_err(|e| anyhow!("invalid signature file: {}", e))?;
+        Ok(Self {
+            key,
+            signature,
+            sig_path: sig.to_path_buf(),
+        })
+    }
+
+    /// Feed it the archive bytes (e.g. as a download tap), then `finalize`.
+    pub fn verifier(&self) -> Result<Verifier<'_>> {
+        let inner = self
+            .key
+            .verify_stream(&self.signature)
+            .map_err(|e| anyhow!("cannot verify {}: {}", self.sig_path.display(), e))?;
+        Ok(Verifier(inner))
+    }
+}
+
+/// A signature check in progress.
+pub struct Verifier<'a>(StreamVerifier<'a>);
+
+impl Verifier<'_> {
+    /// `what` names the checked data in the error.
+    pub fn finalize(mut self, what: &str) -> Result<()> {
+        self.0.finalize().map_err(|e| {
+            WindmanError::VerificationFailed {
+                what: what.to_string(),
+                reason: format!("signature does not match ({})", e),
+            }
+            .into()
+        })
+    }
+}
+
+impl crate::download::Tap for Verifier<'_> {
+    fn update(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+}
+
+/// Verify `archive` against a minisign signature file using `public_key`
+/// (base64 key line, or the full two-line .pub file content).
+pub fn verify_archive(archive: &Path, sig: &Path, public_key: &str) -> Result<()> {
+    let check = SignatureCheck::load(sig, public_key)?;
+    let mut verifier = check.verifier()?;
+
+    // Stream the archive: release tarballs are hundreds of MB.
+    let mut f = File::open(archive).with_context(|| format!("opening {}", archive.display()))?;
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = f
+            .read(&mut buf)
+            .with_context(|| format!("reading {}", archive.display()))?;
+        if n == 0 {
+            break;
+        }
+        verifier.0.update(&buf[..n]);
+    }
+    verifier.finalize(&archive.display().to_string())
+}
+
+/// Move a rejected artifact aside as `<file>.quarantine` and return the new path.
+pub fn quarantine(path: &Path) -> Result<PathBuf> {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".quarantine");
+    let dest = PathBuf::from(name);
+    fs::rename(path, &dest)
+        .with_context(|| format!("moving {} -> {}", path.display(), dest.display()))?;
+    Ok(dest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    // Test vector from minisign: signature of the 4 bytes "test" (prehashed).
+    const PK: &str = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+    const SIG: &str = "untrusted comment: signature from minisign secret key
+RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+trusted comment: timestamp:1556193335\tfile:test
+y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==";
+
+    #[test]
+    fn accepts_valid_and_rejects_tampered_archive() {
+        let td = tempdir().unwrap();
+        let archive = td.path().join("a.tar.gz");
+        let sig = td.path().join("a.tar.gz.minisig");
+        fs::write(&sig, SIG).unwrap();
+
+        fs::write(&archive, b"test").unwrap();
+        verify_archive(&archive, &sig, PK).unwrap();
+
+        fs::write(&archive, b"Test").unwrap();
+        let err = verify_archive(&archive, &sig, PK).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<WindmanError>(),
+            Some(WindmanError::VerificationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn quarantine_renames_with_suffix() {
+        let td = tempdir().unwrap();
+        let archive = td.path().join("a.tar.gz");
+        fs::write(&archive, b"x").unwrap();
+        let q = quarantine(&archive).unwrap();
+        assert!(!archive.exists());
+        assert_eq!(q.file_name().unwrap(), "a.tar.gz.quarantine");
+    }
+}
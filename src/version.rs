@@ -1,56 +1,351 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::{fs, path::PathBuf};
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
 use crate::paths::EffectivePaths;
 
+/// Detected versions, per version dir, in the state dir.
+pub const VERSION_CACHE_FILE: &str = "detected-versions.json";
+
+/// A version read from a version dir, valid while the dir's mtime is unchanged.
+/// Version dirs are replaced whole (`install --force`), not edited in place, so
+/// one stat of the dir stands for a search of its product.json.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CachedVersion {
+    mtime: SystemTime,
+    version: String,
+}
+
+fn version_cache_path(state_dir: &Path) -> PathBuf {
+    state_dir.join(VERSION_CACHE_FILE)
+}
+
+fn read_version_cache(state_dir: &Path) -> BTreeMap<PathBuf, CachedVersion> {
+    fs::read_to_string(version_cache_path(state_dir))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// Best-effort: an unwritable state dir only costs a product.json search.
+fn write_version_cache(state_dir: &Path, mut cache: BTreeMap<PathBuf, CachedVersion>) {
+    cache.retain(|dir, _| dir.is_dir());
+    if let Ok(s) = serde_json::to_string_pretty(&cache) {
+        let _ = fs::create_dir_all(state_dir);
+        let _ = fs::write(version_cache_path(state_dir), s);
+    }
+}
+
+/// Drop what is cached for `dir` (after installing into it or switching to it).
+pub fn forget_cached_version(state_dir: &Path, dir: &Path) {
+    let mut cache = read_version_cache(state_dir);
+    let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    if cache.remove(&dir).is_some() {
+        write_version_cache(state_dir, cache);
+    }
+}
+
 /// Detect the local Windsurf version by resolving the 'current' symlink
-/// and scanning for a 'product.json' (up to a few levels deep).
+/// (cached per version dir, see `detect_local_version_with`).
 /// Returns Some("x.y.z") or Ok(None) if not installed.
 pub fn detect_local_version(eff: &EffectivePaths) -> Result<Option<String>> {
+    detect_local_version_with(eff, true)
+}
+
+/// `detect_local_version`; with `use_cache`, a version already read from the
+/// same, unchanged dir is returned after a single stat (`status --no-cache`
+/// reads it again).
+pub fn detect_local_version_with(eff: &EffectivePaths, use_cache: bool) -> Result<Option<String>> {
     // 1) Ensure the 'current' symlink exists
     if !eff.current_symlink.exists() {
         return Ok(None);
     }
 
     // 2) Resolve the symlink target (folder of the active install)
-    let current_target =
-        fs::read_link(&eff.current_symlink).unwrap_or_else(|_| eff.current_symlink.clone());
-
-    // 3) Search for product.json within the active folder
-    if let Some(product_path) = find_product_json(&current_target) {
-        let data = fs::read_to_string(&product_path)
-            .with_context(|| format!("reading {}", product_path.display()))?;
+    let current_target = crate::util::resolve_symlink(&eff.current_symlink)
+        .unwrap_or_else(|| eff.current_symlink.clone());
+    if !use_cache {
+        return version_of_dir(&current_target);
+    }
 
-        // Try JSON parse first
-        if let Ok(json) = serde_json::from_str::<Value>(&data) {
-            // Prefer "windsurfVersion", else "version"
-            if let Some(v) = json.get("windsurfVersion").and_then(|x| x.as_str()) {
-                return Ok(Some(v.to_string()));
-            }
-            if let Some(v) = json.get("version").and_then(|x| x.as_str()) {
-                return Ok(Some(v.to_string()));
-            }
+    let mtime = fs::metadata(&current_target)
+        .and_then(|m| m.modified())
+        .ok();
+    let mut cache = read_version_cache(&eff.state_dir);
+    if let (Some(mtime), Some(hit)) = (mtime, cache.get(&current_target)) {
+        if hit.mtime == mtime {
+            return Ok(Some(hit.version.clone()));
         }
+    }
+    let found = version_of_dir(&current_target)?;
+    if let (Some(mtime), Some(version)) = (mtime, &found) {
+        let entry = CachedVersion {
+            mtime,
+            version: version.clone(),
+        };
+        cache.insert(current_target, entry);
+        write_version_cache(&eff.state_dir, cache);
+    }
+    Ok(found)
+}
 
-        // Fallback: regex fish-out if JSON had comments or odd format
-        if let Some(v) = regex_fish_version(&data) {
-            return Ok(Some(v));
+/// Version of one version dir: the one windman recorded at install when it
+/// names a release (no walk at all), else read from its product.json.
+fn version_of_dir(current_target: &Path) -> Result<Option<String>> {
+    if let Some(meta) = crate::meta::read(current_target) {
+        if semver::Version::parse(&meta.version).is_ok() {
+            return Ok(Some(meta.version));
         }
+    }
+
+    // Search for product.json within the active folder
+    if let Some(product_path) = find_product_json(current_target) {
+        let data = fs::read_to_string(&product_path)
+            .map_err(crate::error::WindmanError::io("reading", &product_path))?;
 
         // If product.json exists but we couldn't parse a version, treat as not installed
-        return Ok(None);
+        return Ok(version_from_product_json(&data));
     }
 
     // No product.json found → not installed (or layout unexpected)
     Ok(None)
 }
 
+/// State of the `current` link, as `status` (and `status --json`) reports it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum StatusKind {
+    /// No `current` link at all
+    NotInstalled,
+    /// `current` points to a directory that no longer exists
+    Broken {
+        target: PathBuf,
+    },
+    /// The target exists but no version can be read from a product.json
+    UnknownVersion {
+        target: PathBuf,
+    },
+    Healthy {
+        version: String,
+        target: PathBuf,
+    },
+}
+
+impl StatusKind {
+    /// `use_cache`: see `detect_local_version_with`.
+    pub fn detect(eff: &EffectivePaths, use_cache: bool) -> Result<Self> {
+        let link = &eff.current_symlink;
+        if fs::symlink_metadata(link).is_err() {
+            return Ok(Self::NotInstalled);
+        }
+        if !link.exists() {
+            // dangling: report the dead target as the link spells it, made absolute
+            let target = match fs::read_link(link) {
+                Ok(t) => link.parent().map_or(t.clone(), |dir| dir.join(t)),
+                Err(_) => link.clone(),
+            };
+            return Ok(Self::Broken { target });
+        }
+        let target = crate::util::resolve_symlink(link).unwrap_or_else(|| link.clone());
+        Ok(match detect_local_version_with(eff, use_cache)? {
+            Some(version) => Self::Healthy { version, target },
+            None => Self::UnknownVersion { target },
+        })
+    }
+
+    pub fn version(&self) -> Option<&str> {
+        match self {
+            Self::Healthy { version, .. } => Some(version),
+            _ => None,
+        }
+    }
+
+    /// Command that gets back to a healthy state, when there is an obvious one.
+    pub fn suggestion(&self, eff: &EffectivePaths) -> Option<String> {
+        let newest = newest_installed(&eff.versions_dir);
+        match self {
+            Self::Healthy { .. } => None,
+            Self::NotInstalled | Self::Broken { .. } => Some(match newest {
+                Some(v) => format!("windman use {}", v),
+                None => "windman install".to_string(),
+            }),
+            Self::UnknownVersion { target } => newest
+                .filter(|v| target.file_name().is_none_or(|n| n.to_string_lossy() != *v))
+                .map(|v| format!("windman use {}", v))
+                .or_else(|| Some("windman install --force".to_string())),
+        }
+    }
+}
+
+/// Highest installed version dir name (semver first, see `compare_version_names`).
+fn newest_installed(versions_dir: &Path) -> Option<String> {
+    fs::read_dir(versions_dir)
+        .ok()?
+        .flatten()
+        .filter(|e| e.path().is_dir())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .filter(|n| crate::util::is_version_dir_name(n))
+        .max_by(|a, b| crate::util::compare_version_names(a, b))
+}
+
+/// Fields of product.json that name the release, most specific first.
+const PRODUCT_VERSION_FIELDS: [&str; 3] = ["windsurfVersion", "productVersion", "version"];
+
+/// The release a product.json names: `windsurfVersion`, else `productVersion`,
+/// else `version`. Install (to name the dir) and status both go through it,
+/// so they agree on what is installed.
+pub fn version_from_product_json(data: &str) -> Option<String> {
+    // Try JSON parse first
+    if let Ok(json) = serde_json::from_str::<Value>(data) {
+        return PRODUCT_VERSION_FIELDS.iter().find_map(|f| {
+            json.get(f)
+                .and_then(|x| x.as_str())
+                .filter(|v| !v.trim().is_empty())
+                .map(str::to_string)
+        });
+    }
+    // Fallback: regex fish-out if JSON had comments or odd format
+    PRODUCT_VERSION_FIELDS
+        .iter()
+        .find_map(|f| regex_fish_version(data, f))
+}
+
+/// `local` is at least `latest`, as `update` decides whether to install.
+pub fn is_up_to_date(local: &str, latest: &semver::Version) -> bool {
+    semver::Version::parse(local).is_ok_and(|v| v >= *latest)
+}
+
+/// What is installed, as `update` compares it with the latest release: the
+/// version read from current and the metadata recorded when it was installed.
+#[derive(Debug, Clone, Default)]
+pub struct LocalRelease {
+    pub version: Option<String>,
+    pub meta: Option<crate::meta::VersionMeta>,
+}
+
+impl LocalRelease {
+    pub fn of_current(eff: &EffectivePaths, version: Option<String>) -> Self {
+        LocalRelease {
+            version,
+            meta: crate::util::resolve_symlink(&eff.current_symlink)
+                .and_then(|dir| crate::meta::read(&dir)),
+        }
+    }
+
+    /// What `update` on `channel` compares the latest release with: current,
+    /// unless it came from another channel; then the newest installed version
+    /// from `channel` (none: nothing to compare, install).
+    pub fn baseline(eff: &EffectivePaths, version: Option<String>, channel: &str) -> Self {
+        let installed = crate::prune::scan(&eff.versions_dir)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|e| LocalRelease {
+                meta: crate::meta::read(&e.path),
+                version: Some(e.name),
+            })
+            .collect();
+        pick_baseline(Self::of_current(eff, version), installed, channel)
+    }
+
+    /// Installed from `channel`, or from no channel on record (a local
+    /// archive, or before channels were recorded), which counts for any.
+    pub fn is_from(&self, channel: &str) -> bool {
+        self.channel().is_none_or(|c| c == channel)
+    }
+
+    /// The channel recorded at install.
+    pub fn channel(&self) -> Option<&str> {
+        self.meta.as_ref().and_then(|m| m.channel.as_deref())
+    }
+
+    /// Current was installed from this download: the same URL (query aside,
+    /// signed links change on every request) or the same published SHA-256.
+    fn installed_from(&self, remote: &crate::remote::LatestInfo) -> bool {
+        let Some(meta) = &self.meta else {
+            return false;
+        };
+        let bare = |u: &str| u.split(['?', '#']).next().unwrap_or(u).to_string();
+        bare(&meta.source) == bare(&remote.url)
+            || remote.sha256.is_some() && meta.sha256 == remote.sha256
+    }
+}
+
+/// `LocalRelease::baseline` for `current` and the `installed` versions.
+fn pick_baseline(
+    current: LocalRelease,
+    installed: Vec<LocalRelease>,
+    channel: &str,
+) -> LocalRelease {
+    if current.is_from(channel) {
+        return current;
+    }
+    installed
+        .into_iter()
+        .filter(|r| r.is_from(channel))
+        .max_by(|a, b| {
+            crate::util::compare_version_names(
+                a.version.as_deref().unwrap_or_default(),
+                b.version.as_deref().unwrap_or_default(),
+            )
+        })
+        .unwrap_or_default()
+}
+
+/// What `update` does with the latest release.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateDecision {
+    /// Nothing installed, or the latest release is newer
+    Install,
+    /// The installed version is the latest or newer
+    UpToDate,
+    /// The latest release has no semver (neither its version nor its URL name
+    /// one) and current came from another download: install it, named by
+    /// timestamp
+    InstallUnversioned,
+    /// No version to compare, but current came from that very download
+    SameDownload,
+    /// The installed version is not semver and came from another download:
+    /// nothing to compare, only `--force` reinstalls
+    LocalUnknown,
+}
+
+pub fn decide_update_action(
+    local: &LocalRelease,
+    remote: &crate::remote::LatestInfo,
+) -> UpdateDecision {
+    let same_download = local.installed_from(remote);
+    let Ok(latest) = semver::Version::parse(&remote.version) else {
+        return if same_download {
+            UpdateDecision::SameDownload
+        } else {
+            UpdateDecision::InstallUnversioned
+        };
+    };
+    match local.version.as_deref() {
+        None => UpdateDecision::Install,
+        Some(v) if semver::Version::parse(v).is_err() => {
+            if same_download {
+                UpdateDecision::SameDownload
+            } else {
+                UpdateDecision::LocalUnknown
+            }
+        }
+        Some(v) if is_up_to_date(v, &latest) => UpdateDecision::UpToDate,
+        Some(_) => UpdateDecision::Install,
+    }
+}
+
 /// Walk a few levels to find .../resources/app/product.json under current target.
 /// Typical paths:
 ///   <root>/Windsurf/resources/app/product.json
 ///   <root>/resources/app/product.json
-fn find_product_json(root: &PathBuf) -> Option<PathBuf> {
+pub fn find_product_json(root: &Path) -> Option<PathBuf> {
     // Minimal scanning to avoid heavy WalkDir; we know common patterns.
     let candidates = [
         root.join("resources/app/product.json"),
@@ -78,9 +373,13 @@ fn find_product_json(root: &PathBuf) -> Option<PathBuf> {
     None
 }
 
-fn regex_fish_version(s: &str) -> Option<String> {
-    // look for "windsurfVersion":"x.y.z" or "version":"x.y.z"
-    let re = regex::Regex::new(r#""(?:windsurfVersion|version)"\s*:\s*"(\d+\.\d+\.\d+)""#).ok()?;
+fn regex_fish_version(s: &str, field: &str) -> Option<String> {
+    // look for "<field>":"x.y.z"
+    let re = regex::Regex::new(&format!(
+        r#""{}"\s*:\s*"(\d+\.\d+\.\d+[^"]*)""#,
+        regex::escape(field)
+    ))
+    .ok()?;
     let caps = re.captures(s)?;
     Some(caps.get(1)?.as_str().to_string())
 }
@@ -118,9 +417,331 @@ mod tests_detect_version_layout_linux {
             bin_shim: tmp.path().join("bin/windsurf"),
             desktop_file: tmp.path().join("share/applications/windsurf.desktop"),
             icons_dir: tmp.path().join("share/icons"),
+            cache_dir: tmp.path().join("cache"),
+            state_dir: tmp.path().join("state"),
+            profile: None,
+            arch: None,
         };
 
         let v = detect_local_version(&eff).unwrap();
         assert_eq!(v.as_deref(), Some("1.2.3"));
     }
+
+    #[test]
+    fn product_version_fields_are_tried_in_order() {
+        let v = |s: &str| version_from_product_json(s);
+        assert_eq!(
+            v(r#"{ "version":"1.0.0", "windsurfVersion":"1.12.11" }"#).as_deref(),
+            Some("1.12.11")
+        );
+        assert_eq!(
+            v(r#"{ "version":"1.0.0", "productVersion":"1.12.10" }"#).as_deref(),
+            Some("1.12.10")
+        );
+        assert_eq!(v(r#"{ "version":"1.12.9" }"#).as_deref(), Some("1.12.9"));
+        assert_eq!(
+            v(r#"{ "windsurfVersion":"", "version":"1.2.3" }"#).as_deref(),
+            Some("1.2.3")
+        );
+        assert_eq!(v(r#"{ "nameShort":"Windsurf" }"#), None);
+        // not strict JSON (comment, trailing comma): the regex fallback
+        assert_eq!(
+            v("{ // build\n \"version\": \"1.12.8\",\n}").as_deref(),
+            Some("1.12.8")
+        );
+
+        let latest = semver::Version::parse("1.12.9").unwrap();
+        assert!(is_up_to_date("1.12.9", &latest));
+        assert!(!is_up_to_date("1.12.8", &latest));
+        assert!(!is_up_to_date("20250927153322", &latest));
+    }
+}
+
+#[cfg(test)]
+mod tests_update_decision {
+    use super::*;
+    use crate::meta::VersionMeta;
+    use crate::remote::LatestInfo;
+
+    fn remote(version: &str, url: &str) -> LatestInfo {
+        LatestInfo {
+            version: version.to_string(),
+            url: url.to_string(),
+            sha256: None,
+        }
+    }
+
+    fn local(version: Option<&str>, source: &str) -> LocalRelease {
+        LocalRelease {
+            version: version.map(str::to_string),
+            meta: Some(VersionMeta {
+                version: version.unwrap_or("20250927153322").to_string(),
+                source: source.to_string(),
+                ..Default::default()
+            }),
+        }
+    }
+
+    const OLD_URL: &str = "https://cdn.invalid/linux-x64/Windsurf-linux-x64-1.12.10.tar.gz";
+    const HASH_URL: &str = "https://cdn.invalid/linux-x64/stable/4f2a9c/Windsurf-linux-x64.tar.gz";
+
+    #[test]
+    fn semver_on_both_sides_compares_versions() {
+        let latest = remote(
+            "1.12.11",
+            "https://cdn.invalid/Windsurf-linux-x64-1.12.11.tar.gz",
+        );
+        assert_eq!(
+            decide_update_action(&local(Some("1.12.10"), OLD_URL), &latest),
+            UpdateDecision::Install
+        );
+        assert_eq!(
+            decide_update_action(&local(Some("1.12.11"), OLD_URL), &latest),
+            UpdateDecision::UpToDate
+        );
+        assert_eq!(
+            decide_update_action(&LocalRelease::default(), &latest),
+            UpdateDecision::Install
+        );
+    }
+
+    #[test]
+    fn unversioned_latest_is_compared_by_download() {
+        let latest = remote("4f2a9c0e", HASH_URL);
+        assert_eq!(
+            decide_update_action(&local(Some("1.12.10"), OLD_URL), &latest),
+            UpdateDecision::InstallUnversioned
+        );
+        assert_eq!(
+            decide_update_action(&LocalRelease::default(), &latest),
+            UpdateDecision::InstallUnversioned
+        );
+        // a fresh signature on the same file is the same download
+        let signed = format!("{}?Expires=2&Signature=b", HASH_URL);
+        assert_eq!(
+            decide_update_action(
+                &local(None, &format!("{}?Expires=1", HASH_URL)),
+                &remote("4f2a9c0e", &signed)
+            ),
+            UpdateDecision::SameDownload
+        );
+        // or the same published checksum behind another URL
+        let mut mirrored = remote("4f2a9c0e", "https://mirror.invalid/ws.tar.gz");
+        mirrored.sha256 = Some("ab".repeat(32));
+        let mut installed = local(None, HASH_URL);
+        installed.meta.as_mut().unwrap().sha256 = Some("ab".repeat(32));
+        assert_eq!(
+            decide_update_action(&installed, &mirrored),
+            UpdateDecision::SameDownload
+        );
+    }
+
+    fn from(version: &str, channel: Option<&str>) -> LocalRelease {
+        let mut r = local(Some(version), OLD_URL);
+        r.meta.as_mut().unwrap().channel = channel.map(str::to_string);
+        r
+    }
+
+    #[test]
+    fn a_newer_build_from_another_channel_does_not_block_an_update() {
+        let latest = remote(
+            "1.12.11",
+            "https://cdn.invalid/Windsurf-linux-x64-1.12.11.tar.gz",
+        );
+        let next = from("1.13.0-next", Some("next"));
+        let installed = vec![
+            next.clone(),
+            from("1.12.9", Some("stable")),
+            from("1.12.10", None),
+        ];
+        let base = pick_baseline(next.clone(), installed.clone(), "stable");
+        assert_eq!(base.version.as_deref(), Some("1.12.10"));
+        assert_eq!(
+            decide_update_action(&base, &latest),
+            UpdateDecision::Install
+        );
+
+        // on the next channel, current is the baseline and is newer
+        let base = pick_baseline(next.clone(), installed, "next");
+        assert_eq!(base.version.as_deref(), Some("1.13.0-next"));
+        assert_eq!(
+            decide_update_action(&base, &latest),
+            UpdateDecision::UpToDate
+        );
+
+        // nothing from stable installed: install it
+        let base = pick_baseline(next.clone(), vec![next], "stable");
+        assert_eq!(base.version, None);
+        assert_eq!(
+            decide_update_action(&base, &latest),
+            UpdateDecision::Install
+        );
+
+        // current with no channel on record is compared as before
+        let legacy = from("1.12.11", None);
+        let base = pick_baseline(legacy.clone(), vec![legacy], "stable");
+        assert_eq!(
+            decide_update_action(&base, &latest),
+            UpdateDecision::UpToDate
+        );
+    }
+
+    #[test]
+    fn unversioned_local_is_not_silently_replaced() {
+        let latest = remote(
+            "1.12.11",
+            "https://cdn.invalid/Windsurf-linux-x64-1.12.11.tar.gz",
+        );
+        assert_eq!(
+            decide_update_action(&local(Some("20250927153322"), HASH_URL), &latest),
+            UpdateDecision::LocalUnknown
+        );
+        assert_eq!(
+            decide_update_action(&local(Some("20250927153322"), &latest.url), &latest),
+            UpdateDecision::SameDownload
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests_status_kind {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn version(eff: &EffectivePaths, v: &str, product: bool) -> PathBuf {
+        let dir = eff.versions_dir.join(v);
+        let app = dir.join("Windsurf/resources/app");
+        fs::create_dir_all(&app).unwrap();
+        if product {
+            fs::write(
+                app.join("product.json"),
+                format!(r#"{{"windsurfVersion":"{}"}}"#, v),
+            )
+            .unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn not_installed_and_healthy() {
+        let td = tempdir().unwrap();
+        let eff = EffectivePaths::for_test(td.path());
+        assert_eq!(
+            StatusKind::detect(&eff, true).unwrap(),
+            StatusKind::NotInstalled
+        );
+        assert_eq!(
+            StatusKind::NotInstalled.suggestion(&eff).as_deref(),
+            Some("windman install")
+        );
+
+        let dir = version(&eff, "1.12.11", true);
+        std::os::unix::fs::symlink("versions/1.12.11", &eff.current_symlink).unwrap();
+        let kind = StatusKind::detect(&eff, true).unwrap();
+        assert_eq!(
+            kind,
+            StatusKind::Healthy {
+                version: "1.12.11".into(),
+                target: fs::canonicalize(dir).unwrap()
+            }
+        );
+        assert_eq!(kind.version(), Some("1.12.11"));
+        assert_eq!(kind.suggestion(&eff), None);
+        assert_eq!(serde_json::to_value(&kind).unwrap()["state"], "healthy");
+    }
+
+    #[test]
+    fn dangling_link_is_broken_and_names_the_dead_target() {
+        let td = tempdir().unwrap();
+        let eff = EffectivePaths::for_test(td.path());
+        version(&eff, "1.12.10", true);
+        let gone = version(&eff, "1.12.11", true);
+        std::os::unix::fs::symlink("versions/1.12.11", &eff.current_symlink).unwrap();
+        fs::remove_dir_all(&gone).unwrap();
+
+        let kind = StatusKind::detect(&eff, true).unwrap();
+        assert_eq!(kind, StatusKind::Broken { target: gone });
+        assert_eq!(kind.version(), None);
+        assert_eq!(
+            kind.suggestion(&eff).as_deref(),
+            Some("windman use 1.12.10")
+        );
+        assert_eq!(serde_json::to_value(&kind).unwrap()["state"], "broken");
+    }
+
+    #[test]
+    fn detected_version_is_cached_per_dir_until_forgotten() {
+        let td = tempdir().unwrap();
+        let eff = EffectivePaths::for_test(td.path());
+        let dir = version(&eff, "1.12.11", true);
+        std::os::unix::fs::symlink(&dir, &eff.current_symlink).unwrap();
+        assert_eq!(
+            detect_local_version(&eff).unwrap().as_deref(),
+            Some("1.12.11")
+        );
+
+        // primed: product.json is not read again
+        fs::remove_file(dir.join("Windsurf/resources/app/product.json")).unwrap();
+        assert_eq!(
+            detect_local_version(&eff).unwrap().as_deref(),
+            Some("1.12.11")
+        );
+        assert_eq!(detect_local_version_with(&eff, false).unwrap(), None);
+        assert_eq!(
+            StatusKind::detect(&eff, false).unwrap(),
+            StatusKind::UnknownVersion {
+                target: fs::canonicalize(&dir).unwrap()
+            }
+        );
+
+        forget_cached_version(&eff.state_dir, &dir);
+        assert_eq!(detect_local_version(&eff).unwrap(), None);
+    }
+
+    #[test]
+    fn install_metadata_is_preferred_over_searching_product_json() {
+        let td = tempdir().unwrap();
+        let eff = EffectivePaths::for_test(td.path());
+        let dir = version(&eff, "1.13.0-nightly.3", false);
+        std::os::unix::fs::symlink(&dir, &eff.current_symlink).unwrap();
+        let meta = |version: &str| crate::meta::VersionMeta {
+            version: version.into(),
+            ..Default::default()
+        };
+        crate::meta::write(&dir, &meta("1.13.0-nightly.3")).unwrap();
+        assert_eq!(
+            detect_local_version_with(&eff, false).unwrap().as_deref(),
+            Some("1.13.0-nightly.3")
+        );
+
+        // a timestamp-named install says nothing about the release
+        crate::meta::write(&dir, &meta("20250101120000")).unwrap();
+        assert_eq!(detect_local_version_with(&eff, false).unwrap(), None);
+    }
+
+    #[test]
+    fn target_without_product_json_has_an_unknown_version() {
+        let td = tempdir().unwrap();
+        let eff = EffectivePaths::for_test(td.path());
+        let dir = version(&eff, "1.12.11", false);
+        std::os::unix::fs::symlink(&dir, &eff.current_symlink).unwrap();
+
+        let kind = StatusKind::detect(&eff, true).unwrap();
+        assert_eq!(
+            kind,
+            StatusKind::UnknownVersion {
+                target: fs::canonicalize(&dir).unwrap()
+            }
+        );
+        // the only version is the unreadable one: nothing to switch to
+        assert_eq!(
+            kind.suggestion(&eff).as_deref(),
+            Some("windman install --force")
+        );
+        version(&eff, "1.12.12", true);
+        assert_eq!(
+            kind.suggestion(&eff).as_deref(),
+            Some("windman use 1.12.12")
+        );
+    }
 }
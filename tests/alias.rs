@@ -0,0 +1,78 @@
+//! Built-in aliases (`up`, `ls`, ...) and `[cli.aliases]` from the config:
+//! expanded before parsing, with the rest of the command line appended, and
+//! listed by `alias list`.
+
+mod common;
+
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+
+fn windman(root: &Path, aliases: &str) -> Command {
+    common::write_config(root, "keep = 1\n", &format!("[cli.aliases]\n{}", aliases));
+    common::windman(root)
+}
+
+fn stdout(cmd: &mut Command) -> String {
+    let out = cmd.assert().success().get_output().clone();
+    String::from_utf8_lossy(&out.stdout).into_owned()
+}
+
+#[test]
+fn user_aliases_run_with_the_flags_after_them_appended() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    fs::create_dir_all(root.join("opt/1.0.0/Windsurf")).unwrap();
+    let aliases = "aliases = \"alias list\"\nversions = \"ls --porcelain\"\n";
+
+    assert_eq!(
+        stdout(windman(root, aliases).arg("versions")),
+        stdout(windman(root, aliases).args(["list", "--porcelain"]))
+    );
+    let json = stdout(windman(root, aliases).args(["aliases", "--json"]));
+    let v: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(v["user"]["versions"], "ls --porcelain");
+    assert!(v["builtin"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|b| b["alias"] == "up" && b["command"] == "update"));
+
+    let text = stdout(windman(root, aliases).args(["alias", "list"]));
+    assert!(
+        text.contains("versions  ls --porcelain  cli.aliases"),
+        "{}",
+        text
+    );
+}
+
+#[test]
+fn alias_loops_and_shadowed_commands_are_refused() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    let out = windman(root, "a = \"-v b\"\nb = \"a --json\"\n")
+        .arg("a")
+        .assert()
+        .code(2)
+        .get_output()
+        .clone();
+    let err = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        err.contains("alias loop in cli.aliases: a -> b -> a"),
+        "{}",
+        err
+    );
+
+    let out = windman(root, "up = \"list\"\n")
+        .args(["alias", "list"])
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    let err = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        err.contains("cli.aliases.up: 'up' is a built-in"),
+        "{}",
+        err
+    );
+}
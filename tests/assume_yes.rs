@@ -0,0 +1,61 @@
+//! Confirmations without a terminal: refused (exit 4, naming the flag) unless
+//! `-y/--assume-yes` answers them.
+
+mod common;
+
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+
+/// Versions 1.0.0 and 2.0.0 installed under `<root>/opt`, current -> 2.0.0.
+fn downgradable(root: &Path) -> Command {
+    let opt = root.join("opt");
+    for v in ["1.0.0", "2.0.0"] {
+        fs::create_dir_all(opt.join(v)).unwrap();
+    }
+    std::os::unix::fs::symlink(opt.join("2.0.0"), opt.join("current")).unwrap();
+
+    let mut cmd = common::windman(root);
+    // a pipe, not a terminal
+    cmd.write_stdin("");
+    cmd
+}
+
+fn current(root: &Path) -> String {
+    let target = fs::read_link(root.join("opt/current")).unwrap();
+    target.file_name().unwrap().to_string_lossy().to_string()
+}
+
+#[test]
+fn prompt_without_terminal_is_refused_and_names_the_flag() {
+    let tmp = tempfile::tempdir().unwrap();
+    let out = downgradable(tmp.path())
+        .args(["use", "1.0.0"])
+        .assert()
+        .code(4)
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("--assume-yes"), "{}", stderr);
+    assert_eq!(current(tmp.path()), "2.0.0");
+}
+
+#[test]
+fn assume_yes_answers_the_prompt() {
+    for flag in ["-y", "--assume-yes"] {
+        let tmp = tempfile::tempdir().unwrap();
+        downgradable(tmp.path())
+            .args([flag, "use", "1.0.0"])
+            .assert()
+            .success();
+        assert_eq!(current(tmp.path()), "1.0.0");
+    }
+
+    let tmp = tempfile::tempdir().unwrap();
+    downgradable(tmp.path())
+        .env("WINDMAN_ASSUME_YES", "1")
+        .args(["use", "1.0.0"])
+        .assert()
+        .success();
+    assert_eq!(current(tmp.path()), "1.0.0");
+}
@@ -0,0 +1,74 @@
+//! `block` keeps a bad release out: `update` stays put when the latest one is
+//! blocked, `install` refuses it without --force, `list --blocked` shows them.
+
+mod common;
+
+use common::{fake_archive, windman};
+
+const LATEST: &str = "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 71\r\nConnection: close\r\n\r\n{\"version\":\"1.12.11\",\"url\":\"https://x.invalid/Windsurf-1.12.11.tar.gz\"}";
+
+fn stdout(assert: assert_cmd::assert::Assert) -> String {
+    String::from_utf8_lossy(&assert.get_output().stdout).to_string()
+}
+
+#[test]
+fn update_stays_put_when_the_latest_release_is_blocked() {
+    let tmp = tempfile::tempdir().unwrap();
+    windman(tmp.path())
+        .args(["block", "1.12.11"])
+        .assert()
+        .success();
+
+    let out = stdout(
+        windman(tmp.path())
+            .env("WINDMAN_LATEST_ENDPOINT", common::serve_once(LATEST))
+            .args(["update", "--no-desktop"])
+            .assert()
+            .code(10),
+    );
+    assert!(
+        out.contains("Windsurf 1.12.11 is blocked; staying on <none>"),
+        "{}",
+        out
+    );
+    assert!(!tmp.path().join("opt/current").exists());
+    assert!(!tmp.path().join("cache/downloads").exists());
+}
+
+#[test]
+fn install_needs_force_and_list_shows_the_blocklist() {
+    let tmp = tempfile::tempdir().unwrap();
+    for v in ["1.12.10", "1.9.0"] {
+        windman(tmp.path()).args(["block", v]).assert().success();
+    }
+    let out = stdout(windman(tmp.path()).args(["list", "--blocked"]).assert());
+    assert_eq!(out, "1.9.0\n1.12.10\n");
+
+    let tar = fake_archive(tmp.path(), "1.12.10");
+    let install = |extra: &[&str]| {
+        let mut cmd = windman(tmp.path());
+        cmd.args(["--quiet", "install", "--no-desktop", "--no-smoke-test"])
+            .args(extra)
+            .arg("--tar")
+            .arg(&tar);
+        cmd.assert()
+    };
+    let err = install(&[]).failure().get_output().stderr.clone();
+    let err = String::from_utf8_lossy(&err);
+    assert!(err.contains("Windsurf 1.12.10 is blocked"), "{}", err);
+    assert!(!tmp.path().join("opt/1.12.10").exists());
+    install(&["--force"]).success();
+    assert!(tmp.path().join("opt/1.12.10").is_dir());
+
+    windman(tmp.path())
+        .args(["unblock", "1.12.10"])
+        .assert()
+        .success();
+    let out = stdout(
+        windman(tmp.path())
+            .args(["list", "--blocked", "--json"])
+            .assert(),
+    );
+    let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(json, serde_json::json!(["1.9.0"]));
+}
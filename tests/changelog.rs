@@ -0,0 +1,91 @@
+//! `[changelog]` picks the releases page, how `changelog` prints it and how
+//! many versions; a source that is not a releases page is an error.
+
+mod common;
+
+use assert_cmd::Command;
+use std::io::{Read, Write};
+use std::path::Path;
+
+const PAGE: &str = "<html><h2>1.12.11</h2><ul><li>Fix <b>crash</b></li></ul><h2>1.12.10</h2><h3>Fixes</h3><p>Proxy auth</p></html>";
+
+/// A server answering one request with `status` and `body`.
+fn serve(status: &str, body: &'static str) -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0u8; 4096];
+        let _ = stream.read(&mut buf);
+        let _ = stream.write_all(response.as_bytes());
+    });
+    format!("http://{}/releases", addr)
+}
+
+fn windman(root: &Path, changelog: &str) -> Command {
+    common::write_config(root, "keep = 2\n", &format!("[changelog]\n{}\n", changelog));
+    common::windman(root)
+}
+
+fn stdout(assert: assert_cmd::assert::Assert) -> String {
+    String::from_utf8_lossy(&assert.get_output().stdout).to_string()
+}
+
+#[test]
+fn plain_rendering_and_max_entries_from_a_custom_source() {
+    let tmp = tempfile::tempdir().unwrap();
+    let source = format!(
+        "source_url = \"{}\"\nrender = \"plain\"\nmax_entries = 1",
+        serve("200 OK", PAGE)
+    );
+    let out = stdout(
+        windman(tmp.path(), &source)
+            .arg("changelog")
+            .assert()
+            .success(),
+    );
+    assert_eq!(out, "Windsurf 1.12.11\n\n- Fix crash\n\n");
+
+    let source = format!("source_url = \"{}\"", serve("200 OK", PAGE));
+    let out = stdout(
+        windman(tmp.path(), &source)
+            .args(["changelog", "--raw"])
+            .assert()
+            .success(),
+    );
+    assert!(
+        out.starts_with("# Windsurf 1.12.11\n\n- Fix crash\n\n# Windsurf 1.12.10\n\n### Fixes"),
+        "{}",
+        out
+    );
+}
+
+#[test]
+fn a_source_that_is_not_a_releases_page_is_an_error() {
+    let tmp = tempfile::tempdir().unwrap();
+    for (status, body, expected) in [
+        (
+            "200 OK",
+            "<html>Welcome</html>",
+            "no release notes found at http://",
+        ),
+        ("404 Not Found", "gone", "unexpected status 404"),
+    ] {
+        let source = format!("source_url = \"{}\"", serve(status, body));
+        let out = windman(tmp.path(), &source)
+            .arg("changelog")
+            .assert()
+            .failure()
+            .get_output()
+            .clone();
+        let err = String::from_utf8_lossy(&out.stderr);
+        assert!(err.contains(expected), "{}", err);
+        assert!(out.stdout.is_empty());
+    }
+}
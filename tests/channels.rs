@@ -0,0 +1,75 @@
+//! Installs remember the channel they came from: `list` tags them when
+//! channels mix, `info` shows it, and a newer build from another channel does
+//! not keep `update` from installing the configured channel's latest release.
+
+mod common;
+
+use assert_cmd::Command;
+use common::{fake_archive, serve_latest};
+use std::fs;
+use std::path::Path;
+
+fn windman(root: &Path) -> Command {
+    // release notes from a port nobody listens on: stored as unavailable
+    common::write_config(
+        root,
+        "keep = 1\n",
+        "[changelog]\nsource_url = \"http://127.0.0.1:9/releases\"\n",
+    );
+    common::windman(root)
+}
+
+fn stdout(assert: assert_cmd::assert::Assert) -> String {
+    String::from_utf8_lossy(&assert.get_output().stdout).to_string()
+}
+
+#[test]
+fn a_newer_next_build_does_not_block_a_stable_update() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    let next = fake_archive(root, "1.13.0");
+    windman(root)
+        .args(["install", "--no-smoke-test", "--tar"])
+        .arg(&next)
+        .assert()
+        .success();
+    // as `update` on the next channel would have recorded it
+    let meta_path = root.join("opt/1.13.0/.windman-meta.json");
+    let mut meta: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&meta_path).unwrap()).unwrap();
+    meta["channel"] = "next".into();
+    fs::write(&meta_path, meta.to_string()).unwrap();
+
+    let stable = fake_archive(root, "1.12.11");
+    let out = stdout(
+        windman(root)
+            .env("WINDMAN_LATEST_ENDPOINT", serve_latest("1.12.11", &stable))
+            .args(["update", "--no-smoke-test", "--no-prune"])
+            .assert()
+            .success(),
+    );
+    assert!(
+        out.contains("Current (1.13.0) is a next build; comparing the latest stable release with nothing installed from it."),
+        "{}",
+        out
+    );
+    assert!(out.contains("skipping prune (--no-prune)"), "{}", out);
+    assert!(fs::read_link(root.join("opt/current"))
+        .unwrap()
+        .ends_with("1.12.11"));
+
+    let list = stdout(windman(root).arg("list").assert().success());
+    assert!(list.contains(" 1.13.0 [next] "), "{}", list);
+    assert!(list.contains(" 1.12.11 [stable] "), "{}", list);
+    let info = stdout(windman(root).args(["info", "1.12.11"]).assert().success());
+    assert!(info.contains("Channel     : stable"), "{}", info);
+
+    // going back to the next build says it leaves the channel
+    let out = windman(root).args(["use", "1.13.0"]).assert().success();
+    let err = String::from_utf8_lossy(&out.get_output().stderr).to_string();
+    assert!(
+        err.contains("switching channels: 1.12.11 is a stable build, 1.13.0 a next one"),
+        "{}",
+        err
+    );
+}
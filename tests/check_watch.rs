@@ -0,0 +1,63 @@
+//! `check --watch` polls through the cached check (the API is asked once within
+//! the TTL however short the interval) and runs `--on-update` once per version.
+
+mod common;
+
+use common::windman;
+use std::fs;
+
+const LATEST: &str = "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 71\r\nConnection: close\r\n\r\n{\"version\":\"1.12.11\",\"url\":\"https://x.invalid/Windsurf-1.12.11.tar.gz\"}";
+
+#[test]
+fn watch_reuses_the_cached_answer_and_runs_the_hook_once() {
+    let tmp = tempfile::tempdir().unwrap();
+    let hook = tmp.path().join("hook");
+    let out = windman(tmp.path())
+        .env("WINDMAN_LATEST_ENDPOINT", common::serve_once(LATEST))
+        .args([
+            "check",
+            "--watch",
+            "--interval",
+            "1s",
+            "--max-iterations",
+            "2",
+        ])
+        .arg("--on-update")
+        .arg(format!(
+            "echo \"$WINDMAN_LOCAL>$WINDMAN_LATEST\" >> '{}'",
+            hook.display()
+        ))
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", stdout);
+    for line in lines {
+        assert!(line.starts_with('['), "{}", line);
+        assert!(
+            line.ends_with("] local <none>, latest 1.12.11: update available"),
+            "{}",
+            line
+        );
+    }
+    assert_eq!(fs::read_to_string(&hook).unwrap(), ">1.12.11\n");
+}
+
+#[test]
+fn interval_needs_watch_and_a_valid_duration() {
+    let tmp = tempfile::tempdir().unwrap();
+    windman(tmp.path())
+        .args(["check", "--interval", "5m"])
+        .assert()
+        .code(2);
+    let out = windman(tmp.path())
+        .args(["check", "--watch", "--interval", "5y"])
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    let err = String::from_utf8_lossy(&out.stderr);
+    assert!(err.contains("invalid duration unit in '5y'"), "{}", err);
+}
@@ -0,0 +1,91 @@
+//! `--color`: styles only reach a terminal (or `--color always`); piped output,
+//! `--color never`, `NO_COLOR`, `--porcelain` and `--json` carry no escape codes.
+
+mod common;
+
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+
+/// `<root>/opt/1.0.0`, current.
+fn installed(root: &Path) {
+    let version = root.join("opt/1.0.0");
+    fs::create_dir_all(version.join("Windsurf/bin")).unwrap();
+    std::os::unix::fs::symlink(&version, root.join("opt/current")).unwrap();
+}
+
+fn windman(root: &Path) -> Command {
+    let mut cmd = common::windman(root);
+    // nothing listens there: `check` warns
+    cmd.env("WINDMAN_LATEST_ENDPOINT", "http://127.0.0.1:1/latest");
+    cmd
+}
+
+/// stdout and stderr of a run.
+fn output(cmd: &mut Command) -> (String, String) {
+    let out = cmd.output().unwrap();
+    (
+        String::from_utf8_lossy(&out.stdout).to_string(),
+        String::from_utf8_lossy(&out.stderr).to_string(),
+    )
+}
+
+fn has_escapes(s: &str) -> bool {
+    s.contains('\x1b')
+}
+
+#[test]
+fn piped_output_is_plain_unless_color_is_forced() {
+    let tmp = tempfile::tempdir().unwrap();
+    installed(tmp.path());
+
+    for args in [&["list"][..], &["check"], &["use", "9.9.9"]] {
+        let (out, err) = output(windman(tmp.path()).args(args));
+        assert!(
+            !has_escapes(&out) && !has_escapes(&err),
+            "{:?}: {}{}",
+            args,
+            out,
+            err
+        );
+        let (out, err) = output(windman(tmp.path()).env("NO_COLOR", "1").args(args));
+        assert!(
+            !has_escapes(&out) && !has_escapes(&err),
+            "{:?}: {}{}",
+            args,
+            out,
+            err
+        );
+    }
+
+    let (out, _) = output(windman(tmp.path()).args(["--color", "always", "list"]));
+    let current = out.lines().find(|l| l.contains("1.0.0")).unwrap();
+    assert!(
+        current.starts_with("\x1b[") && current.contains("current"),
+        "{}",
+        out
+    );
+    let (_, err) = output(windman(tmp.path()).args(["--color", "always", "check"]));
+    assert!(err.contains("\x1b[33m"), "warning in yellow: {}", err);
+    let (_, err) = output(windman(tmp.path()).args(["--color", "always", "use", "9.9.9"]));
+    assert!(err.contains("\x1b[31mError:"), "error in red: {}", err);
+
+    let (out, err) = output(windman(tmp.path()).args(["--color", "never", "list"]));
+    assert!(!has_escapes(&out) && !has_escapes(&err), "{}{}", out, err);
+}
+
+#[test]
+fn porcelain_and_json_never_carry_escape_codes() {
+    let tmp = tempfile::tempdir().unwrap();
+    installed(tmp.path());
+    for args in [
+        &["list", "--porcelain"][..],
+        &["list", "--json"],
+        &["check", "--json"],
+        &["status", "--json"],
+    ] {
+        let (out, _) = output(windman(tmp.path()).args(["--color", "always"]).args(args));
+        assert!(!out.is_empty(), "{:?}", args);
+        assert!(!has_escapes(&out), "{:?}: {}", args, out);
+    }
+}
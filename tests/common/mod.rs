@@ -0,0 +1,120 @@
+//! Fixtures the integration tests share: a fake Windsurf archive, windman
+//! confined to a temp dir and a mock update API. Each test crate uses only
+//! some of them, hence the `allow(dead_code)` on every one.
+
+use assert_cmd::Command;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Windsurf/bin/windsurf + a product.json naming `version`, gzipped.
+#[allow(dead_code)]
+pub fn archive_bytes(version: &str) -> Vec<u8> {
+    let enc = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    let mut builder = tar::Builder::new(enc);
+    let product = format!(r#"{{ "windsurfVersion":"{}" }}"#, version);
+    for (path, mode, data) in [
+        ("Windsurf/bin/windsurf", 0o755, &b"#!/bin/sh\n"[..]),
+        (
+            "Windsurf/resources/app/product.json",
+            0o644,
+            product.as_bytes(),
+        ),
+    ] {
+        let mut hdr = tar::Header::new_gnu();
+        hdr.set_path(path).unwrap();
+        hdr.set_mode(mode);
+        hdr.set_size(data.len() as u64);
+        hdr.set_cksum();
+        builder.append(&hdr, data).unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+/// `archive_bytes(version)` as `dir/Windsurf-linux-x64-<version>.tar.gz`.
+#[allow(dead_code)]
+pub fn fake_archive(dir: &Path, version: &str) -> PathBuf {
+    let p = dir.join(format!("Windsurf-linux-x64-{}.tar.gz", version));
+    fs::write(&p, archive_bytes(version)).unwrap();
+    p
+}
+
+/// `<root>/windman.toml`: prefix `<root>/opt`, bin dir `<root>/bin`, no
+/// desktop integration, no proxy. `install` lines go in `[install]`, `rest`
+/// after `[network]` (keys of it or further tables).
+#[allow(dead_code)]
+pub fn write_config(root: &Path, install: &str, rest: &str) {
+    fs::write(
+        root.join("windman.toml"),
+        format!(
+            "[install]\nprefix_dir = \"{opt}\"\nbin_dir = \"{bin}\"\nchannel = \"stable\"\ndesktop_integration = false\n{install}[network]\nproxy_enabled = false\n{rest}",
+            opt = root.join("opt").display(),
+            bin = root.join("bin").display(),
+        ),
+    )
+    .unwrap();
+}
+
+/// windman with nothing of the environment: HOME, the config
+/// (`<root>/windman.toml`) and the cache under `root`, `--prefix <root>/opt`.
+#[allow(dead_code)]
+pub fn windman(root: &Path) -> Command {
+    let mut cmd = Command::cargo_bin("windman").unwrap();
+    cmd.env_clear()
+        .env("HOME", root.join("home"))
+        .env("WINDMAN_CONFIG_PATH", root.join("windman.toml"))
+        .env("WINDMAN_CACHE_DIR", root.join("cache"))
+        .arg("--prefix")
+        .arg(root.join("opt"));
+    cmd
+}
+
+/// An update API answering a single request with `response`. Returns its
+/// /latest URL.
+#[allow(dead_code)]
+pub fn serve_once(response: &'static str) -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0u8; 4096];
+        let _ = stream.read(&mut buf);
+        let _ = stream.write_all(response.as_bytes());
+    });
+    format!("http://{}/latest", addr)
+}
+
+/// An update API naming `version`, served with its archive `tar`. Returns
+/// its /latest URL.
+#[allow(dead_code)]
+pub fn serve_latest(version: &str, tar: &Path) -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let latest = format!(
+        r#"{{"version":"{}","url":"http://{}/{}"}}"#,
+        version,
+        addr,
+        tar.file_name().unwrap().to_string_lossy()
+    )
+    .into_bytes();
+    let archive = fs::read(tar).unwrap();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut buf = [0u8; 4096];
+            let n = stream.read(&mut buf).unwrap_or(0);
+            let body = if buf[..n].starts_with(b"GET /latest") {
+                &latest
+            } else {
+                &archive
+            };
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            let _ = stream.write_all(body);
+        }
+    });
+    format!("http://{}/latest", addr)
+}
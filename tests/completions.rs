@@ -0,0 +1,66 @@
+//! `completions --install/--uninstall` against a temp HOME: the script lands
+//! where the shell looks and is removed again.
+
+use assert_cmd::Command;
+use std::path::Path;
+
+fn windman(home: &Path, shell: &str) -> Command {
+    let mut cmd = Command::cargo_bin("windman").unwrap();
+    cmd.env_clear()
+        .env("HOME", home)
+        .env("SHELL", shell)
+        .env("WINDMAN_CONFIG_PATH", home.join("windman.toml"));
+    cmd
+}
+
+#[test]
+fn installs_for_the_shell_in_shell_and_uninstalls() {
+    let tmp = tempfile::tempdir().unwrap();
+    let home = tmp.path();
+
+    let out = windman(home, "/bin/zsh")
+        .args(["completions", "--install"])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let script = home.join(".zfunc/_windman");
+    assert!(std::fs::read_to_string(&script)
+        .unwrap()
+        .starts_with("#compdef windman"));
+    assert!(stdout.contains("fpath=(~/.zfunc $fpath)"), "{}", stdout);
+
+    windman(home, "/bin/zsh")
+        .args(["completions", "--uninstall"])
+        .assert()
+        .success();
+    assert!(!script.exists());
+
+    // an explicit shell wins over $SHELL
+    windman(home, "/bin/zsh")
+        .args(["completions", "bash", "--install"])
+        .assert()
+        .success();
+    let script = home.join(".local/share/bash-completion/completions/windman");
+    let status = std::process::Command::new("bash")
+        .arg("-n")
+        .arg(&script)
+        .status();
+    if let Ok(status) = status {
+        assert!(status.success(), "bash -n {}", script.display());
+    }
+}
+
+#[test]
+fn unknown_shell_asks_for_one() {
+    let tmp = tempfile::tempdir().unwrap();
+    let out = windman(tmp.path(), "/bin/tcsh")
+        .arg("completions")
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("unsupported shell 'tcsh'"), "{}", stderr);
+}
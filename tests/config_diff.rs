@@ -0,0 +1,101 @@
+//! `config diff` lists exactly the settings that differ from the defaults,
+//! with where they were set; `config show --default` ignores the file.
+
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+
+/// The required settings at their defaults, plus two overrides.
+const TWO_OVERRIDES: &str = r#"[install]
+prefix_dir = "~/.local/opt/windsurf"
+bin_dir = "~/.local/bin"
+channel = "stable"
+keep = 5
+desktop_integration = true
+
+[network]
+proxy_enabled = false
+check_cache_ttl_minutes = 10
+"#;
+
+fn windman(root: &Path) -> Command {
+    let config = root.join("windman.toml");
+    fs::write(&config, TWO_OVERRIDES).unwrap();
+    let mut cmd = Command::cargo_bin("windman").unwrap();
+    cmd.env_clear()
+        .env("HOME", root.join("home"))
+        .env("WINDMAN_CONFIG_PATH", config);
+    cmd
+}
+
+fn stdout(cmd: &mut Command) -> String {
+    String::from_utf8_lossy(&cmd.assert().success().get_output().stdout).to_string()
+}
+
+#[test]
+fn diff_shows_exactly_the_overridden_keys() {
+    let tmp = tempfile::tempdir().unwrap();
+    let out = stdout(windman(tmp.path()).args(["config", "diff"]));
+    assert_eq!(
+        out,
+        "[install]\n\
+         keep = 5  # file (default: 2)\n\
+         \n\
+         [network]\n\
+         check_cache_ttl_minutes = 10  # file (default: 60)\n"
+    );
+
+    let out = stdout(windman(tmp.path()).args(["config", "diff", "--json"]));
+    let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!([
+            {"key": "install.keep", "value": 5, "default": 2, "source": "file"},
+            {
+                "key": "network.check_cache_ttl_minutes",
+                "value": 10,
+                "default": 60,
+                "source": "file"
+            },
+        ])
+    );
+}
+
+#[test]
+fn flags_and_environment_are_named_as_sources() {
+    let tmp = tempfile::tempdir().unwrap();
+    let prefix = tmp.path().join("opt");
+    let cache = tmp.path().join("cache");
+    let out = stdout(
+        windman(tmp.path())
+            .env("WINDMAN_CACHE_DIR", &cache)
+            .arg("--prefix")
+            .arg(&prefix)
+            .args(["config", "diff"]),
+    );
+    assert!(
+        out.contains(&format!(
+            "prefix_dir = \"{}\"  # cli (default: \"~/.local/opt/windsurf\")\n",
+            prefix.display()
+        )),
+        "{}",
+        out
+    );
+    assert!(
+        out.contains(&format!(
+            "[paths]\ncache_dir = \"{}\"  # env (default: none)\n",
+            cache.display()
+        )),
+        "{}",
+        out
+    );
+}
+
+#[test]
+fn show_default_ignores_the_config_file() {
+    let tmp = tempfile::tempdir().unwrap();
+    let out = stdout(windman(tmp.path()).args(["config", "show", "--default"]));
+    assert!(out.contains("keep = 2\n"), "{}", out);
+    assert!(out.contains("check_cache_ttl_minutes = 60\n"), "{}", out);
+    assert!(!out.starts_with('#'), "{}", out);
+}
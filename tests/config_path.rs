@@ -0,0 +1,81 @@
+//! `--config` pointing at a file that is not there, or at a directory.
+
+use assert_cmd::Command;
+use std::path::Path;
+
+fn windman(root: &Path, config: &Path) -> Command {
+    let mut cmd = Command::cargo_bin("windman").unwrap();
+    cmd.env_clear()
+        .env("HOME", root.join("home"))
+        .arg("--config")
+        .arg(config)
+        .arg("--prefix")
+        .arg(root.join("opt"));
+    cmd
+}
+
+fn stdout(cmd: &mut Command) -> String {
+    String::from_utf8_lossy(&cmd.assert().success().get_output().stdout).to_string()
+}
+
+#[test]
+fn a_missing_file_is_named_by_show_path_and_verbose() {
+    let tmp = tempfile::tempdir().unwrap();
+    let typo = tmp.path().join("windmna.toml");
+    let shown = typo.display().to_string();
+
+    let show = stdout(windman(tmp.path(), &typo).args(["config", "show"]));
+    assert!(
+        show.starts_with(&format!(
+            "# {} (config file not found, using defaults)\n",
+            shown
+        )),
+        "{}",
+        show
+    );
+
+    let path = stdout(windman(tmp.path(), &typo).args(["config", "path"]));
+    assert_eq!(
+        path,
+        format!("{} (not found; `windman config init` creates it)\n", shown)
+    );
+
+    let out = windman(tmp.path(), &typo)
+        .args(["--verbose", "list"])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        stderr.contains(&format!(
+            "Using config at {} (config file not found, using defaults)",
+            shown
+        )),
+        "{}",
+        stderr
+    );
+
+    // init writes it where the other commands looked
+    windman(tmp.path(), &typo)
+        .args(["config", "init"])
+        .assert()
+        .success();
+    let path = stdout(windman(tmp.path(), &typo).args(["config", "path"]));
+    assert_eq!(path, format!("{} (exists)\n", shown));
+    let show = stdout(windman(tmp.path(), &typo).args(["config", "show"]));
+    assert!(!show.contains("not found"), "{}", show);
+}
+
+#[test]
+fn a_directory_is_refused() {
+    let tmp = tempfile::tempdir().unwrap();
+    let out = windman(tmp.path(), tmp.path())
+        .args(["config", "show"])
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("is a directory"), "{}", stderr);
+}
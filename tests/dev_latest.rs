@@ -0,0 +1,108 @@
+//! `dev-latest --verbose --endpoint URL` against a local server: the raw
+//! exchange is shown, a 429 says when to retry, and the configured headers
+//! are sent (secrets redacted in the output).
+
+use assert_cmd::Command;
+use std::io::{Read, Write};
+use std::thread::JoinHandle;
+
+const LATEST: &str = "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nETag: \"v7\"\r\nContent-Length: 71\r\nConnection: close\r\n\r\n{\"version\":\"1.12.11\",\"url\":\"https://x.invalid/Windsurf-1.12.11.tar.gz\"}";
+
+/// Answer one request with `response`; the handle gives the request received.
+fn serve_once(response: &'static str) -> (String, JoinHandle<String>) {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0u8; 4096];
+        let n = stream.read(&mut buf).unwrap_or(0);
+        let _ = stream.write_all(response.as_bytes());
+        String::from_utf8_lossy(&buf[..n]).to_lowercase()
+    });
+    (format!("http://{}/latest", addr), server)
+}
+
+fn dev_latest(endpoint: &str) -> assert_cmd::assert::Assert {
+    dev_latest_with_config(endpoint, "")
+}
+
+fn dev_latest_with_config(endpoint: &str, config: &str) -> assert_cmd::assert::Assert {
+    let tmp = tempfile::tempdir().unwrap();
+    let config_path = tmp.path().join("windman.toml");
+    if !config.is_empty() {
+        std::fs::write(&config_path, config).unwrap();
+    }
+    Command::cargo_bin("windman")
+        .unwrap()
+        .env_clear()
+        .env("HOME", tmp.path())
+        .env("WINDMAN_CONFIG_PATH", &config_path)
+        .args([
+            "--verbose",
+            "dev-latest",
+            "--timeout",
+            "5",
+            "--endpoint",
+            endpoint,
+        ])
+        .assert()
+}
+
+#[test]
+fn verbose_prints_status_headers_and_body() {
+    let (endpoint, _) = serve_once(LATEST);
+    let out = dev_latest(&endpoint).success().get_output().clone();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains(&format!("GET {}", endpoint)), "{}", stdout);
+    assert!(stdout.contains("HTTP 200"), "{}", stdout);
+    assert!(stdout.contains("etag: \"v7\""), "{}", stdout);
+    assert!(stdout.contains("{\"version\":\"1.12.11\""), "{}", stdout);
+    assert!(stdout.contains("latest.version = 1.12.11"), "{}", stdout);
+}
+
+#[test]
+fn rate_limited_shows_retry_after_and_fails() {
+    let (endpoint, _) = serve_once("HTTP/1.1 429 Too Many Requests\r\nRetry-After: 30\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+    let out = dev_latest(&endpoint).failure().get_output().clone();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains("HTTP 429"), "{}", stdout);
+    assert!(stdout.contains("retry-after: 30"), "{}", stdout);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("retry in 30s"), "{}", stderr);
+}
+
+#[test]
+fn configured_user_agent_and_headers_are_sent_and_secrets_redacted() {
+    let (endpoint, server) = serve_once(LATEST);
+    let config = r#"
+[install]
+prefix_dir = "~/.local/opt/windsurf"
+bin_dir = "~/.local/bin"
+channel = "stable"
+keep = 2
+desktop_integration = false
+
+[network]
+proxy_enabled = false
+user_agent = "acme-updater/2"
+extra_headers = { "X-Corp-Token" = "s3cr3t", "X-Team" = "desktop" }
+"#;
+    let out = dev_latest_with_config(&endpoint, config)
+        .success()
+        .get_output()
+        .clone();
+    let request = server.join().unwrap();
+    assert!(
+        request.contains("user-agent: acme-updater/2\r\n"),
+        "{}",
+        request
+    );
+    assert!(request.contains("x-corp-token: s3cr3t\r\n"), "{}", request);
+    assert!(request.contains("x-team: desktop\r\n"), "{}", request);
+
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains("> x-corp-token: <redacted>"), "{}", stdout);
+    assert!(stdout.contains("> x-team: desktop"), "{}", stdout);
+    assert!(!stdout.contains("s3cr3t"), "{}", stdout);
+    assert!(!String::from_utf8_lossy(&out.stderr).contains("s3cr3t"));
+}
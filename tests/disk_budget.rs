@@ -0,0 +1,106 @@
+//! `install.max_total_size` bounds the install: `prune` drops the oldest
+//! versions until the prefix fits, `status` and `list --size` report usage
+//! against the limit and warn when even the survivors exceed it.
+
+mod common;
+
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+fn windman(root: &Path, limit: &str) -> Command {
+    common::write_config(
+        root,
+        &format!("keep = 5\nmax_total_size = \"{}\"\n", limit),
+        "",
+    );
+    common::windman(root)
+}
+
+/// `<root>/opt/<version>` holding `kib` KiB, oldest first; current is the last.
+fn versions(root: &Path, sizes: &[(&str, usize)]) {
+    for (version, kib) in sizes {
+        let dir = root.join("opt").join(version).join("Windsurf");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("blob"), vec![0u8; kib * 1024]).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    let last = sizes.last().unwrap().0;
+    std::os::unix::fs::symlink(root.join("opt").join(last), root.join("opt/current")).unwrap();
+}
+
+fn output(assert: assert_cmd::assert::Assert) -> (String, String) {
+    let out = assert.get_output();
+    (
+        String::from_utf8_lossy(&out.stdout).to_string(),
+        String::from_utf8_lossy(&out.stderr).to_string(),
+    )
+}
+
+#[test]
+fn prune_removes_the_oldest_versions_until_the_prefix_fits() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    versions(
+        root,
+        &[("1.0.0", 4), ("1.0.1", 4), ("1.0.2", 4), ("1.0.3", 4)],
+    );
+
+    let (out, _) = output(
+        windman(root, "10K")
+            .args(["prune", "--explain", "--dry-run"])
+            .assert()
+            .success(),
+    );
+    for v in ["1.0.0", "1.0.1"] {
+        assert!(
+            out.lines().any(|l| l.starts_with(&format!("remove {} ", v))
+                && l.ends_with("over the size limit (max_total_size = 10.00 KiB)")),
+            "{}",
+            out
+        );
+    }
+    windman(root, "10K").arg("prune").assert().success();
+    assert!(!root.join("opt/1.0.0").exists() && !root.join("opt/1.0.1").exists());
+    assert!(root.join("opt/1.0.2").is_dir() && root.join("opt/1.0.3").is_dir());
+
+    let (out, err) = output(
+        windman(root, "10K")
+            .args(["list", "--size"])
+            .assert()
+            .success(),
+    );
+    assert!(
+        out.contains("Total: 8.00 KiB of 10.00 KiB (max_total_size)"),
+        "{}",
+        out
+    );
+    assert!(err.is_empty(), "{}", err);
+}
+
+#[test]
+fn current_alone_over_the_limit_is_kept_and_reported() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    versions(root, &[("1.0.0", 2), ("1.0.1", 6)]);
+
+    let (_, err) = output(windman(root, "4K").arg("prune").assert().success());
+    assert!(
+        err.contains(
+            "warning: 6.00 KiB used, over install.max_total_size (4.00 KiB) even after pruning"
+        ),
+        "{}",
+        err
+    );
+    assert!(!root.join("opt/1.0.0").exists());
+    assert!(root.join("opt/1.0.1").is_dir());
+
+    let (out, err) = output(windman(root, "4K").arg("status").assert().success());
+    assert!(
+        out.contains("Disk usage     : 6.00 KiB of 4.00 KiB (max_total_size)"),
+        "{}",
+        out
+    );
+    assert!(err.contains("over install.max_total_size"), "{}", err);
+}
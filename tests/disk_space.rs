@@ -0,0 +1,81 @@
+//! `update` looks at the free space before downloading: an archive announced
+//! bigger than the disk is refused up front, cached or streamed, and nothing
+//! is left behind.
+
+mod common;
+
+use assert_cmd::Command;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// 1 PiB, more than any test machine has free.
+const HUGE: u64 = 1 << 50;
+
+/// An update API whose archive claims to be `HUGE` bytes (and sends none).
+fn serve_latest() -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let latest = format!(
+        r#"{{"version":"1.12.11","url":"http://{}/Windsurf-linux-x64-1.12.11.tar.gz"}}"#,
+        addr
+    );
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut buf = [0u8; 4096];
+            let n = stream.read(&mut buf).unwrap_or(0);
+            let _ = if buf[..n].starts_with(b"GET /latest") {
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    latest.len(),
+                    latest
+                )
+            } else {
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    HUGE
+                )
+            };
+        }
+    });
+    format!("http://{}/latest", addr)
+}
+
+fn windman(root: &Path) -> Command {
+    common::write_config(
+        root,
+        "keep = 1\n",
+        "[security]\nsignature_verification = false\n",
+    );
+    common::windman(root)
+}
+
+#[test]
+fn an_archive_bigger_than_the_disk_is_refused_before_downloading() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    let endpoint = serve_latest();
+    for extra in [&[][..], &["--stream"]] {
+        let out = windman(root)
+            .env("WINDMAN_LATEST_ENDPOINT", &endpoint)
+            .args(["update", "--no-desktop"])
+            .args(extra)
+            .assert()
+            .failure()
+            .get_output()
+            .clone();
+        let err = String::from_utf8_lossy(&out.stderr);
+        assert!(err.contains(" PiB free on /"), "{}", err);
+        assert!(err.contains("install.space_check"), "{}", err);
+    }
+    assert!(!root.join("opt/current").exists());
+    let leftovers: Vec<_> = walkdir::WalkDir::new(root.join("cache"))
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().starts_with("Windsurf"))
+        .map(|e| e.path().to_path_buf())
+        .collect();
+    assert!(leftovers.is_empty(), "{:?}", leftovers);
+}
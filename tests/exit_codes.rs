@@ -0,0 +1,50 @@
+//! Typed failures end with their own exit code (see the README table).
+
+mod common;
+
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+
+fn windman(root: &Path) -> Command {
+    let mut cmd = common::windman(root);
+    cmd.arg("--bin-dir").arg(root.join("bin"));
+    cmd
+}
+
+#[test]
+fn a_version_that_is_not_installed_exits_7() {
+    let tmp = tempfile::tempdir().unwrap();
+    fs::create_dir_all(tmp.path().join("opt/1.0.0")).unwrap();
+    let out = windman(tmp.path())
+        .args(["use", "9.9.9"])
+        .assert()
+        .code(7)
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("Available: 1.0.0"), "{}", stderr);
+}
+
+#[test]
+fn an_archive_that_is_no_windsurf_build_exits_8() {
+    let tmp = tempfile::tempdir().unwrap();
+    let archive = tmp.path().join("photos.tar.gz");
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+        fs::File::create(&archive).unwrap(),
+        flate2::Compression::fast(),
+    ));
+    let mut hdr = tar::Header::new_gnu();
+    hdr.set_path("photos/README").unwrap();
+    hdr.set_mode(0o644);
+    hdr.set_size(4);
+    hdr.set_cksum();
+    builder.append(&hdr, &b"hols"[..]).unwrap();
+    builder.into_inner().unwrap().finish().unwrap();
+
+    windman(tmp.path())
+        .args(["install", "--tar"])
+        .arg(&archive)
+        .assert()
+        .code(8);
+}
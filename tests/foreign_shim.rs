@@ -0,0 +1,93 @@
+//! With `install.manage_shim = false` (or `--no-shim`) windman leaves bin_dir
+//! alone: a `windsurf` put there by a distro package survives install, use,
+//! doctor and uninstall, and `status` says the shim is managed elsewhere.
+
+mod common;
+
+use assert_cmd::Command;
+use common::fake_archive;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const FOREIGN: &str = "#!/bin/sh\nexec /usr/share/windsurf/bin/windsurf \"$@\"\n";
+
+fn windman(root: &Path, manage_shim: bool) -> Command {
+    common::write_config(
+        root,
+        &format!("keep = 1\nmanage_shim = {}\n", manage_shim),
+        "",
+    );
+    common::windman(root)
+}
+
+fn stdout(assert: assert_cmd::assert::Assert) -> String {
+    String::from_utf8_lossy(&assert.get_output().stdout).to_string()
+}
+
+/// A `windsurf` in bin_dir that windman did not write.
+fn foreign_shim(root: &Path) -> PathBuf {
+    let shim = root.join("bin/windsurf");
+    fs::create_dir_all(root.join("bin")).unwrap();
+    fs::write(&shim, FOREIGN).unwrap();
+    shim
+}
+
+#[test]
+fn a_foreign_windsurf_survives_everything_with_manage_shim_off() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    let shim = foreign_shim(root);
+    for version in ["1.0.0", "1.0.1"] {
+        windman(root, false)
+            .args(["install", "--no-smoke-test", "--tar"])
+            .arg(fake_archive(root, version))
+            .assert()
+            .success();
+    }
+    windman(root, false)
+        .args(["use", "1.0.0", "--allow-downgrade"])
+        .assert()
+        .success();
+    windman(root, false)
+        .args(["doctor", "--fix"])
+        .assert()
+        .success();
+    let status = stdout(windman(root, false).arg("status").assert().success());
+    assert!(
+        status.contains("Shim           : disabled (managed externally)"),
+        "{}",
+        status
+    );
+    assert_eq!(fs::read_to_string(&shim).unwrap(), FOREIGN);
+
+    let out = stdout(windman(root, false).arg("uninstall").assert().success());
+    assert!(!out.contains("bin/windsurf"), "{}", out);
+    assert_eq!(fs::read_to_string(&shim).unwrap(), FOREIGN);
+    assert!(!root.join("opt/current").exists());
+}
+
+#[test]
+fn no_shim_skips_it_for_one_run_and_uninstall_only_removes_its_own() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    let shim = foreign_shim(root);
+    windman(root, true)
+        .args(["install", "--no-shim", "--no-smoke-test", "--tar"])
+        .arg(fake_archive(root, "1.0.0"))
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(&shim).unwrap(), FOREIGN);
+
+    // managed by windman otherwise, but a file it did not write stays
+    windman(root, true).arg("uninstall").assert().success();
+    assert_eq!(fs::read_to_string(&shim).unwrap(), FOREIGN);
+
+    windman(root, true)
+        .args(["install", "--no-smoke-test", "--tar"])
+        .arg(fake_archive(root, "1.0.0"))
+        .assert()
+        .success();
+    assert_ne!(fs::read_to_string(&shim).unwrap(), FOREIGN);
+    windman(root, true).arg("uninstall").assert().success();
+    assert!(!shim.exists());
+}
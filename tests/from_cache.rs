@@ -0,0 +1,183 @@
+//! `install --from-cache` (a truncated archive included), `cache list` and
+//! `cache move`, against a cache seeded through WINDMAN_CACHE_DIR or
+//! `paths.cache_dir` (nothing under the real home is touched).
+
+mod common;
+
+use assert_cmd::Command;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `<cache>/downloads/stable/<version>/Windsurf-linux-x64-<version>.tar.gz`,
+/// with its checksum recorded next to it when `sha256` is given.
+fn seed(cache: &Path, version: &str, sha256: Option<&str>) -> PathBuf {
+    let dir = cache.join("downloads/stable").join(version);
+    fs::create_dir_all(&dir).unwrap();
+    let name = format!("Windsurf-linux-x64-{}.tar.gz", version);
+    let archive = dir.join(&name);
+    let data = common::archive_bytes(version);
+    fs::write(&archive, &data).unwrap();
+    if let Some(sum) = sha256 {
+        let sum = if sum.is_empty() {
+            hex::encode(Sha256::digest(&data))
+        } else {
+            sum.to_string()
+        };
+        fs::write(
+            dir.join(format!("{}.sha256", name)),
+            format!("{}  {}\n", sum, name),
+        )
+        .unwrap();
+    }
+    archive
+}
+
+fn windman(root: &Path) -> Command {
+    let mut cmd = common::windman(root);
+    cmd.arg("--quiet");
+    cmd
+}
+
+fn install(root: &Path, version: &str) -> assert_cmd::assert::Assert {
+    windman(root)
+        .args([
+            "install",
+            "--no-desktop",
+            "--no-smoke-test",
+            "--from-cache",
+            version,
+        ])
+        .assert()
+}
+
+#[test]
+fn reinstalls_a_cached_version_and_lists_it_as_installed() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cache = tmp.path().join("cache");
+    seed(&cache, "1.12.7", Some(""));
+    seed(&cache, "1.13.0", None);
+
+    let out = windman(tmp.path())
+        .args(["cache", "list"])
+        .output()
+        .unwrap();
+    let listed = String::from_utf8_lossy(&out.stdout);
+    assert!(listed.contains("not installed"), "{}", listed);
+
+    install(tmp.path(), "1.12.7").success();
+    assert!(tmp
+        .path()
+        .join("opt/1.12.7/Windsurf/bin/windsurf")
+        .is_file());
+
+    let out = windman(tmp.path())
+        .args(["cache", "list"])
+        .output()
+        .unwrap();
+    let listed = String::from_utf8_lossy(&out.stdout);
+    let line = |v: &str| {
+        listed
+            .lines()
+            .find(|l| l.starts_with(v))
+            .unwrap_or_else(|| panic!("{} not listed:\n{}", v, listed))
+            .to_string()
+    };
+    assert!(line("1.12.7").contains("installed (current)"), "{}", listed);
+    assert!(line("1.12.7").ends_with("sha256 recorded"), "{}", listed);
+    assert!(line("1.13.0").contains("not installed"), "{}", listed);
+}
+
+#[test]
+fn missing_version_lists_the_cache_and_bad_checksum_is_refused() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cache = tmp.path().join("cache");
+    seed(&cache, "1.12.7", Some(&"0".repeat(64)));
+    seed(&cache, "1.13.0", None);
+
+    let out = install(tmp.path(), "1.11.0").failure().get_output().clone();
+    let err = String::from_utf8_lossy(&out.stderr);
+    assert!(err.contains("cached versions: 1.13.0, 1.12.7"), "{}", err);
+
+    let out = install(tmp.path(), "1.12.7").failure().get_output().clone();
+    let err = String::from_utf8_lossy(&out.stderr);
+    assert!(err.contains("checksum mismatch"), "{}", err);
+    assert!(!tmp.path().join("opt/1.12.7").exists());
+}
+
+#[test]
+fn truncated_cached_archive_says_how_to_download_it_again() {
+    let tmp = tempfile::tempdir().unwrap();
+    let archive = seed(&tmp.path().join("cache"), "1.13.0", None);
+    let full = fs::read(&archive).unwrap();
+    fs::write(&archive, &full[..full.len() / 2]).unwrap();
+
+    let out = install(tmp.path(), "1.13.0").code(8).get_output().clone();
+    let err = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        err.contains("the cached archive of 1.13.0 is incomplete"),
+        "{}",
+        err
+    );
+    assert!(
+        err.contains(&format!("`rm {}`", archive.display())),
+        "{}",
+        err
+    );
+    // no network here: the archive is left for the user to deal with
+    assert!(archive.is_file());
+    assert!(!tmp.path().join("opt/1.13.0").exists());
+}
+
+#[test]
+fn cache_move_relocates_the_cache_and_sets_it_in_the_config() {
+    let tmp = tempfile::tempdir().unwrap();
+    let old = tmp.path().join("cache");
+    seed(&old, "1.12.7", Some(""));
+    fs::write(
+        tmp.path().join("windman.toml"),
+        format!(
+            "[install]\nprefix_dir = \"/opt/ws\"\nbin_dir = \"/opt/bin\"\nchannel = \"stable\"\n\
+             keep = 2\ndesktop_integration = false\n[network]\nproxy_enabled = false\n\
+             [paths]\ncache_dir = \"{}\"\n",
+            old.display()
+        ),
+    )
+    .unwrap();
+    let stdout = |cmd: &mut Command| {
+        let out = cmd.assert().success().get_output().stdout.clone();
+        String::from_utf8_lossy(&out).to_string()
+    };
+    let new = tmp.path().join("scratch/windman");
+
+    // WINDMAN_CACHE_DIR wins over the config, so moving is refused while it is set
+    let out = windman(tmp.path())
+        .args(["cache", "move"])
+        .arg(&new)
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    assert!(String::from_utf8_lossy(&out.stderr).contains("unset it first"));
+
+    let mut cmd = windman(tmp.path());
+    cmd.env_remove("WINDMAN_CACHE_DIR");
+    stdout(cmd.args(["cache", "move"]).arg(&new));
+    assert!(!old.exists());
+
+    let mut cmd = windman(tmp.path());
+    cmd.env_remove("WINDMAN_CACHE_DIR");
+    assert_eq!(
+        stdout(cmd.args(["cache", "path"])),
+        format!("{}\n", new.display())
+    );
+    let mut cmd = windman(tmp.path());
+    cmd.env_remove("WINDMAN_CACHE_DIR");
+    assert!(stdout(cmd.args(["cache", "list"])).contains("1.12.7"));
+    let config = fs::read_to_string(tmp.path().join("windman.toml")).unwrap();
+    assert!(
+        config.contains(&format!("cache_dir = \"{}\"", new.display())),
+        "{}",
+        config
+    );
+}
@@ -0,0 +1,88 @@
+//! `init --defaults` writes a first config without asking; run again, it finds
+//! the install and repairs it instead of installing again.
+
+mod common;
+
+use assert_cmd::Command;
+use common::fake_archive;
+use std::fs;
+use std::path::Path;
+
+/// No WINDMAN_CONFIG_PATH: the config goes where a first run would put it.
+fn windman(root: &Path) -> Command {
+    let mut cmd = Command::cargo_bin("windman").unwrap();
+    cmd.env_clear()
+        .env("HOME", root.join("home"))
+        .env("PATH", "/usr/bin:/bin")
+        .env("WINDMAN_CACHE_DIR", root.join("cache"));
+    cmd
+}
+
+fn stdout(cmd: &mut Command) -> String {
+    String::from_utf8_lossy(&cmd.assert().success().get_output().stdout).to_string()
+}
+
+#[test]
+fn defaults_write_the_config_once_and_say_what_next() {
+    let tmp = tempfile::tempdir().unwrap();
+    let config = tmp.path().join("home/.config/windman/windman.toml");
+
+    let out = stdout(windman(tmp.path()).args(["init", "--defaults", "--skip-install"]));
+    assert!(config.is_file(), "{}", out);
+    assert!(out.contains("Config written to"), "{}", out);
+    let bin = tmp.path().join("home/.local/bin");
+    assert!(
+        out.contains(&format!("warning: {} is not in PATH", bin.display())),
+        "{}",
+        out
+    );
+    assert!(out.contains("windman update"), "{}", out);
+    assert!(!tmp.path().join("home/.local/opt").exists());
+
+    // a second run keeps the file as it is
+    fs::write(&config, fs::read_to_string(&config).unwrap() + "# mine\n").unwrap();
+    let out = stdout(
+        windman(tmp.path())
+            .env("PATH", format!("{}:/usr/bin", bin.display()))
+            .args(["init", "--defaults", "--skip-install"]),
+    );
+    assert!(out.contains("Using the existing config"), "{}", out);
+    assert!(!out.contains("not in PATH"), "{}", out);
+    assert!(fs::read_to_string(&config).unwrap().ends_with("# mine\n"));
+}
+
+#[test]
+fn rerun_repairs_an_existing_install_instead_of_reinstalling() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (opt, bin) = (tmp.path().join("opt"), tmp.path().join("bin"));
+    let with_dirs = |cmd: &mut Command| {
+        cmd.arg("--prefix").arg(&opt).arg("--bin-dir").arg(&bin);
+    };
+
+    let mut init = windman(tmp.path());
+    with_dirs(&mut init);
+    stdout(init.args(["init", "--defaults", "--skip-install", "--no-desktop"]));
+    let mut install = windman(tmp.path());
+    with_dirs(&mut install);
+    stdout(
+        install
+            .args(["--quiet", "install", "--no-smoke-test", "--tar"])
+            .arg(fake_archive(tmp.path(), "1.0.0")),
+    );
+    let shim = bin.join("windsurf");
+    fs::remove_file(&shim).unwrap();
+
+    let mut init = windman(tmp.path());
+    with_dirs(&mut init);
+    let out = stdout(init.args(["init", "--defaults"]));
+    assert!(out.contains("Windsurf 1.0.0 is installed"), "{}", out);
+    assert!(
+        out.contains(&format!("Repaired shim {}", shim.display())),
+        "{}",
+        out
+    );
+    assert!(shim.is_file());
+    assert!(fs::read_link(opt.join("current"))
+        .unwrap()
+        .ends_with("1.0.0"));
+}
@@ -0,0 +1,83 @@
+//! `inspect` prints the tree of an installed version with the executable and
+//! product.json marked, and the same listing as JSON.
+
+mod common;
+
+use common::windman;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+/// `<root>/opt/<version>` with a Windsurf layout and 150 locales; current.
+fn fake_install(root: &Path, version: &str) {
+    let dir = root.join("opt").join(version);
+    let app = dir.join("Windsurf/resources/app");
+    fs::create_dir_all(app.join("locales")).unwrap();
+    for n in 0..150 {
+        fs::write(app.join(format!("locales/{}.pak", n)), "pak").unwrap();
+    }
+    fs::write(
+        app.join("product.json"),
+        format!(r#"{{"windsurfVersion":"{}"}}"#, version),
+    )
+    .unwrap();
+    fs::create_dir_all(dir.join("Windsurf/bin")).unwrap();
+    let exe = dir.join("Windsurf/bin/windsurf");
+    fs::write(&exe, "#!/bin/sh\n").unwrap();
+    fs::set_permissions(&exe, fs::Permissions::from_mode(0o755)).unwrap();
+    std::os::unix::fs::symlink(&dir, root.join("opt/current")).unwrap();
+}
+
+#[test]
+fn tree_marks_the_detected_files_and_summarizes_big_dirs() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    fake_install(root, "1.12.11");
+
+    let out = windman(root)
+        .args(["inspect", "--depth", "4"])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let text = String::from_utf8_lossy(&out.stdout);
+    assert!(text.starts_with("1.12.11 ("), "{}", text);
+    assert!(
+        text.contains("Executable   : Windsurf/bin/windsurf\n"),
+        "{}",
+        text
+    );
+    assert!(
+        text.contains("    │   └── windsurf  ← executable\n"),
+        "{}",
+        text
+    );
+    assert!(text.contains("locales/  … 150 files, 450 B\n"), "{}", text);
+    assert!(text.contains("product.json  ← product.json\n"), "{}", text);
+    assert!(!text.contains('\x1b'), "{}", text);
+
+    let out = windman(root)
+        .args(["inspect", "1.12.11", "--depth", "1", "--json"])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let v: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(v["version"], "1.12.11");
+    assert_eq!(v["files"], 152);
+    assert_eq!(v["product_json"], "Windsurf/resources/app/product.json");
+    assert_eq!(v["tree"][0]["name"], "Windsurf");
+    assert_eq!(v["tree"][0]["summarized"], true);
+}
+
+#[test]
+fn an_unknown_version_is_an_error() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    fake_install(root, "1.12.11");
+    windman(root).args(["inspect", "9.9.9"]).assert().failure();
+    windman(root)
+        .args(["inspect", "--depth", "0"])
+        .assert()
+        .failure();
+}
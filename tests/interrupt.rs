@@ -0,0 +1,67 @@
+//! Ctrl-C during a download: the process exits 130 and leaves no `.part.<pid>-<nanos>-<n>` file.
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Serves one response that announces 10 MiB but trickles 1 KiB every 50ms.
+fn slow_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0u8; 4096];
+        let _ = stream.read(&mut buf);
+        let _ = stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 10485760\r\nConnection: close\r\n\r\n");
+        let chunk = [0u8; 1024];
+        while stream.write_all(&chunk).is_ok() {
+            thread::sleep(Duration::from_millis(50));
+        }
+    });
+    format!("http://{}/windsurf.tar.gz", addr)
+}
+
+#[test]
+fn sigint_during_download_cleans_part_file_and_exits_130() {
+    let tmp = tempfile::tempdir().unwrap();
+    let out = tmp.path().join("windsurf.tar.gz");
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_windman"))
+        .args(["--quiet", "dev-download", "--url", &slow_server(), "--out"])
+        .arg(&out)
+        .env("WINDMAN_CONFIG_PATH", tmp.path().join("windman.toml"))
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    // wait for the download to actually start, into a file of its own
+    let part = tmp
+        .path()
+        .join(format!("windsurf.tar.gz.part.{}-", child.id()));
+    let part_exists = || {
+        std::fs::read_dir(tmp.path()).unwrap().any(|e| {
+            let p = e.unwrap().path();
+            p.to_string_lossy().starts_with(&*part.to_string_lossy())
+        })
+    };
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !part_exists() {
+        assert!(Instant::now() < deadline, "download never started");
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    let killed = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+
+    let status = child.wait().unwrap();
+    assert_eq!(status.code(), Some(130));
+    assert!(!part_exists(), ".part file left behind");
+    assert!(!out.exists());
+}
@@ -0,0 +1,79 @@
+//! `install --keep-archive`: the verified archive lands in the version dir,
+//! is recorded in its metadata, survives a `use` switch and is what `export`
+//! hands out.
+
+mod common;
+
+use assert_cmd::Command;
+use common::fake_archive;
+use std::fs;
+use std::path::Path;
+
+fn windman(root: &Path) -> Command {
+    let mut cmd = common::windman(root);
+    cmd.arg("--quiet");
+    cmd
+}
+
+fn stdout(cmd: &mut Command) -> String {
+    let out = cmd.assert().success().get_output().clone();
+    String::from_utf8_lossy(&out.stdout).to_string()
+}
+
+#[test]
+fn kept_archive_lands_in_the_version_dir_and_survives_use() {
+    let tmp = tempfile::tempdir().unwrap();
+    let old = fake_archive(tmp.path(), "1.0.0");
+    let new = fake_archive(tmp.path(), "1.0.1");
+    let install = |how: &str, src: String| {
+        windman(tmp.path())
+            .args([
+                "install",
+                "--no-desktop",
+                "--no-smoke-test",
+                "--keep-archive",
+            ])
+            .args([how, &src])
+            .assert()
+            .success();
+    };
+    install("--url", format!("file://{}", old.display()));
+    install("--tar", new.display().to_string());
+
+    let kept = |v: &str| {
+        tmp.path()
+            .join("opt")
+            .join(v)
+            .join(".artifact")
+            .join(format!("Windsurf-linux-x64-{}.tar.gz", v))
+    };
+    for (v, src) in [("1.0.0", &old), ("1.0.1", &new)] {
+        assert_eq!(fs::read(kept(v)).unwrap(), fs::read(src).unwrap(), "{}", v);
+        let sidecar = fs::read_to_string(format!("{}.sha256", kept(v).display())).unwrap();
+        let meta =
+            fs::read_to_string(tmp.path().join("opt").join(v).join(".windman-meta.json")).unwrap();
+        let meta: serde_json::Value = serde_json::from_str(&meta).unwrap();
+        assert_eq!(
+            meta["artifact"],
+            format!("Windsurf-linux-x64-{}.tar.gz", v).as_str()
+        );
+        assert!(sidecar.starts_with(meta["sha256"].as_str().unwrap()));
+    }
+
+    windman(tmp.path())
+        .args(["use", "1.0.0", "--allow-downgrade"])
+        .assert()
+        .success();
+    assert!(kept("1.0.0").is_file() && kept("1.0.1").is_file());
+
+    let info = stdout(windman(tmp.path()).args(["info", "1.0.1"]));
+    assert!(info.contains("Artifact    : "), "{}", info);
+
+    let out = tmp.path().join("exported.tar.gz");
+    windman(tmp.path())
+        .args(["export", "1.0.1", "--out"])
+        .arg(&out)
+        .assert()
+        .success();
+    assert_eq!(fs::read(&out).unwrap(), fs::read(&new).unwrap());
+}
@@ -0,0 +1,58 @@
+//! `dev-mangen --out-dir DIR` renders the man pages packagers ship, the same
+//! bytes on every run and without a config or even a HOME.
+
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+
+fn mangen(out: &Path) {
+    Command::cargo_bin("windman")
+        .unwrap()
+        .env_clear()
+        .arg("--quiet")
+        .arg("dev-mangen")
+        .arg("--out-dir")
+        .arg(out)
+        .assert()
+        .success();
+}
+
+#[test]
+fn renders_a_page_per_command_deterministically() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (a, b) = (tmp.path().join("a"), tmp.path().join("b/man1"));
+    mangen(&a);
+    mangen(&b);
+
+    let root = fs::read_to_string(a.join("windman.1")).unwrap();
+    for cmd in ["install", "update", "use", "block", "config"] {
+        let page = fs::read_to_string(a.join(format!("windman-{}.1", cmd)))
+            .unwrap_or_else(|e| panic!("windman-{}.1: {}", cmd, e));
+        assert!(
+            page.contains(&format!("\\fBwindman {}\\fR", cmd)),
+            "{}",
+            page
+        );
+        assert!(
+            root.contains(&format!("\\fBwindman\\-{}\\fR(1)", cmd)),
+            "{}",
+            root
+        );
+    }
+    assert!(a.join("windman-config-show.1").is_file());
+    assert!(!a.join("windman-dev-mangen.1").exists(), "hidden");
+
+    let mut names: Vec<_> = fs::read_dir(&a)
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .collect();
+    names.sort();
+    for name in names {
+        assert_eq!(
+            fs::read(a.join(&name)).unwrap(),
+            fs::read(b.join(&name)).unwrap(),
+            "{:?} differs between runs",
+            name
+        );
+    }
+}
@@ -0,0 +1,79 @@
+//! `install --no-activate` stages a version in the prefix without switching to
+//! it; `--target-dir` extracts a build elsewhere and leaves the install alone.
+
+mod common;
+
+use assert_cmd::Command;
+use common::fake_archive;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn windman(root: &Path) -> Command {
+    let mut cmd = common::windman(root);
+    cmd.arg("--quiet");
+    cmd
+}
+
+fn install(root: &Path, extra: &[&str], tar: &Path) -> String {
+    let out = windman(root)
+        .args(["install", "--no-desktop", "--no-smoke-test", "--tar"])
+        .arg(tar)
+        .args(extra)
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    String::from_utf8_lossy(&out.stdout).to_string()
+}
+
+fn current(root: &Path) -> PathBuf {
+    fs::read_link(root.join("opt/current")).unwrap()
+}
+
+#[test]
+fn no_activate_stages_a_version_for_use_later() {
+    let tmp = tempfile::tempdir().unwrap();
+    let old = fake_archive(tmp.path(), "1.0.0");
+    let new = fake_archive(tmp.path(), "1.0.1");
+    install(tmp.path(), &[], &old);
+    assert!(current(tmp.path()).ends_with("1.0.0"));
+
+    let out = install(tmp.path(), &["--no-activate"], &new);
+    assert!(
+        out.contains("`windman use 1.0.1` switches current to it"),
+        "{}",
+        out
+    );
+    assert!(tmp.path().join("opt/1.0.1/Windsurf/bin/windsurf").is_file());
+    assert!(current(tmp.path()).ends_with("1.0.0"));
+
+    windman(tmp.path())
+        .args(["use", "1.0.1"])
+        .assert()
+        .success();
+    assert!(current(tmp.path()).ends_with("1.0.1"));
+}
+
+#[test]
+fn target_dir_extracts_elsewhere_and_touches_nothing_installed() {
+    let tmp = tempfile::tempdir().unwrap();
+    let tar = fake_archive(tmp.path(), "1.2.0");
+    let eval = tmp.path().join("ws-eval");
+
+    let out = install(tmp.path(), &["--target-dir", eval.to_str().unwrap()], &tar);
+    let exe = eval.join("1.2.0/Windsurf/bin/windsurf");
+    assert!(exe.is_file());
+    assert!(
+        out.contains(&format!("Executable: {}", exe.display())),
+        "{}",
+        out
+    );
+    // the operations log aside: no prefix, no shim
+    for untouched in ["opt", "home/.local/bin"] {
+        assert!(
+            !tmp.path().join(untouched).exists(),
+            "{} was created",
+            untouched
+        );
+    }
+}
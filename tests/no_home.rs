@@ -0,0 +1,144 @@
+//! Without HOME (a minimal container), windman works from explicit locations
+//! only, and says which one is missing instead of guessing or panicking.
+
+mod common;
+
+use assert_cmd::Command;
+use common::fake_archive;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `<root>/windman.toml` with `install` lines of its own.
+fn write_config(root: &Path, install: &str) -> PathBuf {
+    let file = root.join("windman.toml");
+    fs::write(
+        &file,
+        format!(
+            "[install]\nchannel = \"stable\"\nkeep = 1\n{}\n[network]\nproxy_enabled = false\n",
+            install
+        ),
+    )
+    .unwrap();
+    file
+}
+
+/// windman with no HOME and no XDG variable at all.
+fn windman() -> Command {
+    let mut cmd = Command::cargo_bin("windman").unwrap();
+    cmd.env_clear();
+    cmd
+}
+
+#[test]
+fn explicit_locations_are_enough_to_install() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    let tar = fake_archive(root, "1.12.11");
+    let config = write_config(
+        root,
+        "prefix_dir = \"~/.local/opt/windsurf\"\nbin_dir = \"~/.local/bin\"\ndesktop_integration = true",
+    );
+    let install = |cmd: &mut Command| {
+        cmd.arg("--config")
+            .arg(&config)
+            .arg("--prefix")
+            .arg(root.join("opt"))
+            .arg("--bin-dir")
+            .arg(root.join("bin"))
+            .env("WINDMAN_CACHE_DIR", root.join("cache"))
+            .args(["install", "--no-desktop", "--tar"])
+            .arg(&tar)
+            .assert()
+            .success();
+    };
+    install(&mut windman());
+    assert!(root.join("opt/1.12.11/Windsurf/bin/windsurf").is_file());
+    assert!(root.join("bin/windsurf").is_file());
+    // the state has no HOME to go under: it sits next to the cache
+    assert!(root.join("cache/state").is_dir());
+
+    let out = windman()
+        .arg("--config")
+        .arg(&config)
+        .arg("--prefix")
+        .arg(root.join("opt"))
+        .arg("--bin-dir")
+        .arg(root.join("bin"))
+        .env("WINDMAN_CACHE_DIR", root.join("cache"))
+        .args(["list", "--porcelain"])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    assert!(String::from_utf8_lossy(&out.stdout).starts_with("1.12.11\tcurrent"));
+}
+
+#[test]
+fn each_missing_location_names_what_to_pass() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    let err = |cmd: &mut Command| {
+        let out = cmd.arg("list").assert().failure().get_output().clone();
+        String::from_utf8_lossy(&out.stderr).into_owned()
+    };
+
+    let e = err(&mut windman());
+    assert!(e.contains("HOME is not set"), "{}", e);
+    assert!(e.contains("--config FILE"), "{}", e);
+    assert!(!e.contains("panicked"), "{}", e);
+
+    let config = write_config(
+        root,
+        "prefix_dir = \"~/.local/opt/windsurf\"\nbin_dir = \"~/.local/bin\"\ndesktop_integration = false",
+    );
+    let e = err(windman().arg("--config").arg(&config));
+    assert!(e.contains("install.prefix_dir (or --prefix)"), "{}", e);
+
+    let e = err(windman()
+        .arg("--config")
+        .arg(&config)
+        .arg("--prefix")
+        .arg(root.join("opt")));
+    assert!(e.contains("install.bin_dir (or --bin-dir)"), "{}", e);
+
+    let e = err(windman()
+        .arg("--config")
+        .arg(&config)
+        .arg("--prefix")
+        .arg(root.join("opt"))
+        .arg("--bin-dir")
+        .arg(root.join("bin")));
+    assert!(e.contains("WINDMAN_CACHE_DIR"), "{}", e);
+
+    let config = write_config(
+        root,
+        &format!(
+            "prefix_dir = \"{}\"\nbin_dir = \"{}\"\ndesktop_integration = true",
+            root.join("opt").display(),
+            root.join("bin").display()
+        ),
+    );
+    windman()
+        .arg("--config")
+        .arg(&config)
+        .env("WINDMAN_CACHE_DIR", root.join("cache"))
+        .arg("list")
+        .assert()
+        .success();
+    // the desktop entry has nowhere to go
+    let tar = fake_archive(root, "1.12.11");
+    let out = windman()
+        .arg("--config")
+        .arg(&config)
+        .env("WINDMAN_CACHE_DIR", root.join("cache"))
+        .args(["install", "--tar"])
+        .arg(&tar)
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    let e = String::from_utf8_lossy(&out.stderr);
+    assert!(e.contains("set XDG_DATA_HOME"), "{}", e);
+    assert!(e.contains("--no-desktop"), "{}", e);
+    assert!(!root.join("opt/1.12.11").exists());
+}
@@ -0,0 +1,112 @@
+//! `open` starts Windsurf in a session of its own and returns at once with its
+//! PID; `--wait` stays attached; nothing installed says how to install.
+
+mod common;
+
+use assert_cmd::Command;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// A "Windsurf" in `<root>/opt/<version>` that records its arguments in
+/// `<root>/args`, lingers a moment, then writes `<root>/done`.
+fn fake_windsurf(root: &Path, version: &str, script_tail: &str) -> PathBuf {
+    let bin = root.join("opt").join(version).join("Windsurf/bin");
+    fs::create_dir_all(&bin).unwrap();
+    let exe = bin.join("windsurf");
+    fs::write(
+        &exe,
+        format!(
+            "#!/bin/sh\necho \"$@\" > '{root}/args'\n{tail}\n",
+            root = root.display(),
+            tail = script_tail
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&exe, fs::Permissions::from_mode(0o755)).unwrap();
+    let current = root.join("opt/current");
+    let _ = fs::remove_file(&current);
+    std::os::unix::fs::symlink(root.join("opt").join(version), current).unwrap();
+    exe
+}
+
+fn windman(root: &Path) -> Command {
+    let mut cmd = common::windman(root);
+    cmd.current_dir(root);
+    cmd
+}
+
+fn wait_for(p: &Path) -> bool {
+    let started = Instant::now();
+    while started.elapsed() < Duration::from_secs(5) {
+        if p.exists() {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    false
+}
+
+#[test]
+fn open_returns_at_once_and_windsurf_keeps_running_on_its_own() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    let done = root.join("done");
+    fake_windsurf(
+        root,
+        "1.0.0",
+        &format!("/bin/sleep 1\necho ok > '{}'", done.display()),
+    );
+
+    let out = windman(root)
+        .args(["open", "project", "--goto", "a.rs"])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let pid: u32 = String::from_utf8_lossy(&out.stdout).trim().parse().unwrap();
+    // windman is gone; its child still runs, leading a session of its own
+    assert!(!done.exists(), "open waited for Windsurf");
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap();
+    let fields: Vec<&str> = stat
+        .rsplit(')')
+        .next()
+        .unwrap()
+        .split_whitespace()
+        .collect();
+    assert_eq!(fields[3], pid.to_string(), "session id: {}", stat);
+
+    assert!(wait_for(&done));
+    assert_eq!(
+        fs::read_to_string(root.join("args")).unwrap(),
+        "project --goto a.rs\n"
+    );
+}
+
+#[test]
+fn wait_passes_the_exit_code_and_version_picks_the_install() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    fake_windsurf(root, "1.0.0", "exit 3");
+    fake_windsurf(root, "1.1.0", "exit 0");
+
+    windman(root)
+        .args(["open", "--wait", "--version", "1.0.0", "x"])
+        .assert()
+        .code(3);
+    assert_eq!(fs::read_to_string(root.join("args")).unwrap(), "x\n");
+}
+
+#[test]
+fn nothing_installed_points_at_update() {
+    let tmp = tempfile::tempdir().unwrap();
+    let out = windman(tmp.path())
+        .args(["open", "."])
+        .assert()
+        .code(7)
+        .get_output()
+        .clone();
+    let err = String::from_utf8_lossy(&out.stderr);
+    assert!(err.contains("`windman update`"), "{}", err);
+}
@@ -0,0 +1,114 @@
+//! Every install records where the archive came from and how it was checked;
+//! `provenance` prints it, as JSON or as a CycloneDX BOM, and says "unknown"
+//! for versions installed before it was recorded.
+
+mod common;
+
+use assert_cmd::Command;
+use common::fake_archive;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// Serves `tar` under /cdn/, redirecting /dl/ there. Returns the /dl/ URL.
+fn serve_redirected(tar: &Path) -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let name = tar.file_name().unwrap().to_string_lossy().to_string();
+    let archive = fs::read(tar).unwrap();
+    let location = format!("http://{}/cdn/{}", addr, name);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut buf = [0u8; 4096];
+            let n = stream.read(&mut buf).unwrap_or(0);
+            let _ = if buf[..n].starts_with(b"GET /dl/") {
+                write!(
+                    stream,
+                    "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    location
+                )
+            } else {
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    archive.len()
+                )
+                .and_then(|()| stream.write_all(&archive))
+            };
+        }
+    });
+    format!("http://{}/dl/{}", addr, name)
+}
+
+fn windman(root: &Path) -> Command {
+    common::write_config(root, "keep = 3\n", "[changelog]\nsource_url = \"http://127.0.0.1:9/releases\"\n[security]\nsignature_verification = false\n");
+    common::windman(root)
+}
+
+fn json(cmd: &mut Command) -> serde_json::Value {
+    let out = cmd.assert().success().get_output().clone();
+    serde_json::from_slice(&out.stdout).unwrap()
+}
+
+#[test]
+fn a_download_records_its_final_url_and_exports_as_cyclonedx() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    let url = serve_redirected(&fake_archive(root, "1.0.0"));
+    windman(root)
+        .args(["install", "--no-smoke-test", "--url", &url])
+        .assert()
+        .success();
+
+    let p = json(windman(root).args(["provenance", "1.0.0", "--json"]));
+    assert_eq!(p["source"], url.as_str());
+    assert_eq!(p["final_url"], url.replace("/dl/", "/cdn/").as_str());
+    assert_eq!(p["verification"], "unsigned");
+    assert_eq!(p["windman_version"], env!("CARGO_PKG_VERSION"));
+    assert!(p["hostname"].is_string(), "{}", p);
+    assert!(p["installed_at"].is_string(), "{}", p);
+    let sha = p["sha256"].as_str().unwrap().to_string();
+    assert_eq!(sha.len(), 64);
+
+    let bom = json(windman(root).args(["provenance", "--export", "cyclonedx"]));
+    assert_eq!(bom["bomFormat"], "CycloneDX");
+    let c = &bom["components"][0];
+    assert_eq!(c["name"], "windsurf");
+    assert_eq!(c["version"], "1.0.0");
+    assert_eq!(c["hashes"][0]["alg"], "SHA-256");
+    assert_eq!(c["hashes"][0]["content"], sha.as_str());
+    assert_eq!(c["externalReferences"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn a_checked_local_archive_and_an_unrecorded_version() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    let tar = fake_archive(root, "1.0.1");
+    let sha = {
+        use sha2::Digest;
+        hex::encode(sha2::Sha256::digest(fs::read(&tar).unwrap()))
+    };
+    windman(root)
+        .args(["install", "--no-smoke-test", "--sha256", &sha, "--tar"])
+        .arg(&tar)
+        .assert()
+        .success();
+    let p = json(windman(root).args(["provenance", "--json"]));
+    assert_eq!(p["version"], "1.0.1");
+    assert_eq!(p["verification"], "checksum");
+    assert_eq!(p["final_url"], serde_json::Value::Null);
+
+    // installed before provenance was recorded: no metadata at all
+    fs::create_dir_all(root.join("opt/0.9.0/Windsurf/bin")).unwrap();
+    let out = windman(root)
+        .args(["provenance", "0.9.0"])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let text = String::from_utf8_lossy(&out.stdout);
+    assert!(text.contains("Source       : unknown"), "{}", text);
+    assert!(text.contains("Verification : unknown"), "{}", text);
+}
@@ -0,0 +1,151 @@
+//! Three installs in a row with `--keep 1`: the versions of the recent
+//! switches survive the prune (install.protect_history), so `rollback --list`
+//! shows them and rollback can go back twice. The `--dry-run` of rollback and
+//! uninstall change nothing. With automatic pruning off, nothing is pruned
+//! until `windman prune` is run.
+
+mod common;
+
+use assert_cmd::Command;
+use common::fake_archive;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn windman(root: &Path) -> Command {
+    let mut cmd = common::windman(root);
+    cmd.arg("--quiet");
+    cmd
+}
+
+fn stdout(cmd: &mut Command) -> String {
+    let out = cmd.assert().success().get_output().clone();
+    String::from_utf8_lossy(&out.stdout).to_string()
+}
+
+#[test]
+fn recent_switches_survive_keep_one_and_rollback_walks_back() {
+    let tmp = tempfile::tempdir().unwrap();
+    for v in ["1.0.0", "1.0.1", "1.0.2"] {
+        let archive = fake_archive(tmp.path(), v);
+        windman(tmp.path())
+            .args(["install", "--no-desktop", "--no-smoke-test", "--keep", "1"])
+            .arg("--tar")
+            .arg(&archive)
+            .assert()
+            .success();
+    }
+    for v in ["1.0.0", "1.0.1", "1.0.2"] {
+        assert!(tmp.path().join("opt").join(v).is_dir(), "{} pruned", v);
+    }
+
+    let listed = stdout(windman(tmp.path()).args(["rollback", "--list"]));
+    let lines: Vec<&str> = listed.lines().collect();
+    assert!(lines[0].starts_with("* 1.0.1"), "{}", listed);
+    assert!(lines[0].contains("history") && lines[0].ends_with("on disk"));
+    assert!(lines[1].starts_with("  1.0.0"), "{}", listed);
+
+    assert!(stdout(windman(tmp.path()).arg("rollback")).contains("Rolled back to 1.0.1"));
+    assert!(stdout(windman(tmp.path()).arg("rollback")).contains("Rolled back to 1.0.0"));
+}
+
+/// Every path under `root` with its symlink target or file contents.
+fn snapshot(root: &Path) -> Vec<(PathBuf, String)> {
+    walkdir::WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .map(|e| e.unwrap())
+        .map(|e| {
+            let state = if e.path_is_symlink() {
+                fs::read_link(e.path()).unwrap().display().to_string()
+            } else if e.file_type().is_file() {
+                String::from_utf8_lossy(&fs::read(e.path()).unwrap()).to_string()
+            } else {
+                String::new()
+            };
+            (e.path().to_path_buf(), state)
+        })
+        .collect()
+}
+
+#[test]
+fn dry_runs_print_the_plan_and_touch_nothing() {
+    let tmp = tempfile::tempdir().unwrap();
+    for v in ["1.0.0", "1.0.1"] {
+        let archive = fake_archive(tmp.path(), v);
+        windman(tmp.path())
+            .args(["install", "--no-desktop", "--no-smoke-test", "--tar"])
+            .arg(&archive)
+            .assert()
+            .success();
+    }
+    fs::create_dir_all(tmp.path().join("cache/downloads")).unwrap();
+    fs::write(tmp.path().join("cache/downloads/w.tar.gz"), "x").unwrap();
+    windman(tmp.path())
+        .args(["config", "init"])
+        .assert()
+        .success();
+    let before = snapshot(tmp.path());
+
+    let out = stdout(windman(tmp.path()).args(["rollback", "--dry-run"]));
+    assert!(
+        out.starts_with("Would roll back to 1.0.0: undoes the switch 1.0.0 -> 1.0.1"),
+        "{}",
+        out
+    );
+
+    let out = stdout(windman(tmp.path()).args(["uninstall", "--purge", "--dry-run"]));
+    for p in [
+        "opt/current",
+        "home/.local/bin/windsurf",
+        "opt",
+        "cache",
+        "windman.toml",
+    ] {
+        let line = format!("Would remove {} (", tmp.path().join(p).display());
+        assert!(out.contains(&line), "{} missing:\n{}", p, out);
+    }
+    assert!(out.contains("Would reclaim"), "{}", out);
+
+    assert_eq!(snapshot(tmp.path()), before);
+}
+
+#[test]
+fn auto_prune_off_keeps_every_install_until_prune_is_run() {
+    let tmp = tempfile::tempdir().unwrap();
+    windman(tmp.path())
+        .args(["config", "init"])
+        .assert()
+        .success();
+    let cfg = tmp.path().join("windman.toml");
+    let text = fs::read_to_string(&cfg)
+        .unwrap()
+        .replace("keep = 2\n", "keep = 1\n")
+        .replace("protect_history = 2\n", "protect_history = 0\n")
+        .replace("auto_prune = true\n", "auto_prune = false\n");
+    fs::write(&cfg, text).unwrap();
+
+    for (v, flag) in [
+        ("1.0.0", None),
+        ("1.0.1", None),
+        ("1.0.2", Some("--no-prune")),
+    ] {
+        let archive = fake_archive(tmp.path(), v);
+        let out = stdout(
+            windman(tmp.path())
+                .args(["install", "--no-desktop", "--no-smoke-test"])
+                .args(flag)
+                .arg("--tar")
+                .arg(&archive),
+        );
+        // the setting goes without saying, and --quiet silences the flag too
+        assert!(!out.contains("skipping prune"), "{} {:?}", out, flag);
+    }
+    for v in ["1.0.0", "1.0.1", "1.0.2"] {
+        assert!(tmp.path().join("opt").join(v).is_dir(), "{} pruned", v);
+    }
+
+    // the explicit command is not affected
+    windman(tmp.path()).arg("prune").assert().success();
+    assert!(!tmp.path().join("opt/1.0.0").exists());
+    assert!(tmp.path().join("opt/1.0.2").is_dir());
+}
@@ -0,0 +1,166 @@
+//! Requests through a SOCKS5 proxy: a minimal one runs in the test, sending
+//! every connection to a local HTTP server whatever host was asked for.
+
+mod common;
+
+use common::windman;
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::mpsc;
+
+const BODY: &[u8] = b"not really an archive";
+
+/// Answers every request with `BODY`.
+fn http_server() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut buf = [0u8; 4096];
+            let _ = stream.read(&mut buf);
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                BODY.len()
+            );
+            let _ = stream.write_all(head.as_bytes());
+            let _ = stream.write_all(BODY);
+        }
+    });
+    port
+}
+
+/// A SOCKS5 proxy (no auth, CONNECT only) relaying to `127.0.0.1:backend`;
+/// reports each requested destination (`host:port`, or `ip:port`).
+fn socks5_proxy(backend: u16) -> (u16, mpsc::Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for client in listener.incoming().flatten() {
+            let tx = tx.clone();
+            std::thread::spawn(move || relay(client, backend, tx));
+        }
+    });
+    (port, rx)
+}
+
+fn relay(mut client: TcpStream, backend: u16, tx: mpsc::Sender<String>) -> std::io::Result<()> {
+    // greeting: VER NMETHODS METHODS..., answered with "no authentication"
+    let mut head = [0u8; 2];
+    client.read_exact(&mut head)?;
+    let mut methods = vec![0u8; head[1] as usize];
+    client.read_exact(&mut methods)?;
+    client.write_all(&[5, 0])?;
+
+    // request: VER CMD RSV ATYP DST.ADDR DST.PORT
+    let mut req = [0u8; 4];
+    client.read_exact(&mut req)?;
+    let host = match req[3] {
+        1 => {
+            let mut ip = [0u8; 4];
+            client.read_exact(&mut ip)?;
+            Ipv4Addr::from(ip).to_string()
+        }
+        3 => {
+            let mut len = [0u8; 1];
+            client.read_exact(&mut len)?;
+            let mut name = vec![0u8; len[0] as usize];
+            client.read_exact(&mut name)?;
+            String::from_utf8_lossy(&name).to_string()
+        }
+        _ => return Ok(()),
+    };
+    let mut port = [0u8; 2];
+    client.read_exact(&mut port)?;
+    let _ = tx.send(format!("{}:{}", host, u16::from_be_bytes(port)));
+
+    let upstream = TcpStream::connect(("127.0.0.1", backend))?;
+    client.write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 0])?;
+    let (mut up_r, mut client_w) = (upstream.try_clone()?, client.try_clone()?);
+    std::thread::spawn(move || std::io::copy(&mut up_r, &mut client_w));
+    let mut up_w = upstream;
+    std::io::copy(&mut client, &mut up_w)?;
+    Ok(())
+}
+
+fn config_with_proxy(root: &Path, proxy: &str) {
+    std::fs::write(
+        root.join("windman.toml"),
+        format!(
+            "[install]\nprefix_dir = \"/opt/ws\"\nbin_dir = \"/opt/bin\"\nchannel = \"stable\"\n\
+             keep = 2\ndesktop_integration = false\n\
+             [network]\nproxy_enabled = true\nproxy_url = \"{}\"\n",
+            proxy
+        ),
+    )
+    .unwrap();
+}
+
+#[test]
+fn configured_socks5h_proxy_resolves_names_remotely() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (proxy, seen) = socks5_proxy(http_server());
+    config_with_proxy(tmp.path(), &format!("socks5h://127.0.0.1:{}", proxy));
+    let out = tmp.path().join("a.tar.gz");
+
+    windman(tmp.path())
+        .args([
+            "dev-download",
+            "--url",
+            "http://windman.invalid/a.tar.gz",
+            "--out",
+        ])
+        .arg(&out)
+        .assert()
+        .success();
+    assert_eq!(std::fs::read(&out).unwrap(), BODY);
+    // the proxy got the name, not an address resolved here
+    assert_eq!(seen.try_recv().unwrap(), "windman.invalid:80");
+}
+
+#[test]
+fn all_proxy_is_honored_and_reported_by_remote_check() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (proxy, seen) = socks5_proxy(http_server());
+    let proxy = format!("socks5h://127.0.0.1:{}", proxy);
+
+    let out = windman(tmp.path())
+        .env("ALL_PROXY", &proxy)
+        .env("WINDMAN_LATEST_ENDPOINT", "http://windman.invalid/api")
+        .args(["remote", "check", "--timeout", "5"])
+        .assert()
+        // the canned body is no update API answer
+        .failure()
+        .get_output()
+        .clone();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let api = stdout
+        .lines()
+        .find(|l| l.starts_with("update API"))
+        .unwrap_or_else(|| panic!("{}", stdout));
+    assert!(api.ends_with(&format!("(via {})", proxy)), "{}", stdout);
+    assert_eq!(seen.recv().unwrap(), "windman.invalid:80");
+}
+
+#[test]
+fn socks5_with_a_name_only_the_proxy_knows_suggests_socks5h() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (proxy, _seen) = socks5_proxy(http_server());
+    config_with_proxy(tmp.path(), &format!("socks5://127.0.0.1:{}", proxy));
+
+    let out = windman(tmp.path())
+        .args([
+            "dev-download",
+            "--url",
+            "http://windman.invalid/a.tar.gz",
+            "--out",
+        ])
+        .arg(tmp.path().join("a.tar.gz"))
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("use socks5h://"), "{}", stderr);
+}
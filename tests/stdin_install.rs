@@ -0,0 +1,93 @@
+//! `install --tar -` installs an archive piped in: the version from its
+//! product.json, `--sha256` checked before anything moves, buffered in the cache
+//! or (with `install.stream_extract`) extracted as it arrives.
+
+mod common;
+
+use assert_cmd::Command;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+fn windman(root: &Path) -> Command {
+    let mut cmd = common::windman(root);
+    cmd.args(["--quiet", "install", "--no-desktop", "--no-smoke-test"]);
+    cmd
+}
+
+fn source_of(root: &Path, version: &str) -> String {
+    let meta =
+        fs::read_to_string(root.join("opt").join(version).join(".windman-meta.json")).unwrap();
+    let meta: serde_json::Value = serde_json::from_str(&meta).unwrap();
+    meta["source"].as_str().unwrap().to_string()
+}
+
+#[test]
+fn piped_archive_is_installed_under_its_product_version() {
+    let tmp = tempfile::tempdir().unwrap();
+    let archive = common::archive_bytes("1.12.7");
+    let sha = hex::encode(Sha256::digest(&archive));
+
+    windman(tmp.path())
+        .args(["--tar", "-", "--sha256", &sha.to_uppercase()])
+        .write_stdin(archive)
+        .assert()
+        .success();
+
+    let current = fs::read_link(tmp.path().join("opt/current")).unwrap();
+    assert!(current.ends_with("1.12.7"), "{}", current.display());
+    assert_eq!(source_of(tmp.path(), "1.12.7"), "stdin");
+    // the buffer is gone once installed
+    let left: Vec<_> = fs::read_dir(tmp.path().join("cache/stdin"))
+        .unwrap()
+        .collect();
+    assert!(left.is_empty(), "{:?}", left);
+}
+
+#[test]
+fn a_wrong_checksum_installs_nothing() {
+    let tmp = tempfile::tempdir().unwrap();
+    let out = windman(tmp.path())
+        .args(["--tar", "-", "--sha256", &"0".repeat(64)])
+        .write_stdin(common::archive_bytes("1.12.7"))
+        .assert()
+        .code(6)
+        .get_output()
+        .clone();
+    let err = String::from_utf8_lossy(&out.stderr);
+    assert!(err.contains("checksum mismatch"), "{}", err);
+    assert!(!tmp.path().join("opt/1.12.7").exists());
+
+    windman(tmp.path())
+        .args(["--tar", "-", "--sha256", "abc"])
+        .write_stdin(common::archive_bytes("1.12.7"))
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn stream_extract_extracts_as_it_arrives() {
+    let tmp = tempfile::tempdir().unwrap();
+    fs::write(
+        tmp.path().join("windman.toml"),
+        format!(
+            "[install]\nprefix_dir = \"{root}/opt\"\nbin_dir = \"{root}/bin\"\nchannel = \"stable\"\n\
+             keep = 2\ndesktop_integration = false\nstream_extract = true\n\
+             [network]\nproxy_enabled = false\n",
+            root = tmp.path().display()
+        ),
+    )
+    .unwrap();
+    windman(tmp.path())
+        .args(["--tar", "-"])
+        .write_stdin(common::archive_bytes("1.13.0"))
+        .assert()
+        .success();
+    assert!(tmp
+        .path()
+        .join("opt/1.13.0/Windsurf/bin/windsurf")
+        .is_file());
+    assert_eq!(source_of(tmp.path(), "1.13.0"), "stdin");
+    assert!(!tmp.path().join("cache/stdin").exists());
+    assert!(tmp.path().join("bin/windsurf").is_file());
+}
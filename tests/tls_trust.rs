@@ -0,0 +1,149 @@
+//! HTTPS behind a TLS-intercepting proxy: a server whose certificate comes
+//! from a private CA is refused, then accepted once `network.ca_bundle` or
+//! `SSL_CERT_FILE` names that CA, or with the (loudly warned about)
+//! `network.danger_accept_invalid_certs`.
+
+mod common;
+
+use assert_cmd::Command;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/tls")
+        .join(name)
+}
+
+/// An update API on https://127.0.0.1 with a certificate of the test CA.
+fn serve_latest() -> String {
+    let certs: Vec<CertificateDer> = CertificateDer::pem_file_iter(fixture("server.pem"))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    let key = PrivateKeyDer::from_pem_file(fixture("server.key")).unwrap();
+    let config = Arc::new(
+        rustls::ServerConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .unwrap(),
+    );
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let latest = format!(
+        r#"{{"version":"1.12.11","url":"https://{}/Windsurf-linux-x64-1.12.11.tar.gz"}}"#,
+        addr
+    );
+    std::thread::spawn(move || {
+        for tcp in listener.incoming() {
+            let conn = rustls::ServerConnection::new(config.clone()).unwrap();
+            let mut tls = rustls::StreamOwned::new(conn, tcp.unwrap());
+            let mut buf = [0u8; 4096];
+            // a client refusing the certificate ends the handshake here
+            if tls.read(&mut buf).is_err() {
+                continue;
+            }
+            let _ = write!(
+                tls,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                latest.len(),
+                latest
+            );
+            tls.conn.send_close_notify();
+            let _ = tls.flush();
+        }
+    });
+    format!("https://{}/latest", addr)
+}
+
+fn windman(root: &Path, network: &str) -> Command {
+    common::write_config(root, "keep = 1\n", network);
+    let mut cmd = common::windman(root);
+    cmd.args(["update", "--dry-run", "--refresh"]);
+    cmd
+}
+
+fn output(cmd: &mut Command, ok: bool) -> (String, String) {
+    let assert = cmd.assert();
+    let assert = if ok {
+        assert.success()
+    } else {
+        assert.failure()
+    };
+    let out = assert.get_output();
+    (
+        String::from_utf8_lossy(&out.stdout).to_string(),
+        String::from_utf8_lossy(&out.stderr).to_string(),
+    )
+}
+
+#[test]
+fn a_private_ca_is_trusted_only_when_configured() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    let endpoint = serve_latest();
+    let ca = fixture("ca.pem");
+
+    let (_, err) = output(
+        windman(root, "").env("WINDMAN_LATEST_ENDPOINT", &endpoint),
+        false,
+    );
+    assert!(err.contains("network.ca_bundle"), "{}", err);
+    assert!(err.contains("built-in Mozilla roots"), "{}", err);
+
+    let bundle = format!("ca_bundle = \"{}\"\n", ca.display());
+    let (out, _) = output(
+        windman(root, &bundle).env("WINDMAN_LATEST_ENDPOINT", &endpoint),
+        true,
+    );
+    assert!(out.contains("1.12.11"), "{}", out);
+
+    let (out, _) = output(
+        windman(root, "")
+            .env("WINDMAN_LATEST_ENDPOINT", &endpoint)
+            .env("SSL_CERT_FILE", &ca),
+        true,
+    );
+    assert!(out.contains("1.12.11"), "{}", out);
+
+    let (out, err) = output(
+        windman(root, "danger_accept_invalid_certs = true\n")
+            .env("WINDMAN_LATEST_ENDPOINT", &endpoint),
+        true,
+    );
+    assert!(out.contains("1.12.11"), "{}", out);
+    assert!(err.contains("certificates are NOT checked"), "{}", err);
+}
+
+#[test]
+fn remote_check_names_the_trust_in_effect() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    let ca = fixture("ca.pem");
+    let out = Command::cargo_bin("windman")
+        .unwrap()
+        .env_clear()
+        .env("HOME", root.join("home"))
+        .env("WINDMAN_CONFIG_PATH", root.join("missing.toml"))
+        .env("SSL_CERT_FILE", &ca)
+        // nothing answers there: only the first line matters
+        .env("WINDMAN_LATEST_ENDPOINT", "http://127.0.0.1:9/latest")
+        .args(["remote", "check", "--timeout", "1"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert_eq!(
+        stdout.lines().next().unwrap(),
+        format!(
+            "TLS trust      built-in roots + {} (1 certificate, from SSL_CERT_FILE)",
+            ca.display()
+        )
+    );
+}
@@ -0,0 +1,83 @@
+//! `update --stage-only` downloads and extracts the latest release without
+//! touching current; `activate` switches to it later, as `update` would have.
+
+mod common;
+
+use assert_cmd::Command;
+use common::{fake_archive, serve_latest};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn windman(root: &Path) -> Command {
+    // release notes from a port nobody listens on: stored as unavailable
+    common::write_config(
+        root,
+        "keep = 1\n",
+        "[changelog]\nsource_url = \"http://127.0.0.1:9/releases\"\n",
+    );
+    common::windman(root)
+}
+
+fn stdout(assert: assert_cmd::assert::Assert) -> String {
+    String::from_utf8_lossy(&assert.get_output().stdout).to_string()
+}
+
+fn current(root: &Path) -> PathBuf {
+    fs::read_link(root.join("opt/current")).unwrap()
+}
+
+#[test]
+fn stage_now_activate_later() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    let old = fake_archive(root, "1.0.0");
+    let new = fake_archive(root, "1.0.1");
+    windman(root)
+        .args(["install", "--no-smoke-test", "--tar"])
+        .arg(&old)
+        .assert()
+        .success();
+    let shim = fs::read(root.join("bin/windsurf")).unwrap();
+
+    let endpoint = serve_latest("1.0.1", &new);
+    let update = || {
+        let mut cmd = windman(root);
+        cmd.env("WINDMAN_LATEST_ENDPOINT", &endpoint).args([
+            "update",
+            "--stage-only",
+            "--no-smoke-test",
+        ]);
+        cmd
+    };
+    let out = stdout(update().assert().success());
+    assert!(
+        out.contains("Staged Windsurf 1.0.1 (not active); `windman activate` switches to it."),
+        "{}",
+        out
+    );
+    assert!(root.join("opt/1.0.1/Windsurf/bin/windsurf").is_file());
+    assert!(current(root).ends_with("1.0.0"));
+    assert_eq!(fs::read(root.join("bin/windsurf")).unwrap(), shim);
+    let status = stdout(windman(root).arg("status").assert().success());
+    assert!(
+        status.contains("Staged         : 1.0.1 (not active)"),
+        "{}",
+        status
+    );
+
+    // a second cron run finds it staged and downloads nothing
+    let out = stdout(update().assert().code(10));
+    assert!(out.contains("Windsurf 1.0.1 is already staged"), "{}", out);
+
+    let out = stdout(windman(root).arg("activate").assert().success());
+    assert!(out.contains("Now using 1.0.1."), "{}", out);
+    assert!(current(root).ends_with("1.0.1"));
+    // keep = 1: the version it replaced stays as the rollback target
+    assert!(root.join("opt/1.0.0").is_dir());
+    let status = stdout(windman(root).arg("status").assert().success());
+    assert!(!status.contains("Staged"), "{}", status);
+
+    let out = windman(root).arg("activate").assert().failure();
+    let err = String::from_utf8_lossy(&out.get_output().stderr).to_string();
+    assert!(err.contains("nothing is staged"), "{}", err);
+}
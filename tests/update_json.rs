@@ -0,0 +1,115 @@
+//! `update --json` prints one report on stdout, successful or not, with the
+//! same keys as tests/golden/update_report.json; progress goes to stderr.
+
+mod common;
+
+use assert_cmd::Command;
+use common::{fake_archive, serve_latest};
+use std::fs;
+use std::path::Path;
+
+fn windman(root: &Path) -> Command {
+    // release notes from a port nobody listens on: stored as unavailable
+    common::write_config(
+        root,
+        "keep = 1\nprotect_history = 0\n",
+        "[changelog]\nsource_url = \"http://127.0.0.1:9/releases\"\n",
+    );
+    common::windman(root)
+}
+
+fn report(assert: assert_cmd::assert::Assert) -> serde_json::Value {
+    let out = assert.get_output();
+    serde_json::from_slice(&out.stdout)
+        .unwrap_or_else(|e| panic!("{}: {}", e, String::from_utf8_lossy(&out.stdout)))
+}
+
+fn keys(v: &serde_json::Value) -> Vec<String> {
+    let mut k: Vec<String> = v.as_object().unwrap().keys().cloned().collect();
+    k.sort();
+    k
+}
+
+fn update(root: &Path, endpoint: &str) -> Command {
+    let mut cmd = windman(root);
+    cmd.env("WINDMAN_LATEST_ENDPOINT", endpoint)
+        .args(["update", "--json", "--no-smoke-test"]);
+    cmd
+}
+
+#[test]
+fn the_report_names_what_was_installed_and_pruned() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    let golden: serde_json::Value =
+        serde_json::from_str(include_str!("golden/update_report.json")).unwrap();
+    windman(root)
+        .args(["install", "--no-smoke-test", "--tar"])
+        .arg(fake_archive(root, "1.0.0"))
+        .assert()
+        .success();
+    // keep = 1 and the previous current stays: 1.0.0 goes on the second update
+    for (version, old, pruned) in [("1.0.1", "1.0.0", None), ("1.0.2", "1.0.1", Some("1.0.0"))] {
+        let endpoint = serve_latest(version, &fake_archive(root, version));
+        let r = report(update(root, &endpoint).assert().success());
+        assert_eq!(keys(&r), keys(&golden));
+        assert_eq!(r["outcome"], "updated");
+        assert_eq!(r["step"], "done");
+        assert_eq!(r["previous_version"], old);
+        assert_eq!(r["new_version"], version);
+        assert_eq!(r["desktop"], "skipped");
+        assert!(r["bytes_downloaded"].as_u64().unwrap() > 0, "{}", r);
+        assert_eq!(r["sha256"].as_str().unwrap().len(), 64);
+        let names: Vec<&str> = r["pruned"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["version"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, Vec::from_iter(pruned), "{}", r);
+        if pruned.is_some() {
+            assert!(r["pruned"][0]["bytes"].as_u64().unwrap() > 0, "{}", r);
+        }
+    }
+    let r = report(
+        update(
+            root,
+            &serve_latest("1.0.2", &root.join("Windsurf-linux-x64-1.0.2.tar.gz")),
+        )
+        .assert()
+        .code(10),
+    );
+    assert_eq!(r["outcome"], "up_to_date");
+    assert!(!root.join("opt/1.0.0").exists());
+
+    // --quiet: not even the "Already up to date" line
+    let out = windman(root)
+        .env(
+            "WINDMAN_LATEST_ENDPOINT",
+            serve_latest("1.0.2", &root.join("Windsurf-linux-x64-1.0.2.tar.gz")),
+        )
+        .args(["--quiet", "update", "--no-smoke-test"])
+        .assert()
+        .code(10)
+        .get_output()
+        .clone();
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "");
+}
+
+#[test]
+fn a_failed_update_still_reports_the_step_it_stopped_at() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    let broken = root.join("Windsurf-linux-x64-1.0.1.tar.gz");
+    fs::write(&broken, b"not an archive").unwrap();
+    let r = report(
+        update(root, &serve_latest("1.0.1", &broken))
+            .assert()
+            .failure(),
+    );
+    assert_eq!(r["outcome"], "failed");
+    assert_eq!(r["step"], "install");
+    assert_eq!(r["latest_version"], "1.0.1");
+    assert!(r["error"].as_str().is_some_and(|e| !e.is_empty()), "{}", r);
+    assert!(r["new_version"].is_null());
+}